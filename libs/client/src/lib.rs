@@ -31,8 +31,8 @@ pub use http;
 use kanidm_proto::constants::uri::V1_AUTH_VALID;
 use kanidm_proto::constants::{
     ATTR_DOMAIN_DISPLAY_NAME, ATTR_DOMAIN_LDAP_BASEDN, ATTR_DOMAIN_SSID, ATTR_ENTRY_MANAGED_BY,
-    ATTR_KEY_ACTION_REVOKE, ATTR_LDAP_ALLOW_UNIX_PW_BIND, ATTR_LDAP_MAX_QUERYABLE_ATTRS, ATTR_NAME,
-    CLIENT_TOKEN_CACHE, KOPID, KSESSIONID, KVERSION,
+    ATTR_IDP_CERTIFICATE, ATTR_KEY_ACTION_REVOKE, ATTR_LDAP_ALLOW_UNIX_PW_BIND,
+    ATTR_LDAP_MAX_QUERYABLE_ATTRS, ATTR_NAME, CLIENT_TOKEN_CACHE, KOPID, KSESSIONID, KVERSION,
 };
 use kanidm_proto::internal::*;
 use kanidm_proto::v1::*;
@@ -61,6 +61,7 @@ mod scim;
 mod service_account;
 mod sync_account;
 mod system;
+mod tls;
 
 const EXPECT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -136,12 +137,19 @@ pub struct KanidmClientBuilder {
     verify_ca: bool,
     verify_hostnames: bool,
     ca: Option<reqwest::Certificate>,
+    /// The PEM of `ca`, needed to build the TLS configuration when a pin is set.
+    ca_pem: Option<Vec<u8>>,
+    /// If set, the server must present this public key.
+    tls_pin_sha256: Option<TlsPinSha256>,
     connect_timeout: Option<u64>,
     request_timeout: Option<u64>,
+    idle_timeout: Option<u64>,
     use_system_proxies: bool,
     /// Where to store auth tokens, only use in testing!
     token_cache_path: Option<String>,
     disable_system_ca_store: bool,
+    /// Replaces the default user agent sent to the server.
+    user_agent: Option<String>,
 }
 
 impl Display for KanidmClientBuilder {
@@ -156,6 +164,10 @@ impl Display for KanidmClientBuilder {
             Some(value) => writeln!(f, "ca: {value:#?}")?,
             None => writeln!(f, "ca: unset")?,
         }
+        match &self.tls_pin_sha256 {
+            Some(value) => writeln!(f, "tls_pin_sha256: {value}")?,
+            None => writeln!(f, "tls_pin_sha256: unset")?,
+        }
         match self.connect_timeout {
             Some(value) => writeln!(f, "connect_timeout: {value}")?,
             None => writeln!(f, "connect_timeout: unset")?,
@@ -164,7 +176,18 @@ impl Display for KanidmClientBuilder {
             Some(value) => writeln!(f, "request_timeout: {value}")?,
             None => writeln!(f, "request_timeout: unset")?,
         }
+        match self.idle_timeout {
+            Some(value) => writeln!(f, "idle_timeout: {value}")?,
+            None => writeln!(f, "idle_timeout: unset")?,
+        }
         writeln!(f, "use_system_proxies: {}", self.use_system_proxies)?;
+        writeln!(
+            f,
+            "user_agent: {}",
+            self.user_agent
+                .as_deref()
+                .unwrap_or(KanidmClientBuilder::user_agent())
+        )?;
         writeln!(
             f,
             "token_cache_path: {}",
@@ -207,15 +230,19 @@ impl KanidmClientBuilder {
             verify_ca: true,
             verify_hostnames: true,
             ca: None,
+            ca_pem: None,
+            tls_pin_sha256: None,
             connect_timeout: None,
             request_timeout: None,
+            idle_timeout: None,
             use_system_proxies: true,
             token_cache_path: None,
             disable_system_ca_store: false,
+            user_agent: None,
         }
     }
 
-    fn parse_certificate(ca_path: &str) -> Result<reqwest::Certificate, ClientError> {
+    fn parse_certificate(ca_path: &str) -> Result<(reqwest::Certificate, Vec<u8>), ClientError> {
         let mut buf = Vec::new();
         // Is the CA secure?
         #[cfg(target_family = "windows")]
@@ -253,10 +280,11 @@ impl KanidmClientBuilder {
             error!("{:?}", e);
             ClientError::ConfigParseIssue(format!("{e:?}"))
         })?;
-        reqwest::Certificate::from_pem(&buf).map_err(|e| {
+        let ca = reqwest::Certificate::from_pem(&buf).map_err(|e| {
             error!("{:?}", e);
             ClientError::CertParseIssue(format!("{e:?}"))
-        })
+        })?;
+        Ok((ca, buf))
     }
 
     fn apply_config_options(self, kcc: KanidmClientConfigInstance) -> Result<Self, ClientError> {
@@ -265,11 +293,15 @@ impl KanidmClientBuilder {
             verify_ca,
             verify_hostnames,
             ca,
+            ca_pem,
+            tls_pin_sha256,
             connect_timeout,
             request_timeout,
+            idle_timeout,
             use_system_proxies,
             token_cache_path,
             disable_system_ca_store,
+            user_agent,
         } = self;
         // Process and apply all our options if they exist.
         let address = match kcc.uri {
@@ -281,9 +313,12 @@ impl KanidmClientBuilder {
         };
         let verify_ca = kcc.verify_ca.unwrap_or(verify_ca);
         let verify_hostnames = kcc.verify_hostnames.unwrap_or(verify_hostnames);
-        let ca = match kcc.ca_path {
-            Some(ca_path) => Some(Self::parse_certificate(&ca_path)?),
-            None => ca,
+        let (ca, ca_pem) = match kcc.ca_path {
+            Some(ca_path) => {
+                let (ca, ca_pem) = Self::parse_certificate(&ca_path)?;
+                (Some(ca), Some(ca_pem))
+            }
+            None => (ca, ca_pem),
         };
         let connect_timeout = kcc.connect_timeout.or(connect_timeout);
 
@@ -292,11 +327,15 @@ impl KanidmClientBuilder {
             verify_ca,
             verify_hostnames,
             ca,
+            ca_pem,
+            tls_pin_sha256,
             connect_timeout,
             request_timeout,
+            idle_timeout,
             use_system_proxies,
             token_cache_path,
             disable_system_ca_store,
+            user_agent,
         })
     }
 
@@ -426,6 +465,15 @@ impl KanidmClientBuilder {
         }
     }
 
+    /// Only connect to a server presenting this public key, in addition to the usual
+    /// certificate checks.
+    pub fn tls_pin_sha256(self, pin: TlsPinSha256) -> Self {
+        KanidmClientBuilder {
+            tls_pin_sha256: Some(pin),
+            ..self
+        }
+    }
+
     pub fn connect_timeout(self, secs: u64) -> Self {
         KanidmClientBuilder {
             connect_timeout: Some(secs),
@@ -440,6 +488,14 @@ impl KanidmClientBuilder {
         }
     }
 
+    /// How long an idle pooled connection is kept open before it is closed.
+    pub fn idle_timeout(self, secs: u64) -> Self {
+        KanidmClientBuilder {
+            idle_timeout: Some(secs),
+            ..self
+        }
+    }
+
     pub fn no_proxy(self) -> Self {
         KanidmClientBuilder {
             use_system_proxies: false,
@@ -454,16 +510,22 @@ impl KanidmClientBuilder {
         }
     }
 
+    /// Send this user agent to the server instead of [KanidmClientBuilder::user_agent].
+    pub fn set_user_agent(self, user_agent: Option<String>) -> Self {
+        KanidmClientBuilder { user_agent, ..self }
+    }
+
     #[allow(clippy::result_unit_err)]
     pub fn add_root_certificate_filepath(self, ca_path: &str) -> Result<Self, ClientError> {
         //Okay we have a ca to add. Let's read it in and setup.
-        let ca = Self::parse_certificate(ca_path).map_err(|e| {
+        let (ca, ca_pem) = Self::parse_certificate(ca_path).map_err(|e| {
             error!("{:?}", e);
             ClientError::CertParseIssue(format!("{e:?}"))
         })?;
 
         Ok(KanidmClientBuilder {
             ca: Some(ca),
+            ca_pem: Some(ca_pem),
             ..self
         })
     }
@@ -516,7 +578,11 @@ impl KanidmClientBuilder {
         let client_cookies = Arc::new(Jar::default());
 
         let mut client_builder = reqwest::Client::builder()
-            .user_agent(KanidmClientBuilder::user_agent())
+            .user_agent(
+                self.user_agent
+                    .as_deref()
+                    .unwrap_or(KanidmClientBuilder::user_agent()),
+            )
             // We don't directly use cookies, but it may be required for load balancers that
             // implement sticky sessions with cookies.
             .cookie_store(true)
@@ -543,6 +609,18 @@ impl KanidmClientBuilder {
             None => client_builder,
         };
 
+        // The pin can only be checked by our own verifier, which replaces the tls options above.
+        let client_builder = match self.tls_pin_sha256 {
+            Some(pin) => client_builder.tls_backend_preconfigured(tls::pinned_client_config(
+                pin,
+                self.ca_pem.as_deref(),
+                self.verify_ca,
+                self.verify_hostnames,
+                self.disable_system_ca_store,
+            )?),
+            None => client_builder,
+        };
+
         let client_builder = match &self.connect_timeout {
             Some(secs) => client_builder.connect_timeout(Duration::from_secs(*secs)),
             None => client_builder,
@@ -553,6 +631,11 @@ impl KanidmClientBuilder {
             None => client_builder,
         };
 
+        let client_builder = match &self.idle_timeout {
+            Some(secs) => client_builder.pool_idle_timeout(Duration::from_secs(*secs)),
+            None => client_builder,
+        };
+
         let client = client_builder.build().map_err(ClientError::Transport)?;
 
         // Now get the origin.
@@ -599,6 +682,17 @@ fn find_reqwest_error_source<E: std::error::Error + 'static>(
     None
 }
 
+/// Decode the [OperationError] from the body of an error response. Some errors, such as
+/// schema violations, carry a "hint" alongside the error which is not part of the error
+/// itself, so it is removed before decoding.
+async fn operation_error_from_response(response: reqwest::Response) -> Option<OperationError> {
+    let mut value: serde_json::Value = response.json().await.ok()?;
+    if let Some(object) = value.as_object_mut() {
+        object.remove("hint");
+    }
+    serde_json::from_value(value).ok()
+}
+
 impl KanidmClient {
     /// Access the underlying reqwest client that has been configured for this Kanidm server
     pub fn client(&self) -> &reqwest::Client {
@@ -906,7 +1000,7 @@ impl KanidmClient {
             unexpected => {
                 return Err(ClientError::Http(
                     unexpected,
-                    response.json().await.ok(),
+                    operation_error_from_response(response).await,
                     opid,
                 ))
             }
@@ -928,7 +1022,7 @@ impl KanidmClient {
             reqwest::StatusCode::OK => Ok(response),
             unexpected => Err(ClientError::Http(
                 unexpected,
-                response.json().await.ok(),
+                operation_error_from_response(response).await,
                 opid.to_string(),
             )),
         }
@@ -1557,7 +1651,7 @@ impl KanidmClient {
             unexpected => {
                 return Err(ClientError::Http(
                     unexpected,
-                    response.json().await.ok(),
+                    operation_error_from_response(response).await,
                     opid,
                 ))
             }
@@ -1698,6 +1792,16 @@ impl KanidmClient {
             .await
     }
 
+    /// Wait up to `wait` seconds for posix accounts or groups to be changed or deleted.
+    /// The request timeout of this client must be longer than `wait`.
+    pub async fn idm_unix_changes_wait(&self, wait: u64) -> Result<UnixChanges, ClientError> {
+        self.perform_get_request_query(
+            "/v1/unix/_changes",
+            Some(UnixChangesQuery { wait: Some(wait) }),
+        )
+        .await
+    }
+
     pub async fn idm_group_delete(&self, id: &str) -> Result<(), ClientError> {
         self.perform_delete_request(&format!("/v1/group/{id}"))
             .await
@@ -1710,6 +1814,38 @@ impl KanidmClient {
             .await
     }
 
+    pub async fn idm_account_certificate_list(
+        &self,
+        id: &str,
+    ) -> Result<Vec<CertificateSummary>, ClientError> {
+        self.perform_get_request(&format!("/v1/account/{id}/_certificate"))
+            .await
+    }
+
+    pub async fn idm_account_certificate_add(
+        &self,
+        id: &str,
+        pem_data: &str,
+    ) -> Result<(), ClientError> {
+        let mut new_cert = Entry {
+            attrs: BTreeMap::new(),
+        };
+        new_cert
+            .attrs
+            .insert(ATTR_IDP_CERTIFICATE.to_string(), vec![pem_data.to_string()]);
+        self.perform_post_request(&format!("/v1/account/{id}/_certificate"), new_cert)
+            .await
+    }
+
+    pub async fn idm_account_certificate_remove(
+        &self,
+        id: &str,
+        fingerprint: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request(&format!("/v1/account/{id}/_certificate/{fingerprint}"))
+            .await
+    }
+
     #[instrument(level = "debug", skip(self))]
     pub async fn idm_person_account_credential_update_send_intent(
         &self,
@@ -1741,6 +1877,24 @@ impl KanidmClient {
         }
     }
 
+    /// Create a credential update intent token that is limited to the given
+    /// credential types. An empty set is not a restriction, and the token keeps
+    /// the permissions of the initiator.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn idm_person_account_credential_update_intent_with_permissions(
+        &self,
+        id: &str,
+        ttl: Option<u64>,
+        permissions: BTreeSet<CUIntentPermission>,
+    ) -> Result<CUIntentToken, ClientError> {
+        let req = CUIntentCreate {
+            ttl,
+            permissions: (!permissions.is_empty()).then_some(permissions),
+        };
+        self.perform_post_request(&format!("/v1/person/{id}/_credential/_update_intent"), req)
+            .await
+    }
+
     pub async fn idm_account_credential_update_begin(
         &self,
         id: &str,
@@ -2188,15 +2342,20 @@ mod tests {
             verify_ca: true,
             verify_hostnames: true,
             ca: None,
+            ca_pem: None,
+            tls_pin_sha256: None,
             connect_timeout: Some(420),
             request_timeout: Some(69),
+            idle_timeout: Some(42),
             use_system_proxies: true,
             token_cache_path: Some(CLIENT_TOKEN_CACHE.to_string()),
             disable_system_ca_store: false,
+            user_agent: None,
         };
         println!("testclient {testclient}");
         assert!(testclient.to_string().contains("verify_ca: true"));
         assert!(testclient.to_string().contains("verify_hostnames: true"));
+        assert!(testclient.to_string().contains("idle_timeout: 42"));
 
         let badness = testclient.danger_accept_invalid_hostnames(true);
         let badness = badness.danger_accept_invalid_certs(true);
@@ -8,8 +8,12 @@ use kanidm_proto::constants::{
     ATTR_OAUTH2_RS_ORIGIN_LANDING, ATTR_OAUTH2_STRICT_REDIRECT_URI,
 };
 use kanidm_proto::internal::{ImageValue, Oauth2ClaimMapJoin};
+use kanidm_proto::oauth2::OidcDiscoveryResponse;
+use kanidm_proto::scim_v1::client::{ScimOAuth2ClaimMap, ScimOAuth2ScopeMap};
+use kanidm_proto::scim_v1::ScimEntryGetQuery;
 use kanidm_proto::v1::Entry;
 use reqwest::multipart;
+use serde::de::DeserializeOwned;
 use std::collections::BTreeMap;
 use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
@@ -77,6 +81,17 @@ impl KanidmClient {
             .await
     }
 
+    /// Fetch the OpenID Connect discovery document of an oauth2 client.
+    pub async fn idm_oauth2_rs_get_openid_configuration(
+        &self,
+        client_name: &str,
+    ) -> Result<OidcDiscoveryResponse, ClientError> {
+        self.perform_get_request(
+            format!("/oauth2/openid/{client_name}/.well-known/openid-configuration").as_str(),
+        )
+        .await
+    }
+
     pub async fn idm_oauth2_rs_get_basic_secret(
         &self,
         client_name: &str,
@@ -197,6 +212,49 @@ impl KanidmClient {
             .await
     }
 
+    /// Fetch a single multi-valued attribute of an oauth2 client through SCIM. An absent
+    /// attribute is returned as an empty list.
+    async fn idm_oauth2_rs_get_scim_attr<T>(
+        &self,
+        id: &str,
+        attr: Attribute,
+    ) -> Result<Vec<T>, ClientError>
+    where
+        T: DeserializeOwned,
+    {
+        let entry = self
+            .scim_v1_entry_get(
+                id,
+                Some(ScimEntryGetQuery {
+                    attributes: Some(vec![attr.clone()]),
+                    ..Default::default()
+                }),
+            )
+            .await?;
+
+        match entry.get_attr(&attr) {
+            Some(Ok(values)) => Ok(values),
+            Some(Err(err)) => Err(ClientError::InvalidResponseFormat(err.to_string())),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub async fn idm_oauth2_rs_get_scope_maps(
+        &self,
+        id: &str,
+    ) -> Result<Vec<ScimOAuth2ScopeMap>, ClientError> {
+        self.idm_oauth2_rs_get_scim_attr(id, Attribute::OAuth2RsScopeMap)
+            .await
+    }
+
+    pub async fn idm_oauth2_rs_get_claim_maps(
+        &self,
+        id: &str,
+    ) -> Result<Vec<ScimOAuth2ClaimMap>, ClientError> {
+        self.idm_oauth2_rs_get_scim_attr(id, Attribute::OAuth2RsClaimMap)
+            .await
+    }
+
     pub async fn idm_oauth2_rs_delete(&self, id: &str) -> Result<(), ClientError> {
         self.perform_delete_request(["/v1/oauth2/", id].concat().as_str())
             .await
@@ -408,6 +466,23 @@ impl KanidmClient {
         .await
     }
 
+    /// Set the claim values of a group, where values may be booleans, numbers or objects
+    /// as well as strings. This replaces all the values the group has for this claim.
+    pub async fn idm_oauth2_rs_update_claim_map_typed(
+        &self,
+        id: &str,
+        claim_name: &str,
+        group_id: &str,
+        values: &[serde_json::Value],
+    ) -> Result<(), ClientError> {
+        let values: Vec<serde_json::Value> = values.to_vec();
+        self.perform_post_request(
+            format!("/v1/oauth2/{id}/_claimmap/{claim_name}/{group_id}").as_str(),
+            values,
+        )
+        .await
+    }
+
     pub async fn idm_oauth2_rs_update_claim_map_join(
         &self,
         id: &str,
@@ -517,6 +592,34 @@ impl KanidmClient {
         }
     }
 
+    pub async fn idm_oauth2_rs_set_access_token_expiry(
+        &self,
+        id: &str,
+        expiry: Option<u32>,
+    ) -> Result<(), ClientError> {
+        match expiry {
+            Some(exp) => {
+                let mut update_oauth2_rs = Entry {
+                    attrs: BTreeMap::new(),
+                };
+                update_oauth2_rs.attrs.insert(
+                    Attribute::OAuth2AccessTokenExpiry.into(),
+                    vec![exp.to_string()],
+                );
+                self.perform_patch_request(format!("/v1/oauth2/{id}").as_str(), update_oauth2_rs)
+                    .await
+            }
+            None => {
+                self.perform_delete_request(&format!(
+                    "/v1/oauth2/{}/_attr/{}",
+                    id,
+                    Attribute::OAuth2AccessTokenExpiry.as_str()
+                ))
+                .await
+            }
+        }
+    }
+
     pub async fn idm_oauth2_rs_enable_consent_prompt(&self, id: &str) -> Result<(), ClientError> {
         let mut update_oauth2_rs = Entry {
             attrs: BTreeMap::new(),
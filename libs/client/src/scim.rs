@@ -38,6 +38,15 @@ impl KanidmClient {
             .await
     }
 
+    /// List the Persons matching this query, such as those matching a filter.
+    pub async fn scim_v1_person_query(
+        &self,
+        query: ScimEntryGetQuery,
+    ) -> Result<ScimListEntry, ClientError> {
+        self.perform_get_request_query("/scim/v1/Person", Some(query))
+            .await
+    }
+
     pub async fn scim_v1_entry_query(
         &self,
         query: ScimEntryGetQuery,
@@ -1,4 +1,6 @@
 use crate::{ClientError, KanidmClient};
+use kanidm_proto::internal::{ApiTokenListQuery, ApiTokenSummary};
+use std::collections::BTreeMap;
 
 impl KanidmClient {
     pub async fn system_password_badlist_get(&self) -> Result<Vec<String>, ClientError> {
@@ -40,4 +42,20 @@ impl KanidmClient {
         self.perform_delete_request_with_body("/v1/system/_attr/denied_name", list)
             .await
     }
+
+    pub async fn system_api_token_list(
+        &self,
+        account: Option<&str>,
+    ) -> Result<Vec<ApiTokenSummary>, ClientError> {
+        let query = ApiTokenListQuery {
+            account: account.map(str::to_string),
+        };
+        self.perform_get_request_query("/v1/system/_api_token", Some(query))
+            .await
+    }
+
+    /// Fetch the number of live entries per class, such as person and group.
+    pub async fn system_entry_counts(&self) -> Result<BTreeMap<String, u64>, ClientError> {
+        self.perform_get_request("/v1/stats/entry_counts").await
+    }
 }
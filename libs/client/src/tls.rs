@@ -0,0 +1,270 @@
+//! TLS configuration for when the server's public key is pinned. reqwest has no way to
+//! inspect the certificate the server presented, so when a pin is set we build the rustls
+//! configuration ourselves, and wrap the usual verifier with a check of the pin.
+
+use crate::ClientError;
+use kanidm_proto::internal::TlsPinSha256;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{CertificateError, ClientConfig, DigitallySignedStruct, RootCertStore};
+use std::sync::Arc;
+
+#[derive(Debug)]
+struct PinnedServerCertVerifier {
+    /// The verifier of the certificate chain, or `None` if the chain is not verified.
+    inner: Option<Arc<dyn ServerCertVerifier>>,
+    verify_hostnames: bool,
+    pin: TlsPinSha256,
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for PinnedServerCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if let Some(inner) = &self.inner {
+            match inner.verify_server_cert(
+                end_entity,
+                intermediates,
+                server_name,
+                ocsp_response,
+                now,
+            ) {
+                Ok(_) => {}
+                // The name is only checked once the chain is valid, so this is still trusted.
+                Err(rustls::Error::InvalidCertificate(
+                    CertificateError::NotValidForName
+                    | CertificateError::NotValidForNameContext { .. },
+                )) if !self.verify_hostnames => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        if !self.pin.matches_certificate(end_entity) {
+            error!(
+                expected = %self.pin,
+                "The server's TLS public key does not match the pinned key, refusing to connect"
+            );
+            return Err(rustls::Error::InvalidCertificate(
+                CertificateError::ApplicationVerificationFailure,
+            ));
+        }
+
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+/// The trust roots that the server's certificate chain is verified against.
+#[derive(Debug)]
+enum ChainRoots {
+    /// Only the configured CA certificates, as the system store is disabled.
+    Only(Vec<CertificateDer<'static>>),
+    /// The system store, along with any configured CA certificates.
+    System(Vec<CertificateDer<'static>>),
+}
+
+impl ChainRoots {
+    fn new(ca_certs: Vec<CertificateDer<'static>>, disable_system_ca_store: bool) -> Self {
+        if disable_system_ca_store {
+            ChainRoots::Only(ca_certs)
+        } else {
+            ChainRoots::System(ca_certs)
+        }
+    }
+
+    fn verifier(
+        self,
+        provider: Arc<CryptoProvider>,
+    ) -> Result<Arc<dyn ServerCertVerifier>, ClientError> {
+        match self {
+            ChainRoots::Only(ca_certs) => {
+                if ca_certs.is_empty() {
+                    return Err(ClientError::ConfigParseIssue(
+                        "The system CA store is disabled, but no CA certificate was provided to verify the server with".to_string(),
+                    ));
+                }
+                let mut roots = RootCertStore::empty();
+                roots.add_parsable_certificates(ca_certs);
+                let verifier =
+                    WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider)
+                        .build()
+                        .map_err(|err| ClientError::CertParseIssue(format!("{err:?}")))?;
+                Ok(verifier)
+            }
+            ChainRoots::System(ca_certs) if ca_certs.is_empty() => {
+                let verifier = rustls_platform_verifier::Verifier::new(provider)
+                    .map_err(|err| ClientError::CertParseIssue(format!("{err:?}")))?;
+                Ok(Arc::new(verifier))
+            }
+            ChainRoots::System(ca_certs) => {
+                let verifier =
+                    rustls_platform_verifier::Verifier::new_with_extra_roots(ca_certs, provider)
+                        .map_err(|err| ClientError::CertParseIssue(format!("{err:?}")))?;
+                Ok(Arc::new(verifier))
+            }
+        }
+    }
+}
+
+/// Build a rustls configuration that only accepts a server presenting the pinned public key.
+/// The remaining options have the same meaning as they do on the client builder.
+pub(crate) fn pinned_client_config(
+    pin: TlsPinSha256,
+    ca_pem: Option<&[u8]>,
+    verify_ca: bool,
+    verify_hostnames: bool,
+    disable_system_ca_store: bool,
+) -> Result<ClientConfig, ClientError> {
+    // Use the same provider as reqwest would.
+    let provider = CryptoProvider::get_default()
+        .cloned()
+        .unwrap_or_else(|| Arc::new(rustls::crypto::aws_lc_rs::default_provider()));
+
+    let ca_certs = ca_pem
+        .map(|pem| {
+            CertificateDer::pem_slice_iter(pem)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| ClientError::CertParseIssue(format!("{err:?}")))
+        })
+        .transpose()?
+        .unwrap_or_default();
+
+    let inner = if verify_ca {
+        Some(ChainRoots::new(ca_certs, disable_system_ca_store).verifier(provider.clone())?)
+    } else {
+        None
+    };
+
+    let verifier = PinnedServerCertVerifier {
+        inner,
+        verify_hostnames,
+        pin,
+        provider: provider.clone(),
+    };
+
+    let mut config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|err| ClientError::CertParseIssue(format!("{err:?}")))?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChainRoots, PinnedServerCertVerifier};
+    use crate::ClientError;
+    use kanidm_proto::internal::TlsPinSha256;
+    use rustls::client::danger::ServerCertVerifier;
+    use rustls::pki_types::pem::PemObject;
+    use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    const PEM_DATA: &str = r#"-----BEGIN CERTIFICATE-----
+MIIB3zCCAYWgAwIBAgIUdJ6IWvI+8M6nwK7ykUK7/iBq7yQwCgYIKoZIzj0EAwIw
+RTELMAkGA1UEBhMCQVUxEzARBgNVBAgMClNvbWUtU3RhdGUxITAfBgNVBAoMGElu
+dGVybmV0IFdpZGdpdHMgUHR5IEx0ZDAeFw0yNDA4MjEwNjQ2MzBaFw0yNTA4MjEw
+NjQ2MzBaMEUxCzAJBgNVBAYTAkFVMRMwEQYDVQQIDApTb21lLVN0YXRlMSEwHwYD
+VQQKDBhJbnRlcm5ldCBXaWRnaXRzIFB0eSBMdGQwWTATBgcqhkjOPQIBBggqhkjO
+PQMBBwNCAAS2Szn4NPmgxawC1+MRC41jqobemNkXkRZ9AgozK0zRDFc6k1IHUZ++
+wN0USpXDQYDnJfATqvlpKPebnHxTytt6o1MwUTAdBgNVHQ4EFgQU1oR1x2CnoPap
+JMKPCVVzqWf2ANYwHwYDVR0jBBgwFoAU1oR1x2CnoPapJMKPCVVzqWf2ANYwDwYD
+VR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNIADBFAiBpy0o2CY97MIxeQ0HgG44Y
+raBy6edj7W0EIH+yQxkDEwIhAI0nVKaI6duHLAvtKW6CfEQFG6jKg7dyk37YYiRD
+2jS0
+-----END CERTIFICATE-----"#;
+
+    #[test]
+    fn test_pinned_verifier() {
+        let cert = CertificateDer::from_pem_slice(PEM_DATA.as_bytes()).unwrap();
+        let server_name = ServerName::try_from("idm.example.com").unwrap();
+
+        let verify = |pin: &str| {
+            let verifier = PinnedServerCertVerifier {
+                // Only the pin is checked.
+                inner: None,
+                verify_hostnames: true,
+                pin: TlsPinSha256::from_str(pin).unwrap(),
+                provider: Arc::new(rustls::crypto::aws_lc_rs::default_provider()),
+            };
+            verifier.verify_server_cert(&cert, &[], &server_name, &[], UnixTime::now())
+        };
+
+        assert!(verify("XCBEacVVydeBXobyO9YOZ3ancoyXnwtrT1DEl5xbtOs=").is_ok());
+        assert!(verify("AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=").is_err());
+    }
+
+    #[test]
+    fn test_chain_roots_system_ca_store() {
+        let cert = CertificateDer::from_pem_slice(PEM_DATA.as_bytes()).unwrap();
+        let provider = Arc::new(rustls::crypto::aws_lc_rs::default_provider());
+
+        // With the system store disabled, only the configured CA is trusted.
+        let roots = ChainRoots::new(vec![cert.clone()], true);
+        assert!(matches!(&roots, ChainRoots::Only(certs) if certs.len() == 1));
+        assert!(roots.verifier(provider.clone()).is_ok());
+
+        // And without a CA there is nothing to trust, rather than falling back to
+        // the system store.
+        let roots = ChainRoots::new(Vec::new(), true);
+        assert!(matches!(&roots, ChainRoots::Only(certs) if certs.is_empty()));
+        assert!(matches!(
+            roots.verifier(provider.clone()),
+            Err(ClientError::ConfigParseIssue(_))
+        ));
+
+        // Otherwise the system store is used, extended by the configured CA.
+        let roots = ChainRoots::new(vec![cert], false);
+        assert!(matches!(&roots, ChainRoots::System(certs) if certs.len() == 1));
+
+        let roots = ChainRoots::new(Vec::new(), false);
+        assert!(matches!(&roots, ChainRoots::System(certs) if certs.is_empty()));
+    }
+}
@@ -21,6 +21,7 @@ use crypto_glue::{
     s512::Sha512,
     sha1::Sha1,
     traits::Digest,
+    zeroize::Zeroize,
 };
 use kanidm_hsm_crypto::{provider::TpmHmacS256, structures::HmacS256Key};
 use md4::Md4;
@@ -30,6 +31,7 @@ use std::fmt;
 use std::fmt::Display;
 use std::num::ParseIntError;
 use std::time::{Duration, Instant};
+use subtle::ConstantTimeEq;
 use tracing::{debug, error, warn};
 
 mod crypt_md5;
@@ -147,6 +149,85 @@ impl fmt::Debug for DbPasswordV1 {
     }
 }
 
+impl Zeroize for DbPasswordV1 {
+    /// Clear the salts and hashes, leaving only the parameters of the kdf.
+    fn zeroize(&mut self) {
+        match self {
+            DbPasswordV1::TPM_ARGON2ID { s, k, .. } | DbPasswordV1::ARGON2ID { s, k, .. } => {
+                s.zeroize();
+                k.zeroize();
+            }
+            DbPasswordV1::PBKDF2(_, salt, key)
+            | DbPasswordV1::PBKDF2_SHA1(_, salt, key)
+            | DbPasswordV1::PBKDF2_SHA512(_, salt, key)
+            | DbPasswordV1::SSHA1(salt, key)
+            | DbPasswordV1::SSHA256(salt, key)
+            | DbPasswordV1::SSHA512(salt, key) => {
+                salt.zeroize();
+                key.zeroize();
+            }
+            DbPasswordV1::SHA1(key)
+            | DbPasswordV1::SHA256(key)
+            | DbPasswordV1::SHA512(key)
+            | DbPasswordV1::NT_MD4(key) => key.zeroize(),
+            DbPasswordV1::CRYPT_MD5 { s, h } => {
+                s.zeroize();
+                h.zeroize();
+            }
+            DbPasswordV1::CRYPT_SHA256 { h } | DbPasswordV1::CRYPT_SHA512 { h } => h.zeroize(),
+        }
+    }
+}
+
+impl DbPasswordV1 {
+    /// Re-hash a PBKDF2 password that was stored with fewer iterations than
+    /// `current_work_factor`, keeping the same digest and key length. The password must
+    /// match the stored hash, so a wrong password never causes an upgrade. Returns true
+    /// if the password was upgraded.
+    pub fn upgrade_if_needed(&mut self, current_work_factor: u32, password: &[u8]) -> bool {
+        type Derive = fn(&[u8], &[u8], u32, &mut [u8]);
+
+        let (cost, salt, key, derive): (&mut u32, &mut Vec<u8>, &mut Vec<u8>, Derive) = match self {
+            DbPasswordV1::PBKDF2(cost, salt, key) => (cost, salt, key, pbkdf2_hmac::<Sha256>),
+            DbPasswordV1::PBKDF2_SHA1(cost, salt, key) => (cost, salt, key, pbkdf2_hmac::<Sha1>),
+            DbPasswordV1::PBKDF2_SHA512(cost, salt, key) => {
+                (cost, salt, key, pbkdf2_hmac::<Sha512>)
+            }
+            _ => return false,
+        };
+
+        if *cost >= current_work_factor {
+            return false;
+        }
+
+        let mut chal_key: Vec<u8> = vec![0; key.len()];
+        derive(password, salt.as_slice(), *cost, chal_key.as_mut_slice());
+        if chal_key != *key {
+            return false;
+        }
+
+        let mut rng = rand::rng();
+        let new_salt: Vec<u8> = (0..PBKDF2_SALT_LEN).map(|_| rng.random()).collect();
+        let mut new_key: Vec<u8> = vec![0; key.len()];
+        derive(
+            password,
+            new_salt.as_slice(),
+            current_work_factor,
+            new_key.as_mut_slice(),
+        );
+
+        debug!(
+            from = *cost,
+            to = current_work_factor,
+            "upgraded pbkdf2 work factor"
+        );
+        *cost = current_work_factor;
+        *salt = new_salt;
+        *key = new_key;
+        true
+    }
+}
+
 #[derive(Debug)]
 pub struct CryptoPolicy {
     pub(crate) pbkdf2_cost: u32,
@@ -966,7 +1047,7 @@ impl Password {
                     })
                     .map(|hmac_key| {
                         // Actually compare the outputs.
-                        hmac_key.into_bytes().as_slice() == key
+                        hmac_key.into_bytes().as_slice().ct_eq(key).into()
                     })
             }
             (Kdf::TPM_ARGON2ID { .. }, None) => {
@@ -1012,7 +1093,7 @@ impl Password {
                     })
                     .map(|()| {
                         // Actually compare the outputs.
-                        &check_key == key
+                        check_key.ct_eq(key).into()
                     })
             }
             (Kdf::PBKDF2(cost, salt, key), _) => {
@@ -1239,6 +1320,63 @@ mod tests {
 
     use crate::*;
 
+    #[test]
+    fn test_dbpassword_upgrade_if_needed() {
+        let p = CryptoPolicy::minimum();
+        let c = Password::new_pbkdf2(&p, "password").unwrap();
+        let mut dbpw = c.to_dbpasswordv1();
+        let original = dbpw.clone();
+
+        // A wrong password never upgrades.
+        assert!(!dbpw.upgrade_if_needed(PBKDF2_MIN_NIST_COST * 2, b"wrong"));
+        assert_eq!(dbpw, original);
+
+        // Already at the current work factor.
+        assert!(!dbpw.upgrade_if_needed(PBKDF2_MIN_NIST_COST, b"password"));
+        assert_eq!(dbpw, original);
+
+        assert!(dbpw.upgrade_if_needed(PBKDF2_MIN_NIST_COST * 2, b"password"));
+        assert!(matches!(
+            dbpw,
+            DbPasswordV1::PBKDF2(cost, _, _) if cost == PBKDF2_MIN_NIST_COST * 2
+        ));
+        assert_ne!(dbpw, original);
+
+        // Idempotent once upgraded.
+        let upgraded = dbpw.clone();
+        assert!(!dbpw.upgrade_if_needed(PBKDF2_MIN_NIST_COST * 2, b"password"));
+        assert_eq!(dbpw, upgraded);
+
+        let c = Password::try_from(dbpw).unwrap();
+        assert!(c.verify("password").unwrap());
+        assert!(!c.verify("wrong").unwrap());
+
+        // Other kdfs are never changed.
+        let c = Password::new_argon2id(&p, "password").unwrap();
+        let mut dbpw = c.to_dbpasswordv1();
+        assert!(!dbpw.upgrade_if_needed(u32::MAX, b"password"));
+    }
+
+    #[test]
+    fn test_dbpassword_zeroize() {
+        let p = CryptoPolicy::minimum();
+        let c = Password::new_argon2id(&p, "password").unwrap();
+        let mut dbpw = c.to_dbpasswordv1();
+        dbpw.zeroize();
+        assert!(matches!(
+            &dbpw,
+            DbPasswordV1::ARGON2ID { s, k, .. } if s.is_empty() && k.is_empty()
+        ));
+
+        let c = Password::new_pbkdf2(&p, "password").unwrap();
+        let mut dbpw = c.to_dbpasswordv1();
+        dbpw.zeroize();
+        assert!(matches!(
+            &dbpw,
+            DbPasswordV1::PBKDF2(_, salt, key) if salt.is_empty() && key.is_empty()
+        ));
+    }
+
     #[test]
     fn test_credential_simple() {
         let p = CryptoPolicy::minimum();
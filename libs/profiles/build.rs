@@ -3,6 +3,10 @@ use std::{env, fs};
 
 use base64::{engine::general_purpose, Engine as _};
 
+// The profile is also applied to this crate, so that BuildInfo can be read here.
+#[path = "src/profile.rs"]
+mod profile;
+
 /// Work out where the workspace dir is
 fn workspace_dir() -> Option<PathBuf> {
     let output = std::process::Command::new(env!("CARGO"))
@@ -55,7 +59,8 @@ fn main() {
 
     let contents = general_purpose::STANDARD.encode(data);
 
-    if let Some(commit_rev) = determine_git_rev() {
+    let commit_rev = determine_git_rev();
+    if let Some(commit_rev) = &commit_rev {
         println!("cargo:rustc-env=KANIDM_PKG_COMMIT_REV={commit_rev}");
     }
 
@@ -63,4 +68,6 @@ fn main() {
 
     println!("cargo:rustc-env=KANIDM_BUILD_PROFILE={profile}");
     println!("cargo:rustc-env=KANIDM_BUILD_PROFILE_TOML={contents}");
+
+    profile::emit_profile(&profile, Some(&contents), commit_rev.as_deref());
 }
@@ -1,171 +1,67 @@
-use base64::prelude::BASE64_STANDARD;
-use base64::{engine::general_purpose, Engine as _};
-use serde::Deserialize;
-use sha2::Digest;
-use std::env;
+mod profile;
 
-// To debug why a rebuild is requested.
-// CARGO_LOG=cargo::core::compiler::fingerprint=info cargo ...
-
-#[derive(Debug, Deserialize)]
-#[allow(non_camel_case_types)]
-enum CpuOptLevel {
-    apple_m1,
-    armv8_a,
-    none,
-    native,
-    neon_v8,
-    x86_64_legacy, // don't use this it's the oldest and worst. unless you've got a really old CPU, in which case, sorry?
-    x86_64_v2,
-    x86_64_v3,
+/// Apply the build profile to the calling crate. This must be called from the
+/// build.rs of any crate that uses the `KANIDM_*` build environment.
+pub fn apply_profile() {
+    profile::emit_profile(
+        env!("KANIDM_BUILD_PROFILE"),
+        option_env!("KANIDM_BUILD_PROFILE_TOML"),
+        option_env!("KANIDM_PKG_COMMIT_REV"),
+    )
 }
 
-impl Default for CpuOptLevel {
-    fn default() -> Self {
-        if cfg!(target_arch = "x86_64") {
-            CpuOptLevel::x86_64_v2
-        } else if cfg!(target_arch = "aarch64") && cfg!(target_os = "macos") {
-            CpuOptLevel::apple_m1
-        } else if cfg!(target_arch = "aarch64") && cfg!(target_os = "linux") {
-            // Keep native arm64 Linux release builds on the ARMv8.0-A baseline.
-            // Dependency C/C++ flags for cross builds are enforced in .cargo/config.toml.
-            CpuOptLevel::armv8_a
-        /*
-        } else if cfg!(target_arch = "aarch64") && cfg!(target_os = "linux") {
-            // Disable neon_v8 on linux - this has issues on non-apple hardware and on
-            // opensuse/distro builds.
-            CpuOptLevel::neon_v8
-        */
-        } else {
-            CpuOptLevel::none
-        }
-    }
+/// The details of how this build of Kanidm was made, as emitted by [apply_profile].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuildInfo {
+    /// The package version, with the commit rev if it was known.
+    pub version: &'static str,
+    /// A short hash of the version, used for cache busting.
+    pub version_hash: &'static str,
+    /// The major and minor version, excluding the patch version.
+    pub series: &'static str,
+    /// If this is a development build.
+    pub pre_release: bool,
+    pub profile_name: &'static str,
+    pub cpu_flags: &'static str,
 }
 
-impl std::fmt::Display for CpuOptLevel {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match &self {
-            CpuOptLevel::apple_m1 => write!(f, "apple_m1"),
-            CpuOptLevel::armv8_a => write!(f, "armv8_a"),
-            CpuOptLevel::none => write!(f, "none"),
-            CpuOptLevel::native => write!(f, "native"),
-            CpuOptLevel::neon_v8 => write!(f, "neon_v8"),
-            CpuOptLevel::x86_64_legacy => write!(f, "x86_64"),
-            CpuOptLevel::x86_64_v2 => write!(f, "x86_64_v2"),
-            CpuOptLevel::x86_64_v3 => write!(f, "x86_64_v3"),
+impl BuildInfo {
+    pub const fn current() -> Self {
+        BuildInfo {
+            version: env!("KANIDM_PKG_VERSION"),
+            version_hash: env!("KANIDM_PKG_VERSION_HASH"),
+            series: env!("KANIDM_PKG_SERIES"),
+            pre_release: option_env!("KANIDM_PRE_RELEASE").is_some(),
+            profile_name: env!("KANIDM_PROFILE_NAME"),
+            cpu_flags: env!("KANIDM_CPU_FLAGS"),
         }
     }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(deny_unknown_fields)]
-struct ProfileConfig {
-    #[serde(default)]
-    cpu_flags: CpuOptLevel,
-    server_admin_bind_path: String,
-    server_config_path: String,
-    server_migration_path: String,
-    server_ui_pkg_path: String,
-    client_config_path: String,
-    resolver_config_path: String,
-    resolver_unix_shell_path: String,
-    resolver_service_account_token_path: String,
-}
-
-pub fn apply_profile() {
-    println!("cargo:rerun-if-env-changed=KANIDM_BUILD_PROFILE");
-    println!("cargo:rerun-if-env-changed=KANIDM_BUILD_PROFILE_TOML");
-
-    // transform any requested paths for our server. We do this by reading
-    // our profile that we have been provided.
-    let profile = env!("KANIDM_BUILD_PROFILE");
-    let contents = env!("KANIDM_BUILD_PROFILE_TOML");
-
-    let data = general_purpose::STANDARD
-        .decode(contents)
-        .unwrap_or_else(|_| panic!("Failed to parse profile - {profile} - {contents}"));
-
-    let data_str = String::from_utf8(data)
-        .unwrap_or_else(|_| panic!("Failed to read profile data to UTF-8 string - {profile}"));
-
-    let profile_cfg: ProfileConfig = toml::from_str(&data_str)
-        .unwrap_or_else(|_| panic!("Failed to parse profile - {profile} - {contents}"));
-
-    // We have to setup for our pkg version to be passed into things correctly
-    // now. This relies on the profile build.rs to get the commit rev if present, but
-    // we combine it with the local package version
-    println!("cargo:rerun-if-env-changed=CARGO_PKG_VERSION");
-    println!("cargo:rerun-if-env-changed=KANIDM_PKG_COMMIT_REV");
-
-    let kanidm_pkg_version = match option_env!("KANIDM_PKG_COMMIT_REV") {
-        Some(commit_rev) => format!("{} {}", env!("CARGO_PKG_VERSION"), commit_rev),
-        None => env!("CARGO_PKG_VERSION").to_string(),
-    };
-
-    println!("cargo:rustc-env=KANIDM_PKG_VERSION={kanidm_pkg_version}");
-
-    // KANIDM_PKG_VERSION_HASH is used for cache busting in the web UI
-    let mut kanidm_pkg_version_hash = sha2::Sha256::new();
-    kanidm_pkg_version_hash.update(kanidm_pkg_version.as_bytes());
-    let kanidm_pkg_version_hash = &BASE64_STANDARD.encode(kanidm_pkg_version_hash.finalize())[..8];
-    println!("cargo:rustc-env=KANIDM_PKG_VERSION_HASH={kanidm_pkg_version_hash}");
-
-    let version_pre = env!("CARGO_PKG_VERSION_PRE");
-    if version_pre == "dev" {
-        println!("cargo:rustc-env=KANIDM_PRE_RELEASE=1");
-    }
-
-    // For some checks we only want the series (i.e. exclude the patch version).
-    let version_major = env!("CARGO_PKG_VERSION_MAJOR");
-    let version_minor = env!("CARGO_PKG_VERSION_MINOR");
-    println!("cargo:rustc-env=KANIDM_PKG_SERIES={version_major}.{version_minor}");
-
-    match profile_cfg.cpu_flags {
-        CpuOptLevel::apple_m1 => println!("cargo:rustc-env=RUSTFLAGS=-Ctarget-cpu=apple_m1"),
-        CpuOptLevel::armv8_a => {
-            println!("cargo:rustc-env=RUSTFLAGS=-Ctarget-feature=-lse")
-        }
-        CpuOptLevel::none => {}
-        CpuOptLevel::native => println!("cargo:rustc-env=RUSTFLAGS=-Ctarget-cpu=native"),
-        CpuOptLevel::neon_v8 => {
-            println!("cargo:rustc-env=RUSTFLAGS=-Ctarget-features=+neon,+fp-armv8")
-        }
-        CpuOptLevel::x86_64_legacy => println!("cargo:rustc-env=RUSTFLAGS=-Ctarget-cpu=x86-64"),
-        CpuOptLevel::x86_64_v2 => println!("cargo:rustc-env=RUSTFLAGS=-Ctarget-cpu=x86-64-v2"),
-        CpuOptLevel::x86_64_v3 => println!("cargo:rustc-env=RUSTFLAGS=-Ctarget-cpu=x86-64-v3"),
+#[cfg(test)]
+mod tests {
+    use super::BuildInfo;
+
+    const BUILD_INFO: BuildInfo = BuildInfo::current();
+
+    #[test]
+    fn test_build_info_current() {
+        assert!(BUILD_INFO.version.starts_with(env!("CARGO_PKG_VERSION")));
+        assert_eq!(BUILD_INFO.version_hash.len(), 8);
+        assert_eq!(
+            BUILD_INFO.series,
+            format!(
+                "{}.{}",
+                env!("CARGO_PKG_VERSION_MAJOR"),
+                env!("CARGO_PKG_VERSION_MINOR")
+            )
+        );
+        assert_eq!(
+            BUILD_INFO.pre_release,
+            env!("CARGO_PKG_VERSION_PRE") == "dev"
+        );
+        assert!(!BUILD_INFO.profile_name.is_empty());
+        assert!(!BUILD_INFO.cpu_flags.is_empty());
+        assert_eq!(BuildInfo::current(), BUILD_INFO);
     }
-    println!("cargo:rustc-env=KANIDM_PROFILE_NAME={profile}");
-    println!("cargo:rustc-env=KANIDM_CPU_FLAGS={}", profile_cfg.cpu_flags);
-    println!(
-        "cargo:rustc-env=KANIDM_SERVER_UI_PKG_PATH={}",
-        profile_cfg.server_ui_pkg_path
-    );
-    println!(
-        "cargo:rustc-env=KANIDM_SERVER_ADMIN_BIND_PATH={}",
-        profile_cfg.server_admin_bind_path
-    );
-    println!(
-        "cargo:rustc-env=KANIDM_SERVER_CONFIG_PATH={}",
-        profile_cfg.server_config_path
-    );
-    println!(
-        "cargo:rustc-env=KANIDM_SERVER_MIGRATION_PATH={}",
-        profile_cfg.server_migration_path
-    );
-    println!(
-        "cargo:rustc-env=KANIDM_CLIENT_CONFIG_PATH={}",
-        profile_cfg.client_config_path
-    );
-    println!(
-        "cargo:rustc-env=KANIDM_RESOLVER_CONFIG_PATH={}",
-        profile_cfg.resolver_config_path
-    );
-    println!(
-        "cargo:rustc-env=KANIDM_RESOLVER_SERVICE_ACCOUNT_TOKEN_PATH={}",
-        profile_cfg.resolver_service_account_token_path
-    );
-    println!(
-        "cargo:rustc-env=KANIDM_RESOLVER_UNIX_SHELL_PATH={}",
-        profile_cfg.resolver_unix_shell_path
-    );
 }
@@ -0,0 +1,340 @@
+use base64::prelude::BASE64_STANDARD;
+use base64::{engine::general_purpose, Engine as _};
+use serde::Deserialize;
+use sha2::Digest;
+use std::env;
+
+// To debug why a rebuild is requested.
+// CARGO_LOG=cargo::core::compiler::fingerprint=info cargo ...
+
+#[derive(Debug, Deserialize)]
+#[allow(non_camel_case_types)]
+enum CpuOptLevel {
+    apple_m1,
+    armv8_a,
+    none,
+    native,
+    neon_v8,
+    x86_64_legacy, // don't use this it's the oldest and worst. unless you've got a really old CPU, in which case, sorry?
+    x86_64_v2,
+    x86_64_v3,
+    // Requires AVX-512, binaries will fail with an illegal instruction on CPUs without it.
+    x86_64_v4,
+}
+
+impl Default for CpuOptLevel {
+    fn default() -> Self {
+        if cfg!(target_arch = "x86_64") {
+            CpuOptLevel::x86_64_v2
+        } else if cfg!(target_arch = "aarch64") && cfg!(target_os = "macos") {
+            CpuOptLevel::apple_m1
+        } else if cfg!(target_arch = "aarch64") && cfg!(target_os = "linux") {
+            // Keep native arm64 Linux release builds on the ARMv8.0-A baseline.
+            // Dependency C/C++ flags for cross builds are enforced in .cargo/config.toml.
+            CpuOptLevel::armv8_a
+        /*
+        } else if cfg!(target_arch = "aarch64") && cfg!(target_os = "linux") {
+            // Disable neon_v8 on linux - this has issues on non-apple hardware and on
+            // opensuse/distro builds.
+            CpuOptLevel::neon_v8
+        */
+        } else {
+            CpuOptLevel::none
+        }
+    }
+}
+
+impl std::fmt::Display for CpuOptLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self {
+            CpuOptLevel::apple_m1 => write!(f, "apple_m1"),
+            CpuOptLevel::armv8_a => write!(f, "armv8_a"),
+            CpuOptLevel::none => write!(f, "none"),
+            CpuOptLevel::native => write!(f, "native"),
+            CpuOptLevel::neon_v8 => write!(f, "neon_v8"),
+            CpuOptLevel::x86_64_legacy => write!(f, "x86_64"),
+            CpuOptLevel::x86_64_v2 => write!(f, "x86_64_v2"),
+            CpuOptLevel::x86_64_v3 => write!(f, "x86_64_v3"),
+            CpuOptLevel::x86_64_v4 => write!(f, "x86_64_v4"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfileConfig {
+    #[serde(default)]
+    cpu_flags: CpuOptLevel,
+    server_admin_bind_path: String,
+    server_config_path: String,
+    server_migration_path: String,
+    server_ui_pkg_path: String,
+    client_config_path: String,
+    resolver_config_path: String,
+    resolver_unix_shell_path: String,
+    resolver_service_account_token_path: String,
+    /// If true, the server defaults to a read only replica unless the runtime
+    /// configuration sets a role.
+    #[serde(default)]
+    default_read_only: Option<bool>,
+}
+
+/// The reasons a build profile could not be loaded.
+#[derive(Debug)]
+pub(crate) enum ProfileError {
+    /// The profile was not valid base64.
+    Base64(base64::DecodeError),
+    /// The decoded profile was not valid UTF-8.
+    Utf8(std::string::FromUtf8Error),
+    /// The profile was not valid toml, or did not match the expected layout.
+    Toml(toml::de::Error),
+    /// The named environment variable holding the profile was not set.
+    MissingEnv(&'static str),
+}
+
+impl std::fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProfileError::Base64(err) => write!(f, "invalid base64 - {err}"),
+            ProfileError::Utf8(err) => write!(f, "invalid UTF-8 - {err}"),
+            ProfileError::Toml(err) => write!(f, "invalid toml - {err}"),
+            ProfileError::MissingEnv(var) => write!(f, "{var} is not set"),
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {}
+
+fn parse_profile(data: &str) -> Result<ProfileConfig, toml::de::Error> {
+    toml::from_str(data)
+}
+
+/// Decode and parse a profile, where `contents` is the base64 encoded profile toml as
+/// found in `KANIDM_BUILD_PROFILE_TOML`.
+fn try_load_profile(contents: Option<&str>) -> Result<ProfileConfig, ProfileError> {
+    let contents = contents.ok_or(ProfileError::MissingEnv("KANIDM_BUILD_PROFILE_TOML"))?;
+
+    let data = general_purpose::STANDARD
+        .decode(contents)
+        .map_err(ProfileError::Base64)?;
+
+    let data_str = String::from_utf8(data).map_err(ProfileError::Utf8)?;
+
+    parse_profile(&data_str).map_err(ProfileError::Toml)
+}
+
+fn is_truthy(value: &str) -> bool {
+    matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "1" | "true" | "yes" | "on"
+    )
+}
+
+/// Combine the package version with the commit rev if known. Builds from a
+/// dirty working tree are marked so they can't be mistaken for a clean build
+/// of the same commit.
+fn compose_pkg_version(pkg_version: &str, commit_rev: Option<&str>, dirty: bool) -> String {
+    let mut kanidm_pkg_version = match commit_rev {
+        Some(commit_rev) => format!("{pkg_version} {commit_rev}"),
+        None => pkg_version.to_string(),
+    };
+
+    if dirty {
+        kanidm_pkg_version.push_str("-dirty");
+    }
+
+    kanidm_pkg_version
+}
+
+/// Emit the build environment of the named profile, where `contents` is the base64
+/// encoded profile toml. This is shared with our own build.rs so that the profile
+/// crate is built with the same environment as the crates that apply it.
+pub(crate) fn emit_profile(profile: &str, contents: Option<&str>, commit_rev: Option<&str>) {
+    println!("cargo:rerun-if-env-changed=KANIDM_BUILD_PROFILE");
+    println!("cargo:rerun-if-env-changed=KANIDM_BUILD_PROFILE_TOML");
+
+    // transform any requested paths for our server. We do this by reading
+    // our profile that we have been provided.
+
+    let profile_cfg = try_load_profile(contents)
+        .unwrap_or_else(|err| panic!("Failed to load profile - {profile} - {err}"));
+
+    // We have to setup for our pkg version to be passed into things correctly
+    // now. This relies on the profile build.rs to get the commit rev if present, but
+    // we combine it with the local package version
+    println!("cargo:rerun-if-env-changed=CARGO_PKG_VERSION");
+    println!("cargo:rerun-if-env-changed=KANIDM_PKG_COMMIT_REV");
+    println!("cargo:rerun-if-env-changed=KANIDM_PKG_DIRTY");
+
+    let dirty = env::var("KANIDM_PKG_DIRTY")
+        .map(|value| is_truthy(&value))
+        .unwrap_or(false);
+
+    let kanidm_pkg_version = compose_pkg_version(env!("CARGO_PKG_VERSION"), commit_rev, dirty);
+
+    println!("cargo:rustc-env=KANIDM_PKG_VERSION={kanidm_pkg_version}");
+
+    // KANIDM_PKG_VERSION_HASH is used for cache busting in the web UI
+    let mut kanidm_pkg_version_hash = sha2::Sha256::new();
+    kanidm_pkg_version_hash.update(kanidm_pkg_version.as_bytes());
+    let kanidm_pkg_version_hash = &BASE64_STANDARD.encode(kanidm_pkg_version_hash.finalize())[..8];
+    println!("cargo:rustc-env=KANIDM_PKG_VERSION_HASH={kanidm_pkg_version_hash}");
+
+    let version_pre = env!("CARGO_PKG_VERSION_PRE");
+    if version_pre == "dev" {
+        println!("cargo:rustc-env=KANIDM_PRE_RELEASE=1");
+    }
+
+    // For some checks we only want the series (i.e. exclude the patch version).
+    let version_major = env!("CARGO_PKG_VERSION_MAJOR");
+    let version_minor = env!("CARGO_PKG_VERSION_MINOR");
+    println!("cargo:rustc-env=KANIDM_PKG_SERIES={version_major}.{version_minor}");
+
+    match profile_cfg.cpu_flags {
+        CpuOptLevel::apple_m1 => println!("cargo:rustc-env=RUSTFLAGS=-Ctarget-cpu=apple_m1"),
+        CpuOptLevel::armv8_a => {
+            println!("cargo:rustc-env=RUSTFLAGS=-Ctarget-feature=-lse")
+        }
+        CpuOptLevel::none => {}
+        CpuOptLevel::native => println!("cargo:rustc-env=RUSTFLAGS=-Ctarget-cpu=native"),
+        CpuOptLevel::neon_v8 => {
+            println!("cargo:rustc-env=RUSTFLAGS=-Ctarget-features=+neon,+fp-armv8")
+        }
+        CpuOptLevel::x86_64_legacy => println!("cargo:rustc-env=RUSTFLAGS=-Ctarget-cpu=x86-64"),
+        CpuOptLevel::x86_64_v2 => println!("cargo:rustc-env=RUSTFLAGS=-Ctarget-cpu=x86-64-v2"),
+        CpuOptLevel::x86_64_v3 => println!("cargo:rustc-env=RUSTFLAGS=-Ctarget-cpu=x86-64-v3"),
+        CpuOptLevel::x86_64_v4 => println!("cargo:rustc-env=RUSTFLAGS=-Ctarget-cpu=x86-64-v4"),
+    }
+    println!("cargo:rustc-env=KANIDM_PROFILE_NAME={profile}");
+    println!("cargo:rustc-env=KANIDM_CPU_FLAGS={}", profile_cfg.cpu_flags);
+    println!(
+        "cargo:rustc-env=KANIDM_SERVER_UI_PKG_PATH={}",
+        profile_cfg.server_ui_pkg_path
+    );
+    println!(
+        "cargo:rustc-env=KANIDM_SERVER_ADMIN_BIND_PATH={}",
+        profile_cfg.server_admin_bind_path
+    );
+    println!(
+        "cargo:rustc-env=KANIDM_SERVER_CONFIG_PATH={}",
+        profile_cfg.server_config_path
+    );
+    println!(
+        "cargo:rustc-env=KANIDM_SERVER_MIGRATION_PATH={}",
+        profile_cfg.server_migration_path
+    );
+    println!(
+        "cargo:rustc-env=KANIDM_CLIENT_CONFIG_PATH={}",
+        profile_cfg.client_config_path
+    );
+    println!(
+        "cargo:rustc-env=KANIDM_RESOLVER_CONFIG_PATH={}",
+        profile_cfg.resolver_config_path
+    );
+    println!(
+        "cargo:rustc-env=KANIDM_RESOLVER_SERVICE_ACCOUNT_TOKEN_PATH={}",
+        profile_cfg.resolver_service_account_token_path
+    );
+    println!(
+        "cargo:rustc-env=KANIDM_RESOLVER_UNIX_SHELL_PATH={}",
+        profile_cfg.resolver_unix_shell_path
+    );
+    println!(
+        "cargo:rustc-env=KANIDM_DEFAULT_READ_ONLY={}",
+        profile_cfg.default_read_only.unwrap_or(false)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        compose_pkg_version, is_truthy, parse_profile, try_load_profile, CpuOptLevel, ProfileError,
+    };
+    use base64::{engine::general_purpose, Engine as _};
+
+    const PROFILE_BASE: &str = r#"
+server_admin_bind_path = "/tmp/kanidmd.sock"
+server_ui_pkg_path = "../core/static"
+server_config_path = "/etc/kanidm/server.toml"
+server_migration_path = "/etc/kanidm/migrations.d"
+client_config_path = "/etc/kanidm/config"
+resolver_config_path = "/etc/kanidm/unixd"
+resolver_service_account_token_path = "/etc/kanidm/token"
+resolver_unix_shell_path = "/bin/sh"
+"#;
+
+    #[test]
+    fn test_compose_pkg_version() {
+        assert_eq!(compose_pkg_version("1.2.3", None, false), "1.2.3");
+        assert_eq!(
+            compose_pkg_version("1.2.3", Some("abcdef0123"), false),
+            "1.2.3 abcdef0123"
+        );
+        assert_eq!(compose_pkg_version("1.2.3", None, true), "1.2.3-dirty");
+        assert_eq!(
+            compose_pkg_version("1.2.3", Some("abcdef0123"), true),
+            "1.2.3 abcdef0123-dirty"
+        );
+
+        assert!(is_truthy("1"));
+        assert!(is_truthy("TRUE"));
+        assert!(!is_truthy("0"));
+        assert!(!is_truthy(""));
+    }
+
+    #[test]
+    fn test_profile_default_read_only() {
+        let profile_cfg = parse_profile(PROFILE_BASE).expect("Failed to parse profile");
+        assert_eq!(profile_cfg.default_read_only, None);
+
+        let profile_cfg = parse_profile(&format!("{PROFILE_BASE}default_read_only = true\n"))
+            .expect("Failed to parse profile");
+        assert_eq!(profile_cfg.default_read_only, Some(true));
+
+        let profile_cfg = parse_profile(&format!("{PROFILE_BASE}default_read_only = false\n"))
+            .expect("Failed to parse profile");
+        assert_eq!(profile_cfg.default_read_only, Some(false));
+
+        // Only a real toml bool is accepted.
+        assert!(parse_profile(&format!("{PROFILE_BASE}default_read_only = \"true\"\n")).is_err());
+        assert!(parse_profile(&format!("{PROFILE_BASE}default_read_only = 1\n")).is_err());
+    }
+
+    #[test]
+    fn test_profile_cpu_flags() {
+        let profile_cfg = parse_profile(&format!("{PROFILE_BASE}cpu_flags = \"x86_64_v4\"\n"))
+            .expect("Failed to parse profile");
+        assert!(matches!(profile_cfg.cpu_flags, CpuOptLevel::x86_64_v4));
+        assert_eq!(profile_cfg.cpu_flags.to_string(), "x86_64_v4");
+
+        assert!(parse_profile(&format!("{PROFILE_BASE}cpu_flags = \"x86_64_v5\"\n")).is_err());
+    }
+
+    #[test]
+    fn test_try_load_profile() {
+        let contents = general_purpose::STANDARD.encode(PROFILE_BASE);
+        assert!(try_load_profile(Some(&contents)).is_ok());
+
+        assert!(matches!(
+            try_load_profile(None),
+            Err(ProfileError::MissingEnv("KANIDM_BUILD_PROFILE_TOML"))
+        ));
+
+        assert!(matches!(
+            try_load_profile(Some("not base64!")),
+            Err(ProfileError::Base64(_))
+        ));
+
+        let contents = general_purpose::STANDARD.encode([0xff, 0xfe, 0xfd]);
+        assert!(matches!(
+            try_load_profile(Some(&contents)),
+            Err(ProfileError::Utf8(_))
+        ));
+
+        let contents = general_purpose::STANDARD.encode("server_admin_bind_path = ");
+        assert!(matches!(
+            try_load_profile(Some(&contents)),
+            Err(ProfileError::Toml(_))
+        ));
+    }
+}
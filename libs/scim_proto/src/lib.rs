@@ -10,8 +10,10 @@
 #![deny(clippy::needless_pass_by_value)]
 #![deny(clippy::trivially_copy_pass_by_ref)]
 
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
 use base64urlsafedata::Base64UrlSafeData;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::BTreeMap;
 use time::{format_description::well_known::Rfc3339, OffsetDateTime};
 use url::Url;
@@ -149,6 +151,22 @@ pub struct ScimMeta {
     pub version: String,
 }
 
+impl ScimMeta {
+    /// The version of an entry with these attributes, for use in `meta.version` and
+    /// as an ETag in change detection. This is the base64url encoded SHA-256 of the
+    /// JSON form of the attributes.
+    pub fn version_of<K, V>(attrs: &BTreeMap<K, V>) -> Result<String, serde_json::Error>
+    where
+        K: Serialize + Ord,
+        V: Serialize,
+    {
+        // The BTreeMap is ordered, so the same content always has the same hash.
+        let content = serde_json::to_string(attrs)?;
+        let digest = Sha256::digest(content.as_bytes());
+        Ok(URL_SAFE_NO_PAD.encode(digest))
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ScimEntryHeader {
@@ -178,6 +196,31 @@ mod tests {
     use super::*;
     use crate::constants::RFC7643_USER;
 
+    #[test]
+    fn scim_meta_version_of() {
+        let mut attrs = BTreeMap::new();
+        attrs.insert("name".to_string(), "testperson".to_string());
+        attrs.insert("displayname".to_string(), "Test Person".to_string());
+
+        let version = ScimMeta::version_of(&attrs).expect("Failed to compute version");
+        // base64url of a sha256 digest, without padding.
+        assert_eq!(version.len(), 43);
+        assert!(!version.contains(['+', '/', '=']));
+
+        // The same content has the same version.
+        assert_eq!(
+            ScimMeta::version_of(&attrs.clone()).expect("Failed to compute version"),
+            version
+        );
+
+        // Different content changes the version.
+        attrs.insert("displayname".to_string(), "Changed".to_string());
+        assert_ne!(
+            ScimMeta::version_of(&attrs).expect("Failed to compute version"),
+            version
+        );
+    }
+
     #[test]
     fn parse_scim_entry() {
         let _ = tracing_subscriber::fmt::try_init();
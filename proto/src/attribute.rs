@@ -20,6 +20,7 @@ pub enum Attribute {
     AccountSoftlockExpire,
     AcpCreateAttr,
     AcpCreateClass,
+    AcpDenyAttribute,
     AcpEnable,
     AcpModifyClass,
     AcpModifyPresentClass,
@@ -30,6 +31,8 @@ pub enum Attribute {
     AcpReceiverGroup,
     AcpSearchAttr,
     AcpTargetScope,
+    /// A SCIM virtual attribute, derived from account_valid_from and account_expire
+    Active,
     ApiTokenSession,
     ApplicationPassword,
     ApplicationUrl,
@@ -48,6 +51,7 @@ pub enum Attribute {
     ClassName,
     Cn,
     CookiePrivateKey,
+    CreatedAt,
     CreatedAtCid,
     CredentialUpdateIntentToken,
     CredentialTypeMinimum,
@@ -62,8 +66,12 @@ pub enum Attribute {
     DomainAllowAccountRecovery,
     DomainDevelopmentTaint,
     DomainDisplayName,
+    DomainImageMaxBytes,
+    DomainImageMaxHeight,
+    DomainImageMaxWidth,
     DomainLdapBasedn,
     DomainName,
+    DomainRevokedSessionRetentionDays,
     DomainSsid,
     DomainTokenKey,
     DomainUuid,
@@ -87,6 +95,7 @@ pub enum Attribute {
     HmacNameHistory,
     HomeDirectory,
     IdVerificationEcKey,
+    IdpCertificate,
     Image,
     Index,
     Indexed,
@@ -126,6 +135,7 @@ pub enum Attribute {
     NoIndex,
     NsUniqueId,
     NsAccountLock,
+    OAuth2AccessTokenExpiry,
     OAuth2AllowInsecureClientDisablePkce,
     OAuth2AllowLocalhostRedirect,
     OAuth2AuthorisationEndpoint,
@@ -296,6 +306,7 @@ impl Attribute {
             Attribute::AccountSoftlockExpire => ATTR_ACCOUNT_SOFTLOCK_EXPIRE,
             Attribute::AcpCreateAttr => ATTR_ACP_CREATE_ATTR,
             Attribute::AcpCreateClass => ATTR_ACP_CREATE_CLASS,
+            Attribute::AcpDenyAttribute => ATTR_ACP_DENY_ATTRIBUTE,
             Attribute::AcpEnable => ATTR_ACP_ENABLE,
             Attribute::AcpModifyClass => ATTR_ACP_MODIFY_CLASS,
             Attribute::AcpModifyPresentClass => ATTR_ACP_MODIFY_PRESENT_CLASS,
@@ -306,6 +317,7 @@ impl Attribute {
             Attribute::AcpReceiverGroup => ATTR_ACP_RECEIVER_GROUP,
             Attribute::AcpSearchAttr => ATTR_ACP_SEARCH_ATTR,
             Attribute::AcpTargetScope => ATTR_ACP_TARGET_SCOPE,
+            Attribute::Active => ATTR_ACTIVE,
             Attribute::AllowPrimaryCredFallback => ATTR_ALLOW_PRIMARY_CRED_FALLBACK,
             Attribute::ApiTokenSession => ATTR_API_TOKEN_SESSION,
             Attribute::ApplicationPassword => ATTR_APPLICATION_PASSWORD,
@@ -324,6 +336,7 @@ impl Attribute {
             Attribute::ClassName => ATTR_CLASSNAME,
             Attribute::Cn => ATTR_CN,
             Attribute::CookiePrivateKey => ATTR_COOKIE_PRIVATE_KEY,
+            Attribute::CreatedAt => ATTR_CREATED_AT,
             Attribute::CreatedAtCid => ATTR_CREATED_AT_CID,
             Attribute::CredentialUpdateIntentToken => ATTR_CREDENTIAL_UPDATE_INTENT_TOKEN,
             Attribute::CredentialTypeMinimum => ATTR_CREDENTIAL_TYPE_MINIMUM,
@@ -338,8 +351,14 @@ impl Attribute {
             Attribute::DomainAllowAccountRecovery => ATTR_DOMAIN_ALLOW_ACCOUNT_RECOVERY,
             Attribute::DomainDevelopmentTaint => ATTR_DOMAIN_DEVELOPMENT_TAINT,
             Attribute::DomainDisplayName => ATTR_DOMAIN_DISPLAY_NAME,
+            Attribute::DomainImageMaxBytes => ATTR_DOMAIN_IMAGE_MAX_BYTES,
+            Attribute::DomainImageMaxHeight => ATTR_DOMAIN_IMAGE_MAX_HEIGHT,
+            Attribute::DomainImageMaxWidth => ATTR_DOMAIN_IMAGE_MAX_WIDTH,
             Attribute::DomainLdapBasedn => ATTR_DOMAIN_LDAP_BASEDN,
             Attribute::DomainName => ATTR_DOMAIN_NAME,
+            Attribute::DomainRevokedSessionRetentionDays => {
+                ATTR_DOMAIN_REVOKED_SESSION_RETENTION_DAYS
+            }
             Attribute::DomainSsid => ATTR_DOMAIN_SSID,
             Attribute::DomainTokenKey => ATTR_DOMAIN_TOKEN_KEY,
             Attribute::DomainUuid => ATTR_DOMAIN_UUID,
@@ -363,6 +382,7 @@ impl Attribute {
             Attribute::HmacNameHistory => ATTR_HMAC_NAME_HISTORY,
             Attribute::HomeDirectory => ATTR_HOME_DIRECTORY,
             Attribute::IdVerificationEcKey => ATTR_ID_VERIFICATION_ECKEY,
+            Attribute::IdpCertificate => ATTR_IDP_CERTIFICATE,
             Attribute::Image => ATTR_IMAGE,
             Attribute::Index => ATTR_INDEX,
             Attribute::Indexed => ATTR_INDEXED,
@@ -401,6 +421,7 @@ impl Attribute {
             Attribute::NoIndex => ATTR_NO_INDEX,
             Attribute::NsUniqueId => ATTR_NSUNIQUEID,
             Attribute::NsAccountLock => ATTR_NSACCOUNTLOCK,
+            Attribute::OAuth2AccessTokenExpiry => ATTR_OAUTH2_ACCESS_TOKEN_EXPIRY,
             Attribute::OAuth2AllowInsecureClientDisablePkce => {
                 ATTR_OAUTH2_ALLOW_INSECURE_CLIENT_DISABLE_PKCE
             }
@@ -520,6 +541,7 @@ impl Attribute {
             ATTR_ACCOUNT_SOFTLOCK_EXPIRE => Attribute::AccountSoftlockExpire,
             ATTR_ACP_CREATE_ATTR => Attribute::AcpCreateAttr,
             ATTR_ACP_CREATE_CLASS => Attribute::AcpCreateClass,
+            ATTR_ACP_DENY_ATTRIBUTE => Attribute::AcpDenyAttribute,
             ATTR_ACP_ENABLE => Attribute::AcpEnable,
             ATTR_ACP_MODIFY_CLASS => Attribute::AcpModifyClass,
             ATTR_ACP_MODIFY_PRESENT_CLASS => Attribute::AcpModifyPresentClass,
@@ -530,6 +552,7 @@ impl Attribute {
             ATTR_ACP_RECEIVER_GROUP => Attribute::AcpReceiverGroup,
             ATTR_ACP_SEARCH_ATTR => Attribute::AcpSearchAttr,
             ATTR_ACP_TARGET_SCOPE => Attribute::AcpTargetScope,
+            ATTR_ACTIVE => Attribute::Active,
             ATTR_ALLOW_PRIMARY_CRED_FALLBACK => Attribute::AllowPrimaryCredFallback,
             ATTR_API_TOKEN_SESSION => Attribute::ApiTokenSession,
             ATTR_APPLICATION_PASSWORD => Attribute::ApplicationPassword,
@@ -548,6 +571,7 @@ impl Attribute {
             ATTR_CLASSNAME => Attribute::ClassName,
             ATTR_CN => Attribute::Cn,
             ATTR_COOKIE_PRIVATE_KEY => Attribute::CookiePrivateKey,
+            ATTR_CREATED_AT => Attribute::CreatedAt,
             ATTR_CREATED_AT_CID => Attribute::CreatedAtCid,
             ATTR_CREDENTIAL_UPDATE_INTENT_TOKEN => Attribute::CredentialUpdateIntentToken,
             ATTR_CREDENTIAL_TYPE_MINIMUM => Attribute::CredentialTypeMinimum,
@@ -561,9 +585,15 @@ impl Attribute {
             ATTR_DOMAIN_ALLOW_EASTER_EGGS => Attribute::DomainAllowEasterEggs,
             ATTR_DOMAIN_ALLOW_ACCOUNT_RECOVERY => Attribute::DomainAllowAccountRecovery,
             ATTR_DOMAIN_DISPLAY_NAME => Attribute::DomainDisplayName,
+            ATTR_DOMAIN_IMAGE_MAX_BYTES => Attribute::DomainImageMaxBytes,
+            ATTR_DOMAIN_IMAGE_MAX_HEIGHT => Attribute::DomainImageMaxHeight,
+            ATTR_DOMAIN_IMAGE_MAX_WIDTH => Attribute::DomainImageMaxWidth,
             ATTR_DOMAIN_DEVELOPMENT_TAINT => Attribute::DomainDevelopmentTaint,
             ATTR_DOMAIN_LDAP_BASEDN => Attribute::DomainLdapBasedn,
             ATTR_DOMAIN_NAME => Attribute::DomainName,
+            ATTR_DOMAIN_REVOKED_SESSION_RETENTION_DAYS => {
+                Attribute::DomainRevokedSessionRetentionDays
+            }
             ATTR_DOMAIN_SSID => Attribute::DomainSsid,
             ATTR_DOMAIN_TOKEN_KEY => Attribute::DomainTokenKey,
             ATTR_DOMAIN_UUID => Attribute::DomainUuid,
@@ -587,6 +617,7 @@ impl Attribute {
             ATTR_HMAC_NAME_HISTORY => Attribute::HmacNameHistory,
             ATTR_HOME_DIRECTORY => Attribute::HomeDirectory,
             ATTR_ID_VERIFICATION_ECKEY => Attribute::IdVerificationEcKey,
+            ATTR_IDP_CERTIFICATE => Attribute::IdpCertificate,
             ATTR_IMAGE => Attribute::Image,
             ATTR_INDEX => Attribute::Index,
             ATTR_INDEXED => Attribute::Indexed,
@@ -625,6 +656,7 @@ impl Attribute {
             ATTR_NO_INDEX => Attribute::NoIndex,
             ATTR_NSUNIQUEID => Attribute::NsUniqueId,
             ATTR_NSACCOUNTLOCK => Attribute::NsAccountLock,
+            ATTR_OAUTH2_ACCESS_TOKEN_EXPIRY => Attribute::OAuth2AccessTokenExpiry,
             ATTR_OAUTH2_ALLOW_INSECURE_CLIENT_DISABLE_PKCE => {
                 Attribute::OAuth2AllowInsecureClientDisablePkce
             }
@@ -61,6 +61,7 @@ pub const ATTR_ACCOUNT_SOFTLOCK_EXPIRE: &str = "account_softlock_expire";
 pub const ATTR_ACCOUNT: &str = "account";
 pub const ATTR_ACP_CREATE_ATTR: &str = "acp_create_attr";
 pub const ATTR_ACP_CREATE_CLASS: &str = "acp_create_class";
+pub const ATTR_ACP_DENY_ATTRIBUTE: &str = "acp_deny_attribute";
 pub const ATTR_DELETE_AFTER: &str = "delete_after";
 pub const ATTR_ACP_ENABLE: &str = "acp_enable";
 pub const ATTR_ACP_MODIFY_CLASS: &str = "acp_modify_class";
@@ -72,6 +73,7 @@ pub const ATTR_ACP_RECEIVER_GROUP: &str = "acp_receiver_group";
 pub const ATTR_ACP_RECEIVER: &str = "acp_receiver";
 pub const ATTR_ACP_SEARCH_ATTR: &str = "acp_search_attr";
 pub const ATTR_ACP_TARGET_SCOPE: &str = "acp_targetscope";
+pub const ATTR_ACTIVE: &str = "active";
 pub const ATTR_API_TOKEN_SESSION: &str = "api_token_session";
 pub const ATTR_APPLICATION_PASSWORD: &str = "application_password";
 pub const ATTR_APPLICATION_URL: &str = "application_url";
@@ -89,6 +91,7 @@ pub const ATTR_CLASS: &str = "class";
 pub const ATTR_CLASSNAME: &str = "classname";
 pub const ATTR_CN: &str = "cn";
 pub const ATTR_COOKIE_PRIVATE_KEY: &str = "cookie_private_key";
+pub const ATTR_CREATED_AT: &str = "created_at";
 pub const ATTR_CREATED_AT_CID: &str = "created_at_cid";
 pub const ATTR_CREDENTIAL_UPDATE_INTENT_TOKEN: &str = "credential_update_intent_token";
 pub const ATTR_CREDENTIAL_TYPE_MINIMUM: &str = "credential_type_minimum";
@@ -101,8 +104,13 @@ pub const ATTR_DOMAIN_ALLOW_EASTER_EGGS: &str = "domain_allow_easter_eggs";
 pub const ATTR_DOMAIN_ALLOW_ACCOUNT_RECOVERY: &str = "domain_allow_account_recovery";
 pub const ATTR_DOMAIN_DEVELOPMENT_TAINT: &str = "domain_development_taint";
 pub const ATTR_DOMAIN_DISPLAY_NAME: &str = "domain_display_name";
+pub const ATTR_DOMAIN_IMAGE_MAX_BYTES: &str = "domain_image_max_bytes";
+pub const ATTR_DOMAIN_IMAGE_MAX_HEIGHT: &str = "domain_image_max_height";
+pub const ATTR_DOMAIN_IMAGE_MAX_WIDTH: &str = "domain_image_max_width";
 pub const ATTR_DOMAIN_LDAP_BASEDN: &str = "domain_ldap_basedn";
 pub const ATTR_DOMAIN_NAME: &str = "domain_name";
+pub const ATTR_DOMAIN_REVOKED_SESSION_RETENTION_DAYS: &str =
+    "domain_revoked_session_retention_days";
 pub const ATTR_DOMAIN_SSID: &str = "domain_ssid";
 pub const ATTR_DOMAIN_TOKEN_KEY: &str = "domain_token_key";
 pub const ATTR_DOMAIN_UUID: &str = "domain_uuid";
@@ -132,6 +140,7 @@ pub const ATTR_GROUP: &str = "group";
 pub const ATTR_HMAC_NAME_HISTORY: &str = "hmac_name_history";
 pub const ATTR_HOME_DIRECTORY: &str = "homedirectory";
 pub const ATTR_ID_VERIFICATION_ECKEY: &str = "id_verification_eckey";
+pub const ATTR_IDP_CERTIFICATE: &str = "idp_certificate";
 pub const ATTR_IMAGE: &str = "image";
 pub const ATTR_INDEX: &str = "index";
 pub const ATTR_INDEXED: &str = "indexed";
@@ -166,6 +175,7 @@ pub const ATTR_NSACCOUNTLOCK: &str = "nsaccountlock";
 pub const ATTR_NSUNIQUEID: &str = "nsuniqueid";
 pub const ATTR_PWD_CHANGED_TIME: &str = "pwd_changed_time";
 
+pub const ATTR_OAUTH2_ACCESS_TOKEN_EXPIRY: &str = "oauth2_access_token_expiry";
 pub const ATTR_OAUTH2_ALLOW_INSECURE_CLIENT_DISABLE_PKCE: &str =
     "oauth2_allow_insecure_client_disable_pkce";
 pub const ATTR_OAUTH2_ALLOW_LOCALHOST_REDIRECT: &str = "oauth2_allow_localhost_redirect";
@@ -300,6 +310,8 @@ pub const TEST_ENTRYCLASS_TEST_CLASS: &str = "testclass";
 
 /// HTTP Header containing an auth session ID for when you're going through an auth flow
 pub const KSESSIONID: &str = "X-KANIDM-AUTH-SESSION-ID";
+/// HTTP Header containing a credential update session token
+pub const KCUSESSIONTOKEN: &str = "X-KANIDM-CU-SESSION-TOKEN";
 /// HTTP Header containing the backend operation ID
 pub const KOPID: &str = "X-KANIDM-OPID";
 /// HTTP Header containing the Kanidm server version
@@ -340,6 +352,7 @@ pub const ENTRYCLASS_DOMAIN_INFO: &str = "domain_info";
 pub const ENTRYCLASS_DYN_GROUP: &str = "dyngroup";
 pub const ENTRYCLASS_EXTENSIBLE_OBJECT: &str = "extensibleobject";
 pub const ENTRYCLASS_GROUP: &str = "group";
+pub const ENTRYCLASS_IDENTITY_PROVIDER_CERTIFICATE: &str = "identity_provider_certificate";
 pub const ENTRYCLASS_FEATURE: &str = "feature";
 pub const ENTRYCLASS_MEMBER_OF: &str = "memberof";
 pub const ENTRYCLASS_MEMORIAL: &str = "memorial";
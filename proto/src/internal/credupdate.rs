@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
+use std::str::FromStr;
 use url::Url;
 use utoipa::ToSchema;
 use uuid::Uuid;
@@ -68,6 +69,55 @@ pub struct CUIntentSend {
     pub email: Option<String>,
 }
 
+/// A type of credential that a credential update intent token can be limited to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CUIntentPermission {
+    Primary,
+    Passkeys,
+    AttestedPasskeys,
+    UnixCred,
+    SshPublicKey,
+}
+
+impl fmt::Display for CUIntentPermission {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CUIntentPermission::Primary => write!(f, "primary"),
+            CUIntentPermission::Passkeys => write!(f, "passkeys"),
+            CUIntentPermission::AttestedPasskeys => write!(f, "attested_passkeys"),
+            CUIntentPermission::UnixCred => write!(f, "unixcred"),
+            CUIntentPermission::SshPublicKey => write!(f, "sshpubkey"),
+        }
+    }
+}
+
+impl FromStr for CUIntentPermission {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "primary" => Ok(CUIntentPermission::Primary),
+            "passkeys" => Ok(CUIntentPermission::Passkeys),
+            "attested_passkeys" => Ok(CUIntentPermission::AttestedPasskeys),
+            "unixcred" => Ok(CUIntentPermission::UnixCred),
+            "sshpubkey" => Ok(CUIntentPermission::SshPublicKey),
+            _ => Err(format!(
+                "Invalid permission '{s}', must be one of primary, passkeys, attested_passkeys, unixcred or sshpubkey"
+            )),
+        }
+    }
+}
+
+/// Structure denoting the parameters for creating a credential update intent
+/// token. If `permissions` is set, the token can only be used to update those
+/// credential types, and only where the requester could update them.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CUIntentCreate {
+    pub ttl: Option<u64>,
+    pub permissions: Option<BTreeSet<CUIntentPermission>>,
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct CUIntentToken {
     pub token: String,
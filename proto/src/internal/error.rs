@@ -21,6 +21,58 @@ pub enum SchemaError {
     EmptyFilter,
     Corrupted,
     PhantomAttribute(String),
+    ImageTooLarge { width: u32, height: u32, bytes: u64 },
+    InvalidDisplayName { value: String, reason: String },
+}
+
+impl SchemaError {
+    /// Return a short, human readable suggestion on how to resolve this error, if there is one.
+    pub fn resolution_hint(&self) -> Option<&'static str> {
+        match self {
+            Self::NotImplemented => None,
+            Self::NoClassFound => Some("Add at least one class to the entry."),
+            Self::InvalidClass(_) => {
+                Some("Check that the class names are spelt correctly and exist in the schema.")
+            }
+            Self::MissingMustAttribute(_) => {
+                Some("Provide a value for each listed attribute, as the entry's classes require them.")
+            }
+            Self::InvalidAttribute(_) => {
+                Some("Check that the attribute name is spelt correctly and exists in the schema.")
+            }
+            Self::InvalidAttributeSyntax(_) => {
+                Some("Check that the value matches the syntax the schema defines for this attribute.")
+            }
+            Self::AttributeNotValidForClass(_) => {
+                Some("Add a class to the entry that allows this attribute, or remove the attribute.")
+            }
+            Self::SupplementsNotSatisfied(_) => {
+                Some("Add one of the listed classes, as the entry's classes require it.")
+            }
+            Self::ExcludesNotSatisfied(_) => {
+                Some("Remove the listed classes, as they can not be combined with the entry's other classes.")
+            }
+            Self::EmptyFilter => Some("Provide a filter with at least one condition."),
+            Self::Corrupted => None,
+            Self::PhantomAttribute(_) => {
+                Some("This attribute is generated by the server and can not be set directly.")
+            }
+            Self::ImageTooLarge { .. } => Some("Resize or compress the image and try again."),
+            Self::InvalidDisplayName { .. } => {
+                Some("Choose a display name that addresses the reason given.")
+            }
+        }
+    }
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{self:?}")?;
+        if let Some(hint) = self.resolution_hint() {
+            write!(f, " - {hint}")?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, ToSchema)]
@@ -30,6 +82,7 @@ pub enum PluginError {
     ReferentialIntegrity(String),
     CredImport(String),
     Oauth2Secrets,
+    SpnNotUnique(String),
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, ToSchema)]
@@ -52,6 +105,7 @@ pub enum ConsistencyError {
     SqliteIntegrityFailure,
     BackendAllIdsSync,
     BackendIndexSync,
+    BackendEntryCacheSync(u64),
     ChangelogDesynchronised(u64),
     ChangeStateDesynchronised(u64),
     RuvInconsistent(String),
@@ -74,6 +128,7 @@ pub enum OperationError {
     NoMatchingEntries,
     NoMatchingAttributes,
     UniqueConstraintViolation,
+    EntryAlreadyExists,
     CorruptedEntry(u64),
     CorruptedIndex(String),
     ConsistencyError(Vec<ConsistencyError>),
@@ -231,6 +286,7 @@ pub enum OperationError {
     SC0031Int64SyntaxInvalid,
     SC0032Uint64SyntaxInvalid,
     SC0033AssertionContainsDuplicateUuids,
+    SC0034ActiveConflictsWithAccountExpire,
     // Migration
     MG0001InvalidReMigrationLevel,
     MG0002RaiseDomainLevelExceedsMaximum,
@@ -331,6 +387,10 @@ pub enum OperationError {
 
     // Plugins
     PL0001GidOverlapsSystemRange,
+    PL0002MailNoAddresses,
+    PL0003MailMultiplePrimary,
+    PL0004OAuth2AccessTokenExpiryInvalid,
+    PL0005OAuth2RefreshTokenExpiryTooShort,
 
     // Web UI
     UI0001ChallengeSerialisation,
@@ -345,6 +405,7 @@ pub enum OperationError {
     KU004PamInitFailed,
     KU005ErrorCheckingAccount,
     KU006OnlyRootAllowed,
+    KU007CacheWarmupFailed,
 }
 
 impl PartialEq for OperationError {
@@ -381,10 +442,11 @@ impl OperationError {
             Self::NoMatchingEntries => None,
             Self::NoMatchingAttributes => None,
             Self::UniqueConstraintViolation => Some("A unique constraint was violated resulting in multiple conflicting results.".into()),
+            Self::EntryAlreadyExists => Some("An entry matching the create condition already exists.".into()),
             Self::CorruptedEntry(_) => None,
             Self::CorruptedIndex(_) => None,
             Self::ConsistencyError(_) => None,
-            Self::SchemaViolation(_) => None,
+            Self::SchemaViolation(err) => err.resolution_hint().map(|hint| hint.into()),
             Self::Plugin(_) => None,
             Self::FilterGeneration => None,
             Self::FilterParseError => None,
@@ -564,6 +626,7 @@ impl OperationError {
             Self::KU004PamInitFailed => Some("Failed to initialise PAM authentication".into()),
             Self::KU005ErrorCheckingAccount => Some("Error checking account".into()),
             Self::KU006OnlyRootAllowed => Some("Only root is allowed to perform this operation".into()),
+            Self::KU007CacheWarmupFailed => Some("Failed to warm up the cache".into()),
             Self::LD0001AnonymousNotAllowed => Some("Anonymous is not allowed to access LDAP with this method.".into()),
             Self::MG0001InvalidReMigrationLevel => None,
             Self::MG0002RaiseDomainLevelExceedsMaximum => None,
@@ -576,6 +639,10 @@ impl OperationError {
             Self::MG0009InvalidTargetLevelForBootstrap => Some("The request target domain level was not valid for bootstrapping a new server instance".into()),
             Self::MG0010DowngradeNotAllowed => Some("Downgrade Attempted".into()),
             Self::PL0001GidOverlapsSystemRange => None,
+            Self::PL0002MailNoAddresses => Some("A mail attribute was provided without any addresses".into()),
+            Self::PL0003MailMultiplePrimary => Some("More than one mail address was marked as primary".into()),
+            Self::PL0004OAuth2AccessTokenExpiryInvalid => Some("The OAuth2 access token expiry must be between 30 and 3600 seconds".into()),
+            Self::PL0005OAuth2RefreshTokenExpiryTooShort => Some("The OAuth2 refresh token expiry must not be shorter than the access token expiry".into()),
             Self::SC0001IncomingSshPublicKey => None,
             Self::SC0002ReferenceSyntaxInvalid => Some("A SCIM Reference Set contained invalid syntax and can not be processed.".into()),
             Self::SC0003MailSyntaxInvalid => Some("A SCIM Mail Address contained invalid syntax".into()),
@@ -610,6 +677,7 @@ impl OperationError {
             Self::SC0031Int64SyntaxInvalid => Some("A SCIM Int64 contained invalid syntax".into()),
             Self::SC0032Uint64SyntaxInvalid => Some("A SCIM Uint64 contained invalid syntax".into()),
             Self::SC0033AssertionContainsDuplicateUuids => Some("SCIM assertion contains duplicate entry ids, unable to proceed.".into()),
+            Self::SC0034ActiveConflictsWithAccountExpire => Some("A SCIM PUT can not set both active and account_expire.".into()),
             Self::UI0001ChallengeSerialisation => Some("The WebAuthn challenge was unable to be serialised.".into()),
             Self::UI0002InvalidState => Some("The credential update process returned an invalid state transition.".into()),
             Self::UI0003InvalidOauth2Resume => Some("The server attempted to resume OAuth2, but no OAuth2 session is in progress.".into()),
@@ -18,11 +18,13 @@ use num_enum::TryFromPrimitive;
 mod credupdate;
 mod error;
 mod raw;
+mod tls;
 mod token;
 
 pub use self::credupdate::*;
 pub use self::error::*;
 pub use self::raw::*;
+pub use self::tls::*;
 pub use self::token::*;
 
 pub const COOKIE_CSRF_NONCE: &str = "csrf-nonce";
@@ -274,6 +276,34 @@ pub struct DomainUpgradeCheckItem {
     pub affected_entries: Vec<String>,
 }
 
+/// The details of a certificate held by an account, decoded so that an administrator
+/// can identify it without needing to parse the certificate themselves.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct CertificateSummary {
+    /// The uuid of the entry that holds this certificate.
+    pub uuid: Uuid,
+    pub subject: String,
+    pub issuer: String,
+    #[serde(with = "time::serde::timestamp")]
+    pub not_before: time::OffsetDateTime,
+    #[serde(with = "time::serde::timestamp")]
+    pub not_after: time::OffsetDateTime,
+    /// The hex encoded SHA-256 digest of the DER encoded certificate. This is how
+    /// the certificate is identified when it is removed.
+    pub fingerprint_sha256: String,
+}
+
+impl fmt::Display for CertificateSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "uuid: {}", self.uuid)?;
+        writeln!(f, "subject: {}", self.subject)?;
+        writeln!(f, "issuer: {}", self.issuer)?;
+        writeln!(f, "not before: {}", self.not_before)?;
+        writeln!(f, "not after: {}", self.not_after)?;
+        writeln!(f, "sha256 fingerprint: {}", self.fingerprint_sha256)
+    }
+}
+
 #[test]
 fn test_fstype_deser() {
     assert_eq!(FsType::try_from("zfs"), Ok(FsType::Zfs));
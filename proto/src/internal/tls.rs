@@ -0,0 +1,146 @@
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use crypto_glue::hex;
+use crypto_glue::s256::{Sha256, Sha256Output};
+use crypto_glue::traits::{DecodeDer, Digest, EncodeDer};
+use crypto_glue::x509::Certificate;
+use std::fmt;
+use std::str::FromStr;
+
+/// A pin of a TLS server's public key - the SHA-256 digest of the DER encoded
+/// SubjectPublicKeyInfo of its certificate. Because the key rather than the
+/// certificate is pinned, the pin survives a certificate being renewed with the
+/// same key.
+///
+/// This is parsed from either hex, or base64 as used by `curl --pinnedpubkey`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct TlsPinSha256([u8; 32]);
+
+impl TlsPinSha256 {
+    /// The pin of a DER encoded SubjectPublicKeyInfo.
+    pub fn from_spki_der(spki_der: &[u8]) -> Self {
+        let digest: Sha256Output = Sha256::digest(spki_der);
+        TlsPinSha256(digest.into())
+    }
+
+    /// The pin of the public key in a DER encoded certificate.
+    pub fn from_certificate_der(cert_der: &[u8]) -> Result<Self, &'static str> {
+        let cert = Certificate::from_der(cert_der).map_err(|_| "invalid certificate")?;
+        let spki_der = cert
+            .tbs_certificate
+            .subject_public_key_info
+            .to_der()
+            .map_err(|_| "invalid certificate public key")?;
+        Ok(Self::from_spki_der(&spki_der))
+    }
+
+    /// If this pin matches the DER encoded SubjectPublicKeyInfo.
+    pub fn matches_spki(&self, spki_der: &[u8]) -> bool {
+        *self == Self::from_spki_der(spki_der)
+    }
+
+    /// If this pin matches the public key of a DER encoded certificate. A certificate
+    /// that can not be decoded never matches.
+    pub fn matches_certificate(&self, cert_der: &[u8]) -> bool {
+        Self::from_certificate_der(cert_der).is_ok_and(|pin| *self == pin)
+    }
+}
+
+impl FromStr for TlsPinSha256 {
+    type Err = &'static str;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        // A hex digest is 64 characters, and base64 is 44 so there is no overlap.
+        let value = value.trim();
+        let bytes = if value.len() == 64 {
+            hex::decode(value).map_err(|_| "tls pin is not valid hex")?
+        } else {
+            STANDARD
+                .decode(value)
+                .map_err(|_| "tls pin is not valid hex or base64")?
+        };
+
+        <[u8; 32]>::try_from(bytes)
+            .map(TlsPinSha256)
+            .map_err(|_| "tls pin is not a sha256 digest")
+    }
+}
+
+impl fmt::Display for TlsPinSha256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", STANDARD.encode(self.0))
+    }
+}
+
+impl fmt::Debug for TlsPinSha256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TlsPinSha256({self})")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TlsPinSha256;
+    use crypto_glue::traits::{DecodePem, EncodeDer};
+    use crypto_glue::x509::Certificate;
+    use std::str::FromStr;
+
+    const PEM_DATA: &str = r#"-----BEGIN CERTIFICATE-----
+MIIB3zCCAYWgAwIBAgIUdJ6IWvI+8M6nwK7ykUK7/iBq7yQwCgYIKoZIzj0EAwIw
+RTELMAkGA1UEBhMCQVUxEzARBgNVBAgMClNvbWUtU3RhdGUxITAfBgNVBAoMGElu
+dGVybmV0IFdpZGdpdHMgUHR5IEx0ZDAeFw0yNDA4MjEwNjQ2MzBaFw0yNTA4MjEw
+NjQ2MzBaMEUxCzAJBgNVBAYTAkFVMRMwEQYDVQQIDApTb21lLVN0YXRlMSEwHwYD
+VQQKDBhJbnRlcm5ldCBXaWRnaXRzIFB0eSBMdGQwWTATBgcqhkjOPQIBBggqhkjO
+PQMBBwNCAAS2Szn4NPmgxawC1+MRC41jqobemNkXkRZ9AgozK0zRDFc6k1IHUZ++
+wN0USpXDQYDnJfATqvlpKPebnHxTytt6o1MwUTAdBgNVHQ4EFgQU1oR1x2CnoPap
+JMKPCVVzqWf2ANYwHwYDVR0jBBgwFoAU1oR1x2CnoPapJMKPCVVzqWf2ANYwDwYD
+VR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNIADBFAiBpy0o2CY97MIxeQ0HgG44Y
+raBy6edj7W0EIH+yQxkDEwIhAI0nVKaI6duHLAvtKW6CfEQFG6jKg7dyk37YYiRD
+2jS0
+-----END CERTIFICATE-----"#;
+
+    // openssl x509 -pubkey -noout | openssl pkey -pubin -outform der | openssl dgst -sha256
+    const PIN_HEX: &str = "5c204469c555c9d7815e86f23bd60e6776a7728c979f0b6b4f50c4979c5bb4eb";
+    const PIN_B64: &str = "XCBEacVVydeBXobyO9YOZ3ancoyXnwtrT1DEl5xbtOs=";
+
+    #[test]
+    fn test_tls_pin_parse() {
+        let from_hex = TlsPinSha256::from_str(PIN_HEX).expect("valid hex pin");
+        let from_b64 = TlsPinSha256::from_str(PIN_B64).expect("valid base64 pin");
+        assert_eq!(from_hex, from_b64);
+        assert_eq!(from_hex.to_string(), PIN_B64);
+
+        // Upper case hex is fine too.
+        assert!(TlsPinSha256::from_str(&PIN_HEX.to_uppercase()).is_ok());
+
+        assert!(TlsPinSha256::from_str("").is_err());
+        assert!(TlsPinSha256::from_str("not a pin").is_err());
+        // Valid encodings, but the wrong length for sha256.
+        assert!(TlsPinSha256::from_str(&PIN_HEX[..62]).is_err());
+        assert!(TlsPinSha256::from_str("AAAA").is_err());
+        assert!(TlsPinSha256::from_str(&"z".repeat(64)).is_err());
+    }
+
+    #[test]
+    fn test_tls_pin_matches() {
+        let cert = Certificate::from_pem(PEM_DATA).expect("valid certificate");
+        let cert_der = cert.to_der().expect("encodable certificate");
+        let spki_der = cert
+            .tbs_certificate
+            .subject_public_key_info
+            .to_der()
+            .expect("encodable public key");
+
+        let pin = TlsPinSha256::from_str(PIN_B64).expect("valid base64 pin");
+        assert!(pin.matches_certificate(&cert_der));
+        assert!(pin.matches_spki(&spki_der));
+        // The whole certificate is not the public key.
+        assert!(!pin.matches_spki(&cert_der));
+
+        let other = TlsPinSha256::from_spki_der(b"some other key");
+        assert!(!other.matches_certificate(&cert_der));
+        assert!(!other.matches_spki(&spki_der));
+
+        // Garbage never matches.
+        assert!(!pin.matches_certificate(b"not a certificate"));
+    }
+}
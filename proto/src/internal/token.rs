@@ -109,6 +109,39 @@ impl UserAuthToken {
     }
 }
 
+/// How a session was authenticated. This is the stable public form of the
+/// server's internal auth type, intended for session listings and audit exports.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthType {
+    Anonymous,
+    Password,
+    GeneratedPassword,
+    PasswordTotp,
+    PasswordBackupCode,
+    PasswordSecurityKey,
+    Passkey,
+    AttestedPasskey,
+    OAuth2Trust,
+}
+
+impl fmt::Display for AuthType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            AuthType::Anonymous => "Anonymous",
+            AuthType::Password => "Password",
+            AuthType::GeneratedPassword => "Generated Password",
+            AuthType::PasswordTotp => "Password + TOTP",
+            AuthType::PasswordBackupCode => "Password + Backup Code",
+            AuthType::PasswordSecurityKey => "Password + Security Key",
+            AuthType::Passkey => "Passkey",
+            AuthType::AttestedPasskey => "Attested Passkey",
+            AuthType::OAuth2Trust => "OAuth2 Trust",
+        };
+        f.write_str(label)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Default, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum ApiTokenPurpose {
@@ -165,6 +198,48 @@ impl PartialEq for ApiToken {
 
 impl Eq for ApiToken {}
 
+/// An api token as shown in a listing of all tokens across all accounts. This is
+/// used to audit which accounts hold api tokens, and what they may do.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ApiTokenSummary {
+    pub account_spn: String,
+    pub token_uuid: Uuid,
+    pub label: String,
+    #[serde(with = "time::serde::timestamp::option")]
+    pub expiry: Option<time::OffsetDateTime>,
+    pub scope: ApiTokenPurpose,
+}
+
+impl fmt::Display for ApiTokenSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "account: {}", self.account_spn)?;
+        writeln!(f, "token_id: {}", self.token_uuid)?;
+        writeln!(f, "label: {}", self.label)?;
+        writeln!(f, "scope: {:?}", self.scope)?;
+        if let Some(expiry) = self.expiry {
+            // if this fails we're in trouble!
+            #[allow(clippy::expect_used)]
+            let expiry_str = expiry
+                .to_offset(
+                    time::UtcOffset::local_offset_at(OffsetDateTime::UNIX_EPOCH)
+                        .unwrap_or(time::UtcOffset::UTC),
+                )
+                .format(&time::format_description::well_known::Rfc3339)
+                .expect("Failed to format timestamp to RFC3339");
+            writeln!(f, "token expiry: {expiry_str}")
+        } else {
+            writeln!(f, "token expiry: never")
+        }
+    }
+}
+
+/// Query parameters for listing all api tokens. If `account` is set, only the
+/// tokens held by that account are returned.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, ToSchema)]
+pub struct ApiTokenListQuery {
+    pub account: Option<String>,
+}
+
 // This is similar to uat, but omits claims (they have no role in radius), and adds
 // the radius secret field.
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
@@ -0,0 +1,178 @@
+//! SCIM Bulk messages, allowing many operations to be sent in a single request.
+//!
+//! <https://datatracker.ietf.org/doc/html/rfc7644#section-3.7>
+//!
+//! These are only the message types - how the operations are applied is up to
+//! the server.
+
+use super::JsonValue;
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use utoipa::ToSchema;
+
+pub const SCIM_SCHEMA_BULK_REQUEST: &str = "urn:ietf:params:scim:api:messages:2.0:BulkRequest";
+pub const SCIM_SCHEMA_BULK_RESPONSE: &str = "urn:ietf:params:scim:api:messages:2.0:BulkResponse";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum ScimBulkMethod {
+    Post,
+    Put,
+    Patch,
+    Delete,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimBulkRequest {
+    pub schemas: Vec<String>,
+    /// The number of errors the server should accept before it stops processing
+    /// the remaining operations.
+    pub fail_on_errors: Option<u32>,
+    #[serde(rename = "Operations")]
+    pub operations: Vec<ScimBulkOperation>,
+}
+
+impl ScimBulkRequest {
+    pub fn new(operations: Vec<ScimBulkOperation>) -> Self {
+        ScimBulkRequest {
+            schemas: vec![SCIM_SCHEMA_BULK_REQUEST.to_string()],
+            fail_on_errors: None,
+            operations,
+        }
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimBulkOperation {
+    pub method: ScimBulkMethod,
+    /// A client chosen identifier, required for POST so that other operations in
+    /// the same request can refer to the created resource as `bulkId:<id>`.
+    pub bulk_id: Option<String>,
+    pub version: Option<String>,
+    pub path: String,
+    pub data: Option<JsonValue>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimBulkResponse {
+    pub schemas: Vec<String>,
+    #[serde(rename = "Operations")]
+    pub operations: Vec<ScimBulkOperationResult>,
+}
+
+impl ScimBulkResponse {
+    pub fn new(operations: Vec<ScimBulkOperationResult>) -> Self {
+        ScimBulkResponse {
+            schemas: vec![SCIM_SCHEMA_BULK_RESPONSE.to_string()],
+            operations,
+        }
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimBulkOperationResult {
+    pub method: ScimBulkMethod,
+    pub bulk_id: Option<String>,
+    pub version: Option<String>,
+    /// The URI of the resource the operation acted on. Absent if the operation failed.
+    pub location: Option<String>,
+    /// The HTTP status code of the operation. The RFC transmits this as a string.
+    pub status: String,
+    pub response: Option<JsonValue>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scim_bulk_request_rfc_example() {
+        let request: ScimBulkRequest = serde_json::from_str(
+            r#"{
+              "schemas": ["urn:ietf:params:scim:api:messages:2.0:BulkRequest"],
+              "failOnErrors": 1,
+              "Operations": [
+                {
+                  "method": "POST",
+                  "path": "/Users",
+                  "bulkId": "qwerty",
+                  "data": {
+                    "schemas": ["urn:ietf:params:scim:schemas:core:2.0:User"],
+                    "userName": "Alice"
+                  }
+                },
+                {
+                  "method": "DELETE",
+                  "path": "/Users/b7c14771-226c-4d05-8860-134711653041",
+                  "version": "W/\"0ee8add0a938e1a\""
+                }
+              ]
+            }"#,
+        )
+        .expect("Failed to parse bulk request");
+
+        assert_eq!(request.schemas, vec![SCIM_SCHEMA_BULK_REQUEST.to_string()]);
+        assert_eq!(request.fail_on_errors, Some(1));
+        assert_eq!(request.operations.len(), 2);
+        assert_eq!(request.operations[0].method, ScimBulkMethod::Post);
+        assert_eq!(request.operations[0].bulk_id.as_deref(), Some("qwerty"));
+        assert_eq!(request.operations[1].method, ScimBulkMethod::Delete);
+        assert!(request.operations[1].data.is_none());
+
+        let json = serde_json::to_string(&request).expect("Failed to serialise bulk request");
+        assert!(json.contains(r#""Operations":"#));
+        assert!(json.contains(r#""failOnErrors":1"#));
+
+        let round_trip: ScimBulkRequest =
+            serde_json::from_str(&json).expect("Failed to parse bulk request");
+        assert_eq!(request, round_trip);
+    }
+
+    #[test]
+    fn scim_bulk_response_round_trip() {
+        let response = ScimBulkResponse::new(vec![
+            ScimBulkOperationResult {
+                method: ScimBulkMethod::Post,
+                bulk_id: Some("qwerty".to_string()),
+                version: None,
+                location: Some(
+                    "https://example.com/v2/Users/92b725cd-9465-4e7d-8c16-01f8e146b87a".to_string(),
+                ),
+                status: "201".to_string(),
+                response: None,
+            },
+            ScimBulkOperationResult {
+                method: ScimBulkMethod::Patch,
+                bulk_id: None,
+                version: None,
+                location: None,
+                status: "400".to_string(),
+                response: Some(serde_json::json!({
+                    "schemas": ["urn:ietf:params:scim:api:messages:2.0:Error"],
+                    "scimType": "invalidSyntax",
+                    "status": "400"
+                })),
+            },
+        ]);
+
+        let value = serde_json::to_value(&response).expect("Failed to serialise bulk response");
+        assert_eq!(
+            value["schemas"],
+            serde_json::json!([SCIM_SCHEMA_BULK_RESPONSE])
+        );
+        assert_eq!(value["Operations"][0]["bulkId"], "qwerty");
+        // Unset optional values aren't sent.
+        assert!(value["Operations"][0].get("response").is_none());
+
+        let round_trip: ScimBulkResponse =
+            serde_json::from_value(value).expect("Failed to parse bulk response");
+        assert_eq!(response, round_trip);
+    }
+}
@@ -19,13 +19,21 @@ use uuid::Uuid;
 
 pub type ScimSshPublicKeys = Vec<ScimSshPublicKey>;
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
 #[serde(deny_unknown_fields, rename_all = "camelCase")]
+#[schema(as = client::ScimSshPublicKey)]
 pub struct ScimSshPublicKey {
     pub label: String,
+    #[schema(value_type = String)]
     pub value: SshPublicKey,
 }
 
+impl std::fmt::Display for ScimSshPublicKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        super::fmt_ssh_public_key(f, &self.label, &self.value)
+    }
+}
+
 #[derive(Deserialize)]
 #[serde(untagged)]
 enum ScimReferenceAdapter {
@@ -61,12 +69,13 @@ impl From<ScimReferenceAdapter> for ScimReference {
 
 #[serde_as]
 #[skip_serializing_none]
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
 #[serde(
     deny_unknown_fields,
     rename_all = "camelCase",
     from = "ScimReferenceAdapter"
 )]
+#[schema(as = client::ScimReference)]
 pub struct ScimReference {
     pub uuid: Option<Uuid>,
     pub value: Option<String>,
@@ -95,15 +104,18 @@ pub struct ScimDateTime {
 }
 
 #[serde_as]
-#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
 #[serde(rename_all = "camelCase")]
+#[schema(as = client::ScimCertificate)]
 pub struct ScimCertificate {
     #[serde_as(as = "base64::Base64<base64::UrlSafe, formats::Unpadded>")]
+    #[schema(value_type = String)]
     pub der: Vec<u8>,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
 #[serde(rename_all = "camelCase")]
+#[schema(as = client::ScimAddress)]
 pub struct ScimAddress {
     pub street_address: String,
     pub locality: String,
@@ -113,19 +125,24 @@ pub struct ScimAddress {
 }
 
 #[serde_as]
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
 #[serde(rename_all = "camelCase")]
+#[schema(as = client::ScimOAuth2ClaimMap)]
 pub struct ScimOAuth2ClaimMap {
     pub group: Option<String>,
     pub group_uuid: Option<Uuid>,
     pub claim: String,
     pub join_char: ScimOauth2ClaimMapJoinChar,
     pub values: BTreeSet<String>,
+    /// Claim values that are not strings, such as numbers, booleans or objects.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub typed_values: Vec<JsonValue>,
 }
 
 #[serde_as]
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
 #[serde(rename_all = "camelCase")]
+#[schema(as = client::ScimOAuth2ScopeMap)]
 pub struct ScimOAuth2ScopeMap {
     pub group: Option<String>,
     pub group_uuid: Option<Uuid>,
@@ -133,18 +150,20 @@ pub struct ScimOAuth2ScopeMap {
 }
 
 #[serde_as]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ScimListEntry {
     pub schemas: Vec<String>,
     pub total_results: u64,
+    #[schema(value_type = Option<u64>)]
     pub items_per_page: Option<NonZeroU64>,
+    #[schema(value_type = Option<u64>)]
     pub start_index: Option<NonZeroU64>,
     pub resources: Vec<ScimEntryGeneric>,
 }
 
 #[serde_as]
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct ScimEntryApplicationPost {
     pub name: String,
@@ -153,7 +172,7 @@ pub struct ScimEntryApplicationPost {
 }
 
 #[serde_as]
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct ScimEntryApplication {
     #[serde(flatten)]
@@ -169,12 +188,14 @@ pub struct ScimEntryApplication {
 }
 
 #[serde_as]
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Clone, Debug, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ScimListApplication {
     pub schemas: Vec<String>,
     pub total_results: u64,
+    #[schema(value_type = Option<u64>)]
     pub items_per_page: Option<NonZeroU64>,
+    #[schema(value_type = Option<u64>)]
     pub start_index: Option<NonZeroU64>,
     pub resources: Vec<ScimEntryApplication>,
 }
@@ -208,7 +229,7 @@ pub struct ScimListMessage {
 }
 
 #[serde_as]
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct ScimEntrySchemaClass {
     #[serde(flatten)]
@@ -222,18 +243,20 @@ pub struct ScimEntrySchemaClass {
 }
 
 #[serde_as]
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Clone, Debug, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ScimListSchemaClass {
     pub schemas: Vec<String>,
     pub total_results: u64,
+    #[schema(value_type = Option<u64>)]
     pub items_per_page: Option<NonZeroU64>,
+    #[schema(value_type = Option<u64>)]
     pub start_index: Option<NonZeroU64>,
     pub resources: Vec<ScimEntrySchemaClass>,
 }
 
 #[serde_as]
-#[derive(Deserialize, Debug, Clone, Serialize)]
+#[derive(Deserialize, Debug, Clone, Serialize, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct ScimEntrySchemaAttribute {
     #[serde(flatten)]
@@ -251,19 +274,22 @@ pub struct ScimEntrySchemaAttribute {
 }
 
 #[serde_as]
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Clone, Debug, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ScimListSchemaAttribute {
     pub schemas: Vec<String>,
     pub total_results: u64,
+    #[schema(value_type = Option<u64>)]
     pub items_per_page: Option<NonZeroU64>,
+    #[schema(value_type = Option<u64>)]
     pub start_index: Option<NonZeroU64>,
     pub resources: Vec<ScimEntrySchemaAttribute>,
 }
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, ToSchema)]
 pub struct ScimEntryPutKanidm {
     pub id: Uuid,
+    #[schema(value_type = BTreeMap<String, Value>)]
     #[serde(flatten)]
     pub attrs: BTreeMap<Attribute, Option<super::server::ScimValueKanidm>>,
 }
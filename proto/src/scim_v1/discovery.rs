@@ -0,0 +1,572 @@
+//! SCIM service discovery, allowing a client to determine the resources and schemas
+//! that a server supports.
+//!
+//! <https://datatracker.ietf.org/doc/html/rfc7643#section-6>
+//! <https://datatracker.ietf.org/doc/html/rfc7643#section-7>
+//!
+//! These are only the message types - the endpoints that serve them are up to
+//! the server.
+
+use crate::attribute::Attribute;
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use utoipa::ToSchema;
+
+pub const SCIM_SCHEMA_RESOURCE_TYPE: &str = "urn:ietf:params:scim:schemas:core:2.0:ResourceType";
+pub const SCIM_SCHEMA_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:Schema";
+pub const SCIM_SCHEMA_KANIDM_PERSON: &str = "urn:ietf:params:scim:schemas:kanidm:1.0:person";
+pub const SCIM_SCHEMA_KANIDM_GROUP: &str = "urn:ietf:params:scim:schemas:kanidm:1.0:group";
+
+/// The `meta` of a discovery resource. Unlike an entry these have no creation or
+/// modification time.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimDiscoveryMeta {
+    pub resource_type: String,
+    pub location: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimSchemaExtension {
+    /// The URN of the extension schema.
+    pub schema: String,
+    /// If resources must include this extension.
+    pub required: bool,
+}
+
+/// A resource type that the server offers, as returned from `/ResourceTypes`.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimResourceType {
+    pub schemas: Vec<String>,
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    /// The endpoint relative to the SCIM base url, such as `/Users`.
+    pub endpoint: String,
+    /// The URN of the primary schema of this resource.
+    pub schema: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schema_extensions: Vec<ScimSchemaExtension>,
+    pub meta: Option<ScimDiscoveryMeta>,
+}
+
+impl ScimResourceType {
+    pub fn new(name: &str, endpoint: &str, schema: &str) -> Self {
+        ScimResourceType {
+            schemas: vec![SCIM_SCHEMA_RESOURCE_TYPE.to_string()],
+            id: name.to_string(),
+            name: name.to_string(),
+            description: None,
+            endpoint: endpoint.to_string(),
+            schema: schema.to_string(),
+            schema_extensions: Vec::with_capacity(0),
+            meta: Some(ScimDiscoveryMeta {
+                resource_type: "ResourceType".to_string(),
+                location: None,
+            }),
+        }
+    }
+
+    pub fn kanidm_person() -> Self {
+        let mut resource_type = Self::new("Person", "/Person", SCIM_SCHEMA_KANIDM_PERSON);
+        resource_type.description = Some("Kanidm Person Account".to_string());
+        resource_type
+    }
+
+    pub fn kanidm_group() -> Self {
+        let mut resource_type = Self::new("Group", "/Group", SCIM_SCHEMA_KANIDM_GROUP);
+        resource_type.description = Some("Kanidm Group".to_string());
+        resource_type
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ScimSchemaAttributeType {
+    String,
+    Boolean,
+    Decimal,
+    Integer,
+    DateTime,
+    Binary,
+    Reference,
+    Complex,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ScimSchemaMutability {
+    ReadOnly,
+    #[default]
+    ReadWrite,
+    Immutable,
+    WriteOnly,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ScimSchemaReturned {
+    Always,
+    Never,
+    #[default]
+    Default,
+    Request,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ScimSchemaUniqueness {
+    #[default]
+    None,
+    Server,
+    Global,
+}
+
+/// How an attribute behaves when it is exposed over SCIM - whether clients may
+/// write it, when it is returned, and how unique its values are.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScimAttributeProfile {
+    mutability: ScimSchemaMutability,
+    returned: ScimSchemaReturned,
+    uniqueness: ScimSchemaUniqueness,
+}
+
+impl ScimAttributeProfile {
+    const fn new(
+        mutability: ScimSchemaMutability,
+        returned: ScimSchemaReturned,
+        uniqueness: ScimSchemaUniqueness,
+    ) -> Self {
+        ScimAttributeProfile {
+            mutability,
+            returned,
+            uniqueness,
+        }
+    }
+
+    /// The profile of a kanidm attribute. Attributes not listed here are plain
+    /// read-write values that are returned by default.
+    pub fn for_attribute(attr: &Attribute) -> Self {
+        use ScimSchemaMutability as M;
+        use ScimSchemaReturned as R;
+        use ScimSchemaUniqueness as U;
+
+        match attr {
+            // Secrets may be set, but must never be sent back to a client.
+            Attribute::PasswordImport
+            | Attribute::UnixPassword
+            | Attribute::UnixPasswordImport
+            | Attribute::UserPassword
+            | Attribute::TotpImport => Self::new(M::WriteOnly, R::Never, U::None),
+            // Credentials are only changed through a credential update session.
+            Attribute::PrimaryCredential | Attribute::PassKeys | Attribute::AttestedPasskeys => {
+                Self::new(M::ReadOnly, R::Never, U::None)
+            }
+            Attribute::Uuid => Self::new(M::Immutable, R::Always, U::Global),
+            Attribute::Name | Attribute::Mail | Attribute::GidNumber => {
+                Self::new(M::ReadWrite, R::Default, U::Server)
+            }
+            Attribute::Spn => Self::new(M::ReadOnly, R::Default, U::Server),
+            Attribute::MemberOf
+            | Attribute::DirectMemberOf
+            | Attribute::CreatedAtCid
+            | Attribute::LastModifiedCid => Self::new(M::ReadOnly, R::Default, U::None),
+            _ => Self::default(),
+        }
+    }
+
+    pub fn mutability(&self) -> ScimSchemaMutability {
+        self.mutability
+    }
+
+    pub fn returned(&self) -> ScimSchemaReturned {
+        self.returned
+    }
+
+    pub fn uniqueness(&self) -> ScimSchemaUniqueness {
+        self.uniqueness
+    }
+
+    /// If a client is able to supply values for this attribute.
+    pub fn is_writable(&self) -> bool {
+        matches!(
+            self.mutability,
+            ScimSchemaMutability::ReadWrite
+                | ScimSchemaMutability::Immutable
+                | ScimSchemaMutability::WriteOnly
+        )
+    }
+
+    /// If values of this attribute may be serialised back to a client. When
+    /// `requested` is true the client explicitly asked for this attribute.
+    pub fn is_returned(&self, requested: bool) -> bool {
+        match self.returned {
+            ScimSchemaReturned::Always | ScimSchemaReturned::Default => true,
+            ScimSchemaReturned::Request => requested,
+            ScimSchemaReturned::Never => false,
+        }
+    }
+}
+
+/// The definition of a single attribute within a schema.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimSchemaAttribute {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_: ScimSchemaAttributeType,
+    pub multi_valued: bool,
+    pub description: Option<String>,
+    pub required: bool,
+    pub case_exact: bool,
+    pub mutability: ScimSchemaMutability,
+    pub returned: ScimSchemaReturned,
+    pub uniqueness: ScimSchemaUniqueness,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schema(no_recursion)]
+    pub sub_attributes: Vec<ScimSchemaAttribute>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub canonical_values: Vec<String>,
+    /// The resource types that a reference attribute may refer to.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reference_types: Vec<String>,
+}
+
+impl ScimSchemaAttribute {
+    pub fn new(name: &str, type_: ScimSchemaAttributeType) -> Self {
+        ScimSchemaAttribute {
+            name: name.to_string(),
+            type_,
+            multi_valued: false,
+            description: None,
+            required: false,
+            case_exact: false,
+            mutability: ScimSchemaMutability::default(),
+            returned: ScimSchemaReturned::default(),
+            uniqueness: ScimSchemaUniqueness::default(),
+            sub_attributes: Vec::with_capacity(0),
+            canonical_values: Vec::with_capacity(0),
+            reference_types: Vec::with_capacity(0),
+        }
+    }
+
+    /// Define a kanidm attribute, taking its mutability, returned and uniqueness
+    /// from its [`ScimAttributeProfile`].
+    pub fn from_attribute(attr: &Attribute, type_: ScimSchemaAttributeType) -> Self {
+        let profile = ScimAttributeProfile::for_attribute(attr);
+        ScimSchemaAttribute {
+            mutability: profile.mutability(),
+            returned: profile.returned(),
+            uniqueness: profile.uniqueness(),
+            ..Self::new(attr.as_str(), type_)
+        }
+    }
+}
+
+/// A schema that the server offers, as returned from `/Schemas`.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimSchemaDefinition {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schemas: Vec<String>,
+    /// The URN of this schema.
+    pub id: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub attributes: Vec<ScimSchemaAttribute>,
+    pub meta: Option<ScimDiscoveryMeta>,
+}
+
+impl ScimSchemaDefinition {
+    pub fn new(id: &str, name: &str, attributes: Vec<ScimSchemaAttribute>) -> Self {
+        ScimSchemaDefinition {
+            schemas: vec![SCIM_SCHEMA_SCHEMA.to_string()],
+            id: id.to_string(),
+            name: Some(name.to_string()),
+            description: None,
+            attributes,
+            meta: Some(ScimDiscoveryMeta {
+                resource_type: "Schema".to_string(),
+                location: None,
+            }),
+        }
+    }
+
+    pub fn kanidm_person() -> Self {
+        let name = ScimSchemaAttribute {
+            required: true,
+            ..ScimSchemaAttribute::from_attribute(&Attribute::Name, ScimSchemaAttributeType::String)
+        };
+
+        let displayname = ScimSchemaAttribute {
+            required: true,
+            ..ScimSchemaAttribute::from_attribute(
+                &Attribute::DisplayName,
+                ScimSchemaAttributeType::String,
+            )
+        };
+
+        let mail = ScimSchemaAttribute {
+            multi_valued: true,
+            sub_attributes: vec![
+                ScimSchemaAttribute::new("value", ScimSchemaAttributeType::String),
+                ScimSchemaAttribute::new("primary", ScimSchemaAttributeType::Boolean),
+            ],
+            ..ScimSchemaAttribute::from_attribute(
+                &Attribute::Mail,
+                ScimSchemaAttributeType::Complex,
+            )
+        };
+
+        let mut schema = Self::new(
+            SCIM_SCHEMA_KANIDM_PERSON,
+            "Person",
+            vec![name, displayname, mail],
+        );
+        schema.description = Some("Kanidm Person Account".to_string());
+        schema
+    }
+
+    pub fn kanidm_group() -> Self {
+        let name = ScimSchemaAttribute {
+            required: true,
+            ..ScimSchemaAttribute::from_attribute(&Attribute::Name, ScimSchemaAttributeType::String)
+        };
+
+        let description = ScimSchemaAttribute::from_attribute(
+            &Attribute::Description,
+            ScimSchemaAttributeType::String,
+        );
+
+        let member = ScimSchemaAttribute {
+            multi_valued: true,
+            reference_types: vec!["Person".to_string(), "Group".to_string()],
+            ..ScimSchemaAttribute::from_attribute(
+                &Attribute::Member,
+                ScimSchemaAttributeType::Reference,
+            )
+        };
+
+        let mut schema = Self::new(
+            SCIM_SCHEMA_KANIDM_GROUP,
+            "Group",
+            vec![name, description, member],
+        );
+        schema.description = Some("Kanidm Group".to_string());
+        schema
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scim_resource_type_rfc_example() {
+        let resource_type: ScimResourceType = serde_json::from_str(
+            r#"{
+              "schemas": ["urn:ietf:params:scim:schemas:core:2.0:ResourceType"],
+              "id": "User",
+              "name": "User",
+              "endpoint": "/Users",
+              "description": "User Account",
+              "schema": "urn:ietf:params:scim:schemas:core:2.0:User",
+              "schemaExtensions": [
+                {
+                  "schema": "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User",
+                  "required": true
+                }
+              ],
+              "meta": {
+                "location": "https://example.com/v2/ResourceTypes/User",
+                "resourceType": "ResourceType"
+              }
+            }"#,
+        )
+        .expect("Failed to parse resource type");
+
+        assert_eq!(resource_type.endpoint, "/Users");
+        assert_eq!(resource_type.schema_extensions.len(), 1);
+        assert!(resource_type.schema_extensions[0].required);
+
+        let json = serde_json::to_string(&resource_type).expect("Failed to serialise");
+        assert!(json.contains(r#""schemaExtensions":"#));
+        assert!(json.contains(r#""resourceType":"ResourceType""#));
+
+        let round_trip: ScimResourceType =
+            serde_json::from_str(&json).expect("Failed to parse resource type");
+        assert_eq!(resource_type, round_trip);
+    }
+
+    #[test]
+    fn scim_resource_type_kanidm_round_trip() {
+        for resource_type in [
+            ScimResourceType::kanidm_person(),
+            ScimResourceType::kanidm_group(),
+        ] {
+            let value = serde_json::to_value(&resource_type).expect("Failed to serialise");
+            assert_eq!(
+                value["schemas"],
+                serde_json::json!([SCIM_SCHEMA_RESOURCE_TYPE])
+            );
+            // No extensions are sent if there are none.
+            assert!(value.get("schemaExtensions").is_none());
+
+            let round_trip: ScimResourceType =
+                serde_json::from_value(value).expect("Failed to parse resource type");
+            assert_eq!(resource_type, round_trip);
+        }
+    }
+
+    #[test]
+    fn scim_schema_rfc_example() {
+        let schema: ScimSchemaDefinition = serde_json::from_str(
+            r#"{
+              "id": "urn:ietf:params:scim:schemas:core:2.0:Group",
+              "name": "Group",
+              "description": "Group",
+              "attributes": [
+                {
+                  "name": "displayName",
+                  "type": "string",
+                  "multiValued": false,
+                  "description": "A human-readable name for the Group. REQUIRED.",
+                  "required": false,
+                  "caseExact": false,
+                  "mutability": "readWrite",
+                  "returned": "default",
+                  "uniqueness": "none"
+                },
+                {
+                  "name": "members",
+                  "type": "complex",
+                  "multiValued": true,
+                  "description": "A list of members of the Group.",
+                  "required": false,
+                  "subAttributes": [
+                    {
+                      "name": "value",
+                      "type": "string",
+                      "multiValued": false,
+                      "description": "Identifier of the member of this Group.",
+                      "required": false,
+                      "caseExact": false,
+                      "mutability": "immutable",
+                      "returned": "default",
+                      "uniqueness": "none"
+                    },
+                    {
+                      "name": "$ref",
+                      "type": "reference",
+                      "referenceTypes": ["User", "Group"],
+                      "multiValued": false,
+                      "description": "The URI corresponding to a SCIM resource that is a member of this Group.",
+                      "required": false,
+                      "caseExact": false,
+                      "mutability": "immutable",
+                      "returned": "default",
+                      "uniqueness": "none"
+                    }
+                  ],
+                  "caseExact": false,
+                  "mutability": "readWrite",
+                  "returned": "default",
+                  "uniqueness": "none"
+                }
+              ],
+              "meta": {
+                "resourceType": "Schema",
+                "location": "/v2/Schemas/urn:ietf:params:scim:schemas:core:2.0:Group"
+              }
+            }"#,
+        )
+        .expect("Failed to parse schema");
+
+        assert!(schema.schemas.is_empty());
+        assert_eq!(schema.attributes.len(), 2);
+        assert_eq!(schema.attributes[1].type_, ScimSchemaAttributeType::Complex);
+        assert_eq!(
+            schema.attributes[1].sub_attributes[0].mutability,
+            ScimSchemaMutability::Immutable
+        );
+        assert_eq!(
+            schema.attributes[1].sub_attributes[1].reference_types,
+            vec!["User".to_string(), "Group".to_string()]
+        );
+
+        let json = serde_json::to_string(&schema).expect("Failed to serialise");
+        assert!(json.contains(r#""multiValued":true"#));
+        assert!(json.contains(r#""type":"reference""#));
+
+        let round_trip: ScimSchemaDefinition =
+            serde_json::from_str(&json).expect("Failed to parse schema");
+        assert_eq!(schema, round_trip);
+    }
+
+    #[test]
+    fn scim_attribute_profile_registry() {
+        for attr in [Attribute::UserPassword, Attribute::PasswordImport] {
+            let profile = ScimAttributeProfile::for_attribute(&attr);
+            assert_eq!(profile.mutability(), ScimSchemaMutability::WriteOnly);
+            assert_eq!(profile.returned(), ScimSchemaReturned::Never);
+            assert!(profile.is_writable());
+            assert!(!profile.is_returned(false));
+            // Even when asked for, a password is never given back.
+            assert!(!profile.is_returned(true));
+        }
+
+        let profile = ScimAttributeProfile::for_attribute(&Attribute::Name);
+        assert_eq!(profile.mutability(), ScimSchemaMutability::ReadWrite);
+        assert_eq!(profile.returned(), ScimSchemaReturned::Default);
+        assert_eq!(profile.uniqueness(), ScimSchemaUniqueness::Server);
+        assert!(profile.is_writable());
+        assert!(profile.is_returned(false));
+
+        let profile = ScimAttributeProfile::for_attribute(&Attribute::MemberOf);
+        assert!(!profile.is_writable());
+
+        // Attributes without a specific profile are plain read-write values.
+        let profile = ScimAttributeProfile::for_attribute(&Attribute::Description);
+        assert_eq!(profile, ScimAttributeProfile::default());
+
+        let password = ScimSchemaAttribute::from_attribute(
+            &Attribute::UserPassword,
+            ScimSchemaAttributeType::String,
+        );
+        let value = serde_json::to_value(&password).expect("Failed to serialise");
+        assert_eq!(value["mutability"], "writeOnly");
+        assert_eq!(value["returned"], "never");
+    }
+
+    #[test]
+    fn scim_schema_kanidm_round_trip() {
+        let person = ScimSchemaDefinition::kanidm_person();
+        let value = serde_json::to_value(&person).expect("Failed to serialise");
+        assert_eq!(value["id"], SCIM_SCHEMA_KANIDM_PERSON);
+        assert_eq!(value["schemas"], serde_json::json!([SCIM_SCHEMA_SCHEMA]));
+        assert_eq!(value["attributes"][0]["uniqueness"], "server");
+        assert_eq!(value["attributes"][2]["type"], "complex");
+
+        let round_trip: ScimSchemaDefinition =
+            serde_json::from_value(value).expect("Failed to parse schema");
+        assert_eq!(person, round_trip);
+
+        let group = ScimSchemaDefinition::kanidm_group();
+        let value = serde_json::to_value(&group).expect("Failed to serialise");
+        assert_eq!(value["id"], SCIM_SCHEMA_KANIDM_GROUP);
+        assert_eq!(
+            value["attributes"][2]["referenceTypes"],
+            serde_json::json!(["Person", "Group"])
+        );
+
+        let round_trip: ScimSchemaDefinition =
+            serde_json::from_value(value).expect("Failed to parse schema");
+        assert_eq!(group, round_trip);
+    }
+}
@@ -21,34 +21,162 @@ use serde::{Deserialize, Serialize};
 use serde_with::formats::CommaSeparator;
 use serde_with::{serde_as, skip_serializing_none, DisplayFromStr, StringWithSeparator};
 use sshkey_attest::proto::PublicKey as SshPublicKey;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::num::NonZeroU64;
 use std::ops::Not;
 use std::str::FromStr;
-use utoipa::ToSchema;
+use utoipa::openapi::OpenApi as OpenApiDoc;
+use utoipa::{OpenApi, ToSchema};
 use uuid::Uuid;
 
+pub use self::bulk::*;
+pub use self::discovery::*;
 pub use self::synch::*;
 pub use scim_proto::prelude::*;
 pub use serde_json::Value as JsonValue;
 
+mod bulk;
 pub mod client;
+mod discovery;
 pub mod server;
 mod synch;
 
 /// A generic ScimEntry. This retains attribute
 /// values in a generic state awaiting processing by schema aware transforms
 /// either by the server or the client.
+///
+/// Attributes are always stored in `attrs` in their canonical order. If the entry was
+/// deserialised with [ScimEntryGeneric::deserialize_with_order] and order tracking was
+/// requested, the order the attributes were submitted in is also retained and can be
+/// accessed with [ScimEntryGeneric::iter_ordered].
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct ScimEntryGeneric {
     #[serde(flatten)]
     pub header: ScimEntryHeader,
     #[serde(flatten)]
     pub attrs: BTreeMap<Attribute, JsonValue>,
+    #[serde(skip)]
+    #[schema(ignore)]
+    attr_order: Option<Vec<Attribute>>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, Default, ToSchema)]
+impl PartialEq for ScimEntryGeneric {
+    fn eq(&self, other: &Self) -> bool {
+        // Submission order is not significant to the content of the entry.
+        self.header == other.header && self.attrs == other.attrs
+    }
+}
+
+impl Eq for ScimEntryGeneric {}
+
+impl ScimEntryGeneric {
+    pub fn new(header: ScimEntryHeader, attrs: BTreeMap<Attribute, JsonValue>) -> Self {
+        ScimEntryGeneric {
+            header,
+            attrs,
+            attr_order: None,
+        }
+    }
+
+    /// Deserialise an entry, optionally retaining the order that attributes were
+    /// submitted in. When `track_order` is false this is identical to the standard
+    /// deserialisation.
+    pub fn deserialize_with_order<'de, D>(
+        deserializer: D,
+        track_order: bool,
+    ) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        if !track_order {
+            return Self::deserialize(deserializer);
+        }
+
+        let fields = deserializer.deserialize_map(OrderedFieldsVisitor)?;
+
+        // Header fields are flattened into the same object, so they must be skipped.
+        const HEADER_FIELDS: [&str; 4] = ["schemas", "id", "externalId", "meta"];
+
+        let mut attr_order: Vec<Attribute> = Vec::with_capacity(fields.len());
+        for attr in fields
+            .iter()
+            .map(|(key, _)| key.as_str())
+            .filter(|key| !HEADER_FIELDS.contains(key))
+            .map(Attribute::from)
+        {
+            if !attr_order.contains(&attr) {
+                attr_order.push(attr);
+            }
+        }
+
+        let object: serde_json::Map<String, JsonValue> = fields.into_iter().collect();
+        let mut entry =
+            Self::deserialize(JsonValue::Object(object)).map_err(serde::de::Error::custom)?;
+
+        attr_order.retain(|attr| entry.attrs.contains_key(attr));
+        entry.attr_order = Some(attr_order);
+
+        Ok(entry)
+    }
+
+    /// Iterate over the attributes of this entry in the order they were submitted. If
+    /// the order was not tracked, or attributes were added after deserialisation, these
+    /// are returned in canonical order.
+    pub fn iter_ordered(&self) -> impl Iterator<Item = (&Attribute, &JsonValue)> {
+        let attr_order = self.attr_order.as_deref().unwrap_or_default();
+
+        attr_order
+            .iter()
+            .filter_map(|attr| self.attrs.get_key_value(attr))
+            .chain(
+                self.attrs
+                    .iter()
+                    .filter(move |(attr, _)| !attr_order.contains(attr)),
+            )
+    }
+
+    /// Convert this generic entry into a strongly typed SCIM entry.
+    pub fn into_typed<T>(self) -> Result<T, serde_json::Error>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        serde_json::to_value(self).and_then(serde_json::from_value)
+    }
+
+    /// Extract and deserialise a single attribute from this entry, returning `None`
+    /// if the attribute is not present.
+    pub fn get_attr<T>(&self, attr: &Attribute) -> Option<Result<T, serde_json::Error>>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        self.attrs.get(attr).map(|value| T::deserialize(value))
+    }
+}
+
+struct OrderedFieldsVisitor;
+
+impl<'de> serde::de::Visitor<'de> for OrderedFieldsVisitor {
+    type Value = Vec<(String, JsonValue)>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a scim entry")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        let mut fields = Vec::with_capacity(map.size_hint().unwrap_or_default());
+        while let Some(field) = map.next_entry::<String, JsonValue>()? {
+            fields.push(field);
+        }
+        Ok(fields)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum ScimSortOrder {
     #[default]
@@ -57,6 +185,10 @@ pub enum ScimSortOrder {
 }
 
 /// SCIM Query Parameters used during the get of a single entry
+///
+/// Equality and hashing treat `attributes` as a set, so queries that request the same
+/// attributes in a different order, or with duplicates, are considered the same query.
+/// `if_none_match` is a request header rather than part of the query, so it is ignored.
 #[serde_as]
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Clone, Debug, Default, ToSchema)]
@@ -74,6 +206,8 @@ pub struct ScimEntryGetQuery {
     pub sort_order: Option<ScimSortOrder>,
 
     // Pagination https://www.rfc-editor.org/rfc/rfc7644#section-3.4.2.4
+    /// The 1-based index of the first result to return. Use
+    /// [ScimEntryGetQuery::zero_based_offset] to convert this to an offset.
     #[schema(value_type = u64)]
     pub start_index: Option<NonZeroU64>,
     #[schema(value_type = u64)]
@@ -83,6 +217,83 @@ pub struct ScimEntryGetQuery {
     #[serde_as(as = "Option<DisplayFromStr>")]
     #[schema(value_type = JsonValue)]
     pub filter: Option<ScimFilter>,
+
+    /// The value of the `If-None-Match` header for this request. This is bound to the
+    /// request header, not the query string, so it is never serialised with the query.
+    /// See [ScimEntryGetQuery::matches_version].
+    #[serde(skip)]
+    #[schema(ignore)]
+    pub if_none_match: Option<String>,
+}
+
+/// Strip the weak indicator and quotes from an entity tag, leaving the opaque tag.
+fn etag_opaque(etag: &str) -> &str {
+    let etag = etag.trim();
+    let etag = etag.strip_prefix("W/").unwrap_or(etag);
+    etag.strip_prefix('"')
+        .and_then(|etag| etag.strip_suffix('"'))
+        .unwrap_or(etag)
+}
+
+impl ScimEntryGetQuery {
+    /// Returns true if `current`, the version of the entry, matches the `If-None-Match`
+    /// condition of this request, meaning that the client already holds this version.
+    /// This uses the weak comparison of RFC 9110, so the `W/` prefix is ignored on
+    /// either side. `current` may be given with or without quotes.
+    pub fn matches_version(&self, current: &str) -> bool {
+        let Some(if_none_match) = self.if_none_match.as_deref() else {
+            return false;
+        };
+
+        if if_none_match.trim() == "*" {
+            return true;
+        }
+
+        let current = etag_opaque(current);
+        if_none_match
+            .split(',')
+            .any(|etag| etag_opaque(etag) == current)
+    }
+
+    /// SCIM `startIndex` is 1-based, so the first result is at index 1. This returns the
+    /// equivalent 0-based offset into the result set, which is 0 when no index is set.
+    pub fn zero_based_offset(&self) -> u64 {
+        self.start_index
+            .map(|start_index| start_index.get() - 1)
+            .unwrap_or_default()
+    }
+
+    fn canonical_attributes(&self) -> Option<BTreeSet<&Attribute>> {
+        self.attributes.as_ref().map(|attrs| attrs.iter().collect())
+    }
+}
+
+impl PartialEq for ScimEntryGetQuery {
+    fn eq(&self, other: &Self) -> bool {
+        self.canonical_attributes() == other.canonical_attributes()
+            && self.ext_access_check == other.ext_access_check
+            && self.sort_by == other.sort_by
+            && self.sort_order == other.sort_order
+            && self.start_index == other.start_index
+            && self.count == other.count
+            && self.filter == other.filter
+    }
+}
+
+impl Eq for ScimEntryGetQuery {}
+
+impl Hash for ScimEntryGetQuery {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.canonical_attributes().hash(state);
+        self.ext_access_check.hash(state);
+        self.sort_by.hash(state);
+        self.sort_order.hash(state);
+        self.start_index.hash(state);
+        self.count.hash(state);
+        // Filters contain json values which can't be hashed, so use the same string
+        // form that the filter is sent in.
+        self.filter.as_ref().map(ToString::to_string).hash(state);
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
@@ -108,6 +319,16 @@ pub struct ScimMail {
     pub value: String,
 }
 
+impl fmt::Display for ScimMail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.primary {
+            write!(f, "{} (primary)", self.value)
+        } else {
+            write!(f, "{}", self.value)
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ScimSshPublicKey {
@@ -117,7 +338,37 @@ pub struct ScimSshPublicKey {
     pub value: SshPublicKey,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+impl ScimSshPublicKey {
+    /// The SHA256 fingerprint of this key, in the `SHA256:<base64>` form that
+    /// `ssh-keygen -l` displays.
+    pub fn fingerprint_sha256(&self) -> String {
+        self.value.fingerprint().to_string()
+    }
+}
+
+impl fmt::Display for ScimSshPublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_ssh_public_key(f, &self.label, &self.value)
+    }
+}
+
+/// Display a labelled ssh key, with only the start of the key data so that it
+/// remains readable in logs.
+fn fmt_ssh_public_key(
+    f: &mut fmt::Formatter<'_>,
+    label: &str,
+    value: &SshPublicKey,
+) -> fmt::Result {
+    let openssh = value.to_string();
+    let mut parts = openssh.split_whitespace();
+    let key_type = parts.next().unwrap_or_default();
+    let data = parts.next().unwrap_or_default();
+    let data = data.get(..16).unwrap_or(data);
+    write!(f, "{label}: {key_type} {data}...")
+}
+
+/// References are ordered by uuid, then by value.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ScimReference {
     pub uuid: Uuid,
@@ -471,10 +722,293 @@ impl FromStr for ScimComplexFilter {
     }
 }
 
+/// The schemas of the v1 SCIM request and response types.
+#[derive(OpenApi)]
+#[openapi(components(schemas(
+    ScimEntryGeneric,
+    ScimSortOrder,
+    ScimEntryGetQuery,
+    ScimSchema,
+    ScimMail,
+    ScimSshPublicKey,
+    ScimReference,
+    ScimOauth2ClaimMapJoinChar,
+    ScimApplicationPassword,
+    ScimApplicationPasswordCreate,
+    AttrPath,
+    ScimSyncState,
+    ScimSyncRetentionMode,
+    ScimSyncRequest,
+    TotpAlgorithm,
+    ScimBulkMethod,
+    ScimBulkRequest,
+    ScimBulkOperation,
+    ScimBulkResponse,
+    ScimBulkOperationResult,
+    ScimDiscoveryMeta,
+    ScimSchemaExtension,
+    ScimResourceType,
+    ScimSchemaAttributeType,
+    ScimSchemaMutability,
+    ScimSchemaReturned,
+    ScimSchemaUniqueness,
+    ScimSchemaAttribute,
+    ScimSchemaDefinition,
+    client::ScimSshPublicKey,
+    client::ScimReference,
+    client::ScimCertificate,
+    client::ScimAddress,
+    client::ScimOAuth2ClaimMap,
+    client::ScimOAuth2ScopeMap,
+    client::ScimListEntry,
+    client::ScimEntryApplicationPost,
+    client::ScimEntryApplication,
+    client::ScimListApplication,
+    client::ScimEntrySchemaClass,
+    client::ScimListSchemaClass,
+    client::ScimEntrySchemaAttribute,
+    client::ScimListSchemaAttribute,
+    client::ScimEntryPutKanidm,
+    client::ScimEntryPostGeneric,
+    client::ScimEntryAssertion,
+    client::ScimAssertGeneric,
+    client::ScimEntryPutGeneric,
+    server::ScimEntryKanidm,
+    server::ScimListResponse,
+    server::ScimAttributeEffectiveAccess,
+    server::ScimEffectiveAccess,
+    server::ScimAddress,
+    server::ScimApplicationPasswordReference,
+    server::ScimBinary,
+    server::ScimCertificate,
+    server::ScimAuditString,
+    server::ScimIntentTokenState,
+    server::ScimIntentToken,
+    server::ScimKeyInternal,
+    server::ScimAuthSession,
+    server::ScimOAuth2Session,
+    server::ScimApiToken,
+    server::ScimOAuth2ScopeMap,
+    server::ScimOAuth2ClaimMap,
+    server::ScimReference,
+    server::ScimValueKanidm,
+    server::ScimPerson,
+    server::ScimGroup,
+)))]
+struct ScimV1Schemas;
+
+/// Register the schemas of all the v1 SCIM types into the components section
+/// of `openapi`, so that they are available for client generation.
+pub fn register_openapi_components(openapi: &mut OpenApiDoc) {
+    openapi.merge(ScimV1Schemas::openapi());
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn scim_entry_generic_ordered() {
+        let data = r#"{
+            "schemas": ["urn:ietf:params:scim:schemas:kanidm:1.0:person"],
+            "id": "cb3de098-33fd-4565-9d80-4f7ed6a664e9",
+            "name": "testuser",
+            "displayname": "Test User",
+            "description": "a test user"
+        }"#;
+
+        let mut de = serde_json::Deserializer::from_str(data);
+        let ordered =
+            ScimEntryGeneric::deserialize_with_order(&mut de, true).expect("failed to deserialise");
+
+        let ordered_keys: Vec<_> = ordered.iter_ordered().map(|(attr, _)| attr).collect();
+        assert_eq!(
+            ordered_keys,
+            vec![
+                &Attribute::Name,
+                &Attribute::DisplayName,
+                &Attribute::Description
+            ]
+        );
+
+        // Canonical iteration is unchanged.
+        let canonical_keys: Vec<_> = ordered.attrs.keys().collect();
+        assert_eq!(
+            canonical_keys,
+            vec![
+                &Attribute::Description,
+                &Attribute::DisplayName,
+                &Attribute::Name
+            ]
+        );
+
+        // Without tracking, ordered iteration falls back to canonical order.
+        let mut de = serde_json::Deserializer::from_str(data);
+        let unordered = ScimEntryGeneric::deserialize_with_order(&mut de, false)
+            .expect("failed to deserialise");
+        let unordered_keys: Vec<_> = unordered.iter_ordered().map(|(attr, _)| attr).collect();
+        assert_eq!(unordered_keys, canonical_keys);
+
+        // Equality ignores the submission order.
+        assert_eq!(ordered, unordered);
+
+        // Attributes added later are returned after the tracked attributes.
+        let mut ordered = ordered;
+        ordered
+            .attrs
+            .insert(Attribute::Class, JsonValue::String("person".to_string()));
+        let ordered_keys: Vec<_> = ordered.iter_ordered().map(|(attr, _)| attr).collect();
+        assert_eq!(
+            ordered_keys,
+            vec![
+                &Attribute::Name,
+                &Attribute::DisplayName,
+                &Attribute::Description,
+                &Attribute::Class
+            ]
+        );
+    }
+
+    #[test]
+    fn scim_entry_get_query_matches_version() {
+        let query = ScimEntryGetQuery::default();
+        assert!(!query.matches_version("abc"));
+
+        let query = ScimEntryGetQuery {
+            if_none_match: Some("\"abc\"".to_string()),
+            ..Default::default()
+        };
+        assert!(query.matches_version("abc"));
+        assert!(query.matches_version("\"abc\""));
+        assert!(query.matches_version("W/\"abc\""));
+        assert!(!query.matches_version("abd"));
+
+        // Weak comparison ignores the W/ prefix on the request too.
+        let query = ScimEntryGetQuery {
+            if_none_match: Some("W/\"abc\"".to_string()),
+            ..Default::default()
+        };
+        assert!(query.matches_version("abc"));
+        assert!(query.matches_version("W/\"abc\""));
+        assert!(!query.matches_version("W/\"abd\""));
+
+        let query = ScimEntryGetQuery {
+            if_none_match: Some("W/\"abc\", \"def\"".to_string()),
+            ..Default::default()
+        };
+        assert!(query.matches_version("abc"));
+        assert!(query.matches_version("def"));
+        assert!(!query.matches_version("ghi"));
+
+        let query = ScimEntryGetQuery {
+            if_none_match: Some("*".to_string()),
+            ..Default::default()
+        };
+        assert!(query.matches_version("abc"));
+
+        // The header is never part of the query string.
+        let query = ScimEntryGetQuery {
+            count: NonZeroU64::new(1),
+            if_none_match: Some("\"abc\"".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_urlencoded::to_string(&query).expect("Failed to encode query"),
+            "count=1"
+        );
+        assert_eq!(
+            query,
+            ScimEntryGetQuery {
+                count: NonZeroU64::new(1),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn scim_person_is_active() {
+        use server::is_active;
+        use time::{Duration, OffsetDateTime};
+
+        let now = OffsetDateTime::UNIX_EPOCH + Duration::days(1000);
+        let past = now - Duration::days(1);
+        let future = now + Duration::days(1);
+
+        assert!(is_active(now, None, None));
+        assert!(is_active(now, Some(&past), None));
+        assert!(is_active(now, Some(&now), Some(&future)));
+        assert!(!is_active(now, Some(&future), None));
+        assert!(!is_active(now, None, Some(&past)));
+        // An account expiring right now is no longer active.
+        assert!(!is_active(now, None, Some(&now)));
+    }
+
+    #[test]
+    fn scim_entry_get_query_zero_based_offset() {
+        let query = ScimEntryGetQuery::default();
+        assert_eq!(query.zero_based_offset(), 0);
+
+        let query = ScimEntryGetQuery {
+            start_index: NonZeroU64::new(1),
+            ..Default::default()
+        };
+        assert_eq!(query.zero_based_offset(), 0);
+
+        let query = ScimEntryGetQuery {
+            start_index: NonZeroU64::new(10),
+            ..Default::default()
+        };
+        assert_eq!(query.zero_based_offset(), 9);
+    }
+
+    #[test]
+    fn scim_entry_generic_into_typed() {
+        let data = r#"{
+            "schemas": ["urn:ietf:params:scim:schemas:kanidm:1.0:application"],
+            "id": "cb3de098-33fd-4565-9d80-4f7ed6a664e9",
+            "name": "test_app",
+            "displayname": "Test App",
+            "linked_group": [{
+                "uuid": "b3f2a1d4-4a9e-4d6c-9f0c-0e3f5d7a2c11",
+                "value": "test_group@example.com"
+            }],
+            "description": "an application"
+        }"#;
+
+        let generic: ScimEntryGeneric = serde_json::from_str(data).expect("invalid entry");
+
+        let name: String = generic
+            .get_attr(&Attribute::Name)
+            .expect("name not present")
+            .expect("name invalid");
+        assert_eq!(name, "test_app");
+
+        // Wrong type
+        assert!(generic.get_attr::<u32>(&Attribute::Name).unwrap().is_err());
+        // Not present
+        assert!(generic.get_attr::<String>(&Attribute::Mail).is_none());
+
+        let app: client::ScimEntryApplication = generic.clone().into_typed().expect("not an app");
+        assert_eq!(
+            app.header.id,
+            uuid::uuid!("cb3de098-33fd-4565-9d80-4f7ed6a664e9")
+        );
+        assert_eq!(app.name, "test_app");
+        assert_eq!(app.displayname, "Test App");
+        assert_eq!(app.linked_group.len(), 1);
+        assert_eq!(
+            app.attrs.get(&Attribute::Description),
+            Some(&JsonValue::String("an application".to_string()))
+        );
+
+        // Missing required attributes fail to convert.
+        let mut generic = generic;
+        generic.attrs.remove(&Attribute::DisplayName);
+        assert!(generic
+            .into_typed::<client::ScimEntryApplication>()
+            .is_err());
+    }
+
     #[test]
     fn scim_rfc_to_generic() {
         // Assert that we can transition from the rfc generic entries to the
@@ -528,10 +1062,11 @@ mod tests {
         .set_totp_import(vec![ScimTotp {
             external_id: "Totp".to_string(),
             secret: "abcd".to_string(),
-            algo: "SHA3".to_string(),
+            algo: TotpAlgorithm::Sha256,
             step: 60,
             digits: 8,
         }])
+        .unwrap()
         .set_mail(vec![MultiValueAttr {
             primary: Some(true),
             value: "testuser@example.com".to_string(),
@@ -552,6 +1087,121 @@ mod tests {
         assert!(entry.is_ok());
     }
 
+    #[test]
+    fn scim_sync_totp_validate() {
+        use super::*;
+
+        let totp = ScimTotp {
+            external_id: "Totp".to_string(),
+            secret: "abcd".to_string(),
+            algo: TotpAlgorithm::Sha256,
+            step: 30,
+            digits: 6,
+        };
+
+        assert_eq!(totp.validate(), Ok(()));
+
+        let mut good = totp.clone();
+        good.digits = 8;
+        assert_eq!(good.validate(), Ok(()));
+
+        let mut bad = totp.clone();
+        bad.digits = 0;
+        assert_eq!(bad.validate(), Err(ScimTotpError::InvalidDigits(0)));
+
+        let mut bad = totp.clone();
+        bad.digits = 7;
+        assert_eq!(bad.validate(), Err(ScimTotpError::InvalidDigits(7)));
+
+        let mut bad = totp.clone();
+        bad.step = 14;
+        assert_eq!(bad.validate(), Err(ScimTotpError::InvalidStep(14)));
+
+        let mut bad = totp.clone();
+        bad.step = 301;
+        assert_eq!(bad.validate(), Err(ScimTotpError::InvalidStep(301)));
+
+        // The builder must reject the import as a whole.
+        let result = ScimSyncPerson::builder(
+            uuid::uuid!("cb3de098-33fd-4565-9d80-4f7ed6a664e9"),
+            "cn=testuser".to_string(),
+            "testuser".to_string(),
+            "Test User".to_string(),
+        )
+        .set_totp_import(vec![totp, bad]);
+
+        assert!(matches!(result, Err(ScimTotpError::InvalidStep(301))));
+    }
+
+    #[test]
+    fn scim_sync_state_serialisation() {
+        // Agents only send the cookie, and older servers only return it.
+        let state: ScimSyncState =
+            serde_json::from_str(r#"{"Active":{"cookie":"AQIDBA"}}"#).expect("Failed to parse");
+        assert_eq!(state, ScimSyncState::active(vec![1, 2, 3, 4]));
+        assert_eq!(state.cookie(), Some([1, 2, 3, 4].as_slice()));
+        assert_eq!(state.change_count(), None);
+        assert_eq!(
+            serde_json::to_string(&state).expect("Failed to serialise"),
+            r#"{"Active":{"cookie":"AQIDBA"}}"#
+        );
+
+        let state: ScimSyncState = serde_json::from_str(
+            r#"{"Active":{"cookie":"AQIDBA","change_count":0,"generated_at":"2024-01-01T00:00:00Z"}}"#,
+        )
+        .expect("Failed to parse");
+        assert_eq!(state.change_count(), Some(0));
+        let ScimSyncState::Active { generated_at, .. } = &state else {
+            panic!("Expected an active state");
+        };
+        assert_eq!(
+            generated_at.map(|t| t.unix_timestamp()),
+            Some(1_704_067_200)
+        );
+
+        let state: ScimSyncState = serde_json::from_str(r#""Refresh""#).expect("Failed to parse");
+        assert_eq!(state.cookie(), None);
+        assert_eq!(state.change_count(), None);
+    }
+
+    #[test]
+    fn scim_sync_totp_algorithm() {
+        use super::*;
+
+        assert_eq!("SHA1".parse(), Ok(TotpAlgorithm::Sha1));
+        assert_eq!("sha256".parse(), Ok(TotpAlgorithm::Sha256));
+        assert_eq!("Sha512".parse(), Ok(TotpAlgorithm::Sha512));
+
+        for algo in ["md5", "SHA-256", "sha3-256", ""] {
+            assert_eq!(
+                algo.parse::<TotpAlgorithm>(),
+                Err(ScimTotpError::UnknownAlgo(algo.to_string()))
+            );
+        }
+
+        let err = ScimTotpError::UnknownAlgo("md5".to_string()).to_string();
+        assert_eq!(
+            err,
+            "unknown totp algorithm md5, must be one of sha1, sha256, sha512"
+        );
+
+        // Unknown algorithms are rejected when the import is deserialised.
+        let data =
+            r#"{"external_id":"Totp","secret":"abcd","algo":"SHA-256","step":30,"digits":6}"#;
+        let result = serde_json::from_str::<ScimTotp>(data);
+        assert!(result.is_err_and(|err| err.to_string().contains("unknown totp algorithm")));
+
+        let data = r#"{"external_id":"Totp","secret":"abcd","algo":"SHA256","step":30,"digits":6}"#;
+        let totp = serde_json::from_str::<ScimTotp>(data).expect("failed to deserialise");
+        assert_eq!(totp.algo, TotpAlgorithm::Sha256);
+
+        // The server expects the lower case name.
+        assert_eq!(
+            serde_json::to_value(&totp).expect("failed to serialise")["algo"],
+            "sha256"
+        );
+    }
+
     #[test]
     fn scim_entry_get_query() {
         use super::*;
@@ -1053,4 +1703,172 @@ mod tests {
         scimfilter::parse_depth("name pr and (name pr and (name pr and name pr))", 3)
             .expect("Must pass");
     }
+
+    #[test]
+    fn scim_entry_get_query_attribute_order() {
+        use std::collections::hash_map::DefaultHasher;
+
+        fn hash_of(query: &ScimEntryGetQuery) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            query.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let query_a = ScimEntryGetQuery {
+            attributes: Some(vec![Attribute::Name, Attribute::Mail, Attribute::Uuid]),
+            filter: Some(ScimFilter::Present(Attribute::Name.into())),
+            ..Default::default()
+        };
+
+        let query_b = ScimEntryGetQuery {
+            attributes: Some(vec![Attribute::Uuid, Attribute::Name, Attribute::Mail]),
+            filter: Some(ScimFilter::Present(Attribute::Name.into())),
+            ..Default::default()
+        };
+
+        assert_eq!(query_a, query_b);
+        assert_eq!(hash_of(&query_a), hash_of(&query_b));
+
+        let query_c = ScimEntryGetQuery {
+            attributes: Some(vec![Attribute::Uuid, Attribute::Name]),
+            filter: Some(ScimFilter::Present(Attribute::Name.into())),
+            ..Default::default()
+        };
+
+        assert_ne!(query_a, query_c);
+    }
+
+    #[test]
+    fn scim_mail_display() {
+        let mail = ScimMail {
+            primary: false,
+            value: "email@example.com".to_string(),
+        };
+        assert_eq!(mail.to_string(), "email@example.com");
+
+        let mail = ScimMail {
+            primary: true,
+            value: "email@example.com".to_string(),
+        };
+        assert_eq!(mail.to_string(), "email@example.com (primary)");
+    }
+
+    #[test]
+    fn scim_ssh_public_key_display() {
+        let value = SshPublicKey::from_string("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIEchaWqELH+swwROwM043oyVkCX+HcCUWY0ClglEtIVo testuser@host")
+            .expect("Failed to parse ssh public key");
+
+        let key = client::ScimSshPublicKey {
+            label: "laptop".to_string(),
+            value: value.clone(),
+        };
+        assert_eq!(key.to_string(), "laptop: ssh-ed25519 AAAAC3NzaC1lZDI1...");
+
+        let key = ScimSshPublicKey {
+            label: "laptop".to_string(),
+            value,
+        };
+        assert_eq!(key.to_string(), "laptop: ssh-ed25519 AAAAC3NzaC1lZDI1...");
+    }
+
+    #[test]
+    fn scim_ssh_public_key_fingerprint() {
+        let value = SshPublicKey::from_string("sk-ecdsa-sha2-nistp256@openssh.com AAAAInNrLWVjZHNhLXNoYTItbmlzdHAyNTZAb3BlbnNzaC5jb20AAAAIbmlzdHAyNTYAAABBBENubZikrb8hu+HeVRdZ0pp/VAk2qv4JDbuJhvD0yNdWDL2e3cBbERiDeNPkWx58Q4rVnxkbV1fa8E2waRtT91wAAAAEc3NoOg== testuser@fidokey")
+            .expect("Failed to parse ssh public key");
+
+        let sk_key = ScimSshPublicKey {
+            label: "fidokey".to_string(),
+            value,
+        };
+
+        assert_eq!(
+            sk_key.fingerprint_sha256(),
+            "SHA256:UWyz3jnbvslMIvhN0p5l5i+Btn2oDi7J9I8eaCXZp9s"
+        );
+
+        let value = SshPublicKey::from_string("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIEchaWqELH+swwROwM043oyVkCX+HcCUWY0ClglEtIVo testuser@host")
+            .expect("Failed to parse ssh public key");
+
+        let ed_key = ScimSshPublicKey {
+            label: "ed25519".to_string(),
+            value,
+        };
+
+        assert_eq!(
+            ed_key.fingerprint_sha256(),
+            "SHA256:1lFGqcjouqHprpR6i120cIy9LyYSA1Nbpe0ZPzvQnPk"
+        );
+    }
+
+    #[test]
+    fn scim_v1_openapi_components() {
+        let mut openapi = utoipa::openapi::OpenApiBuilder::new().build();
+        register_openapi_components(&mut openapi);
+
+        let json = openapi.to_json().expect("Failed to generate openapi json");
+        let doc: JsonValue = serde_json::from_str(&json).expect("Invalid openapi json");
+        let schemas = doc["components"]["schemas"]
+            .as_object()
+            .expect("No component schemas present");
+
+        for name in [
+            "ScimEntryGeneric",
+            "ScimEntryGetQuery",
+            "ScimReference",
+            "ScimSshPublicKey",
+            "ScimMail",
+            "client.ScimReference",
+            "client.ScimSshPublicKey",
+            "ScimEntryPutGeneric",
+            "server.ScimReference",
+            "ScimEntryKanidm",
+        ] {
+            assert!(schemas.contains_key(name), "missing schema {name}");
+        }
+    }
+
+    #[test]
+    fn scim_reference_set_dedup() {
+        use std::hash::{BuildHasher, RandomState};
+
+        let uuid_a = uuid::uuid!("00000000-0000-0000-0000-00000000000a");
+        let uuid_b = uuid::uuid!("00000000-0000-0000-0000-00000000000b");
+
+        let refs = [
+            ScimReference {
+                uuid: uuid_b,
+                value: "b@example.com".to_string(),
+            },
+            ScimReference {
+                uuid: uuid_a,
+                value: "z@example.com".to_string(),
+            },
+            ScimReference {
+                uuid: uuid_a,
+                value: "a@example.com".to_string(),
+            },
+            ScimReference {
+                uuid: uuid_b,
+                value: "b@example.com".to_string(),
+            },
+        ];
+
+        let set: BTreeSet<_> = refs.iter().cloned().collect();
+        assert_eq!(set.len(), 3);
+
+        // Ordered by uuid first, then value.
+        let ordered: Vec<_> = set.iter().map(|r| (r.uuid, r.value.as_str())).collect();
+        assert_eq!(
+            ordered,
+            vec![
+                (uuid_a, "a@example.com"),
+                (uuid_a, "z@example.com"),
+                (uuid_b, "b@example.com"),
+            ]
+        );
+
+        // Equal references must hash the same.
+        let hasher = RandomState::new();
+        assert_eq!(hasher.hash_one(&refs[0]), hasher.hash_one(&refs[3]));
+    }
 }
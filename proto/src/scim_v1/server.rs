@@ -1,3 +1,4 @@
+use super::JsonValue;
 use super::ScimMail;
 use super::ScimOauth2ClaimMapJoinChar;
 use super::ScimSshPublicKey;
@@ -33,6 +34,11 @@ pub struct ScimEntryKanidm {
 }
 
 impl ScimEntryKanidm {
+    /// The version of this entry from its `meta`, for use as an ETag in change detection.
+    pub fn version(&self) -> Option<&str> {
+        self.header.meta.as_ref().map(|meta| meta.version.as_str())
+    }
+
     fn get_string_attr(&self, attr: &Attribute) -> Option<&String> {
         self.attrs.get(attr).and_then(|v| match v {
             ScimValueKanidm::String(s) => Some(s),
@@ -40,6 +46,13 @@ impl ScimEntryKanidm {
         })
     }
 
+    fn get_bool_attr(&self, attr: &Attribute) -> Option<bool> {
+        self.attrs.get(attr).and_then(|v| match v {
+            ScimValueKanidm::Bool(b) => Some(*b),
+            _ => None,
+        })
+    }
+
     fn get_scim_refs_attr(&self, attr: &Attribute) -> Option<&Vec<ScimReference>> {
         let option = self.attrs.get(attr);
         option.and_then(|v| match v {
@@ -111,6 +124,7 @@ pub struct ScimEffectiveAccess {
 
 #[derive(Serialize, Debug, Clone, ToSchema)]
 #[serde(rename_all = "camelCase")]
+#[schema(as = server::ScimAddress)]
 pub struct ScimAddress {
     pub formatted: String,
     pub street_address: String,
@@ -140,6 +154,7 @@ pub struct ScimBinary {
 #[serde_as]
 #[derive(Serialize, Debug, Clone, ToSchema)]
 #[serde(rename_all = "camelCase")]
+#[schema(as = server::ScimCertificate)]
 pub struct ScimCertificate {
     #[serde_as(as = "Hex")]
     pub s256: Vec<u8>,
@@ -237,6 +252,7 @@ pub struct ScimApiToken {
 #[serde_as]
 #[derive(Serialize, Debug, Clone, ToSchema)]
 #[serde(rename_all = "camelCase")]
+#[schema(as = server::ScimOAuth2ScopeMap)]
 pub struct ScimOAuth2ScopeMap {
     pub group: String,
     pub group_uuid: Uuid,
@@ -246,16 +262,22 @@ pub struct ScimOAuth2ScopeMap {
 #[serde_as]
 #[derive(Serialize, Debug, Clone, ToSchema)]
 #[serde(rename_all = "camelCase")]
+#[schema(as = server::ScimOAuth2ClaimMap)]
 pub struct ScimOAuth2ClaimMap {
     pub group: String,
     pub group_uuid: Uuid,
     pub claim: String,
     pub join_char: ScimOauth2ClaimMapJoinChar,
     pub values: BTreeSet<String>,
+    /// Claim values that are not strings, such as numbers, booleans or objects.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub typed_values: Vec<JsonValue>,
 }
 
-#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+/// References are ordered by uuid, then by value.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, ToSchema)]
 #[serde(rename_all = "camelCase")]
+#[schema(as = server::ScimReference)]
 pub struct ScimReference {
     pub uuid: Uuid,
     pub value: String,
@@ -306,6 +328,10 @@ pub enum ScimValueKanidm {
 
     Message(OutboundMessage),
 
+    /// A json object, such as the data of scim extensions keyed by their urn.
+    #[schema(value_type = Object)]
+    Object(BTreeMap<String, JsonValue>),
+
     #[schema(value_type = Vec<String>)]
     Sha256(#[serde_as(as = "Vec<Hex>")] Vec<Sha256Output>),
 }
@@ -321,6 +347,9 @@ pub struct ScimPerson {
     pub mails: Vec<ScimMail>,
     pub managed_by: Option<ScimReference>,
     pub groups: Vec<ScimReference>,
+    /// If the account could authenticate when it was read, as determined by its
+    /// `account_valid_from` and `account_expire` attributes.
+    pub active: bool,
 }
 
 impl TryFrom<ScimEntryKanidm> for ScimPerson {
@@ -341,6 +370,7 @@ impl TryFrom<ScimEntryKanidm> for ScimPerson {
             .cloned()
             .ok_or(())?;
         let description = scim_entry.get_string_attr(&Attribute::Description).cloned();
+        let active = scim_entry.get_bool_attr(&Attribute::Active).ok_or(())?;
 
         let mails = scim_entry
             .attrs
@@ -373,10 +403,20 @@ impl TryFrom<ScimEntryKanidm> for ScimPerson {
             mails,
             managed_by,
             groups,
+            active,
         })
     }
 }
 
+/// Determine the SCIM `active` state of an account from its validity window.
+pub fn is_active(
+    now: OffsetDateTime,
+    valid_from: Option<&OffsetDateTime>,
+    expire: Option<&OffsetDateTime>,
+) -> bool {
+    valid_from.is_none_or(|vf| *vf <= now) && expire.is_none_or(|ex| now < *ex)
+}
+
 #[serde_as]
 #[derive(Serialize, Debug, Clone, ToSchema)]
 pub struct ScimGroup {
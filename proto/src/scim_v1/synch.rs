@@ -1,5 +1,9 @@
 use serde::{Deserialize, Serialize};
 use serde_with::{base64, formats, serde_as};
+use std::fmt;
+use std::str::FromStr;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
@@ -7,16 +11,56 @@ use scim_proto::user::MultiValueAttr;
 use scim_proto::{ScimEntry, ScimEntryHeader};
 use serde_with::skip_serializing_none;
 
+/// The opaque state a sync agent stores in Kanidm between syncs.
+pub type ScimSyncCookie = Vec<u8>;
+
 #[serde_as]
+#[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
 pub enum ScimSyncState {
     Refresh,
     Active {
         #[serde_as(as = "base64::Base64<base64::UrlSafe, formats::Unpadded>")]
-        cookie: Vec<u8>,
+        cookie: ScimSyncCookie,
+        /// The number of synchronised entries that have been modified in Kanidm since
+        /// this cookie was stored. Only set by the server when reporting the sync state.
+        #[serde(default)]
+        change_count: Option<u64>,
+        /// When the server generated this state. Only set by the server.
+        #[serde(default)]
+        #[serde_as(as = "Option<Rfc3339>")]
+        generated_at: Option<OffsetDateTime>,
     },
 }
 
+impl ScimSyncState {
+    /// An active sync state with this cookie, as sent by a sync agent.
+    pub fn active(cookie: ScimSyncCookie) -> Self {
+        ScimSyncState::Active {
+            cookie,
+            change_count: None,
+            generated_at: None,
+        }
+    }
+
+    /// The cookie of this state, if it is active.
+    pub fn cookie(&self) -> Option<&[u8]> {
+        match self {
+            ScimSyncState::Refresh => None,
+            ScimSyncState::Active { cookie, .. } => Some(cookie),
+        }
+    }
+
+    /// The number of entries modified in Kanidm since the last sync, if the server
+    /// reported it.
+    pub fn change_count(&self) -> Option<u64> {
+        match self {
+            ScimSyncState::Refresh => None,
+            ScimSyncState::Active { change_count, .. } => *change_count,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
 pub enum ScimSyncRetentionMode {
     /// No actions are to be taken - only update or create entries in the
@@ -74,11 +118,115 @@ pub struct ScimTotp {
     /// maps to "label" in kanidm.
     pub external_id: String,
     pub secret: String,
-    pub algo: String,
+    pub algo: TotpAlgorithm,
     pub step: u32,
     pub digits: u32,
 }
 
+/// The HMAC algorithm of a [ScimTotp] import.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(try_from = "String", into = "String")]
+pub enum TotpAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl TotpAlgorithm {
+    /// The names of the accepted algorithms.
+    pub const ACCEPTED: [&'static str; 3] = ["sha1", "sha256", "sha512"];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TotpAlgorithm::Sha1 => "sha1",
+            TotpAlgorithm::Sha256 => "sha256",
+            TotpAlgorithm::Sha512 => "sha512",
+        }
+    }
+}
+
+impl fmt::Display for TotpAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TotpAlgorithm {
+    type Err = ScimTotpError;
+
+    /// Algorithm names are compared case insensitively.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "sha1" => Ok(TotpAlgorithm::Sha1),
+            "sha256" => Ok(TotpAlgorithm::Sha256),
+            "sha512" => Ok(TotpAlgorithm::Sha512),
+            _ => Err(ScimTotpError::UnknownAlgo(value.to_string())),
+        }
+    }
+}
+
+impl TryFrom<String> for TotpAlgorithm {
+    type Error = ScimTotpError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        TotpAlgorithm::from_str(&value)
+    }
+}
+
+impl From<TotpAlgorithm> for String {
+    fn from(value: TotpAlgorithm) -> Self {
+        value.as_str().to_string()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScimTotpError {
+    /// The number of digits was not 6 or 8.
+    InvalidDigits(u32),
+    /// The step in seconds was outside of the accepted range. This is 15 to 300 for
+    /// [ScimTotp::validate], while the server accepts any step of at least 30 on import.
+    InvalidStep(u32),
+    /// The algorithm was not one of [TotpAlgorithm::ACCEPTED].
+    UnknownAlgo(String),
+    /// The secret was not valid base64.
+    InvalidSecret,
+}
+
+impl fmt::Display for ScimTotpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScimTotpError::InvalidDigits(d) => write!(f, "invalid totp digits {d}, must be 6 or 8"),
+            ScimTotpError::InvalidStep(s) => {
+                write!(f, "invalid totp step of {s} seconds")
+            }
+            ScimTotpError::UnknownAlgo(a) => write!(
+                f,
+                "unknown totp algorithm {a}, must be one of {}",
+                TotpAlgorithm::ACCEPTED.join(", ")
+            ),
+            ScimTotpError::InvalidSecret => write!(f, "invalid totp secret, must be base64"),
+        }
+    }
+}
+
+impl std::error::Error for ScimTotpError {}
+
+impl ScimTotp {
+    /// Check that the parameters of this TOTP are sensible before it is sent
+    /// for import.
+    pub fn validate(&self) -> Result<(), ScimTotpError> {
+        if !matches!(self.digits, 6 | 8) {
+            return Err(ScimTotpError::InvalidDigits(self.digits));
+        }
+
+        if !(15..=300).contains(&self.step) {
+            return Err(ScimTotpError::InvalidStep(self.step));
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ScimSshPubKey {
     pub label: String,
@@ -169,9 +317,10 @@ impl ScimSyncPersonBuilder {
         self
     }
 
-    pub fn set_totp_import(mut self, totp_import: Vec<ScimTotp>) -> Self {
+    pub fn set_totp_import(mut self, totp_import: Vec<ScimTotp>) -> Result<Self, ScimTotpError> {
+        totp_import.iter().try_for_each(ScimTotp::validate)?;
         self.inner.totp_import = totp_import;
-        self
+        Ok(self)
     }
 
     pub fn set_mail(mut self, mail: Vec<MultiValueAttr>) -> Self {
@@ -105,6 +105,23 @@ impl Display for UnixUserToken {
     }
 }
 
+/// Query parameters for waiting on changes to posix accounts and groups. `wait` is the
+/// number of seconds to wait for a change, and is limited by the server.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, ToSchema)]
+pub struct UnixChangesQuery {
+    pub wait: Option<u64>,
+}
+
+/// The posix accounts and groups that were changed or deleted while a client waited.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, ToSchema)]
+pub struct UnixChanges {
+    pub uuids: Vec<Uuid>,
+    /// More changes occurred than could be listed, so every cached posix account
+    /// and group must be refreshed.
+    #[serde(default)]
+    pub all: bool,
+}
+
 /// Request addition of unix attributes to an account
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 #[serde(deny_unknown_fields)]
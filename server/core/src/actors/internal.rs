@@ -10,7 +10,9 @@ use kanidm_proto::internal::{
 use kanidm_proto::scim_v1::client::ScimAssertGeneric;
 use kanidmd_lib::prelude::*;
 use kanidmd_lib::{
-    event::{PurgeDeleteAfterEvent, PurgeRecycledEvent, PurgeTombstoneEvent},
+    event::{
+        PurgeDeleteAfterEvent, PurgeRecycledEvent, PurgeRevokedSessionsEvent, PurgeTombstoneEvent,
+    },
     idm::delayed::DelayedAction,
     server::scim::ScimAssertEvent,
 };
@@ -123,6 +125,58 @@ impl QueryServerWriteV1 {
             .inspect_err(|err| error!(?err, "Unable to purge delete after entries"));
     }
 
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?msg.eventid)
+    )]
+    pub async fn handle_purge_revoked_sessions_event(&self, msg: PurgeRevokedSessionsEvent) {
+        let ct = duration_from_epoch_now();
+        let Ok(mut idms_prox_write) = self.idms.proxy_write(ct).await else {
+            warn!("Unable to start purge revoked sessions event, will retry later");
+            return;
+        };
+        let _ = idms_prox_write
+            .qs_write
+            .revoked_session_retention_cid()
+            .and_then(|older_than| idms_prox_write.qs_write.purge_revoked_sessions(older_than))
+            .and_then(|touched| {
+                // don't need to commit a txn with no changes
+                if touched > 0 {
+                    idms_prox_write.commit()
+                } else {
+                    Ok(())
+                }
+            })
+            .inspect_err(|err| error!(?err, "Unable to purge revoked sessions"));
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub(crate) async fn handle_credential_migration(&self, eventid: Uuid) {
+        let ct = duration_from_epoch_now();
+        let Ok(mut idms_prox_write) = self.idms.proxy_write(ct).await else {
+            warn!("Unable to start legacy credential migration");
+            return;
+        };
+        let _ = idms_prox_write
+            .qs_write
+            .internal_migrate_all_credentials()
+            .and_then(|stats| {
+                // Commit the migration and the record of its completion. If it had
+                // already completed there is nothing to commit.
+                if stats.is_some() {
+                    idms_prox_write.commit()
+                } else {
+                    Ok(())
+                }
+            })
+            .inspect_err(|err| error!(?err, "Unable to migrate legacy credentials"));
+    }
+
     pub(crate) async fn handle_delayedaction(&self, da_batch: &mut Vec<DelayedAction>) {
         let eventid = Uuid::new_v4();
         let span = span!(Level::INFO, "process_delayed_action", uuid = ?eventid);
@@ -1,13 +1,14 @@
 use compact_jwt::{JweCompact, Jwk, JwsCompact};
 use kanidm_proto::backup::BackupCompression;
 use kanidm_proto::internal::{
-    ApiToken, AppLink, CURequest, CUSessionToken, CUStatus, CredentialStatus, IdentifyUserRequest,
-    IdentifyUserResponse, ImageValue, OperationError, RadiusAuthToken, SearchRequest,
-    SearchResponse, UserAuthToken,
+    ApiToken, ApiTokenSummary, AppLink, CURequest, CUSessionToken, CUStatus, CertificateSummary,
+    CredentialStatus, IdentifyUserRequest, IdentifyUserResponse, ImageValue, OperationError,
+    RadiusAuthToken, SearchRequest, SearchResponse, TotpSecret, UserAuthToken,
 };
 use kanidm_proto::oauth2::OidcWebfingerResponse;
 use kanidm_proto::v1::{
-    AuthIssueSession, Entry as ProtoEntry, UatStatus, UnixGroupToken, UnixUserToken, WhoamiResponse,
+    AuthIssueSession, Entry as ProtoEntry, UatStatus, UnixChanges, UnixGroupToken, UnixUserToken,
+    WhoamiResponse,
 };
 use kanidmd_lib::be::BackendTransaction;
 use kanidmd_lib::idm::identityverification::{
@@ -31,20 +32,29 @@ use kanidmd_lib::{
         Oauth2Rfc8414MetadataResponse, OidcDiscoveryResponse, OidcToken,
     },
     idm::server::{DomainInfoRead, IdmServerTransaction},
-    idm::serviceaccount::ListApiTokenEvent,
+    idm::serviceaccount::{ListAllApiTokenEvent, ListApiTokenEvent},
+    server::UnixChangeEvent,
+    valueset::ValueSetCertificate,
 };
 use ldap3_proto::simple::*;
 use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
 use std::convert::TryFrom;
 use std::fs;
 use std::net::IpAddr;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::Duration;
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
 use tracing::{error, info, instrument, trace};
 use uuid::Uuid;
 
 use super::QueryServerReadV1;
 
+/// The longest time in seconds that a client may wait for changes to posix accounts
+/// and groups in a single request.
+const UNIX_CHANGES_MAX_WAIT: u64 = 60;
+
 // ===========================================================
 
 impl QueryServerReadV1 {
@@ -552,6 +562,63 @@ impl QueryServerReadV1 {
         }
     }
 
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_account_certificate_list(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        uuid_or_name: String,
+        eventid: Uuid,
+    ) -> Result<Vec<CertificateSummary>, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        let ident = idms_prox_read
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!("Invalid identity: {:?}", e);
+                e
+            })?;
+
+        let target_uuid = idms_prox_read
+            .qs_read
+            .name_to_uuid(uuid_or_name.as_str())
+            .inspect_err(|err| {
+                error!(?err, "Error resolving id to target");
+            })?;
+
+        let filter = filter_all!(f_and!([
+            f_eq(
+                Attribute::Class,
+                EntryClass::IdentityProviderCertificate.into()
+            ),
+            f_eq(Attribute::Refers, PartialValue::Refer(target_uuid))
+        ]));
+
+        let srch =
+            SearchEvent::from_internal_message(ident, &filter, None, &mut idms_prox_read.qs_read)
+                .inspect_err(|err| {
+                error!(?err, "Failed to begin certificate search");
+            })?;
+
+        trace!(?srch, "Begin event");
+
+        let entries = idms_prox_read.qs_read.search_ext(&srch)?;
+
+        Ok(entries
+            .iter()
+            .filter_map(|entry| {
+                entry
+                    .get_ava_set(Attribute::IdpCertificate)
+                    .and_then(|vs| vs.as_certificate_set())
+                    .map(|certs| ValueSetCertificate::summarise(entry.get_uuid(), certs))
+            })
+            .flatten()
+            .collect())
+    }
+
     #[instrument(
         level = "info",
         skip_all,
@@ -800,6 +867,73 @@ impl QueryServerReadV1 {
         idms_prox_read.get_unixgrouptoken(&rate)
     }
 
+    /// Wait for posix accounts or groups to be changed or deleted, so that unix clients
+    /// can refresh them without waiting for their cache to expire. This returns once a
+    /// change is committed, or with no changes once `wait` seconds have passed.
+    #[instrument(
+        level = "debug",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_unix_changes_wait(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        wait: Option<u64>,
+        eventid: Uuid,
+    ) -> Result<UnixChanges, OperationError> {
+        // Subscribe first so that nothing committed after this request arrived is missed.
+        let mut unix_change_rx = self.idms.subscribe_unix_changes();
+
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        idms_prox_read
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!("Invalid identity: {:?}", e);
+                e
+            })?;
+        // Don't hold the read transaction while waiting.
+        drop(idms_prox_read);
+
+        let wait = Duration::from_secs(
+            wait.unwrap_or(UNIX_CHANGES_MAX_WAIT)
+                .min(UNIX_CHANGES_MAX_WAIT),
+        );
+
+        let mut changes = UnixChanges::default();
+        let mut uuids = BTreeSet::new();
+
+        let Ok(mut event) = tokio::time::timeout(wait, unix_change_rx.recv()).await else {
+            return Ok(changes);
+        };
+
+        // Gather anything else that was committed at the same time.
+        loop {
+            match event {
+                Ok(UnixChangeEvent { uuids: changed }) => uuids.extend(changed),
+                Err(RecvError::Lagged(skipped)) => {
+                    debug!(?skipped, "Unix change events were skipped");
+                    changes.all = true;
+                }
+                Err(RecvError::Closed) => {
+                    error!("Unix change notifications are no longer available");
+                    return Err(OperationError::InvalidState);
+                }
+            }
+
+            event = match unix_change_rx.try_recv() {
+                Ok(next) => Ok(next),
+                Err(TryRecvError::Lagged(skipped)) => Err(RecvError::Lagged(skipped)),
+                Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => break,
+            };
+        }
+
+        if !changes.all {
+            changes.uuids = uuids.into_iter().collect();
+        }
+        Ok(changes)
+    }
+
     #[instrument(
         level = "debug",
         skip_all,
@@ -956,6 +1090,41 @@ impl QueryServerReadV1 {
         idms_prox_read.service_account_list_api_token(&lte)
     }
 
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_system_api_token_list(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        account: Option<String>,
+        eventid: Uuid,
+    ) -> Result<Vec<ApiTokenSummary>, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        let ident = idms_prox_read
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!("Invalid identity: {:?}", e);
+                e
+            })?;
+        let account = account
+            .map(|uuid_or_name| {
+                idms_prox_read
+                    .qs_read
+                    .name_to_uuid(uuid_or_name.as_str())
+                    .inspect_err(|err| {
+                        error!(?err, "Error resolving id to target");
+                    })
+            })
+            .transpose()?;
+
+        let late = ListAllApiTokenEvent { ident, account };
+
+        idms_prox_read.system_list_api_tokens(&late)
+    }
+
     #[instrument(
         level = "info",
         skip_all,
@@ -1159,6 +1328,35 @@ impl QueryServerReadV1 {
             .map(|sta| sta.into())
     }
 
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_idmcredentialupdatependingtotp(
+        &self,
+        session_token: CUSessionToken,
+        cred_uuid: Uuid,
+        totp_label: String,
+        eventid: Uuid,
+    ) -> Result<TotpSecret, OperationError> {
+        let session_token = JweCompact::from_str(&session_token.token)
+            .map(|token_enc| CredentialUpdateSessionToken { token_enc })
+            .map_err(|err| {
+                error!(?err, "malformed token");
+                OperationError::InvalidRequestState
+            })?;
+
+        let ct = duration_from_epoch_now();
+        let idms_cred_update = self.idms.cred_update_transaction().await?;
+
+        idms_cred_update
+            .credential_primary_pending_totp(&session_token, ct, cred_uuid, &totp_label)
+            .inspect_err(|err| {
+                error!(?err, "Failed to begin credential_primary_pending_totp");
+            })
+    }
+
     #[instrument(
         level = "info",
         skip_all,
@@ -1492,6 +1690,28 @@ impl QueryServerReadV1 {
         idms_prox_read.oauth2_openid_publickey(&client_id)
     }
 
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_entry_counts(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        eventid: Uuid,
+    ) -> Result<BTreeMap<String, u64>, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        let ident = idms_prox_read
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!("Invalid identity: {:?}", e);
+                e
+            })?;
+
+        idms_prox_read.entry_counts(&ident)
+    }
+
     #[instrument(
         level = "info",
         skip_all,
@@ -416,7 +416,7 @@ impl QueryServerReadV1 {
 
         let ident = idms_prox_read.validate_sync_client_auth_info_to_ident(client_auth_info, ct)?;
 
-        idms_prox_read.scim_sync_get_state(&ident)
+        idms_prox_read.scim_sync_get_state(&ident, ct)
     }
 
     #[instrument(
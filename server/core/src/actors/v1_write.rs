@@ -2,12 +2,13 @@ use std::iter;
 
 use compact_jwt::JweCompact;
 use kanidm_proto::internal::{
-    CUIntentToken, CUSessionToken, CUStatus, CreateRequest, DeleteRequest, ImageValue,
-    Modify as ProtoModify, ModifyList as ProtoModifyList, ModifyRequest,
+    CUIntentPermission, CUIntentToken, CUSessionToken, CUStatus, CreateRequest, DeleteRequest,
+    ImageValue, Modify as ProtoModify, ModifyList as ProtoModifyList, ModifyRequest,
     Oauth2ClaimMapJoin as ProtoOauth2ClaimMapJoin, OperationError,
 };
 use kanidm_proto::v1::{AccountUnixExtend, Entry as ProtoEntry, GroupUnixExtend};
 use kanidmd_lib::valueset::image::ImageValueThings;
+use kanidmd_lib::valueset::ValueSetCertificate;
 use std::str::FromStr;
 use time::OffsetDateTime;
 use tracing::{info, instrument, trace};
@@ -35,7 +36,6 @@ use kanidmd_lib::{
 
 use kanidmd_lib::prelude::*;
 
-#[cfg(feature = "dev-oauth2-device-flow")]
 use std::collections::BTreeSet;
 
 use super::QueryServerWriteV1;
@@ -326,6 +326,81 @@ impl QueryServerWriteV1 {
             .and_then(|_| idms_prox_write.commit().map(|_| ()))
     }
 
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_account_certificate_remove(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        uuid_or_name: String,
+        fingerprint: String,
+        eventid: Uuid,
+    ) -> Result<(), OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        let target_uuid = idms_prox_write
+            .qs_write
+            .name_to_uuid(uuid_or_name.as_str())
+            .inspect_err(|err| {
+                error!(?err, "Error resolving id to target");
+            })?;
+
+        let filter = filter_all!(f_and!([
+            f_eq(
+                Attribute::Class,
+                EntryClass::IdentityProviderCertificate.into()
+            ),
+            f_eq(Attribute::Refers, PartialValue::Refer(target_uuid))
+        ]));
+
+        // The fingerprint is a digest of the whole certificate, which is not what
+        // the certificate valueset is indexed by, so find the holding entry here.
+        let cert_uuid = idms_prox_write
+            .qs_write
+            .impersonate_search_ext(filter.clone(), filter, &ident)?
+            .iter()
+            .filter_map(|entry| {
+                entry
+                    .get_ava_set(Attribute::IdpCertificate)
+                    .and_then(|vs| vs.as_certificate_set())
+                    .map(|certs| ValueSetCertificate::summarise(entry.get_uuid(), certs))
+            })
+            .flatten()
+            .find(|summary| {
+                summary
+                    .fingerprint_sha256
+                    .eq_ignore_ascii_case(&fingerprint)
+            })
+            .map(|summary| summary.uuid)
+            .ok_or_else(|| {
+                error!("No certificate matches the requested fingerprint");
+                OperationError::NoMatchingEntries
+            })?;
+
+        let filter = filter_all!(f_eq(Attribute::Uuid, PartialValue::Uuid(cert_uuid)));
+
+        let del = DeleteEvent::from_parts(ident, &filter, &mut idms_prox_write.qs_write)
+            .inspect_err(|err| {
+                error!(?err, "Failed to begin delete");
+            })?;
+
+        trace!(?del, "Begin delete event");
+
+        idms_prox_write
+            .qs_write
+            .delete(&del)
+            .and_then(|_| idms_prox_write.commit().map(|_| ()))
+    }
+
     #[instrument(
         level = "info",
         skip_all,
@@ -639,6 +714,7 @@ impl QueryServerWriteV1 {
         client_auth_info: ClientAuthInfo,
         uuid_or_name: String,
         ttl: Option<Duration>,
+        permissions: Option<BTreeSet<CUIntentPermission>>,
         eventid: Uuid,
     ) -> Result<CUIntentToken, OperationError> {
         let ct = duration_from_epoch_now();
@@ -660,7 +736,8 @@ impl QueryServerWriteV1 {
 
         idms_prox_write
             .init_credential_update_intent(
-                &InitCredentialUpdateIntentEvent::new(ident, target_uuid, ttl),
+                &InitCredentialUpdateIntentEvent::new(ident, target_uuid, ttl)
+                    .with_permissions(permissions),
                 ct,
             )
             .and_then(|tok| idms_prox_write.commit().map(|_| tok))
@@ -1421,7 +1498,7 @@ impl QueryServerWriteV1 {
         client_auth_info: ClientAuthInfo,
         claim_name: String,
         group: String,
-        claims: Vec<String>,
+        claims: Vec<JsonValue>,
         filter: Filter<FilterInvalid>,
         eventid: Uuid,
     ) -> Result<(), OperationError> {
@@ -1445,13 +1522,37 @@ impl QueryServerWriteV1 {
                 e
             })?;
 
-        let ml = ModifyList::new_append(
-            Attribute::OAuth2RsClaimMap,
-            Value::new_oauthclaimmap(claim_name, group_uuid, claims.into_iter().collect())
-                .ok_or_else(|| {
-                    OperationError::InvalidAttribute("Invalid Oauth Claim Map syntax".to_string())
-                })?,
-        );
+        // String claims are kept apart from claims of other types, such as booleans or
+        // objects. Both are replaced so that the group holds exactly these claims, where
+        // an empty set removes them. The typed claims are first, as an empty set of
+        // strings can't begin a claim map.
+        let (claims, typed_claims): (Vec<_>, Vec<_>) = claims
+            .into_iter()
+            .partition(|claim| matches!(claim, JsonValue::String(_)));
+
+        let claims = claims
+            .into_iter()
+            .filter_map(|claim| match claim {
+                JsonValue::String(claim) => Some(claim),
+                _ => None,
+            })
+            .collect();
+
+        let invalid_claim_map =
+            || OperationError::InvalidAttribute("Invalid Oauth Claim Map syntax".to_string());
+
+        let ml = ModifyList::new_list(vec![
+            Modify::Present(
+                Attribute::OAuth2RsClaimMap,
+                Value::new_oauthclaimmap_typed(claim_name.clone(), group_uuid, typed_claims)
+                    .ok_or_else(invalid_claim_map)?,
+            ),
+            Modify::Present(
+                Attribute::OAuth2RsClaimMap,
+                Value::new_oauthclaimmap(claim_name, group_uuid, claims)
+                    .ok_or_else(invalid_claim_map)?,
+            ),
+        ]);
 
         let mdf = match ModifyEvent::from_internal_parts(
             ident,
@@ -111,6 +111,27 @@ pub struct TlsConfiguration {
     pub chain: PathBuf,
     pub key: PathBuf,
     pub client_ca: Option<PathBuf>,
+    /// Certificates selected by the client's SNI. When empty, chain and key are used for
+    /// all connections. Otherwise chain and key are only used when no certificate matches.
+    #[serde(default)]
+    pub certificates: Vec<TlsCertificate>,
+}
+
+/// A certificate and key served to clients that request `domain` via SNI.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct TlsCertificate {
+    pub domain: String,
+    pub certificate_chain: PathBuf,
+    pub private_key: PathBuf,
+}
+
+/// The `[tls]` section of the server configuration.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct TlsSection {
+    #[serde(default)]
+    certificates: Vec<TlsCertificate>,
 }
 
 #[derive(Debug, Default)]
@@ -256,6 +277,18 @@ pub enum ServerRole {
     ReadOnlyReplica,
 }
 
+impl ServerRole {
+    /// The role used when the configuration doesn't set one. Build profiles can
+    /// set `default_read_only` to make this a read only replica.
+    fn profile_default() -> Self {
+        if env!("KANIDM_DEFAULT_READ_ONLY") == "true" {
+            ServerRole::ReadOnlyReplica
+        } else {
+            ServerRole::WriteReplica
+        }
+    }
+}
+
 impl Display for ServerRole {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -317,7 +350,7 @@ pub struct ServerConfig {
     ///
     /// If unset, the LDAP server will be disabled.
     ldapbindaddress: Option<String>,
-    /// The role of this server, one of write_replica, write_replica_no_ui, read_only_replica, defaults to [ServerRole::WriteReplica]
+    /// The role of this server, one of write_replica, write_replica_no_ui, read_only_replica, defaults to [ServerRole::WriteReplica] unless the build profile sets `default_read_only`
     role: Option<ServerRole>,
     /// The log level, one of info, debug, trace. Defaults to "info" if not set.
     log_level: Option<LogLevel>,
@@ -410,6 +443,7 @@ pub struct ServerConfigV2 {
     tls_chain: Option<PathBuf>,
     tls_key: Option<PathBuf>,
     tls_client_ca: Option<PathBuf>,
+    tls: Option<TlsSection>,
 
     migration_path: Option<PathBuf>,
 
@@ -425,6 +459,9 @@ pub struct ServerConfigV2 {
     http_client_address_info: Option<HttpAddressInfo>,
     ldap_client_address_info: Option<LdapAddressInfo>,
 
+    bind_session_to_ip: Option<bool>,
+    session_ip_bind_exclude_cidrs: Option<Vec<IpCidr>>,
+
     adminbindpath: Option<String>,
     thread_count: Option<usize>,
     maximum_request_size_bytes: Option<usize>,
@@ -471,6 +508,12 @@ pub struct Configuration {
     pub http_client_address_info: HttpAddressInfo,
     pub ldap_client_address_info: LdapAddressInfo,
 
+    /// Reject user sessions presented from an address other than the one they
+    /// authenticated from.
+    pub bind_session_to_ip: bool,
+    /// Client addresses that are exempt from session address binding.
+    pub session_ip_bind_exclude_cidrs: Vec<IpCidr>,
+
     pub tls_config: Option<TlsConfiguration>,
     pub integration_test_config: Option<Box<IntegrationTestConfig>>,
     pub online_backup: Option<OnlineBackup>,
@@ -505,9 +548,12 @@ impl Configuration {
             maximum_request: 256 * 1024, // 256k
             http_client_address_info: HttpAddressInfo::default(),
             ldap_client_address_info: LdapAddressInfo::default(),
+            bind_session_to_ip: false,
+            session_ip_bind_exclude_cidrs: Vec::new(),
             tls_key: None,
             tls_chain: None,
             tls_client_ca: None,
+            tls_certificates: Vec::new(),
             online_backup: None,
             domain: None,
             origin: None,
@@ -532,6 +578,8 @@ impl Configuration {
             maximum_request: 256 * 1024, // 256k
             http_client_address_info: HttpAddressInfo::default(),
             ldap_client_address_info: LdapAddressInfo::default(),
+            bind_session_to_ip: false,
+            session_ip_bind_exclude_cidrs: Vec::new(),
             tls_config: None,
             integration_test_config: None,
             online_backup: None,
@@ -587,6 +635,14 @@ impl fmt::Display for Configuration {
             "ldap client address info: {}, ",
             self.ldap_client_address_info
         )?;
+        write!(f, "bind session to ip: {}, ", self.bind_session_to_ip)?;
+        if self.bind_session_to_ip {
+            write!(f, "session ip bind exclusions: [")?;
+            for cidr in &self.session_ip_bind_exclude_cidrs {
+                write!(f, "{cidr}, ")?;
+            }
+            write!(f, "], ")?;
+        }
 
         write!(f, "with TLS: {}, ", self.tls_config.is_some())?;
         match &self.online_backup {
@@ -644,9 +700,12 @@ pub struct ConfigurationBuilder {
     maximum_request: usize,
     http_client_address_info: HttpAddressInfo,
     ldap_client_address_info: LdapAddressInfo,
+    bind_session_to_ip: bool,
+    session_ip_bind_exclude_cidrs: Vec<IpCidr>,
     tls_key: Option<PathBuf>,
     tls_chain: Option<PathBuf>,
     tls_client_ca: Option<PathBuf>,
+    tls_certificates: Vec<TlsCertificate>,
     online_backup: Option<OnlineBackup>,
     domain: Option<String>,
     origin: Option<Url>,
@@ -928,6 +987,10 @@ impl ConfigurationBuilder {
             self.tls_client_ca = config.tls_client_ca;
         }
 
+        if let Some(tls) = config.tls {
+            self.tls_certificates = tls.certificates;
+        }
+
         if config.bindaddress.is_some() {
             self.bindaddress = config.bindaddress;
         }
@@ -968,6 +1031,14 @@ impl ConfigurationBuilder {
             self.ldap_client_address_info = ldap_client_address_info
         }
 
+        if let Some(bind_session_to_ip) = config.bind_session_to_ip {
+            self.bind_session_to_ip = bind_session_to_ip
+        }
+
+        if let Some(session_ip_bind_exclude_cidrs) = config.session_ip_bind_exclude_cidrs {
+            self.session_ip_bind_exclude_cidrs = session_ip_bind_exclude_cidrs
+        }
+
         if config.online_backup.is_some() {
             self.online_backup = config.online_backup;
         }
@@ -1004,9 +1075,12 @@ impl ConfigurationBuilder {
             maximum_request,
             http_client_address_info,
             ldap_client_address_info,
+            bind_session_to_ip,
+            session_ip_bind_exclude_cidrs,
             tls_key,
             tls_chain,
             tls_client_ca,
+            tls_certificates,
             mut online_backup,
             domain,
             origin,
@@ -1016,11 +1090,24 @@ impl ConfigurationBuilder {
             otel_grpc_endpoint,
         } = self;
 
-        let tls_config = match (tls_key, tls_chain, tls_client_ca) {
-            (Some(key), Some(chain), client_ca) => Some(TlsConfiguration {
+        if tls_key.is_some() || tls_chain.is_some() {
+            eprintln!("WARNING: tls_chain and tls_key are deprecated. Please migrate to [[tls.certificates]] entries with domain, certificate_chain and private_key.");
+        }
+
+        let tls_config = match (tls_key, tls_chain, tls_certificates.first()) {
+            (Some(key), Some(chain), _) => Some(TlsConfiguration {
                 chain,
                 key,
-                client_ca,
+                client_ca: tls_client_ca,
+                certificates: tls_certificates,
+            }),
+            // Without the deprecated fields, the first certificate is the fallback for clients
+            // that don't send SNI, or that request a name we don't have.
+            (None, None, Some(first)) => Some(TlsConfiguration {
+                chain: first.certificate_chain.clone(),
+                key: first.private_key.clone(),
+                client_ca: tls_client_ca,
+                certificates: tls_certificates,
             }),
             _ => {
                 eprintln!("ERROR: Tls Private Key and Certificate Chain are required.");
@@ -1056,7 +1143,7 @@ impl ConfigurationBuilder {
 
         // Apply any defaults if needed
         let address = bindaddress.unwrap_or(vec![DEFAULT_SERVER_ADDRESS.to_string()]);
-        let role = role.unwrap_or(ServerRole::WriteReplica);
+        let role = role.unwrap_or_else(ServerRole::profile_default);
         let log_level = log_level.unwrap_or_default();
 
         Some(Configuration {
@@ -1071,6 +1158,8 @@ impl ConfigurationBuilder {
             maximum_request,
             http_client_address_info,
             ldap_client_address_info,
+            bind_session_to_ip,
+            session_ip_bind_exclude_cidrs,
             tls_config,
             online_backup,
             domain,
@@ -1087,8 +1176,12 @@ impl ConfigurationBuilder {
 
 #[cfg(test)]
 mod tests {
+    use super::{
+        Configuration, ServerConfigUntagged, ServerConfigV2, ServerConfigVersion, TlsCertificate,
+    };
     use cidr::{IpCidr, Ipv4Cidr, Ipv6Cidr};
     use std::net::{Ipv4Addr, Ipv6Addr};
+    use std::path::PathBuf;
 
     #[test]
     fn assert_cidr_parsing_behaviour() {
@@ -1112,4 +1205,84 @@ mod tests {
         );
         assert_eq!(parsed_ip_cidr, expect_ip_cidr);
     }
+
+    fn config_from_v2_toml(toml_str: &str) -> Option<Configuration> {
+        let values: ServerConfigV2 = toml::from_str(toml_str).expect("Invalid server config");
+        Configuration::build()
+            .add_opt_toml_config(Some(ServerConfigUntagged::Version(
+                ServerConfigVersion::V2 { values },
+            )))
+            .finish()
+    }
+
+    #[test]
+    fn test_tls_certificates_config() {
+        const BASE: &str = r#"
+version = "2"
+domain = "idm.example.com"
+origin = "https://idm.example.com"
+"#;
+
+        let cert_com = TlsCertificate {
+            domain: "idm.example.com".to_string(),
+            certificate_chain: PathBuf::from("/data/chain-com.pem"),
+            private_key: PathBuf::from("/data/key-com.pem"),
+        };
+        let cert_org = TlsCertificate {
+            domain: "idm.example.org".to_string(),
+            certificate_chain: PathBuf::from("/data/chain-org.pem"),
+            private_key: PathBuf::from("/data/key-org.pem"),
+        };
+
+        const CERTIFICATES: &str = r#"
+[[tls.certificates]]
+domain = "idm.example.com"
+certificate_chain = "/data/chain-com.pem"
+private_key = "/data/key-com.pem"
+
+[[tls.certificates]]
+domain = "idm.example.org"
+certificate_chain = "/data/chain-org.pem"
+private_key = "/data/key-org.pem"
+"#;
+
+        // No certificates at all is an error.
+        assert!(config_from_v2_toml(BASE).is_none());
+
+        // The deprecated single certificate still works alone.
+        let config = config_from_v2_toml(&format!(
+            "tls_chain = \"/data/chain.pem\"\ntls_key = \"/data/key.pem\"\n{BASE}"
+        ))
+        .expect("Invalid configuration");
+        let tls_config = config.tls_config.expect("Missing tls config");
+        assert_eq!(tls_config.chain, PathBuf::from("/data/chain.pem"));
+        assert_eq!(tls_config.key, PathBuf::from("/data/key.pem"));
+        assert!(tls_config.certificates.is_empty());
+
+        // Without the deprecated fields, the first certificate is the fallback.
+        let config =
+            config_from_v2_toml(&format!("{BASE}{CERTIFICATES}")).expect("Invalid configuration");
+        let tls_config = config.tls_config.expect("Missing tls config");
+        assert_eq!(tls_config.chain, cert_com.certificate_chain);
+        assert_eq!(tls_config.key, cert_com.private_key);
+        assert_eq!(
+            tls_config.certificates,
+            vec![cert_com.clone(), cert_org.clone()]
+        );
+
+        // With them, they are the fallback.
+        let config = config_from_v2_toml(&format!(
+            "tls_chain = \"/data/chain.pem\"\ntls_key = \"/data/key.pem\"\n{BASE}{CERTIFICATES}"
+        ))
+        .expect("Invalid configuration");
+        let tls_config = config.tls_config.expect("Missing tls config");
+        assert_eq!(tls_config.chain, PathBuf::from("/data/chain.pem"));
+        assert_eq!(tls_config.certificates, vec![cert_com, cert_org]);
+
+        // Unknown keys in a certificate are rejected.
+        assert!(toml::from_str::<ServerConfigV2>(&format!(
+            "{BASE}[[tls.certificates]]\ndomain = \"idm.example.com\"\nchain = \"/data/chain.pem\"\n"
+        ))
+        .is_err());
+    }
 }
@@ -21,7 +21,11 @@ use crypto_glue::{
 };
 use rustls::{
     pki_types::{pem::PemObject, CertificateDer, CertificateRevocationListDer, PrivateKeyDer},
-    server::{ServerConfig, WebPkiClientVerifier},
+    server::{
+        ClientHello, ResolvesServerCert, ResolvesServerCertUsingSni, ServerConfig,
+        WebPkiClientVerifier,
+    },
+    sign::CertifiedKey,
     RootCertStore,
 };
 use std::fs;
@@ -136,41 +140,108 @@ pub fn check_privkey_minimums(privkey: &PrivateKeyDer<'_>) -> Result<(), String>
     }
 }
 
-/// From the server configuration, generate a TLS acceptor that we can use
-/// to build our sockets for HTTPS/LDAPS.
-pub fn setup_tls(
-    tls_config: &Option<TlsConfiguration>,
-) -> Result<Option<TlsAcceptor>, std::io::Error> {
-    let Some(tls_param) = tls_config.as_ref() else {
-        return Ok(None);
-    };
-
-    let cert_iter = CertificateDer::pem_file_iter(&tls_param.chain).map_err(|err| {
+fn load_cert_chain(path: &Path) -> Result<Vec<CertificateDer<'static>>, std::io::Error> {
+    let cert_iter = CertificateDer::pem_file_iter(path).map_err(|err| {
         std::io::Error::other(format!(
-            "Failed to create TLS listener. The Certificate Chain could not be parsed: {err:?}"
+            "Failed to create TLS listener. The Certificate Chain {} could not be parsed: {err:?}",
+            path.display()
         ))
     })?;
 
-    let cert_chain_der = cert_iter
+    cert_iter
         .collect::<Result<Vec<_>, _>>()
-        .map_err(|err| std::io::Error::other(format!("Failed to create TLS listener. The Certificate Chain could not be transformed from PEM to DER: {err:?}")))?;
+        .map_err(|err| std::io::Error::other(format!("Failed to create TLS listener. The Certificate Chain {} could not be transformed from PEM to DER: {err:?}", path.display())))
+}
 
-    let private_key_der = PrivateKeyDer::from_pem_file(&tls_param.key).map_err(|err| {
+fn load_private_key(path: &Path) -> Result<PrivateKeyDer<'static>, std::io::Error> {
+    let private_key_der = PrivateKeyDer::from_pem_file(path).map_err(|err| {
         std::io::Error::other(format!(
-            "Failed to create TLS listener. The Private Key could not be parsed {err:?}"
+            "Failed to create TLS listener. The Private Key {} could not be parsed {err:?}",
+            path.display()
         ))
     })?;
 
     check_privkey_minimums(&private_key_der).map_err(|err| {
         std::io::Error::other(format!(
-            "Private Key does not meet cryptographic minimum standards: {err:?}"
+            "Private Key {} does not meet cryptographic minimum standards: {err:?}",
+            path.display()
         ))
     })?;
 
+    Ok(private_key_der)
+}
+
+/// Select a certificate by the client's SNI, falling back to the default certificate
+/// for clients that don't send SNI or request a name we have no certificate for.
+#[derive(Debug)]
+struct SniCertResolver {
+    sni: ResolvesServerCertUsingSni,
+    fallback: Arc<CertifiedKey>,
+}
+
+impl ResolvesServerCert for SniCertResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        self.sni
+            .resolve(client_hello)
+            .or_else(|| Some(self.fallback.clone()))
+    }
+}
+
+/// From the server configuration, generate a TLS acceptor that we can use
+/// to build our sockets for HTTPS/LDAPS.
+pub fn setup_tls(
+    tls_config: &Option<TlsConfiguration>,
+) -> Result<Option<TlsAcceptor>, std::io::Error> {
+    let Some(tls_param) = tls_config.as_ref() else {
+        return Ok(None);
+    };
+
+    let cert_chain_der = load_cert_chain(&tls_param.chain)?;
+    let private_key_der = load_private_key(&tls_param.key)?;
+
     // Configure the rustls cryptoprovider. We currently use aws-lc-rc as the
     // default. We may swap to rustcrypto in future.
     let provider: Arc<_> = rustls::crypto::aws_lc_rs::default_provider().into();
 
+    let cert_resolver = if tls_param.certificates.is_empty() {
+        None
+    } else {
+        let mut sni = ResolvesServerCertUsingSni::new();
+
+        for tls_cert in tls_param.certificates.iter() {
+            let certified_key = CertifiedKey::from_der(
+                load_cert_chain(&tls_cert.certificate_chain)?,
+                load_private_key(&tls_cert.private_key)?,
+                &provider,
+            )
+            .map_err(|err| {
+                std::io::Error::other(format!(
+                    "Failed to create TLS listener. The Certificate and Private Key for {} could not be loaded: {err:?}",
+                    tls_cert.domain
+                ))
+            })?;
+
+            sni.add(&tls_cert.domain, certified_key).map_err(|err| {
+                std::io::Error::other(format!(
+                    "Failed to create TLS listener. The Certificate is not valid for {}: {err:?}",
+                    tls_cert.domain
+                ))
+            })?;
+        }
+
+        let fallback = CertifiedKey::from_der(cert_chain_der.clone(), private_key_der.clone_key(), &provider)
+            .map_err(|err| {
+                std::io::Error::other(format!(
+                    "Failed to create TLS listener. The fallback Certificate and Private Key could not be loaded: {err:?}"
+                ))
+            })?;
+
+        Some(Arc::new(SniCertResolver {
+            sni,
+            fallback: Arc::new(fallback),
+        }))
+    };
+
     let client_cert_verifier = if let Some(client_ca) = tls_param.client_ca.as_ref() {
         info!(
             "Loading client CA certificates from {}",
@@ -238,9 +309,11 @@ pub fn setup_tls(
     let tls_server_config = ServerConfig::builder_with_provider(provider)
         .with_safe_default_protocol_versions()
         .and_then(|builder| {
-            builder
-                .with_client_cert_verifier(client_cert_verifier)
-                .with_single_cert(cert_chain_der, private_key_der)
+            let builder = builder.with_client_cert_verifier(client_cert_verifier);
+            match cert_resolver {
+                Some(cert_resolver) => Ok(builder.with_cert_resolver(cert_resolver)),
+                None => builder.with_single_cert(cert_chain_der, private_key_der),
+            }
         })
         .map_err(|err| std::io::Error::other(format!("Failed to create TLS listener. The TLS Server Configuration could not be built: {err:?}")))?;
 
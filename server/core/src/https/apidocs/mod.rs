@@ -32,6 +32,14 @@ impl Modify for SecurityAddon {
     }
 }
 
+struct ScimV1Addon;
+
+impl Modify for ScimV1Addon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        scim_v1::register_openapi_components(openapi);
+    }
+}
+
 // docs for the derive macro are here: <https://docs.rs/utoipa-gen/3.5.0/utoipa_gen/derive.OpenApi.html#info-attribute-syntax>
 #[derive(OpenApi)]
 #[openapi(
@@ -80,6 +88,7 @@ impl Modify for SecurityAddon {
         super::v1_scim::scim_entry_put,
         super::v1_scim::scim_entry_id_get,
         super::v1_scim::scim_entry_id_delete,
+        super::v1_scim::scim_person_get,
         super::v1_scim::scim_person_id_get,
         super::v1_scim::scim_person_id_application_create_password,
         super::v1_scim::scim_person_id_application_delete_password,
@@ -122,6 +131,7 @@ impl Modify for SecurityAddon {
         super::v1::person_get_id_credential_status,
         super::v1::person_id_credential_update_get,
         super::v1::person_id_credential_update_intent_get,
+        super::v1::person_id_credential_update_intent_post,
         super::v1::person_id_credential_update_intent_send_post,
         super::v1::person_id_credential_update_intent_ttl_get,
 
@@ -170,12 +180,16 @@ impl Modify for SecurityAddon {
         // super::v1::account_id_ssh_pubkeys_get,
         super::v1::account_id_ssh_pubkeys_tag_get,
         super::v1::account_id_user_auth_token_get,
+        super::v1::account_id_certificate_get,
+        super::v1::account_id_certificate_post,
+        super::v1::account_id_certificate_delete,
         super::v1::account_user_auth_token_delete,
         super::v1::credential_update_exchange_intent,
         super::v1::credential_update_status,
         super::v1::credential_update_update,
         super::v1::credential_update_commit,
         super::v1::credential_update_cancel,
+        super::v1::credential_update_totp_qr_get,
         super::v1::domain_get,
         super::v1::domain_attr_get,
         super::v1::domain_attr_put,
@@ -184,6 +198,7 @@ impl Modify for SecurityAddon {
         super::v1_domain::image_delete,
 
         super::v1::group_id_unix_token_get,
+        super::v1::unix_changes_get,
         super::v1::group_id_unix_post,
         super::v1::group_get,
         super::v1::group_post,
@@ -197,6 +212,8 @@ impl Modify for SecurityAddon {
         super::v1::group_id_attr_post,
         super::v1::system_get,
         super::v1::system_attr_get,
+        super::v1::system_api_token_get,
+        super::v1::stats_entry_counts_get,
         super::v1::system_attr_post,
         super::v1::system_attr_put,
         super::v1::system_attr_delete,
@@ -238,6 +255,9 @@ impl Modify for SecurityAddon {
 
             internal::ApiToken,
             internal::ApiTokenPurpose,
+            internal::ApiTokenSummary,
+            internal::CertificateSummary,
+            internal::ApiTokenListQuery,
             internal::BackupCodesView,
             internal::ConsistencyError,
             internal::CreateRequest,
@@ -245,6 +265,8 @@ impl Modify for SecurityAddon {
             internal::CredentialDetailType,
             internal::CredentialStatus,
             internal::CUExtPortal,
+            internal::CUIntentCreate,
+            internal::CUIntentPermission,
             internal::CUIntentToken,
             internal::CURegState,
             internal::CUSessionToken,
@@ -289,6 +311,8 @@ impl Modify for SecurityAddon {
             v1::UatPurposeStatus,
             v1::UatStatus,
             v1::UatStatusState,
+            v1::UnixChanges,
+            v1::UnixChangesQuery,
             v1::UnixGroupToken,
             v1::UnixUserToken,
             v1::WhoamiResponse,
@@ -321,7 +345,7 @@ impl Modify for SecurityAddon {
             WebError,
         )
     ),
-    modifiers(&SecurityAddon),
+    modifiers(&SecurityAddon, &ScimV1Addon),
     tags(
         (name = "kanidm", description = "Kanidm API")
     ),
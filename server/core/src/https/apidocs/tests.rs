@@ -121,3 +121,18 @@ fn figure_out_if_we_have_all_the_routes() {
         }
     }
 }
+
+#[test]
+fn scim_v1_schemas_are_registered() {
+    use utoipa::OpenApi;
+
+    let openapi = super::ApiDoc::openapi();
+    let schemas = &openapi
+        .components
+        .expect("No components in the API docs")
+        .schemas;
+
+    for name in ["ScimEntryGetQuery", "ScimReference", "ScimSshPublicKey"] {
+        assert!(schemas.contains_key(name), "missing schema {name}");
+    }
+}
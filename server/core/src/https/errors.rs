@@ -107,6 +107,7 @@ impl IntoResponse for WebError {
                     }
 
                     OperationError::NoMatchingEntries => (StatusCode::NOT_FOUND, None),
+                    OperationError::EntryAlreadyExists => (StatusCode::CONFLICT, None),
                     OperationError::PasswordQuality(_)
                     | OperationError::EmptyRequest
                     | OperationError::InvalidAttribute(_)
@@ -118,7 +119,19 @@ impl IntoResponse for WebError {
                     }
                     _ => (StatusCode::INTERNAL_SERVER_ERROR, None),
                 };
-                let body = serde_json::to_string(&inner).unwrap_or(inner.to_string());
+                let body = match &inner {
+                    // Schema violations carry a hint alongside the error on how to resolve them.
+                    OperationError::SchemaViolation(schema_err) => {
+                        match (schema_err.resolution_hint(), serde_json::to_value(&inner)) {
+                            (Some(hint), Ok(serde_json::Value::Object(mut object))) => {
+                                object.insert("hint".to_string(), hint.into());
+                                serde_json::Value::Object(object).to_string()
+                            }
+                            _ => serde_json::to_string(&inner).unwrap_or(inner.to_string()),
+                        }
+                    }
+                    _ => serde_json::to_string(&inner).unwrap_or(inner.to_string()),
+                };
                 debug!(?body);
 
                 match headers {
@@ -144,4 +157,30 @@ mod tests {
         let response = err.into_response();
         assert_eq!(response.status(), StatusCode::CONFLICT);
     }
+
+    #[tokio::test]
+    async fn test_schema_violation_error_response_hint() {
+        use super::*;
+        use axum::response::IntoResponse;
+        use kanidm_proto::internal::SchemaError;
+
+        let err = WebError::OperationError(OperationError::SchemaViolation(
+            SchemaError::InvalidAttributeSyntax("mail".to_string()),
+        ));
+        let response = err.into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Failed to read body");
+        let body: serde_json::Value = serde_json::from_slice(&body).expect("Invalid json");
+        assert_eq!(
+            body["schemaviolation"]["invalidattributesyntax"],
+            serde_json::json!("mail")
+        );
+        assert_eq!(
+            body["hint"].as_str(),
+            SchemaError::InvalidAttributeSyntax("mail".to_string()).resolution_hint()
+        );
+    }
 }
@@ -6,7 +6,8 @@ use super::middleware::KOpId;
 use super::ServerState;
 use crate::https::apidocs::response_schema::{ApiResponseWithout200, DefaultApiResponse};
 use crate::https::extractors::{ClientConnInfo, VerifiedClientInformation};
-use axum::extract::{Path, State};
+use axum::extract::{Path, Query, State};
+use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE};
 use axum::http::{HeaderMap, HeaderValue};
 use axum::middleware::from_fn;
 use axum::response::{IntoResponse, Response};
@@ -15,21 +16,25 @@ use axum::{Extension, Json, Router};
 use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
 use compact_jwt::{Jwk, Jws, JwsSigner};
 use kanidm_proto::constants::uri::V1_AUTH_VALID;
+use kanidm_proto::constants::KCUSESSIONTOKEN;
 use kanidm_proto::internal::{
-    ApiToken, AppLink, CUIntentSend, CUIntentToken, CURequest, CUSessionToken, CUStatus,
-    CreateRequest, CredentialStatus, DeleteRequest, IdentifyUserRequest, IdentifyUserResponse,
-    ModifyRequest, RadiusAuthToken, SearchRequest, SearchResponse, UserAuthToken,
-    COOKIE_AUTH_SESSION_ID, COOKIE_BEARER_TOKEN,
+    ApiToken, ApiTokenListQuery, ApiTokenSummary, AppLink, CUIntentCreate, CUIntentSend,
+    CUIntentToken, CURequest, CUSessionToken, CUStatus, CertificateSummary, CreateRequest,
+    CredentialStatus, DeleteRequest, IdentifyUserRequest, IdentifyUserResponse, ModifyRequest,
+    RadiusAuthToken, SearchRequest, SearchResponse, UserAuthToken, COOKIE_AUTH_SESSION_ID,
+    COOKIE_BEARER_TOKEN, COOKIE_CU_SESSION_TOKEN,
 };
 use kanidm_proto::v1::{
     AccountUnixExtend, ApiTokenGenerate, AuthIssueSession, AuthRequest, AuthResponse,
     AuthState as ProtoAuthState, Entry as ProtoEntry, GroupUnixExtend, SingleStringRequest,
-    UatStatus, UnixGroupToken, UnixUserToken, WhoamiResponse,
+    UatStatus, UnixChanges, UnixChangesQuery, UnixGroupToken, UnixUserToken, WhoamiResponse,
 };
 use kanidmd_lib::idm::authentication::{AuthState, AuthStep, ReauthRequest};
 use kanidmd_lib::idm::event::AuthResult;
 use kanidmd_lib::prelude::*;
 use kanidmd_lib::value::PartialValue;
+use opentelemetry::KeyValue;
+use std::collections::BTreeMap;
 use std::net::IpAddr;
 use uuid::Uuid;
 
@@ -734,7 +739,7 @@ pub async fn person_post_id_certificate(
     Json(mut obj): Json<ProtoEntry>,
 ) -> Result<Json<()>, WebError> {
     let classes: Vec<String> = vec![
-        EntryClass::ClientCertificate.into(),
+        EntryClass::IdentityProviderCertificate.into(),
         EntryClass::Object.into(),
     ];
     obj.attrs.insert(Attribute::Refers.to_string(), vec![id]);
@@ -1245,6 +1250,7 @@ pub async fn person_id_credential_update_intent_ttl_get(
             client_auth_info,
             id,
             Some(Duration::from_secs(ttl)),
+            None,
             kopid.eventid,
         )
         .await
@@ -1271,7 +1277,40 @@ pub async fn person_id_credential_update_intent_get(
 ) -> Result<Json<CUIntentToken>, WebError> {
     state
         .qe_w_ref
-        .handle_idmcredentialupdateintent(client_auth_info, id, None, kopid.eventid)
+        .handle_idmcredentialupdateintent(client_auth_info, id, None, None, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/person/{id}/_credential/_update_intent",
+    request_body=CUIntentCreate,
+    responses(
+        (status=200, body=CUIntentToken),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "person/credential",
+)]
+#[instrument(level = "trace", skip(state, kopid))]
+pub async fn person_id_credential_update_intent_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Path(id): Path<String>,
+    Json(cu_intent_create): Json<CUIntentCreate>,
+) -> Result<Json<CUIntentToken>, WebError> {
+    state
+        .qe_w_ref
+        .handle_idmcredentialupdateintent(
+            client_auth_info,
+            id,
+            cu_intent_create.ttl.map(Duration::from_secs),
+            cu_intent_create.permissions,
+            kopid.eventid,
+        )
         .await
         .map(Json::from)
         .map_err(WebError::from)
@@ -1356,6 +1395,87 @@ pub async fn account_user_auth_token_delete(
         .map_err(WebError::from)
 }
 
+#[utoipa::path(
+    get,
+    path = "/v1/account/{id}/_certificate",
+    responses(
+        (status=200, body=Vec<CertificateSummary>, content_type=APPLICATION_JSON),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "account",
+    operation_id = "account_id_certificate_get",
+)]
+/// List the certificates held by an account, decoded for display.
+pub async fn account_id_certificate_get(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<Vec<CertificateSummary>>, WebError> {
+    state
+        .qe_r_ref
+        .handle_account_certificate_list(client_auth_info, id, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/account/{id}/_certificate",
+    responses(
+        DefaultApiResponse,
+    ),
+    request_body=ProtoEntry,
+    security(("token_jwt" = [])),
+    tag = "account",
+    operation_id = "account_id_certificate_post",
+)]
+/// Expects the following fields in the attrs field of the req: [idp_certificate]
+///
+/// The account's id will be added implicitly as a reference.
+pub async fn account_id_certificate_post(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(mut obj): Json<ProtoEntry>,
+) -> Result<Json<()>, WebError> {
+    let classes: Vec<String> = vec![
+        EntryClass::IdentityProviderCertificate.into(),
+        EntryClass::Object.into(),
+    ];
+    obj.attrs.insert(Attribute::Refers.to_string(), vec![id]);
+
+    json_rest_event_post(state, classes, obj, kopid, client_auth_info).await
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/account/{id}/_certificate/{fingerprint}",
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "account",
+    operation_id = "account_id_certificate_delete",
+)]
+/// Remove the certificate held by an account with the given sha256 fingerprint.
+pub async fn account_id_certificate_delete(
+    State(state): State<ServerState>,
+    Path((id, fingerprint)): Path<(String, String)>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<()>, WebError> {
+    state
+        .qe_w_ref
+        .handle_account_certificate_remove(client_auth_info, id, fingerprint, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
 #[utoipa::path(
     post,
     path = "/v1/credential/_exchange_intent",
@@ -1502,6 +1622,94 @@ pub async fn credential_update_cancel(
         .map_err(WebError::from)
 }
 
+/// The size in pixels of each module of a TOTP QR code.
+const TOTP_QR_MODULE_PIXELS: usize = 8;
+/// The width in modules of the blank border around a TOTP QR code.
+const TOTP_QR_QUIET_ZONE: usize = 4;
+
+/// Render the otpauth uri of a TOTP secret as a greyscale PNG QR code.
+fn totp_qr_png(uri: &str) -> Result<Vec<u8>, WebError> {
+    let qr = qrcode::QrCode::new(uri).map_err(|err| {
+        error!(?err, "Failed to create TOTP QR code");
+        WebError::InternalServerError("QR Code Generation Failed".to_string())
+    })?;
+
+    let modules = qr.width();
+    let colors = qr.to_colors();
+    let size = (modules + 2 * TOTP_QR_QUIET_ZONE) * TOTP_QR_MODULE_PIXELS;
+
+    let mut pixels = vec![u8::MAX; size * size];
+    for (idx, color) in colors.iter().enumerate() {
+        if *color != qrcode::Color::Dark {
+            continue;
+        }
+        let x = (idx % modules + TOTP_QR_QUIET_ZONE) * TOTP_QR_MODULE_PIXELS;
+        let y = (idx / modules + TOTP_QR_QUIET_ZONE) * TOTP_QR_MODULE_PIXELS;
+        for row in pixels
+            .chunks_exact_mut(size)
+            .skip(y)
+            .take(TOTP_QR_MODULE_PIXELS)
+        {
+            row.iter_mut()
+                .skip(x)
+                .take(TOTP_QR_MODULE_PIXELS)
+                .for_each(|px| *px = 0);
+        }
+    }
+
+    lodepng::encode_memory(&pixels, size, size, lodepng::ColorType::GREY, 8).map_err(|err| {
+        error!(?err, "Failed to encode TOTP QR code");
+        WebError::InternalServerError("QR Code Generation Failed".to_string())
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/credential/{cred_uuid}/totp/{totp_label}/qr",
+    params(
+        ("cred_uuid" = Uuid, description="The primary credential the TOTP is being added to"),
+        ("totp_label" = String, description="The label the TOTP will be saved under"),
+    ),
+    responses(
+        (status=200, content_type="image/png", description="A QR code of the otpauth uri of the pending TOTP"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "credential",
+)]
+/// Get a QR code of the TOTP that is awaiting verification in a credential update
+/// session, for enrolment in an authenticator app. The session token is read from
+/// the `X-KANIDM-CU-SESSION-TOKEN` header, or the credential update session cookie.
+pub async fn credential_update_totp_qr_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    Path((cred_uuid, totp_label)): Path<(Uuid, String)>,
+    headers: HeaderMap,
+    jar: CookieJar,
+) -> Result<Response, WebError> {
+    let session_token = headers
+        .get(KCUSESSIONTOKEN)
+        .and_then(|hv| hv.to_str().ok())
+        .or_else(|| jar.get(COOKIE_CU_SESSION_TOKEN).map(|c| c.value()))
+        .map(|token| CUSessionToken {
+            token: token.to_string(),
+        })
+        .ok_or(WebError::OperationError(OperationError::NotAuthenticated))?;
+
+    let secret = state
+        .qe_r_ref
+        .handle_idmcredentialupdatependingtotp(session_token, cred_uuid, totp_label, kopid.eventid)
+        .await?;
+
+    let png = totp_qr_png(secret.to_uri().as_str())?;
+
+    Ok((
+        [(CONTENT_TYPE, "image/png"), (CACHE_CONTROL, "no-store")],
+        png,
+    )
+        .into_response())
+}
+
 #[utoipa::path(
     get,
     path = "/v1/service_account/{id}/_credential/_status",
@@ -2474,6 +2682,36 @@ pub async fn group_id_unix_token_get(
         .map_err(WebError::from)
 }
 
+#[utoipa::path(
+    get,
+    path = "/v1/unix/_changes",
+    params(
+        ("wait" = Option<u64>, Query, description = "The number of seconds to wait for a change"),
+    ),
+    responses(
+        (status=200, body=UnixChanges, content_type=APPLICATION_JSON),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "group/unix",
+    operation_id = "unix_changes_get",
+)]
+/// Wait for posix accounts or groups to change, so that unix clients can refresh them
+/// from their caches. This returns an empty set of changes if nothing changed in time.
+pub async fn unix_changes_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Query(query): Query<UnixChangesQuery>,
+) -> Result<Json<UnixChanges>, WebError> {
+    state
+        .qe_r_ref
+        .handle_unix_changes_wait(client_auth_info, query.wait, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
 #[utoipa::path(
     get,
     path = "/v1/domain",
@@ -2609,6 +2847,69 @@ pub async fn system_get(
     json_rest_event_get(state, None, filter, kopid, client_auth_info).await
 }
 
+#[utoipa::path(
+    get,
+    path = "/v1/system/_api_token",
+    params(
+        ("account" = Option<String>, Query, description = "Only list the api tokens of this account"),
+    ),
+    responses(
+        (status=200, body=Vec<ApiTokenSummary>, content_type=APPLICATION_JSON),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "system",
+    operation_id = "system_api_token_get",
+)]
+/// List the api tokens held by all accounts, optionally limited to a single account.
+pub async fn system_api_token_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Query(query): Query<ApiTokenListQuery>,
+) -> Result<Json<Vec<ApiTokenSummary>>, WebError> {
+    state
+        .qe_r_ref
+        .handle_system_api_token_list(client_auth_info, query.account, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/stats/entry_counts",
+    responses(
+        (status=200, body=BTreeMap<String, u64>, content_type=APPLICATION_JSON),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "system",
+    operation_id = "stats_entry_counts_get",
+)]
+/// Count the live entries of common classes such as person, group and service_account.
+/// The counts are also recorded to the `kanidm_entry_count` metrics gauge.
+pub async fn stats_entry_counts_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<BTreeMap<String, u64>>, WebError> {
+    let counts = state
+        .qe_r_ref
+        .handle_entry_counts(client_auth_info, kopid.eventid)
+        .await?;
+
+    let gauge = opentelemetry::global::meter("kanidm")
+        .u64_gauge("kanidm_entry_count")
+        .with_description("Number of live entries of a class")
+        .build();
+    for (class, count) in counts.iter() {
+        gauge.record(*count, &[KeyValue::new("class", class.clone())]);
+    }
+
+    Ok(Json(counts))
+}
+
 #[utoipa::path(
     get,
     path = "/v1/system/_attr/{attr}",
@@ -3256,7 +3557,8 @@ pub(crate) fn route_setup(state: ServerState) -> Router<ServerState> {
         )
         .route(
             "/v1/person/{id}/_credential/_update_intent",
-            get(person_id_credential_update_intent_get),
+            get(person_id_credential_update_intent_get)
+                .post(person_id_credential_update_intent_post),
         )
         .route(
             "/v1/person/{id}/_credential/_update_intent_send",
@@ -3372,6 +3674,14 @@ pub(crate) fn route_setup(state: ServerState) -> Router<ServerState> {
             "/v1/account/{id}/_user_auth_token",
             get(account_id_user_auth_token_get),
         )
+        .route(
+            "/v1/account/{id}/_certificate",
+            get(account_id_certificate_get).post(account_id_certificate_post),
+        )
+        .route(
+            "/v1/account/{id}/_certificate/{fingerprint}",
+            delete(account_id_certificate_delete),
+        )
         .route(
             "/v1/account/{id}/_user_auth_token/{token_id}",
             delete(account_user_auth_token_delete),
@@ -3384,6 +3694,10 @@ pub(crate) fn route_setup(state: ServerState) -> Router<ServerState> {
         .route("/v1/credential/_update", post(credential_update_update))
         .route("/v1/credential/_commit", post(credential_update_commit))
         .route("/v1/credential/_cancel", post(credential_update_cancel))
+        .route(
+            "/v1/credential/{cred_uuid}/totp/{totp_label}/qr",
+            get(credential_update_totp_qr_get),
+        )
         // domain-things
         .route("/v1/domain", get(domain_get))
         .route(
@@ -3398,6 +3712,7 @@ pub(crate) fn route_setup(state: ServerState) -> Router<ServerState> {
         )
         .route("/v1/group/{id}/_unix/_token", get(group_id_unix_token_get))
         .route("/v1/group/{id}/_unix", post(group_id_unix_post))
+        .route("/v1/unix/_changes", get(unix_changes_get))
         .route("/v1/group", get(group_get).post(group_post))
         .route("/v1/group/_search/{id}", get(group_search_id))
         .route(
@@ -3415,6 +3730,8 @@ pub(crate) fn route_setup(state: ServerState) -> Router<ServerState> {
         )
         .with_state(state.clone())
         .route("/v1/system", get(system_get))
+        .route("/v1/system/_api_token", get(system_api_token_get))
+        .route("/v1/stats/entry_counts", get(stats_entry_counts_get))
         .route(
             "/v1/system/_attr/{attr}",
             get(system_attr_get)
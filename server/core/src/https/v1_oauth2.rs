@@ -287,7 +287,7 @@ pub(crate) async fn oauth2_id_scopemap_delete(
 #[utoipa::path(
     post,
     path = "/v1/oauth2/{rs_name}/_claimmap/{claim_name}/{group}",
-    request_body=Vec<String>,
+    request_body=Vec<Object>,
     responses(
         DefaultApiResponse,
     ),
@@ -295,13 +295,14 @@ pub(crate) async fn oauth2_id_scopemap_delete(
     tag = "oauth2",
     operation_id = "oauth2_id_claimmap_post"
 )]
-/// Modify the claim map for a given OAuth2 Resource Server
+/// Modify the claim map for a given OAuth2 Resource Server. Claim values may be strings,
+/// or other json types such as booleans, numbers or objects.
 pub(crate) async fn oauth2_id_claimmap_post(
     State(state): State<ServerState>,
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
     Path((rs_name, claim_name, group)): Path<(String, String, String)>,
-    Json(claims): Json<Vec<String>>,
+    Json(claims): Json<Vec<JsonValue>>,
 ) -> Result<Json<()>, WebError> {
     let filter = oauth2_id(&rs_name);
     state
@@ -8,6 +8,7 @@ use super::v1::{
 use super::ServerState;
 use crate::https::extractors::VerifiedClientInformation;
 use axum::extract::{rejection::JsonRejection, DefaultBodyLimit, Path, Query, State};
+use axum::http::header::ETAG;
 use axum::response::{Html, IntoResponse, Response};
 use axum::routing::{delete, get, post};
 use axum::{Extension, Json, Router};
@@ -23,6 +24,23 @@ use kanidmd_lib::prelude::*;
 
 const DEFAULT_SCIM_SYNC_BYTES: usize = 1024 * 1024 * 32;
 
+/// Respond with a SCIM entry, sending its version as the ETag so that clients can
+/// detect changes to it. The location of the entry is set from our origin.
+fn scim_entry_response(origin: &Url, mut entry: ScimEntryKanidm) -> Response {
+    let id = entry.header.id;
+    if let Some(meta) = entry.header.meta.as_mut() {
+        match origin.join(&format!("/scim/v1/Entry/{id}")) {
+            Ok(location) => meta.location = location,
+            Err(err) => warn!(?err, "Unable to build the location of a scim entry"),
+        }
+    }
+
+    match entry.version().map(|version| format!("\"{version}\"")) {
+        Some(etag) => ([(ETAG, etag)], Json(entry)).into_response(),
+        None => Json(entry).into_response(),
+    }
+}
+
 #[utoipa::path(
     get,
     path = "/v1/sync_account",
@@ -369,12 +387,12 @@ async fn scim_entry_post(
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
     Json(post_generic): Json<ScimEntryPostGeneric>,
-) -> Result<Json<ScimEntryKanidm>, WebError> {
+) -> Result<Response, WebError> {
     state
         .qe_w_ref
         .scim_entry_create(client_auth_info, kopid.eventid, &[], post_generic)
         .await
-        .map(Json::from)
+        .map(|entry| scim_entry_response(&state.origin, entry))
         .map_err(WebError::from)
 }
 
@@ -394,12 +412,12 @@ async fn scim_entry_put(
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
     Json(put_generic): Json<ScimEntryPutGeneric>,
-) -> Result<Json<ScimEntryKanidm>, WebError> {
+) -> Result<Response, WebError> {
     state
         .qe_w_ref
         .handle_scim_entry_put(client_auth_info, kopid.eventid, put_generic)
         .await
-        .map(Json::from)
+        .map(|entry| scim_entry_response(&state.origin, entry))
         .map_err(WebError::from)
 }
 
@@ -445,7 +463,7 @@ async fn scim_entry_id_get(
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
     Query(scim_entry_get_query): Query<ScimEntryGetQuery>,
-) -> Result<Json<ScimEntryKanidm>, WebError> {
+) -> Result<Response, WebError> {
     state
         .qe_r_ref
         .scim_entry_id_get(
@@ -456,6 +474,36 @@ async fn scim_entry_id_get(
             scim_entry_get_query,
         )
         .await
+        .map(|entry| scim_entry_response(&state.origin, entry))
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    get,
+    path = "/scim/v1/Person",
+    responses(
+        (status = 200, content_type=APPLICATION_JSON, body=ScimListResponse),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "scim",
+    operation_id = "scim_person_get"
+)]
+async fn scim_person_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Query(scim_entry_get_query): Query<ScimEntryGetQuery>,
+) -> Result<Json<ScimListResponse>, WebError> {
+    state
+        .qe_r_ref
+        .scim_entry_search(
+            client_auth_info,
+            kopid.eventid,
+            EntryClass::Person.into(),
+            scim_entry_get_query,
+        )
+        .await
         .map(Json::from)
         .map_err(WebError::from)
 }
@@ -477,7 +525,7 @@ async fn scim_person_id_get(
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
     Query(scim_entry_get_query): Query<ScimEntryGetQuery>,
-) -> Result<Json<ScimEntryKanidm>, WebError> {
+) -> Result<Response, WebError> {
     state
         .qe_r_ref
         .scim_entry_id_get(
@@ -488,7 +536,7 @@ async fn scim_person_id_get(
             scim_entry_get_query,
         )
         .await
-        .map(Json::from)
+        .map(|entry| scim_entry_response(&state.origin, entry))
         .map_err(WebError::from)
 }
 
@@ -590,7 +638,7 @@ async fn scim_application_post(
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
     Json(entry_post): Json<ScimEntryPostGeneric>,
-) -> Result<Json<ScimEntryKanidm>, WebError> {
+) -> Result<Response, WebError> {
     state
         .qe_w_ref
         .scim_entry_create(
@@ -604,7 +652,7 @@ async fn scim_application_post(
             entry_post,
         )
         .await
-        .map(Json::from)
+        .map(|entry| scim_entry_response(&state.origin, entry))
         .map_err(WebError::from)
 }
 
@@ -624,7 +672,7 @@ async fn scim_application_id_get(
     Path(id): Path<String>,
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
-) -> Result<Json<ScimEntryKanidm>, WebError> {
+) -> Result<Response, WebError> {
     state
         .qe_r_ref
         .scim_entry_id_get(
@@ -635,7 +683,7 @@ async fn scim_application_id_get(
             ScimEntryGetQuery::default(),
         )
         .await
-        .map(Json::from)
+        .map(|entry| scim_entry_response(&state.origin, entry))
         .map_err(WebError::from)
 }
 
@@ -771,7 +819,7 @@ async fn scim_message_id_get(
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
     Query(scim_entry_get_query): Query<ScimEntryGetQuery>,
-) -> Result<Json<ScimEntryKanidm>, WebError> {
+) -> Result<Response, WebError> {
     state
         .qe_r_ref
         .scim_entry_id_get(
@@ -782,7 +830,7 @@ async fn scim_message_id_get(
             scim_entry_get_query,
         )
         .await
-        .map(Json::from)
+        .map(|entry| scim_entry_response(&state.origin, entry))
         .map_err(WebError::from)
 }
 
@@ -962,6 +1010,9 @@ pub fn route_setup() -> Router<ServerState> {
             "/scim/v1/Entry/{id}",
             get(scim_entry_id_get).delete(scim_entry_id_delete),
         )
+        //  Person   /Person          GET                    List or query persons
+        //
+        .route("/scim/v1/Person", get(scim_person_get))
         //  Person   /Person/{id}     GET                    Retrieve a a person from the
         //                                                   database.
         //                                                   {id} is any unique id.
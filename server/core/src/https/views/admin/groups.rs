@@ -289,6 +289,7 @@ pub(crate) async fn add_member(
         start_index: None,
         count: None,
         filter: None,
+        if_none_match: None,
     };
     let get_member_query = ScimEntryGetQuery {
         attributes: Some(vec![Attribute::Spn]),
@@ -298,6 +299,7 @@ pub(crate) async fn add_member(
         start_index: None,
         count: None,
         filter: None,
+        if_none_match: None,
     };
     let group_uuid_str = String::from(group_uuid);
     let group_before = state
@@ -22,7 +22,7 @@ use kanidmd_lib::constants::EntryClass;
 use kanidmd_lib::idm::authentication::ClientAuthInfo;
 use uuid::Uuid;
 
-pub const PERSON_ATTRIBUTES: [Attribute; 9] = [
+pub const PERSON_ATTRIBUTES: [Attribute; 11] = [
     Attribute::Uuid,
     Attribute::Description,
     Attribute::Name,
@@ -32,6 +32,8 @@ pub const PERSON_ATTRIBUTES: [Attribute; 9] = [
     Attribute::Class,
     Attribute::EntryManagedBy,
     Attribute::DirectMemberOf,
+    Attribute::AccountValidFrom,
+    Attribute::AccountExpire,
 ];
 
 #[derive(Template, WebTemplate)]
@@ -73,6 +73,7 @@ pub(crate) async fn view_enrol_get(
             client_auth_info.clone(),
             spn,
             Some(Duration::from_secs(900)),
+            None,
             kopid.eventid,
         )
         .await
@@ -17,7 +17,8 @@ use crate::CoreAction;
 use crate::actors::{QueryServerReadV1, QueryServerWriteV1};
 use kanidmd_lib::constants::PURGE_FREQUENCY;
 use kanidmd_lib::event::{
-    OnlineBackupEvent, PurgeDeleteAfterEvent, PurgeRecycledEvent, PurgeTombstoneEvent,
+    OnlineBackupEvent, PurgeDeleteAfterEvent, PurgeRecycledEvent, PurgeRevokedSessionsEvent,
+    PurgeTombstoneEvent,
 };
 
 pub(crate) struct IntervalActor;
@@ -41,6 +42,9 @@ impl IntervalActor {
                 server
                     .handle_purge_delete_after_event(PurgeDeleteAfterEvent::new())
                     .await;
+                server
+                    .handle_purge_revoked_sessions_event(PurgeRevokedSessionsEvent::new())
+                    .await;
 
                 tokio::select! {
                     Ok(action) = rx.recv() => {
@@ -53,10 +53,15 @@ use kanidm_proto::internal::OperationError;
 use kanidm_proto::scim_v1::client::ScimAssertGeneric;
 use kanidmd_lib::be::{Backend, BackendConfig, BackendTransaction};
 use kanidmd_lib::idm::ldap::LdapServer;
+use kanidmd_lib::idm::server::SessionIpBinding;
 use kanidmd_lib::prelude::*;
 use kanidmd_lib::schema::Schema;
 use kanidmd_lib::status::StatusActor;
 use kanidmd_lib::value::CredentialType;
+use object_store::aws::{AmazonS3, AmazonS3Builder};
+use object_store::buffered::BufWriter;
+use object_store::path::Path as ObjectPath;
+use object_store::ObjectStore;
 use regex::Regex;
 use sketching::LoggerType;
 use std::collections::BTreeSet;
@@ -67,6 +72,7 @@ use std::sync::Arc;
 use std::sync::LazyLock;
 use tokio::sync::broadcast;
 use tokio::task;
+use tokio_util::io::SyncIoBridge;
 
 #[cfg(not(target_family = "windows"))]
 use libc::umask;
@@ -126,7 +132,7 @@ async fn setup_qs_idms(
 
     // We generate a SINGLE idms only!
     let is_integration_test = config.integration_test_config.is_some();
-    let (idms, idms_delayed, idms_audit) = IdmServer::new(
+    let (mut idms, idms_delayed, idms_audit) = IdmServer::new(
         query_server.clone(),
         &config.origin,
         is_integration_test,
@@ -134,6 +140,16 @@ async fn setup_qs_idms(
     )
     .await?;
 
+    if config.bind_session_to_ip {
+        idms.set_session_ip_binding(Some(SessionIpBinding {
+            exclude_cidrs: config.session_ip_bind_exclude_cidrs.clone(),
+        }));
+    }
+
+    if config.role == ServerRole::ReadOnlyReplica {
+        idms.set_record_session_last_seen(false);
+    }
+
     Ok((query_server, idms, idms_delayed, idms_audit))
 }
 
@@ -275,6 +291,44 @@ pub fn dbscan_get_id2entry_core(config: &Configuration, id: u64) {
     };
 }
 
+pub fn dbscan_dump_raw_core(config: &Configuration, uuid: &str) {
+    let uuid = match Uuid::parse_str(uuid) {
+        Ok(uuid) => uuid,
+        Err(err) => {
+            error!(?err, "Invalid uuid");
+            return;
+        }
+    };
+
+    let be = dbscan_setup_be!(config);
+    let mut be_rotxn = match be.read() {
+        Ok(txn) => txn,
+        Err(err) => {
+            error!(?err, "Unable to proceed, backend read transaction failure.");
+            return;
+        }
+    };
+
+    match be_rotxn.get_id2entry_raw(uuid) {
+        Ok(dump) => {
+            println!("id: {}", dump.id);
+            println!("raw: {}", hex::encode(&dump.data));
+            for attr_dump in dump.attrs.iter() {
+                println!();
+                println!("{}:", attr_dump.attr);
+                println!("raw: {}", hex::encode(&attr_dump.data));
+                match &attr_dump.decoded {
+                    Ok(valueset) => println!("decoded: {valueset}"),
+                    Err(err) => println!("decode failed: {err}"),
+                }
+            }
+        }
+        Err(e) => {
+            error!("Failed to retrieve raw id2entry value: {:?}", e);
+        }
+    };
+}
+
 pub fn dbscan_quarantine_id2entry_core(config: &Configuration, id: u64) {
     let be = dbscan_setup_be!(config);
     let mut be_wrtxn = match be.write() {
@@ -350,37 +404,36 @@ pub fn dbscan_restore_quarantined_core(config: &Configuration, id: u64) {
     };
 }
 
-pub fn backup_server_core(config: &Configuration, dst_path: Option<&Path>) {
-    let schema = match Schema::new() {
-        Ok(s) => s,
-        Err(e) => {
-            error!("Failed to setup in memory schema: {:?}", e);
-            std::process::exit(1);
-        }
-    };
+/// Write a backup of the database content to `output`.
+fn backup_server_write<W: std::io::Write>(
+    config: &Configuration,
+    output: W,
+) -> Result<(), OperationError> {
+    let schema = Schema::new().inspect_err(|err| {
+        error!(?err, "Failed to setup in memory schema");
+    })?;
 
-    let be = match setup_backend(config, &schema) {
-        Ok(be) => be,
-        Err(e) => {
-            error!("Failed to setup BE: {:?}", e);
-            return;
-        }
-    };
+    let be = setup_backend(config, &schema).inspect_err(|err| {
+        error!(?err, "Failed to setup BE");
+    })?;
 
-    let mut be_ro_txn = match be.read() {
-        Ok(txn) => txn,
-        Err(err) => {
-            error!(?err, "Unable to proceed, backend read transaction failure.");
-            return;
-        }
-    };
+    let mut be_ro_txn = be.read().inspect_err(|err| {
+        error!(?err, "Unable to proceed, backend read transaction failure.");
+    })?;
 
     let compression = match config.online_backup.as_ref() {
         Some(backup_config) => backup_config.compression,
         None => BackupCompression::default(),
     };
 
-    if let Some(dst_path) = dst_path {
+    be_ro_txn.backup(output, compression).inspect_err(|err| {
+        error!(?err, "Backup failed");
+    })
+    // Let the txn abort, even on success.
+}
+
+pub fn backup_server_core(config: &Configuration, dst_path: Option<&Path>) {
+    let result = if let Some(dst_path) = dst_path {
         if dst_path.exists() {
             error!(
                 "backup file {} already exists, will not overwrite it.",
@@ -397,26 +450,115 @@ pub fn backup_server_core(config: &Configuration, dst_path: Option<&Path>) {
             }
         };
 
-        match be_ro_txn.backup(output, compression) {
-            Ok(_) => info!("Backup success!"),
-            Err(e) => {
-                error!("Backup failed: {:?}", e);
-                std::process::exit(1);
-            }
-        };
+        backup_server_write(config, output)
     } else {
         // No path set, default to stdout
-        let stdout = std::io::stdout().lock();
+        backup_server_write(config, std::io::stdout().lock())
+    };
 
-        match be_ro_txn.backup(stdout, compression) {
-            Ok(_) => info!("Backup success!"),
-            Err(e) => {
-                error!("Backup failed: {:?}", e);
-                std::process::exit(1);
-            }
-        };
+    match result {
+        Ok(_) => info!("Backup success!"),
+        Err(_) => std::process::exit(1),
+    }
+}
+
+/// The location in S3 compatible object storage to stream a backup to.
+#[derive(Debug, Clone)]
+pub struct S3BackupTarget {
+    /// The object to write, in the form `s3://bucket/path`.
+    pub url: Url,
+    /// The server side encryption to request, one of `AES256`, `aws:kms` or `aws:kms:dsse`.
+    pub sse: Option<String>,
+    /// The KMS key to encrypt with, when `sse` is a KMS type.
+    pub sse_kms_key_id: Option<String>,
+}
+
+impl S3BackupTarget {
+    /// Credentials and the region are taken from the standard AWS environment variables,
+    /// falling back to instance metadata.
+    fn object_store(&self) -> Result<(AmazonS3, ObjectPath), object_store::Error> {
+        let mut builder = AmazonS3Builder::from_env().with_url(self.url.as_str());
+
+        if let Some(sse) = &self.sse {
+            builder = builder.with_config("aws_server_side_encryption".parse()?, sse);
+        }
+
+        if let Some(sse_kms_key_id) = &self.sse_kms_key_id {
+            builder = builder.with_config("aws_sse_kms_key_id".parse()?, sse_kms_key_id);
+        }
+
+        let path = ObjectPath::from_url_path(self.url.path())?;
+
+        Ok((builder.build()?, path))
+    }
+}
+
+/// Stream a backup of the database content to S3 compatible object storage, without
+/// writing a local copy. Once the backup exceeds the write buffer it is sent as a
+/// multipart upload, so there is no limit on the size of the object.
+pub async fn backup_server_s3_core(config: &Configuration, target: &S3BackupTarget) {
+    let (store, path) = match target.object_store() {
+        Ok(store) => store,
+        Err(err) => {
+            error!(
+                ?err,
+                "Unable to configure object storage for {}", target.url
+            );
+            std::process::exit(1);
+        }
     };
-    // Let the txn abort, even on success.
+
+    if path.as_ref().is_empty() {
+        error!("backup url {} must include an object path.", target.url);
+        std::process::exit(1);
+    }
+
+    match store.head(&path).await {
+        Ok(_) => {
+            error!(
+                "backup object {} already exists, will not overwrite it.",
+                target.url
+            );
+            std::process::exit(1);
+        }
+        Err(object_store::Error::NotFound { .. }) => {}
+        Err(err) => {
+            error!(?err, "Unable to access object storage for {}", target.url);
+            std::process::exit(1);
+        }
+    }
+
+    let writer = BufWriter::new(Arc::new(store), path);
+    let config = config.clone();
+
+    // The backend is synchronous, so the backup is written from a blocking task.
+    let result = task::spawn_blocking(move || {
+        let mut output = SyncIoBridge::new(writer);
+        let result = backup_server_write(&config, &mut output).and_then(|()| {
+            // Completes the upload.
+            output.shutdown().map_err(|err| {
+                error!(?err, "Failed to complete upload");
+                OperationError::FsError
+            })
+        });
+        (result, output.into_inner())
+    })
+    .await;
+
+    match result {
+        Ok((Ok(()), _)) => info!("Backup success!"),
+        Ok((Err(_), mut writer)) => {
+            // Don't leave a partial multipart upload behind.
+            if let Err(err) = writer.abort().await {
+                warn!(?err, "Failed to abort upload");
+            }
+            std::process::exit(1);
+        }
+        Err(err) => {
+            error!(?err, "Backup task failed");
+            std::process::exit(1);
+        }
+    }
 }
 
 pub async fn restore_server_core(config: &Configuration, dst_path: &Path) {
@@ -1098,11 +1240,15 @@ pub async fn create_server_core(
         // Add admin to idm_admins to allow tests more flexibility wrt to permissions.
         // This way our default access controls can be stricter to prevent lateral
         // movement.
-        match idms_prox_write.qs_write.internal_modify_uuid(
+        match idms_prox_write.qs_write.internal_modify_uuid_checked(
             UUID_IDM_ADMINS,
             &ModifyList::new_append(Attribute::Member, Value::Refer(UUID_ADMIN)),
         ) {
-            Ok(_) => {}
+            Ok(true) => {}
+            Ok(false) => {
+                error!("Unable to configure INTEGRATION TEST admin, idm_admins does not exist");
+                return Err(());
+            }
             Err(e) => {
                 error!(
                     "Unable to configure INTEGRATION TEST admin as member of idm_admins -> {:?}",
@@ -1112,14 +1258,18 @@ pub async fn create_server_core(
             }
         };
 
-        match idms_prox_write.qs_write.internal_modify_uuid(
+        match idms_prox_write.qs_write.internal_modify_uuid_checked(
             UUID_IDM_ALL_PERSONS,
             &ModifyList::new_purge_and_set(
                 Attribute::CredentialTypeMinimum,
                 CredentialType::Any.into(),
             ),
         ) {
-            Ok(_) => {}
+            Ok(true) => {}
+            Ok(false) => {
+                error!("Unable to configure INTEGRATION TEST default credential policy, idm_all_persons does not exist");
+                return Err(());
+            }
             Err(e) => {
                 error!(
                     "Unable to configure INTEGRATION TEST default credential policy -> {:?}",
@@ -1218,6 +1368,13 @@ pub async fn create_server_core(
         migration_apply(eventid, server_write_ref, migration_path.as_path()).await;
     }
 
+    // Rewrite any credentials still stored in a legacy format. This only scans the
+    // database until the migration has completed once.
+    task::spawn(async move {
+        let eventid = Uuid::new_v4();
+        server_write_ref.handle_credential_migration(eventid).await;
+    });
+
     // Setup the Migration Reload Trigger.
     let mut broadcast_rx = broadcast_tx.subscribe();
     let migration_reload_handle = task::spawn(async move {
@@ -37,11 +37,12 @@ use kanidmd_core::admin::{
 };
 use kanidmd_core::config::{Configuration, ServerConfigUntagged};
 use kanidmd_core::{
-    backup_server_core, cert_generate_core, create_server_core, dbscan_get_id2entry_core,
-    dbscan_list_id2entry_core, dbscan_list_index_analysis_core, dbscan_list_index_core,
-    dbscan_list_indexes_core, dbscan_list_quarantined_core, dbscan_quarantine_id2entry_core,
-    dbscan_restore_quarantined_core, domain_rename_core, reindex_server_core, restore_server_core,
-    vacuum_server_core, verify_server_core, CoreAction,
+    backup_server_core, backup_server_s3_core, cert_generate_core, create_server_core,
+    dbscan_dump_raw_core, dbscan_get_id2entry_core, dbscan_list_id2entry_core,
+    dbscan_list_index_analysis_core, dbscan_list_index_core, dbscan_list_indexes_core,
+    dbscan_list_quarantined_core, dbscan_quarantine_id2entry_core, dbscan_restore_quarantined_core,
+    domain_rename_core, reindex_server_core, restore_server_core, vacuum_server_core,
+    verify_server_core, CoreAction, S3BackupTarget,
 };
 use serde::Serialize;
 use sketching::pipeline::TracingPipelineGuard;
@@ -52,6 +53,7 @@ use std::path::PathBuf;
 use std::process::ExitCode;
 use tokio::net::UnixStream;
 use tokio_util::codec::Framed;
+use url::Url;
 
 include!("./opt.rs");
 
@@ -390,10 +392,34 @@ async fn scripting_command(cmd: ScriptingCommand, config: Configuration) -> Exit
             .await;
         }
 
-        ScriptingCommand::Backup { path } => {
+        ScriptingCommand::Backup {
+            path, s3_url: None, ..
+        } => {
             backup_server_core(&config, path.as_deref());
         }
 
+        ScriptingCommand::Backup {
+            s3_url: Some(s3_url),
+            s3_sse,
+            s3_sse_kms_key_id,
+            ..
+        } => {
+            let url = match Url::parse(&s3_url) {
+                Ok(url) => url,
+                Err(err) => {
+                    error!(?err, "Invalid backup url {}", s3_url);
+                    return ExitCode::FAILURE;
+                }
+            };
+
+            let target = S3BackupTarget {
+                url,
+                sse: s3_sse,
+                sse_kms_key_id: s3_sse_kms_key_id,
+            };
+            backup_server_s3_core(&config, &target).await;
+        }
+
         ScriptingCommand::Reload => {
             submit_admin_req_json(config.adminbindpath.as_str(), AdminTaskRequest::Reload).await;
         }
@@ -1060,6 +1086,13 @@ async fn kanidm_main(config: Configuration, opt: KanidmdParser) -> ExitCode {
             dbscan_get_id2entry_core(&config, dopt.id);
         }
 
+        KanidmdOpt::DbScan {
+            commands: DbScanOpt::DumpRaw { uuid },
+        } => {
+            info!("👀 db scan - dump raw entry - {}", uuid);
+            dbscan_dump_raw_core(&config, uuid.as_str());
+        }
+
         KanidmdOpt::DbScan {
             commands: DbScanOpt::QuarantineId2Entry { id },
         } => {
@@ -88,6 +88,13 @@ enum DbScanOpt {
     #[clap(name = "get-id2entry")]
     /// View the data of a specific entry from id2entry
     GetId2Entry(DbScanGetId2Entry),
+    #[clap(name = "dump-raw")]
+    /// View the raw stored content of the entry with this uuid, and the stored
+    /// value of each of its attributes. This is safe to use while the server is running.
+    DumpRaw {
+        /// The uuid of the entry to display
+        uuid: String,
+    },
     #[clap(name = "list-index-analysis")]
     /// List all content of index analysis
     ListIndexAnalysis,
@@ -133,6 +140,18 @@ enum ScriptingCommand {
     Backup {
         /// The path to backup to. If not set, defaults to stdout.
         path: Option<PathBuf>,
+        /// Stream the backup to S3 compatible object storage at this url, in the form
+        /// s3://bucket/path. Credentials and the region are taken from the standard AWS
+        /// environment variables, or instance metadata.
+        #[clap(long, conflicts_with = "path")]
+        s3_url: Option<String>,
+        /// The server side encryption to request for the uploaded backup, one of
+        /// AES256, aws:kms or aws:kms:dsse.
+        #[clap(long, requires = "s3_url")]
+        s3_sse: Option<String>,
+        /// The KMS key to encrypt the uploaded backup with.
+        #[clap(long, requires = "s3_sse")]
+        s3_sse_kms_key_id: Option<String>,
     },
     /// Initiate a server reload.
     Reload,
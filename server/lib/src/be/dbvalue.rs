@@ -6,7 +6,9 @@ use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
+use std::net::IpAddr;
 use std::time::Duration;
+use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
 use url::Url;
 use uuid::Uuid;
@@ -17,7 +19,7 @@ use webauthn_rs::prelude::{
 use webauthn_rs_core::proto::{COSEKey, UserVerificationPolicy};
 // Re-export this as though it was here.
 use crate::repl::cid::Cid;
-use crypto_glue::{s256::Sha256Output, traits::Zeroizing};
+use crypto_glue::{s256::Sha256Output, traits::Zeroizing, zeroize::Zeroize};
 pub use kanidm_lib_crypto::DbPasswordV1;
 
 fn unix_epoch() -> OffsetDateTime {
@@ -256,6 +258,19 @@ impl DbCred {
         }
     }
 
+    /// True if this credential is stored in a format that is no longer written. Note that
+    /// TmpWn is still the current format for passkey-only credentials.
+    pub fn is_legacy(&self) -> bool {
+        matches!(
+            self,
+            DbCred::Pw { .. }
+                | DbCred::GPw { .. }
+                | DbCred::PwMfa { .. }
+                | DbCred::Wn { .. }
+                | DbCred::V2PasswordMfa { .. }
+        )
+    }
+
     pub fn last_changed_timestamp(&self) -> OffsetDateTime {
         match self {
             DbCred::V2Password { timestamp, .. }
@@ -435,26 +450,115 @@ pub enum DbValueAttestedPasskeyV1 {
         t: String,
         k: AttestedPasskeyV4,
     },
+    V5 {
+        u: Uuid,
+        t: String,
+        k: AttestedPasskeyV4,
+        /// The attestation CA that vouched for this passkey at registration.
+        #[serde(default)]
+        ca: Option<Uuid>,
+        /// The authenticator model reported at registration.
+        #[serde(default)]
+        aaguid: Option<Uuid>,
+    },
+}
+
+impl DbValueAttestedPasskeyV1 {
+    fn key(&self) -> (&Uuid, &AttestedPasskeyV4) {
+        match self {
+            DbValueAttestedPasskeyV1::V4 { u, k, .. }
+            | DbValueAttestedPasskeyV1::V5 { u, k, .. } => (u, k),
+        }
+    }
 }
 
 impl Eq for DbValueAttestedPasskeyV1 {}
 
 impl PartialEq for DbValueAttestedPasskeyV1 {
     fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (
-                DbValueAttestedPasskeyV1::V4 {
-                    u: self_uuid,
-                    k: self_key,
-                    t: _,
-                },
-                DbValueAttestedPasskeyV1::V4 {
-                    u: other_uuid,
-                    k: other_key,
-                    t: _,
-                },
-            ) => self_uuid == other_uuid && self_key.cred_id() == other_key.cred_id(),
-        }
+        // The attestation is determined by the key, so only the key is compared.
+        let (self_uuid, self_key) = self.key();
+        let (other_uuid, other_key) = other.key();
+        self_uuid == other_uuid && self_key.cred_id() == other_key.cred_id()
+    }
+}
+
+/// A set of standalone break-glass codes for an account, independent of the account's
+/// credential. The plaintext codes are never stored.
+///
+/// Each code is hashed with the same KDF as passwords (see [kanidm_lib_crypto::Password]),
+/// and is keyed by an id. To verify a presented code, it is checked against every code
+/// whose id is absent from `consumed`. Once a code is used its id is added to `consumed`
+/// so that it can not be replayed.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct DbValueBreakGlassV1 {
+    #[serde(rename = "c")]
+    pub code_set: BTreeMap<Uuid, DbPasswordV1>,
+    #[serde(rename = "u", default)]
+    pub consumed: BTreeSet<Uuid>,
+}
+
+impl fmt::Debug for DbValueBreakGlassV1 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "codes: {}, consumed: {}",
+            self.code_set.len(),
+            self.consumed.len()
+        )
+    }
+}
+
+impl Drop for DbValueBreakGlassV1 {
+    fn drop(&mut self) {
+        self.code_set.values_mut().for_each(|hash| hash.zeroize());
+    }
+}
+
+/// A grace window in which an account may still authenticate without MFA, allowing
+/// the account time to enrol a second factor after MFA has been required.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(try_from = "DbValueMfaGraceV1Unchecked")]
+pub struct DbValueMfaGraceV1 {
+    #[serde(rename = "g")]
+    granted_at: DbCidV1,
+    #[serde(rename = "e")]
+    expires_at: String,
+}
+
+#[derive(Deserialize)]
+struct DbValueMfaGraceV1Unchecked {
+    #[serde(rename = "g")]
+    granted_at: DbCidV1,
+    #[serde(rename = "e")]
+    expires_at: String,
+}
+
+impl TryFrom<DbValueMfaGraceV1Unchecked> for DbValueMfaGraceV1 {
+    type Error = &'static str;
+
+    fn try_from(value: DbValueMfaGraceV1Unchecked) -> Result<Self, Self::Error> {
+        DbValueMfaGraceV1::new(value.granted_at, value.expires_at)
+            .ok_or("mfa grace expiry must be an rfc3339 timestamp")
+    }
+}
+
+impl DbValueMfaGraceV1 {
+    /// The expiry must be a valid RFC 3339 timestamp.
+    pub fn new(granted_at: DbCidV1, expires_at: String) -> Option<Self> {
+        OffsetDateTime::parse(&expires_at, &Rfc3339).ok()?;
+        Some(DbValueMfaGraceV1 {
+            granted_at,
+            expires_at,
+        })
+    }
+
+    pub fn granted_at(&self) -> &DbCidV1 {
+        &self.granted_at
+    }
+
+    pub fn expires_at(&self) -> &str {
+        &self.expires_at
     }
 }
 
@@ -466,6 +570,136 @@ pub struct DbValueTaggedStringV1 {
     pub data: String,
 }
 
+/// A link from this entry to its counterpart in an external system, such as an
+/// AD objectGUID or an LDAP DN. Unlike [DbValueSetV2::NsUniqueId] this records
+/// which system the identifier belongs to, so an entry may be linked to many.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(try_from = "DbValueExternalIdV1Unchecked")]
+pub struct DbValueExternalIdV1 {
+    #[serde(rename = "s")]
+    system: String,
+    #[serde(rename = "i")]
+    identifier: String,
+}
+
+#[derive(Deserialize)]
+struct DbValueExternalIdV1Unchecked {
+    #[serde(rename = "s")]
+    system: String,
+    #[serde(rename = "i")]
+    identifier: String,
+}
+
+impl TryFrom<DbValueExternalIdV1Unchecked> for DbValueExternalIdV1 {
+    type Error = &'static str;
+
+    fn try_from(value: DbValueExternalIdV1Unchecked) -> Result<Self, Self::Error> {
+        DbValueExternalIdV1::new(value.system, value.identifier)
+            .ok_or("external id system and identifier must not be empty")
+    }
+}
+
+impl DbValueExternalIdV1 {
+    /// Both the system and the identifier must be non-empty.
+    pub fn new(system: String, identifier: String) -> Option<Self> {
+        if system.is_empty() || identifier.is_empty() {
+            None
+        } else {
+            Some(DbValueExternalIdV1 { system, identifier })
+        }
+    }
+
+    pub fn system(&self) -> &str {
+        &self.system
+    }
+
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+}
+
+/// The largest serialised size in bytes of the data of a [DbValueExtensionJsonV1].
+/// Extension data is stored inline with the entry, so this bounds how much one
+/// extension can grow an entry by. The nesting depth is separately bounded by
+/// the recursion limits of the json and cbor decoders (128 levels).
+pub const DBVALUE_EXTENSION_JSON_MAX_BYTES: usize = 64 * 1024;
+
+/// Structured data from a SCIM extension schema that Kanidm has no typed
+/// attribute for, such as an enterprise extension supplied by a sync agent. The
+/// data is kept as given so that it can be returned without loss.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(try_from = "DbValueExtensionJsonV1Unchecked")]
+pub struct DbValueExtensionJsonV1 {
+    #[serde(rename = "u")]
+    urn: String,
+    #[serde(rename = "d")]
+    data: JsonValue,
+}
+
+#[derive(Deserialize)]
+struct DbValueExtensionJsonV1Unchecked {
+    #[serde(rename = "u")]
+    urn: String,
+    #[serde(rename = "d")]
+    data: JsonValue,
+}
+
+impl TryFrom<DbValueExtensionJsonV1Unchecked> for DbValueExtensionJsonV1 {
+    type Error = &'static str;
+
+    fn try_from(value: DbValueExtensionJsonV1Unchecked) -> Result<Self, Self::Error> {
+        DbValueExtensionJsonV1::new(value.urn, value.data)
+            .ok_or("extension json must have a valid urn and be within the size limit")
+    }
+}
+
+impl DbValueExtensionJsonV1 {
+    /// The urn must be a SCIM style urn such as
+    /// `urn:ietf:params:scim:schemas:extension:enterprise:2.0:User`, and the
+    /// serialised data must be at most [DBVALUE_EXTENSION_JSON_MAX_BYTES].
+    pub fn new(urn: String, data: JsonValue) -> Option<Self> {
+        if Self::is_valid(&urn, &data) {
+            Some(DbValueExtensionJsonV1 { urn, data })
+        } else {
+            None
+        }
+    }
+
+    /// Check a urn and its data against the rules of [DbValueExtensionJsonV1::new].
+    pub(crate) fn is_valid(urn: &str, data: &JsonValue) -> bool {
+        Self::is_valid_urn(urn)
+            && serde_json::to_vec(data)
+                .map(|bytes| bytes.len() <= DBVALUE_EXTENSION_JSON_MAX_BYTES)
+                .unwrap_or(false)
+    }
+
+    /// A urn is `urn:<nid>:<nss>`, where the namespace id is alphanumeric or `-`,
+    /// and neither part is empty or contains whitespace.
+    fn is_valid_urn(urn: &str) -> bool {
+        let mut parts = urn.splitn(3, ':');
+        let (Some(scheme), Some(nid), Some(nss)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return false;
+        };
+
+        scheme.eq_ignore_ascii_case("urn")
+            && !nid.is_empty()
+            && nid.len() <= 32
+            && !nid.starts_with('-')
+            && nid.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            && !nss.is_empty()
+            && !nss.chars().any(|c| c.is_whitespace() || c.is_control())
+    }
+
+    pub fn urn(&self) -> &str {
+        &self.urn
+    }
+
+    pub fn data(&self) -> &JsonValue {
+        &self.data
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub struct DbValueAddressV1 {
     #[serde(rename = "f")]
@@ -502,6 +736,42 @@ pub enum DbValueOauthClaimMap {
         #[serde(rename = "d")]
         values: BTreeMap<Uuid, BTreeSet<String>>,
     },
+    V2 {
+        #[serde(rename = "n")]
+        name: String,
+        #[serde(rename = "j")]
+        join: DbValueOauthClaimMapJoinV1,
+        #[serde(rename = "d")]
+        values: BTreeMap<Uuid, Vec<JsonValue>>,
+    },
+}
+
+impl DbValueOauthClaimMap {
+    /// Upgrade this claim map to the current (V2) representation. V1 values are
+    /// always strings, so they become json strings in the same order.
+    pub fn into_v2(
+        self,
+    ) -> (
+        String,
+        DbValueOauthClaimMapJoinV1,
+        BTreeMap<Uuid, Vec<JsonValue>>,
+    ) {
+        match self {
+            DbValueOauthClaimMap::V1 { name, join, values } => {
+                let values = values
+                    .into_iter()
+                    .map(|(group_uuid, claim_values)| {
+                        (
+                            group_uuid,
+                            claim_values.into_iter().map(JsonValue::String).collect(),
+                        )
+                    })
+                    .collect();
+                (name, join, values)
+            }
+            DbValueOauthClaimMap::V2 { name, join, values } => (name, join, values),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
@@ -540,6 +810,18 @@ pub enum DbValueIdentityId {
     V1Sync(Uuid),
 }
 
+impl DbValueIdentityId {
+    /// The entry that this identity refers to, if it is not the internal identity.
+    pub fn uuid(&self) -> Option<Uuid> {
+        match self {
+            DbValueIdentityId::V1Internal => None,
+            DbValueIdentityId::V2Internal(u)
+            | DbValueIdentityId::V1Uuid(u)
+            | DbValueIdentityId::V1Sync(u) => Some(*u),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub enum DbValueSessionStateV1 {
     #[serde(rename = "ea")]
@@ -572,7 +854,41 @@ pub enum DbValueAuthTypeV1 {
     OAuth2Trust,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+impl From<DbValueAuthTypeV1> for kanidm_proto::internal::AuthType {
+    fn from(value: DbValueAuthTypeV1) -> Self {
+        use kanidm_proto::internal::AuthType;
+        match value {
+            DbValueAuthTypeV1::Anonymous => AuthType::Anonymous,
+            DbValueAuthTypeV1::Password => AuthType::Password,
+            DbValueAuthTypeV1::GeneratedPassword => AuthType::GeneratedPassword,
+            DbValueAuthTypeV1::PasswordTotp => AuthType::PasswordTotp,
+            DbValueAuthTypeV1::PasswordBackupCode => AuthType::PasswordBackupCode,
+            DbValueAuthTypeV1::PasswordSecurityKey => AuthType::PasswordSecurityKey,
+            DbValueAuthTypeV1::Passkey => AuthType::Passkey,
+            DbValueAuthTypeV1::AttestedPasskey => AuthType::AttestedPasskey,
+            DbValueAuthTypeV1::OAuth2Trust => AuthType::OAuth2Trust,
+        }
+    }
+}
+
+impl From<kanidm_proto::internal::AuthType> for DbValueAuthTypeV1 {
+    fn from(value: kanidm_proto::internal::AuthType) -> Self {
+        use kanidm_proto::internal::AuthType;
+        match value {
+            AuthType::Anonymous => DbValueAuthTypeV1::Anonymous,
+            AuthType::Password => DbValueAuthTypeV1::Password,
+            AuthType::GeneratedPassword => DbValueAuthTypeV1::GeneratedPassword,
+            AuthType::PasswordTotp => DbValueAuthTypeV1::PasswordTotp,
+            AuthType::PasswordBackupCode => DbValueAuthTypeV1::PasswordBackupCode,
+            AuthType::PasswordSecurityKey => DbValueAuthTypeV1::PasswordSecurityKey,
+            AuthType::Passkey => DbValueAuthTypeV1::Passkey,
+            AuthType::AttestedPasskey => DbValueAuthTypeV1::AttestedPasskey,
+            AuthType::OAuth2Trust => DbValueAuthTypeV1::OAuth2Trust,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Default)]
 pub enum DbValueSessionExtMetadataV1 {
     #[default]
     None,
@@ -583,6 +899,21 @@ pub enum DbValueSessionExtMetadataV1 {
     },
 }
 
+/// The tokens are never shown, so that sessions can be safely logged.
+impl fmt::Debug for DbValueSessionExtMetadataV1 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => f.write_str("None"),
+            Self::OAuth2 {
+                access_expires_at, ..
+            } => f
+                .debug_struct("OAuth2")
+                .field("access_expires_at", access_expires_at)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub enum DbValueSession {
     V1 {
@@ -651,6 +982,33 @@ pub enum DbValueSession {
         #[serde(rename = "x", default)]
         ext_metadata: DbValueSessionExtMetadataV1,
     },
+    V5 {
+        #[serde(rename = "u")]
+        refer: Uuid,
+        #[serde(rename = "l")]
+        label: String,
+        #[serde(rename = "e")]
+        state: DbValueSessionStateV1,
+        #[serde(rename = "i")]
+        issued_at: String,
+        #[serde(rename = "b")]
+        issued_by: DbValueIdentityId,
+        #[serde(rename = "c")]
+        cred_id: Uuid,
+        #[serde(rename = "s", default)]
+        scope: DbValueAccessScopeV1,
+        #[serde(rename = "t")]
+        type_: DbValueAuthTypeV1,
+        #[serde(rename = "x", default)]
+        ext_metadata: DbValueSessionExtMetadataV1,
+        /// The address the session was authenticated from, if it is bound to it.
+        #[serde(rename = "ip", default)]
+        client_ip: Option<IpAddr>,
+        /// The RFC3339 time the session was last seen in use. This is a best-effort
+        /// write the server makes on token use, so it may lag behind the true last use.
+        #[serde(rename = "ls", default, skip_serializing_if = "Option::is_none")]
+        last_seen: Option<String>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
@@ -761,6 +1119,223 @@ pub enum DbValueKeyInternal {
     },
 }
 
+/// The index types of an attribute. These are stored by their number, which must never
+/// change.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(from = "u16", into = "u16")]
+pub enum DbValueIndexTypeV1 {
+    Equality,
+    Presence,
+    SubString,
+    Ordering,
+    /// A value that this version does not know of. This is retained as is, so that
+    /// it is not lost when the valueset is written back.
+    Unknown(u16),
+}
+
+impl From<u16> for DbValueIndexTypeV1 {
+    fn from(value: u16) -> Self {
+        match value {
+            0 => DbValueIndexTypeV1::Equality,
+            1 => DbValueIndexTypeV1::Presence,
+            2 => DbValueIndexTypeV1::SubString,
+            3 => DbValueIndexTypeV1::Ordering,
+            value => DbValueIndexTypeV1::Unknown(value),
+        }
+    }
+}
+
+impl From<DbValueIndexTypeV1> for u16 {
+    fn from(value: DbValueIndexTypeV1) -> Self {
+        match value {
+            DbValueIndexTypeV1::Equality => 0,
+            DbValueIndexTypeV1::Presence => 1,
+            DbValueIndexTypeV1::SubString => 2,
+            DbValueIndexTypeV1::Ordering => 3,
+            DbValueIndexTypeV1::Unknown(value) => value,
+        }
+    }
+}
+
+/// The syntax of an attribute. These are stored by their number, which must never
+/// change.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(from = "u16", into = "u16")]
+pub enum DbValueSyntaxTypeV1 {
+    Utf8String,
+    Utf8StringInsensitive,
+    Uuid,
+    Boolean,
+    SyntaxId,
+    IndexId,
+    ReferenceUuid,
+    JsonFilter,
+    Credential,
+    SecretUtf8String,
+    SshKey,
+    SecurityPrincipalName,
+    Uint32,
+    Cid,
+    Utf8StringIname,
+    NsUniqueId,
+    DateTime,
+    EmailAddress,
+    Url,
+    OauthScope,
+    OauthScopeMap,
+    PrivateBinary,
+    IntentToken,
+    Passkey,
+    AttestedPasskey,
+    Session,
+    JwsKeyEs256,
+    JwsKeyRs256,
+    Oauth2Session,
+    UiHint,
+    TotpSecret,
+    ApiToken,
+    AuditLogString,
+    EcKeyPrivate,
+    Image,
+    CredentialType,
+    WebauthnAttestationCaList,
+    OauthClaimMap,
+    KeyInternal,
+    HexString,
+    Certificate,
+    ApplicationPassword,
+    Json,
+    Message,
+    Sha256,
+    Int64,
+    Uint64,
+    ExternalId,
+    MfaGrace,
+    BreakGlass,
+    ExtensionJson,
+    /// A value that this version does not know of. This is retained as is, so that
+    /// it is not lost when the valueset is written back.
+    Unknown(u16),
+}
+
+impl From<u16> for DbValueSyntaxTypeV1 {
+    fn from(value: u16) -> Self {
+        match value {
+            0 => DbValueSyntaxTypeV1::Utf8String,
+            1 => DbValueSyntaxTypeV1::Utf8StringInsensitive,
+            2 => DbValueSyntaxTypeV1::Uuid,
+            3 => DbValueSyntaxTypeV1::Boolean,
+            4 => DbValueSyntaxTypeV1::SyntaxId,
+            5 => DbValueSyntaxTypeV1::IndexId,
+            6 => DbValueSyntaxTypeV1::ReferenceUuid,
+            7 => DbValueSyntaxTypeV1::JsonFilter,
+            8 => DbValueSyntaxTypeV1::Credential,
+            9 => DbValueSyntaxTypeV1::SecretUtf8String,
+            10 => DbValueSyntaxTypeV1::SshKey,
+            11 => DbValueSyntaxTypeV1::SecurityPrincipalName,
+            12 => DbValueSyntaxTypeV1::Uint32,
+            13 => DbValueSyntaxTypeV1::Cid,
+            14 => DbValueSyntaxTypeV1::Utf8StringIname,
+            15 => DbValueSyntaxTypeV1::NsUniqueId,
+            16 => DbValueSyntaxTypeV1::DateTime,
+            17 => DbValueSyntaxTypeV1::EmailAddress,
+            18 => DbValueSyntaxTypeV1::Url,
+            19 => DbValueSyntaxTypeV1::OauthScope,
+            20 => DbValueSyntaxTypeV1::OauthScopeMap,
+            21 => DbValueSyntaxTypeV1::PrivateBinary,
+            22 => DbValueSyntaxTypeV1::IntentToken,
+            23 => DbValueSyntaxTypeV1::Passkey,
+            24 => DbValueSyntaxTypeV1::AttestedPasskey,
+            25 => DbValueSyntaxTypeV1::Session,
+            26 => DbValueSyntaxTypeV1::JwsKeyEs256,
+            27 => DbValueSyntaxTypeV1::JwsKeyRs256,
+            28 => DbValueSyntaxTypeV1::Oauth2Session,
+            29 => DbValueSyntaxTypeV1::UiHint,
+            30 => DbValueSyntaxTypeV1::TotpSecret,
+            31 => DbValueSyntaxTypeV1::ApiToken,
+            32 => DbValueSyntaxTypeV1::AuditLogString,
+            33 => DbValueSyntaxTypeV1::EcKeyPrivate,
+            34 => DbValueSyntaxTypeV1::Image,
+            35 => DbValueSyntaxTypeV1::CredentialType,
+            36 => DbValueSyntaxTypeV1::WebauthnAttestationCaList,
+            37 => DbValueSyntaxTypeV1::OauthClaimMap,
+            38 => DbValueSyntaxTypeV1::KeyInternal,
+            39 => DbValueSyntaxTypeV1::HexString,
+            40 => DbValueSyntaxTypeV1::Certificate,
+            41 => DbValueSyntaxTypeV1::ApplicationPassword,
+            42 => DbValueSyntaxTypeV1::Json,
+            43 => DbValueSyntaxTypeV1::Message,
+            44 => DbValueSyntaxTypeV1::Sha256,
+            45 => DbValueSyntaxTypeV1::Int64,
+            46 => DbValueSyntaxTypeV1::Uint64,
+            47 => DbValueSyntaxTypeV1::ExternalId,
+            48 => DbValueSyntaxTypeV1::MfaGrace,
+            49 => DbValueSyntaxTypeV1::BreakGlass,
+            50 => DbValueSyntaxTypeV1::ExtensionJson,
+            value => DbValueSyntaxTypeV1::Unknown(value),
+        }
+    }
+}
+
+impl From<DbValueSyntaxTypeV1> for u16 {
+    fn from(value: DbValueSyntaxTypeV1) -> Self {
+        match value {
+            DbValueSyntaxTypeV1::Utf8String => 0,
+            DbValueSyntaxTypeV1::Utf8StringInsensitive => 1,
+            DbValueSyntaxTypeV1::Uuid => 2,
+            DbValueSyntaxTypeV1::Boolean => 3,
+            DbValueSyntaxTypeV1::SyntaxId => 4,
+            DbValueSyntaxTypeV1::IndexId => 5,
+            DbValueSyntaxTypeV1::ReferenceUuid => 6,
+            DbValueSyntaxTypeV1::JsonFilter => 7,
+            DbValueSyntaxTypeV1::Credential => 8,
+            DbValueSyntaxTypeV1::SecretUtf8String => 9,
+            DbValueSyntaxTypeV1::SshKey => 10,
+            DbValueSyntaxTypeV1::SecurityPrincipalName => 11,
+            DbValueSyntaxTypeV1::Uint32 => 12,
+            DbValueSyntaxTypeV1::Cid => 13,
+            DbValueSyntaxTypeV1::Utf8StringIname => 14,
+            DbValueSyntaxTypeV1::NsUniqueId => 15,
+            DbValueSyntaxTypeV1::DateTime => 16,
+            DbValueSyntaxTypeV1::EmailAddress => 17,
+            DbValueSyntaxTypeV1::Url => 18,
+            DbValueSyntaxTypeV1::OauthScope => 19,
+            DbValueSyntaxTypeV1::OauthScopeMap => 20,
+            DbValueSyntaxTypeV1::PrivateBinary => 21,
+            DbValueSyntaxTypeV1::IntentToken => 22,
+            DbValueSyntaxTypeV1::Passkey => 23,
+            DbValueSyntaxTypeV1::AttestedPasskey => 24,
+            DbValueSyntaxTypeV1::Session => 25,
+            DbValueSyntaxTypeV1::JwsKeyEs256 => 26,
+            DbValueSyntaxTypeV1::JwsKeyRs256 => 27,
+            DbValueSyntaxTypeV1::Oauth2Session => 28,
+            DbValueSyntaxTypeV1::UiHint => 29,
+            DbValueSyntaxTypeV1::TotpSecret => 30,
+            DbValueSyntaxTypeV1::ApiToken => 31,
+            DbValueSyntaxTypeV1::AuditLogString => 32,
+            DbValueSyntaxTypeV1::EcKeyPrivate => 33,
+            DbValueSyntaxTypeV1::Image => 34,
+            DbValueSyntaxTypeV1::CredentialType => 35,
+            DbValueSyntaxTypeV1::WebauthnAttestationCaList => 36,
+            DbValueSyntaxTypeV1::OauthClaimMap => 37,
+            DbValueSyntaxTypeV1::KeyInternal => 38,
+            DbValueSyntaxTypeV1::HexString => 39,
+            DbValueSyntaxTypeV1::Certificate => 40,
+            DbValueSyntaxTypeV1::ApplicationPassword => 41,
+            DbValueSyntaxTypeV1::Json => 42,
+            DbValueSyntaxTypeV1::Message => 43,
+            DbValueSyntaxTypeV1::Sha256 => 44,
+            DbValueSyntaxTypeV1::Int64 => 45,
+            DbValueSyntaxTypeV1::Uint64 => 46,
+            DbValueSyntaxTypeV1::ExternalId => 47,
+            DbValueSyntaxTypeV1::MfaGrace => 48,
+            DbValueSyntaxTypeV1::BreakGlass => 49,
+            DbValueSyntaxTypeV1::ExtensionJson => 50,
+            DbValueSyntaxTypeV1::Unknown(value) => value,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub enum DbValueCertificate {
     V1 { certificate_der: Vec<u8> },
@@ -780,7 +1355,7 @@ pub enum DbValueApplicationPassword {
     },
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, PartialEq, Eq)]
 pub enum DbValueSetV2 {
     #[serde(rename = "U8")]
     Utf8(Vec<String>),
@@ -793,9 +1368,9 @@ pub enum DbValueSetV2 {
     #[serde(rename = "BO")]
     Bool(Vec<bool>),
     #[serde(rename = "SY")]
-    SyntaxType(Vec<u16>),
+    SyntaxType(Vec<DbValueSyntaxTypeV1>),
     #[serde(rename = "IN")]
-    IndexType(Vec<u16>),
+    IndexType(Vec<DbValueIndexTypeV1>),
     #[serde(rename = "RF")]
     Reference(Vec<Uuid>),
     #[serde(rename = "JF")]
@@ -886,9 +1461,205 @@ pub enum DbValueSetV2 {
     Message(OutboundMessage),
     #[serde(rename = "S256")]
     Sha256(BTreeSet<Sha256Output>),
+    #[serde(rename = "XI")]
+    ExternalId(Vec<DbValueExternalIdV1>),
+    #[serde(rename = "MG")]
+    MfaGrace(Vec<DbValueMfaGraceV1>),
+    #[serde(rename = "BG")]
+    BreakGlass(Vec<DbValueBreakGlassV1>),
+    #[serde(rename = "EJ")]
+    ExtensionJson(Vec<DbValueExtensionJsonV1>),
+}
+
+/// Secret bearing values only show how many values are present, so that a value
+/// set can be safely logged.
+impl fmt::Debug for DbValueSetV2 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbValueSetV2::Utf8(set) => f.debug_tuple("Utf8").field(set).finish(),
+            DbValueSetV2::Iutf8(set) => f.debug_tuple("Iutf8").field(set).finish(),
+            DbValueSetV2::Iname(set) => f.debug_tuple("Iname").field(set).finish(),
+            DbValueSetV2::Uuid(set) => f.debug_tuple("Uuid").field(set).finish(),
+            DbValueSetV2::Bool(set) => f.debug_tuple("Bool").field(set).finish(),
+            DbValueSetV2::SyntaxType(set) => f.debug_tuple("SyntaxType").field(set).finish(),
+            DbValueSetV2::IndexType(set) => f.debug_tuple("IndexType").field(set).finish(),
+            DbValueSetV2::Reference(set) => f.debug_tuple("Reference").field(set).finish(),
+            DbValueSetV2::JsonFilter(set) => f.debug_tuple("JsonFilter").field(set).finish(),
+            DbValueSetV2::Credential(set) => f
+                .debug_struct("Credential")
+                .field("count", &set.len())
+                .finish(),
+            DbValueSetV2::SecretValue(set) => f
+                .debug_struct("SecretValue")
+                .field("count", &set.len())
+                .finish(),
+            DbValueSetV2::SshKey(set) => f.debug_tuple("SshKey").field(set).finish(),
+            DbValueSetV2::Spn(set) => f.debug_tuple("Spn").field(set).finish(),
+            DbValueSetV2::Uint32(set) => f.debug_tuple("Uint32").field(set).finish(),
+            DbValueSetV2::Int64(set) => f.debug_tuple("Int64").field(set).finish(),
+            DbValueSetV2::Uint64(set) => f.debug_tuple("Uint64").field(set).finish(),
+            DbValueSetV2::Cid(set) => f.debug_tuple("Cid").field(set).finish(),
+            DbValueSetV2::NsUniqueId(set) => f.debug_tuple("NsUniqueId").field(set).finish(),
+            DbValueSetV2::DateTime(set) => f.debug_tuple("DateTime").field(set).finish(),
+            DbValueSetV2::EmailAddress(primary, set) => f
+                .debug_tuple("EmailAddress")
+                .field(primary)
+                .field(set)
+                .finish(),
+            DbValueSetV2::PhoneNumber(primary, set) => f
+                .debug_tuple("PhoneNumber")
+                .field(primary)
+                .field(set)
+                .finish(),
+            DbValueSetV2::Address(set) => f.debug_tuple("Address").field(set).finish(),
+            DbValueSetV2::Url(set) => f.debug_tuple("Url").field(set).finish(),
+            DbValueSetV2::OauthScope(set) => f.debug_tuple("OauthScope").field(set).finish(),
+            DbValueSetV2::OauthScopeMap(set) => f.debug_tuple("OauthScopeMap").field(set).finish(),
+            DbValueSetV2::OauthClaimMap(set) => f.debug_tuple("OauthClaimMap").field(set).finish(),
+            DbValueSetV2::PrivateBinary(set) => f
+                .debug_struct("PrivateBinary")
+                .field("count", &set.len())
+                .finish(),
+            DbValueSetV2::PublicBinary(set) => f.debug_tuple("PublicBinary").field(set).finish(),
+            DbValueSetV2::RestrictedString(set) => {
+                f.debug_tuple("RestrictedString").field(set).finish()
+            }
+            DbValueSetV2::IntentToken(set) => f
+                .debug_struct("IntentToken")
+                .field("count", &set.len())
+                .finish(),
+            DbValueSetV2::Passkey(set) => f.debug_tuple("Passkey").field(set).finish(),
+            DbValueSetV2::AttestedPasskey(set) => {
+                f.debug_tuple("AttestedPasskey").field(set).finish()
+            }
+            DbValueSetV2::TrustedDeviceEnrollment(set) => {
+                f.debug_tuple("TrustedDeviceEnrollment").field(set).finish()
+            }
+            DbValueSetV2::Session(set) => f.debug_tuple("Session").field(set).finish(),
+            DbValueSetV2::JwsKeyEs256(set) => f
+                .debug_struct("JwsKeyEs256")
+                .field("count", &set.len())
+                .finish(),
+            DbValueSetV2::JwsKeyRs256(set) => f
+                .debug_struct("JwsKeyRs256")
+                .field("count", &set.len())
+                .finish(),
+            DbValueSetV2::Oauth2Session(set) => f.debug_tuple("Oauth2Session").field(set).finish(),
+            DbValueSetV2::UiHint(set) => f.debug_tuple("UiHint").field(set).finish(),
+            DbValueSetV2::TotpSecret(set) => f
+                .debug_struct("TotpSecret")
+                .field("count", &set.len())
+                .finish(),
+            DbValueSetV2::ApiToken(set) => f.debug_tuple("ApiToken").field(set).finish(),
+            DbValueSetV2::AuditLogString(set) => {
+                f.debug_tuple("AuditLogString").field(set).finish()
+            }
+            DbValueSetV2::EcKeyPrivate(key) => f
+                .debug_struct("EcKeyPrivate")
+                .field("len", &key.len())
+                .finish(),
+            DbValueSetV2::Image(set) => f.debug_tuple("Image").field(set).finish(),
+            DbValueSetV2::CredentialType(set) => {
+                f.debug_tuple("CredentialType").field(set).finish()
+            }
+            DbValueSetV2::WebauthnAttestationCaList { ca_list } => f
+                .debug_struct("WebauthnAttestationCaList")
+                .field("ca_list", ca_list)
+                .finish(),
+            DbValueSetV2::KeyInternal(set) => f
+                .debug_struct("KeyInternal")
+                .field("count", &set.len())
+                .finish(),
+            DbValueSetV2::HexString(set) => f.debug_tuple("HexString").field(set).finish(),
+            DbValueSetV2::Certificate(set) => f.debug_tuple("Certificate").field(set).finish(),
+            DbValueSetV2::ApplicationPassword(set) => f
+                .debug_struct("ApplicationPassword")
+                .field("count", &set.len())
+                .finish(),
+            DbValueSetV2::Json(set) => f.debug_tuple("Json").field(set).finish(),
+            DbValueSetV2::Message(set) => f.debug_tuple("Message").field(set).finish(),
+            DbValueSetV2::Sha256(set) => f.debug_tuple("Sha256").field(set).finish(),
+            DbValueSetV2::ExternalId(set) => f.debug_tuple("ExternalId").field(set).finish(),
+            DbValueSetV2::MfaGrace(set) => f.debug_tuple("MfaGrace").field(set).finish(),
+            DbValueSetV2::BreakGlass(set) => f
+                .debug_struct("BreakGlass")
+                .field("count", &set.len())
+                .finish(),
+            DbValueSetV2::ExtensionJson(set) => f.debug_tuple("ExtensionJson").field(set).finish(),
+        }
+    }
+}
+
+/// An error in the content of a [DbValueSetV2].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DbValueError {
+    /// The value at this index is not a valid RFC 3339 timestamp.
+    InvalidDateTime(usize),
+    /// The operation does not apply to this variant of value set.
+    IncorrectVariant,
+    /// A single valued set contains this many values.
+    MultipleValues(usize),
+}
+
+impl fmt::Display for DbValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbValueError::InvalidDateTime(idx) => {
+                write!(f, "value {idx} is not a valid RFC 3339 timestamp")
+            }
+            DbValueError::IncorrectVariant => write!(f, "incorrect value set variant"),
+            DbValueError::MultipleValues(count) => {
+                write!(f, "expected at most one value, found {count}")
+            }
+        }
+    }
 }
 
 impl DbValueSetV2 {
+    /// Create a DateTime value set, checking that every value is a valid RFC 3339
+    /// timestamp. The values are stored as given.
+    pub fn new_datetime(values: Vec<String>) -> Result<Self, DbValueError> {
+        let set = DbValueSetV2::DateTime(values);
+        set.datetimes_parsed()?;
+        Ok(set)
+    }
+
+    /// Parse the values of a DateTime value set, normalised to UTC.
+    pub fn datetimes_parsed(&self) -> Result<Vec<OffsetDateTime>, DbValueError> {
+        let DbValueSetV2::DateTime(values) = self else {
+            return Err(DbValueError::IncorrectVariant);
+        };
+
+        values
+            .iter()
+            .enumerate()
+            .map(|(idx, value)| {
+                OffsetDateTime::parse(value, &Rfc3339)
+                    .map(|odt| odt.to_offset(time::UtcOffset::UTC))
+                    .map_err(|_| DbValueError::InvalidDateTime(idx))
+            })
+            .collect()
+    }
+
+    /// Check that this value set holds at most one value, for attributes that
+    /// the schema declares single valued. Storage remains permissive, so this
+    /// is the point where the server or repair tooling can reject a set such as
+    /// `[true, false]` before it is interpreted.
+    pub fn enforce_single(self) -> Result<Self, DbValueError> {
+        match self.len() {
+            0 | 1 => Ok(self),
+            count => Err(DbValueError::MultipleValues(count)),
+        }
+    }
+
+    /// As [Self::enforce_single], also checking that this is a Bool value set.
+    pub fn enforce_single_bool(self) -> Result<Self, DbValueError> {
+        if !matches!(self, DbValueSetV2::Bool(_)) {
+            return Err(DbValueError::IncorrectVariant);
+        }
+        self.enforce_single()
+    }
+
     pub fn len(&self) -> usize {
         match self {
             DbValueSetV2::Utf8(set)
@@ -942,6 +1713,10 @@ impl DbValueSetV2 {
             DbValueSetV2::Certificate(set) => set.len(),
             DbValueSetV2::ApplicationPassword(set) => set.len(),
             DbValueSetV2::Sha256(set) => set.len(),
+            DbValueSetV2::ExternalId(set) => set.len(),
+            DbValueSetV2::MfaGrace(set) => set.len(),
+            DbValueSetV2::BreakGlass(set) => set.len(),
+            DbValueSetV2::ExtensionJson(set) => set.len(),
             DbValueSetV2::Json(_) | DbValueSetV2::Message(_) => 1,
         }
     }
@@ -949,16 +1724,255 @@ impl DbValueSetV2 {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// Collect the uuids that values in this set point at, so that reference
+    /// integrity and garbage collection can find what each value depends on. The
+    /// result is sorted and free of duplicates.
+    ///
+    /// Uuids that identify the value itself are not references and are skipped -
+    /// the `refer` of sessions, api tokens, oauth2 sessions and application
+    /// passwords, the ids of passkeys and intent token sessions, and plain
+    /// [DbValueSetV2::Uuid] values. Per variant, the included uuids are:
+    ///
+    /// * `Reference` - every value.
+    /// * `OauthScopeMap` - the `refer` group of each scope map.
+    /// * `OauthClaimMap` - the group uuids keying each claim's values.
+    /// * `Session` - `issued_by` unless issued internally, and `cred_id` from V2
+    ///   onward, as the session is invalid once that credential is gone.
+    /// * `ApiToken` - `issued_by` unless issued internally.
+    /// * `Oauth2Session` - `parent` when present, and `rs_uuid`.
+    /// * `ApplicationPassword` - `application_refer`.
+    ///
+    /// All other variants reference nothing.
+    pub fn referenced_uuids(&self) -> Vec<Uuid> {
+        let mut refers = BTreeSet::new();
+
+        match self {
+            DbValueSetV2::Reference(set) => refers.extend(set.iter().copied()),
+            DbValueSetV2::OauthScopeMap(set) => {
+                refers.extend(set.iter().map(|scope_map| scope_map.refer))
+            }
+            DbValueSetV2::OauthClaimMap(set) => {
+                for claim_map in set {
+                    match claim_map {
+                        DbValueOauthClaimMap::V1 { values, .. } => {
+                            refers.extend(values.keys().copied())
+                        }
+                        DbValueOauthClaimMap::V2 { values, .. } => {
+                            refers.extend(values.keys().copied())
+                        }
+                    }
+                }
+            }
+            DbValueSetV2::Session(set) => {
+                for session in set {
+                    let (issued_by, cred_id) = match session {
+                        DbValueSession::V1 { issued_by, .. } => (issued_by, None),
+                        DbValueSession::V2 {
+                            issued_by, cred_id, ..
+                        }
+                        | DbValueSession::V3 {
+                            issued_by, cred_id, ..
+                        }
+                        | DbValueSession::V4 {
+                            issued_by, cred_id, ..
+                        }
+                        | DbValueSession::V5 {
+                            issued_by, cred_id, ..
+                        } => (issued_by, Some(*cred_id)),
+                    };
+                    refers.extend(issued_by.uuid());
+                    refers.extend(cred_id);
+                }
+            }
+            DbValueSetV2::ApiToken(set) => {
+                for token in set {
+                    let DbValueApiToken::V1 { issued_by, .. } = token;
+                    refers.extend(issued_by.uuid());
+                }
+            }
+            DbValueSetV2::Oauth2Session(set) => {
+                for session in set {
+                    let (parent, rs_uuid) = match session {
+                        DbValueOauth2Session::V1 {
+                            parent, rs_uuid, ..
+                        }
+                        | DbValueOauth2Session::V2 {
+                            parent, rs_uuid, ..
+                        } => (Some(*parent), *rs_uuid),
+                        DbValueOauth2Session::V3 {
+                            parent, rs_uuid, ..
+                        } => (*parent, *rs_uuid),
+                    };
+                    refers.extend(parent);
+                    refers.insert(rs_uuid);
+                }
+            }
+            DbValueSetV2::ApplicationPassword(set) => {
+                for app_pw in set {
+                    let DbValueApplicationPassword::V1 {
+                        application_refer, ..
+                    } = app_pw;
+                    refers.insert(*application_refer);
+                }
+            }
+            // These do not refer to other entries. This is exhaustive so that a new
+            // variant must be considered here, else refint would miss its references.
+            DbValueSetV2::Utf8(_)
+            | DbValueSetV2::Iutf8(_)
+            | DbValueSetV2::Iname(_)
+            | DbValueSetV2::Uuid(_)
+            | DbValueSetV2::Bool(_)
+            | DbValueSetV2::SyntaxType(_)
+            | DbValueSetV2::IndexType(_)
+            | DbValueSetV2::JsonFilter(_)
+            | DbValueSetV2::Credential(_)
+            | DbValueSetV2::SecretValue(_)
+            | DbValueSetV2::SshKey(_)
+            | DbValueSetV2::Spn(_)
+            | DbValueSetV2::Uint32(_)
+            | DbValueSetV2::Int64(_)
+            | DbValueSetV2::Uint64(_)
+            | DbValueSetV2::Cid(_)
+            | DbValueSetV2::NsUniqueId(_)
+            | DbValueSetV2::DateTime(_)
+            | DbValueSetV2::EmailAddress(_, _)
+            | DbValueSetV2::PhoneNumber(_, _)
+            | DbValueSetV2::Address(_)
+            | DbValueSetV2::Url(_)
+            | DbValueSetV2::OauthScope(_)
+            | DbValueSetV2::PrivateBinary(_)
+            | DbValueSetV2::PublicBinary(_)
+            | DbValueSetV2::RestrictedString(_)
+            | DbValueSetV2::IntentToken(_)
+            | DbValueSetV2::Passkey(_)
+            | DbValueSetV2::AttestedPasskey(_)
+            | DbValueSetV2::TrustedDeviceEnrollment(_)
+            | DbValueSetV2::JwsKeyEs256(_)
+            | DbValueSetV2::JwsKeyRs256(_)
+            | DbValueSetV2::UiHint(_)
+            | DbValueSetV2::TotpSecret(_)
+            | DbValueSetV2::AuditLogString(_)
+            | DbValueSetV2::EcKeyPrivate(_)
+            | DbValueSetV2::Image(_)
+            | DbValueSetV2::CredentialType(_)
+            | DbValueSetV2::KeyInternal(_)
+            | DbValueSetV2::HexString(_)
+            | DbValueSetV2::Certificate(_)
+            | DbValueSetV2::Json(_)
+            | DbValueSetV2::Message(_)
+            | DbValueSetV2::Sha256(_)
+            | DbValueSetV2::ExternalId(_)
+            | DbValueSetV2::MfaGrace(_)
+            | DbValueSetV2::BreakGlass(_)
+            | DbValueSetV2::ExtensionJson(_)
+            | DbValueSetV2::WebauthnAttestationCaList { .. } => {}
+        }
+
+        refers.into_iter().collect()
+    }
+
+    /// Compare this value set to a newer one of the same attribute. Returns `None` if the
+    /// two sets are of different variants, as that is a change of type rather than of values.
+    ///
+    /// String and uuid variants are diffed element by element, with the results borrowed
+    /// from the two sets. All other variants, including those with secrets and those that
+    /// carry extra state such as a primary address, only report whether they changed.
+    pub fn diff<'a>(&'a self, other: &'a DbValueSetV2) -> Option<DbValueSetDiff<'a>> {
+        match (self, other) {
+            (DbValueSetV2::Utf8(old), DbValueSetV2::Utf8(new))
+            | (DbValueSetV2::Iutf8(old), DbValueSetV2::Iutf8(new))
+            | (DbValueSetV2::Iname(old), DbValueSetV2::Iname(new))
+            | (DbValueSetV2::JsonFilter(old), DbValueSetV2::JsonFilter(new))
+            | (DbValueSetV2::NsUniqueId(old), DbValueSetV2::NsUniqueId(new))
+            | (DbValueSetV2::DateTime(old), DbValueSetV2::DateTime(new))
+            | (DbValueSetV2::OauthScope(old), DbValueSetV2::OauthScope(new))
+            | (DbValueSetV2::RestrictedString(old), DbValueSetV2::RestrictedString(new))
+            | (DbValueSetV2::HexString(old), DbValueSetV2::HexString(new)) => {
+                let (added, removed) = diff_elements(old, new);
+                Some(DbValueSetDiff::Strings {
+                    added: added.into_iter().map(String::as_str).collect(),
+                    removed: removed.into_iter().map(String::as_str).collect(),
+                })
+            }
+            (DbValueSetV2::Uuid(old), DbValueSetV2::Uuid(new))
+            | (DbValueSetV2::Reference(old), DbValueSetV2::Reference(new))
+            | (
+                DbValueSetV2::TrustedDeviceEnrollment(old),
+                DbValueSetV2::TrustedDeviceEnrollment(new),
+            ) => {
+                let (added, removed) = diff_elements(old, new);
+                Some(DbValueSetDiff::Uuids { added, removed })
+            }
+            (old, new) if std::mem::discriminant(old) == std::mem::discriminant(new) => {
+                Some(DbValueSetDiff::Coarse {
+                    changed: old != new,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The result of [DbValueSetV2::diff].
+#[derive(Debug, PartialEq, Eq)]
+pub enum DbValueSetDiff<'a> {
+    Strings {
+        added: Vec<&'a str>,
+        removed: Vec<&'a str>,
+    },
+    Uuids {
+        added: Vec<&'a Uuid>,
+        removed: Vec<&'a Uuid>,
+    },
+    /// The variant isn't diffed by element, so only whether it changed at all is known.
+    Coarse { changed: bool },
+}
+
+impl DbValueSetDiff<'_> {
+    pub fn is_changed(&self) -> bool {
+        match self {
+            DbValueSetDiff::Strings { added, removed } => !added.is_empty() || !removed.is_empty(),
+            DbValueSetDiff::Uuids { added, removed } => !added.is_empty() || !removed.is_empty(),
+            DbValueSetDiff::Coarse { changed } => *changed,
+        }
+    }
+}
+
+/// Returns the elements only in `new` and the elements only in `old`, in their original order.
+fn diff_elements<'a, T>(old: &'a [T], new: &'a [T]) -> (Vec<&'a T>, Vec<&'a T>)
+where
+    T: Eq + std::hash::Hash,
+{
+    let old_set: HashSet<&T> = old.iter().collect();
+    let new_set: HashSet<&T> = new.iter().collect();
+
+    let added = new.iter().filter(|v| !old_set.contains(v)).collect();
+    let removed = old.iter().filter(|v| !new_set.contains(v)).collect();
+    (added, removed)
 }
 
 #[cfg(test)]
 mod tests {
     use base64::{engine::general_purpose, Engine as _};
+    use kanidm_proto::v1::OutboundMessage;
     use serde::{Deserialize, Serialize};
     use serde_with::skip_serializing_none;
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::time::Duration;
+    use time::OffsetDateTime;
     use uuid::Uuid;
+    use webauthn_rs::prelude::AttestationCaList;
 
-    use super::{DbBackupCodeV1, DbCred, DbPasswordV1, DbTotpV1, DbWebauthnV1};
+    use super::{
+        DbBackupCodeV1, DbCidV1, DbCred, DbPasswordV1, DbTotpV1, DbValueAccessScopeV1,
+        DbValueAuthTypeV1, DbValueBreakGlassV1, DbValueError, DbValueExtensionJsonV1,
+        DbValueExternalIdV1, DbValueIdentityId, DbValueIndexTypeV1, DbValueMfaGraceV1,
+        DbValueOauth2Session, DbValueOauthClaimMap, DbValueOauthClaimMapJoinV1,
+        DbValueOauthScopeMapV1, DbValueSession, DbValueSessionExtMetadataV1, DbValueSessionStateV1,
+        DbValueSetDiff, DbValueSetV2, DbValueSyntaxTypeV1, DbWebauthnV1,
+        DBVALUE_EXTENSION_JSON_MAX_BYTES,
+    };
 
     fn dbcred_type_default_pw() -> DbCredTypeV1 {
         DbCredTypeV1::Pw
@@ -988,6 +2002,172 @@ mod tests {
         pub uuid: Uuid,
     }
 
+    /// The on disk tag of each variant. These are a storage contract - changing one
+    /// would make every existing database unreadable, so this match must never be
+    /// altered, only extended.
+    fn dbvs_tag(dbvs: &DbValueSetV2) -> &'static str {
+        match dbvs {
+            DbValueSetV2::Utf8(_) => "U8",
+            DbValueSetV2::Iutf8(_) => "I8",
+            DbValueSetV2::Iname(_) => "N8",
+            DbValueSetV2::Uuid(_) => "UU",
+            DbValueSetV2::Bool(_) => "BO",
+            DbValueSetV2::SyntaxType(_) => "SY",
+            DbValueSetV2::IndexType(_) => "IN",
+            DbValueSetV2::Reference(_) => "RF",
+            DbValueSetV2::JsonFilter(_) => "JF",
+            DbValueSetV2::Credential(_) => "CR",
+            DbValueSetV2::SecretValue(_) => "RU",
+            DbValueSetV2::SshKey(_) => "SK",
+            DbValueSetV2::Spn(_) => "SP",
+            DbValueSetV2::Uint32(_) => "UI",
+            DbValueSetV2::Int64(_) => "I64",
+            DbValueSetV2::Uint64(_) => "U64",
+            DbValueSetV2::Cid(_) => "CI",
+            DbValueSetV2::NsUniqueId(_) => "NU",
+            DbValueSetV2::DateTime(_) => "DT",
+            DbValueSetV2::EmailAddress(_, _) => "EM",
+            DbValueSetV2::PhoneNumber(_, _) => "PN",
+            DbValueSetV2::Address(_) => "AD",
+            DbValueSetV2::Url(_) => "UR",
+            DbValueSetV2::OauthScope(_) => "OS",
+            DbValueSetV2::OauthScopeMap(_) => "OM",
+            DbValueSetV2::OauthClaimMap(_) => "OC",
+            DbValueSetV2::PrivateBinary(_) => "E2",
+            DbValueSetV2::PublicBinary(_) => "PB",
+            DbValueSetV2::RestrictedString(_) => "RS",
+            DbValueSetV2::IntentToken(_) => "IT",
+            DbValueSetV2::Passkey(_) => "PK",
+            DbValueSetV2::AttestedPasskey(_) => "DK",
+            DbValueSetV2::TrustedDeviceEnrollment(_) => "TE",
+            DbValueSetV2::Session(_) => "AS",
+            DbValueSetV2::JwsKeyEs256(_) => "JE",
+            DbValueSetV2::JwsKeyRs256(_) => "JR",
+            DbValueSetV2::Oauth2Session(_) => "OZ",
+            DbValueSetV2::UiHint(_) => "UH",
+            DbValueSetV2::TotpSecret(_) => "TO",
+            DbValueSetV2::ApiToken(_) => "AT",
+            DbValueSetV2::AuditLogString(_) => "SA",
+            DbValueSetV2::EcKeyPrivate(_) => "EK",
+            DbValueSetV2::Image(_) => "IM",
+            DbValueSetV2::CredentialType(_) => "CT",
+            DbValueSetV2::WebauthnAttestationCaList { .. } => "WC",
+            DbValueSetV2::KeyInternal(_) => "KI",
+            DbValueSetV2::HexString(_) => "HS",
+            DbValueSetV2::Certificate(_) => "X509",
+            DbValueSetV2::ApplicationPassword(_) => "AP",
+            DbValueSetV2::Json(_) => "JO",
+            DbValueSetV2::Message(_) => "MS",
+            DbValueSetV2::Sha256(_) => "S256",
+            DbValueSetV2::ExternalId(_) => "XI",
+            DbValueSetV2::MfaGrace(_) => "MG",
+            DbValueSetV2::BreakGlass(_) => "BG",
+            DbValueSetV2::ExtensionJson(_) => "EJ",
+        }
+    }
+
+    #[test]
+    fn test_dbvs_tag_stability() {
+        // One instance of every variant. The tag match above is exhaustive, so a new
+        // variant must be given a tag there, and should be added here too.
+        let all = vec![
+            DbValueSetV2::Utf8(vec!["a".to_string()]),
+            DbValueSetV2::Iutf8(vec!["a".to_string()]),
+            DbValueSetV2::Iname(vec!["a".to_string()]),
+            DbValueSetV2::Uuid(vec![Uuid::nil()]),
+            DbValueSetV2::Bool(vec![true]),
+            DbValueSetV2::SyntaxType(vec![DbValueSyntaxTypeV1::Utf8StringInsensitive]),
+            DbValueSetV2::IndexType(vec![DbValueIndexTypeV1::Presence]),
+            DbValueSetV2::Reference(vec![Uuid::nil()]),
+            DbValueSetV2::JsonFilter(vec!["{}".to_string()]),
+            DbValueSetV2::Credential(Vec::new()),
+            DbValueSetV2::SecretValue(vec!["a".to_string()]),
+            DbValueSetV2::SshKey(Vec::new()),
+            DbValueSetV2::Spn(vec![("name".to_string(), "example.com".to_string())]),
+            DbValueSetV2::Uint32(vec![1]),
+            DbValueSetV2::Int64(vec![-1]),
+            DbValueSetV2::Uint64(vec![1]),
+            DbValueSetV2::Cid(Vec::new()),
+            DbValueSetV2::NsUniqueId(vec!["a".to_string()]),
+            DbValueSetV2::DateTime(vec!["1970-01-01T00:00:00Z".to_string()]),
+            DbValueSetV2::EmailAddress(
+                "a@example.com".to_string(),
+                vec!["a@example.com".to_string(), "b@example.com".to_string()],
+            ),
+            DbValueSetV2::PhoneNumber("+1000".to_string(), vec!["+1000".to_string()]),
+            DbValueSetV2::Address(Vec::new()),
+            DbValueSetV2::Url(Vec::new()),
+            DbValueSetV2::OauthScope(vec!["openid".to_string()]),
+            DbValueSetV2::OauthScopeMap(Vec::new()),
+            DbValueSetV2::OauthClaimMap(Vec::new()),
+            DbValueSetV2::PrivateBinary(vec![vec![1]]),
+            DbValueSetV2::PublicBinary(vec![("a".to_string(), vec![1])]),
+            DbValueSetV2::RestrictedString(vec!["a".to_string()]),
+            DbValueSetV2::IntentToken(Vec::new()),
+            DbValueSetV2::Passkey(Vec::new()),
+            DbValueSetV2::AttestedPasskey(Vec::new()),
+            DbValueSetV2::TrustedDeviceEnrollment(vec![Uuid::nil()]),
+            DbValueSetV2::Session(Vec::new()),
+            DbValueSetV2::JwsKeyEs256(Vec::new()),
+            DbValueSetV2::JwsKeyRs256(Vec::new()),
+            DbValueSetV2::Oauth2Session(Vec::new()),
+            DbValueSetV2::UiHint(vec![1]),
+            DbValueSetV2::TotpSecret(Vec::new()),
+            DbValueSetV2::ApiToken(Vec::new()),
+            DbValueSetV2::AuditLogString(Vec::new()),
+            DbValueSetV2::EcKeyPrivate(vec![1]),
+            DbValueSetV2::Image(Vec::new()),
+            DbValueSetV2::CredentialType(vec![1]),
+            DbValueSetV2::WebauthnAttestationCaList {
+                ca_list: AttestationCaList::default(),
+            },
+            DbValueSetV2::KeyInternal(Vec::new()),
+            DbValueSetV2::HexString(vec!["00".to_string()]),
+            DbValueSetV2::Certificate(Vec::new()),
+            DbValueSetV2::ApplicationPassword(Vec::new()),
+            DbValueSetV2::Json(serde_json::json!({"a": 1})),
+            DbValueSetV2::Message(OutboundMessage::TestMessageV1 {
+                display_name: "a".to_string(),
+            }),
+            DbValueSetV2::Sha256(BTreeSet::new()),
+            DbValueSetV2::ExternalId(Vec::new()),
+            DbValueSetV2::MfaGrace(Vec::new()),
+            DbValueSetV2::BreakGlass(Vec::new()),
+            DbValueSetV2::ExtensionJson(Vec::new()),
+        ];
+
+        let mut seen = BTreeSet::new();
+        for dbvs in all.iter() {
+            let tag = dbvs_tag(dbvs);
+            assert!(seen.insert(tag), "duplicate tag {tag}");
+
+            let json = serde_json::to_string(dbvs).expect("Failed to serialise");
+            assert!(
+                json.starts_with(&format!("{{\"{tag}\":")),
+                "{dbvs:?} serialised with an unexpected tag: {json}"
+            );
+
+            let decoded: DbValueSetV2 = serde_json::from_str(&json).expect("Failed to deserialise");
+            assert_eq!(&decoded, dbvs);
+        }
+
+        // The tuple variants store their data positionally.
+        let golden = [
+            (&all[12], r#"{"SP":[["name","example.com"]]}"#),
+            (
+                &all[19],
+                r#"{"EM":["a@example.com",["a@example.com","b@example.com"]]}"#,
+            ),
+            (&all[20], r#"{"PN":["+1000",["+1000"]]}"#),
+        ];
+        for (dbvs, expect) in golden {
+            assert_eq!(
+                serde_json::to_string(dbvs).expect("Failed to serialise"),
+                expect
+            );
+        }
+    }
+
     #[test]
     fn test_dbcred_pre_totp_decode() {
         // This test exists to prove that the previous dbcredv1 format (without totp)
@@ -1015,4 +2195,636 @@ mod tests {
 
         // assert_eq!(dbcred,e_dbcred);
     }
+
+    #[test]
+    fn test_dbvs_external_id() {
+        assert!(DbValueExternalIdV1::new(String::new(), "abcd".to_string()).is_none());
+        assert!(DbValueExternalIdV1::new("ad".to_string(), String::new()).is_none());
+
+        let ad = DbValueExternalIdV1::new(
+            "ad".to_string(),
+            "f4d3ba3c-6fb6-4d4c-9c8e-6d0d3e4e9b3a".to_string(),
+        )
+        .unwrap();
+        let ldap = DbValueExternalIdV1::new(
+            "ldap".to_string(),
+            "uid=testuser,ou=people,dc=example,dc=com".to_string(),
+        )
+        .unwrap();
+        assert_eq!(ad.system(), "ad");
+        assert_eq!(
+            ldap.identifier(),
+            "uid=testuser,ou=people,dc=example,dc=com"
+        );
+
+        // Deserialisation is held to the same rules as the constructor.
+        assert!(serde_json::from_str::<DbValueSetV2>(r#"{"XI":[{"s":"ad","i":"abcd"}]}"#).is_ok());
+        assert!(serde_json::from_str::<DbValueSetV2>(r#"{"XI":[{"s":"","i":"abcd"}]}"#).is_err());
+        assert!(serde_json::from_str::<DbValueSetV2>(r#"{"XI":[{"s":"ad","i":""}]}"#).is_err());
+    }
+
+    #[test]
+    fn test_dbvs_extension_json() {
+        const ENTERPRISE: &str = "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User";
+
+        for urn in [
+            "",
+            "urn",
+            "urn:",
+            "urn:ietf",
+            "urn:ietf:",
+            "urn::params",
+            "urn:-ietf:params",
+            "urn:ie tf:params",
+            "urn:ietf:params scim",
+            "enterprise:2.0:User",
+            "https://example.com/scim",
+        ] {
+            assert!(
+                DbValueExtensionJsonV1::new(urn.to_string(), serde_json::json!({})).is_none(),
+                "{urn} should be rejected"
+            );
+        }
+
+        // Data at the limit is accepted, but not beyond it. The two bytes are the quotes.
+        let at_limit = "a".repeat(DBVALUE_EXTENSION_JSON_MAX_BYTES - 2);
+        assert!(DbValueExtensionJsonV1::new(ENTERPRISE.to_string(), at_limit.into()).is_some());
+        let too_large = "a".repeat(DBVALUE_EXTENSION_JSON_MAX_BYTES - 1);
+        assert!(DbValueExtensionJsonV1::new(ENTERPRISE.to_string(), too_large.into()).is_none());
+
+        let nested = serde_json::json!({
+            "employeeNumber": "701984",
+            "costCenter": 4130,
+            "active": true,
+            "manager": {
+                "value": "26118915-6090-4610-87e4-49d8ca9f808d",
+                "displayName": "John Smith",
+                "reports": [{ "value": 1 }, { "value": 2.5 }, null]
+            }
+        });
+
+        let enterprise = DbValueExtensionJsonV1::new(
+            "URN:ietf:params:scim:schemas:extension:enterprise:2.0:User".to_string(),
+            nested.clone(),
+        )
+        .unwrap();
+        let custom = DbValueExtensionJsonV1::new(
+            "urn:example:scim:1.0:Badge".to_string(),
+            serde_json::json!(["a", 1]),
+        )
+        .unwrap();
+        assert_eq!(custom.urn(), "urn:example:scim:1.0:Badge");
+        assert_eq!(enterprise.data(), &nested);
+
+        let dbvs = DbValueSetV2::ExtensionJson(vec![enterprise, custom]);
+        assert!(dbvs.referenced_uuids().is_empty());
+
+        // Deserialisation is held to the same rules as the constructor.
+        let json = serde_json::to_string(&dbvs).unwrap();
+        assert!(serde_json::from_str::<DbValueSetV2>(&json).is_ok());
+        let bad = json.replace("urn:example:scim:1.0:Badge", "urn:example");
+        assert!(serde_json::from_str::<DbValueSetV2>(&bad).is_err());
+        let oversized = serde_json::json!({
+            "EJ": [{ "u": ENTERPRISE, "d": "a".repeat(DBVALUE_EXTENSION_JSON_MAX_BYTES) }]
+        });
+        assert!(serde_json::from_value::<DbValueSetV2>(oversized).is_err());
+    }
+
+    #[test]
+    fn test_dbvs_mfa_grace() {
+        let granted_at = DbCidV1 {
+            timestamp: std::time::Duration::from_secs(1_700_000_000),
+            server_id: Uuid::new_v4(),
+        };
+
+        assert!(DbValueMfaGraceV1::new(granted_at.clone(), String::new()).is_none());
+        assert!(
+            DbValueMfaGraceV1::new(granted_at.clone(), "2024-13-01T00:00:00Z".to_string())
+                .is_none()
+        );
+
+        let grace =
+            DbValueMfaGraceV1::new(granted_at.clone(), "2023-11-21T22:13:20+10:00".to_string())
+                .unwrap();
+        assert_eq!(grace.granted_at(), &granted_at);
+        // The timestamp is stored as given.
+        assert_eq!(grace.expires_at(), "2023-11-21T22:13:20+10:00");
+
+        // Deserialisation is held to the same rules as the constructor.
+        let json = serde_json::to_string(&DbValueSetV2::MfaGrace(vec![grace])).unwrap();
+        assert!(serde_json::from_str::<DbValueSetV2>(&json).is_ok());
+        let bad = json.replace("2023-11-21T22:13:20+10:00", "2024-13-01T00:00:00Z");
+        assert!(serde_json::from_str::<DbValueSetV2>(&bad).is_err());
+    }
+
+    #[test]
+    fn test_dbvs_break_glass() {
+        let policy = kanidm_lib_crypto::CryptoPolicy::minimum();
+        let code_set: BTreeMap<Uuid, DbPasswordV1> =
+            ["correct-horse", "battery-staple", "tr0ub4dor"]
+                .into_iter()
+                .map(|code| {
+                    let pw = kanidm_lib_crypto::Password::new(&policy, code).unwrap();
+                    (Uuid::new_v4(), pw.to_dbpasswordv1())
+                })
+                .collect();
+        let consumed = code_set.keys().take(1).copied().collect();
+
+        let bg = DbValueBreakGlassV1 { code_set, consumed };
+        // The hashes must not be leaked via debug.
+        assert_eq!(format!("{bg:?}"), "codes: 3, consumed: 1");
+
+        // Only hashes are stored.
+        let json = serde_json::to_string(&DbValueSetV2::BreakGlass(vec![bg])).unwrap();
+        assert!(!json.contains("correct-horse"));
+    }
+
+    #[test]
+    fn test_dbvs_tagged_round_trip() {
+        let granted_at = DbCidV1 {
+            timestamp: std::time::Duration::from_secs(1_700_000_000),
+            server_id: Uuid::new_v4(),
+        };
+
+        let cases = [
+            (
+                "XI",
+                DbValueSetV2::ExternalId(vec![
+                    DbValueExternalIdV1::new("ad".to_string(), "abcd".to_string()).unwrap(),
+                    DbValueExternalIdV1::new("ldap".to_string(), "uid=a,dc=b".to_string()).unwrap(),
+                ]),
+                2,
+            ),
+            (
+                "MG",
+                DbValueSetV2::MfaGrace(vec![DbValueMfaGraceV1::new(
+                    granted_at,
+                    "2023-11-21T22:13:20+10:00".to_string(),
+                )
+                .unwrap()]),
+                1,
+            ),
+            (
+                "BG",
+                DbValueSetV2::BreakGlass(vec![DbValueBreakGlassV1 {
+                    code_set: [(
+                        Uuid::new_v4(),
+                        kanidm_lib_crypto::Password::new(
+                            &kanidm_lib_crypto::CryptoPolicy::minimum(),
+                            "correct-horse",
+                        )
+                        .unwrap()
+                        .to_dbpasswordv1(),
+                    )]
+                    .into_iter()
+                    .collect(),
+                    consumed: BTreeSet::new(),
+                }]),
+                1,
+            ),
+            (
+                "EJ",
+                DbValueSetV2::ExtensionJson(vec![DbValueExtensionJsonV1::new(
+                    "urn:example:scim:1.0:Badge".to_string(),
+                    serde_json::json!({ "manager": { "reports": [{ "value": 1 }, null] } }),
+                )
+                .unwrap()]),
+                1,
+            ),
+        ];
+
+        for (tag, dbvs, len) in cases {
+            assert_eq!(dbvs.len(), len, "{tag}");
+            assert!(!dbvs.is_empty(), "{tag}");
+
+            let json = serde_json::to_string(&dbvs).unwrap();
+            assert!(json.starts_with(&format!(r#"{{"{tag}":"#)), "{json}");
+
+            let dbvs_out: DbValueSetV2 = serde_json::from_str(&json).unwrap();
+            assert_eq!(dbvs, dbvs_out, "{tag}");
+
+            // An unknown tag must not be silently accepted.
+            let bad = json.replacen(tag, "ZZ", 1);
+            assert!(serde_json::from_str::<DbValueSetV2>(&bad).is_err(), "{tag}");
+        }
+    }
+
+    #[test]
+    fn test_dbvs_index_syntax_type_numeric() {
+        // These were previously stored as plain numbers, and must remain so.
+        let dbvs = DbValueSetV2::IndexType(vec![
+            DbValueIndexTypeV1::Equality,
+            DbValueIndexTypeV1::SubString,
+            DbValueIndexTypeV1::Unknown(900),
+        ]);
+        let json = serde_json::to_string(&dbvs).unwrap();
+        assert_eq!(json, r#"{"IN":[0,2,900]}"#);
+        let dbvs_out: DbValueSetV2 = serde_json::from_str(&json).unwrap();
+        assert_eq!(dbvs, dbvs_out);
+
+        let dbvs = DbValueSetV2::SyntaxType(vec![
+            DbValueSyntaxTypeV1::Utf8String,
+            DbValueSyntaxTypeV1::Uint64,
+            DbValueSyntaxTypeV1::Unknown(900),
+        ]);
+        let json = serde_json::to_string(&dbvs).unwrap();
+        assert_eq!(json, r#"{"SY":[0,46,900]}"#);
+        let dbvs_out: DbValueSetV2 = serde_json::from_str(&json).unwrap();
+        assert_eq!(dbvs, dbvs_out);
+
+        let cbor = serde_cbor::to_vec(&dbvs).unwrap();
+        assert_eq!(
+            cbor,
+            serde_cbor::to_vec(&BTreeMap::from([("SY", vec![0u16, 46, 900])])).unwrap()
+        );
+        let dbvs_out: DbValueSetV2 = serde_cbor::from_slice(&cbor).unwrap();
+        assert_eq!(dbvs, dbvs_out);
+
+        // Every number that is known to the server has a named variant, and the
+        // numbers survive a round trip.
+        for n in 0..=u8::MAX as u16 {
+            let index = DbValueIndexTypeV1::from(n);
+            assert_eq!(u16::from(index), n);
+            assert_eq!(
+                crate::value::IndexType::try_from(n).is_ok(),
+                !matches!(index, DbValueIndexTypeV1::Unknown(_))
+            );
+
+            let syntax = DbValueSyntaxTypeV1::from(n);
+            assert_eq!(u16::from(syntax), n);
+            assert_eq!(
+                crate::value::SyntaxType::try_from(n).is_ok(),
+                !matches!(syntax, DbValueSyntaxTypeV1::Unknown(_))
+            );
+        }
+    }
+
+    #[test]
+    fn test_dbvs_referenced_uuids_scope_map() {
+        let group_a = Uuid::new_v4();
+        let group_b = Uuid::new_v4();
+
+        let dbvs = DbValueSetV2::OauthScopeMap(vec![
+            DbValueOauthScopeMapV1 {
+                refer: group_b,
+                data: vec!["openid".to_string()],
+            },
+            DbValueOauthScopeMapV1 {
+                refer: group_a,
+                data: vec!["email".to_string()],
+            },
+        ]);
+        let mut expect = vec![group_a, group_b];
+        expect.sort();
+        assert_eq!(dbvs.referenced_uuids(), expect);
+
+        let dbvs = DbValueSetV2::OauthClaimMap(vec![DbValueOauthClaimMap::V2 {
+            name: "role".to_string(),
+            join: DbValueOauthClaimMapJoinV1::JsonArray,
+            values: [(group_a, vec![]), (group_b, vec![])].into_iter().collect(),
+        }]);
+        assert_eq!(dbvs.referenced_uuids(), expect);
+
+        // Values that are not references yield nothing.
+        assert!(DbValueSetV2::Uuid(vec![group_a])
+            .referenced_uuids()
+            .is_empty());
+        assert!(DbValueSetV2::Utf8(vec!["a".to_string()])
+            .referenced_uuids()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_dbvs_referenced_uuids_session() {
+        let session_id = Uuid::new_v4();
+        let issuer = Uuid::new_v4();
+        let cred_id = Uuid::new_v4();
+
+        let dbvs = DbValueSetV2::Session(vec![
+            DbValueSession::V5 {
+                refer: session_id,
+                label: "session".to_string(),
+                state: DbValueSessionStateV1::Never,
+                issued_at: "1970-01-01T00:00:00Z".to_string(),
+                issued_by: DbValueIdentityId::V1Uuid(issuer),
+                cred_id,
+                scope: DbValueAccessScopeV1::ReadOnly,
+                type_: DbValueAuthTypeV1::Password,
+                ext_metadata: DbValueSessionExtMetadataV1::None,
+                client_ip: None,
+                last_seen: None,
+            },
+            DbValueSession::V1 {
+                refer: Uuid::new_v4(),
+                label: "internal".to_string(),
+                expiry: None,
+                issued_at: "1970-01-01T00:00:00Z".to_string(),
+                issued_by: DbValueIdentityId::V1Internal,
+                scope: DbValueAccessScopeV1::ReadOnly,
+            },
+        ]);
+
+        let refers = dbvs.referenced_uuids();
+        assert_eq!(refers.len(), 2);
+        assert!(refers.contains(&issuer));
+        assert!(refers.contains(&cred_id));
+        // The session's own id is not a reference.
+        assert!(!refers.contains(&session_id));
+    }
+
+    #[test]
+    fn test_dbvs_referenced_uuids_oauth2_session() {
+        let session_id = Uuid::new_v4();
+        let parent = Uuid::new_v4();
+        let rs_uuid = Uuid::new_v4();
+
+        let dbvs = DbValueSetV2::Oauth2Session(vec![
+            DbValueOauth2Session::V3 {
+                refer: session_id,
+                parent: Some(parent),
+                state: DbValueSessionStateV1::Never,
+                issued_at: "1970-01-01T00:00:00Z".to_string(),
+                rs_uuid,
+            },
+            // Without a parent, and sharing the resource server.
+            DbValueOauth2Session::V3 {
+                refer: Uuid::new_v4(),
+                parent: None,
+                state: DbValueSessionStateV1::Never,
+                issued_at: "1970-01-01T00:00:00Z".to_string(),
+                rs_uuid,
+            },
+        ]);
+
+        let mut expect = vec![parent, rs_uuid];
+        expect.sort();
+        assert_eq!(dbvs.referenced_uuids(), expect);
+    }
+
+    #[test]
+    fn test_dbvs_diff() {
+        let old = DbValueSetV2::Iutf8(vec!["a".to_string(), "b".to_string()]);
+
+        // Added element
+        let new = DbValueSetV2::Iutf8(vec!["a".to_string(), "b".to_string(), "c".to_string()]);
+        let diff = old.diff(&new).unwrap();
+        assert!(diff.is_changed());
+        assert_eq!(
+            diff,
+            DbValueSetDiff::Strings {
+                added: vec!["c"],
+                removed: vec![],
+            }
+        );
+
+        // Removed element
+        let new = DbValueSetV2::Iutf8(vec!["b".to_string()]);
+        assert_eq!(
+            old.diff(&new),
+            Some(DbValueSetDiff::Strings {
+                added: vec![],
+                removed: vec!["a"],
+            })
+        );
+
+        // Unchanged
+        assert!(!old.diff(&old).unwrap().is_changed());
+
+        let u1 = Uuid::new_v4();
+        let u2 = Uuid::new_v4();
+        let old_refs = DbValueSetV2::Reference(vec![u1]);
+        let new_refs = DbValueSetV2::Reference(vec![u2]);
+        assert_eq!(
+            old_refs.diff(&new_refs),
+            Some(DbValueSetDiff::Uuids {
+                added: vec![&u2],
+                removed: vec![&u1],
+            })
+        );
+
+        // Variant mismatch, even with the same contents.
+        let new = DbValueSetV2::Utf8(vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(old.diff(&new), None);
+        assert_eq!(old_refs.diff(&DbValueSetV2::Uuid(vec![u1])), None);
+
+        // Complex variants are only diffed coarsely.
+        let old = DbValueSetV2::EmailAddress("a@example.com".to_string(), vec![]);
+        let new = DbValueSetV2::EmailAddress("b@example.com".to_string(), vec![]);
+        assert_eq!(
+            old.diff(&new),
+            Some(DbValueSetDiff::Coarse { changed: true })
+        );
+        assert_eq!(
+            old.diff(&old),
+            Some(DbValueSetDiff::Coarse { changed: false })
+        );
+    }
+
+    #[test]
+    fn test_dbvs2_datetime_valid() {
+        let set = DbValueSetV2::new_datetime(vec![
+            "2024-01-01T00:00:00Z".to_string(),
+            "2024-06-30T12:30:00+10:00".to_string(),
+        ])
+        .expect("valid timestamps were rejected");
+
+        let parsed = set.datetimes_parsed().expect("unable to parse timestamps");
+        assert_eq!(
+            parsed,
+            vec![
+                OffsetDateTime::from_unix_timestamp(1704067200).unwrap(),
+                OffsetDateTime::from_unix_timestamp(1719714600).unwrap(),
+            ]
+        );
+
+        // The wire format is unchanged.
+        assert_eq!(
+            set,
+            DbValueSetV2::DateTime(vec![
+                "2024-01-01T00:00:00Z".to_string(),
+                "2024-06-30T12:30:00+10:00".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_dbvs2_datetime_invalid() {
+        let values = vec!["2024-01-01T00:00:00Z".to_string(), "yesterday".to_string()];
+
+        assert_eq!(
+            DbValueSetV2::new_datetime(values.clone()),
+            Err(DbValueError::InvalidDateTime(1))
+        );
+
+        // Values that were persisted before validation are rejected on parse.
+        assert_eq!(
+            DbValueSetV2::DateTime(values).datetimes_parsed(),
+            Err(DbValueError::InvalidDateTime(1))
+        );
+
+        assert_eq!(
+            DbValueSetV2::Uint32(vec![1]).datetimes_parsed(),
+            Err(DbValueError::IncorrectVariant)
+        );
+    }
+
+    #[test]
+    fn test_dbvs2_enforce_single_bool() {
+        assert_eq!(
+            DbValueSetV2::Bool(vec![true]).enforce_single_bool(),
+            Ok(DbValueSetV2::Bool(vec![true]))
+        );
+        assert_eq!(
+            DbValueSetV2::Bool(Vec::new()).enforce_single_bool(),
+            Ok(DbValueSetV2::Bool(Vec::new()))
+        );
+        assert_eq!(
+            DbValueSetV2::Bool(vec![true, false]).enforce_single_bool(),
+            Err(DbValueError::MultipleValues(2))
+        );
+        assert_eq!(
+            DbValueSetV2::Uint32(vec![1]).enforce_single_bool(),
+            Err(DbValueError::IncorrectVariant)
+        );
+    }
+
+    #[test]
+    fn test_dbvs2_enforce_single() {
+        assert_eq!(
+            DbValueSetV2::Uint32(vec![1]).enforce_single(),
+            Ok(DbValueSetV2::Uint32(vec![1]))
+        );
+        assert_eq!(
+            DbValueSetV2::Iname(Vec::new()).enforce_single(),
+            Ok(DbValueSetV2::Iname(Vec::new()))
+        );
+        assert_eq!(
+            DbValueSetV2::DateTime(vec![
+                "2024-01-01T00:00:00Z".to_string(),
+                "2024-06-30T12:30:00Z".to_string(),
+                "2024-12-31T23:59:59Z".to_string(),
+            ])
+            .enforce_single(),
+            Err(DbValueError::MultipleValues(3))
+        );
+    }
+
+    #[test]
+    fn test_dbvs2_oauth_claim_map_v1_into_v2() {
+        let group_uuid = uuid::uuid!("5a6b8783-3f67-4ebb-b6aa-77fd6e66589f");
+
+        let v1 = DbValueOauthClaimMap::V1 {
+            name: "claim".to_string(),
+            join: DbValueOauthClaimMapJoinV1::JsonArray,
+            values: [(group_uuid, ["read".to_string(), "write".to_string()].into())].into(),
+        };
+
+        let (name, join, values) = v1.into_v2();
+        assert_eq!(name, "claim");
+        assert_eq!(join, DbValueOauthClaimMapJoinV1::JsonArray);
+        assert_eq!(
+            values.get(&group_uuid),
+            Some(&vec![
+                serde_json::Value::String("read".to_string()),
+                serde_json::Value::String("write".to_string()),
+            ])
+        );
+
+        // V2 maps of any value type survive a round trip through the db encoding.
+        let v2 = DbValueOauthClaimMap::V2 {
+            name: "claim".to_string(),
+            join: DbValueOauthClaimMapJoinV1::JsonArray,
+            values: [(
+                group_uuid,
+                vec![
+                    serde_json::Value::Bool(true),
+                    serde_json::json!(42),
+                    serde_json::json!([{ "tenant": "example" }]),
+                ],
+            )]
+            .into(),
+        };
+
+        let set = DbValueSetV2::OauthClaimMap(vec![v2]);
+        let data = serde_json::to_vec(&set).expect("unable to serialise");
+        let decoded: DbValueSetV2 = serde_json::from_slice(&data).expect("unable to deserialise");
+        assert_eq!(set, decoded);
+    }
+
+    #[test]
+    fn test_dbvalue_auth_type_public_mapping() {
+        use kanidm_proto::internal::AuthType;
+
+        let all = [
+            (DbValueAuthTypeV1::Anonymous, "\"an\""),
+            (DbValueAuthTypeV1::Password, "\"po\""),
+            (DbValueAuthTypeV1::GeneratedPassword, "\"pg\""),
+            (DbValueAuthTypeV1::PasswordTotp, "\"pt\""),
+            (DbValueAuthTypeV1::PasswordBackupCode, "\"pb\""),
+            (DbValueAuthTypeV1::PasswordSecurityKey, "\"ps\""),
+            (DbValueAuthTypeV1::Passkey, "\"as\""),
+            (DbValueAuthTypeV1::AttestedPasskey, "\"ap\""),
+            (DbValueAuthTypeV1::OAuth2Trust, "\"ot\""),
+        ];
+
+        let mut public = BTreeSet::new();
+        let mut labels = BTreeSet::new();
+
+        for (db, tag) in all {
+            // The db tags must never change.
+            assert_eq!(
+                serde_json::to_string(&db).expect("unable to serialise"),
+                tag
+            );
+
+            let pub_type = AuthType::from(db.clone());
+            assert_eq!(DbValueAuthTypeV1::from(pub_type), db);
+
+            let label = pub_type.to_string();
+            assert!(!label.is_empty());
+            assert!(public.insert(format!("{pub_type:?}")));
+            assert!(labels.insert(label));
+        }
+
+        assert_eq!(AuthType::PasswordTotp.to_string(), "Password + TOTP");
+        assert_eq!(AuthType::Passkey.to_string(), "Passkey");
+    }
+
+    #[test]
+    fn test_dbvaluesetv2_debug_redacts_secrets() {
+        let set = DbValueSetV2::SecretValue(vec!["very secret value".to_string()]);
+        let debug = format!("{set:?}");
+        assert!(!debug.contains("very secret value"));
+        assert_eq!(debug, "SecretValue { count: 1 }");
+
+        let set = DbValueSetV2::EcKeyPrivate(vec![0xAA; 32]);
+        assert_eq!(format!("{set:?}"), "EcKeyPrivate { len: 32 }");
+
+        // Non secret values are still shown.
+        let set = DbValueSetV2::Utf8(vec!["public value".to_string()]);
+        assert!(format!("{set:?}").contains("public value"));
+
+        // Sessions from an oauth2 trust carry the upstream tokens.
+        let set = DbValueSetV2::Session(vec![DbValueSession::V5 {
+            refer: Uuid::new_v4(),
+            label: "session".to_string(),
+            state: DbValueSessionStateV1::Never,
+            issued_at: "1970-01-01T00:00:00Z".to_string(),
+            issued_by: DbValueIdentityId::V1Internal,
+            cred_id: Uuid::new_v4(),
+            scope: DbValueAccessScopeV1::ReadOnly,
+            type_: DbValueAuthTypeV1::OAuth2Trust,
+            ext_metadata: DbValueSessionExtMetadataV1::OAuth2 {
+                access_expires_at: Duration::from_secs(300),
+                access_token: "upstream access token".to_string(),
+                refresh_token: Some("upstream refresh token".to_string()),
+            },
+            client_ip: None,
+            last_seen: None,
+        }]);
+        let debug = format!("{set:?}");
+        assert!(!debug.contains("upstream access token"));
+        assert!(!debug.contains("upstream refresh token"));
+        assert!(debug.contains("access_expires_at"));
+    }
 }
@@ -396,6 +396,9 @@ pub trait IdlArcSqliteTransaction {
 
     fn is_dirty(&self) -> bool;
 
+    /// The ids of all entries, as known to the cache.
+    fn get_allids(&self) -> IDLBitRange;
+
     fn name2uuid(&mut self, name: &str) -> Result<Option<Uuid>, OperationError>;
 
     fn externalid2uuid(&mut self, name: &str) -> Result<Option<Uuid>, OperationError>;
@@ -468,6 +471,10 @@ impl IdlArcSqliteTransaction for IdlArcSqliteReadTransaction<'_> {
         false
     }
 
+    fn get_allids(&self) -> IDLBitRange {
+        (*self.allids).clone()
+    }
+
     fn name2uuid(&mut self, name: &str) -> Result<Option<Uuid>, OperationError> {
         name2uuid!(self, name)
     }
@@ -566,6 +573,10 @@ impl IdlArcSqliteTransaction for IdlArcSqliteWriteTransaction<'_> {
         self.entry_cache.is_dirty()
     }
 
+    fn get_allids(&self) -> IDLBitRange {
+        (*self.allids).clone()
+    }
+
     fn name2uuid(&mut self, name: &str) -> Result<Option<Uuid>, OperationError> {
         name2uuid!(self, name)
     }
@@ -1232,6 +1243,14 @@ impl IdlArcSqliteWriteTransaction<'_> {
         self.db.set_db_index_version(v)
     }
 
+    pub(crate) fn get_db_credential_version(&self) -> Result<i64, OperationError> {
+        self.db.get_db_credential_version()
+    }
+
+    pub(crate) fn set_db_credential_version(&self, v: i64) -> Result<(), OperationError> {
+        self.db.set_db_credential_version(v)
+    }
+
     pub fn setup(&mut self) -> Result<(), OperationError> {
         self.db
             .setup()
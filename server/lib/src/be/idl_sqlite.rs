@@ -19,6 +19,7 @@ use uuid::Uuid;
 
 const DBV_ID2ENTRY: &str = "id2entry";
 const DBV_INDEXV: &str = "indexv";
+const DBV_CREDV: &str = "credv";
 
 #[allow(clippy::needless_pass_by_value)] // needs to accept value from `map_err`
 pub(super) fn sqlite_error(e: rusqlite::Error) -> OperationError {
@@ -1488,6 +1489,14 @@ impl IdlSqliteWriteTransaction {
         self.set_db_version_key(DBV_INDEXV, v)
     }
 
+    pub(crate) fn get_db_credential_version(&self) -> Result<i64, OperationError> {
+        self.get_db_version_key(DBV_CREDV)
+    }
+
+    pub(crate) fn set_db_credential_version(&self, v: i64) -> Result<(), OperationError> {
+        self.set_db_version_key(DBV_CREDV, v)
+    }
+
     pub fn setup(&self) -> Result<(), OperationError> {
         // If the db_name is NOT main, we MAY need to create it as we are in
         // a test!
@@ -4,8 +4,9 @@
 //! is to persist content safely to disk, load that content, and execute queries
 //! utilising indexes in the most effective way possible.
 
-use crate::be::dbentry::{DbBackup, DbEntry};
+use crate::be::dbentry::{DbBackup, DbEntry, DbEntryVers};
 use crate::be::dbrepl::DbReplMeta;
+use crate::be::dbvalue::{DbCred, DbValueSetV2};
 use crate::entry::Entry;
 use crate::filter::{Filter, FilterPlan, FilterResolved, FilterValidResolved};
 use crate::prelude::*;
@@ -56,6 +57,9 @@ const FILTER_SEARCH_TEST_THRESHOLD: usize = 0;
 const FILTER_EXISTS_TEST_THRESHOLD: usize = 0;
 const FILTER_SUBSTR_TEST_THRESHOLD: usize = 4;
 
+// The number of entries compared at a time when verifying the entry cache.
+const VERIFY_ENTRY_CACHE_BATCH_SIZE: usize = 256;
+
 #[derive(Debug, Clone)]
 /// Limits on the resources a single event can consume. These are defined per-event
 /// as they are derived from the userAuthToken based on that individual session
@@ -122,6 +126,23 @@ pub struct IdRawEntry {
     data: Vec<u8>,
 }
 
+/// The content of an entry exactly as it is stored in id2entry, along with each
+/// attribute's stored value. This is only used by debugging tools.
+#[derive(Debug)]
+pub struct IdRawEntryDump {
+    pub id: u64,
+    pub data: Vec<u8>,
+    pub attrs: Vec<IdRawAttrDump>,
+}
+
+#[derive(Debug)]
+pub struct IdRawAttrDump {
+    pub attr: String,
+    pub data: Vec<u8>,
+    /// The decoded value set, or the reason it could not be decoded.
+    pub decoded: Result<String, String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct IdxMeta {
     pub idxkeys: HashMap<IdxKey, IdxSlope>,
@@ -213,6 +234,41 @@ impl IdRawEntry {
             .map(|dbe| (self.id, dbe))
     }
 
+    fn into_dump(self) -> Result<IdRawEntryDump, OperationError> {
+        // We deliberately avoid deserialising to a DbEntry here, so that an entry that can't
+        // be deserialised can still be inspected.
+        let value: serde_json::Value =
+            serde_json::from_slice(self.data.as_slice()).map_err(|e| {
+                admin_error!(?e, id = %self.id, "Serde JSON Error");
+                OperationError::SerdeJsonError
+            })?;
+
+        let attrs = value
+            .get("ent")
+            .and_then(|ent| ent.get("V3"))
+            .and_then(|ent| ent.get("attrs"))
+            .and_then(|attrs| attrs.as_object())
+            .map(|attrs| {
+                attrs
+                    .iter()
+                    .map(|(attr, value)| IdRawAttrDump {
+                        attr: attr.clone(),
+                        data: serde_json::to_vec(value).unwrap_or_default(),
+                        decoded: serde_json::from_value::<DbValueSetV2>(value.clone())
+                            .map(|vs| format!("{vs:#?}"))
+                            .map_err(|e| e.to_string()),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(IdRawEntryDump {
+            id: self.id,
+            data: self.data,
+            attrs,
+        })
+    }
+
     fn into_entry(self) -> Result<EntrySealedCommitted, OperationError> {
         let db_e = serde_json::from_slice(self.data.as_slice()).map_err(|e| {
             admin_error!(?e, id = %self.id, "Serde JSON Error");
@@ -823,6 +879,27 @@ pub trait BackendTransaction {
         } // end match idl
     }
 
+    /// Count the live entries that hold `class`, using only the class equality
+    /// index. Recycled and tombstoned entries are excluded. No entries are loaded.
+    #[instrument(level = "debug", name = "be::count_class", skip_all)]
+    fn count_class(&mut self, class: &PartialValue) -> Result<u64, OperationError> {
+        let mut get_class_idl = |pv: &PartialValue| {
+            self.get_idlayer()
+                .get_idl(&Attribute::Class, IndexType::Equality, &pv.get_idx_eq_key())?
+                .ok_or_else(|| {
+                    admin_error!(?pv, "class equality index is missing or corrupt");
+                    OperationError::InvalidState
+                })
+        };
+
+        let idl = get_class_idl(class)?;
+        let recycled = get_class_idl(&EntryClass::Recycled.to_partialvalue())?;
+        let tombstone = get_class_idl(&EntryClass::Tombstone.to_partialvalue())?;
+
+        let live = idl.andnot(recycled).andnot(tombstone);
+        Ok(live.len() as u64)
+    }
+
     fn retrieve_range(
         &mut self,
         ranges: &BTreeMap<Uuid, ReplCidRange>,
@@ -943,6 +1020,69 @@ pub trait BackendTransaction {
         }
     }
 
+    /// Check that the entries held in the cache match those in the database, reporting
+    /// the attributes that differ for any that have diverged.
+    fn verify_entry_cache(&mut self) -> Vec<Result<(), ConsistencyError>> {
+        // A dirty cache holds changes that are not yet written to the database.
+        if self.get_idlayer().is_dirty() {
+            return Vec::with_capacity(0);
+        }
+
+        // Compare in batches so that only a batch of entries is held in memory at a time.
+        let allids: Vec<u64> = self.get_idlayer().get_allids().into_iter().collect();
+        let mut results = Vec::with_capacity(0);
+
+        for batch in allids.chunks(VERIFY_ENTRY_CACHE_BATCH_SIZE) {
+            let idl = IdList::Indexed(IDLBitRange::from_iter(batch.iter().copied()));
+
+            let entries = match self.get_idlayer().get_identry(&idl) {
+                Ok(s) => s,
+                Err(e) => {
+                    admin_error!(?e, "get_identry failure");
+                    return vec![Err(ConsistencyError::Unknown)];
+                }
+            };
+
+            let db_entries: Result<BTreeMap<u64, EntrySealedCommitted>, _> = self
+                .get_idlayer()
+                .get_identry_raw(&idl)
+                .and_then(|raw_entries| {
+                    raw_entries
+                        .into_iter()
+                        .map(|raw| raw.into_entry().map(|e| (e.get_id(), e)))
+                        .collect()
+                });
+            let db_entries = match db_entries {
+                Ok(s) => s,
+                Err(e) => {
+                    admin_error!(?e, "get_identry_raw failure");
+                    return vec![Err(ConsistencyError::Unknown)];
+                }
+            };
+
+            results.extend(entries.iter().filter_map(|e| {
+                let id = e.get_id();
+                match db_entries.get(&id) {
+                    Some(db_e) if db_e == e.as_ref() => None,
+                    Some(db_e) => {
+                        admin_error!(
+                            %id,
+                            diff = ?db_e.diff(e.as_ref()),
+                            "Cached entry has diverged from the database"
+                        );
+                        Some(Err(ConsistencyError::BackendEntryCacheSync(id)))
+                    }
+                    None => {
+                        admin_error!(%id, "Cached entry is missing from the database");
+                        Some(Err(ConsistencyError::BackendEntryCacheSync(id)))
+                    }
+                }
+            }));
+        }
+
+        results
+    }
+
     fn verify_ruv(&mut self, results: &mut Vec<Result<(), ConsistencyError>>) {
         // The way we verify this is building a whole second RUV and then comparing it.
         let idl = IdList::AllIds;
@@ -1097,6 +1237,22 @@ impl BackendReadTransaction<'_> {
     pub fn list_quarantined(&mut self) -> Result<Vec<(u64, String)>, OperationError> {
         self.get_idlayer().list_quarantined()
     }
+
+    pub fn get_id2entry_raw(&mut self, uuid: Uuid) -> Result<IdRawEntryDump, OperationError> {
+        let idx_key = PartialValue::Uuid(uuid).get_idx_eq_key();
+        let id = self
+            .get_idlayer()
+            .get_idl(&Attribute::Uuid, IndexType::Equality, &idx_key)?
+            .and_then(|idl| idl.into_iter().next())
+            .ok_or(OperationError::NoMatchingEntries)?;
+
+        let idl = IdList::Indexed(IDLBitRange::from_u64(id));
+        self.get_idlayer()
+            .get_identry_raw(&idl)?
+            .pop()
+            .ok_or(OperationError::InvalidEntryId)
+            .and_then(|data| data.into_dump())
+    }
 }
 
 impl<'a> BackendTransaction for BackendWriteTransaction<'a> {
@@ -1838,6 +1994,8 @@ impl<'a> BackendWriteTransaction<'a> {
         self.get_idlayer()
             .danger_purge_id2entry()
             .and_then(|_| self.danger_purge_idxs())
+            // Restored content may hold legacy credentials again.
+            .and_then(|_| self.set_db_credential_version(0))
     }
 
     #[cfg(test)]
@@ -2051,6 +2209,55 @@ impl<'a> BackendWriteTransaction<'a> {
         Ok(())
     }
 
+    /// Scan the raw content of id2entry for credentials that are still stored in a legacy
+    /// format, returning the uuid of each affected entry along with those credentials.
+    ///
+    /// NOTE: This deliberately reads the serialised form, since once an entry is loaded the
+    /// credentials are converted in memory and we can no longer tell which format they had.
+    /// As a result, only content committed by a previous transaction is examined.
+    pub fn list_legacy_credentials(&mut self) -> Result<Vec<(Uuid, Vec<DbCred>)>, OperationError> {
+        let idl = IdList::AllIds;
+        let raw_entries = self.get_idlayer().get_identry_raw(&idl)?;
+
+        let mut legacy = Vec::with_capacity(0);
+
+        for raw_entry in raw_entries {
+            let (id, dbe) = raw_entry.into_dbentry()?;
+            let DbEntryVers::V3 { mut attrs, .. } = dbe.ent;
+
+            let entry_uuid = match attrs.get(&Attribute::Uuid) {
+                Some(DbValueSetV2::Uuid(uuids)) => match uuids.as_slice() {
+                    [entry_uuid] => *entry_uuid,
+                    _ => {
+                        admin_warn!(?id, "entry has no valid uuid, ignoring");
+                        continue;
+                    }
+                },
+                _ => {
+                    admin_warn!(?id, "entry has no valid uuid, ignoring");
+                    continue;
+                }
+            };
+
+            let legacy_creds: Vec<DbCred> = attrs
+                .values_mut()
+                .filter_map(|vs| match vs {
+                    DbValueSetV2::Credential(creds) => Some(std::mem::take(creds)),
+                    _ => None,
+                })
+                .flatten()
+                .map(|cred| cred.data)
+                .filter(DbCred::is_legacy)
+                .collect();
+
+            if !legacy_creds.is_empty() {
+                legacy.push((entry_uuid, legacy_creds));
+            }
+        }
+
+        Ok(legacy)
+    }
+
     pub fn quarantine_entry(&mut self, id: u64) -> Result<(), OperationError> {
         self.get_idlayer().quarantine_entry(id)?;
         // We have to set the index version to 0 so that on next start we force
@@ -2070,6 +2277,30 @@ impl<'a> BackendWriteTransaction<'a> {
         self.get_idlayer().clear_cache()
     }
 
+    /// Directly alter the serialised form of every entry. This exists so that tests can
+    /// create content in legacy formats that the server would no longer write.
+    #[cfg(test)]
+    pub(crate) fn danger_rewrite_entries_raw<F>(&mut self, mut f: F) -> Result<(), OperationError>
+    where
+        F: FnMut(&mut DbEntry),
+    {
+        let idlayer = self.get_idlayer();
+        let raw_entries = idlayer.get_identry_raw(&IdList::AllIds)?;
+
+        let raw_entries: Result<Vec<IdRawEntry>, _> = raw_entries
+            .into_iter()
+            .map(|raw_entry| {
+                let (id, mut dbe) = raw_entry.into_dbentry()?;
+                f(&mut dbe);
+                serde_json::to_vec(&dbe)
+                    .map_err(|_| OperationError::SerdeJsonError)
+                    .map(|data| IdRawEntry { id, data })
+            })
+            .collect();
+
+        idlayer.write_identries_raw(raw_entries?.into_iter())
+    }
+
     pub fn commit(self) -> Result<(), OperationError> {
         let BackendWriteTransaction {
             mut idlayer,
@@ -2154,6 +2385,16 @@ impl<'a> BackendWriteTransaction<'a> {
     fn set_db_index_version(&mut self, v: i64) -> Result<(), OperationError> {
         self.get_idlayer().set_db_index_version(v)
     }
+
+    /// The version of the local credential storage format. This records that legacy
+    /// credentials were migrated, so that the scan for them only needs to run once.
+    pub(crate) fn get_db_credential_version(&mut self) -> Result<i64, OperationError> {
+        self.get_idlayer().get_db_credential_version()
+    }
+
+    pub(crate) fn set_db_credential_version(&mut self, v: i64) -> Result<(), OperationError> {
+        self.get_idlayer().set_db_credential_version(v)
+    }
 }
 
 // We have a number of hardcoded, "obvious" slopes that should
@@ -2280,7 +2521,7 @@ mod tests {
     use super::Limits;
     use super::{
         Backend, BackendConfig, BackendTransaction, BackendWriteTransaction, DbBackup, IdList,
-        IdxKey, OperationError,
+        IdxKey, OperationError, VERIFY_ENTRY_CACHE_BATCH_SIZE,
     };
     use crate::prelude::*;
     use crate::repl::cid::Cid;
@@ -4002,4 +4243,111 @@ mod tests {
         let r = be_b_txn.search(&lims, &filt);
         assert!(r.expect("Search failed!").len() == 1);
     }
+
+    #[test]
+    fn test_be_verify_entry_cache() {
+        sketching::test_init();
+
+        let idxmeta = vec![IdxKey {
+            attr: Attribute::Uuid,
+            itype: IndexType::Equality,
+        }];
+
+        let be = Backend::new(BackendConfig::new_test("main"), idxmeta, false)
+            .expect("Failed to setup backend");
+
+        let mut be_txn = be.write().unwrap();
+        assert!(be_txn.reindex(false).is_ok());
+        let mut e: Entry<EntryInit, EntryNew> = Entry::new();
+        e.add_ava(Attribute::UserId, Value::from("william"));
+        e.add_ava(
+            Attribute::Uuid,
+            Value::from("db237e8a-0079-4b8c-8a56-593b22aa44d1"),
+        );
+        let e = e.into_sealed_new();
+        assert!(be_txn.create(&CID_ZERO, vec![e]).is_ok());
+
+        // The new entry is only in the cache until commit, so it can't be compared yet.
+        assert!(be_txn.verify_entry_cache().is_empty());
+        assert!(be_txn.commit().is_ok());
+
+        // Once committed the cached entry matches the database.
+        let mut be_txn = be.read().unwrap();
+        let filt = filter_resolved!(f_eq(Attribute::UserId, PartialValue::new_utf8s("william")));
+        let r = be_txn.search(&Limits::unlimited(), &filt);
+        assert_eq!(r.expect("Search failed!").len(), 1);
+        assert!(be_txn.verify_entry_cache().is_empty());
+        drop(be_txn);
+
+        // Enough entries that they are compared over multiple batches.
+        let mut be_txn = be.write().unwrap();
+        let entries = (0..VERIFY_ENTRY_CACHE_BATCH_SIZE)
+            .map(|i| {
+                let mut e: Entry<EntryInit, EntryNew> = Entry::new();
+                e.add_ava(Attribute::UserId, Value::new_utf8s(&format!("user{i}")));
+                e.add_ava(Attribute::Uuid, Value::Uuid(Uuid::new_v4()));
+                e.into_sealed_new()
+            })
+            .collect();
+        assert!(be_txn.create(&CID_ZERO, entries).is_ok());
+        assert!(be_txn.commit().is_ok());
+
+        let mut be_txn = be.read().unwrap();
+        let filt = filter_resolved!(f_pres(Attribute::UserId));
+        let r = be_txn.search(&Limits::unlimited(), &filt);
+        assert_eq!(
+            r.expect("Search failed!").len(),
+            VERIFY_ENTRY_CACHE_BATCH_SIZE + 1
+        );
+        assert!(be_txn.verify_entry_cache().is_empty());
+    }
+
+    #[test]
+    fn test_be_get_id2entry_raw() {
+        sketching::test_init();
+
+        let idxmeta = vec![IdxKey {
+            attr: Attribute::Uuid,
+            itype: IndexType::Equality,
+        }];
+
+        let be = Backend::new(BackendConfig::new_test("main"), idxmeta, false)
+            .expect("Failed to setup backend");
+
+        let uuid = uuid::uuid!("db237e8a-0079-4b8c-8a56-593b22aa44d1");
+
+        let mut be_txn = be.write().unwrap();
+        assert!(be_txn.reindex(false).is_ok());
+        let mut e: Entry<EntryInit, EntryNew> = Entry::new();
+        e.add_ava(Attribute::UserId, Value::from("william"));
+        e.add_ava(Attribute::Uuid, Value::Uuid(uuid));
+        let e = e.into_sealed_new();
+        assert!(be_txn.create(&CID_ZERO, vec![e]).is_ok());
+        assert!(be_txn.commit().is_ok());
+
+        let mut be_txn = be.read().unwrap();
+        let dump = be_txn
+            .get_id2entry_raw(uuid)
+            .expect("Failed to get raw entry");
+
+        assert_eq!(dump.id, 1);
+        assert!(!dump.data.is_empty());
+
+        let userid = dump
+            .attrs
+            .iter()
+            .find(|attr_dump| attr_dump.attr == Attribute::UserId.as_str())
+            .expect("userid not present");
+        assert!(!userid.data.is_empty());
+        assert!(userid
+            .decoded
+            .as_ref()
+            .expect("Failed to decode userid")
+            .contains("william"));
+
+        assert_eq!(
+            be_txn.get_id2entry_raw(Uuid::new_v4()).unwrap_err(),
+            OperationError::NoMatchingEntries
+        );
+    }
 }
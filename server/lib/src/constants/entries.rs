@@ -39,6 +39,7 @@ pub enum EntryClass {
     ExtensibleObject,
     Feature,
     Group,
+    IdentityProviderCertificate,
     KeyProvider,
     KeyProviderInternal,
     KeyObject,
@@ -108,6 +109,7 @@ impl From<EntryClass> for &'static str {
             EntryClass::ExtensibleObject => ENTRYCLASS_EXTENSIBLE_OBJECT,
             EntryClass::Feature => ENTRYCLASS_FEATURE,
             EntryClass::Group => ENTRYCLASS_GROUP,
+            EntryClass::IdentityProviderCertificate => ENTRYCLASS_IDENTITY_PROVIDER_CERTIFICATE,
             EntryClass::KeyProvider => ENTRYCLASS_KEY_PROVIDER,
             EntryClass::KeyProviderInternal => ENTRYCLASS_KEY_PROVIDER_INTERNAL,
             EntryClass::KeyObject => ENTRYCLASS_KEY_OBJECT,
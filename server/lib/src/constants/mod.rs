@@ -168,6 +168,10 @@ pub const RECYCLEBIN_MAX_AGE: u64 = 300;
 /// In production we allow 1 week
 pub const RECYCLEBIN_MAX_AGE: u64 = 7 * 86400;
 
+/// The default number of days that revoked sessions are retained before they are
+/// removed, allowing the revocation to replicate.
+pub const DEFAULT_REVOKED_SESSION_RETENTION_DAYS: u32 = 7;
+
 // 5 minute auth session window.
 pub const AUTH_SESSION_TIMEOUT: u64 = 300;
 // 5 minute mfa reg window
@@ -184,12 +188,19 @@ pub const MAXIMUM_AUTH_PRIVILEGE_EXPIRY: u32 = 3600;
 pub const DEFAULT_AUTH_PRIVILEGE_EXPIRY: u32 = 600;
 // Default - directly privileged sessions only last 1 hour.
 pub const DEFAULT_AUTH_SESSION_LIMITED_EXPIRY: u32 = 3600;
+/// How often the use of a session is recorded. Uses within this interval of the last
+/// recorded use are not written, so that every request doesn't cause a write.
+pub const SESSION_LAST_SEEN_INTERVAL: Duration = Duration::from_secs(300);
 // Default - oauth refresh tokens last for 16 hours.
 pub const OAUTH_REFRESH_TOKEN_EXPIRY: u32 = 3600 * 16;
 
 /// How long access tokens should last. This is NOT the length
 /// of the refresh token, which is bound to the issuing session.
 pub const OAUTH2_ACCESS_TOKEN_EXPIRY: u32 = 15 * 60;
+/// The shortest access token expiry that a client may be configured with.
+pub const OAUTH2_ACCESS_TOKEN_EXPIRY_MINIMUM: u32 = 30;
+/// The longest access token expiry that a client may be configured with.
+pub const OAUTH2_ACCESS_TOKEN_EXPIRY_MAXIMUM: u32 = 3600;
 
 /// The absolute maximum that can be requested for max-age requests in OIDC.
 pub const OAUTH2_OIDC_MAX_AGE_CLAMP: i64 = 86400;
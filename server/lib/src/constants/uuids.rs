@@ -394,6 +394,21 @@ pub const UUID_SCHEMA_ATTR_DOMAIN_ALLOW_ACCOUNT_RECOVERY: Uuid =
     uuid!("00000000-0000-0000-0000-ffff00000224");
 pub const UUID_SCHEMA_ATTR_OAUTH2_REFRESH_TOKEN_EXPIRY: Uuid =
     uuid!("00000000-0000-0000-0000-ffff00000225");
+pub const UUID_SCHEMA_ATTR_DOMAIN_REVOKED_SESSION_RETENTION_DAYS: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000226");
+pub const UUID_SCHEMA_ATTR_CREATED_AT: Uuid = uuid!("00000000-0000-0000-0000-ffff00000227");
+pub const UUID_SCHEMA_ATTR_OAUTH2_ACCESS_TOKEN_EXPIRY: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000228");
+pub const UUID_SCHEMA_ATTR_ACP_DENY_ATTRIBUTE: Uuid = uuid!("00000000-0000-0000-0000-ffff00000229");
+pub const UUID_SCHEMA_ATTR_IDP_CERTIFICATE: Uuid = uuid!("00000000-0000-0000-0000-ffff00000230");
+pub const UUID_SCHEMA_CLASS_IDENTITY_PROVIDER_CERTIFICATE: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000231");
+pub const UUID_SCHEMA_ATTR_DOMAIN_IMAGE_MAX_WIDTH: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000232");
+pub const UUID_SCHEMA_ATTR_DOMAIN_IMAGE_MAX_HEIGHT: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000233");
+pub const UUID_SCHEMA_ATTR_DOMAIN_IMAGE_MAX_BYTES: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000234");
 
 // =====
 // Incorrectly name spaced.
@@ -531,6 +546,8 @@ pub const UUID_INTERNAL_MIGRATION: Uuid = uuid!("00000000-0000-0000-0000-ffffff0
 pub const UUID_INTERNAL_SESSION_ID: Uuid = uuid!("00000000-0000-0000-0000-ffffff000083");
 pub const UUID_INTERNAL_ACCOUNT_REQUEST: Uuid = uuid!("00000000-0000-0000-0000-ffffff000084");
 pub const UUID_INTERNAL_MESSAGE_QUEUE: Uuid = uuid!("00000000-0000-0000-0000-ffffff000085");
+pub const UUID_IDM_ACP_HP_IDP_CERTIFICATE_MANAGER: Uuid =
+    uuid!("00000000-0000-0000-0000-ffffff000086");
 
 // End of system ranges
 pub const UUID_DOES_NOT_EXIST: Uuid = uuid!("00000000-0000-0000-0000-fffffffffffe");
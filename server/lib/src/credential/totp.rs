@@ -1,3 +1,7 @@
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE},
+    Engine as _,
+};
 use crypto_glue::{
     hmac_s1::{HmacSha1, HmacSha1Key},
     hmac_s256::{HmacSha256, HmacSha256Key},
@@ -5,6 +9,7 @@ use crypto_glue::{
     traits::Mac,
 };
 use kanidm_proto::internal::{TotpAlgo as ProtoTotpAlgo, TotpSecret as ProtoTotp};
+use kanidm_proto::scim_v1::{ScimTotp, ScimTotpError, TotpAlgorithm};
 use rand::RngExt;
 use std::convert::{TryFrom, TryInto};
 use std::time::{Duration, SystemTime};
@@ -14,6 +19,9 @@ use crate::be::dbvalue::{DbTotpAlgoV1, DbTotpV1};
 // This is the same size as an AES256KEY making it infeasible to bruteforce.
 const SECRET_SIZE_BYTES: usize = 32;
 pub const TOTP_DEFAULT_STEP: u64 = 30;
+/// The shortest step a sync agent may import. Unlike [ScimTotp::validate] there is no
+/// upper bound, as sync imports have always accepted any longer step.
+pub const TOTP_IMPORT_MIN_STEP: u32 = 30;
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum TotpError {
@@ -112,6 +120,26 @@ impl TotpAlgo {
     }
 }
 
+impl From<TotpAlgorithm> for TotpAlgo {
+    fn from(value: TotpAlgorithm) -> Self {
+        match value {
+            TotpAlgorithm::Sha1 => TotpAlgo::Sha1,
+            TotpAlgorithm::Sha256 => TotpAlgo::Sha256,
+            TotpAlgorithm::Sha512 => TotpAlgo::Sha512,
+        }
+    }
+}
+
+impl From<TotpAlgo> for TotpAlgorithm {
+    fn from(value: TotpAlgo) -> Self {
+        match value {
+            TotpAlgo::Sha1 => TotpAlgorithm::Sha1,
+            TotpAlgo::Sha256 => TotpAlgorithm::Sha256,
+            TotpAlgo::Sha512 => TotpAlgorithm::Sha512,
+        }
+    }
+}
+
 /// <https://tools.ietf.org/html/rfc6238> which relies on <https://tools.ietf.org/html/rfc4226>
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Totp {
@@ -159,6 +187,69 @@ impl TryFrom<ProtoTotp> for Totp {
     }
 }
 
+impl TryFrom<&ScimTotp> for Totp {
+    type Error = ScimTotpError;
+
+    /// Convert a sync TOTP import. The secret may be either url safe or standard
+    /// base64, as sync agents are not consistent about this.
+    fn try_from(value: &ScimTotp) -> Result<Self, Self::Error> {
+        if value.step < TOTP_IMPORT_MIN_STEP {
+            return Err(ScimTotpError::InvalidStep(value.step));
+        }
+
+        let secret = URL_SAFE
+            .decode(value.secret.as_str())
+            .or_else(|_| STANDARD.decode(value.secret.as_str()))
+            .map_err(|_| ScimTotpError::InvalidSecret)?;
+
+        let digits = u8::try_from(value.digits)
+            .ok()
+            .and_then(|digits| TotpDigits::try_from(digits).ok())
+            .ok_or(ScimTotpError::InvalidDigits(value.digits))?;
+
+        Ok(Totp {
+            secret,
+            step: value.step.into(),
+            algo: value.algo.into(),
+            digits,
+        })
+    }
+}
+
+impl TryFrom<ScimTotp> for DbTotpV1 {
+    type Error = ScimTotpError;
+
+    /// Convert a sync TOTP import to its stored form, labelled with its external id.
+    fn try_from(value: ScimTotp) -> Result<Self, Self::Error> {
+        let totp = Totp::try_from(&value)?;
+        Ok(DbTotpV1 {
+            label: value.external_id,
+            ..totp.to_dbtotpv1()
+        })
+    }
+}
+
+impl TryFrom<&DbTotpV1> for ScimTotp {
+    type Error = ScimTotpError;
+
+    fn try_from(value: &DbTotpV1) -> Result<Self, Self::Error> {
+        // Matches the default used when loading a Totp.
+        let digits = value.digits.unwrap_or(6);
+        let totp = Totp {
+            secret: value.key.clone(),
+            step: value.step,
+            algo: match value.algo {
+                DbTotpAlgoV1::S1 => TotpAlgo::Sha1,
+                DbTotpAlgoV1::S256 => TotpAlgo::Sha256,
+                DbTotpAlgoV1::S512 => TotpAlgo::Sha512,
+            },
+            digits: TotpDigits::try_from(digits)
+                .map_err(|()| ScimTotpError::InvalidDigits(digits.into()))?,
+        };
+        totp.to_scim(&value.label)
+    }
+}
+
 impl Totp {
     pub fn new(secret: Vec<u8>, step: u64, algo: TotpAlgo, digits: TotpDigits) -> Self {
         Totp {
@@ -267,6 +358,24 @@ impl Totp {
         }
     }
 
+    /// The sync import form of this TOTP, the inverse of `TryFrom<&ScimTotp>`.
+    pub fn to_scim(&self, label: &str) -> Result<ScimTotp, ScimTotpError> {
+        let digits: u8 = self.digits.into();
+        let totp = ScimTotp {
+            external_id: label.to_string(),
+            secret: URL_SAFE.encode(&self.secret),
+            algo: self.algo.into(),
+            step: u32::try_from(self.step).map_err(|_| ScimTotpError::InvalidStep(u32::MAX))?,
+            digits: digits.into(),
+        };
+
+        if totp.step < TOTP_IMPORT_MIN_STEP {
+            return Err(ScimTotpError::InvalidStep(totp.step));
+        }
+
+        Ok(totp)
+    }
+
     pub fn is_legacy_algo(&self) -> bool {
         matches!(&self.algo, TotpAlgo::Sha1)
     }
@@ -285,6 +394,10 @@ impl Totp {
 mod tests {
     use std::time::Duration;
 
+    use base64::{engine::general_purpose, Engine as _};
+    use kanidm_proto::scim_v1::{ScimTotp, ScimTotpError, TotpAlgorithm};
+
+    use crate::be::dbvalue::{DbTotpAlgoV1, DbTotpV1};
     use crate::credential::totp::{Totp, TotpAlgo, TotpDigits, TotpError, TOTP_DEFAULT_STEP};
 
     #[test]
@@ -414,4 +527,109 @@ mod tests {
         // This is step + 1
         assert!(!otp.verify(972806, d));
     }
+
+    #[test]
+    fn totp_scim_conversion() {
+        let secret = vec![0x5a; 20];
+
+        for (algo, totp_algo) in [
+            (TotpAlgorithm::Sha1, TotpAlgo::Sha1),
+            (TotpAlgorithm::Sha256, TotpAlgo::Sha256),
+            (TotpAlgorithm::Sha512, TotpAlgo::Sha512),
+        ] {
+            let scim_totp = ScimTotp {
+                external_id: "Totp".to_string(),
+                secret: general_purpose::URL_SAFE.encode(&secret),
+                algo,
+                step: 30,
+                digits: 8,
+            };
+
+            let totp = Totp::try_from(&scim_totp).expect("Failed to convert totp");
+            assert_eq!(
+                totp,
+                Totp::new(secret.clone(), 30, totp_algo, TotpDigits::Eight)
+            );
+
+            let scim_totp = totp.to_scim("Totp").expect("Failed to convert totp");
+            assert_eq!(scim_totp.external_id, "Totp");
+            assert_eq!(scim_totp.algo, algo);
+            assert_eq!(scim_totp.step, 30);
+            assert_eq!(scim_totp.digits, 8);
+            assert_eq!(Totp::try_from(&scim_totp), Ok(totp));
+        }
+
+        let scim_totp = ScimTotp {
+            external_id: "Totp".to_string(),
+            secret: general_purpose::STANDARD.encode([0xfb; 20]),
+            algo: TotpAlgorithm::Sha1,
+            step: 30,
+            digits: 6,
+        };
+
+        // Standard base64 is accepted as well as url safe.
+        assert_eq!(
+            Totp::try_from(&scim_totp),
+            Ok(Totp::new(
+                vec![0xfb; 20],
+                30,
+                TotpAlgo::Sha1,
+                TotpDigits::Six
+            ))
+        );
+
+        let mut bad = scim_totp.clone();
+        bad.secret = "not base64!".to_string();
+        assert_eq!(Totp::try_from(&bad), Err(ScimTotpError::InvalidSecret));
+
+        let mut bad = scim_totp.clone();
+        bad.digits = 7;
+        assert_eq!(Totp::try_from(&bad), Err(ScimTotpError::InvalidDigits(7)));
+
+        let mut bad = scim_totp.clone();
+        bad.step = 0;
+        assert_eq!(Totp::try_from(&bad), Err(ScimTotpError::InvalidStep(0)));
+
+        // Imports keep their historical bounds of at least 30 seconds, with no upper
+        // limit, even though ScimTotp::validate is stricter.
+        let mut bad = scim_totp.clone();
+        bad.step = 15;
+        assert_eq!(Totp::try_from(&bad), Err(ScimTotpError::InvalidStep(15)));
+
+        let mut long = scim_totp.clone();
+        long.step = 600;
+        assert!(Totp::try_from(&long).is_ok());
+
+        // The stored form carries the external id as its label.
+        let dbtotp = DbTotpV1::try_from(long.clone()).expect("Failed to convert totp");
+        assert_eq!(dbtotp.label, "Totp");
+        assert_eq!(dbtotp.key, vec![0xfb; 20]);
+        assert_eq!(dbtotp.step, 600);
+        assert_eq!(dbtotp.algo, DbTotpAlgoV1::S1);
+        assert_eq!(dbtotp.digits, Some(6));
+
+        let round_trip = ScimTotp::try_from(&dbtotp).expect("Failed to convert totp");
+        assert_eq!(round_trip.external_id, "Totp");
+        assert_eq!(round_trip.step, 600);
+        assert_eq!(DbTotpV1::try_from(round_trip), Ok(dbtotp));
+
+        let mut bad = long;
+        bad.digits = 7;
+        assert_eq!(
+            DbTotpV1::try_from(bad),
+            Err(ScimTotpError::InvalidDigits(7))
+        );
+
+        // Totps that can't be expressed as an import are rejected.
+        let totp = Totp::new(secret, u64::MAX, TotpAlgo::Sha256, TotpDigits::Six);
+        assert_eq!(
+            totp.to_scim("Totp").map(|_| ()),
+            Err(ScimTotpError::InvalidStep(u32::MAX))
+        );
+
+        // Algorithms we don't support are refused when the import is parsed.
+        let data =
+            r#"{"external_id":"Totp","secret":"abcd","algo":"SHA3-256","step":30,"digits":6}"#;
+        assert!(serde_json::from_str::<ScimTotp>(data).is_err());
+    }
 }
@@ -36,11 +36,11 @@ use crate::prelude::*;
 use crate::repl::cid::Cid;
 use crate::repl::entry::EntryChangeState;
 use crate::repl::proto::{ReplEntryV1, ReplIncrementalEntryV1};
-use crate::schema::{SchemaAttribute, SchemaClass, SchemaTransaction};
+use crate::schema::{SchemaAttribute, SchemaClass, SchemaClassCache, SchemaTransaction};
 use crate::server::access::AccessEffectivePermission;
 use crate::value::{
-    ApiToken, CredentialType, IndexType, IntentTokenState, Oauth2Session, PartialValue, Session,
-    SyntaxType, Value,
+    ApiToken, AttestedPasskeyAttestation, CredentialType, IndexType, IntentTokenState,
+    Oauth2Session, PartialValue, Session, SyntaxType, Value,
 };
 use crate::valueset::{self, ScimResolveStatus, ValueSet, ValueSetSpn};
 use compact_jwt::JwsEs256Signer;
@@ -50,7 +50,8 @@ use kanidm_proto::internal::ImageValue;
 use kanidm_proto::internal::{
     ConsistencyError, Filter as ProtoFilter, OperationError, SchemaError, UiHint,
 };
-use kanidm_proto::scim_v1::server::ScimEffectiveAccess;
+use kanidm_proto::scim_v1::server::{is_active, ScimEffectiveAccess};
+use kanidm_proto::scim_v1::{ScimAttributeProfile, ScimMeta};
 use kanidm_proto::v1::Entry as ProtoEntry;
 use ldap3_proto::simple::{LdapPartialAttribute, LdapSearchResultEntry};
 use std::cmp::Ordering;
@@ -159,6 +160,12 @@ pub struct EntrySealed {
 pub struct EntryReduced {
     uuid: Uuid,
     effective_access: Option<Box<AccessEffectivePermission>>,
+    // When the entry was created and last changed, for the SCIM meta.
+    created: Cid,
+    last_modified: Cid,
+    // If the account validity window survived the reduction, so that active can be
+    // derived from it.
+    validity_visible: bool,
 }
 
 // One day this is going to be Map<Attribute, ValueSet> - @yaleman
@@ -198,6 +205,95 @@ pub(crate) fn compare_attrs(left: &Eattrs, right: &Eattrs) -> bool {
     })
 }
 
+/// The difference of a single attribute between two entries, as returned by [`Entry::diff`].
+///
+/// The debug output redacts secret values such as credentials, so a diff can be logged.
+#[derive(Clone)]
+pub enum AttributeDiff {
+    /// The attribute is only present on the other entry.
+    Added { attr: Attribute, values: Vec<Value> },
+    /// The attribute is only present on this entry.
+    Removed { attr: Attribute, values: Vec<Value> },
+    /// The attribute is present on both entries, but the values differ. Only the values
+    /// that are not present on both sides are listed.
+    Changed {
+        attr: Attribute,
+        old_values: Vec<Value>,
+        new_values: Vec<Value>,
+    },
+}
+
+/// Formats values the way the database would store them, which redacts any secrets.
+struct RedactedValues<'a>(&'a [Value]);
+
+impl std::fmt::Debug for RedactedValues<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0.is_empty() {
+            return f.debug_list().finish();
+        }
+        match valueset::from_value_iter(self.0.iter().cloned()) {
+            Ok(vs) => vs.to_db_valueset_v2().fmt(f),
+            Err(_) => f
+                .debug_struct("InvalidValues")
+                .field("count", &self.0.len())
+                .finish(),
+        }
+    }
+}
+
+impl std::fmt::Debug for AttributeDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AttributeDiff::Added { attr, values } => f
+                .debug_struct("Added")
+                .field("attr", attr)
+                .field("values", &RedactedValues(values))
+                .finish(),
+            AttributeDiff::Removed { attr, values } => f
+                .debug_struct("Removed")
+                .field("attr", attr)
+                .field("values", &RedactedValues(values))
+                .finish(),
+            AttributeDiff::Changed {
+                attr,
+                old_values,
+                new_values,
+            } => f
+                .debug_struct("Changed")
+                .field("attr", attr)
+                .field("old_values", &RedactedValues(old_values))
+                .field("new_values", &RedactedValues(new_values))
+                .finish(),
+        }
+    }
+}
+
+/// Compare two values by converting them to single value sets, since [`Value`]
+/// equality is not defined for every syntax.
+fn value_equal(left: &Value, right: &Value) -> bool {
+    match (
+        valueset::from_value_iter(std::iter::once(left.clone())),
+        valueset::from_value_iter(std::iter::once(right.clone())),
+    ) {
+        (Ok(l), Ok(r)) => l.eq(&r),
+        _ => false,
+    }
+}
+
+/// The values of `left` that are not present in `right`.
+fn valueset_difference(left: &ValueSet, right: &ValueSet) -> Vec<Value> {
+    let right: BTreeMap<PartialValue, Value> = right
+        .to_partialvalue_iter()
+        .zip(right.to_value_iter())
+        .collect();
+
+    left.to_partialvalue_iter()
+        .zip(left.to_value_iter())
+        .filter(|(pv, v)| right.get(pv).map(|rv| !value_equal(v, rv)).unwrap_or(true))
+        .map(|(_, v)| v)
+        .collect()
+}
+
 /// Entry is the core data storage type of the server. Almost every aspect of the server is
 /// designed to read, handle and manipulate entries.
 ///
@@ -1112,6 +1208,24 @@ impl<STATE> Entry<EntryInvalid, STATE> {
     pub fn validate(
         self,
         schema: &dyn SchemaTransaction,
+    ) -> Result<Entry<EntryValid, STATE>, SchemaError> {
+        self.validate_with(schema, None)
+    }
+
+    /// As [Self::validate], reusing the class resolution of previously validated entries
+    /// from `cache`. The cache must only be used with the schema it was populated from.
+    pub(crate) fn validate_cached<'a>(
+        self,
+        schema: &'a dyn SchemaTransaction,
+        cache: &mut SchemaClassCache<'a>,
+    ) -> Result<Entry<EntryValid, STATE>, SchemaError> {
+        self.validate_with(schema, Some(cache))
+    }
+
+    fn validate_with<'a>(
+        self,
+        schema: &'a dyn SchemaTransaction,
+        cache: Option<&mut SchemaClassCache<'a>>,
     ) -> Result<Entry<EntryValid, STATE>, SchemaError> {
         let uuid: Uuid = self
             .attrs
@@ -1132,7 +1246,7 @@ impl<STATE> Entry<EntryInvalid, STATE> {
             attrs: self.attrs,
         };
 
-        ne.validate(schema).map(|()| ne)
+        ne.validate_with(schema, cache).map(|()| ne)
     }
 
     /// Access a reference set in a directly mutable form. This is "safe" because
@@ -1235,6 +1349,15 @@ impl Entry<EntryInvalid, EntryCommitted> {
 // Both invalid states can be reached from "entry -> invalidate"
 
 impl Entry<EntryInvalid, EntryNew> {
+    /// ⚠️  - A tombstone that is created by a restore must keep its tombstone change
+    /// state, so that it is replicated and reaped as a tombstone rather than as a live
+    /// entry that happens to have a tombstone class.
+    pub(crate) fn restore_tombstone_changestate(&mut self) {
+        if self.attribute_equality(Attribute::Class, &EntryClass::Tombstone.into()) {
+            self.valid.ecstate.tombstone(&self.valid.cid);
+        }
+    }
+
     /// This function steps back from EntryInvalid to EntryInit.
     /// This is a TEST ONLY method and will never be exposed in production.
     #[cfg(test)]
@@ -1927,6 +2050,9 @@ impl Entry<EntrySealed, EntryCommitted> {
             valid: EntryReduced {
                 uuid: self.valid.uuid,
                 effective_access: None,
+                created: self.valid.ecstate.at().clone(),
+                last_modified: self.valid.ecstate.get_max_cid().clone(),
+                validity_visible: true,
             },
             state: self.state,
             attrs: self.attrs,
@@ -1953,9 +2079,18 @@ impl Entry<EntrySealed, EntryCommitted> {
             })
             .collect();
 
+        // Active is derived from the validity window, so it may only be shown when every
+        // part of the window that the entry has can be read.
+        let validity_visible = [Attribute::AccountValidFrom, Attribute::AccountExpire]
+            .iter()
+            .all(|attr| !self.attrs.contains_key(attr) || allowed_attrs.contains(attr));
+
         let valid = EntryReduced {
             uuid: self.valid.uuid,
             effective_access,
+            created: self.valid.ecstate.at().clone(),
+            last_modified: self.valid.ecstate.get_max_cid().clone(),
+            validity_visible,
         };
         let state = self.state.clone();
 
@@ -2016,7 +2151,16 @@ impl Entry<EntrySealed, EntryCommitted> {
 
 impl<STATE> Entry<EntryValid, STATE> {
     fn validate(&self, schema: &dyn SchemaTransaction) -> Result<(), SchemaError> {
-        let schema_classes = schema.get_classes();
+        self.validate_with(schema, None)
+    }
+
+    /// Validate this entry, resolving its classes through `cache` if one is given. A
+    /// single entry is validated without a cache, as building one would only add cost.
+    fn validate_with<'a>(
+        &self,
+        schema: &'a dyn SchemaTransaction,
+        cache: Option<&mut SchemaClassCache<'a>>,
+    ) -> Result<(), SchemaError> {
         let schema_attributes = schema.get_attributes();
 
         // Now validate it!
@@ -2046,103 +2190,23 @@ impl<STATE> Entry<EntryValid, STATE> {
             admin_debug!("Attribute '{}' missing from entry", Attribute::Class);
             SchemaError::NoClassFound
         })?;
-        let mut invalid_classes = Vec::with_capacity(0);
-
-        let mut classes: Vec<&SchemaClass> = Vec::with_capacity(entry_classes.len());
 
         // We need to keep the btreeset of entry classes here so we can check the
         // requires and excludes.
-        let entry_classes = if let Some(ec) = entry_classes.as_iutf8_set() {
-            ec.iter()
-                .for_each(|s| match schema_classes.get(s.as_str()) {
-                    Some(x) => classes.push(x),
-                    None => {
-                        admin_debug!("invalid class: {:?}", s);
-                        invalid_classes.push(s.to_string())
-                    }
-                });
-            ec
-        } else {
+        let Some(entry_classes) = entry_classes.as_iutf8_set() else {
             admin_debug!("corrupt class attribute");
             return Err(SchemaError::NoClassFound);
         };
 
-        if !invalid_classes.is_empty() {
-            return Err(SchemaError::InvalidClass(invalid_classes));
-        };
-
-        // Now determine the set of excludes and requires we have, and then
-        // assert we don't violate them.
-
-        let supplements_classes: Vec<_> = classes
-            .iter()
-            .flat_map(|cls| cls.systemsupplements.iter().chain(cls.supplements.iter()))
-            .collect();
-
-        // So long as one supplement is present we can continue.
-        let valid_supplements = if supplements_classes.is_empty() {
-            // No need to check.
-            true
-        } else {
-            supplements_classes
-                .iter()
-                .any(|class| entry_classes.contains(class.as_str()))
-        };
-
-        if !valid_supplements {
-            warn!(
-                "Validation error, the following possible supplement classes are missing - {:?}",
-                supplements_classes
-            );
-            let supplements_classes = supplements_classes.iter().map(|s| s.to_string()).collect();
-            return Err(SchemaError::SupplementsNotSatisfied(supplements_classes));
-        }
-
-        let excludes_classes: Vec<_> = classes
-            .iter()
-            .flat_map(|cls| cls.systemexcludes.iter().chain(cls.excludes.iter()))
-            .collect();
-
-        let mut invalid_excludes = Vec::with_capacity(0);
-
-        excludes_classes.iter().for_each(|class| {
-            if entry_classes.contains(class.as_str()) {
-                invalid_excludes.push(class.to_string())
+        let uncached;
+        let resolved = match cache {
+            Some(cache) => cache.resolve(schema, entry_classes)?,
+            None => {
+                uncached = SchemaClassCache::resolve_uncached(schema, entry_classes)?;
+                &uncached
             }
-        });
-
-        if !invalid_excludes.is_empty() {
-            admin_warn!(
-                "Validation error, the following excluded classes are present - {:?}",
-                invalid_excludes
-            );
-            return Err(SchemaError::ExcludesNotSatisfied(invalid_excludes));
-        }
-
-        // What this is really doing is taking a set of classes, and building an
-        // "overall" class that describes this exact object for checking. IE we
-        // build a super must/may set from the small class must/may sets.
-
-        //   for each class
-        //      add systemmust/must and systemmay/may to their lists
-        //      add anything from must also into may
-
-        // Now from the set of valid classes make a list of must/may
-        //
-        // NOTE: We still need this on extensible, because we still need to satisfy
-        // our other must conditions as well!
-        let must: Result<Vec<&SchemaAttribute>, _> = classes
-            .iter()
-            // Join our class systemmmust + must into one iter
-            .flat_map(|cls| cls.systemmust.iter().chain(cls.must.iter()))
-            .map(|s| {
-                // This should NOT fail - if it does, it means our schema is
-                // in an invalid state!
-                schema_attributes.get(s).ok_or(SchemaError::Corrupted)
-            })
-            .collect();
-
-        let must = must?;
+        };
+        let must = &resolved.must;
 
         // Check that all must are inplace
         //   for each attr in must, check it's present on our ent
@@ -2205,25 +2269,7 @@ impl<STATE> Entry<EntryValid, STATE> {
             // The set of "may" is a combination of may and must, since we have already
             // asserted that all must requirements are fulfilled. This allows us to
             // perform extended attribute checking in a single pass.
-            let may: Result<Map<&Attribute, &SchemaAttribute>, _> = classes
-                .iter()
-                // Join our class systemmmust + must + systemmay + may into one.
-                .flat_map(|cls| {
-                    trace!(?cls);
-                    cls.systemmust
-                        .iter()
-                        .chain(cls.must.iter())
-                        .chain(cls.systemmay.iter())
-                        .chain(cls.may.iter())
-                })
-                .map(|s| {
-                    // This should NOT fail - if it does, it means our schema is
-                    // in an invalid state!
-                    Ok((s, schema_attributes.get(s).ok_or(SchemaError::Corrupted)?))
-                })
-                .collect();
-
-            let may = may?;
+            let may = resolved.may.as_ref().ok_or(SchemaError::Corrupted)?;
 
             // TODO #70: Error needs to say what is missing
             // We need to return *all* missing attributes, not just the first error
@@ -2423,6 +2469,9 @@ impl Entry<EntryReduced, EntryCommitted> {
             .iter()
             // We want to skip some attributes as they are already in the header.
             .filter(|(k, _vs)| **k != Attribute::Uuid)
+            // Write only values such as passwords are never sent back. Anything
+            // else still present was requested and survived access controls.
+            .filter(|(k, _vs)| ScimAttributeProfile::for_attribute(k).is_returned(true))
             .filter_map(|(k, vs)| {
                 let opt_resolve_status = vs.to_scim_value();
                 let res_opt_scim_value = match opt_resolve_status {
@@ -2438,7 +2487,22 @@ impl Entry<EntryReduced, EntryCommitted> {
             })
             .collect();
 
-        let attrs = result?;
+        let mut attrs = result?;
+
+        // Active is not stored, it's derived from the validity window of the account.
+        if self.valid.validity_visible
+            && self.attribute_equality(Attribute::Class, &EntryClass::Account.into())
+        {
+            let now = OffsetDateTime::UNIX_EPOCH + read_txn.get_curtime();
+            let active = is_active(
+                now,
+                self.get_ava_single_datetime(Attribute::AccountValidFrom)
+                    .as_ref(),
+                self.get_ava_single_datetime(Attribute::AccountExpire)
+                    .as_ref(),
+            );
+            attrs.insert(Attribute::Active, ScimValueKanidm::Bool(active));
+        }
 
         let ext_access_check = self.valid.effective_access.as_ref().map(|eff_acc| {
             let ident = eff_acc.ident;
@@ -2462,15 +2526,42 @@ impl Entry<EntryReduced, EntryCommitted> {
         // to achieve this.
         let schemas = Vec::with_capacity(0);
 
+        let resource_type = if self.attribute_equality(Attribute::Class, &EntryClass::Person.into())
+        {
+            "Person"
+        } else if self.attribute_equality(Attribute::Class, &EntryClass::Group.into()) {
+            "Group"
+        } else {
+            "Entry"
+        };
+
+        let version = ScimMeta::version_of(&attrs).map_err(|err| {
+            error!(?err, "Unable to compute the version of a scim entry");
+            OperationError::SerdeJsonError
+        })?;
+
+        // The server doesn't know the url it is reached by, so the location is the uuid
+        // of the entry. The http layer replaces this with the url of the entry.
+        let location = Url::parse(&format!("urn:uuid:{id}")).map_err(|err| {
+            error!(?err, "Unable to build the location of a scim entry");
+            OperationError::InvalidState
+        })?;
+
+        let meta = ScimMeta {
+            resource_type: resource_type.to_string(),
+            created: OffsetDateTime::UNIX_EPOCH + self.valid.created.ts,
+            last_modified: OffsetDateTime::UNIX_EPOCH + self.valid.last_modified.ts,
+            location,
+            version,
+        };
+
         Ok(ScimEntryKanidm {
             header: ScimEntryHeader {
                 schemas,
                 id,
                 // TODO: Should be spn / name or uuid.
                 external_id: None,
-                // TODO - this one will be useful in future, but we need to change
-                // entry to store some extra metadata.
-                meta: None,
+                meta: Some(meta),
             },
             ext_access_check,
             attrs,
@@ -2668,6 +2759,32 @@ impl<VALID, STATE> Entry<VALID, STATE> {
         self.attrs.iter()
     }
 
+    /// Compute the attribute level differences from this entry to `other`. Multi-value
+    /// attributes are compared as sets, so only the values that differ are reported.
+    pub fn diff<VALID2, STATE2>(&self, other: &Entry<VALID2, STATE2>) -> Vec<AttributeDiff> {
+        let allkeys: BTreeSet<&Attribute> = self.attrs.keys().chain(other.attrs.keys()).collect();
+
+        allkeys
+            .into_iter()
+            .filter_map(|attr| match (self.attrs.get(attr), other.attrs.get(attr)) {
+                (Some(old), None) => Some(AttributeDiff::Removed {
+                    attr: attr.clone(),
+                    values: old.to_value_iter().collect(),
+                }),
+                (None, Some(new)) => Some(AttributeDiff::Added {
+                    attr: attr.clone(),
+                    values: new.to_value_iter().collect(),
+                }),
+                (Some(old), Some(new)) if !old.eq(new) => Some(AttributeDiff::Changed {
+                    attr: attr.clone(),
+                    old_values: valueset_difference(old, new),
+                    new_values: valueset_difference(new, old),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
     /// Return a reference to the current set of values that are associated to this attribute.
     pub fn get_ava_set<A: AsRef<Attribute>>(&self, attr: A) -> Option<&ValueSet> {
         self.attrs.get(attr.as_ref())
@@ -2844,6 +2961,15 @@ impl<VALID, STATE> Entry<VALID, STATE> {
             .and_then(|vs| vs.as_attestedpasskey_map())
     }
 
+    /// Get how each attested passkey on this account was attested, if any are present.
+    pub fn get_ava_attestedpasskey_attestations<A: AsRef<Attribute>>(
+        &self,
+        attr: A,
+    ) -> Option<&BTreeMap<Uuid, AttestedPasskeyAttestation>> {
+        self.get_ava_set(attr)
+            .and_then(|vs| vs.as_attestedpasskey_attestation_map())
+    }
+
     /// Get the set of uihints on this account, if any are present.
     pub fn get_ava_uihint<A: AsRef<Attribute>>(&self, attr: A) -> Option<&BTreeSet<UiHint>> {
         self.get_ava_set(attr).and_then(|vs| vs.as_uihint_set())
@@ -3173,6 +3299,13 @@ impl<VALID, STATE> Entry<VALID, STATE> {
         }
     }
 
+    /// Returns `true` if this entry is recycled or a tombstone, meaning it is no longer
+    /// a live entry. Such entries may only be reached through the replication state
+    /// machine, so they must never be created or modified directly.
+    pub fn is_recycled_or_tombstone(&self) -> bool {
+        self.mask_recycled_ts().is_none()
+    }
+
     /// Determine if this entry is recycled, and map that to "None". This allows
     /// filter_map to effectively remove entries that are recycled in some cases.
     pub fn mask_recycled(&self) -> Option<&Self> {
@@ -3899,4 +4032,87 @@ mod tests {
                 == Some(Ok("spn=renameperson@example.com".to_string()))
         );
     }
+
+    #[test]
+    fn test_entry_diff() {
+        use crate::entry::AttributeDiff;
+
+        let mut e1: Entry<EntryInit, EntryNew> = Entry::new();
+        e1.add_ava(Attribute::Class, EntryClass::Person.to_value());
+        e1.add_ava(Attribute::Name, Value::new_iname("testperson"));
+        e1.add_ava(
+            Attribute::Mail,
+            Value::new_email_address_s("a@example.com").unwrap(),
+        );
+        e1.add_ava(
+            Attribute::Mail,
+            Value::new_email_address_s("b@example.com").unwrap(),
+        );
+        e1.add_ava(Attribute::Description, Value::new_utf8s("removed"));
+
+        let mut e2: Entry<EntryInit, EntryNew> = Entry::new();
+        e2.add_ava(Attribute::Class, EntryClass::Person.to_value());
+        e2.add_ava(Attribute::Name, Value::new_iname("testperson"));
+        e2.add_ava(
+            Attribute::Mail,
+            Value::new_email_address_s("b@example.com").unwrap(),
+        );
+        e2.add_ava(
+            Attribute::Mail,
+            Value::new_email_address_s("c@example.com").unwrap(),
+        );
+        e2.add_ava(Attribute::DisplayName, Value::new_utf8s("added"));
+
+        // Identical entries have no difference.
+        assert!(e1.diff(&e1.clone()).is_empty());
+
+        // Secret values are not shown when a diff is logged.
+        let mut e3 = e1.clone();
+        e3.add_ava(
+            Attribute::OAuth2RsBasicSecret,
+            Value::SecretValue("very secret value".to_string()),
+        );
+        let debug = format!("{:?}", e1.diff(&e3));
+        assert!(debug.contains("Added"));
+        assert!(!debug.contains("very secret value"));
+
+        let pvs = |values: &[Value]| -> Vec<PartialValue> {
+            crate::valueset::from_value_iter(values.iter().cloned())
+                .expect("invalid values")
+                .to_partialvalue_iter()
+                .collect()
+        };
+
+        let diff = e1.diff(&e2);
+        assert_eq!(diff.len(), 3);
+
+        for d in diff {
+            match d {
+                AttributeDiff::Added { attr, values } => {
+                    assert_eq!(attr, Attribute::DisplayName);
+                    assert_eq!(pvs(&values), vec![PartialValue::new_utf8s("added")]);
+                }
+                AttributeDiff::Removed { attr, values } => {
+                    assert_eq!(attr, Attribute::Description);
+                    assert_eq!(pvs(&values), vec![PartialValue::new_utf8s("removed")]);
+                }
+                AttributeDiff::Changed {
+                    attr,
+                    old_values,
+                    new_values,
+                } => {
+                    // Only the values that differ are reported.
+                    assert_eq!(attr, Attribute::Mail);
+                    assert_eq!(
+                        pvs(&old_values),
+                        vec![PartialValue::new_email_address_s("a@example.com")]
+                    );
+                    assert_eq!(
+                        pvs(&new_values),
+                        vec![PartialValue::new_email_address_s("c@example.com")]
+                    );
+                }
+            }
+        }
+    }
 }
@@ -348,6 +348,24 @@ pub struct CreateEvent {
     // This may affect which plugins are run ...
     /// If true, the list of created entry UUID's will be returned.
     pub return_created_uuids: bool,
+    /// If true, secrets that were generated by the server during the create are
+    /// returned separately. This is only honoured for internal identities.
+    pub return_generated_secrets: bool,
+    /// The original creation time of the entries, for historical imports. This is
+    /// only permitted for internal and sync identities, and does not alter the
+    /// replication cid of the entries.
+    pub override_created_at: Option<Duration>,
+    /// If set, the create is only performed when no entry visible to the identity
+    /// matches this filter. This is checked within the same transaction as the
+    /// create, so that provisioning does not race between a search and a create.
+    pub unless_exists: Option<Filter<FilterInvalid>>,
+    /// ⚠️  - Permit the creation of recycled or tombstone entries, bypassing the
+    /// replication state machine. This is only set by [CreateEvent::new_internal_restore],
+    /// and is refused for any identity other than internal.
+    pub(crate) allow_state_machine_bypass: bool,
+    /// If true, the spn's of the candidates are checked for collisions before any
+    /// plugins run, so that bulk imports fail early naming the duplicated spn.
+    pub precheck_spn_uniqueness: bool,
 }
 
 impl CreateEvent {
@@ -369,6 +387,11 @@ impl CreateEvent {
                 ident,
                 entries,
                 return_created_uuids: false,
+                return_generated_secrets: false,
+                override_created_at: None,
+                unless_exists: None,
+                allow_state_machine_bypass: false,
+                precheck_spn_uniqueness: false,
             }),
             Err(e) => Err(e),
         }
@@ -382,6 +405,11 @@ impl CreateEvent {
             ident,
             entries,
             return_created_uuids: false,
+            return_generated_secrets: false,
+            override_created_at: None,
+            unless_exists: None,
+            allow_state_machine_bypass: false,
+            precheck_spn_uniqueness: false,
         }
     }
 
@@ -390,6 +418,20 @@ impl CreateEvent {
             ident: Identity::from_internal(),
             entries,
             return_created_uuids: false,
+            return_generated_secrets: false,
+            override_created_at: None,
+            unless_exists: None,
+            allow_state_machine_bypass: false,
+            precheck_spn_uniqueness: false,
+        }
+    }
+
+    /// ⚠️  - Create entries as part of a controlled restore. These may be recycled or
+    /// tombstone entries, which bypass the replication state machine.
+    pub fn new_internal_restore(entries: Vec<Entry<EntryInit, EntryNew>>) -> Self {
+        CreateEvent {
+            allow_state_machine_bypass: true,
+            ..Self::new_internal(entries)
         }
     }
 }
@@ -777,6 +819,27 @@ impl PurgeDeleteAfterEvent {
     }
 }
 
+#[derive(Debug)]
+pub struct PurgeRevokedSessionsEvent {
+    pub ident: Identity,
+    pub eventid: Uuid,
+}
+
+impl Default for PurgeRevokedSessionsEvent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PurgeRevokedSessionsEvent {
+    pub fn new() -> Self {
+        PurgeRevokedSessionsEvent {
+            ident: Identity::from_internal(),
+            eventid: Uuid::new_v4(),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct OnlineBackupEvent {
     pub ident: Identity,
@@ -11,7 +11,9 @@ use crate::idm::server::{IdmServerProxyReadTransaction, IdmServerProxyWriteTrans
 use crate::modify::{ModifyInvalid, ModifyList};
 use crate::prelude::*;
 use crate::schema::SchemaTransaction;
-use crate::value::{IntentTokenState, PartialValue, SessionState, Value};
+use crate::value::{
+    AttestedPasskeyAttestation, IntentTokenState, PartialValue, SessionState, Value,
+};
 use kanidm_lib_crypto::CryptoPolicy;
 use kanidm_proto::internal::{CredentialStatus, UatPurpose, UiHint, UserAuthToken};
 use kanidm_proto::v1::{UatStatus, UatStatusState, UnixGroupToken, UnixUserToken};
@@ -58,6 +60,7 @@ pub struct Account {
     pub primary: Option<Credential>,
     pub passkeys: BTreeMap<Uuid, (String, PasskeyV4)>,
     pub attested_passkeys: BTreeMap<Uuid, (String, AttestedPasskeyV4)>,
+    pub attested_passkey_attestations: BTreeMap<Uuid, AttestedPasskeyAttestation>,
     pub valid_from: Option<OffsetDateTime>,
     pub expire: Option<OffsetDateTime>,
     softlock_expire: Option<OffsetDateTime>,
@@ -121,6 +124,11 @@ macro_rules! try_from_entry {
             .cloned()
             .unwrap_or_default();
 
+        let attested_passkey_attestations = $value
+            .get_ava_attestedpasskey_attestations(Attribute::AttestedPasskeys)
+            .cloned()
+            .unwrap_or_default();
+
         let spn = $value
             .get_ava_single_proto_string(Attribute::Spn)
             .ok_or(OperationError::MissingAttribute(Attribute::Spn))?;
@@ -247,6 +255,7 @@ macro_rules! try_from_entry {
             primary,
             passkeys,
             attested_passkeys,
+            attested_passkey_attestations,
             valid_from,
             expire,
             softlock_expire,
@@ -673,6 +682,12 @@ impl Account {
         // Is it an attested passkey?
         self.attested_passkeys.iter_mut().for_each(|(u, (t, k))| {
             if let Some(true) = k.update_credential(auth_result) {
+                let attestation = self
+                    .attested_passkey_attestations
+                    .get(u)
+                    .copied()
+                    .unwrap_or_default();
+
                 ml.push(Modify::Removed(
                     Attribute::AttestedPasskeys,
                     PartialValue::AttestedPasskey(*u),
@@ -680,7 +695,7 @@ impl Account {
 
                 ml.push(Modify::Present(
                     Attribute::AttestedPasskeys,
-                    Value::AttestedPasskey(*u, t.clone(), k.clone()),
+                    Value::AttestedPasskey(*u, t.clone(), k.clone(), attestation),
                 ));
             }
         });
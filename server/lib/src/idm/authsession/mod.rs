@@ -1691,6 +1691,7 @@ impl AuthSession {
                             scope,
                             type_: auth_type,
                             ext_metadata,
+                            client_ip: self.source.client_ip(),
                         }))
                         .map_err(|e| {
                             debug!(?e, "queue failure");
@@ -6,19 +6,21 @@ use crate::idm::server::{IdmServerCredUpdateTransaction, IdmServerProxyWriteTran
 use crate::prelude::*;
 use crate::server::access::Access;
 use crate::utils::{backup_code_from_random, readable_password_from_random, uuid_from_duration};
-use crate::value::{CredUpdateSessionPerms, CredentialType, IntentTokenState, LABEL_RE};
+use crate::value::{
+    AttestedPasskeyAttestation, CredUpdateSessionPerms, CredentialType, IntentTokenState, LABEL_RE,
+};
 use compact_jwt::compact::JweCompact;
 use compact_jwt::jwe::JweBuilder;
 use core::ops::Deref;
 use hashbrown::HashSet;
 use kanidm_proto::internal::{
-    CUCredState, CUExtPortal, CURegState, CURegWarning, CUStatus, CredentialDetail, PasskeyDetail,
-    PasswordFeedback, TotpSecret,
+    CUCredState, CUExtPortal, CUIntentPermission, CURegState, CURegWarning, CUStatus,
+    CredentialDetail, PasskeyDetail, PasswordFeedback, TotpSecret,
 };
 use kanidm_proto::v1::OutboundMessage;
 use serde::{Deserialize, Serialize};
 use sshkey_attest::proto::PublicKey as SshPublicKey;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::{self, Display};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
@@ -510,6 +512,8 @@ pub struct InitCredentialUpdateIntentEvent {
     pub target: Uuid,
     // How long is it valid for?
     pub max_ttl: Option<Duration>,
+    // Which credentials may it update? If unset, all that the initiator can.
+    pub permissions: Option<BTreeSet<CUIntentPermission>>,
 }
 
 impl InitCredentialUpdateIntentEvent {
@@ -518,9 +522,16 @@ impl InitCredentialUpdateIntentEvent {
             ident,
             target,
             max_ttl,
+            permissions: None,
         }
     }
 
+    /// Limit the intent token to only updating these credential types.
+    pub fn with_permissions(mut self, permissions: Option<BTreeSet<CUIntentPermission>>) -> Self {
+        self.permissions = permissions;
+        self
+    }
+
     #[cfg(test)]
     pub fn new_impersonate_entry(
         e: std::sync::Arc<Entry<EntrySealed, EntryCommitted>>,
@@ -532,10 +543,42 @@ impl InitCredentialUpdateIntentEvent {
             ident,
             target,
             max_ttl: Some(max_ttl),
+            permissions: None,
         }
     }
 }
 
+/// Limit the permissions of an intent token to the requested credential types. Asking for
+/// a credential type that the initiator can't update is denied rather than ignored, so that
+/// the token is never silently less capable than was requested.
+fn restrict_intent_perms(
+    perms: &mut CredUpdateSessionPerms,
+    permissions: &BTreeSet<CUIntentPermission>,
+) -> Result<(), OperationError> {
+    for permission in permissions {
+        let granted = match permission {
+            CUIntentPermission::Primary => perms.primary_can_edit,
+            CUIntentPermission::Passkeys => perms.passkeys_can_edit,
+            CUIntentPermission::AttestedPasskeys => perms.attested_passkeys_can_edit,
+            CUIntentPermission::UnixCred => perms.unixcred_can_edit,
+            CUIntentPermission::SshPublicKey => perms.sshpubkey_can_edit,
+        };
+
+        if !granted {
+            security_access!(%permission, "requested intent permission is not held by the initiator");
+            return Err(OperationError::AccessDenied);
+        }
+    }
+
+    perms.primary_can_edit = permissions.contains(&CUIntentPermission::Primary);
+    perms.passkeys_can_edit = permissions.contains(&CUIntentPermission::Passkeys);
+    perms.attested_passkeys_can_edit = permissions.contains(&CUIntentPermission::AttestedPasskeys);
+    perms.unixcred_can_edit = permissions.contains(&CUIntentPermission::UnixCred);
+    perms.sshpubkey_can_edit = permissions.contains(&CUIntentPermission::SshPublicKey);
+
+    Ok(())
+}
+
 pub struct CredentialUpdateAccountRecovery {
     // Who is it targeting?
     pub email: String,
@@ -1095,12 +1138,16 @@ impl IdmServerProxyWriteTransaction<'_> {
         event: &InitCredentialUpdateIntentEvent,
         ct: Duration,
     ) -> Result<CredentialUpdateIntentToken, OperationError> {
-        let (account, _resolved_account_policy, perms) =
+        let (account, _resolved_account_policy, mut perms) =
             self.validate_init_credential_update(event.target, &event.ident)?;
 
         // We should check in the acc-pol if we can proceed?
         // Is there a reason account policy might deny us from proceeding?
 
+        if let Some(permissions) = &event.permissions {
+            restrict_intent_perms(&mut perms, permissions)?;
+        }
+
         // ==== AUTHORISATION CHECKED ===
         let (intent_id, expiry_time) =
             self.build_credential_update_intent(event.max_ttl, &account, perms, ct)?;
@@ -1329,13 +1376,15 @@ impl IdmServerProxyWriteTransaction<'_> {
                 );
                 return Err(OperationError::SessionExpired);
             }
-            Some(IntentTokenState::InProgress {
-                max_ttl,
-                perms,
-                session_id,
-                session_ttl,
-            }) => {
-                if current_time > *session_ttl {
+            Some(
+                state @ IntentTokenState::InProgress {
+                    max_ttl,
+                    perms,
+                    session_id,
+                    session_ttl: _,
+                },
+            ) => {
+                if state.is_session_expired(current_time) {
                     // The former session has expired, continue.
                     security_info!(
                         %entry,
@@ -1562,15 +1611,20 @@ impl IdmServerProxyWriteTransaction<'_> {
         // If an intent token was used, remove it's former value, and add it as consumed.
         if let Some(intent_token_id) = &session.intent_token_id {
             let max_ttl = match account.credential_update_intent_tokens.get(intent_token_id) {
-                Some(IntentTokenState::InProgress {
-                    max_ttl,
-                    perms: _,
-                    session_id,
-                    session_ttl: _,
-                }) => {
+                Some(
+                    state @ IntentTokenState::InProgress {
+                        max_ttl,
+                        perms: _,
+                        session_id,
+                        session_ttl: _,
+                    },
+                ) => {
                     if *session_id != session_token.sessionid {
                         security_info!("Session originated from an intent token, but the intent token has initiated a conflicting second update session. Refusing to commit changes.");
                         return Err(OperationError::CU0005IntentTokenConflict);
+                    } else if state.is_session_expired(ct) {
+                        security_info!(%session_id, "Session originated from an intent token, but the update session has expired. Refusing to commit changes.");
+                        return Err(OperationError::SessionExpired);
                     } else {
                         *max_ttl
                     }
@@ -1673,13 +1727,28 @@ impl IdmServerProxyWriteTransaction<'_> {
         match session.attested_passkeys_state {
             CredentialState::DeleteOnly | CredentialState::Modifiable => {
                 modlist.push_mod(Modify::Purged(Attribute::AttestedPasskeys));
+                let att_ca_list = session
+                    .resolved_account_policy
+                    .webauthn_attestation_ca_list();
                 // Add all the passkeys. If none, nothing will be added! This handles
                 // the delete case quite cleanly :)
                 session
                     .attested_passkeys
                     .iter()
                     .for_each(|(uuid, (tag, pk))| {
-                        let v_pk = Value::AttestedPasskey(*uuid, tag.clone(), pk.clone());
+                        // Record the CA that attests each key. Without a CA list, keys keep
+                        // the attestation that was recorded when they were registered.
+                        let attestation = match att_ca_list {
+                            Some(att_ca_list) => AttestedPasskeyAttestation::new(pk, att_ca_list),
+                            None => session
+                                .account
+                                .attested_passkey_attestations
+                                .get(uuid)
+                                .copied()
+                                .unwrap_or_else(|| AttestedPasskeyAttestation::from(pk)),
+                        };
+                        let v_pk =
+                            Value::AttestedPasskey(*uuid, tag.clone(), pk.clone(), attestation);
                         modlist.push_mod(Modify::Present(Attribute::AttestedPasskeys, v_pk));
                     });
             }
@@ -2149,6 +2218,57 @@ impl IdmServerCredUpdateTransaction<'_> {
         Ok(session.deref().into())
     }
 
+    /// Retrieve the secret of the TOTP that is awaiting verification in this session, so
+    /// that it can be presented to an authenticator app. The TOTP will be added to the
+    /// primary credential `cred_uuid` under `label`, so both must be valid. The uuid of the
+    /// primary credential changes whenever it is modified, so it is taken from the latest status.
+    pub fn credential_primary_pending_totp(
+        &self,
+        cust: &CredentialUpdateSessionToken,
+        ct: Duration,
+        cred_uuid: Uuid,
+        label: &str,
+    ) -> Result<TotpSecret, OperationError> {
+        let session_handle = self.get_current_session(cust, ct)?;
+        let session = session_handle.try_lock().map_err(|_| {
+            admin_error!("Session already locked, unable to proceed.");
+            OperationError::InvalidState
+        })?;
+        trace!(?session);
+
+        if !matches!(session.primary_state, CredentialState::Modifiable) {
+            error!("Session does not have permission to modify primary credential");
+            return Err(OperationError::AccessDenied);
+        };
+
+        let (MfaRegState::TotpInit(totp_token)
+        | MfaRegState::TotpTryAgain(totp_token)
+        | MfaRegState::TotpNameTryAgain(totp_token, _)) = &session.mfaregstate
+        else {
+            admin_info!("No TOTP is awaiting verification in this session");
+            return Err(OperationError::NoMatchingEntries);
+        };
+
+        let Some(primary) = session
+            .primary
+            .as_ref()
+            .filter(|cred| cred.uuid == cred_uuid)
+        else {
+            admin_info!(?cred_uuid, "Primary credential not found in this session");
+            return Err(OperationError::NoMatchingEntries);
+        };
+
+        if label.trim().is_empty() || !Value::validate_str_escapes(label) {
+            return Err(OperationError::InvalidLabel);
+        }
+
+        if primary.has_totp_by_name(label) {
+            return Err(OperationError::DuplicateLabel);
+        }
+
+        Ok(totp_token.to_proto(session.account.spn(), session.issuer.as_str()))
+    }
+
     pub fn credential_primary_check_totp(
         &self,
         cust: &CredentialUpdateSessionToken,
@@ -2866,10 +2986,12 @@ mod tests {
     use crate::idm::server::{IdmServer, IdmServerCredUpdateTransaction, IdmServerDelayed};
     use crate::prelude::*;
     use crate::utils::password_from_random_len;
-    use crate::value::CredentialType;
+    use crate::value::{attestation_ca_uuid, CredentialType};
     use crate::valueset::ValueSetEmailAddress;
     use compact_jwt::JwsCompact;
-    use kanidm_proto::internal::{CUExtPortal, CredentialDetailType, PasswordFeedback};
+    use kanidm_proto::internal::{
+        CUExtPortal, CUIntentPermission, CredentialDetailType, PasswordFeedback,
+    };
     use kanidm_proto::v1::OutboundMessage;
     use kanidm_proto::v1::{AuthAllowed, AuthIssueSession, AuthMech, UnixUserToken};
     use sshkey_attest::proto::PublicKey as SshPublicKey;
@@ -3037,6 +3159,177 @@ mod tests {
         idms_prox_write.commit().expect("Failed to commit txn");
     }
 
+    #[idm_test]
+    async fn credential_update_intent_session_expired(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let test_pw = "fo3EitierohF9AelaNgiem0Ei6vup4equo1Oogeevaetehah8Tobeengae3Ci0ooh0uki";
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        // Remove the default all persons policy, so a password can be committed.
+        let modlist = ModifyList::new_purge(Attribute::CredentialTypeMinimum);
+        idms_prox_write
+            .qs_write
+            .internal_modify_uuid(UUID_IDM_ALL_PERSONS, &modlist)
+            .expect("Unable to change default credential policy");
+
+        let e = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname(TESTPERSON_NAME)),
+            (Attribute::Uuid, Value::Uuid(TESTPERSON_UUID)),
+            (Attribute::Description, Value::new_utf8s(TESTPERSON_NAME)),
+            (Attribute::DisplayName, Value::new_utf8s(TESTPERSON_NAME))
+        );
+
+        let ce = CreateEvent::new_internal(vec![e]);
+        assert!(idms_prox_write.qs_write.create(&ce).is_ok());
+
+        let idm_admin = idms_prox_write
+            .qs_write
+            .internal_search_uuid(UUID_IDM_ADMIN)
+            .expect("failed");
+
+        let intent_tok = idms_prox_write
+            .init_credential_update_intent(
+                &InitCredentialUpdateIntentEvent::new_impersonate_entry(
+                    idm_admin,
+                    TESTPERSON_UUID,
+                    MAXIMUM_INTENT_TTL,
+                ),
+                ct,
+            )
+            .expect("Failed to create intent token!");
+
+        let (cust, _c_status) = idms_prox_write
+            .exchange_intent_credential_update(intent_tok.into(), ct)
+            .expect("Failed to exchange intent token");
+
+        idms_prox_write.commit().expect("Failed to commit txn");
+
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        let c_status = cutxn
+            .credential_primary_set_password(&cust, ct, test_pw)
+            .expect("Failed to update the primary cred password");
+        assert!(c_status.can_commit);
+        drop(cutxn);
+
+        // The intent token is still valid, but the update session started from it has
+        // passed its ttl, so the changes can't be committed.
+        let expired = ct + MAXIMUM_CRED_UPDATE_TTL + Duration::from_secs(1);
+        assert!(expired < ct + MAXIMUM_INTENT_TTL);
+
+        let mut idms_prox_write = idms.proxy_write(expired).await.unwrap();
+        let cur = idms_prox_write.commit_credential_update(&cust, expired);
+        assert!(matches!(cur, Err(OperationError::SessionExpired)));
+        drop(idms_prox_write);
+
+        // Within the ttl the same session can be committed.
+        let ct = ct + Duration::from_secs(1);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let cur = idms_prox_write.commit_credential_update(&cust, ct);
+        assert!(cur.is_ok());
+        idms_prox_write.commit().expect("Failed to commit txn");
+    }
+
+    #[idm_test]
+    async fn credential_update_intent_permissions(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let nonposix_uuid = Uuid::new_v4();
+
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::PosixAccount.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname(TESTPERSON_NAME)),
+            (Attribute::Uuid, Value::Uuid(TESTPERSON_UUID)),
+            (Attribute::Description, Value::new_utf8s(TESTPERSON_NAME)),
+            (Attribute::DisplayName, Value::new_utf8s(TESTPERSON_NAME))
+        );
+
+        let e2 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("nonposix")),
+            (Attribute::Uuid, Value::Uuid(nonposix_uuid)),
+            (Attribute::Description, Value::new_utf8s("nonposix")),
+            (Attribute::DisplayName, Value::new_utf8s("nonposix"))
+        );
+
+        let ce = CreateEvent::new_internal(vec![e1, e2]);
+        assert!(idms_prox_write.qs_write.create(&ce).is_ok());
+
+        let idm_admin = idms_prox_write
+            .qs_write
+            .internal_search_uuid(UUID_IDM_ADMIN)
+            .expect("failed");
+
+        // Limit the token to the password and ssh keys.
+        let intent_tok = idms_prox_write
+            .init_credential_update_intent(
+                &InitCredentialUpdateIntentEvent::new_impersonate_entry(
+                    idm_admin.clone(),
+                    TESTPERSON_UUID,
+                    MINIMUM_INTENT_TTL,
+                )
+                .with_permissions(Some(btreeset![
+                    CUIntentPermission::Primary,
+                    CUIntentPermission::SshPublicKey
+                ])),
+                ct,
+            )
+            .expect("Failed to create intent token!");
+
+        let (_cust, c_status) = idms_prox_write
+            .exchange_intent_credential_update(intent_tok.into(), ct)
+            .expect("Failed to exchange intent token");
+
+        assert!(matches!(
+            c_status.primary_state,
+            CredentialState::Modifiable
+        ));
+        assert!(matches!(
+            c_status.sshkeys_state,
+            CredentialState::Modifiable
+        ));
+        assert!(matches!(
+            c_status.passkeys_state,
+            CredentialState::AccessDeny
+        ));
+        assert!(matches!(
+            c_status.attested_passkeys_state,
+            CredentialState::AccessDeny
+        ));
+        assert!(matches!(
+            c_status.unixcred_state,
+            CredentialState::AccessDeny
+        ));
+
+        // Asking for a credential the account can't have is denied.
+        let cur = idms_prox_write.init_credential_update_intent(
+            &InitCredentialUpdateIntentEvent::new_impersonate_entry(
+                idm_admin,
+                nonposix_uuid,
+                MINIMUM_INTENT_TTL,
+            )
+            .with_permissions(Some(btreeset![CUIntentPermission::UnixCred])),
+            ct,
+        );
+        assert!(matches!(cur, Err(OperationError::AccessDenied)));
+
+        idms_prox_write.commit().expect("Failed to commit txn");
+    }
+
     async fn setup_test_session(
         idms: &IdmServer,
         ct: Duration,
@@ -4098,6 +4391,76 @@ mod tests {
             .is_some());
     }
 
+    #[idm_test]
+    async fn credential_update_onboarding_pending_totp(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let test_pw = "fo3EitierohF9AelaNgiem0Ei6vup4equo1Oogeevaetehah8Tobeengae3Ci0ooh0uki";
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+
+        let (cust, _) = setup_test_session(idms, ct).await;
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+
+        let c_status = cutxn
+            .credential_primary_set_password(&cust, ct, test_pw)
+            .expect("Failed to update the primary cred password");
+        let cred_uuid = c_status.primary.as_ref().map(|c| c.uuid).unwrap();
+
+        // Nothing is pending yet.
+        assert!(matches!(
+            cutxn.credential_primary_pending_totp(&cust, ct, cred_uuid, "totp"),
+            Err(OperationError::NoMatchingEntries)
+        ));
+
+        let c_status = cutxn
+            .credential_primary_init_totp(&cust, ct)
+            .expect("Failed to update the primary cred password");
+
+        let MfaRegStateStatus::TotpCheck(status_secret) = c_status.mfaregstate else {
+            panic!("Unable to retrieve totp token, invalid state.");
+        };
+
+        let secret = cutxn
+            .credential_primary_pending_totp(&cust, ct, cred_uuid, "totp")
+            .expect("Failed to retrieve the pending totp");
+        assert_eq!(secret.to_uri(), status_secret.to_uri());
+
+        // Only the primary credential of the session is accepted.
+        assert!(matches!(
+            cutxn.credential_primary_pending_totp(&cust, ct, Uuid::new_v4(), "totp"),
+            Err(OperationError::NoMatchingEntries)
+        ));
+
+        assert!(matches!(
+            cutxn.credential_primary_pending_totp(&cust, ct, cred_uuid, "   "),
+            Err(OperationError::InvalidLabel)
+        ));
+
+        // Save the totp, then a second can't reuse the label.
+        let totp_token: Totp = secret.try_into().unwrap();
+        let chal = totp_token
+            .do_totp_duration_from_epoch(&ct)
+            .expect("Failed to perform totp step");
+        cutxn
+            .credential_primary_check_totp(&cust, ct, chal, "totp")
+            .expect("Failed to update the primary cred totp");
+
+        // Changing the credential gives it a new uuid.
+        let c_status = cutxn
+            .credential_primary_init_totp(&cust, ct)
+            .expect("Failed to update the primary cred password");
+        let cred_uuid = c_status.primary.as_ref().map(|c| c.uuid).unwrap();
+
+        assert!(matches!(
+            cutxn.credential_primary_pending_totp(&cust, ct, cred_uuid, "totp"),
+            Err(OperationError::DuplicateLabel)
+        ));
+        assert!(cutxn
+            .credential_primary_pending_totp(&cust, ct, cred_uuid, "totp_2")
+            .is_ok());
+    }
+
     // Check sha1 totp.
     #[idm_test]
     async fn credential_update_onboarding_create_new_mfa_totp_sha1(
@@ -4858,7 +5221,7 @@ mod tests {
         let mut att_ca_builder = AttestationCaListBuilder::new();
         att_ca_builder
             .insert_device_x509(
-                ca_root_a,
+                ca_root_a.clone(),
                 softtoken::AAGUID,
                 "softtoken_a".to_string(),
                 Default::default(),
@@ -4874,6 +5237,13 @@ mod tests {
             .unwrap();
         let att_ca_list = att_ca_builder.build();
 
+        let ca_a_uuid = att_ca_list
+            .cas()
+            .values()
+            .find(|att_ca| *att_ca.ca() == ca_root_a)
+            .and_then(attestation_ca_uuid);
+        assert!(ca_a_uuid.is_some());
+
         let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
 
         let modlist = ModifyList::new_purge_and_set(
@@ -5007,6 +5377,21 @@ mod tests {
         drop(cutxn);
         commit_session(idms, ct, cust).await;
 
+        // The authenticator model and the CA that attests it were recorded.
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let entry = idms_prox_read
+            .qs_read
+            .internal_search_uuid(TESTPERSON_UUID)
+            .expect("Failed to get testperson");
+        let attestation = entry
+            .get_ava_attestedpasskey_attestations(Attribute::AttestedPasskeys)
+            .and_then(|attestations| attestations.get(&pk_uuid))
+            .copied()
+            .expect("No attestation was recorded");
+        assert_eq!(attestation.aaguid, Some(softtoken::AAGUID));
+        assert_eq!(attestation.attestation_ca, ca_a_uuid);
+        drop(idms_prox_read);
+
         // Assert that auth works.
         assert!(check_testperson_passkey(
             idms,
@@ -2,6 +2,7 @@ use crate::prelude::*;
 use crate::value::AuthType;
 use crate::value::SessionExtMetadata;
 use std::fmt;
+use std::net::IpAddr;
 use time::OffsetDateTime;
 use uuid::Uuid;
 use webauthn_rs::prelude::AuthenticationResult;
@@ -13,6 +14,7 @@ pub enum DelayedAction {
     WebauthnCounterIncrement(WebauthnCounterIncrement),
     BackupCodeRemoval(BackupCodeRemoval),
     AuthSessionRecord(AuthSessionRecord),
+    SessionLastSeen(SessionLastSeen),
 }
 
 pub struct PasswordUpgrade {
@@ -65,4 +67,12 @@ pub struct AuthSessionRecord {
     pub scope: SessionScope,
     pub type_: AuthType,
     pub ext_metadata: SessionExtMetadata,
+    pub client_ip: Option<IpAddr>,
+}
+
+#[derive(Debug)]
+pub struct SessionLastSeen {
+    pub target_uuid: Uuid,
+    pub session_id: Uuid,
+    pub last_seen: OffsetDateTime,
 }
@@ -473,14 +473,38 @@ impl std::fmt::Debug for OauthRSType {
 struct ClaimValue {
     join: OauthClaimMapJoin,
     values: BTreeSet<String>,
+    typed_values: Vec<serde_json::Value>,
 }
 
 impl ClaimValue {
     fn merge(&mut self, other: &Self) {
-        self.values.extend(other.values.iter().cloned())
+        self.values.extend(other.values.iter().cloned());
+        for typed_value in other.typed_values.iter() {
+            if !self.typed_values.contains(typed_value) {
+                self.typed_values.push(typed_value.clone());
+            }
+        }
     }
 
     fn to_json_value(&self) -> serde_json::Value {
+        if !self.typed_values.is_empty() {
+            // Typed values are placed into the claim as is. A single typed value is
+            // emitted directly, otherwise all values are combined into an array.
+            if let (true, [typed_value]) = (self.values.is_empty(), self.typed_values.as_slice()) {
+                return typed_value.clone();
+            }
+
+            let arr: Vec<_> = self
+                .values
+                .iter()
+                .cloned()
+                .map(serde_json::Value::String)
+                .chain(self.typed_values.iter().cloned())
+                .collect();
+
+            return serde_json::Value::Array(arr);
+        }
+
         let join_str = match self.join {
             OauthClaimMapJoin::JsonArray => {
                 let arr: Vec<_> = self
@@ -530,6 +554,7 @@ pub struct Oauth2RS {
     key_object: Arc<KeyObject>,
 
     refresh_token_expiry: u32,
+    access_token_expiry: u32,
 
     // For oidc we also need our issuer url.
     iss: Url,
@@ -879,27 +904,29 @@ impl Oauth2ResourceServersWriteTransaction<'_> {
                     let mut claim_map = BTreeMap::default();
 
                     for (claim_name, claim_mapping) in e_claim_maps.iter() {
-                        for (group_uuid, claim_values) in claim_mapping.values().iter() {
+                        for group_uuid in claim_mapping.group_uuids() {
+                            let claim_value = ClaimValue {
+                                join: claim_mapping.join(),
+                                values: claim_mapping
+                                    .values()
+                                    .get(group_uuid)
+                                    .cloned()
+                                    .unwrap_or_default(),
+                                typed_values: claim_mapping
+                                    .typed_values()
+                                    .get(group_uuid)
+                                    .cloned()
+                                    .unwrap_or_default(),
+                            };
+
                             // We always insert/append here because the outer claim_name has
                             // to be unique.
                             match claim_map.entry(*group_uuid) {
                                 BTreeEntry::Vacant(e) => {
-                                    e.insert(vec![(
-                                        claim_name.clone(),
-                                        ClaimValue {
-                                            join: claim_mapping.join(),
-                                            values: claim_values.clone(),
-                                        },
-                                    )]);
+                                    e.insert(vec![(claim_name.clone(), claim_value)]);
                                 }
                                 BTreeEntry::Occupied(mut e) => {
-                                    e.get_mut().push((
-                                        claim_name.clone(),
-                                        ClaimValue {
-                                            join: claim_mapping.join(),
-                                            values: claim_values.clone(),
-                                        },
-                                    ));
+                                    e.get_mut().push((claim_name.clone(), claim_value));
                                 }
                             }
                         }
@@ -953,6 +980,18 @@ impl Oauth2ResourceServersWriteTransaction<'_> {
                     .get_ava_single_uint32(Attribute::OAuth2RefreshTokenExpiry)
                     .unwrap_or(OAUTH_REFRESH_TOKEN_EXPIRY);
 
+                // The plugin validates this on write, but entries may predate that or arrive
+                // via replication, so we still keep it within the permitted range.
+                let access_token_expiry = ent
+                    .get_ava_single_uint32(Attribute::OAuth2AccessTokenExpiry)
+                    .map(|expiry| {
+                        expiry.clamp(
+                            OAUTH2_ACCESS_TOKEN_EXPIRY_MINIMUM,
+                            OAUTH2_ACCESS_TOKEN_EXPIRY_MAXIMUM,
+                        )
+                    })
+                    .unwrap_or(OAUTH2_ACCESS_TOKEN_EXPIRY);
+
                 let mut authorization_endpoint = self.inner.origin.clone();
                 authorization_endpoint.set_path("/ui/oauth2");
 
@@ -1018,6 +1057,7 @@ impl Oauth2ResourceServersWriteTransaction<'_> {
                     sign_alg,
                     key_object,
                     refresh_token_expiry,
+                    access_token_expiry,
                     iss,
                     authorization_endpoint,
                     token_endpoint,
@@ -1892,9 +1932,9 @@ impl IdmServerProxyWriteTransaction<'_> {
 
         let odt_ct = OffsetDateTime::UNIX_EPOCH + ct;
         let iat = ct.as_secs() as i64;
-        let exp = iat + OAUTH2_ACCESS_TOKEN_EXPIRY as i64;
-        let odt_exp = odt_ct + Duration::from_secs(OAUTH2_ACCESS_TOKEN_EXPIRY as u64);
-        let expires_in = OAUTH2_ACCESS_TOKEN_EXPIRY;
+        let exp = iat + o2rs.access_token_expiry as i64;
+        let odt_exp = odt_ct + Duration::from_secs(o2rs.access_token_expiry as u64);
+        let expires_in = o2rs.access_token_expiry;
 
         let session_id = Uuid::new_v4();
 
@@ -1982,11 +2022,11 @@ impl IdmServerProxyWriteTransaction<'_> {
         // expiries are *purely* for the tokens we issue and are *not related* to the expiries of the
         // the session - these are enforced as above!
 
-        // Refresh tokens can be configured, but access token expiry can not. This is because
-        // OAuth2 has no *revocation* mechanism, so we need to be validating and re-issuing
-        // access tokens frequently.
-        let expiry = odt_ct + Duration::from_secs(OAUTH2_ACCESS_TOKEN_EXPIRY as u64);
-        let expires_in = OAUTH2_ACCESS_TOKEN_EXPIRY;
+        // Refresh tokens can be configured freely, but access token expiry is bounded to at
+        // most an hour. This is because OAuth2 has no *revocation* mechanism, so we need to be
+        // validating and re-issuing access tokens frequently.
+        let expiry = odt_ct + Duration::from_secs(o2rs.access_token_expiry as u64);
+        let expires_in = o2rs.access_token_expiry;
         let refresh_expiry = iat + o2rs.refresh_token_expiry as i64;
         let odt_refresh_expiry = odt_ct + Duration::from_secs(o2rs.refresh_token_expiry as u64);
 
@@ -3814,6 +3854,8 @@ mod tests {
                 scope: SessionScope::ReadWrite,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                client_ip: None,
+                last_seen: Some(time::OffsetDateTime::UNIX_EPOCH + ct),
             },
         );
 
@@ -3952,6 +3994,8 @@ mod tests {
                 scope: SessionScope::ReadWrite,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                client_ip: None,
+                last_seen: Some(time::OffsetDateTime::UNIX_EPOCH + ct),
             },
         );
 
@@ -7112,6 +7156,66 @@ mod tests {
         assert!(idms_prox_write.commit().is_ok());
     }
 
+    #[idm_test]
+    async fn test_idm_oauth2_custom_access_token_expiry(
+        idms: &IdmServer,
+        idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+
+        let (access_token_response_1, client_authz, oauth2_rs_uuid) =
+            setup_refresh_token(idms, idms_delayed, ct).await;
+
+        // Without a per client value, the server default applies.
+        assert_eq!(
+            access_token_response_1.expires_in,
+            OAUTH2_ACCESS_TOKEN_EXPIRY
+        );
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let custom_exp = 120;
+
+        let modlist = ModifyList::new_list(vec![Modify::Set(
+            Attribute::OAuth2AccessTokenExpiry,
+            ValueSetUint32::new(custom_exp),
+        )]);
+
+        assert!(idms_prox_write
+            .qs_write
+            .internal_modify(
+                &filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(oauth2_rs_uuid))),
+                &modlist,
+            )
+            .is_ok());
+
+        assert!(idms_prox_write.commit().is_ok());
+
+        let ct = Duration::from_secs(TEST_CURRENT_TIME + 10);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let refresh_token = access_token_response_1
+            .refresh_token
+            .as_ref()
+            .expect("no refresh token was issued")
+            .clone();
+
+        let token_req: AccessTokenRequest = GrantTypeReq::RefreshToken {
+            refresh_token,
+            scope: None,
+        }
+        .into();
+
+        let access_token_response_2 = idms_prox_write
+            .check_oauth2_token_exchange(&client_authz, &token_req, ct)
+            .expect("Unable to exchange for OAuth2 token");
+
+        assert!(idms_prox_write.commit().is_ok());
+
+        // The per client value is preferred.
+        assert_eq!(access_token_response_2.expires_in, custom_exp);
+    }
+
     // refresh when OAuth2 parent session exp / missing.
     #[idm_test]
     async fn test_idm_oauth2_refresh_token_oauth2_session_expired(
@@ -8261,6 +8365,39 @@ mod tests {
         assert!(res.is_err());
     }
 
+    #[test]
+    fn test_oauth2_claim_value_typed_json() {
+        use super::ClaimValue;
+
+        let mut claim_value = ClaimValue {
+            join: OauthClaimMapJoin::CommaSeparatedValue,
+            values: BTreeSet::default(),
+            typed_values: vec![serde_json::Value::Bool(true)],
+        };
+
+        // A single typed value is emitted directly rather than joined.
+        assert_eq!(claim_value.to_json_value(), serde_json::Value::Bool(true));
+
+        claim_value.merge(&ClaimValue {
+            join: OauthClaimMapJoin::CommaSeparatedValue,
+            values: ["admin".to_string()].into(),
+            typed_values: vec![serde_json::Value::Bool(true), serde_json::json!(7)],
+        });
+
+        assert_eq!(
+            claim_value.to_json_value(),
+            serde_json::json!(["admin", true, 7])
+        );
+
+        // String only values retain their join behaviour.
+        let claim_value = ClaimValue {
+            join: OauthClaimMapJoin::CommaSeparatedValue,
+            values: ["a".to_string(), "b".to_string()].into(),
+            typed_values: Vec::default(),
+        };
+        assert_eq!(claim_value.to_json_value(), serde_json::json!("a,b"));
+    }
+
     #[test]
     fn test_url_localhost_domain() {
         // ref #2390 - localhost with ports for OAuth2 redirect_uri
@@ -1,13 +1,9 @@
-use crate::credential::totp::{Totp, TotpAlgo, TotpDigits};
+use crate::credential::totp::Totp;
 use crate::idm::server::{IdmServerProxyReadTransaction, IdmServerProxyWriteTransaction};
 use crate::prelude::*;
 use crate::schema::{SchemaClass, SchemaTransaction};
 use crate::value::ApiToken;
 use crate::valueset::ValueSetDateTime;
-use base64::{
-    engine::general_purpose::{STANDARD, URL_SAFE},
-    Engine as _,
-};
 use compact_jwt::{Jws, JwsCompact};
 use kanidm_proto::internal::{ApiTokenPurpose, ScimSyncToken};
 use kanidm_proto::scim_v1::*;
@@ -15,6 +11,7 @@ use kanidm_proto::v1::OutboundMessage;
 use sshkey_attest::proto::PublicKey as SshPublicKey;
 use std::collections::{BTreeMap, BTreeSet};
 use std::time::Duration;
+use time::OffsetDateTime;
 
 // Internals of a Scim Sync token
 
@@ -605,7 +602,7 @@ impl IdmServerProxyWriteTransaction<'_> {
                 // valid
                 info!("Refresh Sync");
             }
-            (ScimSyncState::Active { cookie }, Some(sync_cookie)) => {
+            (ScimSyncState::Active { cookie, .. }, Some(sync_cookie)) => {
                 // Check cookies.
                 if cookie != sync_cookie {
                     // Invalid
@@ -618,7 +615,7 @@ impl IdmServerProxyWriteTransaction<'_> {
                     info!("Active Sync with valid cookie");
                 }
             }
-            (ScimSyncState::Active { cookie: _ }, None) => {
+            (ScimSyncState::Active { .. }, None) => {
                 error!("Invalid Sync State - Sync Tool Reports Active, but agreement has Refresh Required. You can resync the agreement with `kanidm system sync force-refresh`");
                 return Err(OperationError::InvalidSyncState);
             }
@@ -942,101 +939,59 @@ impl IdmServerProxyWriteTransaction<'_> {
                             }
                         })?;
 
-                    let secret = complex
-                        .get(SCIM_SECRET)
-                        .ok_or_else(|| {
-                            error!("Invalid SCIM complex attr - missing required key secret");
+                    let get_attr = |key: &str| {
+                        complex.get(key).ok_or_else(|| {
+                            error!("Invalid scim complex attr - missing required key {}", key);
                             OperationError::InvalidAttribute(format!(
-                                "missing required key secret - {scim_attr_name}"
+                                "missing required key {key} - {scim_attr_name}"
                             ))
                         })
-                        .and_then(|secret| match secret {
-                            ScimAttr::String(value) => {
-                                URL_SAFE.decode(value.as_str()).or_else(
-                                    |_| STANDARD.decode(value.as_str())
-                                )
-                                    .map_err(|_| {
-                                        error!("Invalid secret attribute - must be base64 string");
-                                        OperationError::InvalidAttribute(format!(
-                                            "secret must be base64 string - {scim_attr_name}"
-                                        ))
-                                    })
-                            }
+                    };
+
+                    let get_string = |key: &str| {
+                        get_attr(key).and_then(|value| match value {
+                            ScimAttr::String(value) => Ok(value.clone()),
                             _ => {
-                                error!("Invalid secret attribute - must be scim simple string");
+                                error!("Invalid {} attribute - must be scim simple string", key);
                                 Err(OperationError::InvalidAttribute(format!(
-                                    "secret must be scim simple string - {scim_attr_name}"
+                                    "{key} must be scim simple string - {scim_attr_name}"
                                 )))
                             }
-                        })?;
-
-                    let algo = complex.get(SCIM_ALGO)
-                        .ok_or_else(|| {
-                            error!("Invalid scim complex attr - missing required key algo");
-                            OperationError::InvalidAttribute(format!(
-                                "missing required key algo - {scim_attr_name}"
-                            ))
                         })
-                        .and_then(|algo_str| {
-                            match algo_str {
-                                ScimAttr::String(value) => {
-                                    match value.as_str() {
-                                        "sha1" => Ok(TotpAlgo::Sha1),
-                                        "sha256" => Ok(TotpAlgo::Sha256),
-                                        "sha512" => Ok(TotpAlgo::Sha512),
-                                        _ => {
-                                            error!("Invalid algo attribute - must be one of sha1, sha256 or sha512");
-                                            Err(OperationError::InvalidAttribute(format!(
-                                                "algo must be one of sha1, sha256 or sha512 - {scim_attr_name}"
-                                            )))
-                                        }
-                                    }
-                                }
-                                _ => {
-                                    error!("Invalid algo attribute - must be scim simple string");
-                                    Err(OperationError::InvalidAttribute(format!(
-                                        "algo must be scim simple string - {scim_attr_name}"
-                                    )))
-                                }
-                            }
-                        })?;
-
-                    let step = complex.get(SCIM_STEP).ok_or_else(|| {
-                        error!("Invalid scim complex attr - missing required key step");
-                        OperationError::InvalidAttribute(format!(
-                            "missing required key step - {scim_attr_name}"
-                        ))
-                    }).and_then(|step| {
-                        match step {
-                            ScimAttr::Integer(s) if *s >= 30 => Ok(*s as u64),
-                            _ =>
-                                Err(OperationError::InvalidAttribute(format!(
-                                    "step must be a positive integer value equal to or greater than 30 - {scim_attr_name}"
-                                ))),
-                        }
-                    })?;
+                    };
 
-                    let digits = complex
-                        .get(SCIM_DIGITS)
-                        .ok_or_else(|| {
-                            error!("Invalid scim complex attr - missing required key digits");
-                            OperationError::InvalidAttribute(format!(
-                                "missing required key digits - {scim_attr_name}"
-                            ))
-                        })
-                        .and_then(|digits| match digits {
-                            ScimAttr::Integer(6) => Ok(TotpDigits::Six),
-                            ScimAttr::Integer(8) => Ok(TotpDigits::Eight),
+                    let get_integer = |key: &str| {
+                        get_attr(key).and_then(|value| match value {
+                            ScimAttr::Integer(value) => u32::try_from(*value).map_err(|_| {
+                                error!("Invalid {} attribute - out of range", key);
+                                OperationError::InvalidAttribute(format!(
+                                    "{key} is out of range - {scim_attr_name}"
+                                ))
+                            }),
                             _ => {
-                                error!("Invalid digits attribute - must be scim simple integer with the value 6 or 8");
+                                error!("Invalid {} attribute - must be scim simple integer", key);
                                 Err(OperationError::InvalidAttribute(format!(
-                                    "digits must be a positive integer value of 6 OR 8 - {scim_attr_name}"
+                                    "{key} must be scim simple integer - {scim_attr_name}"
                                 )))
                             }
-                        })?;
+                        })
+                    };
+
+                    let invalid_totp = |err: ScimTotpError| {
+                        error!(?err, "Invalid totp attribute");
+                        OperationError::InvalidAttribute(format!("{err} - {scim_attr_name}"))
+                    };
+
+                    let scim_totp = ScimTotp {
+                        external_id,
+                        secret: get_string(SCIM_SECRET)?,
+                        algo: get_string(SCIM_ALGO)?.parse().map_err(invalid_totp)?,
+                        step: get_integer(SCIM_STEP)?,
+                        digits: get_integer(SCIM_DIGITS)?,
+                    };
 
-                    let totp = Totp::new(secret, step, algo, digits);
-                    vs.push(Value::TotpSecret(external_id, totp))
+                    let totp = Totp::try_from(&scim_totp).map_err(invalid_totp)?;
+                    vs.push(Value::TotpSecret(scim_totp.external_id, totp))
                 }
                 Ok(vs)
             }
@@ -1481,7 +1436,7 @@ impl IdmServerProxyWriteTransaction<'_> {
         // to reflect the new sync state.
 
         let modlist = match to_state {
-            ScimSyncState::Active { cookie } => ModifyList::new_purge_and_set(
+            ScimSyncState::Active { cookie, .. } => ModifyList::new_purge_and_set(
                 Attribute::SyncCookie,
                 Value::PrivateBinary(cookie.to_vec()),
             ),
@@ -1489,7 +1444,14 @@ impl IdmServerProxyWriteTransaction<'_> {
         };
 
         self.qs_write
-            .internal_modify_uuid(sync_uuid, &modlist)
+            .internal_modify_uuid_checked(sync_uuid, &modlist)
+            .and_then(|modified| {
+                if modified {
+                    Ok(())
+                } else {
+                    Err(OperationError::NoMatchingEntries)
+                }
+            })
             .inspect_err(|err| {
                 error!(?err, "Failed to update sync entry state");
             })
@@ -1500,6 +1462,7 @@ impl IdmServerProxyReadTransaction<'_> {
     pub fn scim_sync_get_state(
         &mut self,
         ident: &Identity,
+        ct: Duration,
     ) -> Result<ScimSyncState, OperationError> {
         // We must be *extra* careful in these functions since we do *internal* searches
         // which are *bypassing* normal access checks!
@@ -1529,12 +1492,41 @@ impl IdmServerProxyReadTransaction<'_> {
         // Get the sync cookie of that session.
         let sync_entry = self.qs_read.internal_search_uuid(sync_uuid)?;
 
-        Ok(
-            match sync_entry.get_ava_single_private_binary(Attribute::SyncCookie) {
-                Some(b) => ScimSyncState::Active { cookie: b.to_vec() },
-                None => ScimSyncState::Refresh,
-            },
-        )
+        let Some(cookie) = sync_entry.get_ava_single_private_binary(Attribute::SyncCookie) else {
+            return Ok(ScimSyncState::Refresh);
+        };
+
+        // The cookie is set in the same transaction as the entries of that sync are
+        // written, so anything changed after the cookie was modified by someone else.
+        let change_count = match sync_entry
+            .get_changestate()
+            .get_attr_cid(&Attribute::SyncCookie)
+        {
+            Some(cookie_cid) => {
+                let f_all_sync = filter!(f_and!([
+                    f_eq(Attribute::Class, EntryClass::SyncObject.into()),
+                    f_eq(Attribute::SyncParentUuid, PartialValue::Refer(sync_uuid))
+                ]));
+
+                let count = self
+                    .qs_read
+                    .internal_search(f_all_sync)?
+                    .iter()
+                    .filter(|entry| entry.get_changestate().get_max_cid() > cookie_cid)
+                    .count();
+
+                Some(count as u64)
+            }
+            None => None,
+        };
+
+        let generated_at = OffsetDateTime::UNIX_EPOCH + ct;
+
+        Ok(ScimSyncState::Active {
+            cookie: cookie.to_vec(),
+            change_count,
+            generated_at: Some(generated_at),
+        })
     }
 }
 
@@ -1548,6 +1540,7 @@ mod tests {
     use kanidm_proto::scim_v1::*;
     use std::sync::Arc;
     use std::time::Duration;
+    use time::OffsetDateTime;
 
     use super::{
         GenerateScimSyncTokenEvent, ScimSyncFinaliseEvent, ScimSyncTerminateEvent, ScimSyncToken,
@@ -1609,7 +1602,7 @@ mod tests {
         assert_eq!(ident.get_uuid(), sync_uuid);
 
         let sync_state = idms_prox_read
-            .scim_sync_get_state(&ident)
+            .scim_sync_get_state(&ident, ct)
             .expect("Failed to get current sync state");
         trace!(?sync_state);
 
@@ -1618,6 +1611,84 @@ mod tests {
         drop(idms_prox_read);
     }
 
+    #[idm_test]
+    async fn test_idm_scim_sync_state_change_count(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let group_sync_uuid = Uuid::new_v4();
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let (_sync_uuid, sync_token) = create_scim_sync_account(&mut idms_prox_write, ct);
+        let ident = idms_prox_write
+            .validate_sync_client_auth_info_to_ident(sync_token.clone().into(), ct)
+            .expect("Failed to validate sync token");
+        let sse = ScimSyncUpdateEvent { ident };
+
+        let changes = ScimSyncRequest {
+            from_state: ScimSyncState::Refresh,
+            to_state: ScimSyncState::active(vec![1, 2, 3, 4]),
+            entries: vec![ScimEntry {
+                schemas: vec![SCIM_SCHEMA_SYNC_GROUP.to_string()],
+                id: group_sync_uuid,
+                external_id: Some("cn=testgroup,ou=people,dc=test".to_string()),
+                meta: None,
+                attrs: btreemap!((
+                    Attribute::Name.to_string(),
+                    ScimValue::Simple(ScimAttr::String("testgroup".to_string()))
+                ),),
+            }],
+            retain: ScimSyncRetentionMode::Ignore,
+        };
+
+        assert!(idms_prox_write.scim_sync_apply(&sse, &changes, ct).is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        // Nothing has changed since the sync.
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let ident = idms_prox_read
+            .validate_sync_client_auth_info_to_ident(sync_token.clone().into(), ct)
+            .expect("Failed to validate sync token");
+        let sync_state = idms_prox_read
+            .scim_sync_get_state(&ident, ct)
+            .expect("Failed to get current sync state");
+        assert_eq!(sync_state.cookie(), Some([1, 2, 3, 4].as_slice()));
+        assert_eq!(sync_state.change_count(), Some(0));
+        assert!(matches!(
+            sync_state,
+            ScimSyncState::Active {
+                generated_at: Some(generated_at),
+                ..
+            } if generated_at == OffsetDateTime::UNIX_EPOCH + ct
+        ));
+        drop(idms_prox_read);
+
+        // Change the synced entry in kanidm.
+        let ct = ct + Duration::from_secs(1);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        assert!(idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                group_sync_uuid,
+                &ModifyList::new_purge_and_set(
+                    Attribute::Description,
+                    Value::new_utf8s("changed in kanidm")
+                )
+            )
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let ident = idms_prox_read
+            .validate_sync_client_auth_info_to_ident(sync_token.into(), ct)
+            .expect("Failed to validate sync token");
+        let sync_state = idms_prox_read
+            .scim_sync_get_state(&ident, ct)
+            .expect("Failed to get current sync state");
+        assert_eq!(sync_state.change_count(), Some(1));
+    }
+
     #[idm_test]
     async fn test_idm_scim_sync_token_security(
         idms: &IdmServer,
@@ -1790,9 +1861,7 @@ mod tests {
         let sse = ScimSyncUpdateEvent { ident };
 
         let changes = ScimSyncRequest {
-            from_state: ScimSyncState::Active {
-                cookie: vec![1, 2, 3, 4],
-            },
+            from_state: ScimSyncState::active(vec![1, 2, 3, 4]),
             to_state: ScimSyncState::Refresh,
             entries: Vec::with_capacity(0),
             retain: ScimSyncRetentionMode::Ignore,
@@ -1819,9 +1888,7 @@ mod tests {
 
         let changes = ScimSyncRequest {
             from_state: ScimSyncState::Refresh,
-            to_state: ScimSyncState::Active {
-                cookie: vec![1, 2, 3, 4],
-            },
+            to_state: ScimSyncState::active(vec![1, 2, 3, 4]),
             entries: vec![ScimEntry {
                 schemas: vec![SCIM_SCHEMA_SYNC_PERSON.to_string()],
                 id: user_sync_uuid,
@@ -1887,9 +1954,7 @@ mod tests {
 
         let changes = ScimSyncRequest {
             from_state: ScimSyncState::Refresh,
-            to_state: ScimSyncState::Active {
-                cookie: vec![1, 2, 3, 4],
-            },
+            to_state: ScimSyncState::active(vec![1, 2, 3, 4]),
             entries: vec![ScimEntry {
                 schemas: vec![SCIM_SCHEMA_SYNC_PERSON.to_string()],
                 id: user_sync_uuid,
@@ -1927,9 +1992,7 @@ mod tests {
 
         let changes = ScimSyncRequest {
             from_state: ScimSyncState::Refresh,
-            to_state: ScimSyncState::Active {
-                cookie: vec![1, 2, 3, 4],
-            },
+            to_state: ScimSyncState::active(vec![1, 2, 3, 4]),
             entries,
             retain: ScimSyncRetentionMode::Ignore,
         };
@@ -2130,9 +2193,7 @@ mod tests {
 
         let changes = ScimSyncRequest {
             from_state: ScimSyncState::Refresh,
-            to_state: ScimSyncState::Active {
-                cookie: vec![1, 2, 3, 4],
-            },
+            to_state: ScimSyncState::active(vec![1, 2, 3, 4]),
             entries: vec![ScimEntry {
                 schemas: vec![SCIM_SCHEMA_SYNC_GROUP.to_string()],
                 id: user_sync_uuid,
@@ -2154,12 +2215,8 @@ mod tests {
         let sse = ScimSyncUpdateEvent { ident };
 
         let changes = ScimSyncRequest {
-            from_state: ScimSyncState::Active {
-                cookie: vec![1, 2, 3, 4],
-            },
-            to_state: ScimSyncState::Active {
-                cookie: vec![2, 3, 4, 5],
-            },
+            from_state: ScimSyncState::active(vec![1, 2, 3, 4]),
+            to_state: ScimSyncState::active(vec![2, 3, 4, 5]),
             entries: vec![],
             retain: ScimSyncRetentionMode::Delete(vec![user_sync_uuid]),
         };
@@ -2197,9 +2254,7 @@ mod tests {
 
         let changes = ScimSyncRequest {
             from_state: ScimSyncState::Refresh,
-            to_state: ScimSyncState::Active {
-                cookie: vec![1, 2, 3, 4],
-            },
+            to_state: ScimSyncState::active(vec![1, 2, 3, 4]),
             // Doesn't exist. If it does, then bless rng.
             entries: Vec::with_capacity(0),
             retain: ScimSyncRetentionMode::Delete(vec![Uuid::new_v4()]),
@@ -2236,9 +2291,7 @@ mod tests {
 
         let changes = ScimSyncRequest {
             from_state: ScimSyncState::Refresh,
-            to_state: ScimSyncState::Active {
-                cookie: vec![1, 2, 3, 4],
-            },
+            to_state: ScimSyncState::active(vec![1, 2, 3, 4]),
             // Doesn't exist. If it does, then bless rng.
             entries: Vec::with_capacity(0),
             retain: ScimSyncRetentionMode::Delete(vec![user_sync_uuid]),
@@ -2278,9 +2331,7 @@ mod tests {
 
         let changes = ScimSyncRequest {
             from_state: ScimSyncState::Refresh,
-            to_state: ScimSyncState::Active {
-                cookie: vec![1, 2, 3, 4],
-            },
+            to_state: ScimSyncState::active(vec![1, 2, 3, 4]),
             // Doesn't exist. If it does, then bless rng.
             entries: Vec::with_capacity(0),
             retain: ScimSyncRetentionMode::Delete(vec![user_sync_uuid]),
@@ -2313,9 +2364,7 @@ mod tests {
 
         let changes = ScimSyncRequest {
             from_state: ScimSyncState::Refresh,
-            to_state: ScimSyncState::Active {
-                cookie: vec![1, 2, 3, 4],
-            },
+            to_state: ScimSyncState::active(vec![1, 2, 3, 4]),
             entries: vec![
                 ScimEntry {
                     schemas: vec![SCIM_SCHEMA_SYNC_GROUP.to_string()],
@@ -2349,12 +2398,8 @@ mod tests {
         let sse = ScimSyncUpdateEvent { ident };
 
         let changes = ScimSyncRequest {
-            from_state: ScimSyncState::Active {
-                cookie: vec![1, 2, 3, 4],
-            },
-            to_state: ScimSyncState::Active {
-                cookie: vec![2, 3, 4, 5],
-            },
+            from_state: ScimSyncState::active(vec![1, 2, 3, 4]),
+            to_state: ScimSyncState::active(vec![2, 3, 4, 5]),
             entries: vec![],
             retain: ScimSyncRetentionMode::Retain(vec![sync_uuid_a]),
         };
@@ -2397,9 +2442,7 @@ mod tests {
 
         let changes = ScimSyncRequest {
             from_state: ScimSyncState::Refresh,
-            to_state: ScimSyncState::Active {
-                cookie: vec![1, 2, 3, 4],
-            },
+            to_state: ScimSyncState::active(vec![1, 2, 3, 4]),
             entries: vec![
                 ScimEntry {
                     schemas: vec![SCIM_SCHEMA_SYNC_GROUP.to_string()],
@@ -2433,12 +2476,8 @@ mod tests {
         let sse = ScimSyncUpdateEvent { ident };
 
         let changes = ScimSyncRequest {
-            from_state: ScimSyncState::Active {
-                cookie: vec![1, 2, 3, 4],
-            },
-            to_state: ScimSyncState::Active {
-                cookie: vec![2, 3, 4, 5],
-            },
+            from_state: ScimSyncState::active(vec![1, 2, 3, 4]),
+            to_state: ScimSyncState::active(vec![2, 3, 4, 5]),
             entries: vec![],
             retain: ScimSyncRetentionMode::Retain(vec![]),
         };
@@ -2495,9 +2534,7 @@ mod tests {
 
         let changes = ScimSyncRequest {
             from_state: ScimSyncState::Refresh,
-            to_state: ScimSyncState::Active {
-                cookie: vec![1, 2, 3, 4],
-            },
+            to_state: ScimSyncState::active(vec![1, 2, 3, 4]),
             entries: vec![ScimEntry {
                 schemas: vec![SCIM_SCHEMA_SYNC_GROUP.to_string()],
                 id: sync_uuid_a,
@@ -2519,12 +2556,8 @@ mod tests {
         let sse = ScimSyncUpdateEvent { ident };
 
         let changes = ScimSyncRequest {
-            from_state: ScimSyncState::Active {
-                cookie: vec![1, 2, 3, 4],
-            },
-            to_state: ScimSyncState::Active {
-                cookie: vec![2, 3, 4, 5],
-            },
+            from_state: ScimSyncState::active(vec![1, 2, 3, 4]),
+            to_state: ScimSyncState::active(vec![2, 3, 4, 5]),
             entries: vec![],
             retain: ScimSyncRetentionMode::Retain(vec![sync_uuid_a]),
         };
@@ -2557,9 +2590,7 @@ mod tests {
 
         let changes = ScimSyncRequest {
             from_state: ScimSyncState::Refresh,
-            to_state: ScimSyncState::Active {
-                cookie: vec![1, 2, 3, 4],
-            },
+            to_state: ScimSyncState::active(vec![1, 2, 3, 4]),
             entries: Vec::with_capacity(0),
             retain: ScimSyncRetentionMode::Ignore,
         };
@@ -2572,12 +2603,8 @@ mod tests {
         let sse = ScimSyncUpdateEvent { ident };
 
         let changes = ScimSyncRequest {
-            from_state: ScimSyncState::Active {
-                cookie: vec![1, 2, 3, 4],
-            },
-            to_state: ScimSyncState::Active {
-                cookie: vec![2, 3, 4, 5],
-            },
+            from_state: ScimSyncState::active(vec![1, 2, 3, 4]),
+            to_state: ScimSyncState::active(vec![2, 3, 4, 5]),
             entries: vec![],
             retain: ScimSyncRetentionMode::Ignore,
         };
@@ -3240,10 +3267,11 @@ mod tests {
         .set_totp_import(vec![ScimTotp {
             external_id: "Totp".to_string(),
             secret: "QICWZTON72IBS5MXWNURKAONC3JNOOOFMLKNRTIPXBYQ4BLRSEBM7KF5".to_string(),
-            algo: "sha256".to_string(),
+            algo: TotpAlgorithm::Sha256,
             step: 60,
             digits: 8,
         }])
+        .unwrap()
         .set_mail(vec![MultiValueAttr {
             primary: Some(true),
             value: "testuser@example.com".to_string(),
@@ -3278,9 +3306,7 @@ mod tests {
 
         let changes = ScimSyncRequest {
             from_state: ScimSyncState::Refresh,
-            to_state: ScimSyncState::Active {
-                cookie: vec![1, 2, 3, 4],
-            },
+            to_state: ScimSyncState::active(vec![1, 2, 3, 4]),
             entries,
             retain: ScimSyncRetentionMode::Ignore,
         };
@@ -3289,6 +3315,85 @@ mod tests {
         assert!(idms_prox_write.commit().is_ok());
     }
 
+    #[idm_test]
+    /// Assert that sync totp imports are checked when they are converted from [ScimTotp].
+    async fn test_idm_scim_sync_totp_import(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+
+        let person = |name: &str, step: u32| -> ScimEntry {
+            ScimSyncPerson::builder(
+                Uuid::new_v4(),
+                format!("cn={name}"),
+                name.to_string(),
+                name.to_string(),
+            )
+            .set_password_import(Some("ipaNTHash: iEb36u6PsRetBr3YMLdYbA".to_string()))
+            .set_totp_import(vec![ScimTotp {
+                external_id: "Totp".to_string(),
+                secret: "QICWZTON72IBS5MXWNURKAONC3JNOOOFMLKNRTIPXBYQ4BLRSEBM7KF5".to_string(),
+                algo: TotpAlgorithm::Sha256,
+                step,
+                digits: 6,
+            }])
+            .unwrap()
+            .build()
+            .try_into()
+            .unwrap()
+        };
+
+        let set_totp_attr = |entry: &mut ScimEntry, key: &str, value: ScimAttr| {
+            let Some(ScimValue::MultiComplex(totps)) =
+                entry.attrs.get_mut(Attribute::TotpImport.as_str())
+            else {
+                panic!("totp_import is not multi complex");
+            };
+            for totp in totps.iter_mut() {
+                totp.insert(key.to_string(), value.clone());
+            }
+        };
+
+        let changes = |entries| ScimSyncRequest {
+            from_state: ScimSyncState::Refresh,
+            to_state: ScimSyncState::active(vec![1, 2, 3, 4]),
+            entries,
+            retain: ScimSyncRetentionMode::Ignore,
+        };
+
+        // Steps shorter than 30 seconds are refused.
+        let mut person_1 = person("testperson_1", 30);
+        set_totp_attr(&mut person_1, SCIM_STEP, ScimAttr::Integer(15));
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let (_sync_uuid, ident) = test_scim_sync_apply_setup_ident(&mut idms_prox_write, ct);
+        let sse = ScimSyncUpdateEvent { ident };
+        assert!(matches!(
+            idms_prox_write.scim_sync_apply(&sse, &changes(vec![person_1]), ct),
+            Err(OperationError::InvalidAttribute(_))
+        ));
+        drop(idms_prox_write);
+
+        // Long steps are still accepted, and the algorithm is case insensitive.
+        let mut person_1 = person("testperson_1", 30);
+        set_totp_attr(&mut person_1, SCIM_STEP, ScimAttr::Integer(600));
+        let mut person_2 = person("testperson_2", 30);
+        set_totp_attr(
+            &mut person_2,
+            SCIM_ALGO,
+            ScimAttr::String("SHA512".to_string()),
+        );
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let (_sync_uuid, ident) = test_scim_sync_apply_setup_ident(&mut idms_prox_write, ct);
+        let sse = ScimSyncUpdateEvent { ident };
+        assert!(idms_prox_write
+            .scim_sync_apply(&sse, &changes(vec![person_1, person_2]), ct)
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+    }
+
     const TEST_SYNC_SCIM_IPA_1: &str = r#"
 {
   "from_state": "Refresh",
@@ -9,8 +9,8 @@ use crate::idm::authentication::{AuthState, PreValidatedTokenStatus};
 use crate::idm::authsession::{AuthSession, AuthSessionData};
 use crate::idm::credupdatesession::CredentialUpdateSessionMutex;
 use crate::idm::delayed::{
-    AuthSessionRecord, BackupCodeRemoval, DelayedAction, PasswordUpgrade, UnixPasswordUpgrade,
-    WebauthnCounterIncrement,
+    AuthSessionRecord, BackupCodeRemoval, DelayedAction, PasswordUpgrade, SessionLastSeen,
+    UnixPasswordUpgrade, WebauthnCounterIncrement,
 };
 use crate::idm::event::{
     AuthEvent, AuthEventStep, AuthResult, CredentialStatusEvent, LdapAuthEvent, LdapTokenAuthEvent,
@@ -28,9 +28,10 @@ use crate::idm::scim::SyncAccount;
 use crate::idm::serviceaccount::ServiceAccount;
 use crate::prelude::*;
 use crate::server::keys::KeyProvidersTransaction;
-use crate::server::DomainInfo;
+use crate::server::{DomainInfo, UnixChangeEvent};
 use crate::utils::{password_from_random, readable_password_from_random, uuid_from_duration, Sid};
 use crate::value::{Session, SessionState};
+use cidr::IpCidr;
 use compact_jwt::{Jwk, JwsCompact};
 use concread::bptree::{BptreeMap, BptreeMapReadTxn, BptreeMapWriteTxn};
 use concread::cowcell::CowCellReadTxn;
@@ -42,12 +43,14 @@ use kanidm_proto::internal::{
 };
 use kanidm_proto::v1::{UnixGroupToken, UnixUserToken};
 use rand::prelude::*;
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
+use std::net::IpAddr;
 use std::sync::Arc;
 use std::time::Duration;
 use time::OffsetDateTime;
 use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
-use tokio::sync::{Mutex, Semaphore};
+use tokio::sync::{broadcast, Mutex, Semaphore};
 use tracing::trace;
 use url::Url;
 use webauthn_rs::prelude::{Webauthn, WebauthnBuilder};
@@ -61,6 +64,29 @@ pub(crate) type CredSoftLockMutex = Arc<Mutex<CredSoftLock>>;
 
 pub type DomainInfoRead = CowCellReadTxn<DomainInfo>;
 
+/// When configured, user sessions are bound to the address they authenticated from
+/// and are rejected if presented from any other address.
+#[derive(Debug, Clone, Default)]
+pub struct SessionIpBinding {
+    /// Requests from these ranges may use a session regardless of where it was created,
+    /// for networks where a client's address changes between requests (split tunneling).
+    pub exclude_cidrs: Vec<IpCidr>,
+}
+
+impl SessionIpBinding {
+    fn permits(&self, bound_ip: IpAddr, source: &Source) -> bool {
+        let Some(client_ip) = source.client_ip() else {
+            return false;
+        };
+
+        client_ip.to_canonical() == bound_ip.to_canonical()
+            || self
+                .exclude_cidrs
+                .iter()
+                .any(|cidr| cidr.contains(&client_ip.to_canonical()))
+    }
+}
+
 pub struct IdmServer {
     // There is a good reason to keep this single thread - it
     // means that limits to sessions can be easily applied and checked to
@@ -85,6 +111,11 @@ pub struct IdmServer {
     /// OAuth2ClientProviders
     origin: Url,
     oauth2_client_providers: HashMap<Uuid, OAuth2ClientProvider>,
+
+    session_ip_binding: Option<SessionIpBinding>,
+    /// Record when sessions were last used. Read only replicas don't, as the record
+    /// is a write to the account.
+    record_session_last_seen: bool,
 }
 
 /// Contains methods that require writes, but in the context of writing to the idm in memory structures (maybe the query server too). This is things like authentication.
@@ -102,6 +133,8 @@ pub struct IdmServerAuthTransaction<'a> {
     pub(crate) audit_tx: UnboundedSender<AuditEvent>,
     pub(crate) webauthn: &'a Webauthn,
     pub(crate) applications: LdapApplicationsReadTransaction,
+    pub(crate) session_ip_binding: Option<&'a SessionIpBinding>,
+    pub(crate) record_session_last_seen: bool,
 }
 
 pub struct IdmServerCredUpdateTransaction<'a> {
@@ -116,6 +149,10 @@ pub struct IdmServerCredUpdateTransaction<'a> {
 pub struct IdmServerProxyReadTransaction<'a> {
     pub qs_read: QueryServerReadTransaction<'a>,
     pub(crate) oauth2rs: Oauth2ResourceServersReadTransaction,
+    pub(crate) session_ip_binding: Option<&'a SessionIpBinding>,
+    pub(crate) record_session_last_seen: bool,
+    // For flagging eventual actions.
+    pub(crate) async_tx: UnboundedSender<DelayedAction>,
 }
 
 pub struct IdmServerProxyWriteTransaction<'a> {
@@ -132,6 +169,10 @@ pub struct IdmServerProxyWriteTransaction<'a> {
 
     pub(crate) origin: &'a Url,
     pub(crate) oauth2_client_providers: HashMapWriteTxn<'a, Uuid, OAuth2ClientProvider>,
+    pub(crate) session_ip_binding: Option<&'a SessionIpBinding>,
+    pub(crate) record_session_last_seen: bool,
+    // For flagging eventual actions.
+    pub(crate) async_tx: UnboundedSender<DelayedAction>,
 }
 
 pub struct IdmServerDelayed {
@@ -225,6 +266,8 @@ impl IdmServer {
             applications: Arc::new(applications),
             origin: origin.clone(),
             oauth2_client_providers: HashMap::new(),
+            session_ip_binding: None,
+            record_session_last_seen: true,
         };
         let idm_server_delayed = IdmServerDelayed { async_rx };
         let idm_server_audit = IdmServerAudit { audit_rx };
@@ -240,6 +283,18 @@ impl IdmServer {
         Ok((idm_server, idm_server_delayed, idm_server_audit))
     }
 
+    /// Bind new user sessions to the client address they authenticate from. Sessions
+    /// that were bound while this was set are only checked while it remains set.
+    pub fn set_session_ip_binding(&mut self, session_ip_binding: Option<SessionIpBinding>) {
+        self.session_ip_binding = session_ip_binding;
+    }
+
+    /// Record when sessions were last used. This should be disabled on read only
+    /// replicas, as each record is a replicated write to the account.
+    pub fn set_record_session_last_seen(&mut self, record_session_last_seen: bool) {
+        self.record_session_last_seen = record_session_last_seen;
+    }
+
     /// Start an auth txn
     pub async fn auth(&self) -> Result<IdmServerAuthTransaction<'_>, OperationError> {
         let qs_read = self.qs.read().await?;
@@ -259,6 +314,8 @@ impl IdmServer {
             webauthn: &self.webauthn,
             applications: self.applications.read(),
             oauth2_client_providers: self.oauth2_client_providers.read(),
+            session_ip_binding: self.session_ip_binding.as_ref(),
+            record_session_last_seen: self.record_session_last_seen,
         })
     }
 
@@ -270,6 +327,11 @@ impl IdmServer {
         self.qs.d_info.read()
     }
 
+    /// Subscribe to notifications of committed changes to posix accounts and groups.
+    pub fn subscribe_unix_changes(&self) -> broadcast::Receiver<UnixChangeEvent> {
+        self.qs.subscribe_unix_changes()
+    }
+
     /// Read from the database, in a transaction.
     #[instrument(level = "debug", skip_all)]
     pub async fn proxy_read(&self) -> Result<IdmServerProxyReadTransaction<'_>, OperationError> {
@@ -277,7 +339,9 @@ impl IdmServer {
         Ok(IdmServerProxyReadTransaction {
             qs_read,
             oauth2rs: self.oauth2rs.read(),
-            // async_tx: self.async_tx.clone(),
+            session_ip_binding: self.session_ip_binding.as_ref(),
+            record_session_last_seen: self.record_session_last_seen,
+            async_tx: self.async_tx.clone(),
         })
     }
 
@@ -302,6 +366,9 @@ impl IdmServer {
             applications: self.applications.write(),
             origin: &self.origin,
             oauth2_client_providers: self.oauth2_client_providers.write(),
+            session_ip_binding: self.session_ip_binding.as_ref(),
+            record_session_last_seen: self.record_session_last_seen,
+            async_tx: self.async_tx.clone(),
         })
     }
 
@@ -387,10 +454,38 @@ impl IdmServerDelayed {
     pub async fn recv_many(&mut self, buffer: &mut Vec<DelayedAction>) -> usize {
         debug_assert!(buffer.is_empty());
         let limit = buffer.capacity();
-        self.async_rx.recv_many(buffer, limit).await
+        let added = self.async_rx.recv_many(buffer, limit).await;
+        dedup_session_last_seen(buffer);
+        added
     }
 }
 
+/// Concurrent requests on the same session can each queue a last seen update. Only
+/// the latest update for each session is kept.
+fn dedup_session_last_seen(buffer: &mut Vec<DelayedAction>) {
+    let mut latest: BTreeMap<Uuid, OffsetDateTime> = BTreeMap::new();
+    for da in buffer.iter() {
+        if let DelayedAction::SessionLastSeen(sls) = da {
+            latest
+                .entry(sls.session_id)
+                .and_modify(|last_seen| *last_seen = (*last_seen).max(sls.last_seen))
+                .or_insert(sls.last_seen);
+        }
+    }
+
+    buffer.retain(|da| match da {
+        DelayedAction::SessionLastSeen(sls) => {
+            if latest.get(&sls.session_id) == Some(&sls.last_seen) {
+                latest.remove(&sls.session_id);
+                true
+            } else {
+                false
+            }
+        }
+        _ => true,
+    });
+}
+
 pub enum Token {
     UserAuthToken(UserAuthToken),
     ApiToken(ApiToken, Arc<EntrySealedCommitted>),
@@ -401,6 +496,12 @@ pub trait IdmServerTransaction<'a> {
 
     fn get_qs_txn(&mut self) -> &mut Self::QsTransactionType;
 
+    fn get_session_ip_binding(&self) -> Option<&SessionIpBinding>;
+
+    fn get_record_session_last_seen(&self) -> bool;
+
+    fn get_async_tx(&self) -> &UnboundedSender<DelayedAction>;
+
     /// This is the preferred method to transform and securely verify a token into
     /// an identity that can be used for operations and access enforcement. This
     /// function *is* aware of the various classes of tokens that may exist, and can
@@ -772,8 +873,49 @@ pub trait IdmServerTransaction<'a> {
             return Err(OperationError::SessionExpired);
         }
 
+        let session = entry
+            .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+            .and_then(|sessions| sessions.get(&uat.session_id));
+
+        if let Some(session_ip_binding) = self.get_session_ip_binding() {
+            let bound_ip = session.and_then(|session| session.client_ip);
+
+            if let Some(bound_ip) = bound_ip {
+                if !session_ip_binding.permits(bound_ip, &source) {
+                    security_info!(
+                        ?bound_ip,
+                        ?source,
+                        "Session is bound to a different client address, rejecting"
+                    );
+                    return Err(OperationError::NotAuthenticated);
+                }
+            }
+        }
+
         // ✅  Session is valid! Start to setup for it to be used.
 
+        // Record the use of the session. This is best effort, and is only written
+        // once per interval to avoid a write on every request.
+        let cot = OffsetDateTime::UNIX_EPOCH + ct;
+        let last_seen_stale = self.get_record_session_last_seen()
+            && session.is_some_and(|session| {
+                session
+                    .last_seen
+                    .is_none_or(|last_seen| last_seen + SESSION_LAST_SEEN_INTERVAL <= cot)
+            });
+        if last_seen_stale {
+            if let Err(err) =
+                self.get_async_tx()
+                    .send(DelayedAction::SessionLastSeen(SessionLastSeen {
+                        target_uuid: uat.uuid,
+                        session_id: uat.session_id,
+                        last_seen: cot,
+                    }))
+            {
+                debug!(?err, "Unable to queue session last seen update");
+            }
+        }
+
         let scope = match uat.purpose {
             UatPurpose::ReadOnly => AccessScope::ReadOnly,
             UatPurpose::ReadWrite { expiry: None } => AccessScope::ReadOnly,
@@ -1159,6 +1301,18 @@ impl<'a> IdmServerTransaction<'a> for IdmServerAuthTransaction<'a> {
     fn get_qs_txn(&mut self) -> &mut Self::QsTransactionType {
         &mut self.qs_read
     }
+
+    fn get_session_ip_binding(&self) -> Option<&SessionIpBinding> {
+        self.session_ip_binding
+    }
+
+    fn get_record_session_last_seen(&self) -> bool {
+        self.record_session_last_seen
+    }
+
+    fn get_async_tx(&self) -> &UnboundedSender<DelayedAction> {
+        &self.async_tx
+    }
 }
 
 impl IdmServerAuthTransaction<'_> {
@@ -1667,6 +1821,18 @@ impl<'a> IdmServerTransaction<'a> for IdmServerProxyReadTransaction<'a> {
     fn get_qs_txn(&mut self) -> &mut Self::QsTransactionType {
         &mut self.qs_read
     }
+
+    fn get_session_ip_binding(&self) -> Option<&SessionIpBinding> {
+        self.session_ip_binding
+    }
+
+    fn get_record_session_last_seen(&self) -> bool {
+        self.record_session_last_seen
+    }
+
+    fn get_async_tx(&self) -> &UnboundedSender<DelayedAction> {
+        &self.async_tx
+    }
 }
 
 fn gen_password_mod(
@@ -1786,6 +1952,18 @@ impl<'a> IdmServerTransaction<'a> for IdmServerProxyWriteTransaction<'a> {
     fn get_qs_txn(&mut self) -> &mut Self::QsTransactionType {
         &mut self.qs_write
     }
+
+    fn get_session_ip_binding(&self) -> Option<&SessionIpBinding> {
+        self.session_ip_binding
+    }
+
+    fn get_record_session_last_seen(&self) -> bool {
+        self.record_session_last_seen
+    }
+
+    fn get_async_tx(&self) -> &UnboundedSender<DelayedAction> {
+        &self.async_tx
+    }
 }
 
 impl IdmServerProxyWriteTransaction<'_> {
@@ -2265,6 +2443,10 @@ impl IdmServerProxyWriteTransaction<'_> {
             None => SessionState::NeverExpires,
         };
 
+        // The client address and last use are only stored once every server in the
+        // domain is able to read them.
+        let session_v5 = self.qs_write.get_domain_version() >= DOMAIN_LEVEL_1_11;
+
         let session = Value::Session(
             asr.session_id,
             Session {
@@ -2282,6 +2464,13 @@ impl IdmServerProxyWriteTransaction<'_> {
                 scope: asr.scope,
                 type_: asr.type_,
                 ext_metadata: Default::default(),
+                // Only record where the session came from if we will enforce it.
+                client_ip: self
+                    .session_ip_binding
+                    .and(asr.client_ip)
+                    .filter(|_| session_v5),
+                // Authentication is the first use of the session.
+                last_seen: Some(asr.issued_at).filter(|_| session_v5),
             },
         );
 
@@ -2302,6 +2491,51 @@ impl IdmServerProxyWriteTransaction<'_> {
         // Done!
     }
 
+    #[instrument(level = "debug", skip_all)]
+    pub(crate) fn process_sessionlastseen(
+        &mut self,
+        sls: &SessionLastSeen,
+    ) -> Result<(), OperationError> {
+        if self.qs_write.get_domain_version() < DOMAIN_LEVEL_1_11 {
+            // Servers on older domain levels can't read the last use of a session.
+            return Ok(());
+        }
+
+        let entry = self.qs_write.internal_search_uuid(sls.target_uuid)?;
+
+        let Some(session) = entry
+            .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+            .and_then(|sessions| sessions.get(&sls.session_id))
+        else {
+            // The session was removed before its use was recorded.
+            return Ok(());
+        };
+
+        if session
+            .last_seen
+            .is_some_and(|last_seen| last_seen + SESSION_LAST_SEEN_INTERVAL > sls.last_seen)
+        {
+            // A use within the interval was already recorded, possibly from an
+            // update that was queued at the same time as this one.
+            return Ok(());
+        }
+
+        let session = Value::Session(
+            sls.session_id,
+            Session {
+                last_seen: Some(sls.last_seen),
+                ..session.clone()
+            },
+        );
+
+        let modlist = ModifyList::new_append(Attribute::UserAuthTokenSession, session);
+
+        self.qs_write.internal_modify(
+            &filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(sls.target_uuid))),
+            &modlist,
+        )
+    }
+
     #[instrument(level = "debug", skip_all)]
     pub fn process_delayedaction(
         &mut self,
@@ -2314,6 +2548,7 @@ impl IdmServerProxyWriteTransaction<'_> {
             DelayedAction::WebauthnCounterIncrement(wci) => self.process_webauthncounterinc(wci),
             DelayedAction::BackupCodeRemoval(bcr) => self.process_backupcoderemoval(bcr),
             DelayedAction::AuthSessionRecord(asr) => self.process_authsessionrecord(asr),
+            DelayedAction::SessionLastSeen(sls) => self.process_sessionlastseen(sls),
         }
     }
 
@@ -2370,24 +2605,30 @@ impl IdmServerProxyWriteTransaction<'_> {
 #[cfg(test)]
 mod tests {
     use std::convert::TryFrom;
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::str::FromStr;
     use std::time::Duration;
 
     use crate::credential::{Credential, Password};
     use crate::idm::account::DestroySessionTokenEvent;
     use crate::idm::accountpolicy::ResolvedAccountPolicy;
     use crate::idm::audit::AuditEvent;
-    use crate::idm::authentication::AuthState;
-    use crate::idm::delayed::{AuthSessionRecord, DelayedAction};
+    use crate::idm::authentication::{AuthState, ClientAuthInfo};
+    use crate::idm::delayed::{AuthSessionRecord, DelayedAction, SessionLastSeen};
     use crate::idm::event::{AuthEvent, AuthResult};
     use crate::idm::event::{
         LdapAuthEvent, PasswordChangeEvent, RadiusAuthTokenEvent, RegenerateRadiusSecretEvent,
         UnixGroupTokenEvent, UnixPasswordChangeEvent, UnixUserAuthEvent, UnixUserTokenEvent,
     };
-    use crate::idm::server::{IdmServer, IdmServerTransaction, Token};
+    use crate::idm::server::{
+        dedup_session_last_seen, IdmServer, IdmServerProxyReadTransaction, IdmServerTransaction,
+        SessionIpBinding, Token,
+    };
     use crate::modify::{Modify, ModifyList};
     use crate::prelude::*;
     use crate::server::keys::KeyProvidersTransaction;
     use crate::value::{AuthType, SessionState};
+    use cidr::IpCidr;
     use compact_jwt::{traits::JwsVerifiable, JwsCompact, JwsEs256Verifier, JwsVerifier};
     use kanidm_lib_crypto::CryptoPolicy;
     use kanidm_proto::v1::{AuthAllowed, AuthIssueSession, AuthMech};
@@ -3707,6 +3948,207 @@ mod tests {
         }
     }
 
+    #[idm_test]
+    async fn test_idm_session_last_seen(idms: &IdmServer, idms_delayed: &mut IdmServerDelayed) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        init_testperson_w_password(idms, TEST_PASSWORD)
+            .await
+            .expect("Failed to setup admin account");
+        let token = check_testperson_password(idms, TEST_PASSWORD, ct).await;
+
+        let da = idms_delayed.try_recv().expect("invalid");
+        assert!(matches!(da, DelayedAction::AuthSessionRecord(_)));
+        let r = idms.delayed_action(ct, da).await;
+        assert_eq!(Ok(true), r);
+
+        let last_seen = |idms_prox_read: &mut IdmServerProxyReadTransaction| {
+            let entry = idms_prox_read
+                .qs_read
+                .internal_search_uuid(UUID_TESTPERSON_1)
+                .expect("Failed to find testperson");
+            entry
+                .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+                .and_then(|sessions| sessions.values().next())
+                .and_then(|session| session.last_seen)
+        };
+
+        // Using the session just after authentication doesn't need a write.
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        idms_prox_read
+            .validate_client_auth_info_to_ident(token.clone().into(), ct)
+            .expect("Failed to validate");
+        assert_eq!(
+            last_seen(&mut idms_prox_read),
+            Some(OffsetDateTime::UNIX_EPOCH + ct)
+        );
+        drop(idms_prox_read);
+        idms_delayed.check_is_empty_or_panic();
+
+        // Once the interval has passed, the use is recorded.
+        let later = ct + SESSION_LAST_SEEN_INTERVAL;
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        idms_prox_read
+            .validate_client_auth_info_to_ident(token.clone().into(), later)
+            .expect("Failed to validate");
+        drop(idms_prox_read);
+
+        let da = idms_delayed.try_recv().expect("invalid");
+        assert!(matches!(da, DelayedAction::SessionLastSeen(_)));
+        let r = idms.delayed_action(later, da).await;
+        assert_eq!(Ok(true), r);
+        idms_delayed.check_is_empty_or_panic();
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        assert_eq!(
+            last_seen(&mut idms_prox_read),
+            Some(OffsetDateTime::UNIX_EPOCH + later)
+        );
+
+        // Concurrent uses of the session each queue an update.
+        let first = later + SESSION_LAST_SEEN_INTERVAL;
+        let second = first + Duration::from_secs(1);
+        for ct in [first, second] {
+            idms_prox_read
+                .validate_client_auth_info_to_ident(token.clone().into(), ct)
+                .expect("Failed to validate");
+        }
+        drop(idms_prox_read);
+
+        let mut buffer = vec![
+            idms_delayed.try_recv().expect("invalid"),
+            idms_delayed.try_recv().expect("invalid"),
+        ];
+        idms_delayed.check_is_empty_or_panic();
+
+        // When they are in the same batch only the latest is kept.
+        let mut batch: Vec<_> = buffer
+            .iter()
+            .map(|da| match da {
+                DelayedAction::SessionLastSeen(sls) => {
+                    DelayedAction::SessionLastSeen(SessionLastSeen { ..*sls })
+                }
+                _ => panic!("Unexpected delayed action"),
+            })
+            .collect();
+        dedup_session_last_seen(&mut batch);
+        assert!(matches!(
+            batch.as_slice(),
+            [DelayedAction::SessionLastSeen(sls)]
+                if sls.last_seen == OffsetDateTime::UNIX_EPOCH + second
+        ));
+
+        // Otherwise the later update is within the interval of the first, so only
+        // the first is written.
+        for da in buffer.drain(..) {
+            let r = idms.delayed_action(second, da).await;
+            assert_eq!(Ok(true), r);
+        }
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        assert_eq!(
+            last_seen(&mut idms_prox_read),
+            Some(OffsetDateTime::UNIX_EPOCH + first)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_idm_session_last_seen_read_only() {
+        let (mut idms, mut idms_delayed, _idms_audit) =
+            crate::testkit::setup_idm_test(Default::default()).await;
+
+        idms.set_record_session_last_seen(false);
+
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        init_testperson_w_password(&idms, TEST_PASSWORD)
+            .await
+            .expect("Failed to setup admin account");
+        let token = check_testperson_password(&idms, TEST_PASSWORD, ct).await;
+
+        let da = idms_delayed.try_recv().expect("invalid");
+        assert!(matches!(da, DelayedAction::AuthSessionRecord(_)));
+        let r = idms.delayed_action(ct, da).await;
+        assert_eq!(Ok(true), r);
+
+        // Using the session after the interval doesn't queue a write.
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        idms_prox_read
+            .validate_client_auth_info_to_ident(token.into(), ct + SESSION_LAST_SEEN_INTERVAL)
+            .expect("Failed to validate");
+        drop(idms_prox_read);
+        idms_delayed.check_is_empty_or_panic();
+    }
+
+    #[tokio::test]
+    async fn test_idm_session_ip_binding() {
+        let (mut idms, mut idms_delayed, _idms_audit) =
+            crate::testkit::setup_idm_test(Default::default()).await;
+
+        idms.set_session_ip_binding(Some(SessionIpBinding {
+            exclude_cidrs: vec![IpCidr::from_str("10.0.0.0/8").expect("Invalid cidr")],
+        }));
+
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        init_testperson_w_password(&idms, TEST_PASSWORD)
+            .await
+            .expect("Failed to setup admin account");
+        let token = check_testperson_password(&idms, TEST_PASSWORD, ct).await;
+
+        let bound_ip = IpAddr::from([192, 0, 2, 1]);
+
+        // Record the session as though it had been created from the bound address.
+        let mut da = idms_delayed.try_recv().expect("invalid");
+        let DelayedAction::AuthSessionRecord(asr) = &mut da else {
+            panic!("Unexpected delayed action");
+        };
+        asr.client_ip = Some(bound_ip);
+        let r = idms.delayed_action(ct, da).await;
+        assert_eq!(Ok(true), r);
+        idms_delayed.check_is_empty_or_panic();
+
+        let client_auth_info =
+            |ip: IpAddr| ClientAuthInfo::new(Source::Https(ip), None, Some(token.clone()), None);
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+
+        // The address the session was bound to may use it.
+        idms_prox_read
+            .validate_client_auth_info_to_ident(client_auth_info(bound_ip), ct)
+            .expect("Failed to validate");
+
+        // As may the same address when presented as an ipv4 mapped ipv6 address.
+        idms_prox_read
+            .validate_client_auth_info_to_ident(
+                client_auth_info(IpAddr::V6(Ipv4Addr::new(192, 0, 2, 1).to_ipv6_mapped())),
+                ct,
+            )
+            .expect("Failed to validate");
+
+        // Any other address is rejected.
+        assert_eq!(
+            idms_prox_read
+                .validate_client_auth_info_to_ident(
+                    client_auth_info(IpAddr::from([192, 0, 2, 2])),
+                    ct
+                )
+                .map(|_| ()),
+            Err(OperationError::NotAuthenticated)
+        );
+
+        // Unless it is excluded from binding.
+        idms_prox_read
+            .validate_client_auth_info_to_ident(client_auth_info(IpAddr::from([10, 1, 2, 3])), ct)
+            .expect("Failed to validate");
+
+        drop(idms_prox_read);
+
+        // Bound sessions aren't enforced once binding is disabled.
+        idms.set_session_ip_binding(None);
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        idms_prox_read
+            .validate_client_auth_info_to_ident(client_auth_info(IpAddr::from([192, 0, 2, 2])), ct)
+            .expect("Failed to validate");
+    }
+
     #[idm_test]
     async fn test_idm_expired_auth_session_cleanup(
         idms: &IdmServer,
@@ -3745,6 +4187,7 @@ mod tests {
             scope: SessionScope::ReadOnly,
             type_: AuthType::Passkey,
             ext_metadata: Default::default(),
+            client_ip: None,
         });
         // Persist it.
         let r = idms.delayed_action(ct, da).await;
@@ -3778,6 +4221,7 @@ mod tests {
             scope: SessionScope::ReadOnly,
             type_: AuthType::Passkey,
             ext_metadata: Default::default(),
+            client_ip: None,
         });
         // Persist it.
         let r = idms.delayed_action(expiry_a, da).await;
@@ -3865,6 +4309,10 @@ mod tests {
 
         drop(idms_prox_read);
 
+        // The later use of the session is queued to be recorded.
+        let da = idms_delayed.try_recv().expect("invalid");
+        assert!(matches!(da, DelayedAction::SessionLastSeen(_)));
+
         // Mark the session as invalid now.
         let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
         let dte = DestroySessionTokenEvent::new_internal(uat_inner.uuid, uat_inner.session_id);
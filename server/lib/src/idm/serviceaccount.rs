@@ -2,7 +2,7 @@ use std::collections::BTreeMap;
 use std::time::Duration;
 
 use compact_jwt::{jws::JwsBuilder, Jws, JwsCompact};
-use kanidm_proto::internal::ApiToken as ProtoApiToken;
+use kanidm_proto::internal::{ApiToken as ProtoApiToken, ApiTokenSummary};
 use time::OffsetDateTime;
 
 use crate::credential::Credential;
@@ -115,6 +115,13 @@ pub struct ListApiTokenEvent {
     pub target: Uuid,
 }
 
+pub struct ListAllApiTokenEvent {
+    // Who initiated this?
+    pub ident: Identity,
+    // Only list the tokens of this account, if set.
+    pub account: Option<Uuid>,
+}
+
 pub struct GenerateApiTokenEvent {
     // Who initiated this?
     pub ident: Identity,
@@ -398,6 +405,66 @@ impl IdmServerProxyReadTransaction<'_> {
             Err(e) => Err(e),
         }
     }
+
+    /// List the api tokens of every account that the initiator can see. Access controls
+    /// apply, so an account without rights to read api tokens receives an empty list.
+    pub fn system_list_api_tokens(
+        &mut self,
+        late: &ListAllApiTokenEvent,
+    ) -> Result<Vec<ApiTokenSummary>, OperationError> {
+        let filter = match late.account {
+            Some(account) => filter!(f_and!([
+                f_pres(Attribute::ApiTokenSession),
+                f_eq(Attribute::Uuid, PartialValue::Uuid(account))
+            ])),
+            None => filter!(f_pres(Attribute::ApiTokenSession)),
+        };
+
+        let filter_orig = filter
+            .validate(self.qs_read.get_schema())
+            .map_err(OperationError::SchemaViolation)?;
+        let filter = filter_orig.clone().into_ignore_hidden();
+
+        let srch = SearchEvent {
+            ident: late.ident.clone(),
+            filter,
+            filter_orig,
+            attrs: Some(btreeset![Attribute::Spn, Attribute::ApiTokenSession]),
+            effective_access_check: false,
+        };
+
+        let entries = self.qs_read.search_ext(&srch).inspect_err(|err| {
+            admin_error!(?err, "Failed to search for api tokens");
+        })?;
+
+        let mut tokens = Vec::new();
+        for entry in entries {
+            let Some(account_spn) = entry.get_ava_single_proto_string(Attribute::Spn) else {
+                // We can see the tokens, but not who they belong to.
+                continue;
+            };
+
+            let Some(smap) = entry.get_ava_as_apitoken_map(Attribute::ApiTokenSession) else {
+                continue;
+            };
+
+            for (token_uuid, token) in smap.iter() {
+                let scope = token.scope.try_into().inspect_err(|err| {
+                    admin_error!(?err, "Invalid api_token {}", token_uuid);
+                })?;
+
+                tokens.push(ApiTokenSummary {
+                    account_spn: account_spn.clone(),
+                    token_uuid: *token_uuid,
+                    label: token.label.clone(),
+                    expiry: token.expiry,
+                    scope,
+                });
+            }
+        }
+
+        Ok(tokens)
+    }
 }
 
 #[cfg(test)]
@@ -407,7 +474,7 @@ mod tests {
     use compact_jwt::{dangernoverify::JwsDangerReleaseWithoutVerify, JwsVerifier};
     use kanidm_proto::internal::ApiToken;
 
-    use super::{DestroyApiTokenEvent, GenerateApiTokenEvent};
+    use super::{DestroyApiTokenEvent, GenerateApiTokenEvent, ListAllApiTokenEvent};
     use crate::idm::server::IdmServerTransaction;
     use crate::prelude::*;
 
@@ -499,6 +566,101 @@ mod tests {
         assert!(idms_prox_write.commit().is_ok());
     }
 
+    #[idm_test]
+    async fn test_idm_system_list_api_tokens(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let exp = Duration::from_secs(TEST_CURRENT_TIME + 6000);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let sa_a_uuid = Uuid::new_v4();
+        let sa_b_uuid = Uuid::new_v4();
+
+        for (name, uuid) in [("test_account_a", sa_a_uuid), ("test_account_b", sa_b_uuid)] {
+            let e = entry_init!(
+                (Attribute::Class, EntryClass::Object.to_value()),
+                (Attribute::Class, EntryClass::Account.to_value()),
+                (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+                (Attribute::Name, Value::new_iname(name)),
+                (Attribute::Uuid, Value::Uuid(uuid)),
+                (Attribute::Description, Value::new_utf8s(name)),
+                (Attribute::DisplayName, Value::new_utf8s(name)),
+                (Attribute::EntryManagedBy, Value::Refer(UUID_IDM_ADMINS))
+            );
+            idms_prox_write
+                .qs_write
+                .internal_create(vec![e])
+                .expect("Failed to create service account");
+        }
+
+        for (uuid, label, expiry) in [
+            (sa_a_uuid, "TokenA1", Some(exp)),
+            (sa_a_uuid, "TokenA2", None),
+            (sa_b_uuid, "TokenB1", None),
+        ] {
+            let gte = GenerateApiTokenEvent::new_internal(uuid, label, expiry);
+            idms_prox_write
+                .service_account_generate_api_token(&gte, ct)
+                .expect("failed to generate new api token");
+        }
+
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+
+        let idm_admin = idms_prox_read
+            .qs_read
+            .internal_search_uuid(UUID_IDM_ADMIN)
+            .expect("Failed to find idm_admin");
+        let ident = Identity::from_impersonate_entry_readonly(idm_admin);
+
+        let late = ListAllApiTokenEvent {
+            ident: ident.clone(),
+            account: None,
+        };
+        let mut tokens = idms_prox_read
+            .system_list_api_tokens(&late)
+            .expect("Failed to list api tokens");
+        tokens.sort_unstable_by(|a, b| a.label.cmp(&b.label));
+
+        let labels: Vec<_> = tokens.iter().map(|t| t.label.as_str()).collect();
+        assert_eq!(labels, vec!["TokenA1", "TokenA2", "TokenB1"]);
+        assert_eq!(tokens[0].account_spn, "test_account_a@example.com");
+        assert_eq!(
+            tokens[0].expiry,
+            Some(time::OffsetDateTime::UNIX_EPOCH + exp)
+        );
+        assert!(tokens[1].expiry.is_none());
+        assert_eq!(tokens[2].account_spn, "test_account_b@example.com");
+
+        // Filtered to a single account.
+        let late = ListAllApiTokenEvent {
+            ident: ident.clone(),
+            account: Some(sa_b_uuid),
+        };
+        let tokens = idms_prox_read
+            .system_list_api_tokens(&late)
+            .expect("Failed to list api tokens");
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].label, "TokenB1");
+
+        // Anonymous can't see any tokens.
+        let anon = idms_prox_read
+            .qs_read
+            .internal_search_uuid(UUID_ANONYMOUS)
+            .expect("Failed to find anonymous");
+        let late = ListAllApiTokenEvent {
+            ident: Identity::from_impersonate_entry_readonly(anon),
+            account: None,
+        };
+        let tokens = idms_prox_read
+            .system_list_api_tokens(&late)
+            .expect("Failed to list api tokens");
+        assert!(tokens.is_empty());
+    }
+
     #[idm_test]
     async fn test_idm_service_account_compact_api_token(
         idms: &IdmServer,
@@ -0,0 +1,115 @@
+use crate::idm::server::IdmServerProxyReadTransaction;
+use crate::prelude::*;
+use std::collections::BTreeMap;
+
+/// The classes reported by [`IdmServerProxyReadTransaction::entry_counts`].
+const COUNTED_CLASSES: &[EntryClass] = &[
+    EntryClass::Account,
+    EntryClass::Application,
+    EntryClass::Group,
+    EntryClass::OAuth2ResourceServer,
+    EntryClass::Person,
+    EntryClass::PosixAccount,
+    EntryClass::PosixGroup,
+    EntryClass::ServiceAccount,
+    EntryClass::SyncAccount,
+];
+
+impl IdmServerProxyReadTransaction<'_> {
+    /// Count the live entries of the common classes in the directory. Counts bypass
+    /// access controls, so only idm and system admins may request them.
+    pub fn entry_counts(
+        &mut self,
+        ident: &Identity,
+    ) -> Result<BTreeMap<String, u64>, OperationError> {
+        if !(ident.is_internal()
+            || ident.is_memberof(UUID_IDM_ADMINS)
+            || ident.is_memberof(UUID_SYSTEM_ADMINS))
+        {
+            security_access!(?ident, "denied access to entry counts");
+            return Err(OperationError::AccessDenied);
+        }
+
+        COUNTED_CLASSES
+            .iter()
+            .map(|class| {
+                let name: &'static str = (*class).into();
+                self.qs_read
+                    .count_entries_by_class(*class)
+                    .map(|count| (name.to_string(), count))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use kanidm_proto::constants::ENTRYCLASS_PERSON;
+
+    #[idm_test]
+    async fn test_idm_entry_counts(idms: &IdmServer, _idms_delayed: &mut IdmServerDelayed) {
+        let ct = duration_from_epoch_now();
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let before = idms_prox_read
+            .entry_counts(&Identity::from_internal())
+            .expect("Failed to count entries");
+        drop(idms_prox_read);
+
+        let usr_uuid = Uuid::new_v4();
+        let rm_uuid = Uuid::new_v4();
+
+        let person = |name: &str, uuid: Uuid| {
+            entry_init!(
+                (Attribute::Class, EntryClass::Object.to_value()),
+                (Attribute::Class, EntryClass::Account.to_value()),
+                (Attribute::Class, EntryClass::Person.to_value()),
+                (Attribute::Name, Value::new_iname(name)),
+                (Attribute::Uuid, Value::Uuid(uuid)),
+                (Attribute::DisplayName, Value::new_utf8s(name))
+            )
+        };
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let ce = CreateEvent::new_internal(vec![
+            person("testaccount", usr_uuid),
+            person("removed", rm_uuid),
+        ]);
+        assert!(idms_prox_write.qs_write.create(&ce).is_ok());
+        // Recycled entries are not counted.
+        assert!(idms_prox_write
+            .qs_write
+            .internal_delete_uuid(rm_uuid)
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let after = idms_prox_read
+            .entry_counts(&Identity::from_internal())
+            .expect("Failed to count entries");
+
+        assert_eq!(
+            after.get(ENTRYCLASS_PERSON),
+            before.get(ENTRYCLASS_PERSON).map(|c| c + 1).as_ref()
+        );
+
+        // Admins may read the counts, ordinary users may not.
+        let admin = idms_prox_read
+            .qs_read
+            .internal_search_uuid(UUID_IDM_ADMIN)
+            .map(Identity::from_impersonate_entry_readonly)
+            .expect("Failed to impersonate identity");
+        assert_eq!(idms_prox_read.entry_counts(&admin), Ok(after));
+
+        let user = idms_prox_read
+            .qs_read
+            .internal_search_uuid(usr_uuid)
+            .map(Identity::from_impersonate_entry_readonly)
+            .expect("Failed to impersonate identity");
+        assert_eq!(
+            idms_prox_read.entry_counts(&user),
+            Err(OperationError::AccessDenied)
+        );
+    }
+}
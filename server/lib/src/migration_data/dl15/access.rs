@@ -578,6 +578,7 @@ pub static IDM_ACP_OAUTH2_MANAGE: LazyLock<BuiltinAcp> = LazyLock::new(|| Builti
         Attribute::OAuth2JwtLegacyCryptoEnable,
         Attribute::OAuth2PreferShortUsername,
         Attribute::OAuth2RefreshTokenExpiry,
+        Attribute::OAuth2AccessTokenExpiry,
         Attribute::OAuth2AllowLocalhostRedirect,
         Attribute::OAuth2RsClaimMap,
         Attribute::Image,
@@ -599,6 +600,7 @@ pub static IDM_ACP_OAUTH2_MANAGE: LazyLock<BuiltinAcp> = LazyLock::new(|| Builti
         Attribute::OAuth2JwtLegacyCryptoEnable,
         Attribute::OAuth2PreferShortUsername,
         Attribute::OAuth2RefreshTokenExpiry,
+        Attribute::OAuth2AccessTokenExpiry,
         Attribute::OAuth2AllowLocalhostRedirect,
         Attribute::OAuth2RsClaimMap,
         Attribute::Image,
@@ -619,6 +621,7 @@ pub static IDM_ACP_OAUTH2_MANAGE: LazyLock<BuiltinAcp> = LazyLock::new(|| Builti
         Attribute::OAuth2JwtLegacyCryptoEnable,
         Attribute::OAuth2PreferShortUsername,
         Attribute::OAuth2RefreshTokenExpiry,
+        Attribute::OAuth2AccessTokenExpiry,
         Attribute::OAuth2AllowLocalhostRedirect,
         Attribute::OAuth2RsClaimMap,
         Attribute::Image,
@@ -641,6 +644,7 @@ pub static IDM_ACP_OAUTH2_MANAGE: LazyLock<BuiltinAcp> = LazyLock::new(|| Builti
         Attribute::OAuth2JwtLegacyCryptoEnable,
         Attribute::OAuth2PreferShortUsername,
         Attribute::OAuth2RefreshTokenExpiry,
+        Attribute::OAuth2AccessTokenExpiry,
         Attribute::OAuth2AllowLocalhostRedirect,
         Attribute::OAuth2RsClaimMap,
         Attribute::Image,
@@ -702,6 +706,10 @@ pub static IDM_ACP_DOMAIN_ADMIN_DL9: LazyLock<BuiltinAcp> = LazyLock::new(|| Bui
         Attribute::Uuid,
         Attribute::DomainAllowEasterEggs,
         Attribute::DomainAllowAccountRecovery,
+        Attribute::DomainRevokedSessionRetentionDays,
+        Attribute::DomainImageMaxWidth,
+        Attribute::DomainImageMaxHeight,
+        Attribute::DomainImageMaxBytes,
         Attribute::DomainDisplayName,
         Attribute::DomainName,
         Attribute::DomainLdapBasedn,
@@ -720,6 +728,10 @@ pub static IDM_ACP_DOMAIN_ADMIN_DL9: LazyLock<BuiltinAcp> = LazyLock::new(|| Bui
         Attribute::LdapMaxQueryableAttrs,
         Attribute::DomainAllowEasterEggs,
         Attribute::DomainAllowAccountRecovery,
+        Attribute::DomainRevokedSessionRetentionDays,
+        Attribute::DomainImageMaxWidth,
+        Attribute::DomainImageMaxHeight,
+        Attribute::DomainImageMaxBytes,
         Attribute::LdapAllowUnixPwBind,
         Attribute::KeyActionRevoke,
         Attribute::KeyActionRotate,
@@ -732,6 +744,10 @@ pub static IDM_ACP_DOMAIN_ADMIN_DL9: LazyLock<BuiltinAcp> = LazyLock::new(|| Bui
         Attribute::DomainSsid,
         Attribute::DomainAllowEasterEggs,
         Attribute::DomainAllowAccountRecovery,
+        Attribute::DomainRevokedSessionRetentionDays,
+        Attribute::DomainImageMaxWidth,
+        Attribute::DomainImageMaxHeight,
+        Attribute::DomainImageMaxBytes,
         Attribute::LdapAllowUnixPwBind,
         Attribute::KeyActionRevoke,
         Attribute::KeyActionRotate,
@@ -1830,6 +1846,44 @@ pub static IDM_ACP_HP_CLIENT_CERTIFICATE_MANAGER_DL7: LazyLock<BuiltinAcp> =
         ..Default::default()
     });
 
+pub static IDM_ACP_HP_IDP_CERTIFICATE_MANAGER: LazyLock<BuiltinAcp> =
+    LazyLock::new(|| BuiltinAcp {
+        classes: vec![
+            EntryClass::Object,
+            EntryClass::AccessControlProfile,
+            EntryClass::AccessControlCreate,
+            EntryClass::AccessControlDelete,
+            EntryClass::AccessControlModify,
+            EntryClass::AccessControlSearch,
+        ],
+        name: "idm_acp_hp_idp_certificate_manager",
+        uuid: UUID_IDM_ACP_HP_IDP_CERTIFICATE_MANAGER,
+        description: "Builtin IDM Control for allowing identity provider certificate management.",
+        receiver: BuiltinAcpReceiver::Group(vec![UUID_IDM_CLIENT_CERTIFICATE_ADMINS]),
+        target: BuiltinAcpTarget::Filter(ProtoFilter::And(vec![
+            ProtoFilter::Eq(
+                EntryClass::Class.to_string(),
+                EntryClass::IdentityProviderCertificate.to_string(),
+            ),
+            FILTER_ANDNOT_TOMBSTONE_OR_RECYCLED.clone(),
+        ])),
+        search_attrs: vec![
+            Attribute::Class,
+            Attribute::Uuid,
+            Attribute::IdpCertificate,
+            Attribute::Refers,
+        ],
+        modify_removed_attrs: vec![Attribute::IdpCertificate, Attribute::Refers],
+        modify_present_attrs: vec![Attribute::IdpCertificate, Attribute::Refers],
+        create_attrs: vec![
+            Attribute::Class,
+            Attribute::IdpCertificate,
+            Attribute::Refers,
+        ],
+        create_classes: vec![EntryClass::Object, EntryClass::IdentityProviderCertificate],
+        ..Default::default()
+    });
+
 pub static IDM_ACP_APPLICATION_MANAGE_DL8: LazyLock<BuiltinAcp> = LazyLock::new(|| BuiltinAcp {
     classes: vec![
         EntryClass::Object,
@@ -129,6 +129,13 @@ pub fn phase_1_schema_attrs() -> Vec<SchemaAttribute> {
         SCHEMA_ATTR_PASSWORD_CHANGED_TIME.clone(),
         SCHEMA_ATTR_OAUTH2_REFRESH_TOKEN_EXPIRY.clone(),
         SCHEMA_ATTR_DOMAIN_ALLOW_ACCOUNT_RECOVERY.clone(),
+        // DL15
+        SCHEMA_ATTR_DOMAIN_REVOKED_SESSION_RETENTION_DAYS.clone(),
+        SCHEMA_ATTR_OAUTH2_ACCESS_TOKEN_EXPIRY.clone(),
+        SCHEMA_ATTR_IDP_CERTIFICATE.clone(),
+        SCHEMA_ATTR_DOMAIN_IMAGE_MAX_WIDTH.clone(),
+        SCHEMA_ATTR_DOMAIN_IMAGE_MAX_HEIGHT.clone(),
+        SCHEMA_ATTR_DOMAIN_IMAGE_MAX_BYTES.clone(),
     ]
 }
 
@@ -178,6 +185,8 @@ pub fn phase_2_schema_classes() -> Vec<SchemaClass> {
         SCHEMA_CLASS_ASSERTION_NONCE.clone(),
         SCHEMA_CLASS_KEY_OBJECT_JWT_HS256_DL6.clone(),
         SCHEMA_CLASS_ASSERTION_NONCE.clone(),
+        // DL15
+        SCHEMA_CLASS_IDENTITY_PROVIDER_CERTIFICATE.clone(),
     ]
 }
 
@@ -318,6 +327,8 @@ pub fn phase_7_builtin_access_control_profiles() -> Vec<EntryInitNew> {
         IDM_ACP_OAUTH2_ACCOUNT_ENROL.clone().into(),
         // DL13
         IDM_ACP_OAUTH2_MANAGE_BASIC.clone().into(),
+        // DL15
+        IDM_ACP_HP_IDP_CERTIFICATE_MANAGER.clone().into(),
     ]
 }
 
@@ -570,6 +570,16 @@ pub static SCHEMA_ATTR_OAUTH2_REFRESH_TOKEN_EXPIRY: LazyLock<SchemaAttribute> =
         ..Default::default()
     });
 
+pub static SCHEMA_ATTR_OAUTH2_ACCESS_TOKEN_EXPIRY: LazyLock<SchemaAttribute> =
+    LazyLock::new(|| SchemaAttribute {
+        uuid: UUID_SCHEMA_ATTR_OAUTH2_ACCESS_TOKEN_EXPIRY,
+        name: Attribute::OAuth2AccessTokenExpiry,
+        description: "Defines the duration in seconds that an access token is valid for."
+            .to_string(),
+        syntax: SyntaxType::Uint32,
+        ..Default::default()
+    });
+
 pub static SCHEMA_ATTR_API_TOKEN_SESSION: LazyLock<SchemaAttribute> =
     LazyLock::new(|| SchemaAttribute {
         uuid: UUID_SCHEMA_ATTR_API_TOKEN_SESSION,
@@ -806,6 +816,43 @@ pub static SCHEMA_ATTR_DOMAIN_ALLOW_ACCOUNT_RECOVERY: LazyLock<SchemaAttribute>
         ..Default::default()
     });
 
+pub static SCHEMA_ATTR_DOMAIN_REVOKED_SESSION_RETENTION_DAYS: LazyLock<SchemaAttribute> =
+    LazyLock::new(|| SchemaAttribute {
+        uuid: UUID_SCHEMA_ATTR_DOMAIN_REVOKED_SESSION_RETENTION_DAYS,
+        name: Attribute::DomainRevokedSessionRetentionDays,
+        description: "The number of days that revoked sessions are retained before removal."
+            .to_string(),
+        syntax: SyntaxType::Uint32,
+        ..Default::default()
+    });
+
+pub static SCHEMA_ATTR_DOMAIN_IMAGE_MAX_WIDTH: LazyLock<SchemaAttribute> =
+    LazyLock::new(|| SchemaAttribute {
+        uuid: UUID_SCHEMA_ATTR_DOMAIN_IMAGE_MAX_WIDTH,
+        name: Attribute::DomainImageMaxWidth,
+        description: "The maximum width in pixels of images stored in this domain.".to_string(),
+        syntax: SyntaxType::Uint32,
+        ..Default::default()
+    });
+
+pub static SCHEMA_ATTR_DOMAIN_IMAGE_MAX_HEIGHT: LazyLock<SchemaAttribute> =
+    LazyLock::new(|| SchemaAttribute {
+        uuid: UUID_SCHEMA_ATTR_DOMAIN_IMAGE_MAX_HEIGHT,
+        name: Attribute::DomainImageMaxHeight,
+        description: "The maximum height in pixels of images stored in this domain.".to_string(),
+        syntax: SyntaxType::Uint32,
+        ..Default::default()
+    });
+
+pub static SCHEMA_ATTR_DOMAIN_IMAGE_MAX_BYTES: LazyLock<SchemaAttribute> =
+    LazyLock::new(|| SchemaAttribute {
+        uuid: UUID_SCHEMA_ATTR_DOMAIN_IMAGE_MAX_BYTES,
+        name: Attribute::DomainImageMaxBytes,
+        description: "The maximum file size in bytes of images stored in this domain.".to_string(),
+        syntax: SyntaxType::Uint32,
+        ..Default::default()
+    });
+
 pub static SCHEMA_ATTR_IMAGE: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     uuid: UUID_SCHEMA_ATTR_IMAGE,
     name: Attribute::Image,
@@ -854,6 +901,17 @@ pub static SCHEMA_ATTR_CERTIFICATE_DL7: LazyLock<SchemaAttribute> =
         ..Default::default()
     });
 
+pub static SCHEMA_ATTR_IDP_CERTIFICATE: LazyLock<SchemaAttribute> =
+    LazyLock::new(|| SchemaAttribute {
+        uuid: UUID_SCHEMA_ATTR_IDP_CERTIFICATE,
+        name: Attribute::IdpCertificate,
+        description: "An x509 Certificate of an identity provider trusted by an account"
+            .to_string(),
+        multivalue: false,
+        syntax: SyntaxType::Certificate,
+        ..Default::default()
+    });
+
 pub static SCHEMA_ATTR_APPLICATION_PASSWORD_DL8: LazyLock<SchemaAttribute> =
     LazyLock::new(|| SchemaAttribute {
         uuid: UUID_SCHEMA_ATTR_APPLICATION_PASSWORD,
@@ -1237,6 +1295,10 @@ pub static SCHEMA_CLASS_DOMAIN_INFO_DL10: LazyLock<SchemaClass> = LazyLock::new(
         Attribute::DomainDevelopmentTaint,
         Attribute::DomainAllowEasterEggs,
         Attribute::DomainAllowAccountRecovery,
+        Attribute::DomainRevokedSessionRetentionDays,
+        Attribute::DomainImageMaxWidth,
+        Attribute::DomainImageMaxHeight,
+        Attribute::DomainImageMaxBytes,
         Attribute::DomainDisplayName,
     ],
     systemmust: vec![
@@ -1298,6 +1360,7 @@ pub static SCHEMA_CLASS_OAUTH2_RS_DL9: LazyLock<SchemaClass> = LazyLock::new(||
         Attribute::OAuth2JwtLegacyCryptoEnable,
         Attribute::OAuth2PreferShortUsername,
         Attribute::OAuth2RefreshTokenExpiry,
+        Attribute::OAuth2AccessTokenExpiry,
         Attribute::Image,
         Attribute::OAuth2RsClaimMap,
         Attribute::OAuth2Session,
@@ -1471,6 +1534,18 @@ pub static SCHEMA_CLASS_CLIENT_CERTIFICATE_DL7: LazyLock<SchemaClass> =
         ..Default::default()
     });
 
+pub static SCHEMA_CLASS_IDENTITY_PROVIDER_CERTIFICATE: LazyLock<SchemaClass> =
+    LazyLock::new(|| SchemaClass {
+        uuid: UUID_SCHEMA_CLASS_IDENTITY_PROVIDER_CERTIFICATE,
+        name: EntryClass::IdentityProviderCertificate.into(),
+        description:
+            "An identity provider certificate. This is never used for client authentication"
+                .to_string(),
+        systemmay: vec![],
+        systemmust: vec![Attribute::IdpCertificate, Attribute::Refers],
+        ..Default::default()
+    });
+
 pub static SCHEMA_CLASS_APPLICATION: LazyLock<SchemaClass> = LazyLock::new(|| SchemaClass {
     uuid: UUID_SCHEMA_CLASS_APPLICATION,
     name: EntryClass::Application.into(),
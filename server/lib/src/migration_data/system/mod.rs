@@ -8,6 +8,7 @@ pub fn attributes() -> Vec<SchemaAttribute> {
         SCHEMA_ATTR_CLASS.clone(),
         SCHEMA_ATTR_UUID.clone(),
         SCHEMA_ATTR_SOURCE_UUID.clone(),
+        SCHEMA_ATTR_CREATED_AT.clone(),
         SCHEMA_ATTR_CREATED_AT_CID.clone(),
         SCHEMA_ATTR_LAST_MODIFIED_CID.clone(),
         SCHEMA_ATTR_NAME.clone(),
@@ -36,6 +37,7 @@ pub fn attributes() -> Vec<SchemaAttribute> {
         SCHEMA_ATTR_ACP_RECEIVER_GROUP.clone(),
         SCHEMA_ATTR_ACP_TARGET_SCOPE.clone(),
         SCHEMA_ATTR_ACP_SEARCH_ATTR.clone(),
+        SCHEMA_ATTR_ACP_DENY_ATTRIBUTE.clone(),
         SCHEMA_ATTR_ACP_CREATE_CLASS.clone(),
         SCHEMA_ATTR_ACP_CREATE_ATTR.clone(),
         SCHEMA_ATTR_ACP_MODIFY_REMOVED_ATTR.clone(),
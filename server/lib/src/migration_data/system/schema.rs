@@ -43,6 +43,20 @@ pub static SCHEMA_ATTR_SOURCE_UUID: LazyLock<SchemaAttribute> = LazyLock::new(||
     indexed: true,
     syntax: SyntaxType::Uuid,
 });
+pub static SCHEMA_ATTR_CREATED_AT: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
+    name: Attribute::CreatedAt,
+    uuid: UUID_SCHEMA_ATTR_CREATED_AT,
+    description: String::from(
+        "The original creation time of an entry that was imported from another system",
+    ),
+    multivalue: false,
+    unique: false,
+    phantom: false,
+    sync_allowed: false,
+    replicated: Replicated::True,
+    indexed: false,
+    syntax: SyntaxType::DateTime,
+});
 pub static SCHEMA_ATTR_CREATED_AT_CID: LazyLock<SchemaAttribute> =
     LazyLock::new(|| SchemaAttribute {
         name: Attribute::CreatedAtCid,
@@ -439,6 +453,22 @@ pub static SCHEMA_ATTR_ACP_SEARCH_ATTR: LazyLock<SchemaAttribute> =
         indexed: true,
         syntax: SyntaxType::Utf8StringInsensitive,
     });
+pub static SCHEMA_ATTR_ACP_DENY_ATTRIBUTE: LazyLock<SchemaAttribute> = LazyLock::new(|| {
+    SchemaAttribute {
+        name: Attribute::AcpDenyAttribute,
+        uuid: UUID_SCHEMA_ATTR_ACP_DENY_ATTRIBUTE,
+        description: String::from(
+            "The attributes that may never be viewed or searched by the receiver on targetscope, even if granted by another ACP.",
+        ),
+        multivalue: true,
+        unique: false,
+        phantom: false,
+        sync_allowed: false,
+        replicated: Replicated::True,
+        indexed: true,
+        syntax: SyntaxType::Utf8StringInsensitive,
+    }
+});
 pub static SCHEMA_ATTR_ACP_CREATE_CLASS: LazyLock<SchemaAttribute> =
     LazyLock::new(|| SchemaAttribute {
         name: Attribute::AcpCreateClass,
@@ -1045,6 +1075,7 @@ pub static SCHEMA_CLASS_OBJECT: LazyLock<SchemaClass> = LazyLock::new(|| SchemaC
         Attribute::EntryManagedBy,
         Attribute::MemberOf,
         Attribute::DirectMemberOf,
+        Attribute::CreatedAt,
     ],
     systemmust: vec![
         Attribute::Class,
@@ -1121,6 +1152,7 @@ pub static SCHEMA_CLASS_ACCESS_CONTROL_SEARCH: LazyLock<SchemaClass> =
         uuid: UUID_SCHEMA_CLASS_ACCESS_CONTROL_SEARCH,
         description: String::from("System Access Control Search Class"),
         systemmust: vec![Attribute::AcpSearchAttr],
+        systemmay: vec![Attribute::AcpDenyAttribute],
         ..Default::default()
     });
 pub static SCHEMA_CLASS_ACCESS_CONTROL_DELETE: LazyLock<SchemaClass> =
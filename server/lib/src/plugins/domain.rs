@@ -9,6 +9,7 @@ use std::sync::Arc;
 
 use regex::Regex;
 use tracing::trace;
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::event::{CreateEvent, ModifyEvent};
 use crate::plugins::Plugin;
@@ -20,6 +21,9 @@ pub static DOMAIN_LDAP_BASEDN_RE: LazyLock<Regex> = LazyLock::new(|| {
         .expect("Invalid domain ldap basedn regex")
 });
 
+/// The maximum length of the domain display name in grapheme clusters.
+const DOMAIN_DISPLAY_NAME_MAX_LEN: usize = 64;
+
 pub struct Domain {}
 
 impl Plugin for Domain {
@@ -80,6 +84,25 @@ impl Domain {
                     }
                 }
 
+                // Validate the display name, but only when it's being changed so that an
+                // existing value can't prevent other changes to the domain.
+                if let Some(display_name) = e.get_ava_single_utf8(Attribute::DomainDisplayName) {
+                    if display_name != qs.get_domain_display_name() {
+                        Self::validate_display_name(display_name).map_err(|reason| {
+                            error!(
+                                "Invalid {} {:?}, {}",
+                                Attribute::DomainDisplayName,
+                                display_name,
+                                reason
+                            );
+                            OperationError::SchemaViolation(SchemaError::InvalidDisplayName {
+                                value: display_name.to_string(),
+                                reason,
+                            })
+                        })?;
+                    }
+                }
+
                 // We always set this, because the DB uuid is authoritative.
                 let u = Value::Uuid(qs.get_domain_uuid());
                 e.set_ava(&Attribute::DomainUuid, once(u));
@@ -127,6 +150,27 @@ impl Domain {
             }
         })
     }
+
+    /// Display names are shown in the web ui, so they must be of a reasonable length and
+    /// must not contain anything that would disrupt rendering.
+    fn validate_display_name(display_name: &str) -> Result<(), String> {
+        let len = display_name.graphemes(true).count();
+        if len == 0 {
+            return Err("must not be empty".to_string());
+        }
+        if len > DOMAIN_DISPLAY_NAME_MAX_LEN {
+            return Err(format!(
+                "must be at most {DOMAIN_DISPLAY_NAME_MAX_LEN} characters, found {len}"
+            ));
+        }
+        if display_name.chars().any(char::is_control) {
+            return Err("must not contain control characters".to_string());
+        }
+        if display_name.chars().all(char::is_whitespace) {
+            return Err("must not be only whitespace".to_string());
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -145,4 +189,50 @@ mod tests {
 
         assert!(e_dom.attribute_equality(Attribute::DomainUuid, &PartialValue::Uuid(u_dom)));
     }
+
+    #[qs_test]
+    async fn test_domain_display_name_validation(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let mut set_display_name = |name: &str| {
+            server_txn.internal_modify_uuid(
+                UUID_DOMAIN_INFO,
+                &ModifyList::new_purge_and_set(
+                    Attribute::DomainDisplayName,
+                    Value::new_utf8s(name),
+                ),
+            )
+        };
+
+        // Lengths are counted in graphemes, not bytes or chars.
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        assert!(set_display_name(&family.repeat(64)).is_ok());
+        assert!(set_display_name("Example Corp – Identity").is_ok());
+
+        for (name, reason) in [
+            ("", "must not be empty"),
+            ("   ", "must not be only whitespace"),
+            ("Bad\u{0007}Name", "must not contain control characters"),
+            ("Bad\u{0085}Name", "must not contain control characters"),
+        ] {
+            assert_eq!(
+                set_display_name(name),
+                Err(OperationError::SchemaViolation(
+                    SchemaError::InvalidDisplayName {
+                        value: name.to_string(),
+                        reason: reason.to_string(),
+                    }
+                ))
+            );
+        }
+
+        let too_long = family.repeat(65);
+        assert!(matches!(
+            set_display_name(&too_long),
+            Err(OperationError::SchemaViolation(SchemaError::InvalidDisplayName { value, .. }))
+                if value == too_long
+        ));
+
+        assert!(server_txn.commit().is_ok());
+    }
 }
@@ -0,0 +1,223 @@
+// Rejects images that are larger than the domain allows.
+//
+// The limits are read from the domain entry, and can only lower the compiled maximums
+// as larger images are refused by the image value set. Only images that are added by
+// the operation are checked, so lowering a limit doesn't prevent unrelated changes to
+// entries that already hold a larger image.
+
+use std::sync::Arc;
+
+use crate::event::{CreateEvent, ModifyEvent};
+use crate::plugins::Plugin;
+use crate::prelude::*;
+use crate::valueset::image::{check_image_limits, ImageLimits};
+use kanidm_proto::internal::ImageValue;
+
+pub struct ImageLimit {}
+
+impl Plugin for ImageLimit {
+    fn id() -> &'static str {
+        "plugin_image_limit"
+    }
+
+    #[instrument(level = "debug", name = "image_limit_pre_create_transform", skip_all)]
+    fn pre_create_transform(
+        qs: &mut QueryServerWriteTransaction,
+        cand: &mut Vec<Entry<EntryInvalid, EntryNew>>,
+        _ce: &CreateEvent,
+    ) -> Result<(), OperationError> {
+        let images: Vec<&ImageValue> = cand
+            .iter()
+            .filter_map(|entry| entry.get_ava_set(Attribute::Image))
+            .filter_map(|vs| vs.as_imageset())
+            .flatten()
+            .collect();
+
+        enforce_image_limits(qs, &images)
+    }
+
+    #[instrument(level = "debug", name = "image_limit_pre_modify", skip_all)]
+    fn pre_modify(
+        qs: &mut QueryServerWriteTransaction,
+        _pre_cand: &[Arc<EntrySealedCommitted>],
+        _cand: &mut Vec<Entry<EntryInvalid, EntryCommitted>>,
+        me: &ModifyEvent,
+    ) -> Result<(), OperationError> {
+        let images = modlist_images(&me.modlist);
+        enforce_image_limits(qs, &images)
+    }
+
+    #[instrument(level = "debug", name = "image_limit_pre_batch_modify", skip_all)]
+    fn pre_batch_modify(
+        qs: &mut QueryServerWriteTransaction,
+        _pre_cand: &[Arc<EntrySealedCommitted>],
+        _cand: &mut Vec<Entry<EntryInvalid, EntryCommitted>>,
+        me: &BatchModifyEvent,
+    ) -> Result<(), OperationError> {
+        let images: Vec<&ImageValue> = me.modset.values().flat_map(modlist_images).collect();
+        enforce_image_limits(qs, &images)
+    }
+}
+
+/// The images that a modification adds. These are taken from the modification rather
+/// than the entry, as the entry may already hold images that predate the limits.
+fn modlist_images(modlist: &ModifyList<ModifyValid>) -> Vec<&ImageValue> {
+    let mut images = Vec::new();
+    for modify in modlist {
+        match modify {
+            Modify::Present(Attribute::Image, Value::Image(image)) => images.push(image),
+            Modify::Set(Attribute::Image, vs) => {
+                images.extend(vs.as_imageset().into_iter().flatten())
+            }
+            _ => {}
+        }
+    }
+    images
+}
+
+fn enforce_image_limits(
+    qs: &mut QueryServerWriteTransaction,
+    images: &[&ImageValue],
+) -> Result<(), OperationError> {
+    if images.is_empty() {
+        return Ok(());
+    }
+
+    let limits = domain_image_limits(qs)?;
+
+    images.iter().try_for_each(|image| {
+        check_image_limits(image, &limits).map_err(|err| {
+            error!(filename = %image.filename, ?err, ?limits, "Image exceeds the domain image limits");
+            OperationError::SchemaViolation(err)
+        })
+    })
+}
+
+fn domain_image_limits(
+    qs: &mut QueryServerWriteTransaction,
+) -> Result<ImageLimits, OperationError> {
+    let max = ImageLimits::default();
+
+    let domain_info = match qs.internal_search_uuid(UUID_DOMAIN_INFO) {
+        Ok(domain_info) => domain_info,
+        // The domain may not exist yet while the server is being initialised.
+        Err(OperationError::NoMatchingEntries) => return Ok(max),
+        Err(err) => return Err(err),
+    };
+
+    let limit = |attr: Attribute, max: u64| {
+        domain_info
+            .get_ava_single_uint32(attr)
+            .map(|value| u64::from(value).min(max))
+            .unwrap_or(max)
+    };
+
+    Ok(ImageLimits {
+        width: limit(Attribute::DomainImageMaxWidth, max.width.into()) as u32,
+        height: limit(Attribute::DomainImageMaxHeight, max.height.into()) as u32,
+        bytes: limit(Attribute::DomainImageMaxBytes, max.bytes),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use kanidm_proto::internal::{ImageType, ImageValue};
+
+    fn ok_png() -> ImageValue {
+        let filename = format!(
+            "{}/src/valueset/image/test_images/ok.png",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        ImageValue {
+            filename: "ok.png".to_string(),
+            filetype: ImageType::Png,
+            contents: std::fs::read(filename).expect("Failed to read test image"),
+        }
+    }
+
+    fn oauth2_client(uuid: Uuid, name: &str) -> EntryInitNew {
+        entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (
+                Attribute::Class,
+                EntryClass::OAuth2ResourceServer.to_value()
+            ),
+            (
+                Attribute::Class,
+                EntryClass::OAuth2ResourceServerBasic.to_value()
+            ),
+            (Attribute::Uuid, Value::Uuid(uuid)),
+            (Attribute::Name, Value::new_iname(name)),
+            (Attribute::DisplayName, Value::new_utf8s(name)),
+            (
+                Attribute::OAuth2RsOriginLanding,
+                Value::new_url_s("https://demo.example.com").unwrap()
+            )
+        )
+    }
+
+    fn set_domain_limit(server_txn: &mut QueryServerWriteTransaction, attr: Attribute, value: u32) {
+        server_txn
+            .internal_modify_uuid(
+                UUID_DOMAIN_INFO,
+                &ModifyList::new_purge_and_set(attr, Value::Uint32(value)),
+            )
+            .expect("Unable to set domain image limit");
+    }
+
+    #[qs_test]
+    async fn test_image_limit_create(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.expect("txn");
+
+        // Within the default limits.
+        let mut e = oauth2_client(Uuid::new_v4(), "image_ok");
+        e.add_ava(Attribute::Image, Value::Image(ok_png()));
+        assert!(server_txn.internal_create(vec![e]).is_ok());
+
+        // Larger than the domain allows.
+        set_domain_limit(&mut server_txn, Attribute::DomainImageMaxBytes, 16);
+
+        let mut e = oauth2_client(Uuid::new_v4(), "image_too_large");
+        e.add_ava(Attribute::Image, Value::Image(ok_png()));
+        assert!(matches!(
+            server_txn.internal_create(vec![e]),
+            Err(OperationError::SchemaViolation(SchemaError::ImageTooLarge { bytes, .. }))
+                if bytes > 16
+        ));
+
+        assert!(server_txn.commit().is_ok());
+    }
+
+    #[qs_test]
+    async fn test_image_limit_modify(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.expect("txn");
+
+        let client_uuid = Uuid::new_v4();
+        let mut e = oauth2_client(client_uuid, "image_client");
+        e.add_ava(Attribute::Image, Value::Image(ok_png()));
+        assert!(server_txn.internal_create(vec![e]).is_ok());
+
+        set_domain_limit(&mut server_txn, Attribute::DomainImageMaxWidth, 1);
+
+        // Adding an image wider than the domain allows is rejected.
+        let modlist = ModifyList::new_purge_and_set(Attribute::Image, Value::Image(ok_png()));
+        assert!(matches!(
+            server_txn.internal_modify_uuid(client_uuid, &modlist),
+            Err(OperationError::SchemaViolation(SchemaError::ImageTooLarge { width, .. }))
+                if width > 1
+        ));
+
+        // The existing image doesn't prevent other changes to the entry.
+        let modlist = ModifyList::new_purge_and_set(
+            Attribute::DisplayName,
+            Value::new_utf8s("unrelated change"),
+        );
+        assert!(server_txn
+            .internal_modify_uuid(client_uuid, &modlist)
+            .is_ok());
+
+        assert!(server_txn.commit().is_ok());
+    }
+}
@@ -0,0 +1,416 @@
+// Ensures that an entry with mail addresses has exactly one primary address.
+//
+// The mail value set can only hold a single primary, so by the time an entry reaches
+// us any conflict has already been resolved by the order values were inserted. For
+// modifications we inspect the modification list instead, which still carries what the
+// client asked for, and reject it if several addresses were marked primary. This matters
+// most for scim sync, where the sync source may send several primaries or none. Scim
+// creates are rejected when the mail values are resolved, so on create only the value
+// set is available and we check that its primary is one of the addresses.
+
+use std::sync::Arc;
+
+use crate::event::{CreateEvent, ModifyEvent};
+use crate::plugins::Plugin;
+use crate::prelude::*;
+use crate::valueset::ValueSetEmailAddress;
+
+pub struct MailPrimary {}
+
+impl Plugin for MailPrimary {
+    fn id() -> &'static str {
+        "plugin_mail_primary"
+    }
+
+    #[instrument(level = "debug", name = "mail_primary_pre_create_transform", skip_all)]
+    fn pre_create_transform(
+        _qs: &mut QueryServerWriteTransaction,
+        cand: &mut Vec<Entry<EntryInvalid, EntryNew>>,
+        _ce: &CreateEvent,
+    ) -> Result<(), OperationError> {
+        cand.iter_mut().try_for_each(|entry| {
+            let primary = entry
+                .get_ava_mail_primary(Attribute::Mail)
+                .map(str::to_string);
+            normalise_mail_primary(entry, primary.as_deref().into_iter().collect(), false)
+        })
+    }
+
+    #[instrument(level = "debug", name = "mail_primary_pre_modify", skip_all)]
+    fn pre_modify(
+        _qs: &mut QueryServerWriteTransaction,
+        pre_cand: &[Arc<EntrySealedCommitted>],
+        cand: &mut Vec<Entry<EntryInvalid, EntryCommitted>>,
+        me: &ModifyEvent,
+    ) -> Result<(), OperationError> {
+        pre_cand
+            .iter()
+            .zip(cand.iter_mut())
+            .try_for_each(|(pre_entry, post_entry)| {
+                enforce_mail_primary(pre_entry, post_entry, &me.modlist)
+            })
+    }
+
+    #[instrument(level = "debug", name = "mail_primary_pre_batch_modify", skip_all)]
+    fn pre_batch_modify(
+        _qs: &mut QueryServerWriteTransaction,
+        pre_cand: &[Arc<EntrySealedCommitted>],
+        cand: &mut Vec<Entry<EntryInvalid, EntryCommitted>>,
+        me: &BatchModifyEvent,
+    ) -> Result<(), OperationError> {
+        pre_cand
+            .iter()
+            .zip(cand.iter_mut())
+            .try_for_each(
+                |(pre_entry, post_entry)| match me.modset.get(&pre_entry.get_uuid()) {
+                    Some(modlist) => enforce_mail_primary(pre_entry, post_entry, modlist),
+                    None => Ok(()),
+                },
+            )
+    }
+}
+
+fn enforce_mail_primary(
+    pre_entry: &EntrySealedCommitted,
+    post_entry: &mut EntryInvalidCommitted,
+    modlist: &ModifyList<ModifyValid>,
+) -> Result<(), OperationError> {
+    // If the entry had no mail, or the modification replaces it, then the primary must
+    // come from this modification. Otherwise an existing primary is retained.
+    let mut replaced = !pre_entry.attribute_pres(Attribute::Mail);
+    let mut requested = Vec::with_capacity(1);
+
+    for modify in modlist {
+        match modify {
+            Modify::Purged(Attribute::Mail) => replaced = true,
+            Modify::Set(Attribute::Mail, vs) => {
+                if vs.is_empty() {
+                    error!("Mail attribute is set but contains no addresses");
+                    return Err(OperationError::PL0002MailNoAddresses);
+                }
+                replaced = true;
+                requested.extend(vs.to_email_address_primary_str());
+            }
+            Modify::Present(Attribute::Mail, Value::EmailAddress(addr, true)) => {
+                requested.push(addr.as_str())
+            }
+            _ => {}
+        }
+    }
+
+    normalise_mail_primary(post_entry, requested, replaced)
+}
+
+/// Make exactly one of the entry's mail addresses primary. `requested` are the addresses
+/// that were marked primary, and `replaced` is whether the existing primary may be kept
+/// when none were.
+fn normalise_mail_primary<STATE: Clone>(
+    entry: &mut Entry<EntryInvalid, STATE>,
+    mut requested: Vec<&str>,
+    replaced: bool,
+) -> Result<(), OperationError> {
+    let Some(addresses) = entry
+        .get_ava_iter_mail(Attribute::Mail)
+        .map(|iter| iter.map(str::to_string).collect::<Vec<_>>())
+    else {
+        return Ok(());
+    };
+
+    if addresses.is_empty() {
+        error!("Mail attribute is present but contains no addresses");
+        return Err(OperationError::PL0002MailNoAddresses);
+    }
+
+    // A primary that was removed later in the same operation can't be used.
+    requested.retain(|addr| addresses.iter().any(|a| a == addr));
+    requested.sort_unstable();
+    requested.dedup();
+
+    let current = entry.get_ava_mail_primary(Attribute::Mail);
+
+    // Choose the first address alphabetically so that the result doesn't depend on the
+    // order the addresses were sent in.
+    let primary = match requested.as_slice() {
+        [] if !replaced && current.is_some_and(|c| addresses.iter().any(|a| a == c)) => {
+            return Ok(());
+        }
+        [] => {
+            let primary = addresses.iter().min().map(String::as_str);
+            if addresses.len() > 1 {
+                warn!(
+                    ?primary,
+                    "No mail address was marked as primary, using the first address alphabetically"
+                );
+            }
+            primary
+        }
+        [primary] => Some(*primary),
+        [_, ..] => {
+            error!(?requested, "Multiple mail addresses were marked as primary");
+            return Err(OperationError::PL0003MailMultiplePrimary);
+        }
+    }
+    .map(str::to_string)
+    .ok_or(OperationError::InvalidValueState)?;
+
+    if current != Some(primary.as_str()) {
+        let vs = ValueSetEmailAddress::from_iter(addresses.into_iter().map(|addr| {
+            let is_primary = addr == primary;
+            (addr, is_primary)
+        }))
+        .ok_or(OperationError::InvalidValueState)?;
+        entry.set_ava_set(&Attribute::Mail, vs);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::server::scim::ScimCreateEvent;
+    use crate::valueset::ValueSetEmailAddress;
+    use kanidm_proto::scim_v1::client::ScimEntryPostGeneric;
+    use serde_json::json;
+
+    fn mail_primary(server_txn: &mut QueryServerWriteTransaction, uuid: Uuid) -> Option<String> {
+        server_txn
+            .internal_search_uuid(uuid)
+            .expect("Unable to access user")
+            .get_ava_mail_primary(Attribute::Mail)
+            .map(str::to_string)
+    }
+
+    fn person(uuid: Uuid, name: &str) -> EntryInitNew {
+        entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname(name)),
+            (Attribute::Uuid, Value::Uuid(uuid)),
+            (Attribute::DisplayName, Value::new_utf8s(name))
+        )
+    }
+
+    #[qs_test]
+    async fn test_mail_primary_create(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.expect("txn");
+
+        // A single address is made primary.
+        let single_uuid = Uuid::new_v4();
+        let mut e = person(single_uuid, "single");
+        e.add_ava(
+            Attribute::Mail,
+            Value::EmailAddress("single@example.com".to_string(), false),
+        );
+        server_txn
+            .internal_create(vec![e])
+            .expect("Unable to create user");
+        assert_eq!(
+            mail_primary(&mut server_txn, single_uuid).as_deref(),
+            Some("single@example.com")
+        );
+
+        // An explicit primary is kept.
+        let explicit_uuid = Uuid::new_v4();
+        let mut e = person(explicit_uuid, "explicit");
+        e.add_ava(
+            Attribute::Mail,
+            Value::EmailAddress("alice@example.com".to_string(), false),
+        );
+        e.add_ava(
+            Attribute::Mail,
+            Value::EmailAddress("zed@example.com".to_string(), true),
+        );
+        server_txn
+            .internal_create(vec![e])
+            .expect("Unable to create user");
+        assert_eq!(
+            mail_primary(&mut server_txn, explicit_uuid).as_deref(),
+            Some("zed@example.com")
+        );
+
+        // Multiple primaries are rejected.
+        let entry = ScimEntryPostGeneric {
+            attrs: [
+                (Attribute::Name, json!("multiple")),
+                (Attribute::DisplayName, json!("multiple")),
+                (
+                    Attribute::Mail,
+                    json!([
+                        { "value": "yan@example.com", "primary": true },
+                        { "value": "bob@example.com", "primary": true }
+                    ]),
+                ),
+            ]
+            .into(),
+        };
+        assert_eq!(
+            ScimCreateEvent::try_from(
+                Identity::from_internal(),
+                &[EntryClass::Account, EntryClass::Person],
+                entry,
+                &mut server_txn,
+            )
+            .map(|_| ()),
+            Err(OperationError::PL0003MailMultiplePrimary)
+        );
+
+        // An empty set of addresses is rejected.
+        let mut empty_vs = ValueSetEmailAddress::new("zed@example.com".to_string());
+        empty_vs.clear();
+        let mut e = person(Uuid::new_v4(), "empty");
+        e.set_ava_set(&Attribute::Mail, empty_vs);
+        assert_eq!(
+            server_txn.internal_create(vec![e]),
+            Err(OperationError::PL0002MailNoAddresses)
+        );
+
+        assert!(server_txn.commit().is_ok());
+    }
+
+    #[qs_test]
+    async fn test_mail_primary_modify(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.expect("txn");
+
+        let user_uuid = Uuid::new_v4();
+        server_txn
+            .internal_create(vec![person(user_uuid, "testperson")])
+            .expect("Unable to create user");
+
+        // A single address is made primary.
+        let modlist = modlist!([
+            m_purge(Attribute::Mail),
+            m_pres(
+                Attribute::Mail,
+                &Value::EmailAddress("single@example.com".to_string(), false)
+            )
+        ]);
+        server_txn
+            .internal_modify_uuid(user_uuid, &modlist)
+            .expect("Unable to modify user");
+        assert_eq!(
+            mail_primary(&mut server_txn, user_uuid).as_deref(),
+            Some("single@example.com")
+        );
+
+        // Several addresses with no primary use the first alphabetically, not the
+        // first sent.
+        let modlist = modlist!([
+            m_purge(Attribute::Mail),
+            m_pres(
+                Attribute::Mail,
+                &Value::EmailAddress("zed@example.com".to_string(), false)
+            ),
+            m_pres(
+                Attribute::Mail,
+                &Value::EmailAddress("alice@example.com".to_string(), false)
+            )
+        ]);
+        server_txn
+            .internal_modify_uuid(user_uuid, &modlist)
+            .expect("Unable to modify user");
+        assert_eq!(
+            mail_primary(&mut server_txn, user_uuid).as_deref(),
+            Some("alice@example.com")
+        );
+
+        // An explicit primary is kept.
+        let modlist = modlist!([
+            m_purge(Attribute::Mail),
+            m_pres(
+                Attribute::Mail,
+                &Value::EmailAddress("zed@example.com".to_string(), true)
+            ),
+            m_pres(
+                Attribute::Mail,
+                &Value::EmailAddress("alice@example.com".to_string(), false)
+            )
+        ]);
+        server_txn
+            .internal_modify_uuid(user_uuid, &modlist)
+            .expect("Unable to modify user");
+        assert_eq!(
+            mail_primary(&mut server_txn, user_uuid).as_deref(),
+            Some("zed@example.com")
+        );
+
+        // Adding another address doesn't change the existing primary.
+        let modlist = modlist!([m_pres(
+            Attribute::Mail,
+            &Value::EmailAddress("bob@example.com".to_string(), false)
+        )]);
+        server_txn
+            .internal_modify_uuid(user_uuid, &modlist)
+            .expect("Unable to modify user");
+        assert_eq!(
+            mail_primary(&mut server_txn, user_uuid).as_deref(),
+            Some("zed@example.com")
+        );
+
+        // Multiple primaries are rejected.
+        let modlist = modlist!([
+            m_purge(Attribute::Mail),
+            m_pres(
+                Attribute::Mail,
+                &Value::EmailAddress("bob@example.com".to_string(), false)
+            ),
+            m_pres(
+                Attribute::Mail,
+                &Value::EmailAddress("carol@example.com".to_string(), true)
+            ),
+            m_pres(
+                Attribute::Mail,
+                &Value::EmailAddress("zed@example.com".to_string(), true)
+            )
+        ]);
+        assert_eq!(
+            server_txn.internal_modify_uuid(user_uuid, &modlist),
+            Err(OperationError::PL0003MailMultiplePrimary)
+        );
+        assert_eq!(
+            mail_primary(&mut server_txn, user_uuid).as_deref(),
+            Some("zed@example.com")
+        );
+
+        // A primary that is removed in the same modification isn't used.
+        let modlist = modlist!([
+            m_pres(
+                Attribute::Mail,
+                &Value::EmailAddress("dan@example.com".to_string(), true)
+            ),
+            m_remove(
+                Attribute::Mail,
+                &PartialValue::EmailAddress("dan@example.com".to_string())
+            ),
+            m_remove(
+                Attribute::Mail,
+                &PartialValue::EmailAddress("zed@example.com".to_string())
+            )
+        ]);
+        server_txn
+            .internal_modify_uuid(user_uuid, &modlist)
+            .expect("Unable to modify user");
+        assert_eq!(
+            mail_primary(&mut server_txn, user_uuid).as_deref(),
+            Some("alice@example.com")
+        );
+
+        // An empty set of addresses is rejected.
+        let mut empty_vs = ValueSetEmailAddress::new("zed@example.com".to_string());
+        empty_vs.clear();
+        let modlist = ModifyList::new_list(vec![Modify::Set(Attribute::Mail, empty_vs)]);
+        assert_eq!(
+            server_txn.internal_modify_uuid(user_uuid, &modlist),
+            Err(OperationError::PL0002MailNoAddresses)
+        );
+
+        // Nothing was changed by the rejected modification.
+        assert_eq!(
+            mail_primary(&mut server_txn, user_uuid).as_deref(),
+            Some("alice@example.com")
+        );
+
+        assert!(server_txn.commit().is_ok());
+    }
+}
@@ -18,7 +18,9 @@ pub(crate) mod dyngroup;
 mod eckeygen;
 pub(crate) mod gidnumber;
 pub(crate) mod hmac_name_unique;
+mod imagelimits;
 mod keyobject;
+mod mailprimary;
 mod memberof;
 mod namehistory;
 mod oauth2;
@@ -264,6 +266,8 @@ impl Plugins {
         domain::Domain::pre_create_transform(qs, cand, ce)?;
         spn::Spn::pre_create_transform(qs, cand, ce)?;
         default_values::DefaultValues::pre_create_transform(qs, cand, ce)?;
+        mailprimary::MailPrimary::pre_create_transform(qs, cand, ce)?;
+        imagelimits::ImageLimit::pre_create_transform(qs, cand, ce)?;
         namehistory::NameHistory::pre_create_transform(qs, cand, ce)?;
         hmac_name_unique::HmacNameUnique::pre_create_transform(qs, cand, ce)?;
         // Should always be last
@@ -318,6 +322,8 @@ impl Plugins {
         spn::Spn::pre_modify(qs, pre_cand, cand, me)?;
         session::SessionConsistency::pre_modify(qs, pre_cand, cand, me)?;
         default_values::DefaultValues::pre_modify(qs, pre_cand, cand, me)?;
+        mailprimary::MailPrimary::pre_modify(qs, pre_cand, cand, me)?;
+        imagelimits::ImageLimit::pre_modify(qs, pre_cand, cand, me)?;
         namehistory::NameHistory::pre_modify(qs, pre_cand, cand, me)?;
         hmac_name_unique::HmacNameUnique::pre_modify(qs, pre_cand, cand, me)?;
         // attr unique should always be last
@@ -356,6 +362,8 @@ impl Plugins {
         spn::Spn::pre_batch_modify(qs, pre_cand, cand, me)?;
         session::SessionConsistency::pre_batch_modify(qs, pre_cand, cand, me)?;
         default_values::DefaultValues::pre_batch_modify(qs, pre_cand, cand, me)?;
+        mailprimary::MailPrimary::pre_batch_modify(qs, pre_cand, cand, me)?;
+        imagelimits::ImageLimit::pre_batch_modify(qs, pre_cand, cand, me)?;
         namehistory::NameHistory::pre_batch_modify(qs, pre_cand, cand, me)?;
         hmac_name_unique::HmacNameUnique::pre_batch_modify(qs, pre_cand, cand, me)?;
         // attr unique should always be last
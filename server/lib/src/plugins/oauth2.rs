@@ -19,26 +19,32 @@ impl Plugin for OAuth2 {
         cand: &mut Vec<Entry<EntryInvalid, EntryNew>>,
         _ce: &CreateEvent,
     ) -> Result<(), OperationError> {
+        cand.iter()
+            .filter(|entry| Self::is_resource_server(entry))
+            .try_for_each(Self::validate_token_expiry)?;
+
         Self::modify_inner(qs, cand)
     }
 
     #[instrument(level = "debug", name = "oauth2_pre_modify", skip_all)]
     fn pre_modify(
         qs: &mut QueryServerWriteTransaction,
-        _pre_cand: &[Arc<EntrySealedCommitted>],
+        pre_cand: &[Arc<EntrySealedCommitted>],
         cand: &mut Vec<Entry<EntryInvalid, EntryCommitted>>,
         _me: &ModifyEvent,
     ) -> Result<(), OperationError> {
+        Self::validate_changed_token_expiry(pre_cand, cand)?;
         Self::modify_inner(qs, cand)
     }
 
     #[instrument(level = "debug", name = "oauth2_pre_batch_modify", skip_all)]
     fn pre_batch_modify(
         qs: &mut QueryServerWriteTransaction,
-        _pre_cand: &[Arc<EntrySealedCommitted>],
+        pre_cand: &[Arc<EntrySealedCommitted>],
         cand: &mut Vec<Entry<EntryInvalid, EntryCommitted>>,
         _me: &BatchModifyEvent,
     ) -> Result<(), OperationError> {
+        Self::validate_changed_token_expiry(pre_cand, cand)?;
         Self::modify_inner(qs, cand)
     }
 }
@@ -68,9 +74,7 @@ impl OAuth2 {
 
         // Populate attributes into the oauth2 clients.
         cand.iter_mut()
-            .filter(|entry| {
-                entry.attribute_equality(Attribute::Class, &EntryClass::OAuth2ResourceServer.into())
-            })
+            .filter(|entry| Self::is_resource_server(entry))
             .try_for_each(|entry| {
                 // Regenerate the basic secret, if needed
                 if entry.attribute_equality(Attribute::Class, &EntryClass::OAuth2ResourceServerBasic.into()) &&
@@ -125,6 +129,64 @@ impl OAuth2 {
             Ok(())
         })
     }
+
+    fn is_resource_server<T: Clone>(entry: &Entry<EntryInvalid, T>) -> bool {
+        entry.attribute_equality(Attribute::Class, &EntryClass::OAuth2ResourceServer.into())
+    }
+
+    /// The token expiry limits are only checked when a modification changes the expiry,
+    /// so that clients that were configured before the limits were added can still be
+    /// modified.
+    fn validate_changed_token_expiry(
+        pre_cand: &[Arc<EntrySealedCommitted>],
+        cand: &[Entry<EntryInvalid, EntryCommitted>],
+    ) -> Result<(), OperationError> {
+        pre_cand
+            .iter()
+            .zip(cand)
+            .filter(|(_, post)| Self::is_resource_server(post))
+            .filter(|(pre, post)| {
+                [
+                    Attribute::OAuth2AccessTokenExpiry,
+                    Attribute::OAuth2RefreshTokenExpiry,
+                ]
+                .into_iter()
+                .any(|attr| pre.get_ava_single_uint32(&attr) != post.get_ava_single_uint32(&attr))
+            })
+            .try_for_each(|(_, post)| Self::validate_token_expiry(post))
+    }
+
+    fn validate_token_expiry<T: Clone>(
+        entry: &Entry<EntryInvalid, T>,
+    ) -> Result<(), OperationError> {
+        let access_token_expiry = entry.get_ava_single_uint32(Attribute::OAuth2AccessTokenExpiry);
+
+        if let Some(expiry) = access_token_expiry {
+            if !(OAUTH2_ACCESS_TOKEN_EXPIRY_MINIMUM..=OAUTH2_ACCESS_TOKEN_EXPIRY_MAXIMUM)
+                .contains(&expiry)
+            {
+                error!(?expiry, "oauth2 access token expiry is out of range");
+                return Err(OperationError::PL0004OAuth2AccessTokenExpiryInvalid);
+            }
+        }
+
+        // A refresh token that expires before the access token it refreshes is meaningless.
+        if let Some(refresh_token_expiry) =
+            entry.get_ava_single_uint32(Attribute::OAuth2RefreshTokenExpiry)
+        {
+            let access_token_expiry = access_token_expiry.unwrap_or(OAUTH2_ACCESS_TOKEN_EXPIRY);
+            if refresh_token_expiry < access_token_expiry {
+                error!(
+                    ?refresh_token_expiry,
+                    ?access_token_expiry,
+                    "oauth2 refresh token expiry is shorter than the access token expiry"
+                );
+                return Err(OperationError::PL0005OAuth2RefreshTokenExpiryTooShort);
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -244,4 +306,146 @@ mod tests {
             }
         );
     }
+
+    fn oauth2_rs_entry(uuid: Uuid) -> Entry<EntryInit, EntryNew> {
+        entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (
+                Attribute::Class,
+                EntryClass::OAuth2ResourceServer.to_value()
+            ),
+            (
+                Attribute::Class,
+                EntryClass::OAuth2ResourceServerBasic.to_value()
+            ),
+            (Attribute::Uuid, Value::Uuid(uuid)),
+            (Attribute::Name, Value::new_iname("test_resource_server")),
+            (
+                Attribute::DisplayName,
+                Value::new_utf8s("test_resource_server")
+            ),
+            (
+                Attribute::OAuth2RsOriginLanding,
+                Value::new_url_s("https://demo.example.com").unwrap()
+            )
+        )
+    }
+
+    #[test]
+    fn test_modify_oauth2_token_expiry() {
+        let uuid = Uuid::new_v4();
+
+        let preload = vec![oauth2_rs_entry(uuid)];
+        run_modify_test!(
+            Ok(()),
+            preload,
+            filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(uuid))),
+            ModifyList::new_list(vec![
+                Modify::Present(Attribute::OAuth2AccessTokenExpiry, Value::Uint32(60)),
+                Modify::Present(Attribute::OAuth2RefreshTokenExpiry, Value::Uint32(60)),
+            ]),
+            None,
+            |_| {},
+            |qs: &mut QueryServerWriteTransaction| {
+                let e = qs
+                    .internal_search_uuid(uuid)
+                    .expect("failed to get oauth2 config");
+                assert_eq!(
+                    e.get_ava_single_uint32(Attribute::OAuth2AccessTokenExpiry),
+                    Some(60)
+                );
+            }
+        );
+
+        for expiry in [
+            OAUTH2_ACCESS_TOKEN_EXPIRY_MINIMUM - 1,
+            OAUTH2_ACCESS_TOKEN_EXPIRY_MAXIMUM + 1,
+        ] {
+            let preload = vec![oauth2_rs_entry(uuid)];
+            run_modify_test!(
+                Err(OperationError::PL0004OAuth2AccessTokenExpiryInvalid),
+                preload,
+                filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(uuid))),
+                ModifyList::new_list(vec![Modify::Present(
+                    Attribute::OAuth2AccessTokenExpiry,
+                    Value::Uint32(expiry)
+                )]),
+                None,
+                |_| {},
+                |_| {}
+            );
+        }
+
+        // Shorter than an explicit access token expiry.
+        let preload = vec![oauth2_rs_entry(uuid)];
+        run_modify_test!(
+            Err(OperationError::PL0005OAuth2RefreshTokenExpiryTooShort),
+            preload,
+            filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(uuid))),
+            ModifyList::new_list(vec![
+                Modify::Present(Attribute::OAuth2AccessTokenExpiry, Value::Uint32(600)),
+                Modify::Present(Attribute::OAuth2RefreshTokenExpiry, Value::Uint32(300)),
+            ]),
+            None,
+            |_| {},
+            |_| {}
+        );
+
+        // Shorter than the default access token expiry.
+        let preload = vec![oauth2_rs_entry(uuid)];
+        run_modify_test!(
+            Err(OperationError::PL0005OAuth2RefreshTokenExpiryTooShort),
+            preload,
+            filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(uuid))),
+            ModifyList::new_list(vec![Modify::Present(
+                Attribute::OAuth2RefreshTokenExpiry,
+                Value::Uint32(OAUTH2_ACCESS_TOKEN_EXPIRY - 1)
+            )]),
+            None,
+            |_| {},
+            |_| {}
+        );
+    }
+
+    #[qs_test]
+    async fn test_modify_oauth2_legacy_token_expiry(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.expect("txn");
+
+        let uuid = Uuid::new_v4();
+        assert!(server_txn
+            .internal_create(vec![oauth2_rs_entry(uuid)])
+            .is_ok());
+
+        // Configure a refresh token expiry from before the limits were enforced. This
+        // bypasses the plugins, as they would refuse it.
+        let filt = filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(uuid)));
+        let mut work_set = server_txn
+            .internal_search_writeable(&filt)
+            .expect("Failed to perform internal search writeable");
+        for (_, entry) in work_set.iter_mut() {
+            entry.set_ava(
+                &Attribute::OAuth2RefreshTokenExpiry,
+                std::iter::once(Value::Uint32(60)),
+            );
+        }
+        assert!(server_txn.internal_apply_writable(work_set).is_ok());
+
+        // Unrelated changes to the client are still allowed.
+        let modlist = ModifyList::new_purge_and_set(
+            Attribute::DisplayName,
+            Value::new_utf8s("legacy_resource_server"),
+        );
+        assert!(server_txn.internal_modify_uuid(uuid, &modlist).is_ok());
+
+        // But changing the expiry must satisfy the limits.
+        let modlist =
+            ModifyList::new_purge_and_set(Attribute::OAuth2RefreshTokenExpiry, Value::Uint32(120));
+        assert_eq!(
+            server_txn.internal_modify_uuid(uuid, &modlist),
+            Err(OperationError::PL0005OAuth2RefreshTokenExpiryTooShort)
+        );
+
+        assert!(server_txn.commit().is_ok());
+    }
 }
@@ -1255,6 +1255,8 @@ mod tests {
                         scope,
                         type_: AuthType::Passkey,
                         ext_metadata: Default::default(),
+                        client_ip: None,
+                        last_seen: None,
                     },
                 )
             ),
@@ -262,6 +262,8 @@ mod tests {
                 scope,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                client_ip: None,
+                last_seen: None,
             },
         );
 
@@ -427,6 +429,8 @@ mod tests {
                         scope,
                         type_: AuthType::Passkey,
                         ext_metadata: Default::default(),
+                        client_ip: None,
+                        last_seen: None,
                     },
                 )
             ),
@@ -602,6 +606,8 @@ mod tests {
                         scope,
                         type_: AuthType::Passkey,
                         ext_metadata: Default::default(),
+                        client_ip: None,
+                        last_seen: None,
                     },
                 )
             ),
@@ -852,6 +858,8 @@ mod tests {
                 scope,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                client_ip: None,
+                last_seen: None,
             },
         );
 
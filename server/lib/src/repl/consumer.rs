@@ -127,6 +127,7 @@ impl QueryServerWriteTransaction<'_> {
 
                 // This must create an EntryInvalidCommitted
                 let merge_ent = ctx_ent.merge_state(db_ent.as_ref(), &self.schema, self.trim_cid());
+                trace!(uuid = %db_ent.get_uuid(), diff = ?db_ent.diff(&merge_ent), "merged incremental entry");
                 (merge_ent, db_ent)
             })
             .collect();
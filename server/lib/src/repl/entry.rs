@@ -216,7 +216,6 @@ impl EntryChangeState {
         }
     }
 
-    #[cfg(test)]
     pub(crate) fn get_attr_cid(&self, attr: &Attribute) -> Option<&Cid> {
         match &self.st {
             State::Live { at: _, changes } => changes.get(attr),
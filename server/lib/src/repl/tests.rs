@@ -3303,6 +3303,8 @@ async fn test_repl_increment_session_new(server_a: &QueryServer, server_b: &Quer
             scope,
             type_,
             ext_metadata: Default::default(),
+            client_ip: None,
+            last_seen: None,
         },
     );
 
@@ -3345,6 +3347,8 @@ async fn test_repl_increment_session_new(server_a: &QueryServer, server_b: &Quer
             scope,
             type_,
             ext_metadata: Default::default(),
+            client_ip: None,
+            last_seen: None,
         },
     );
 
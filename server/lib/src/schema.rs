@@ -17,7 +17,7 @@ use crate::prelude::*;
 use crate::valueset::ValueSet;
 use concread::cowcell::*;
 use hashbrown::{HashMap, HashSet};
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use tracing::trace;
 use uuid::Uuid;
 
@@ -282,6 +282,10 @@ impl SchemaAttribute {
                 matches!(v, PartialValue::Uuid(_)) || matches!(v, PartialValue::Refer(_))
             }
             SyntaxType::Sha256 => matches!(v, PartialValue::Sha256(_)),
+            SyntaxType::ExternalId => matches!(v, PartialValue::ExternalId(_, _)),
+            SyntaxType::MfaGrace => matches!(v, PartialValue::Cid(_)),
+            SyntaxType::BreakGlass => matches!(v, PartialValue::Uuid(_)),
+            SyntaxType::ExtensionJson => matches!(v, PartialValue::Utf8(_)),
             // SyntaxType::Json => matches!(v, PartialValue::Json),
             // Should not be queried
             SyntaxType::Json | SyntaxType::Message => false,
@@ -327,12 +331,13 @@ impl SchemaAttribute {
                 SyntaxType::OauthScopeMap => matches!(v, Value::OauthScopeMap(_, _)),
                 SyntaxType::OauthClaimMap => {
                     matches!(v, Value::OauthClaimValue(_, _, _))
+                        || matches!(v, Value::OauthClaimTypedValue(_, _, _))
                         || matches!(v, Value::OauthClaimMap(_, _))
                 }
                 SyntaxType::PrivateBinary => matches!(v, Value::PrivateBinary(_)),
                 SyntaxType::IntentToken => matches!(v, Value::IntentToken(_, _)),
                 SyntaxType::Passkey => matches!(v, Value::Passkey(_, _, _)),
-                SyntaxType::AttestedPasskey => matches!(v, Value::AttestedPasskey(_, _, _)),
+                SyntaxType::AttestedPasskey => matches!(v, Value::AttestedPasskey(..)),
                 SyntaxType::Session => matches!(v, Value::Session(_, _)),
                 SyntaxType::ApiToken => matches!(v, Value::ApiToken(_, _)),
                 SyntaxType::Oauth2Session => matches!(v, Value::Oauth2Session(_, _)),
@@ -352,6 +357,10 @@ impl SchemaAttribute {
                 SyntaxType::ApplicationPassword => matches!(v, Value::ApplicationPassword(..)),
                 SyntaxType::Json => matches!(v, Value::Json(_)),
                 SyntaxType::Sha256 => matches!(v, Value::Sha256(_)),
+                SyntaxType::ExternalId => matches!(v, Value::ExternalId(_, _)),
+                SyntaxType::MfaGrace => matches!(v, Value::MfaGrace(_, _)),
+                SyntaxType::BreakGlass => matches!(v, Value::BreakGlass(_, _)),
+                SyntaxType::ExtensionJson => matches!(v, Value::ExtensionJson(_, _)),
                 SyntaxType::EcKeyPrivate => matches!(v, Value::SecretValue(_)),
                 SyntaxType::Message => false,
             };
@@ -529,6 +538,166 @@ impl SchemaClass {
     }
 }
 
+/// The schema of an entry that is determined only by its set of classes.
+pub(crate) struct ResolvedClasses<'a> {
+    /// The attributes that must be present on the entry.
+    pub must: Vec<&'a SchemaAttribute>,
+    /// The attributes that may be present on the entry, which includes `must`. This
+    /// is `None` if the classes name an attribute that is not in the schema.
+    pub may: Option<BTreeMap<&'a Attribute, &'a SchemaAttribute>>,
+}
+
+/// A cache of [ResolvedClasses] for a batch of entries, such as a single create. Many
+/// entries in a batch share the same classes, so this avoids resolving the same class
+/// and attribute definitions for each entry in turn.
+///
+/// Only class sets that were valid are cached, so any error is reported for every
+/// entry it applies to exactly as it would be without the cache.
+#[derive(Default)]
+pub(crate) struct SchemaClassCache<'a> {
+    resolved: HashMap<BTreeSet<String>, ResolvedClasses<'a>>,
+}
+
+impl<'a> SchemaClassCache<'a> {
+    /// The number of distinct class sets that have been resolved.
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.resolved.len()
+    }
+
+    /// Resolve the classes of an entry, checking that they exist and that their
+    /// supplements and excludes are satisfied.
+    pub fn resolve(
+        &mut self,
+        schema: &'a dyn SchemaTransaction,
+        entry_classes: &BTreeSet<String>,
+    ) -> Result<&ResolvedClasses<'a>, SchemaError> {
+        if !self.resolved.contains_key(entry_classes) {
+            let resolved = Self::resolve_uncached(schema, entry_classes)?;
+            self.resolved.insert(entry_classes.clone(), resolved);
+        }
+
+        self.resolved
+            .get(entry_classes)
+            .ok_or(SchemaError::Corrupted)
+    }
+
+    /// Resolve the classes of an entry without caching the result, for when only a
+    /// single entry is validated.
+    pub fn resolve_uncached(
+        schema: &'a dyn SchemaTransaction,
+        entry_classes: &BTreeSet<String>,
+    ) -> Result<ResolvedClasses<'a>, SchemaError> {
+        let schema_classes = schema.get_classes();
+        let schema_attributes = schema.get_attributes();
+
+        let mut invalid_classes = Vec::with_capacity(0);
+        let mut classes: Vec<&SchemaClass> = Vec::with_capacity(entry_classes.len());
+
+        entry_classes
+            .iter()
+            .for_each(|s| match schema_classes.get(s.as_str()) {
+                Some(x) => classes.push(x),
+                None => {
+                    admin_debug!("invalid class: {:?}", s);
+                    invalid_classes.push(s.to_string())
+                }
+            });
+
+        if !invalid_classes.is_empty() {
+            return Err(SchemaError::InvalidClass(invalid_classes));
+        };
+
+        // Now determine the set of excludes and requires we have, and then
+        // assert we don't violate them.
+
+        let supplements_classes: Vec<_> = classes
+            .iter()
+            .flat_map(|cls| cls.systemsupplements.iter().chain(cls.supplements.iter()))
+            .collect();
+
+        // So long as one supplement is present we can continue.
+        let valid_supplements = if supplements_classes.is_empty() {
+            // No need to check.
+            true
+        } else {
+            supplements_classes
+                .iter()
+                .any(|class| entry_classes.contains(class.as_str()))
+        };
+
+        if !valid_supplements {
+            warn!(
+                "Validation error, the following possible supplement classes are missing - {:?}",
+                supplements_classes
+            );
+            let supplements_classes = supplements_classes.iter().map(|s| s.to_string()).collect();
+            return Err(SchemaError::SupplementsNotSatisfied(supplements_classes));
+        }
+
+        let excludes_classes: Vec<_> = classes
+            .iter()
+            .flat_map(|cls| cls.systemexcludes.iter().chain(cls.excludes.iter()))
+            .collect();
+
+        let mut invalid_excludes = Vec::with_capacity(0);
+
+        excludes_classes.iter().for_each(|class| {
+            if entry_classes.contains(class.as_str()) {
+                invalid_excludes.push(class.to_string())
+            }
+        });
+
+        if !invalid_excludes.is_empty() {
+            admin_warn!(
+                "Validation error, the following excluded classes are present - {:?}",
+                invalid_excludes
+            );
+            return Err(SchemaError::ExcludesNotSatisfied(invalid_excludes));
+        }
+
+        // What this is really doing is taking a set of classes, and building an
+        // "overall" class that describes this exact object for checking. IE we
+        // build a super must/may set from the small class must/may sets.
+
+        //   for each class
+        //      add systemmust/must and systemmay/may to their lists
+        //      add anything from must also into may
+
+        // Now from the set of valid classes make a list of must/may
+        //
+        // NOTE: We still need this on extensible, because we still need to satisfy
+        // our other must conditions as well!
+        let must = classes
+            .iter()
+            // Join our class systemmmust + must into one iter
+            .flat_map(|cls| cls.systemmust.iter().chain(cls.must.iter()))
+            .map(|s| {
+                // This should NOT fail - if it does, it means our schema is
+                // in an invalid state!
+                schema_attributes.get(s).ok_or(SchemaError::Corrupted)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // The may set is only needed for entries that are not extensible, so a missing
+        // attribute here is only an error for those.
+        let may = classes
+            .iter()
+            // Join our class systemmmust + must + systemmay + may into one.
+            .flat_map(|cls| {
+                cls.systemmust
+                    .iter()
+                    .chain(cls.must.iter())
+                    .chain(cls.systemmay.iter())
+                    .chain(cls.may.iter())
+            })
+            .map(|s| schema_attributes.get(s).map(|a_schema| (s, a_schema)))
+            .collect::<Option<BTreeMap<_, _>>>();
+
+        Ok(ResolvedClasses { must, may })
+    }
+}
+
 pub trait SchemaTransaction {
     fn get_classes(&self) -> &HashMap<AttrString, SchemaClass>;
     fn get_attributes(&self) -> &HashMap<Attribute, SchemaAttribute>;
@@ -922,7 +1091,9 @@ impl Schema {
 #[cfg(test)]
 mod tests {
     use crate::prelude::*;
-    use crate::schema::{Schema, SchemaAttribute, SchemaClass, SchemaTransaction, SyntaxType};
+    use crate::schema::{
+        Schema, SchemaAttribute, SchemaClass, SchemaClassCache, SchemaTransaction, SyntaxType,
+    };
     use uuid::Uuid;
 
     // use crate::proto_v1::Filter as ProtoFilter;
@@ -1730,4 +1901,85 @@ mod tests {
 
         assert!(e_person_valid.validate(&schema).is_ok());
     }
+
+    fn test_schema_class_cache_batch(count: usize) -> Vec<EntryInvalidNew> {
+        (0..count)
+            .map(|i| {
+                entry_init!(
+                    (Attribute::Class, EntryClass::Object.to_value()),
+                    (Attribute::Class, EntryClass::AttributeType.to_value()),
+                    (
+                        Attribute::AttributeName,
+                        Value::new_iutf8(&format!("testattr{i}"))
+                    ),
+                    (Attribute::Description, Value::Utf8(format!("testattr {i}"))),
+                    (Attribute::MultiValue, Value::Bool(true)),
+                    (Attribute::Unique, Value::Bool(false)),
+                    (Attribute::Syntax, Value::Syntax(SyntaxType::Utf8String)),
+                    (Attribute::Uuid, Value::Uuid(Uuid::new_v4()))
+                )
+                .into_invalid_new()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_schema_class_cache_equivalence() {
+        let schema_outer = Schema::new().expect("failed to create schema");
+        let schema = schema_outer.read();
+
+        let batch = test_schema_class_cache_batch(16);
+
+        let mut cache = SchemaClassCache::default();
+        for (id, e) in (1..).zip(batch) {
+            let uncached = e
+                .clone()
+                .validate(&schema)
+                .expect("failed to validate")
+                .seal(&schema)
+                .into_sealed_committed_id(id);
+            let cached = e
+                .validate_cached(&schema, &mut cache)
+                .expect("failed to validate")
+                .seal(&schema)
+                .into_sealed_committed_id(id);
+
+            // The sealed entries must be identical down to what is written to the db.
+            assert_eq!(
+                serde_json::to_vec(&uncached.to_dbentry()).expect("failed to serialise"),
+                serde_json::to_vec(&cached.to_dbentry()).expect("failed to serialise")
+            );
+            assert_eq!(uncached.get_changestate(), cached.get_changestate());
+        }
+
+        // Every entry shared the same classes.
+        assert_eq!(cache.len(), 1);
+
+        // Failures are not cached, so they are reported for each entry they apply to.
+        let e_bad_class = entry_init!(
+            (Attribute::Class, Value::new_iutf8("zzzzzz")),
+            (Attribute::Uuid, Value::Uuid(Uuid::new_v4()))
+        )
+        .into_invalid_new();
+        for _ in 0..2 {
+            assert_eq!(
+                e_bad_class.clone().validate_cached(&schema, &mut cache),
+                Err(SchemaError::InvalidClass(vec!["zzzzzz".to_string()]))
+            );
+        }
+        assert_eq!(cache.len(), 1);
+
+        // A cached class set still checks the attributes of each entry.
+        let e_missing_must = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::AttributeType.to_value()),
+            (Attribute::AttributeName, Value::new_iutf8("testattr")),
+            (Attribute::Uuid, Value::Uuid(Uuid::new_v4()))
+        )
+        .into_invalid_new();
+        assert!(matches!(
+            e_missing_must.validate_cached(&schema, &mut cache),
+            Err(SchemaError::MissingMustAttribute(_))
+        ));
+    }
 }
@@ -3,6 +3,7 @@ use super::profiles::{
     AccessControlCreateResolved, AccessControlReceiverCondition, AccessControlTargetCondition,
 };
 use super::protected::{PROTECTED_ENTRY_CLASSES, PROTECTED_MOD_PRES_ENTRY_CLASSES};
+use super::CreateAcpDenial;
 use crate::prelude::*;
 use std::collections::BTreeSet;
 use std::ops::Sub;
@@ -253,6 +254,50 @@ fn create_filter_entry<'a>(
     }
 }
 
+/// Describe why each of the related access controls did not permit the create of this
+/// entry.
+pub(super) fn explain_create_denial(
+    related_acp: &[AccessControlCreateResolved],
+    entry: &Entry<EntryInit, EntryNew>,
+) -> Vec<CreateAcpDenial> {
+    let create_attrs: BTreeSet<Attribute> = entry.attr_keys().cloned().collect();
+    let create_classes: BTreeSet<&str> = entry
+        .get_ava_iter_iutf8(Attribute::Class)
+        .map(|s| s.collect())
+        .unwrap_or_default();
+
+    related_acp
+        .iter()
+        .map(|accr| {
+            let applies = match (&accr.receiver_condition, &accr.target_condition) {
+                (AccessControlReceiverCondition::EntryManager, _) => false,
+                (
+                    AccessControlReceiverCondition::GroupChecked,
+                    AccessControlTargetCondition::Scope(f_res),
+                ) => entry.entry_match_no_index(f_res),
+            };
+
+            let allowed_attrs: BTreeSet<&Attribute> = accr.acp.attrs.iter().collect();
+            let allowed_classes: BTreeSet<&str> =
+                accr.acp.classes.iter().map(|s| s.as_str()).collect();
+
+            CreateAcpDenial {
+                acp: accr.acp.acp.name.clone(),
+                applies,
+                denied_attrs: create_attrs
+                    .iter()
+                    .filter(|a| !allowed_attrs.contains(a))
+                    .cloned()
+                    .collect(),
+                denied_classes: create_classes
+                    .difference(&allowed_classes)
+                    .map(|s| s.to_string())
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
 fn protected_filter_entry<'a>(ident: &Identity, entry: &Entry<EntryInit, EntryNew>) -> IResult<'a> {
     match &ident.origin {
         IdentType::Internal(InternalRole::System)
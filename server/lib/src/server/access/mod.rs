@@ -38,7 +38,7 @@ use self::profiles::{
 
 use kanidm_proto::scim_v1::server::ScimAttributeEffectiveAccess;
 
-use self::create::{apply_create_access, CreateResult};
+use self::create::{apply_create_access, explain_create_denial, CreateResult};
 use self::delete::{apply_delete_access, DeleteResult};
 use self::modify::{apply_modify_access, ModifyResult};
 use self::search::{apply_search_access, SearchResult};
@@ -71,6 +71,72 @@ impl From<&Access> for ScimAttributeEffectiveAccess {
     }
 }
 
+/// Why a single create access control did not permit the create of an entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreateAcpDenial {
+    pub acp: String,
+    /// If the receiver and target conditions of this access control matched the entry.
+    pub applies: bool,
+    pub denied_attrs: BTreeSet<Attribute>,
+    pub denied_classes: BTreeSet<String>,
+}
+
+/// The reason an entry was rejected by a create access check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CreateDenyReason {
+    /// The entry has no classes, so access can not be determined.
+    NoClass,
+    /// An access module explicitly denied the create of this entry.
+    Denied,
+    /// None of the related access controls granted all of the requested attributes
+    /// and classes.
+    NotGranted(Vec<CreateAcpDenial>),
+}
+
+/// An entry that was denied during a create access check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreateDenial {
+    pub entry_id: String,
+    pub reason: CreateDenyReason,
+}
+
+impl CreateDenial {
+    /// Emit this denial to the security log. The correlation id allows the denial to be
+    /// linked to the other events of the same operation.
+    pub fn log(&self, correlation_id: &dyn std::fmt::Display) {
+        match &self.reason {
+            CreateDenyReason::NoClass => security_access!(
+                %correlation_id,
+                entry_id = %self.entry_id,
+                "create denied - entry has no classes"
+            ),
+            CreateDenyReason::Denied => security_access!(
+                %correlation_id,
+                entry_id = %self.entry_id,
+                "create denied - entry may not be created by this identity"
+            ),
+            CreateDenyReason::NotGranted(acps) if acps.is_empty() => security_access!(
+                %correlation_id,
+                entry_id = %self.entry_id,
+                "create denied - no create access controls apply to this identity"
+            ),
+            CreateDenyReason::NotGranted(acps) => {
+                for acp_denial in acps {
+                    security_access!(
+                        %correlation_id,
+                        entry_id = %self.entry_id,
+                        acp = %acp_denial.acp,
+                        applies = acp_denial.applies,
+                        denied_attrs = ?acp_denial.denied_attrs,
+                        denied_classes = ?acp_denial.denied_classes,
+                        "create denied - access control does not grant the requested attributes or classes"
+                    )
+                }
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum AccessClass {
     Grant,
@@ -116,7 +182,12 @@ pub enum AccessSrchResult {
         attr: BTreeSet<Attribute>,
     },
     */
-    Allow { attr: BTreeSet<Attribute> },
+    // Allow this attr set. Any attribute in deny is removed from the final
+    // allowed set, even if it was allowed by another ACP or module.
+    Allow {
+        attr: BTreeSet<Attribute>,
+        deny: BTreeSet<Attribute>,
+    },
 }
 
 pub enum AccessModResult<'a> {
@@ -740,12 +811,23 @@ pub trait AccessControlsTransaction<'a> {
         }
     }
 
-    #[instrument(level = "debug", name = "access::create_allow_operation", skip_all)]
     fn create_allow_operation(
         &self,
         ce: &CreateEvent,
         entries: &[Entry<EntryInit, EntryNew>],
     ) -> Result<bool, OperationError> {
+        self.create_allow_operation_explain(ce, entries)
+            .map(|denials| denials.is_empty())
+    }
+
+    /// Check if the entries may be created, returning the reason that each denied entry
+    /// was rejected. If the returned set is empty, the create may proceed.
+    #[instrument(level = "debug", name = "access::create_allow_operation", skip_all)]
+    fn create_allow_operation_explain(
+        &self,
+        ce: &CreateEvent,
+        entries: &[Entry<EntryInit, EntryNew>],
+    ) -> Result<Vec<CreateDenial>, OperationError> {
         // Some useful references we'll use for the remainder of the operation
         let create_state = self.get_create();
         let acp_resolve_filter_cache = self.get_acp_resolve_filter_cache();
@@ -773,62 +855,75 @@ pub trait AccessControlsTransaction<'a> {
             .collect();
 
         // For each entry
-        let decision = entries.iter().all(|e| {
-            let requested_pres: BTreeSet<_> = e.attr_keys().cloned().collect();
-            let Some(requested_pres_classes) = e
-                .get_ava_as_iutf8(Attribute::Class)
-                .map(|set| set.iter().map(|s| s.as_str()).collect::<BTreeSet<_>>())
-            else {
-                error!("unable to perform access control checks on entry with no classes, denied.");
-                return false;
-            };
+        let denials: Vec<_> = entries
+            .iter()
+            .filter_map(|e| {
+                let entry_id = e.get_display_id();
+                let requested_pres: BTreeSet<_> = e.attr_keys().cloned().collect();
+                let Some(requested_pres_classes) = e
+                    .get_ava_as_iutf8(Attribute::Class)
+                    .map(|set| set.iter().map(|s| s.as_str()).collect::<BTreeSet<_>>())
+                else {
+                    error!(
+                        "unable to perform access control checks on entry with no classes, denied."
+                    );
+                    return Some(CreateDenial {
+                        entry_id,
+                        reason: CreateDenyReason::NoClass,
+                    });
+                };
 
-            debug!(?requested_pres, "Requested present set");
-            debug!(?requested_pres_classes, "Requested present class set");
-            debug!(entry_id = %e.get_display_id());
-
-            match apply_create_access(&ce.ident, related_acp.as_slice(), e) {
-                CreateResult::Deny => false,
-                CreateResult::Grant => true,
-                CreateResult::Allow { pres, pres_cls } => {
-                    let mut decision = true;
-
-                    if !requested_pres.is_subset(&pres) {
-                        security_error!("requested_pres is not a subset of allowed");
-                        security_error!(
-                            "requested_pres: {:?} !⊆ allowed: {:?}",
-                            requested_pres,
-                            pres
-                        );
-                        decision = false
-                    };
-
-                    if !requested_pres_classes.is_subset(&pres_cls) {
-                        security_error!("requested_pres_classes is not a subset of allowed");
-                        security_error!(
-                            "requested_classes: {:?} !⊆ allowed: {:?}",
-                            requested_pres_classes,
-                            pres_cls
-                        );
-                        decision = false;
-                    };
+                debug!(?requested_pres, "Requested present set");
+                debug!(?requested_pres_classes, "Requested present class set");
+                debug!(%entry_id);
+
+                let reason = match apply_create_access(&ce.ident, related_acp.as_slice(), e) {
+                    CreateResult::Deny => CreateDenyReason::Denied,
+                    CreateResult::Grant => return None,
+                    CreateResult::Allow { pres, pres_cls } => {
+                        if !requested_pres.is_subset(&pres) {
+                            security_error!("requested_pres is not a subset of allowed");
+                            security_error!(
+                                "requested_pres: {:?} !⊆ allowed: {:?}",
+                                requested_pres,
+                                pres
+                            );
+                        };
+
+                        if !requested_pres_classes.is_subset(&pres_cls) {
+                            security_error!("requested_pres_classes is not a subset of allowed");
+                            security_error!(
+                                "requested_classes: {:?} !⊆ allowed: {:?}",
+                                requested_pres_classes,
+                                pres_cls
+                            );
+                        };
+
+                        if requested_pres.is_subset(&pres)
+                            && requested_pres_classes.is_subset(&pres_cls)
+                        {
+                            debug!("passed pres, classes check.");
+                            return None;
+                        }
 
-                    if decision {
-                        debug!("passed pres, classes check.");
+                        CreateDenyReason::NotGranted(explain_create_denial(
+                            related_acp.as_slice(),
+                            e,
+                        ))
                     }
+                };
 
-                    decision
-                }
-            }
-        });
+                Some(CreateDenial { entry_id, reason })
+            })
+            .collect();
 
-        if decision {
+        if denials.is_empty() {
             debug!("allowed create of {} entries ✅", entries.len());
         } else {
             security_access!("denied ❌ - create may not proceed");
         }
 
-        Ok(decision)
+        Ok(denials)
     }
 
     #[instrument(level = "trace", name = "access::delete_related_acp", skip_all)]
@@ -1195,6 +1290,7 @@ mod tests {
             AccessControlSearch, AccessControlTarget,
         },
         Access, AccessClass, AccessControls, AccessControlsTransaction, AccessEffectivePermission,
+        CreateAcpDenial, CreateDenyReason,
     };
     use crate::migration_data::BUILTIN_ACCOUNT_ANONYMOUS;
     use crate::prelude::*;
@@ -1537,6 +1633,42 @@ mod tests {
             ),
             AccessControlSearch
         );
+
+        // Denied attributes are optional, and denying memberof also denies directmemberof.
+        let acs = acp_from_entry_ok!(
+            &mut qs_write,
+            entry_init!(
+                (Attribute::Class, EntryClass::Object.to_value()),
+                (
+                    Attribute::Class,
+                    EntryClass::AccessControlProfile.to_value()
+                ),
+                (Attribute::Class, EntryClass::AccessControlSearch.to_value()),
+                (Attribute::Name, Value::new_iname("acp_valid")),
+                (
+                    Attribute::Uuid,
+                    Value::Uuid(uuid::uuid!("cc8e95b4-c24f-4d68-ba54-8bed76f63930"))
+                ),
+                (
+                    Attribute::AcpReceiverGroup,
+                    Value::Refer(uuid::uuid!("cc8e95b4-c24f-4d68-ba54-8bed76f63930"))
+                ),
+                (
+                    Attribute::AcpTargetScope,
+                    Value::new_json_filter_s("{\"eq\":[\"name\",\"a\"]}").expect("filter")
+                ),
+                (Attribute::AcpSearchAttr, Value::from(Attribute::Name)),
+                (
+                    Attribute::AcpDenyAttribute,
+                    Value::from(Attribute::MemberOf)
+                )
+            ),
+            AccessControlSearch
+        );
+        assert_eq!(
+            acs.deny_attrs,
+            btreeset![Attribute::MemberOf, Attribute::DirectMemberOf]
+        );
     }
 
     #[qs_test]
@@ -2047,6 +2179,87 @@ mod tests {
         test_acp_search_reduce!(&se_anon, vec![acp], r_set, ex_anon);
     }
 
+    #[test]
+    fn test_access_enforce_search_deny_attrs() {
+        // Test that an attribute denied by one acp is removed, even when another
+        // acp that applies to the same entry grants it.
+        let ev1 = E_TESTPERSON_1.clone().into_sealed_committed();
+        let r_set = vec![Arc::new(ev1)];
+
+        let exv1 = E_TESTPERSON_1_REDUCED.clone().into_sealed_committed();
+        let ex_anon = vec![exv1];
+
+        let se_anon = SearchEvent::new_impersonate_entry(
+            E_TEST_ACCOUNT_1.clone(),
+            filter_all!(f_eq(
+                Attribute::Name,
+                PartialValue::new_iname("testperson1")
+            )),
+        );
+
+        // A broad grant over all entries.
+        let acp_grant = AccessControlSearch::from_raw(
+            "test_acp_grant",
+            Uuid::new_v4(),
+            UUID_TEST_GROUP_1,
+            filter_valid!(f_pres(Attribute::Name)),
+            "name class uuid",
+        );
+
+        // A narrower acp that denies class and uuid on testperson1.
+        let acp_deny = AccessControlSearch::from_raw(
+            "test_acp_deny",
+            Uuid::new_v4(),
+            UUID_TEST_GROUP_1,
+            filter_valid!(f_eq(
+                Attribute::Name,
+                PartialValue::new_iname("testperson1")
+            )),
+            Attribute::Name.as_ref(),
+        )
+        .with_deny_attrs("class uuid");
+
+        test_acp_search_reduce!(&se_anon, vec![acp_grant, acp_deny], r_set, ex_anon);
+    }
+
+    #[test]
+    fn test_access_enforce_search_deny_attrs_filter() {
+        // Test that a denied attribute can not be used in a search filter, and that
+        // a deny only applies to the entries the denying acp targets.
+        let ev1 = E_TESTPERSON_1.clone().into_sealed_committed();
+        let ev2 = E_TESTPERSON_2.clone().into_sealed_committed();
+        let r_set = vec![Arc::new(ev1), Arc::new(ev2.clone())];
+        let ex_anon = vec![Arc::new(ev2)];
+
+        let se_anon = SearchEvent::new_impersonate_entry(
+            E_TEST_ACCOUNT_1.clone(),
+            filter_all!(f_pres(Attribute::Uuid)),
+        );
+
+        let acp_grant = AccessControlSearch::from_raw(
+            "test_acp_grant",
+            Uuid::new_v4(),
+            UUID_TEST_GROUP_1,
+            filter_valid!(f_pres(Attribute::Name)),
+            "name class uuid",
+        );
+
+        let acp_deny = AccessControlSearch::from_raw(
+            "test_acp_deny",
+            Uuid::new_v4(),
+            UUID_TEST_GROUP_1,
+            filter_valid!(f_eq(
+                Attribute::Name,
+                PartialValue::new_iname("testperson1")
+            )),
+            Attribute::Name.as_ref(),
+        )
+        .with_deny_attrs("uuid");
+
+        // Only testperson2 may be matched by uuid.
+        test_acp_search!(&se_anon, vec![acp_grant, acp_deny], r_set, ex_anon);
+    }
+
     macro_rules! test_acp_modify {
         (
             $me:expr,
@@ -2447,6 +2660,87 @@ mod tests {
         test_acp_create!(&ce_admin, vec![acp, acp2], &r4_set, false);
     }
 
+    #[derive(Clone, Default)]
+    struct LogCapture(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for LogCapture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().expect("poisoned").extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_access_create_denial_explain() {
+        let ev1 = entry_init!(
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::TestNotAllowed, Value::new_iutf8("notallowed")),
+            (Attribute::Name, Value::new_iname("testperson1")),
+            (Attribute::Uuid, Value::Uuid(UUID_TEST_ACCOUNT_1))
+        );
+        let r1_set = vec![ev1];
+
+        let ce_admin = CreateEvent::new_impersonate_identity(
+            Identity::from_impersonate_entry_readwrite(E_TEST_ACCOUNT_1.clone()),
+            vec![],
+        );
+
+        let acp = AccessControlCreate::from_raw(
+            "test_create",
+            Uuid::new_v4(),
+            UUID_TEST_GROUP_1,
+            filter_valid!(f_eq(
+                Attribute::Name,
+                PartialValue::new_iname("testperson1")
+            )),
+            EntryClass::Account.into(),
+            "class name uuid",
+        );
+
+        let ac = AccessControls::default();
+        let mut acw = ac.write();
+        acw.update_create(vec![acp]).expect("Failed to update");
+
+        let denials = acw
+            .create_allow_operation_explain(&ce_admin, &r1_set)
+            .expect("op failed");
+
+        assert_eq!(denials.len(), 1);
+        let denial = &denials[0];
+        assert_eq!(
+            denial.reason,
+            CreateDenyReason::NotGranted(vec![CreateAcpDenial {
+                acp: "test_create".to_string(),
+                applies: true,
+                denied_attrs: btreeset![Attribute::TestNotAllowed],
+                denied_classes: BTreeSet::default(),
+            }])
+        );
+
+        // Check the diagnostic fields are emitted to the security log.
+        let capture = LogCapture::default();
+        let writer = capture.clone();
+        let subscriber = sketching::tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+
+        tracing::subscriber::with_default(subscriber, || denial.log(&"test-correlation-id"));
+
+        let logged =
+            String::from_utf8(capture.0.lock().expect("poisoned").clone()).expect("invalid utf8");
+        assert!(logged.contains("correlation_id=test-correlation-id"));
+        assert!(logged.contains(&format!("entry_id={}", denial.entry_id)));
+        assert!(logged.contains("acp=test_create"));
+        assert!(logged.contains("applies=true"));
+        assert!(logged.contains("denied_attrs={TestNotAllowed}"));
+        assert!(logged.contains("denied_classes={}"));
+    }
+
     #[test]
     fn test_access_enforce_scope_create() {
         let ev1 = entry_init!(
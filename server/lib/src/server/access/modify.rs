@@ -434,6 +434,10 @@ fn modify_protected_entry_attrs<'a>(classes: &BTreeSet<String>) -> AccessModResu
             Attribute::Image,
             Attribute::DomainAllowEasterEggs,
             Attribute::DomainAllowAccountRecovery,
+            Attribute::DomainRevokedSessionRetentionDays,
+            Attribute::DomainImageMaxWidth,
+            Attribute::DomainImageMaxHeight,
+            Attribute::DomainImageMaxBytes,
         ]);
     }
 
@@ -20,6 +20,7 @@ pub struct AccessControlSearchResolved<'a> {
 pub struct AccessControlSearch {
     pub acp: AccessControlProfile,
     pub attrs: BTreeSet<Attribute>,
+    pub deny_attrs: BTreeSet<Attribute>,
 }
 
 impl AccessControlSearch {
@@ -49,9 +50,23 @@ impl AccessControlSearch {
             attrs.insert(Attribute::DirectMemberOf);
         }
 
+        let mut deny_attrs: BTreeSet<_> = value
+            .get_ava_iter_iutf8(Attribute::AcpDenyAttribute)
+            .map(|i| i.map(Attribute::from).collect())
+            .unwrap_or_default();
+
+        // Likewise, denying memberof must also deny directmemberof
+        if deny_attrs.contains(&Attribute::MemberOf) {
+            deny_attrs.insert(Attribute::DirectMemberOf);
+        }
+
         let acp = AccessControlProfile::try_from(qs, value)?;
 
-        Ok(AccessControlSearch { acp, attrs })
+        Ok(AccessControlSearch {
+            acp,
+            attrs,
+            deny_attrs,
+        })
     }
 
     /// ⚠️  - Manually create a search access profile from values.
@@ -79,9 +94,18 @@ impl AccessControlSearch {
                 target: AccessControlTarget::Scope(targetscope),
             },
             attrs,
+            deny_attrs: BTreeSet::default(),
         }
     }
 
+    /// ⚠️  - Add denied attributes to a manually created search access profile.
+    /// This is a TEST ONLY method and will never be exposed in production.
+    #[cfg(test)]
+    pub(super) fn with_deny_attrs(mut self, deny_attrs: &str) -> Self {
+        self.deny_attrs = deny_attrs.split_whitespace().map(Attribute::from).collect();
+        self
+    }
+
     /// ⚠️  - Manually create a search access profile from values.
     /// This is a TEST ONLY method and will never be exposed in production.
     #[cfg(test)]
@@ -99,6 +123,7 @@ impl AccessControlSearch {
                 target,
             },
             attrs: attrs.split_whitespace().map(Attribute::from).collect(),
+            deny_attrs: BTreeSet::default(),
         }
     }
 }
@@ -26,6 +26,7 @@ pub(super) fn apply_search_access(
     let mut grant = false;
     let constrain = BTreeSet::default();
     let mut allow = BTreeSet::default();
+    let mut deny = BTreeSet::default();
 
     // The access control profile
     match search_filter_entry(ident, related_acp, entry) {
@@ -33,7 +34,13 @@ pub(super) fn apply_search_access(
         AccessSrchResult::Grant => grant = true,
         AccessSrchResult::Ignore => {}
         // AccessSrchResult::Constrain { mut attr } => constrain.append(&mut attr),
-        AccessSrchResult::Allow { mut attr } => allow.append(&mut attr),
+        AccessSrchResult::Allow {
+            mut attr,
+            deny: mut deny_attr,
+        } => {
+            allow.append(&mut attr);
+            deny.append(&mut deny_attr);
+        }
     };
 
     match search_oauth2_filter_entry(ident, entry) {
@@ -41,7 +48,13 @@ pub(super) fn apply_search_access(
         AccessSrchResult::Grant => grant = true,
         AccessSrchResult::Ignore => {}
         // AccessSrchResult::Constrain { mut attr } => constrain.append(&mut attr),
-        AccessSrchResult::Allow { mut attr } => allow.append(&mut attr),
+        AccessSrchResult::Allow {
+            mut attr,
+            deny: mut deny_attr,
+        } => {
+            allow.append(&mut attr);
+            deny.append(&mut deny_attr);
+        }
     };
 
     match search_applications_filter_entry(ident, entry) {
@@ -49,7 +62,13 @@ pub(super) fn apply_search_access(
         AccessSrchResult::Grant => grant = true,
         AccessSrchResult::Ignore => {}
         // AccessSrchResult::Constrain { mut attr } => constrain.append(&mut attr),
-        AccessSrchResult::Allow { mut attr } => allow.append(&mut attr),
+        AccessSrchResult::Allow {
+            mut attr,
+            deny: mut deny_attr,
+        } => {
+            allow.append(&mut attr);
+            deny.append(&mut deny_attr);
+        }
     };
 
     match search_sync_account_filter_entry(ident, entry) {
@@ -57,7 +76,13 @@ pub(super) fn apply_search_access(
         AccessSrchResult::Grant => grant = true,
         AccessSrchResult::Ignore => {}
         // AccessSrchResult::Constrain{ mut attr } => constrain.append(&mut attr),
-        AccessSrchResult::Allow { mut attr } => allow.append(&mut attr),
+        AccessSrchResult::Allow {
+            mut attr,
+            deny: mut deny_attr,
+        } => {
+            allow.append(&mut attr);
+            deny.append(&mut deny_attr);
+        }
     };
 
     // We'll add more modules later.
@@ -75,6 +100,13 @@ pub(super) fn apply_search_access(
         } else {
             allow
         };
+        // Denied attributes always win over any grant.
+        let allowed_attrs = if !deny.is_empty() {
+            security_debug!(?deny, "removing denied attributes");
+            &allowed_attrs - &deny
+        } else {
+            allowed_attrs
+        };
         SearchResult::Allow(allowed_attrs)
     }
 }
@@ -159,6 +191,8 @@ fn search_filter_entry(
     let ident_memberof = ident.get_memberof();
     let ident_uuid = ident.get_uuid();
 
+    let mut denied_attrs: BTreeSet<Attribute> = BTreeSet::default();
+
     let allowed_attrs: BTreeSet<Attribute> = related_acp
         .iter()
         .filter_map(|acs| {
@@ -206,6 +240,8 @@ fn search_filter_entry(
 
             // -- Conditions pass -- release the attributes.
             debug!(entry = ?entry.get_display_id(), acs = %acs.acp.acp.name, "acs applied to entry");
+            // add deny_attrs to denied.
+            denied_attrs.extend(acs.acp.deny_attrs.iter().cloned());
             // add search_attrs to allowed.
             Some(acs.acp.attrs.iter().cloned())
         })
@@ -214,6 +250,7 @@ fn search_filter_entry(
 
     AccessSrchResult::Allow {
         attr: allowed_attrs,
+        deny: denied_attrs,
     }
 }
 
@@ -255,6 +292,7 @@ fn search_oauth2_filter_entry(
                         Attribute::OAuth2RsOriginLanding,
                         Attribute::Image
                     ),
+                    deny: BTreeSet::default(),
                 };
             }
             AccessSrchResult::Ignore
@@ -300,6 +338,7 @@ fn search_applications_filter_entry(
                         Attribute::Name,
                         Attribute::LinkedGroup
                     ),
+                    deny: BTreeSet::default(),
                 };
             }
             AccessSrchResult::Ignore
@@ -353,6 +392,7 @@ fn search_sync_account_filter_entry(
                                 Attribute::Uuid,
                                 Attribute::SyncCredentialPortal
                             ),
+                            deny: BTreeSet::default(),
                         };
                     }
                 }
@@ -298,6 +298,11 @@ impl QueryServerWriteTransaction<'_> {
                         ident: ident.clone(),
                         entries,
                         return_created_uuids: false,
+                        return_generated_secrets: false,
+                        override_created_at: None,
+                        unless_exists: None,
+                        allow_state_machine_bypass: false,
+                        precheck_spn_uniqueness: false,
                     };
 
                     self.create(&create_event)?;
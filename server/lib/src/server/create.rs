@@ -1,13 +1,62 @@
 use crate::prelude::*;
+use crate::schema::SchemaClassCache;
 use crate::server::CreateEvent;
 use crate::server::{ChangeFlag, Plugins};
+use crate::valueset::ValueSetDateTime;
+use crypto_glue::traits::Zeroizing;
+use std::collections::BTreeMap;
+use std::fmt;
+use time::OffsetDateTime;
+
+/// Secrets that the server generated for entries during a create, keyed by the
+/// uuid of the entry and the attribute the secret was stored in. The values are
+/// zeroed on drop.
+#[derive(Default)]
+pub struct GeneratedSecrets {
+    inner: BTreeMap<(Uuid, Attribute), Vec<Zeroizing<String>>>,
+}
+
+impl GeneratedSecrets {
+    pub fn get(&self, uuid: Uuid, attr: Attribute) -> Option<&[Zeroizing<String>]> {
+        self.inner.get(&(uuid, attr)).map(|v| v.as_slice())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+impl fmt::Debug for GeneratedSecrets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never display the secret values.
+        f.debug_set().entries(self.inner.keys()).finish()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CreateResult {
+    /// The uuids of the created entries, if `return_created_uuids` was requested.
+    pub uuids: Option<Vec<Uuid>>,
+    /// Secrets generated during the create, if `return_generated_secrets` was requested.
+    pub generated_secrets: GeneratedSecrets,
+}
 
 impl QueryServerWriteTransaction<'_> {
-    #[instrument(level = "debug", skip_all)]
     /// The create event is a raw, read only representation of the request
     /// that was made to us, including information about the identity
     /// performing the request.
     pub fn create(&mut self, ce: &CreateEvent) -> Result<Option<Vec<Uuid>>, OperationError> {
+        self.create_with_result(ce).map(|cr| cr.uuids)
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    /// As [Self::create], but also returns any secrets that were generated by the
+    /// server for the new entries when `return_generated_secrets` is set.
+    pub fn create_with_result(&mut self, ce: &CreateEvent) -> Result<CreateResult, OperationError> {
         if !ce.ident.is_internal() {
             security_info!(name = %ce.ident, "create initiator");
         }
@@ -20,29 +69,73 @@ impl QueryServerWriteTransaction<'_> {
         // TODO #67: Do we need limits on number of creates, or do we constraint
         // based on request size in the frontend?
 
+        // Only internal and sync imports may backdate the creation of entries.
+        if ce.override_created_at.is_some() && !(ce.ident.is_internal() || ce.ident.is_synch()) {
+            security_access!(name = %ce.ident, "create: refusing to override created at time");
+            return Err(OperationError::AccessDenied);
+        }
+
+        // Generated secrets are only ever returned to internal operations.
+        if ce.return_generated_secrets && !ce.ident.is_internal() {
+            security_access!(name = %ce.ident, "create: refusing to return generated secrets");
+            return Err(OperationError::AccessDenied);
+        }
+
+        // Only internal restore operations may bypass the replication state machine.
+        if ce.allow_state_machine_bypass && !ce.ident.is_internal() {
+            security_access!(name = %ce.ident, "create: refusing to bypass replication state machine");
+            return Err(OperationError::AccessDenied);
+        }
+
         // Copy the entries to a writeable form, this involves assigning a
         // change id so we can track what's happening.
         let candidates: Vec<Entry<EntryInit, EntryNew>> = ce.entries.clone();
 
         // Do we have rights to perform these creates?
-        // create_allow_operation
+        // create_allow_operation_explain
         let access = self.get_accesscontrols();
-        let op_allow = access
-            .create_allow_operation(ce, &candidates)
+        let denials = access
+            .create_allow_operation_explain(ce, &candidates)
             .map_err(|e| {
                 admin_error!("Failed to check create access {:?}", e);
                 e
             })?;
-        if !op_allow {
+        if !denials.is_empty() {
+            for denial in denials.iter() {
+                denial.log(&*self.cid);
+            }
             return Err(OperationError::AccessDenied);
         }
 
+        // If this is a conditional create, refuse it when a matching entry already exists.
+        // The search is performed as the identity so that only entries they can see
+        // are considered.
+        if let Some(filter) = &ce.unless_exists {
+            let f_orig = filter
+                .validate(self.get_schema())
+                .map_err(OperationError::SchemaViolation)?;
+            let f_valid = f_orig.clone().into_ignore_hidden();
+            let se = SearchEvent::new_impersonate(&ce.ident, f_valid, f_orig);
+            if !self.search(&se)?.is_empty() {
+                request_error!("create: an entry matching the create condition already exists");
+                return Err(OperationError::EntryAlreadyExists);
+            }
+        }
+
+        if ce.precheck_spn_uniqueness {
+            self.precheck_spn_uniqueness(&candidates)?;
+        }
+
         // Before we assign replication metadata, we need to assert these entries
         // are valid to create within the set of replication transitions. This
         // means they *can not* be recycled or tombstones!
-        if candidates.iter().any(|e| e.mask_recycled_ts().is_none()) {
-            warn!("Refusing to create invalid entries that are attempting to bypass replication state machine.");
-            return Err(OperationError::AccessDenied);
+        if candidates.iter().any(|e| e.is_recycled_or_tombstone()) {
+            if ce.allow_state_machine_bypass {
+                warn!("⚠️  Creating recycled or tombstone entries, bypassing the replication state machine for an internal restore.");
+            } else {
+                warn!("Refusing to create invalid entries that are attempting to bypass replication state machine.");
+                return Err(OperationError::AccessDenied);
+            }
         }
 
         // Assign our replication metadata now, since we can proceed with this operation.
@@ -51,6 +144,15 @@ impl QueryServerWriteTransaction<'_> {
             .map(|e| e.assign_cid(self.cid.clone(), &self.schema))
             .collect();
 
+        // The created at time reflects the original creation, but the replication cid
+        // must remain current.
+        if let Some(created_at) = ce.override_created_at {
+            let created_at = OffsetDateTime::UNIX_EPOCH + created_at;
+            for e in candidates.iter_mut() {
+                e.set_ava_set(&Attribute::CreatedAt, ValueSetDateTime::new(created_at));
+            }
+        }
+
         // run any pre plugins, giving them the list of mutable candidates.
         // pre-plugins are defined here in their correct order of calling!
         // I have no intent to make these dynamic or configurable.
@@ -59,21 +161,31 @@ impl QueryServerWriteTransaction<'_> {
             e
         })?;
 
-        // Now, normalise AND validate!
-        let norm_cand = candidates
-            .into_iter()
-            .map(|e| {
-                e.validate(&self.schema)
-                    .map_err(|e| {
-                        admin_error!("Schema Violation in create validate {:?}", e);
-                        OperationError::SchemaViolation(e)
-                    })
-                    .map(|e| {
-                        // Then seal the changes?
-                        e.seal(&self.schema)
-                    })
-            })
-            .collect::<Result<Vec<EntrySealedNew>, _>>()?;
+        if ce.allow_state_machine_bypass {
+            candidates
+                .iter_mut()
+                .for_each(|e| e.restore_tombstone_changestate());
+        }
+
+        // Now, normalise AND validate! Candidates commonly share their classes, so the
+        // class resolution is shared across the batch.
+        let norm_cand = {
+            let mut schema_class_cache = SchemaClassCache::default();
+            candidates
+                .into_iter()
+                .map(|e| {
+                    e.validate_cached(&self.schema, &mut schema_class_cache)
+                        .map_err(|e| {
+                            admin_error!("Schema Violation in create validate {:?}", e);
+                            OperationError::SchemaViolation(e)
+                        })
+                        .map(|e| {
+                            // Then seal the changes?
+                            e.seal(&self.schema)
+                        })
+                })
+                .collect::<Result<Vec<EntrySealedNew>, _>>()?
+        };
 
         // Run any pre-create plugins now with schema validated entries.
         // This is important for normalisation of certain types i.e. class
@@ -83,6 +195,12 @@ impl QueryServerWriteTransaction<'_> {
             e
         })?;
 
+        let generated_secrets = if ce.return_generated_secrets {
+            Self::extract_generated_secrets(ce, &norm_cand)
+        } else {
+            GeneratedSecrets::default()
+        };
+
         // We may change from ce.entries later to something else?
         let commit_cand = self.be_txn.create(&self.cid, norm_cand).map_err(|e| {
             admin_error!("betxn create failure {:?}", e);
@@ -192,11 +310,101 @@ impl QueryServerWriteTransaction<'_> {
             admin_info!("Create operation success");
         }
 
-        if ce.return_created_uuids {
-            Ok(Some(commit_cand.iter().map(|e| e.get_uuid()).collect()))
+        let uuids = if ce.return_created_uuids {
+            Some(commit_cand.iter().map(|e| e.get_uuid()).collect())
         } else {
-            Ok(None)
+            None
+        };
+
+        Ok(CreateResult {
+            uuids,
+            generated_secrets,
+        })
+    }
+
+    /// Check that the spn's the candidates will be given do not collide with each
+    /// other, or with an existing entry. The spn plugin enforces this later in the
+    /// pipeline, but this reports the specific spn before any plugins are run.
+    fn precheck_spn_uniqueness(
+        &mut self,
+        candidates: &[EntryInitNew],
+    ) -> Result<(), OperationError> {
+        let domain_name = self.get_domain_name().to_string();
+        let mut cand_spns: BTreeMap<String, PartialValue> = BTreeMap::new();
+
+        for e in candidates.iter().filter(|e| {
+            e.attribute_equality(Attribute::Class, &EntryClass::Group.into())
+                || e.attribute_equality(Attribute::Class, &EntryClass::Account.into())
+        }) {
+            // Entries that can't generate an spn are rejected by the spn plugin.
+            let Some(spn_set) = e.generate_spn(&domain_name) else {
+                continue;
+            };
+
+            for (spn, pv) in spn_set
+                .to_proto_string_clone_iter()
+                .zip(spn_set.to_partialvalue_iter())
+            {
+                if cand_spns.insert(spn.clone(), pv).is_some() {
+                    request_error!(%spn, "create: duplicate spn within create request");
+                    return Err(OperationError::Plugin(PluginError::SpnNotUnique(spn)));
+                }
+            }
+        }
+
+        if cand_spns.is_empty() {
+            return Ok(());
+        }
+
+        // Search for all candidate spn's at once.
+        let filt = filter!(f_or(
+            cand_spns
+                .values()
+                .cloned()
+                .map(|pv| f_eq(Attribute::Spn, pv))
+                .collect()
+        ));
+
+        let conflicts = self.internal_search(filt)?;
+
+        if let Some(spn) = conflicts
+            .iter()
+            .filter_map(|e| e.get_ava_set(Attribute::Spn))
+            .flat_map(|vs| vs.to_proto_string_clone_iter())
+            .find(|spn| cand_spns.contains_key(spn))
+        {
+            request_error!(%spn, "create: spn already exists");
+            return Err(OperationError::Plugin(PluginError::SpnNotUnique(spn)));
         }
+
+        Ok(())
+    }
+
+    /// Collect secret values that are present on the entries to be created, but
+    /// were not supplied in the original request. These were generated by the
+    /// server during the create.
+    fn extract_generated_secrets(
+        ce: &CreateEvent,
+        norm_cand: &[EntrySealedNew],
+    ) -> GeneratedSecrets {
+        let mut inner = BTreeMap::new();
+
+        for (orig, cand) in ce.entries.iter().zip(norm_cand.iter()) {
+            let uuid = cand.get_uuid();
+            for (attr, vs) in cand.get_ava_iter() {
+                if vs.syntax() != SyntaxType::SecretUtf8String || orig.attribute_pres(attr) {
+                    continue;
+                }
+                if let Some(secrets) = vs.as_secret_set() {
+                    inner.insert(
+                        (uuid, attr.clone()),
+                        secrets.iter().cloned().map(Zeroizing::new).collect(),
+                    );
+                }
+            }
+        }
+
+        GeneratedSecrets { inner }
     }
 
     pub fn internal_create(&mut self, entries: Vec<EntryInitNew>) -> Result<(), OperationError> {
@@ -204,6 +412,16 @@ impl QueryServerWriteTransaction<'_> {
         self.create(&ce).map(|_| ())
     }
 
+    /// ⚠️  - Create entries from a controlled restore. Unlike [Self::internal_create]
+    /// this permits recycled and tombstone entries, which keep their state.
+    pub fn internal_restore_create(
+        &mut self,
+        entries: Vec<EntryInitNew>,
+    ) -> Result<(), OperationError> {
+        let ce = CreateEvent::new_internal_restore(entries);
+        self.create(&ce).map(|_| ())
+    }
+
     pub fn impersonate_create(
         &mut self,
         ident: &Identity,
@@ -218,6 +436,7 @@ impl QueryServerWriteTransaction<'_> {
 mod tests {
     use crate::prelude::*;
     use std::sync::Arc;
+    use time::OffsetDateTime;
 
     #[qs_test]
     async fn test_create_user(server: &QueryServer) {
@@ -291,6 +510,67 @@ mod tests {
         assert!(server_txn.commit().is_ok());
     }
 
+    #[qs_test(domain_level=DOMAIN_LEVEL_11)]
+    async fn test_create_returns_generated_secrets(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let person = |name: &str, uuid: Uuid| {
+            entry_init!(
+                (Attribute::Class, EntryClass::Object.to_value()),
+                (Attribute::Class, EntryClass::Person.to_value()),
+                (Attribute::Class, EntryClass::Account.to_value()),
+                (Attribute::Name, Value::new_iname(name)),
+                (Attribute::Uuid, Value::Uuid(uuid)),
+                (Attribute::Description, Value::new_utf8s(name)),
+                (Attribute::DisplayName, Value::new_utf8s(name))
+            )
+        };
+
+        // By default, nothing is returned.
+        let ce = CreateEvent::new_internal(vec![person("testperson1", Uuid::new_v4())]);
+        let cr = server_txn.create_with_result(&ce).expect("create failure");
+        assert!(cr.generated_secrets.is_empty());
+
+        // Request the generated secrets.
+        let tuuid = Uuid::new_v4();
+        let mut ce = CreateEvent::new_internal(vec![person("testperson2", tuuid)]);
+        ce.return_generated_secrets = true;
+
+        let cr = server_txn.create_with_result(&ce).expect("create failure");
+        assert_eq!(cr.generated_secrets.len(), 1);
+        let secrets = cr
+            .generated_secrets
+            .get(tuuid, Attribute::IdVerificationEcKey)
+            .expect("generated key not returned");
+        assert_eq!(secrets.len(), 1);
+
+        // It's the same value that was stored on the entry.
+        let user = server_txn.internal_search_uuid(tuuid).expect("failed");
+        let stored = user
+            .get_ava_single_secret(Attribute::IdVerificationEcKey)
+            .expect("no id verification key");
+        assert_eq!(secrets[0].as_str(), stored);
+
+        // Secrets are not shown in debug output.
+        assert!(!format!("{:?}", cr.generated_secrets).contains(stored));
+
+        // Non-internal identities may not request the generated secrets.
+        let idm_admin = server_txn
+            .internal_search_uuid(UUID_IDM_ADMIN)
+            .expect("failed");
+        let mut ce = CreateEvent::new_impersonate_identity(
+            Identity::from_impersonate_entry_readwrite(idm_admin),
+            vec![person("testperson3", Uuid::new_v4())],
+        );
+        ce.return_generated_secrets = true;
+        assert_eq!(
+            server_txn.create_with_result(&ce).map(|_| ()),
+            Err(OperationError::AccessDenied)
+        );
+
+        assert!(server_txn.commit().is_ok());
+    }
+
     #[qs_pair_test]
     async fn test_pair_create_user(server_a: &QueryServer, server_b: &QueryServer) {
         let mut server_a_txn = server_a.write(duration_from_epoch_now()).await.unwrap();
@@ -338,4 +618,239 @@ mod tests {
         assert!(server_a_txn.commit().is_ok());
         assert!(server_b_txn.commit().is_ok());
     }
+
+    #[qs_test]
+    async fn test_create_override_created_at(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let t_uuid = Uuid::new_v4();
+        let e = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Name, Value::new_iname("testperson")),
+            (Attribute::Uuid, Value::Uuid(t_uuid)),
+            (Attribute::Description, Value::new_utf8s("testperson")),
+            (Attribute::DisplayName, Value::new_utf8s("testperson"))
+        );
+
+        // An imported entry originally created in 2001.
+        let created_at = Duration::from_secs(1_000_000_000);
+
+        let mut ce = CreateEvent::new_internal(vec![e]);
+        ce.override_created_at = Some(created_at);
+        assert!(server_txn.create(&ce).is_ok());
+
+        let entry = server_txn.internal_search_uuid(t_uuid).expect("failed");
+        assert_eq!(
+            entry.get_ava_single_datetime(Attribute::CreatedAt),
+            Some(OffsetDateTime::UNIX_EPOCH + created_at)
+        );
+        // The replication metadata is still current.
+        assert!(entry.attribute_equality(
+            Attribute::CreatedAtCid,
+            &PartialValue::new_cid(server_txn.get_txn_cid().clone())
+        ));
+    }
+
+    #[qs_test]
+    async fn test_create_override_created_at_external_denied(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let admin = server_txn.internal_search_uuid(UUID_ADMIN).expect("failed");
+
+        let e = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("testgroup")),
+            (Attribute::Description, Value::new_utf8s("testgroup"))
+        );
+
+        let mut ce = CreateEvent::new_impersonate_identity(
+            Identity::from_impersonate_entry_readwrite(admin),
+            vec![e],
+        );
+        ce.override_created_at = Some(Duration::from_secs(1_000_000_000));
+
+        assert_eq!(server_txn.create(&ce), Err(OperationError::AccessDenied));
+    }
+
+    #[qs_test]
+    async fn test_create_recycled_or_tombstone_denied(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let recycled = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Class, EntryClass::Recycled.to_value()),
+            (Attribute::Name, Value::new_iname("testgroup_recycled")),
+            (Attribute::Uuid, Value::Uuid(Uuid::new_v4()))
+        );
+        assert!(recycled.is_recycled_or_tombstone());
+
+        let tombstone = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Tombstone.to_value()),
+            (Attribute::Uuid, Value::Uuid(Uuid::new_v4()))
+        );
+        assert!(tombstone.is_recycled_or_tombstone());
+
+        let live = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("testgroup_live")),
+            (Attribute::Uuid, Value::Uuid(Uuid::new_v4()))
+        );
+        assert!(!live.is_recycled_or_tombstone());
+
+        // Even internal creates must not be able to bypass the replication state machine.
+        for e in [recycled, tombstone] {
+            let ce = CreateEvent::new_internal(vec![live.clone(), e]);
+            assert_eq!(server_txn.create(&ce), Err(OperationError::AccessDenied));
+        }
+
+        let ce = CreateEvent::new_internal(vec![live]);
+        assert!(server_txn.create(&ce).is_ok());
+    }
+
+    #[qs_test]
+    async fn test_create_state_machine_bypass(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let admin = server_txn.internal_search_uuid(UUID_ADMIN).expect("failed");
+
+        let r_uuid = Uuid::new_v4();
+        let recycled = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Class, EntryClass::Recycled.to_value()),
+            (Attribute::Name, Value::new_iname("testgroup_recycled")),
+            (Attribute::Uuid, Value::Uuid(r_uuid))
+        );
+
+        // External identities may never bypass the state machine.
+        let mut ce = CreateEvent::new_impersonate_identity(
+            Identity::from_impersonate_entry_readwrite(admin),
+            vec![recycled.clone()],
+        );
+        ce.allow_state_machine_bypass = true;
+        assert_eq!(server_txn.create(&ce), Err(OperationError::AccessDenied));
+
+        // Internal restores may.
+        assert!(server_txn.internal_restore_create(vec![recycled]).is_ok());
+
+        let entry = server_txn.internal_search_all_uuid(r_uuid).expect("failed");
+        assert!(entry.is_recycled_or_tombstone());
+        assert!(entry.get_changestate().is_live());
+    }
+
+    #[qs_test]
+    async fn test_create_state_machine_bypass_tombstone(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let t_uuid = Uuid::new_v4();
+        let tombstone = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Tombstone.to_value()),
+            (Attribute::Uuid, Value::Uuid(t_uuid))
+        );
+
+        assert!(server_txn.internal_restore_create(vec![tombstone]).is_ok());
+
+        // The restored tombstone keeps its state, rather than becoming a live entry
+        // with a tombstone class.
+        let entry = server_txn.internal_search_all_uuid(t_uuid).expect("failed");
+        assert!(entry.attribute_equality(Attribute::Class, &EntryClass::Tombstone.into()));
+        assert!(!entry.get_changestate().is_live());
+        assert!(server_txn.commit().is_ok());
+
+        let mut server_txn = server.read().await.unwrap();
+        assert!(server_txn.verify().is_empty());
+    }
+
+    #[qs_test]
+    async fn test_create_precheck_spn_uniqueness(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let person = |name: &str| {
+            entry_init!(
+                (Attribute::Class, EntryClass::Object.to_value()),
+                (Attribute::Class, EntryClass::Account.to_value()),
+                (Attribute::Class, EntryClass::Person.to_value()),
+                (Attribute::Name, Value::new_iname(name)),
+                (Attribute::Description, Value::new_utf8s(name)),
+                (Attribute::DisplayName, Value::new_utf8s(name))
+            )
+        };
+
+        let precheck = |entries| {
+            let mut ce = CreateEvent::new_internal(entries);
+            ce.precheck_spn_uniqueness = true;
+            ce
+        };
+
+        let domain_name = server_txn.get_domain_name().to_string();
+        let expect_spn = |name: &str| {
+            Err(OperationError::Plugin(PluginError::SpnNotUnique(format!(
+                "{name}@{domain_name}"
+            ))))
+        };
+
+        // Unique spns pass.
+        let ce = precheck(vec![person("testperson1"), person("testperson2")]);
+        assert!(server_txn.create(&ce).is_ok());
+
+        // A duplicate between two candidates is caught.
+        let ce = precheck(vec![person("testperson3"), person("testperson3")]);
+        assert_eq!(
+            server_txn.create(&ce).map(|_| ()),
+            expect_spn("testperson3")
+        );
+
+        // A duplicate against an existing entry is caught.
+        let ce = precheck(vec![person("testperson4"), person("testperson1")]);
+        assert_eq!(
+            server_txn.create(&ce).map(|_| ()),
+            expect_spn("testperson1")
+        );
+    }
+
+    #[qs_test]
+    async fn test_create_unless_exists(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let group = |name: &str| {
+            entry_init!(
+                (Attribute::Class, EntryClass::Object.to_value()),
+                (Attribute::Class, EntryClass::Group.to_value()),
+                (Attribute::Name, Value::new_iname(name)),
+                (Attribute::Description, Value::new_utf8s(name))
+            )
+        };
+
+        let condition = filter!(f_eq(Attribute::Name, PartialValue::new_iname("testgroup")));
+
+        // No entry matches, so the create proceeds.
+        let mut ce = CreateEvent::new_internal(vec![group("testgroup")]);
+        ce.unless_exists = Some(condition.clone());
+        assert!(server_txn.create(&ce).is_ok());
+
+        // Now a matching entry exists, so the create is refused even though the new
+        // entry would not otherwise conflict.
+        let mut ce = CreateEvent::new_internal(vec![group("testgroup_other")]);
+        ce.unless_exists = Some(condition);
+        assert_eq!(
+            server_txn.create(&ce),
+            Err(OperationError::EntryAlreadyExists)
+        );
+        assert!(server_txn
+            .internal_search(filter!(f_eq(
+                Attribute::Name,
+                PartialValue::new_iname("testgroup_other")
+            )))
+            .expect("search failure")
+            .is_empty());
+
+        assert!(server_txn.commit().is_ok());
+    }
 }
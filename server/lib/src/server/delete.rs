@@ -290,6 +290,8 @@ impl QueryServerWriteTransaction<'_> {
             self.changed_flags.insert(ChangeFlag::KEY_MATERIAL)
         }
 
+        self.mark_changed_unix(del_cand.iter());
+
         self.changed_uuid
             .extend(del_cand.iter().map(|e| e.get_uuid()));
 
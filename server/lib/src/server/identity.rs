@@ -21,6 +21,15 @@ pub enum Source {
     Ldaps(IpAddr),
 }
 
+impl Source {
+    pub fn client_ip(&self) -> Option<IpAddr> {
+        match self {
+            Source::Internal => None,
+            Source::Https(ip) | Source::Ldaps(ip) => Some(*ip),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AccessScope {
     ReadOnly,
@@ -326,6 +335,10 @@ impl Identity {
         matches!(self.origin, IdentType::Internal(_))
     }
 
+    pub fn is_synch(&self) -> bool {
+        matches!(self.origin, IdentType::Synch(_))
+    }
+
     pub fn get_uuid(&self) -> Uuid {
         match &self.origin {
             IdentType::Internal(role) => role.get_uuid(),
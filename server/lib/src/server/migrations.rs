@@ -1,5 +1,6 @@
 use crate::prelude::*;
 
+use crate::credential::Credential;
 use crate::migration_data;
 use kanidm_proto::internal::{
     DomainUpgradeCheckItem as ProtoDomainUpgradeCheckItem,
@@ -9,6 +10,28 @@ use kanidm_proto::internal::{
 
 use super::ServerPhase;
 
+/// The local credential storage version once legacy credentials have been migrated.
+const DB_CREDENTIAL_VERSION: i64 = 1;
+
+/// The outcome of a storage migration that rewrites entries in place.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MigrationStats {
+    /// Entries that were rewritten.
+    pub migrated: usize,
+    /// Entries that needed migration but could not be converted.
+    pub skipped: usize,
+}
+
+/// The domain info entry must always exist, so if it was not modified that is an error.
+fn domain_info_modified(modified: bool) -> Result<(), OperationError> {
+    if modified {
+        Ok(())
+    } else {
+        error!("Domain info entry is missing");
+        Err(OperationError::NoMatchingEntries)
+    }
+}
+
 impl QueryServer {
     #[instrument(level = "info", name = "system_initialisation", skip_all)]
     pub async fn initialise_helper(
@@ -209,13 +232,14 @@ impl QueryServer {
         // then a reload will be applied after we raise the patch level.
         if domain_patch_level < DOMAIN_TGT_PATCH_LEVEL {
             write_txn
-                .internal_modify_uuid(
+                .internal_modify_uuid_checked(
                     UUID_DOMAIN_INFO,
                     &ModifyList::new_purge_and_set(
                         Attribute::PatchLevel,
                         Value::new_uint32(DOMAIN_TGT_PATCH_LEVEL),
                     ),
                 )
+                .and_then(domain_info_modified)
                 .map(|()| {
                     warn!(
                         "Domain patch level has been raised to {}",
@@ -240,13 +264,15 @@ impl QueryServer {
             warn!("Domain Development Taint mode is enabled");
         }
         if domain_development_taint != current_devel_flag {
-            write_txn.internal_modify_uuid(
-                UUID_DOMAIN_INFO,
-                &ModifyList::new_purge_and_set(
-                    Attribute::DomainDevelopmentTaint,
-                    Value::Bool(current_devel_flag),
-                ),
-            )?;
+            write_txn
+                .internal_modify_uuid_checked(
+                    UUID_DOMAIN_INFO,
+                    &ModifyList::new_purge_and_set(
+                        Attribute::DomainDevelopmentTaint,
+                        Value::Bool(current_devel_flag),
+                    ),
+                )
+                .and_then(domain_info_modified)?;
         }
 
         // We are ready to run
@@ -269,10 +295,11 @@ impl QueryServerWriteTransaction<'_> {
         &mut self,
         to_level: u32,
     ) -> Result<(), OperationError> {
-        self.internal_modify_uuid(
+        self.internal_modify_uuid_checked(
             UUID_DOMAIN_INFO,
             &ModifyList::new_purge_and_set(Attribute::Version, Value::new_uint32(to_level)),
         )
+        .and_then(domain_info_modified)
         .and_then(|()| self.reload())
     }
 
@@ -1020,6 +1047,65 @@ impl QueryServerWriteTransaction<'_> {
         debug_assert!(r.is_ok());
         r
     }
+
+    /// Rewrite every entry that still holds a credential in a legacy format, so that it
+    /// is stored in the current format. Entries whose credentials can not be converted
+    /// are left untouched and counted as skipped. This only changes the local storage
+    /// format, so it does not generate a replication change.
+    ///
+    /// Completion is recorded in the backend, so the scan of all entries only runs once.
+    /// Returns `None` if the migration had already completed.
+    #[instrument(level = "info", skip_all)]
+    pub fn internal_migrate_all_credentials(
+        &mut self,
+    ) -> Result<Option<MigrationStats>, OperationError> {
+        if self.be_txn.get_db_credential_version()? >= DB_CREDENTIAL_VERSION {
+            debug!("Legacy credentials have already been migrated");
+            return Ok(None);
+        }
+
+        let legacy = self.be_txn.list_legacy_credentials()?;
+
+        let mut stats = MigrationStats::default();
+        let mut migrate = Vec::with_capacity(legacy.len());
+
+        for (entry_uuid, creds) in legacy {
+            if creds
+                .into_iter()
+                .all(|cred| Credential::try_from(cred).is_ok())
+            {
+                migrate.push(f_eq(Attribute::Uuid, PartialValue::Uuid(entry_uuid)));
+            } else {
+                error!(?entry_uuid, "Unable to convert legacy credential, skipping");
+                stats.skipped += 1;
+            }
+        }
+
+        self.be_txn
+            .set_db_credential_version(DB_CREDENTIAL_VERSION)?;
+
+        if migrate.is_empty() {
+            debug!("No legacy credentials require migration");
+            return Ok(Some(stats));
+        }
+
+        // Deliberately include recycled and tombstoned entries.
+        let filter = filter_all!(f_or(migrate));
+
+        // Rewriting the entries as is serialises the credentials in the current format.
+        let candidates = self.internal_search_writeable(&filter)?;
+        stats.migrated = candidates.len();
+
+        self.internal_apply_writable(candidates)?;
+
+        info!(
+            migrated = stats.migrated,
+            skipped = stats.skipped,
+            "Legacy credential migration complete"
+        );
+
+        Ok(Some(stats))
+    }
 }
 
 impl QueryServerReadTransaction<'_> {
@@ -1141,9 +1227,15 @@ impl QueryServerReadTransaction<'_> {
 #[cfg(test)]
 mod tests {
     // use super::{ProtoDomainUpgradeCheckItem, ProtoDomainUpgradeCheckStatus};
+    use super::MigrationStats;
+    use crate::be::dbentry::DbEntryVers;
+    use crate::be::dbvalue::{DbCred, DbValueSetV2};
+    use crate::credential::Credential;
     use crate::prelude::*;
     use crate::value::CredentialType;
     use crate::valueset::ValueSetCredentialType;
+    use kanidm_lib_crypto::CryptoPolicy;
+    use time::OffsetDateTime;
 
     #[qs_test]
     async fn test_init_idempotent_schema_core(server: &QueryServer) {
@@ -1603,4 +1695,96 @@ mod tests {
 
         write_txn.commit().expect("Unable to commit");
     }
+
+    #[qs_test]
+    async fn test_migrate_all_credentials(server: &QueryServer) {
+        let p = CryptoPolicy::minimum();
+        let cred = Credential::new_password_only(&p, "password", OffsetDateTime::UNIX_EPOCH)
+            .expect("Unable to create credential");
+        let cred_id = cred.uuid;
+        let v_cred = Value::new_credential("primary", cred);
+
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let mut e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("testperson1")),
+            (Attribute::Uuid, Value::Uuid(UUID_TESTPERSON_1)),
+            (Attribute::Description, Value::new_utf8s("testperson1")),
+            (Attribute::DisplayName, Value::new_utf8s("testperson1"))
+        );
+        e1.add_ava(Attribute::PrimaryCredential, v_cred);
+        assert!(server_txn.internal_create(vec![e1]).is_ok());
+        assert!(server_txn.commit().is_ok());
+
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        // Downgrade the stored credential to the v1 format.
+        server_txn
+            .be_txn
+            .danger_rewrite_entries_raw(|dbe| {
+                let DbEntryVers::V3 { attrs, .. } = &mut dbe.ent;
+                if let Some(DbValueSetV2::Credential(creds)) =
+                    attrs.get_mut(&Attribute::PrimaryCredential)
+                {
+                    for cred in creds.iter_mut() {
+                        if let DbCred::V2Password { password, uuid, .. } = &cred.data {
+                            cred.data = DbCred::Pw {
+                                password: Some(password.clone()),
+                                webauthn: None,
+                                totp: None,
+                                backup_code: None,
+                                claims: Vec::with_capacity(0),
+                                uuid: *uuid,
+                            };
+                        }
+                    }
+                }
+            })
+            .expect("Unable to rewrite entries");
+        assert!(server_txn.commit().is_ok());
+
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let legacy = server_txn
+            .be_txn
+            .list_legacy_credentials()
+            .expect("Unable to list legacy credentials");
+        assert_eq!(legacy.len(), 1);
+        assert!(legacy.iter().all(|(u, _)| *u == UUID_TESTPERSON_1));
+
+        assert_eq!(
+            server_txn.internal_migrate_all_credentials(),
+            Ok(Some(MigrationStats {
+                migrated: 1,
+                skipped: 0
+            }))
+        );
+        assert!(server_txn.commit().is_ok());
+
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        assert!(server_txn
+            .be_txn
+            .list_legacy_credentials()
+            .expect("Unable to list legacy credentials")
+            .is_empty());
+
+        // The credential is unchanged.
+        let entry = server_txn
+            .internal_search_uuid(UUID_TESTPERSON_1)
+            .expect("Unable to find test person");
+        let cred = entry
+            .get_ava_single_credential(Attribute::PrimaryCredential)
+            .expect("Missing primary credential");
+        assert_eq!(cred.uuid, cred_id);
+        assert!(cred.password_ref().is_ok());
+
+        // And a second run doesn't scan again.
+        assert_eq!(server_txn.internal_migrate_all_credentials(), Ok(None));
+
+        assert!(server_txn.commit().is_ok());
+    }
 }
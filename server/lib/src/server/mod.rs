@@ -39,14 +39,14 @@ use hashbrown::{HashMap, HashSet};
 use kanidm_proto::internal::{DomainInfo as ProtoDomainInfo, ImageValue, UiHint};
 use kanidm_proto::scim_v1::{
     server::{ScimListResponse, ScimOAuth2ClaimMap, ScimOAuth2ScopeMap, ScimReference},
-    JsonValue, ScimEntryGetQuery, ScimFilter,
+    JsonValue, ScimEntryGetQuery, ScimFilter, ScimSortOrder,
 };
 use std::collections::{BTreeMap, BTreeSet};
 use std::num::NonZeroU64;
 use std::str::FromStr;
 use std::sync::Arc;
 use time::OffsetDateTime;
-use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::sync::{broadcast, Semaphore, SemaphorePermit};
 use tracing::trace;
 
 pub(crate) mod access;
@@ -62,6 +62,8 @@ pub(crate) mod recycle;
 pub mod scim;
 pub(crate) mod utils;
 
+pub use self::migrations::MigrationStats;
+
 const RESOLVE_FILTER_CACHE_MAX: usize = 256;
 const RESOLVE_FILTER_CACHE_LOCAL: usize = 8;
 
@@ -86,6 +88,7 @@ pub struct DomainInfo {
     pub(crate) d_ldap_allow_unix_pw_bind: bool,
     pub(crate) d_allow_easter_eggs: bool,
     pub(crate) d_allow_account_recovery: bool,
+    pub(crate) d_revoked_session_retention_days: u32,
     // In future this should be image reference instead of the image itself.
     d_image: Option<ImageValue>,
 }
@@ -119,6 +122,10 @@ impl DomainInfo {
         self.d_allow_account_recovery
     }
 
+    pub fn revoked_session_retention_days(&self) -> u32 {
+        self.d_revoked_session_retention_days
+    }
+
     #[cfg(feature = "test")]
     pub fn new_test() -> CowCell<Self> {
         concread::cowcell::CowCell::new(Self {
@@ -131,6 +138,7 @@ impl DomainInfo {
             d_ldap_allow_unix_pw_bind: false,
             d_allow_easter_eggs: false,
             d_allow_account_recovery: false,
+            d_revoked_session_retention_days: DEFAULT_REVOKED_SESSION_RETENTION_DAYS,
             d_image: None,
         })
     }
@@ -169,6 +177,7 @@ pub struct QueryServer {
     dyngroup_cache: Arc<CowCell<DynGroupCache>>,
     cid_max: Arc<CowCell<Cid>>,
     key_providers: Arc<KeyProviders>,
+    unix_change_tx: broadcast::Sender<UnixChangeEvent>,
 }
 
 pub struct QueryServerReadTransaction<'a> {
@@ -187,6 +196,7 @@ pub struct QueryServerReadTransaction<'a> {
     // Future we may need this.
     // cid_max: CowCellReadTxn<Cid>,
     trim_cid: Cid,
+    curtime: Duration,
     txn_name_to_uuid: BTreeMap<String, Uuid>,
 }
 
@@ -207,9 +217,21 @@ bitflags::bitflags! {
         const APPLICATION    =              0b0000_0000_1000_0000;
         const OAUTH2_CLIENT            =    0b0000_0001_0000_0000;
         const FEATURE                  =    0b0000_0010_0000_0000;
+        const UNIX                     =    0b0000_0100_0000_0000;
     }
 }
 
+/// The number of unix change events that can be queued for a slow subscriber
+/// before it starts to miss events.
+const UNIX_CHANGE_CHANNEL_SIZE: usize = 64;
+
+/// Sent after a write transaction commits that changed or deleted posix accounts
+/// or groups, so that unix clients can drop those entries from their caches.
+#[derive(Debug, Clone)]
+pub struct UnixChangeEvent {
+    pub uuids: Vec<Uuid>,
+}
+
 pub struct QueryServerWriteTransaction<'a> {
     committed: bool,
     phase: CowCellWriteTxn<'a, ServerPhase>,
@@ -230,6 +252,9 @@ pub struct QueryServerWriteTransaction<'a> {
 
     // Store the list of changed uuids for other invalidation needs?
     pub(super) changed_uuid: HashSet<Uuid>,
+    // The subset of changed uuids that are posix accounts or groups.
+    changed_unix_uuid: HashSet<Uuid>,
+    unix_change_tx: broadcast::Sender<UnixChangeEvent>,
     _db_ticket: SemaphorePermit<'a>,
     _write_ticket: SemaphorePermit<'a>,
     resolve_filter_cache_clear: bool,
@@ -285,6 +310,9 @@ pub trait QueryServerTransaction<'a> {
 
     fn get_domain_version(&self) -> DomainVersion;
 
+    /// The time this transaction began.
+    fn get_curtime(&self) -> Duration;
+
     fn get_domain_patch_level(&self) -> u32;
 
     fn get_domain_development_taint(&self) -> bool;
@@ -451,6 +479,18 @@ pub trait QueryServerTransaction<'a> {
         }
     }
 
+    /// Count the live entries of a class from the class index. This does not
+    /// apply access controls, so callers must gate who may see the result.
+    #[instrument(level = "debug", skip_all)]
+    fn count_entries_by_class(&mut self, class: EntryClass) -> Result<u64, OperationError> {
+        self.get_be_txn()
+            .count_class(&class.to_partialvalue())
+            .map_err(|e| {
+                admin_error!(?e, "backend failure");
+                OperationError::Backend
+            })
+    }
+
     fn name_to_uuid(&mut self, name: &str) -> Result<Uuid, OperationError> {
         // There are some contexts where we will be passed an rdn or dn. We need
         // to remove these elements if they exist.
@@ -801,6 +841,10 @@ pub trait QueryServerTransaction<'a> {
                     SyntaxType::Json => Err(OperationError::InvalidAttribute("Json values can not be supplied through modification".to_string())),
                     SyntaxType::Sha256 => Err(OperationError::InvalidAttribute("SHA256 values can not be supplied through modification".to_string())),
                     SyntaxType::Message => Err(OperationError::InvalidAttribute("Message values can not be supplied through modification".to_string())),
+                    SyntaxType::ExternalId => Err(OperationError::InvalidAttribute("External ids can not be supplied through modification".to_string())),
+                    SyntaxType::MfaGrace => Err(OperationError::InvalidAttribute("MFA grace windows can not be supplied through modification".to_string())),
+                    SyntaxType::BreakGlass => Err(OperationError::InvalidAttribute("Break-glass codes can not be supplied through modification".to_string())),
+                    SyntaxType::ExtensionJson => Err(OperationError::InvalidAttribute("Extension json values can not be supplied through modification".to_string())),
                 }
             }
             None => {
@@ -954,6 +998,18 @@ pub trait QueryServerTransaction<'a> {
                     SyntaxType::Message => Err(OperationError::InvalidAttribute(
                         "Message values can not be validated by this interface".to_string(),
                     )),
+                    SyntaxType::ExternalId => Err(OperationError::InvalidAttribute(
+                        "External ids can not be validated by this interface".to_string(),
+                    )),
+                    SyntaxType::MfaGrace => Err(OperationError::InvalidAttribute(
+                        "MFA grace windows can not be validated by this interface".to_string(),
+                    )),
+                    SyntaxType::BreakGlass => Err(OperationError::InvalidAttribute(
+                        "Break-glass codes can not be validated by this interface".to_string(),
+                    )),
+                    SyntaxType::ExtensionJson => Err(OperationError::InvalidAttribute(
+                        "Extension json values can not be validated by this interface".to_string(),
+                    )),
                 }
             }
             None => {
@@ -994,6 +1050,7 @@ pub trait QueryServerTransaction<'a> {
                              claim,
                              join_char,
                              values,
+                             typed_values,
                          }| {
                             self.uuid_to_spn(group_uuid)
                                 .and_then(|maybe_value| {
@@ -1005,6 +1062,7 @@ pub trait QueryServerTransaction<'a> {
                                     claim,
                                     join_char,
                                     values,
+                                    typed_values,
                                 })
                         },
                     )
@@ -1144,6 +1202,7 @@ pub trait QueryServerTransaction<'a> {
                          claim,
                          join_char,
                          claim_values,
+                         typed_claim_values,
                      }| {
                         let group_uuid =
                             self.name_to_uuid(group_name.as_str()).unwrap_or_else(|_| {
@@ -1159,6 +1218,7 @@ pub trait QueryServerTransaction<'a> {
                             claim,
                             join_char,
                             claim_values,
+                            typed_claim_values,
                         }
                     },
                 ));
@@ -1462,6 +1522,10 @@ impl<'a> QueryServerTransaction<'a> for QueryServerReadTransaction<'a> {
         self.d_info.d_vers
     }
 
+    fn get_curtime(&self) -> Duration {
+        self.curtime
+    }
+
     fn get_domain_patch_level(&self) -> u32 {
         self.d_info.d_patch_level
     }
@@ -1533,6 +1597,13 @@ impl QueryServerReadTransaction<'_> {
             return idx_errs;
         }
 
+        //  * The entry cache agrees with the database.
+        let cache_errs = self.get_be_txn().verify_entry_cache();
+
+        if !cache_errs.is_empty() {
+            return cache_errs;
+        }
+
         // If anything error to this point we can't trust the verifications below. From
         // here we can just amass results.
         let mut results = Vec::with_capacity(0);
@@ -1640,6 +1711,8 @@ impl QueryServerReadTransaction<'_> {
 
         let f_valid = f_intent_valid.clone().into_ignore_hidden();
 
+        let start_offset = query.zero_based_offset();
+
         let r_attrs = query
             .attributes
             .map(|attr_set| attr_set.into_iter().collect());
@@ -1661,12 +1734,16 @@ impl QueryServerReadTransaction<'_> {
         // works and it's viable on small datasets. We will make this use indexes
         // in the future!
 
-        // First, sort if any.
+        // First, sort if any. Entries that lack the sort attribute always sort
+        // last, regardless of the requested order.
         if let Some(sort_attr) = query.sort_by {
+            let descending = matches!(query.sort_order, Some(ScimSortOrder::Descending));
+
             result_set.sort_unstable_by(|entry_left, entry_right| {
                 let left = entry_left.get_ava_set(&sort_attr);
                 let right = entry_right.get_ava_set(&sort_attr);
                 match (left, right) {
+                    (Some(left), Some(right)) if descending => right.cmp(left),
                     (Some(left), Some(right)) => left.cmp(right),
                     (Some(_), None) => std::cmp::Ordering::Less,
                     (None, Some(_)) => std::cmp::Ordering::Greater,
@@ -1680,12 +1757,7 @@ impl QueryServerReadTransaction<'_> {
             let count: u64 = count.get();
             // User wants pagination. Count is how many elements they want.
 
-            let start_index: u64 = query
-                .start_index
-                .map(|non_zero_index|
-                    // SCIM pagination is 1 indexed, not 0.
-                    non_zero_index.get() - 1)
-                .unwrap_or_default();
+            let start_index: u64 = start_offset;
 
             // First, check that our start_index is valid.
             if start_index as usize > result_set.len() {
@@ -1815,6 +1887,10 @@ impl<'a> QueryServerTransaction<'a> for QueryServerWriteTransaction<'a> {
         self.d_info.d_vers
     }
 
+    fn get_curtime(&self) -> Duration {
+        self.curtime
+    }
+
     fn get_domain_patch_level(&self) -> u32 {
         self.d_info.d_patch_level
     }
@@ -1878,6 +1954,7 @@ impl QueryServer {
             d_ldap_allow_unix_pw_bind: false,
             d_allow_easter_eggs: false,
             d_allow_account_recovery: false,
+            d_revoked_session_retention_days: DEFAULT_REVOKED_SESSION_RETENTION_DAYS,
             d_image: None,
         }));
 
@@ -1911,6 +1988,8 @@ impl QueryServer {
         debug_assert!(pool_size > 0);
         let read_ticket_pool = std::cmp::max(pool_size - 1, 1);
 
+        let (unix_change_tx, _) = broadcast::channel(UNIX_CHANGE_CHANNEL_SIZE);
+
         Ok(QueryServer {
             phase,
             d_info,
@@ -1926,9 +2005,15 @@ impl QueryServer {
             dyngroup_cache,
             cid_max,
             key_providers,
+            unix_change_tx,
         })
     }
 
+    /// Subscribe to notifications of committed changes to posix accounts and groups.
+    pub fn subscribe_unix_changes(&self) -> broadcast::Receiver<UnixChangeEvent> {
+        self.unix_change_tx.subscribe()
+    }
+
     pub fn try_quiesce(&self) {
         self.be.try_quiesce();
         self.accesscontrols.try_quiesce();
@@ -2017,6 +2102,7 @@ impl QueryServer {
             _read_ticket: read_ticket,
             resolve_filter_cache: self.resolve_filter_cache.read(),
             trim_cid,
+            curtime: duration_from_epoch_now(),
             txn_name_to_uuid: Default::default(),
         })
     }
@@ -2120,6 +2206,8 @@ impl QueryServer {
             accesscontrols: self.accesscontrols.write(),
             changed_flags: ChangeFlag::empty(),
             changed_uuid: HashSet::new(),
+            changed_unix_uuid: HashSet::new(),
+            unix_change_tx: self.unix_change_tx.clone(),
             _db_ticket: db_ticket,
             _write_ticket: write_ticket,
             resolve_filter_cache: self.resolve_filter_cache.read(),
@@ -2183,10 +2271,6 @@ impl<'a> QueryServerWriteTransaction<'a> {
         Ok(())
     }
 
-    pub(crate) fn get_curtime(&self) -> Duration {
-        self.curtime
-    }
-
     pub(crate) fn get_curtime_odt(&self) -> OffsetDateTime {
         OffsetDateTime::UNIX_EPOCH + self.curtime
     }
@@ -2557,6 +2641,10 @@ impl<'a> QueryServerWriteTransaction<'a> {
             .get_ava_single_bool(Attribute::DomainAllowAccountRecovery)
             .unwrap_or_default();
 
+        let domain_revoked_session_retention_days = domain_info
+            .get_ava_single_uint32(Attribute::DomainRevokedSessionRetentionDays)
+            .unwrap_or(DEFAULT_REVOKED_SESSION_RETENTION_DAYS);
+
         // We have to set the domain version here so that features which check for it
         // will now see it's been increased. This also prevents recursion during reloads
         // inside of a domain migration.
@@ -2570,6 +2658,7 @@ impl<'a> QueryServerWriteTransaction<'a> {
         mut_d_info.d_devel_taint = domain_info_devel_taint;
         mut_d_info.d_allow_easter_eggs = domain_allow_easter_eggs;
         mut_d_info.d_allow_account_recovery = domain_allow_account_recovery;
+        mut_d_info.d_revoked_session_retention_days = domain_revoked_session_retention_days;
 
         debug!(?mut_d_info);
 
@@ -2858,6 +2947,26 @@ impl<'a> QueryServerWriteTransaction<'a> {
         self.changed_flags.contains(ChangeFlag::OAUTH2_CLIENT)
     }
 
+    /// Record any posix accounts or groups in this set of entries so that unix
+    /// clients can be notified once this transaction commits.
+    pub(super) fn mark_changed_unix<'b>(
+        &mut self,
+        entries: impl Iterator<Item = &'b EntrySealedCommitted>,
+    ) {
+        self.changed_unix_uuid.extend(
+            entries
+                .filter(|e| {
+                    e.attribute_equality(Attribute::Class, &EntryClass::PosixAccount.into())
+                        || e.attribute_equality(Attribute::Class, &EntryClass::PosixGroup.into())
+                })
+                .map(|e| e.get_uuid()),
+        );
+
+        if !self.changed_unix_uuid.is_empty() {
+            self.changed_flags.insert(ChangeFlag::UNIX)
+        }
+    }
+
     /// Indicate that we are about to re-bootstrap this server. You should ONLY
     /// call this during a replication refresh!!!
     pub(crate) fn set_phase_bootstrap(&mut self) {
@@ -2987,6 +3096,8 @@ impl<'a> QueryServerWriteTransaction<'a> {
             trim_cid: _,
             changed_flags,
             changed_uuid: _,
+            changed_unix_uuid,
+            unix_change_tx,
             resolve_filter_cache: _,
             resolve_filter_cache_clear,
             mut resolve_filter_cache_write,
@@ -3022,7 +3133,16 @@ impl<'a> QueryServerWriteTransaction<'a> {
             .map(|_| dyngroup_cache.commit())
             .and_then(|_| key_providers.commit())
             .and_then(|_| accesscontrols.commit())
-            .and_then(|_| be_txn.commit())
+            .and_then(|_| be_txn.commit())?;
+
+        if changed_flags.contains(ChangeFlag::UNIX) {
+            // An error here only means there are no subscribers right now.
+            let _ = unix_change_tx.send(UnixChangeEvent {
+                uuids: changed_unix_uuid.into_iter().collect(),
+            });
+        }
+
+        Ok(())
     }
 
     pub(crate) fn get_txn_cid(&self) -> &Cid {
@@ -3035,9 +3155,11 @@ mod tests {
     use crate::prelude::*;
     use kanidm_proto::scim_v1::{
         server::{ScimListResponse, ScimReference},
-        JsonValue, ScimEntryGetQuery, ScimFilter,
+        JsonValue, ScimEntryGetQuery, ScimFilter, ScimSortOrder,
     };
+    use std::collections::BTreeSet;
     use std::num::NonZeroU64;
+    use time::OffsetDateTime;
 
     #[qs_test]
     async fn test_name_to_uuid(server: &QueryServer) {
@@ -3441,6 +3563,60 @@ mod tests {
                 panic!("expected EntryReferences, actual {members_scim:?}");
             }
         }
+
+        // The meta is populated, with the version of the returned attributes.
+        let meta = scim_entry.header.meta.as_ref().expect("meta is not set");
+        assert_eq!(meta.resource_type, "Group");
+        assert!(meta.created <= meta.last_modified);
+        assert_eq!(
+            meta.location.as_str(),
+            format!("urn:uuid:{UUID_IDM_PEOPLE_SELF_NAME_WRITE}")
+        );
+        assert_eq!(scim_entry.version(), Some(meta.version.as_str()));
+        assert_eq!(
+            meta.version,
+            kanidm_proto::scim_v1::ScimMeta::version_of(&scim_entry.attrs)
+                .expect("Failed to compute version")
+        );
+    }
+
+    #[qs_test]
+    async fn test_scim_entry_active_requires_validity(server: &QueryServer) {
+        let ct = duration_from_epoch_now();
+        let mut server_txn = server.write(ct).await.unwrap();
+
+        let person_uuid = Uuid::new_v4();
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("testperson")),
+            (Attribute::Uuid, Value::Uuid(person_uuid)),
+            (Attribute::DisplayName, Value::new_utf8s("testperson")),
+            (
+                Attribute::AccountExpire,
+                Value::DateTime(OffsetDateTime::UNIX_EPOCH + ct - Duration::from_secs(60))
+            )
+        );
+        assert!(server_txn.internal_create(vec![e1]).is_ok());
+
+        let entry = server_txn.internal_search_uuid(person_uuid).unwrap();
+
+        // The expiry can't be read, so active is not derived from what remains.
+        let allowed: BTreeSet<_> = [Attribute::Class, Attribute::Name].into();
+        let reduced = entry.reduce_attributes(&allowed, None);
+        let scim_entry = reduced.to_scim_kanidm(&mut server_txn).unwrap();
+        assert!(!scim_entry.attrs.contains_key(&Attribute::Active));
+
+        // When it can be read, the expired account is inactive.
+        let allowed: BTreeSet<_> =
+            [Attribute::Class, Attribute::Name, Attribute::AccountExpire].into();
+        let reduced = entry.reduce_attributes(&allowed, None);
+        let scim_entry = reduced.to_scim_kanidm(&mut server_txn).unwrap();
+        assert!(matches!(
+            scim_entry.attrs.get(&Attribute::Active),
+            Some(ScimValueKanidm::Bool(false))
+        ));
     }
 
     #[qs_test]
@@ -3663,4 +3839,111 @@ mod tests {
         assert_eq!(testgroup_name_0, "testgroup2");
         assert_eq!(testgroup_name_1, "testgroup3");
     }
+
+    #[qs_test]
+    async fn test_scim_basic_search_ext_query_with_sort_order(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        // Chosen so that numeric and string ordering disagree.
+        for (i, gid) in [
+            (1, Some(9000)),
+            (2, Some(20000)),
+            (3, Some(1000)),
+            (4, None),
+        ] {
+            let mut e1 = entry_init!(
+                (Attribute::Class, EntryClass::Object.to_value()),
+                (Attribute::Class, EntryClass::Group.to_value()),
+                (
+                    Attribute::Name,
+                    Value::new_iname(format!("testgroup{i}").as_str())
+                )
+            );
+            if let Some(gid) = gid {
+                e1.add_ava(Attribute::Class, EntryClass::PosixGroup.to_value());
+                e1.add_ava(Attribute::GidNumber, Value::Uint32(gid));
+            }
+            assert!(server_txn.internal_create(vec![e1]).is_ok());
+        }
+
+        assert!(server_txn.commit().is_ok());
+
+        let mut server_txn = server.read().await.unwrap();
+
+        let idm_admin_entry = server_txn.internal_search_uuid(UUID_IDM_ADMIN).unwrap();
+        let idm_admin_ident = Identity::from_impersonate_entry_readwrite(idm_admin_entry);
+
+        let filter = ScimFilter::And(
+            Box::new(ScimFilter::Equal(
+                Attribute::Class.into(),
+                EntryClass::Group.into(),
+            )),
+            Box::new(ScimFilter::StartsWith(
+                Attribute::Name.into(),
+                JsonValue::String("testgroup".into()),
+            )),
+        );
+
+        let names = |base: ScimListResponse| -> Vec<String> {
+            base.resources
+                .iter()
+                .map(|entry| match entry.attrs.get(&Attribute::Name) {
+                    Some(ScimValueKanidm::String(name)) => name.clone(),
+                    _ => panic!("Invalid data in attribute."),
+                })
+                .collect()
+        };
+
+        let base: ScimListResponse = server_txn
+            .scim_search_ext(
+                idm_admin_ident.clone(),
+                filter.clone(),
+                ScimEntryGetQuery {
+                    sort_by: Some(Attribute::Name),
+                    sort_order: Some(ScimSortOrder::Descending),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        assert_eq!(
+            names(base),
+            vec!["testgroup4", "testgroup3", "testgroup2", "testgroup1"]
+        );
+
+        // Numeric comparison, and the entry without a gidnumber is last.
+        let base: ScimListResponse = server_txn
+            .scim_search_ext(
+                idm_admin_ident.clone(),
+                filter.clone(),
+                ScimEntryGetQuery {
+                    sort_by: Some(Attribute::GidNumber),
+                    sort_order: Some(ScimSortOrder::Ascending),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        assert_eq!(
+            names(base),
+            vec!["testgroup3", "testgroup1", "testgroup2", "testgroup4"]
+        );
+
+        let base: ScimListResponse = server_txn
+            .scim_search_ext(
+                idm_admin_ident,
+                filter,
+                ScimEntryGetQuery {
+                    sort_by: Some(Attribute::GidNumber),
+                    sort_order: Some(ScimSortOrder::Descending),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        assert_eq!(
+            names(base),
+            vec!["testgroup2", "testgroup1", "testgroup3", "testgroup4"]
+        );
+    }
 }
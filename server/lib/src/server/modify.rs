@@ -309,6 +309,12 @@ impl QueryServerWriteTransaction<'_> {
             self.changed_flags.insert(ChangeFlag::KEY_MATERIAL)
         }
 
+        self.mark_changed_unix(
+            norm_cand
+                .iter()
+                .chain(pre_candidates.iter().map(|e| e.as_ref())),
+        );
+
         self.changed_uuid.extend(
             norm_cand
                 .iter()
@@ -497,6 +503,12 @@ impl QueryServerWriteTransaction<'_> {
             self.changed_flags.insert(ChangeFlag::DOMAIN)
         }
 
+        self.mark_changed_unix(
+            norm_cand
+                .iter()
+                .chain(pre_candidates.iter().map(|e| e.as_ref())),
+        );
+
         self.changed_uuid.extend(
             norm_cand
                 .iter()
@@ -544,6 +556,28 @@ impl QueryServerWriteTransaction<'_> {
         self.modify(&me)
     }
 
+    /// As [Self::internal_modify_uuid], but reports if the target entry existed. Returns
+    /// `Ok(true)` if the entry was found and modified, and `Ok(false)` if no entry matched.
+    pub fn internal_modify_uuid_checked(
+        &mut self,
+        target_uuid: Uuid,
+        modlist: &ModifyList<ModifyInvalid>,
+    ) -> Result<bool, OperationError> {
+        let filter = filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(target_uuid)));
+        let f_valid = filter
+            .validate(self.get_schema())
+            .map_err(OperationError::SchemaViolation)?;
+        let m_valid = modlist
+            .validate(self.get_schema())
+            .map_err(OperationError::SchemaViolation)?;
+        let me = ModifyEvent::new_internal(f_valid, m_valid);
+
+        match self.modify_pre_apply(&me)? {
+            Some(mp) => self.modify_apply(mp).map(|()| true),
+            None => Ok(false),
+        }
+    }
+
     pub fn impersonate_modify_valid(
         &mut self,
         f_valid: Filter<FilterValid>,
@@ -996,4 +1030,121 @@ mod tests {
             Err(OperationError::AccessDenied)
         );
     }
+
+    #[qs_test]
+    async fn test_internal_modify_uuid_checked(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let t_uuid = Uuid::new_v4();
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("testperson1")),
+            (Attribute::Uuid, Value::Uuid(t_uuid)),
+            (Attribute::Description, Value::new_utf8s("testperson1")),
+            (Attribute::DisplayName, Value::new_utf8s("testperson1"))
+        );
+        assert!(server_txn.internal_create(vec![e1]).is_ok());
+
+        let modlist =
+            ModifyList::new_purge_and_set(Attribute::Description, Value::new_utf8s("modified"));
+
+        assert_eq!(
+            server_txn.internal_modify_uuid_checked(t_uuid, &modlist),
+            Ok(true)
+        );
+        let entry = server_txn.internal_search_uuid(t_uuid).expect("failed");
+        assert_eq!(
+            entry.get_ava_single_utf8(Attribute::Description),
+            Some("modified")
+        );
+
+        // A stale uuid is reported rather than silently ignored.
+        assert_eq!(
+            server_txn.internal_modify_uuid_checked(Uuid::new_v4(), &modlist),
+            Ok(false)
+        );
+
+        // Other failures are still errors.
+        assert!(server_txn
+            .internal_modify_uuid_checked(
+                t_uuid,
+                &ModifyList::new_purge_and_set(Attribute::NonExist, Value::new_utf8s("x")),
+            )
+            .is_err());
+    }
+
+    #[qs_test]
+    async fn test_modify_unix_change_event(server: &QueryServer) {
+        let mut unix_rx = server.subscribe_unix_changes();
+
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let posix_uuid = Uuid::new_v4();
+        let other_uuid = Uuid::new_v4();
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Class, EntryClass::PosixAccount.to_value()),
+            (Attribute::Name, Value::new_iname("testperson1")),
+            (Attribute::Uuid, Value::Uuid(posix_uuid)),
+            (Attribute::DisplayName, Value::new_utf8s("testperson1"))
+        );
+        let e2 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("testperson2")),
+            (Attribute::Uuid, Value::Uuid(other_uuid)),
+            (Attribute::DisplayName, Value::new_utf8s("testperson2"))
+        );
+        assert!(server_txn.internal_create(vec![e1, e2]).is_ok());
+        assert!(server_txn.commit().is_ok());
+
+        // Discard anything raised while the entries were being set up.
+        while unix_rx.try_recv().is_ok() {}
+
+        // Changes to non posix entries are not sent.
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+        assert!(server_txn
+            .internal_modify_uuid(
+                other_uuid,
+                &ModifyList::new_purge_and_set(Attribute::DisplayName, Value::new_utf8s("changed")),
+            )
+            .is_ok());
+        assert!(server_txn.commit().is_ok());
+        assert!(unix_rx.try_recv().is_err());
+
+        // Changing the shell of a posix account is.
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+        assert!(server_txn
+            .internal_modify_uuid(
+                posix_uuid,
+                &ModifyList::new_purge_and_set(Attribute::LoginShell, Value::new_iutf8("/bin/zsh")),
+            )
+            .is_ok());
+        assert!(server_txn.commit().is_ok());
+        let event = unix_rx.try_recv().expect("No unix change event was sent");
+        assert_eq!(event.uuids, vec![posix_uuid]);
+
+        // An uncommitted change is not sent.
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+        assert!(server_txn
+            .internal_modify_uuid(
+                posix_uuid,
+                &ModifyList::new_purge_and_set(Attribute::LoginShell, Value::new_iutf8("/bin/sh")),
+            )
+            .is_ok());
+        drop(server_txn);
+        assert!(unix_rx.try_recv().is_err());
+
+        // As is deleting one.
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+        assert!(server_txn.internal_delete_uuid(posix_uuid).is_ok());
+        assert!(server_txn.commit().is_ok());
+        let event = unix_rx.try_recv().expect("No unix change event was sent");
+        assert_eq!(event.uuids, vec![posix_uuid]);
+    }
 }
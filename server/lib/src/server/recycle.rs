@@ -2,6 +2,7 @@ use super::modify::ModifyPartial;
 use crate::event::ReviveRecycledEvent;
 use crate::prelude::*;
 use crate::server::Plugins;
+use crate::value::SessionState;
 use std::collections::BTreeMap;
 
 impl QueryServerWriteTransaction<'_> {
@@ -96,6 +97,83 @@ impl QueryServerWriteTransaction<'_> {
         Ok(entries.len())
     }
 
+    /// The cid before which revoked sessions may be purged, as determined by the domain
+    /// revoked session retention policy.
+    pub fn revoked_session_retention_cid(&self) -> Result<Cid, OperationError> {
+        let retention_days = self.d_info.revoked_session_retention_days();
+        self.cid
+            .sub_secs(u64::from(retention_days) * 86400)
+            .inspect_err(|err| {
+                error!(?err, "Unable to generate cid for revoked session retention");
+            })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    /// Remove revoked sessions that were revoked before `older_than`. To ensure that the
+    /// revocation has replicated, this is bounded by the changelog trim cid in the same
+    /// manner as tombstone purging.
+    pub fn purge_revoked_sessions(&mut self, older_than: Cid) -> Result<usize, OperationError> {
+        let purge_cid = std::cmp::min(older_than, self.trim_cid().clone());
+
+        let is_purgeable = |state: &SessionState| matches!(state, SessionState::RevokedAt(cid) if *cid < purge_cid);
+
+        let candidates = self.internal_search(filter_all!(f_or!([
+            f_pres(Attribute::UserAuthTokenSession),
+            f_pres(Attribute::OAuth2Session)
+        ])))?;
+
+        let pre_candidates: Vec<_> = candidates
+            .into_iter()
+            .filter(|entry| {
+                entry
+                    .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+                    .map(|sessions| sessions.values().any(|s| is_purgeable(&s.state)))
+                    .unwrap_or_default()
+                    || entry
+                        .get_ava_as_oauth2session_map(Attribute::OAuth2Session)
+                        .map(|sessions| sessions.values().any(|s| is_purgeable(&s.state)))
+                        .unwrap_or_default()
+            })
+            .collect();
+
+        if pre_candidates.is_empty() {
+            debug!("No revoked sessions to purge - purge operation success");
+            return Ok(0);
+        }
+
+        // Invalidating the entry trims revoked sessions prior to the purge cid.
+        let norm_cand: Vec<_> = pre_candidates
+            .iter()
+            .map(|e| {
+                e.as_ref()
+                    .clone()
+                    .invalidate(self.cid.clone(), &purge_cid)
+                    .validate(&self.schema)
+                    .map_err(|e| {
+                        error!(
+                            "Schema Violation in purge_revoked_sessions validate: {:?}",
+                            e
+                        );
+                        OperationError::SchemaViolation(e)
+                    })
+                    .map(|e| e.seal(&self.schema))
+            })
+            .collect::<Result<_, _>>()?;
+
+        let touched = norm_cand.len();
+
+        self.be_txn
+            .modify(&self.cid, &pre_candidates, &norm_cand)
+            .map_err(|e| {
+                error!("Purge revoked sessions operation failed (backend), {:?}", e);
+                e
+            })
+            .map(|_| {
+                info!("Purge revoked sessions operation success");
+                touched
+            })
+    }
+
     #[instrument(level = "debug", skip_all)]
     pub fn revive_recycled(&mut self, re: &ReviveRecycledEvent) -> Result<(), OperationError> {
         // Revive an entry to live. This is a specialised function, and draws a lot of
@@ -364,6 +442,7 @@ mod tests {
     use crate::server::ModifyEvent;
     use crate::server::SearchEvent;
     use crate::server::ValueSetMessage;
+    use crate::value::{AuthType, Session, SessionScope, SessionState};
     use kanidm_proto::v1::OutboundMessage;
     use time::OffsetDateTime;
 
@@ -975,4 +1054,94 @@ mod tests {
 
         server_txn.commit().unwrap();
     }
+
+    #[qs_test]
+    async fn test_purge_revoked_sessions(server: &QueryServer) {
+        let time_p1 = duration_from_epoch_now();
+        let time_p2 = time_p1 + Duration::from_secs(1);
+        let time_p3 = time_p1 + Duration::from_secs(CHANGELOG_MAX_AGE * 2);
+
+        let tuuid = Uuid::new_v4();
+        let session_id = Uuid::new_v4();
+
+        let mut server_txn = server.write(time_p1).await.unwrap();
+
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Name, Value::new_iname("testperson1")),
+            (Attribute::Uuid, Value::Uuid(tuuid)),
+            (Attribute::Description, Value::new_utf8s("testperson1")),
+            (Attribute::DisplayName, Value::new_utf8s("testperson1")),
+            (
+                Attribute::UserAuthTokenSession,
+                Value::Session(
+                    session_id,
+                    Session {
+                        label: "label".to_string(),
+                        state: SessionState::NeverExpires,
+                        issued_at: OffsetDateTime::UNIX_EPOCH + time_p1,
+                        issued_by: IdentityId::User(tuuid),
+                        cred_id: Uuid::new_v4(),
+                        scope: SessionScope::ReadOnly,
+                        type_: AuthType::Passkey,
+                        ext_metadata: Default::default(),
+                        client_ip: None,
+                        last_seen: None,
+                    },
+                )
+            )
+        );
+
+        let ce = CreateEvent::new_internal(vec![e1]);
+        assert!(server_txn.create(&ce).is_ok());
+
+        // Revoke the session.
+        server_txn
+            .internal_modify_uuid(
+                tuuid,
+                &ModifyList::new_remove(
+                    Attribute::UserAuthTokenSession,
+                    PartialValue::Refer(session_id),
+                ),
+            )
+            .expect("Failed to revoke session");
+
+        assert!(server_txn.commit().is_ok());
+
+        let session_state = |server_txn: &mut QueryServerWriteTransaction| {
+            server_txn
+                .internal_search_uuid(tuuid)
+                .expect("failed")
+                .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+                .and_then(|sessions| sessions.get(&session_id))
+                .map(|session| session.state.clone())
+        };
+
+        // Even if requested, the revocation is too recent to be purged since it may not
+        // have replicated yet.
+        let mut server_txn = server.write(time_p2).await.unwrap();
+        let older_than = server_txn.get_txn_cid().clone();
+        assert_eq!(server_txn.purge_revoked_sessions(older_than), Ok(0));
+        assert!(matches!(
+            session_state(&mut server_txn),
+            Some(SessionState::RevokedAt(_))
+        ));
+        assert!(server_txn.commit().is_ok());
+
+        let mut server_txn = server.write(time_p3).await.unwrap();
+        // The default retention period has not yet passed.
+        let older_than = server_txn
+            .revoked_session_retention_cid()
+            .expect("Unable to determine retention cid");
+        assert_eq!(server_txn.purge_revoked_sessions(older_than), Ok(0));
+        assert!(session_state(&mut server_txn).is_some());
+
+        // Now past the replication window, the session is removed.
+        let older_than = server_txn.get_txn_cid().clone();
+        assert_eq!(server_txn.purge_revoked_sessions(older_than), Ok(1));
+        assert!(session_state(&mut server_txn).is_none());
+        assert!(server_txn.commit().is_ok());
+    }
 }
@@ -39,8 +39,10 @@ impl ScimEntryPutEvent {
     ) -> Result<Self, OperationError> {
         let target = entry.id;
 
-        let attrs = entry
-            .attrs
+        let mut entry_attrs = entry.attrs;
+        let active = entry_attrs.remove(&Attribute::Active);
+
+        let mut attrs: BTreeMap<Attribute, Option<ValueSet>> = entry_attrs
             .into_iter()
             .map(|(attr, json_value)| {
                 qs.resolve_scim_json_put(&attr, json_value)
@@ -48,6 +50,37 @@ impl ScimEntryPutEvent {
             })
             .collect::<Result<_, _>>()?;
 
+        // Active is not stored, it is derived from account_expire, so it is mapped
+        // to a change of that attribute instead.
+        if let Some(json_value) = active {
+            if attrs.contains_key(&Attribute::AccountExpire) {
+                return Err(OperationError::SC0034ActiveConflictsWithAccountExpire);
+            }
+
+            let active: bool = json_value
+                .and_then(|value| serde_json::from_value(value).ok())
+                .ok_or(OperationError::SC0005BoolSyntaxInvalid)?;
+
+            let now = qs.get_curtime_odt();
+
+            let account_expire = if active {
+                // Only clear an expiry that has already passed, a future expiry is
+                // still valid for an active account. It is always written back so
+                // that modify access to account_expire is checked either way.
+                let target_entry = qs.internal_search_uuid(target)?;
+                target_entry
+                    .get_ava_single_datetime(Attribute::AccountExpire)
+                    .filter(|expire| *expire > now)
+            } else {
+                Some(now)
+            };
+
+            attrs.insert(
+                Attribute::AccountExpire,
+                account_expire.map(|expire| ValueSetDateTime::new(expire) as ValueSet),
+            );
+        }
+
         let query = entry.query;
 
         Ok(ScimEntryPutEvent {
@@ -223,6 +256,11 @@ impl QueryServerWriteTransaction<'_> {
             ident,
             entries: vec![entry],
             return_created_uuids: true,
+            return_generated_secrets: false,
+            override_created_at: None,
+            unless_exists: None,
+            allow_state_machine_bypass: false,
+            precheck_spn_uniqueness: false,
         };
 
         let changed_uuids = self.create(&create_event)?;
@@ -442,6 +480,22 @@ impl QueryServerWriteTransaction<'_> {
             SyntaxType::Message => Err(OperationError::InvalidAttribute(
                 "Message values are not able to be set.".to_string(),
             )),
+            // Only the sync layer links entries to external systems.
+            SyntaxType::ExternalId => Err(OperationError::InvalidAttribute(
+                "External ids are not able to be set.".to_string(),
+            )),
+            // Grace windows are granted by the server when MFA is required.
+            SyntaxType::MfaGrace => Err(OperationError::InvalidAttribute(
+                "MFA grace windows are not able to be set.".to_string(),
+            )),
+            // Codes are generated by the server so that only their hashes are held.
+            SyntaxType::BreakGlass => Err(OperationError::InvalidAttribute(
+                "Break-glass codes are not able to be set.".to_string(),
+            )),
+            // Extension data is only supplied by sync agents.
+            SyntaxType::ExtensionJson => Err(OperationError::InvalidAttribute(
+                "Extension json values are not able to be set.".to_string(),
+            )),
             // Can't be set currently as these are only internally generated for key-id's
             // SyntaxType::HexString => ValueSetHexString::from_scim_json_put(value),
             SyntaxType::HexString => Err(OperationError::InvalidAttribute(
@@ -791,6 +845,115 @@ mod tests {
         assert!(!updated_entry.attrs.contains_key(&Attribute::Member));
     }
 
+    #[qs_test]
+    async fn scim_put_active(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let idm_admin_entry = server_txn.internal_search_uuid(UUID_IDM_ADMIN).unwrap();
+
+        let idm_admin_ident = Identity::from_impersonate_entry_readwrite(idm_admin_entry);
+
+        let person_uuid = Uuid::new_v4();
+
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("testperson")),
+            (Attribute::DisplayName, Value::new_utf8s("Test Person")),
+            (Attribute::Uuid, Value::Uuid(person_uuid))
+        );
+
+        assert!(server_txn.internal_create(vec![e1]).is_ok());
+
+        let now = server_txn.get_curtime_odt();
+
+        let put_active = |server_txn: &mut QueryServerWriteTransaction, attrs| {
+            let put = ScimEntryPutKanidm {
+                id: person_uuid,
+                attrs,
+            };
+            let put_generic = put.try_into().unwrap();
+            ScimEntryPutEvent::try_from(idm_admin_ident.clone(), put_generic, server_txn)
+                .and_then(|put_event| server_txn.scim_put(put_event))
+        };
+
+        // Inactive expires the account now.
+        let updated_entry = put_active(
+            &mut server_txn,
+            [(Attribute::Active, Some(ScimValueKanidm::Bool(false)))].into(),
+        )
+        .expect("Failed to put");
+        assert!(matches!(
+            updated_entry.attrs.get(&Attribute::AccountExpire),
+            Some(ScimValueKanidm::DateTime(expire)) if *expire == now
+        ));
+        // The returned entry reflects the derived state.
+        assert!(matches!(
+            updated_entry.attrs.get(&Attribute::Active),
+            Some(ScimValueKanidm::Bool(false))
+        ));
+
+        // Active clears an expiry that has passed.
+        let updated_entry = put_active(
+            &mut server_txn,
+            [(Attribute::Active, Some(ScimValueKanidm::Bool(true)))].into(),
+        )
+        .expect("Failed to put");
+        assert!(!updated_entry.attrs.contains_key(&Attribute::AccountExpire));
+        assert!(matches!(
+            updated_entry.attrs.get(&Attribute::Active),
+            Some(ScimValueKanidm::Bool(true))
+        ));
+
+        // But leaves a future expiry in place.
+        let future = now + time::Duration::days(1);
+        server_txn
+            .internal_modify_uuid(
+                person_uuid,
+                &ModifyList::new_purge_and_set(Attribute::AccountExpire, Value::DateTime(future)),
+            )
+            .expect("Failed to set account expiry");
+
+        let updated_entry = put_active(
+            &mut server_txn,
+            [(Attribute::Active, Some(ScimValueKanidm::Bool(true)))].into(),
+        )
+        .expect("Failed to put");
+        assert!(matches!(
+            updated_entry.attrs.get(&Attribute::AccountExpire),
+            Some(ScimValueKanidm::DateTime(expire)) if *expire == future
+        ));
+        assert!(matches!(
+            updated_entry.attrs.get(&Attribute::Active),
+            Some(ScimValueKanidm::Bool(true))
+        ));
+
+        // Active and account_expire can not be set together.
+        let err = put_active(
+            &mut server_txn,
+            [
+                (Attribute::Active, Some(ScimValueKanidm::Bool(false))),
+                (Attribute::AccountExpire, None),
+            ]
+            .into(),
+        )
+        .expect_err("Put should fail");
+        assert_eq!(err, OperationError::SC0034ActiveConflictsWithAccountExpire);
+
+        // Active must be a boolean.
+        let err = put_active(
+            &mut server_txn,
+            [(
+                Attribute::Active,
+                Some(ScimValueKanidm::String("no".into())),
+            )]
+            .into(),
+        )
+        .expect_err("Put should fail");
+        assert_eq!(err, OperationError::SC0005BoolSyntaxInvalid);
+    }
+
     #[qs_test]
     async fn scim_assert_basic(server: &QueryServer) {
         let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
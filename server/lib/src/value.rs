@@ -4,8 +4,8 @@
 //! these into a form for the backend that can be persistent into the [`Backend`](crate::be::Backend).
 
 use crate::be::dbentry::DbIdentSpn;
-use crate::be::dbvalue::DbValueOauthClaimMapJoinV1;
-use crate::credential::{apppwd::ApplicationPassword, totp::Totp, Credential};
+use crate::be::dbvalue::{DbValueExtensionJsonV1, DbValueOauthClaimMapJoinV1};
+use crate::credential::{apppwd::ApplicationPassword, totp::Totp, Credential, Password};
 use crate::prelude::*;
 use crate::repl::cid::Cid;
 use crate::server::identity::IdentityId;
@@ -33,13 +33,15 @@ use std::convert::TryFrom;
 use std::fmt;
 use std::fmt::Formatter;
 use std::hash::Hash;
+use std::net::IpAddr;
 use std::str::FromStr;
 use std::time::Duration;
 use time::OffsetDateTime;
 use url::Url;
 use uuid::Uuid;
 use webauthn_rs::prelude::{
-    AttestationCaList, AttestedPasskey as AttestedPasskeyV4, Passkey as PasskeyV4,
+    AttestationCa, AttestationCaList, AttestationMetadata, AttestedPasskey as AttestedPasskeyV4,
+    Passkey as PasskeyV4,
 };
 
 #[cfg(test)]
@@ -140,6 +142,56 @@ pub struct CredUpdateSessionPerms {
     pub sshpubkey_can_edit: bool,
 }
 
+/// How an attested passkey was attested when it was registered, so that the make and
+/// model of the authenticator can be determined later.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AttestedPasskeyAttestation {
+    /// The attestation CA that vouched for this passkey.
+    pub attestation_ca: Option<Uuid>,
+    /// The authenticator model identifier that the passkey reported.
+    pub aaguid: Option<Uuid>,
+}
+
+impl From<&AttestedPasskeyV4> for AttestedPasskeyAttestation {
+    fn from(k: &AttestedPasskeyV4) -> Self {
+        let aaguid = match k.attestation().metadata {
+            AttestationMetadata::Packed { aaguid } | AttestationMetadata::Tpm { aaguid, .. } => {
+                Some(aaguid)
+            }
+            _ => None,
+        };
+
+        AttestedPasskeyAttestation {
+            attestation_ca: None,
+            aaguid,
+        }
+    }
+}
+
+impl AttestedPasskeyAttestation {
+    /// The attestation of this passkey, including the CA of `ca_list` that attests it.
+    pub fn new(k: &AttestedPasskeyV4, ca_list: &AttestationCaList) -> Self {
+        let attestation_ca = k
+            .verify_attestation(ca_list)
+            .ok()
+            .and_then(attestation_ca_uuid);
+
+        AttestedPasskeyAttestation {
+            attestation_ca,
+            ..AttestedPasskeyAttestation::from(k)
+        }
+    }
+}
+
+/// The uuid of an attestation CA. This is taken from the digest of the CA certificate,
+/// so the same CA has the same uuid in every CA list it is part of.
+pub fn attestation_ca_uuid(att_ca: &AttestationCa) -> Option<Uuid> {
+    att_ca
+        .get_kid()
+        .ok()
+        .and_then(|kid| Uuid::from_slice(kid.get(..16)?).ok())
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum IntentTokenState {
     Valid {
@@ -157,6 +209,17 @@ pub enum IntentTokenState {
     },
 }
 
+impl IntentTokenState {
+    /// If the update session started from this intent token has passed its ttl.
+    /// Only tokens that are in progress have a session that can expire.
+    pub fn is_session_expired(&self, now: Duration) -> bool {
+        match self {
+            IntentTokenState::InProgress { session_ttl, .. } => now > *session_ttl,
+            IntentTokenState::Valid { .. } | IntentTokenState::Consumed { .. } => false,
+        }
+    }
+}
+
 #[allow(non_camel_case_types)]
 #[derive(
     Debug,
@@ -287,6 +350,10 @@ pub enum SyntaxType {
     Sha256 = 44,
     Int64 = 45,
     Uint64 = 46,
+    ExternalId = 47,
+    MfaGrace = 48,
+    BreakGlass = 49,
+    ExtensionJson = 50,
 }
 
 impl TryFrom<&str> for SyntaxType {
@@ -342,6 +409,10 @@ impl TryFrom<&str> for SyntaxType {
             "SHA256" => Ok(SyntaxType::Sha256),
             "INT64" => Ok(SyntaxType::Int64),
             "UINT64" => Ok(SyntaxType::Uint64),
+            "EXTERNAL_ID" => Ok(SyntaxType::ExternalId),
+            "MFA_GRACE" => Ok(SyntaxType::MfaGrace),
+            "BREAK_GLASS" => Ok(SyntaxType::BreakGlass),
+            "EXTENSION_JSON" => Ok(SyntaxType::ExtensionJson),
             _ => Err(()),
         }
     }
@@ -397,6 +468,10 @@ impl fmt::Display for SyntaxType {
             SyntaxType::Sha256 => "SHA256",
             SyntaxType::Int64 => "INT64",
             SyntaxType::Uint64 => "UINT64",
+            SyntaxType::ExternalId => "EXTERNAL_ID",
+            SyntaxType::MfaGrace => "MFA_GRACE",
+            SyntaxType::BreakGlass => "BREAK_GLASS",
+            SyntaxType::ExtensionJson => "EXTENSION_JSON",
         })
     }
 }
@@ -470,6 +545,10 @@ impl SyntaxType {
             SyntaxType::JsonFilter => &[],
             SyntaxType::Json => &[],
             SyntaxType::Message => &[],
+            SyntaxType::ExternalId => &[],
+            SyntaxType::MfaGrace => &[],
+            SyntaxType::BreakGlass => &[],
+            SyntaxType::ExtensionJson => &[],
             SyntaxType::Sha256 => &[IndexType::Equality],
         }
     }
@@ -616,6 +695,8 @@ pub enum PartialValue {
     Sha256(Sha256Output),
     Int64(i64),
     Uint64(u64),
+    /// The system and the identifier within that system.
+    ExternalId(String, String),
 }
 
 impl From<SyntaxType> for PartialValue {
@@ -1019,6 +1100,8 @@ impl PartialValue {
             PartialValue::HexString(hexstr) => hexstr.to_string(),
             PartialValue::Json => "_".to_string(),
             PartialValue::Sha256(bytes) => hex::encode(bytes),
+            // External ids are not indexed.
+            PartialValue::ExternalId(_, _) => "_".to_string(),
         }
     }
 
@@ -1209,7 +1292,7 @@ impl fmt::Debug for SessionExtMetadata {
     }
 }
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, Eq)]
 pub struct Session {
     pub label: String,
     // pub expiry: Option<OffsetDateTime>,
@@ -1220,6 +1303,40 @@ pub struct Session {
     pub scope: SessionScope,
     pub type_: AuthType,
     pub ext_metadata: SessionExtMetadata,
+    /// The address this session was authenticated from. When set, the session may
+    /// only be used from this address.
+    pub client_ip: Option<IpAddr>,
+    /// When this session was last seen in use. Updating this is a best-effort write
+    /// the server makes on token use, so it may lag behind the true last use of the
+    /// session. It is not compared for equality.
+    pub last_seen: Option<OffsetDateTime>,
+}
+
+impl PartialEq for Session {
+    fn eq(&self, other: &Self) -> bool {
+        let Session {
+            label,
+            state,
+            issued_at,
+            issued_by,
+            cred_id,
+            scope,
+            type_,
+            ext_metadata,
+            client_ip,
+            last_seen: _,
+        } = self;
+
+        label == &other.label
+            && state == &other.state
+            && issued_at == &other.issued_at
+            && issued_by == &other.issued_by
+            && cred_id == &other.cred_id
+            && scope == &other.scope
+            && type_ == &other.type_
+            && ext_metadata == &other.ext_metadata
+            && client_ip == &other.client_ip
+    }
 }
 
 impl fmt::Debug for Session {
@@ -1238,7 +1355,14 @@ impl fmt::Debug for Session {
             f,
             "state: {}, issued at: {}, issued by: {}, credential id: {}, scope: {:?}",
             expiry, self.issued_at, issuer, self.cred_id, self.scope
-        )
+        )?;
+        if let Some(client_ip) = self.client_ip {
+            write!(f, ", bound to: {client_ip}")?;
+        }
+        if let Some(last_seen) = self.last_seen {
+            write!(f, ", last seen: {last_seen}")?;
+        }
+        Ok(())
     }
 }
 
@@ -1399,6 +1523,14 @@ pub enum Value {
     Uint64(u64),
     Cid(Cid),
     Nsuniqueid(String),
+    /// The system and the identifier within that system.
+    ExternalId(String, String),
+    /// The change that granted the window, and when the window expires.
+    MfaGrace(Cid, OffsetDateTime),
+    /// The id of a break-glass code, and the hash of the code.
+    BreakGlass(Uuid, Password),
+    /// The urn of a SCIM extension, and the data supplied for it.
+    ExtensionJson(String, JsonValue),
     DateTime(OffsetDateTime),
     EmailAddress(String, bool),
     PhoneNumber(String, bool),
@@ -1411,7 +1543,7 @@ pub enum Value {
     RestrictedString(String),
     IntentToken(String, IntentTokenState),
     Passkey(Uuid, String, PasskeyV4),
-    AttestedPasskey(Uuid, String, AttestedPasskeyV4),
+    AttestedPasskey(Uuid, String, AttestedPasskeyV4, AttestedPasskeyAttestation),
 
     Session(Uuid, Session),
     ApiToken(Uuid, ApiToken),
@@ -1429,6 +1561,7 @@ pub enum Value {
     WebauthnAttestationCaList(AttestationCaList),
 
     OauthClaimValue(String, Uuid, BTreeSet<String>),
+    OauthClaimTypedValue(String, Uuid, Vec<JsonValue>),
     OauthClaimMap(String, OauthClaimMapJoin),
 
     KeyInternal {
@@ -1480,6 +1613,14 @@ impl PartialEq for Value {
             (Value::Int64(a), Value::Int64(b)) => a.eq(b),
             // Uint64
             (Value::Uint64(a), Value::Uint64(b)) => a.eq(b),
+            // ExternalId
+            (Value::ExternalId(a, c), Value::ExternalId(b, d)) => a.eq(b) && c.eq(d),
+            // MfaGrace
+            (Value::MfaGrace(a, c), Value::MfaGrace(b, d)) => a.eq(b) && c.eq(d),
+            // BreakGlass
+            (Value::BreakGlass(a, c), Value::BreakGlass(b, d)) => a.eq(b) && c.eq(d),
+            // ExtensionJson
+            (Value::ExtensionJson(a, c), Value::ExtensionJson(b, d)) => a.eq(b) && c.eq(d),
             // Cid
             (Value::Cid(a), Value::Cid(b)) => a.eq(b),
             // DateTime
@@ -1944,6 +2085,13 @@ impl Value {
         }
     }
 
+    /// Claim values that are not strings, such as booleans, numbers or objects. String
+    /// values must use [Value::new_oauthclaimmap].
+    pub fn new_oauthclaimmap_typed(n: String, u: Uuid, c: Vec<JsonValue>) -> Option<Self> {
+        let v = Value::OauthClaimTypedValue(n, u, c);
+        v.validate().then_some(v)
+    }
+
     pub fn is_oauthscopemap(&self) -> bool {
         matches!(&self, Value::OauthScopeMap(_, _))
     }
@@ -2238,7 +2386,7 @@ impl Value {
             | Value::PublicBinary(s, _)
             | Value::IntentToken(s, _)
             | Value::Passkey(_, s, _)
-            | Value::AttestedPasskey(_, s, _)
+            | Value::AttestedPasskey(_, s, _, _)
             | Value::TotpSecret(s, _) => {
                 Value::validate_str_escapes(s) && Value::validate_singleline(s)
             }
@@ -2274,6 +2422,13 @@ impl Value {
 
             // These have stricter validators so not needed.
             Value::Nsuniqueid(s) => NSUNIQUEID_RE.is_match(s),
+            Value::ExternalId(system, identifier) => {
+                !system.is_empty()
+                    && !identifier.is_empty()
+                    && Value::validate_str_escapes(system)
+                    && Value::validate_str_escapes(identifier)
+            }
+            Value::ExtensionJson(urn, data) => DbValueExtensionJsonV1::is_valid(urn, data),
             Value::DateTime(odt) => odt.offset() == time::UtcOffset::UTC,
             Value::EmailAddress(mail, _) => VALIDATE_EMAIL_RE.is_match(mail.as_str()),
             Value::OauthScope(s) => OAUTHSCOPE_RE.is_match(s),
@@ -2284,6 +2439,12 @@ impl Value {
                 OAUTH_CLAIMNAME_RE.is_match(name)
                     && value.iter().all(|s| OAUTH_CLAIMNAME_RE.is_match(s))
             }
+            Value::OauthClaimTypedValue(name, _, value) => {
+                OAUTH_CLAIMNAME_RE.is_match(name)
+                    && value
+                        .iter()
+                        .all(|v| !matches!(v, JsonValue::Null | JsonValue::String(_)))
+            }
 
             Value::KeyInternal { id, .. } => {
                 let s = id.as_str();
@@ -2311,6 +2472,8 @@ impl Value {
             | Value::Uint32(_)
             | Value::Int64(_)
             | Value::Uint64(_)
+            | Value::MfaGrace(_, _)
+            | Value::BreakGlass(_, _)
             | Value::Url(_)
             | Value::Cid(_)
             | Value::PrivateBinary(_)
@@ -2663,6 +2826,37 @@ mod tests {
         assert!(SessionState::ExpiresAt(OffsetDateTime::UNIX_EPOCH) > SessionState::NeverExpires);
     }
 
+    #[test]
+    fn test_value_intent_token_session_expired() {
+        let perms = CredUpdateSessionPerms {
+            ext_cred_portal_can_view: false,
+            primary_can_edit: true,
+            passkeys_can_edit: true,
+            attested_passkeys_can_edit: false,
+            unixcred_can_edit: false,
+            sshpubkey_can_edit: false,
+        };
+        let max_ttl = Duration::from_secs(3600);
+        let session_ttl = Duration::from_secs(1);
+
+        let state = IntentTokenState::InProgress {
+            max_ttl,
+            perms,
+            session_id: Uuid::new_v4(),
+            session_ttl,
+        };
+
+        assert!(!state.is_session_expired(Duration::ZERO));
+        assert!(!state.is_session_expired(session_ttl));
+        assert!(state.is_session_expired(Duration::from_secs(2)));
+
+        // Without a session in progress, there is nothing to expire.
+        let state = IntentTokenState::Valid { max_ttl, perms };
+        assert!(!state.is_session_expired(Duration::from_secs(2)));
+        let state = IntentTokenState::Consumed { max_ttl };
+        assert!(!state.is_session_expired(Duration::from_secs(2)));
+    }
+
     #[test]
     fn test_extract_val_dn_regexn() {
         fn do_extract(name: &str) -> &str {
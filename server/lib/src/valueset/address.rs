@@ -331,6 +331,11 @@ impl ValueSetScimPut for ValueSetEmailAddress {
             OperationError::SC0003MailSyntaxInvalid
         })?;
 
+        if scim_mails.iter().filter(|mail| mail.primary).count() > 1 {
+            error!("Mail attribute has more than one primary mail address.");
+            return Err(OperationError::PL0003MailMultiplePrimary);
+        }
+
         let mut primary = None;
         let set: BTreeSet<_> = scim_mails
             .into_iter()
@@ -583,9 +588,10 @@ pub struct ValueSetPhoneNumber {
 #[cfg(test)]
 mod tests {
     use super::{ValueSetAddress, ValueSetEmailAddress};
+    use crate::prelude::OperationError;
     use crate::repl::cid::Cid;
     use crate::value::{Address, PartialValue, Value};
-    use crate::valueset::{self, ValueSet};
+    use crate::valueset::{self, ValueSet, ValueSetScimPut};
 
     #[test]
     fn test_valueset_emailaddress() {
@@ -686,7 +692,17 @@ mod tests {
         crate::valueset::scim_json_reflexive(&vs, data);
 
         // Test that we can parse json values into a valueset.
-        crate::valueset::scim_json_put_reflexive::<ValueSetEmailAddress>(&vs, &[])
+        crate::valueset::scim_json_put_reflexive::<ValueSetEmailAddress>(&vs, &[]);
+
+        // More than one primary is ambiguous.
+        let data = serde_json::json!([
+            { "primary": true, "value": "alice@example.com" },
+            { "primary": true, "value": "claire@example.com" }
+        ]);
+        assert!(matches!(
+            ValueSetEmailAddress::from_scim_json_put(data),
+            Err(OperationError::PL0003MailMultiplePrimary)
+        ));
     }
 
     #[test]
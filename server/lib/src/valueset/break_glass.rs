@@ -0,0 +1,219 @@
+use crate::be::dbvalue::DbValueBreakGlassV1;
+use crate::credential::Password;
+use crate::prelude::*;
+use crate::schema::SchemaAttribute;
+use crate::valueset::{DbValueSetV2, ScimResolveStatus, ValueSet};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Standalone break-glass codes for an account, keyed by the id of each code. Only the
+/// hashes of the codes are held, see [DbValueBreakGlassV1] for the scheme.
+#[derive(Debug, Clone)]
+pub struct ValueSetBreakGlass {
+    codes: BTreeMap<Uuid, Password>,
+    consumed: BTreeSet<Uuid>,
+}
+
+impl ValueSetBreakGlass {
+    pub fn new(id: Uuid, code: Password) -> Box<Self> {
+        let mut codes = BTreeMap::new();
+        codes.insert(id, code);
+        Box::new(ValueSetBreakGlass {
+            codes,
+            consumed: BTreeSet::new(),
+        })
+    }
+
+    pub fn from_dbvs2(data: Vec<DbValueBreakGlassV1>) -> Result<ValueSet, OperationError> {
+        let mut codes = BTreeMap::new();
+        let mut consumed = BTreeSet::new();
+        for dbv in data {
+            for (id, dbpw) in dbv.code_set.iter() {
+                let code = Password::try_from(dbpw.clone())
+                    .map_err(|()| OperationError::InvalidValueState)?;
+                codes.insert(*id, code);
+            }
+            consumed.extend(dbv.consumed.iter().copied());
+        }
+        Ok(Box::new(ValueSetBreakGlass { codes, consumed }))
+    }
+
+    /// Returns the id of the code that matches `code`, if it has not yet been consumed.
+    /// Every remaining code is checked, so the time taken does not reveal which matched.
+    pub fn verify(&self, code: &str) -> Option<Uuid> {
+        let mut matched = None;
+        for (id, pw) in self
+            .codes
+            .iter()
+            .filter(|(id, _)| !self.consumed.contains(id))
+        {
+            if pw.verify(code).unwrap_or(false) {
+                matched = Some(*id);
+            }
+        }
+        matched
+    }
+
+    /// Mark a code as used so that it can not be replayed. Returns `false` if the code
+    /// is unknown or was already consumed.
+    pub fn consume(&mut self, id: Uuid) -> bool {
+        self.codes.contains_key(&id) && self.consumed.insert(id)
+    }
+}
+
+impl ValueSetT for ValueSetBreakGlass {
+    fn insert_checked(&mut self, value: Value) -> Result<bool, OperationError> {
+        match value {
+            Value::BreakGlass(id, code) => Ok(self.codes.insert(id, code).is_none()),
+            _ => {
+                debug_assert!(false);
+                Err(OperationError::InvalidValueState)
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.codes.clear();
+        self.consumed.clear();
+    }
+
+    fn remove(&mut self, pv: &PartialValue, _cid: &Cid) -> bool {
+        match pv {
+            PartialValue::Uuid(id) => {
+                self.consumed.remove(id);
+                self.codes.remove(id).is_some()
+            }
+            _ => {
+                debug_assert!(false);
+                true
+            }
+        }
+    }
+
+    fn contains(&self, pv: &PartialValue) -> bool {
+        match pv {
+            PartialValue::Uuid(id) => self.codes.contains_key(id),
+            _ => false,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.codes.len()
+    }
+
+    fn generate_idx_eq_keys(&self) -> Vec<String> {
+        Vec::with_capacity(0)
+    }
+
+    fn syntax(&self) -> SyntaxType {
+        SyntaxType::BreakGlass
+    }
+
+    fn validate(&self, _schema_attr: &SchemaAttribute) -> bool {
+        self.consumed.iter().all(|id| self.codes.contains_key(id))
+    }
+
+    fn to_proto_string_clone_iter(&self) -> Box<dyn Iterator<Item = String> + '_> {
+        Box::new(self.codes.keys().map(|id| {
+            if self.consumed.contains(id) {
+                format!("{id}: consumed")
+            } else {
+                id.to_string()
+            }
+        }))
+    }
+
+    fn to_scim_value(&self) -> Option<ScimResolveStatus> {
+        // Like credentials, the codes are never returned.
+        None
+    }
+
+    fn to_db_valueset_v2(&self) -> DbValueSetV2 {
+        DbValueSetV2::BreakGlass(vec![DbValueBreakGlassV1 {
+            code_set: self
+                .codes
+                .iter()
+                .map(|(id, pw)| (*id, pw.to_dbpasswordv1()))
+                .collect(),
+            consumed: self.consumed.clone(),
+        }])
+    }
+
+    fn to_partialvalue_iter(&self) -> Box<dyn Iterator<Item = PartialValue> + '_> {
+        Box::new(self.codes.keys().copied().map(PartialValue::Uuid))
+    }
+
+    fn to_value_iter(&self) -> Box<dyn Iterator<Item = Value> + '_> {
+        Box::new(
+            self.codes
+                .iter()
+                .map(|(id, pw)| Value::BreakGlass(*id, pw.clone())),
+        )
+    }
+
+    fn equal(&self, other: &ValueSet) -> bool {
+        if let Some((codes, consumed)) = other.as_break_glass() {
+            &self.codes == codes && &self.consumed == consumed
+        } else {
+            debug_assert!(false);
+            false
+        }
+    }
+
+    fn merge(&mut self, other: &ValueSet) -> Result<(), OperationError> {
+        if let Some((codes, consumed)) = other.as_break_glass() {
+            // A code that was consumed on any replica stays consumed.
+            self.consumed.extend(consumed.iter().copied());
+            mergemaps!(self.codes, codes)
+        } else {
+            debug_assert!(false);
+            Err(OperationError::InvalidValueState)
+        }
+    }
+
+    fn as_break_glass(&self) -> Option<(&BTreeMap<Uuid, Password>, &BTreeSet<Uuid>)> {
+        Some((&self.codes, &self.consumed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ValueSetBreakGlass;
+    use crate::credential::Password;
+    use crate::prelude::*;
+    use kanidm_lib_crypto::CryptoPolicy;
+
+    #[test]
+    fn test_valueset_break_glass_verify() {
+        let policy = CryptoPolicy::minimum();
+        let id_a = Uuid::new_v4();
+        let id_b = Uuid::new_v4();
+
+        let mut vs = ValueSetBreakGlass::new(
+            id_a,
+            Password::new(&policy, "correct-horse").expect("Failed to hash code"),
+        );
+        vs.insert_checked(Value::BreakGlass(
+            id_b,
+            Password::new(&policy, "battery-staple").expect("Failed to hash code"),
+        ))
+        .expect("Failed to insert");
+
+        assert_eq!(vs.verify("correct-horse"), Some(id_a));
+        assert_eq!(vs.verify("battery-staple"), Some(id_b));
+        assert_eq!(vs.verify("not-a-code"), None);
+
+        // A consumed code can not be replayed, and can only be consumed once.
+        assert!(vs.consume(id_a));
+        assert!(!vs.consume(id_a));
+        assert!(!vs.consume(Uuid::new_v4()));
+        assert_eq!(vs.verify("correct-horse"), None);
+        assert_eq!(vs.verify("battery-staple"), Some(id_b));
+
+        // The consumed state survives a round trip through the db.
+        let vs: ValueSet = vs;
+        let vs_out =
+            crate::valueset::from_db_valueset_v2(vs.to_db_valueset_v2()).expect("Failed to load");
+        assert_eq!(vs_out.syntax(), SyntaxType::BreakGlass);
+        assert!(vs.equal(&vs_out));
+    }
+}
@@ -4,14 +4,16 @@ use crate::schema::SchemaAttribute;
 use crate::valueset::ScimResolveStatus;
 use crate::valueset::{DbValueSetV2, ValueSet, ValueSetResolveStatus, ValueSetScimPut};
 use crypto_glue::{
-    s256::Sha256Output,
-    traits::{DecodeDer, EncodeDer, EncodePem, LineEndingPem},
+    s256::{Sha256, Sha256Output},
+    traits::{DecodeDer, Digest, EncodeDer, EncodePem, LineEndingPem},
     x509::{x509_digest_public_key_sha256, Certificate},
 };
+use kanidm_proto::internal::CertificateSummary;
 use kanidm_proto::scim_v1::client::ScimCertificate as ClientScimCertificate;
 use kanidm_proto::scim_v1::server::ScimCertificate;
 use kanidm_proto::scim_v1::JsonValue;
 use std::collections::BTreeMap;
+use time::OffsetDateTime;
 
 #[derive(Debug, Clone)]
 pub struct ValueSetCertificate {
@@ -31,6 +33,41 @@ impl ValueSetCertificate {
         Ok(Box::new(ValueSetCertificate { map }))
     }
 
+    /// Decode the certificates of an entry into summaries that identify each certificate
+    /// for display.
+    pub fn summarise(
+        entry_uuid: Uuid,
+        certs: &BTreeMap<Sha256Output, Box<Certificate>>,
+    ) -> Vec<CertificateSummary> {
+        certs
+            .iter()
+            .filter_map(|(pk_s256, cert)| {
+                let certificate_der = cert
+                    .to_der()
+                    .map_err(|der_err| {
+                        error!(
+                            ?pk_s256,
+                            ?der_err,
+                            "Failed to serialise certificate to der. This value will not be summarised!"
+                        );
+                    })
+                    .ok()?;
+
+                let tbs = &cert.tbs_certificate;
+                Some(CertificateSummary {
+                    uuid: entry_uuid,
+                    subject: tbs.subject.to_string(),
+                    issuer: tbs.issuer.to_string(),
+                    not_before: OffsetDateTime::UNIX_EPOCH
+                        + tbs.validity.not_before.to_unix_duration(),
+                    not_after: OffsetDateTime::UNIX_EPOCH
+                        + tbs.validity.not_after.to_unix_duration(),
+                    fingerprint_sha256: hex::encode(Sha256::digest(certificate_der)),
+                })
+            })
+            .collect()
+    }
+
     pub fn from_dbvs2(data: Vec<DbValueCertificate>) -> Result<ValueSet, OperationError> {
         Self::from_dbv_iter(data.into_iter())
     }
@@ -306,7 +343,7 @@ impl ValueSetT for ValueSetCertificate {
 #[cfg(test)]
 mod tests {
     use super::ValueSetCertificate;
-    use crate::prelude::{ScimValueKanidm, ValueSet};
+    use crate::prelude::{ScimValueKanidm, ValueSet, ValueSetT};
     use crypto_glue::{traits::DecodePem, x509::Certificate};
 
     // Generated with:
@@ -349,4 +386,37 @@ raBy6edj7W0EIH+yQxkDEwIhAI0nVKaI6duHLAvtKW6CfEQFG6jKg7dyk37YYiRD
         // Test that we can parse json values into a valueset.
         crate::valueset::scim_json_put_reflexive::<ValueSetCertificate>(&vs, &[])
     }
+
+    #[test]
+    fn test_certificate_summarise() {
+        let cert = Certificate::from_pem(PEM_DATA).unwrap();
+        let vs = ValueSetCertificate::new(Box::new(cert)).unwrap();
+        let entry_uuid = uuid::uuid!("2bd9c8f8-95a4-4b4f-9a9f-1c8d1a66f7b1");
+
+        let mut summaries =
+            ValueSetCertificate::summarise(entry_uuid, vs.as_certificate_set().unwrap());
+        assert_eq!(summaries.len(), 1);
+        let summary = summaries.pop().unwrap();
+
+        assert_eq!(summary.uuid, entry_uuid);
+        // The certificate is self signed.
+        assert_eq!(
+            summary.subject,
+            "O=Internet Widgits Pty Ltd,ST=Some-State,C=AU"
+        );
+        assert_eq!(summary.subject, summary.issuer);
+        assert_eq!(
+            summary.not_before,
+            time::macros::datetime!(2024-08-21 06:46:30 UTC)
+        );
+        assert_eq!(
+            summary.not_after,
+            time::macros::datetime!(2025-08-21 06:46:30 UTC)
+        );
+        // Matches the output of `openssl x509 -noout -fingerprint -sha256`
+        assert_eq!(
+            summary.fingerprint_sha256,
+            "49709e125b28f34e2d24fd012e787a4a4ecd9afb1676f9e2b2030e3bfddfb654"
+        );
+    }
 }
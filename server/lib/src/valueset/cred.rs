@@ -5,7 +5,9 @@ use crate::credential::Credential;
 use crate::prelude::*;
 use crate::schema::SchemaAttribute;
 use crate::utils::trigraph_iter;
-use crate::value::{CredUpdateSessionPerms, CredentialType, IntentTokenState};
+use crate::value::{
+    AttestedPasskeyAttestation, CredUpdateSessionPerms, CredentialType, IntentTokenState,
+};
 use crate::valueset::{
     DbValueSetV2, ScimResolveStatus, ValueSet, ValueSetResolveStatus, ValueSetScimPut,
 };
@@ -673,27 +675,58 @@ impl ValueSetT for ValueSetPasskey {
 #[derive(Debug, Clone)]
 pub struct ValueSetAttestedPasskey {
     map: BTreeMap<Uuid, (String, AttestedPasskeyV4)>,
+    attestation: BTreeMap<Uuid, AttestedPasskeyAttestation>,
 }
 
 impl ValueSetAttestedPasskey {
-    pub fn new(u: Uuid, t: String, k: AttestedPasskeyV4) -> Box<Self> {
+    pub fn new(
+        u: Uuid,
+        t: String,
+        k: AttestedPasskeyV4,
+        a: AttestedPasskeyAttestation,
+    ) -> Box<Self> {
         let mut map = BTreeMap::new();
+        let mut attestation = BTreeMap::new();
+        attestation.insert(u, a);
         map.insert(u, (t, k));
-        Box::new(ValueSetAttestedPasskey { map })
+        Box::new(ValueSetAttestedPasskey { map, attestation })
     }
 
     pub fn push(&mut self, u: Uuid, t: String, k: AttestedPasskeyV4) -> bool {
+        self.attestation
+            .insert(u, AttestedPasskeyAttestation::from(&k));
         self.map.insert(u, (t, k)).is_none()
     }
 
     pub fn from_dbvs2(data: Vec<DbValueAttestedPasskeyV1>) -> Result<ValueSet, OperationError> {
-        let map = data
-            .into_iter()
-            .map(|k| match k {
-                DbValueAttestedPasskeyV1::V4 { u, t, k } => Ok((u, (t, k))),
-            })
-            .collect::<Result<_, _>>()?;
-        Ok(Box::new(ValueSetAttestedPasskey { map }))
+        let mut map = BTreeMap::new();
+        let mut attestation = BTreeMap::new();
+        for dbv in data {
+            let (u, t, k, att) = match dbv {
+                // Passkeys registered before attestation was recorded have none.
+                DbValueAttestedPasskeyV1::V4 { u, t, k } => {
+                    (u, t, k, AttestedPasskeyAttestation::default())
+                }
+                DbValueAttestedPasskeyV1::V5 {
+                    u,
+                    t,
+                    k,
+                    ca,
+                    aaguid,
+                } => (
+                    u,
+                    t,
+                    k,
+                    AttestedPasskeyAttestation {
+                        attestation_ca: ca,
+                        aaguid,
+                    },
+                ),
+            };
+            attestation.insert(u, att);
+            map.insert(u, (t, k));
+        }
+        Ok(Box::new(ValueSetAttestedPasskey { map, attestation }))
     }
 
     // We need to allow this, because rust doesn't allow us to impl FromIterator on foreign
@@ -703,16 +736,21 @@ impl ValueSetAttestedPasskey {
     where
         T: IntoIterator<Item = (Uuid, String, AttestedPasskeyV4)>,
     {
-        let map = iter.into_iter().map(|(u, t, k)| (u, (t, k))).collect();
-        Some(Box::new(ValueSetAttestedPasskey { map }))
+        let map: BTreeMap<_, _> = iter.into_iter().map(|(u, t, k)| (u, (t, k))).collect();
+        let attestation = map
+            .iter()
+            .map(|(u, (_, k))| (*u, AttestedPasskeyAttestation::from(k)))
+            .collect();
+        Some(Box::new(ValueSetAttestedPasskey { map, attestation }))
     }
 }
 
 impl ValueSetT for ValueSetAttestedPasskey {
     fn insert_checked(&mut self, value: Value) -> Result<bool, OperationError> {
         match value {
-            Value::AttestedPasskey(u, t, k) => {
+            Value::AttestedPasskey(u, t, k, a) => {
                 if let BTreeEntry::Vacant(e) = self.map.entry(u) {
+                    self.attestation.insert(u, a);
                     e.insert((t, k));
                     Ok(true)
                 } else {
@@ -725,11 +763,15 @@ impl ValueSetT for ValueSetAttestedPasskey {
 
     fn clear(&mut self) {
         self.map.clear();
+        self.attestation.clear();
     }
 
     fn remove(&mut self, pv: &PartialValue, _cid: &Cid) -> bool {
         match pv {
-            PartialValue::AttestedPasskey(u) => self.map.remove(u).is_some(),
+            PartialValue::AttestedPasskey(u) => {
+                self.attestation.remove(u);
+                self.map.remove(u).is_some()
+            }
             _ => false,
         }
     }
@@ -790,10 +832,15 @@ impl ValueSetT for ValueSetAttestedPasskey {
         DbValueSetV2::AttestedPasskey(
             self.map
                 .iter()
-                .map(|(u, (t, k))| DbValueAttestedPasskeyV1::V4 {
-                    u: *u,
-                    t: t.clone(),
-                    k: k.clone(),
+                .map(|(u, (t, k))| {
+                    let attestation = self.attestation.get(u).copied().unwrap_or_default();
+                    DbValueAttestedPasskeyV1::V5 {
+                        u: *u,
+                        t: t.clone(),
+                        k: k.clone(),
+                        ca: attestation.attestation_ca,
+                        aaguid: attestation.aaguid,
+                    }
                 })
                 .collect(),
         )
@@ -804,11 +851,10 @@ impl ValueSetT for ValueSetAttestedPasskey {
     }
 
     fn to_value_iter(&self) -> Box<dyn Iterator<Item = Value> + '_> {
-        Box::new(
-            self.map
-                .iter()
-                .map(|(u, (t, k))| Value::AttestedPasskey(*u, t.clone(), k.clone())),
-        )
+        Box::new(self.map.iter().map(|(u, (t, k))| {
+            let attestation = self.attestation.get(u).copied().unwrap_or_default();
+            Value::AttestedPasskey(*u, t.clone(), k.clone(), attestation)
+        }))
     }
 
     fn equal(&self, other: &ValueSet) -> bool {
@@ -822,7 +868,11 @@ impl ValueSetT for ValueSetAttestedPasskey {
     }
 
     fn merge(&mut self, other: &ValueSet) -> Result<(), OperationError> {
-        if let Some(b) = other.as_attestedpasskey_map() {
+        if let (Some(b), Some(b_attestation)) = (
+            other.as_attestedpasskey_map(),
+            other.as_attestedpasskey_attestation_map(),
+        ) {
+            mergemaps!(self.attestation, b_attestation)?;
             mergemaps!(self.map, b)
         } else {
             debug_assert!(false);
@@ -843,6 +893,12 @@ impl ValueSetT for ValueSetAttestedPasskey {
     fn as_attestedpasskey_map(&self) -> Option<&BTreeMap<Uuid, (String, AttestedPasskeyV4)>> {
         Some(&self.map)
     }
+
+    fn as_attestedpasskey_attestation_map(
+        &self,
+    ) -> Option<&BTreeMap<Uuid, AttestedPasskeyAttestation>> {
+        Some(&self.attestation)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -1160,9 +1216,143 @@ impl ValueSetT for ValueSetWebauthnAttestationCaList {
 
 #[cfg(test)]
 mod tests {
-    use super::{CredentialType, IntentTokenState, ValueSetCredentialType, ValueSetIntentToken};
-    use crate::prelude::ValueSet;
+    use super::{
+        CredentialType, IntentTokenState, ValueSetAttestedPasskey, ValueSetCredentialType,
+        ValueSetIntentToken,
+    };
+    use crate::be::dbvalue::DbValueAttestedPasskeyV1;
+    use crate::prelude::*;
+    use crate::value::{attestation_ca_uuid, AttestedPasskeyAttestation};
+    use crate::valueset::{self, DbValueSetV2};
     use std::time::Duration;
+    use webauthn_authenticator_rs::softtoken::{self, SoftToken};
+    use webauthn_authenticator_rs::WebauthnAuthenticator;
+    use webauthn_rs::prelude::{
+        AttestationCaList, AttestationCaListBuilder, AttestedPasskey, WebauthnBuilder,
+    };
+
+    fn attested_passkey() -> (AttestedPasskey, AttestationCaList) {
+        let (mut soft_token, ca_root) = SoftToken::new(true).expect("Failed to create token");
+
+        let mut att_ca_builder = AttestationCaListBuilder::new();
+        att_ca_builder
+            .insert_device_x509(
+                ca_root,
+                softtoken::AAGUID,
+                "softtoken".to_string(),
+                Default::default(),
+            )
+            .expect("Failed to add attestation ca");
+        let att_ca_list = att_ca_builder.build();
+
+        let origin = url::Url::parse("https://idm.example.com").expect("Invalid origin");
+        let webauthn = WebauthnBuilder::new("idm.example.com", &origin)
+            .and_then(|builder| builder.build())
+            .expect("Invalid webauthn configuration");
+
+        let (chal, reg_state) = webauthn
+            .start_attested_passkey_registration(
+                Uuid::new_v4(),
+                "testperson",
+                "testperson",
+                None,
+                att_ca_list.clone(),
+                None,
+            )
+            .expect("Failed to start registration");
+
+        let resp = soft_token
+            .do_registration(origin, chal)
+            .expect("Failed to register soft token");
+
+        let key = webauthn
+            .finish_attested_passkey_registration(&resp, &reg_state)
+            .expect("Failed to finish registration");
+
+        (key, att_ca_list)
+    }
+
+    #[test]
+    fn test_attested_passkey_attestation_round_trip() {
+        let (key, att_ca_list) = attested_passkey();
+        let key_uuid = Uuid::new_v4();
+
+        // The CA that attests the key is found in the CA list.
+        let att_ca = key
+            .verify_attestation(&att_ca_list)
+            .expect("Key is not attested");
+        let att_ca_uuid = attestation_ca_uuid(att_ca);
+        assert!(att_ca_uuid.is_some());
+
+        let attestation = AttestedPasskeyAttestation::new(&key, &att_ca_list);
+        let expected = AttestedPasskeyAttestation {
+            attestation_ca: att_ca_uuid,
+            aaguid: Some(softtoken::AAGUID),
+        };
+        assert_eq!(attestation, expected);
+
+        // Inserted keys record their attestation.
+        let vs: ValueSet = ValueSetAttestedPasskey::new(
+            key_uuid,
+            "softtoken".to_string(),
+            key.clone(),
+            attestation,
+        );
+        assert_eq!(
+            vs.as_attestedpasskey_attestation_map()
+                .and_then(|map| map.get(&key_uuid)),
+            Some(&expected)
+        );
+
+        // The attestation survives a round trip through the db representation.
+        let attestation_ca = Uuid::new_v4();
+        let dbvs = DbValueSetV2::AttestedPasskey(vec![DbValueAttestedPasskeyV1::V5 {
+            u: key_uuid,
+            t: "softtoken".to_string(),
+            k: key.clone(),
+            ca: Some(attestation_ca),
+            aaguid: Some(softtoken::AAGUID),
+        }]);
+        let vs = valueset::from_db_valueset_v2(dbvs).expect("Failed to load valueset");
+        let expected = AttestedPasskeyAttestation {
+            attestation_ca: Some(attestation_ca),
+            aaguid: Some(softtoken::AAGUID),
+        };
+        assert_eq!(
+            vs.as_attestedpasskey_attestation_map()
+                .and_then(|map| map.get(&key_uuid)),
+            Some(&expected)
+        );
+
+        let DbValueSetV2::AttestedPasskey(dbv) = vs.to_db_valueset_v2() else {
+            panic!("Unexpected valueset type");
+        };
+        let json = serde_json::to_string(&dbv).expect("Failed to serialise");
+        let dbv: Vec<DbValueAttestedPasskeyV1> =
+            serde_json::from_str(&json).expect("Failed to deserialise");
+        assert!(matches!(
+            dbv.as_slice(),
+            [DbValueAttestedPasskeyV1::V5 { ca, aaguid, .. }]
+                if *ca == Some(attestation_ca) && *aaguid == Some(softtoken::AAGUID)
+        ));
+
+        // Older keys have no attestation recorded, but still compare equal to the
+        // same key with one.
+        let v4 = DbValueAttestedPasskeyV1::V4 {
+            u: key_uuid,
+            t: "softtoken".to_string(),
+            k: key,
+        };
+        assert_eq!(dbv.first(), Some(&v4));
+
+        let vs = valueset::from_db_valueset_v2(DbValueSetV2::AttestedPasskey(vec![v4]))
+            .expect("Failed to load valueset");
+        assert_eq!(
+            vs.as_attestedpasskey_attestation_map()
+                .and_then(|map| map.get(&key_uuid)),
+            Some(&AttestedPasskeyAttestation::default())
+        );
+    }
 
     #[test]
     fn test_scim_intent_token() {
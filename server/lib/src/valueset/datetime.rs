@@ -5,6 +5,7 @@ use crate::valueset::{
 };
 use kanidm_proto::scim_v1::{client::ScimDateTime, JsonValue};
 use smolset::SmolSet;
+use std::cmp::Ordering;
 use time::OffsetDateTime;
 
 #[derive(Debug, Clone)]
@@ -179,6 +180,15 @@ impl ValueSetT for ValueSetDateTime {
         }
     }
 
+    fn cmp(&self, other: &ValueSet) -> Ordering {
+        if let Some(other) = other.as_datetime_set() {
+            self.set.iter().min().cmp(&other.iter().min())
+        } else {
+            debug_assert!(false);
+            Ordering::Equal
+        }
+    }
+
     fn merge(&mut self, other: &ValueSet) -> Result<(), OperationError> {
         if let Some(b) = other.as_datetime_set() {
             mergesets!(self.set, b)
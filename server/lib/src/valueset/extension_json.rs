@@ -0,0 +1,183 @@
+use crate::be::dbvalue::DbValueExtensionJsonV1;
+use crate::prelude::*;
+use crate::schema::SchemaAttribute;
+use crate::valueset::ScimResolveStatus;
+use crate::valueset::{DbValueSetV2, ValueSet};
+use kanidm_proto::scim_v1::JsonValue;
+use std::collections::BTreeMap;
+
+/// Data from SCIM extension schemas that have no typed attribute, keyed by the urn
+/// of each extension. See [DbValueExtensionJsonV1] for the limits on the data.
+#[derive(Debug, Clone)]
+pub struct ValueSetExtensionJson {
+    map: BTreeMap<String, JsonValue>,
+}
+
+impl ValueSetExtensionJson {
+    pub fn new(urn: String, data: JsonValue) -> Box<Self> {
+        let mut map = BTreeMap::new();
+        map.insert(urn, data);
+        Box::new(ValueSetExtensionJson { map })
+    }
+
+    pub fn from_dbvs2(data: Vec<DbValueExtensionJsonV1>) -> Result<ValueSet, OperationError> {
+        let map = data
+            .into_iter()
+            .map(|dbv| (dbv.urn().to_string(), dbv.data().clone()))
+            .collect();
+        Ok(Box::new(ValueSetExtensionJson { map }))
+    }
+}
+
+impl ValueSetT for ValueSetExtensionJson {
+    fn insert_checked(&mut self, value: Value) -> Result<bool, OperationError> {
+        match value {
+            Value::ExtensionJson(urn, data) => Ok(self.map.insert(urn, data).is_none()),
+            _ => {
+                debug_assert!(false);
+                Err(OperationError::InvalidValueState)
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    fn remove(&mut self, pv: &PartialValue, _cid: &Cid) -> bool {
+        match pv {
+            PartialValue::Utf8(urn) => self.map.remove(urn).is_some(),
+            _ => {
+                debug_assert!(false);
+                false
+            }
+        }
+    }
+
+    fn contains(&self, pv: &PartialValue) -> bool {
+        match pv {
+            PartialValue::Utf8(urn) => self.map.contains_key(urn),
+            _ => false,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    fn generate_idx_eq_keys(&self) -> Vec<String> {
+        Vec::with_capacity(0)
+    }
+
+    fn syntax(&self) -> SyntaxType {
+        SyntaxType::ExtensionJson
+    }
+
+    fn validate(&self, _schema_attr: &SchemaAttribute) -> bool {
+        self.map
+            .iter()
+            .all(|(urn, data)| DbValueExtensionJsonV1::is_valid(urn, data))
+    }
+
+    fn to_proto_string_clone_iter(&self) -> Box<dyn Iterator<Item = String> + '_> {
+        Box::new(self.map.iter().filter_map(|(urn, data)| {
+            serde_json::to_string(data)
+                .inspect_err(|err| error!(?err, "A json object was corrupted during run-time"))
+                .ok()
+                .map(|data| format!("{urn}: {data}"))
+        }))
+    }
+
+    fn to_scim_value(&self) -> Option<ScimResolveStatus> {
+        // As with SCIM extensions, the data of each is returned under its urn.
+        Some(ScimResolveStatus::Resolved(ScimValueKanidm::Object(
+            self.map.clone(),
+        )))
+    }
+
+    fn to_db_valueset_v2(&self) -> DbValueSetV2 {
+        DbValueSetV2::ExtensionJson(
+            self.map
+                .iter()
+                .filter_map(|(urn, data)| DbValueExtensionJsonV1::new(urn.clone(), data.clone()))
+                .collect(),
+        )
+    }
+
+    fn to_partialvalue_iter(&self) -> Box<dyn Iterator<Item = PartialValue> + '_> {
+        Box::new(self.map.keys().cloned().map(PartialValue::Utf8))
+    }
+
+    fn to_value_iter(&self) -> Box<dyn Iterator<Item = Value> + '_> {
+        Box::new(
+            self.map
+                .iter()
+                .map(|(urn, data)| Value::ExtensionJson(urn.clone(), data.clone())),
+        )
+    }
+
+    fn equal(&self, other: &ValueSet) -> bool {
+        if let Some(other) = other.as_extension_json_map() {
+            &self.map == other
+        } else {
+            debug_assert!(false);
+            false
+        }
+    }
+
+    fn merge(&mut self, other: &ValueSet) -> Result<(), OperationError> {
+        if let Some(b) = other.as_extension_json_map() {
+            mergemaps!(self.map, b)
+        } else {
+            debug_assert!(false);
+            Err(OperationError::InvalidValueState)
+        }
+    }
+
+    fn as_extension_json_map(&self) -> Option<&BTreeMap<String, JsonValue>> {
+        Some(&self.map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ValueSetExtensionJson;
+    use crate::prelude::*;
+
+    const ENTERPRISE: &str = "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User";
+
+    #[test]
+    fn test_valueset_extension_json_db_round_trip() {
+        let mut vs: ValueSet = ValueSetExtensionJson::new(
+            ENTERPRISE.to_string(),
+            serde_json::json!({
+                "employeeNumber": "701984",
+                "manager": { "value": "26118915-6090-4610-87e4-49d8ca9f808d" }
+            }),
+        );
+        assert!(vs
+            .insert_checked(Value::ExtensionJson(
+                "urn:example:scim:1.0:Badge".to_string(),
+                serde_json::json!(["a", 1]),
+            ))
+            .unwrap());
+
+        let vs_out =
+            crate::valueset::from_db_valueset_v2(vs.to_db_valueset_v2()).expect("Failed to load");
+        assert_eq!(vs_out.syntax(), SyntaxType::ExtensionJson);
+        assert_eq!(vs_out.len(), 2);
+        assert!(vs.equal(&vs_out));
+        assert!(vs_out.contains(&PartialValue::new_utf8s(ENTERPRISE)));
+
+        crate::valueset::scim_json_reflexive(
+            &vs,
+            r#"{
+                "urn:example:scim:1.0:Badge": ["a", 1],
+                "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User": {
+                    "employeeNumber": "701984",
+                    "manager": { "value": "26118915-6090-4610-87e4-49d8ca9f808d" }
+                }
+            }"#,
+        );
+    }
+}
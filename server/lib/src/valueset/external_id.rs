@@ -0,0 +1,183 @@
+use crate::be::dbvalue::DbValueExternalIdV1;
+use crate::prelude::*;
+use crate::schema::SchemaAttribute;
+use crate::valueset::ScimResolveStatus;
+use crate::valueset::{DbValueSetV2, ValueSet};
+use std::collections::BTreeSet;
+
+/// Links from an entry to its counterparts in external systems, as pairs of the
+/// system and the identifier within that system.
+#[derive(Debug, Clone)]
+pub struct ValueSetExternalId {
+    set: BTreeSet<(String, String)>,
+}
+
+impl ValueSetExternalId {
+    pub fn new(system: String, identifier: String) -> Box<Self> {
+        let mut set = BTreeSet::new();
+        set.insert((system, identifier));
+        Box::new(ValueSetExternalId { set })
+    }
+
+    pub fn from_dbvs2(data: Vec<DbValueExternalIdV1>) -> Result<ValueSet, OperationError> {
+        let set = data
+            .into_iter()
+            .map(|dbv| (dbv.system().to_string(), dbv.identifier().to_string()))
+            .collect();
+        Ok(Box::new(ValueSetExternalId { set }))
+    }
+}
+
+impl ValueSetT for ValueSetExternalId {
+    fn insert_checked(&mut self, value: Value) -> Result<bool, OperationError> {
+        match value {
+            Value::ExternalId(system, identifier) => Ok(self.set.insert((system, identifier))),
+            _ => {
+                debug_assert!(false);
+                Err(OperationError::InvalidValueState)
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.set.clear();
+    }
+
+    fn remove(&mut self, pv: &PartialValue, _cid: &Cid) -> bool {
+        match pv {
+            PartialValue::ExternalId(system, identifier) => self
+                .set
+                .remove(&(system.to_string(), identifier.to_string())),
+            _ => {
+                debug_assert!(false);
+                true
+            }
+        }
+    }
+
+    fn contains(&self, pv: &PartialValue) -> bool {
+        match pv {
+            PartialValue::ExternalId(system, identifier) => self
+                .set
+                .contains(&(system.to_string(), identifier.to_string())),
+            _ => false,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    fn generate_idx_eq_keys(&self) -> Vec<String> {
+        Vec::with_capacity(0)
+    }
+
+    fn syntax(&self) -> SyntaxType {
+        SyntaxType::ExternalId
+    }
+
+    fn validate(&self, _schema_attr: &SchemaAttribute) -> bool {
+        self.set.iter().all(|(system, identifier)| {
+            !system.is_empty()
+                && !identifier.is_empty()
+                && Value::validate_str_escapes(system)
+                && Value::validate_str_escapes(identifier)
+        })
+    }
+
+    fn to_proto_string_clone_iter(&self) -> Box<dyn Iterator<Item = String> + '_> {
+        Box::new(
+            self.set
+                .iter()
+                .map(|(system, identifier)| format!("{system}: {identifier}")),
+        )
+    }
+
+    fn to_scim_value(&self) -> Option<ScimResolveStatus> {
+        // External ids are only used by the sync layer to correlate entries.
+        None
+    }
+
+    fn to_db_valueset_v2(&self) -> DbValueSetV2 {
+        DbValueSetV2::ExternalId(
+            self.set
+                .iter()
+                .filter_map(|(system, identifier)| {
+                    DbValueExternalIdV1::new(system.clone(), identifier.clone())
+                })
+                .collect(),
+        )
+    }
+
+    fn to_partialvalue_iter(&self) -> Box<dyn Iterator<Item = PartialValue> + '_> {
+        Box::new(
+            self.set
+                .iter()
+                .cloned()
+                .map(|(system, identifier)| PartialValue::ExternalId(system, identifier)),
+        )
+    }
+
+    fn to_value_iter(&self) -> Box<dyn Iterator<Item = Value> + '_> {
+        Box::new(
+            self.set
+                .iter()
+                .cloned()
+                .map(|(system, identifier)| Value::ExternalId(system, identifier)),
+        )
+    }
+
+    fn equal(&self, other: &ValueSet) -> bool {
+        if let Some(other) = other.as_external_id_set() {
+            &self.set == other
+        } else {
+            debug_assert!(false);
+            false
+        }
+    }
+
+    fn merge(&mut self, other: &ValueSet) -> Result<(), OperationError> {
+        if let Some(b) = other.as_external_id_set() {
+            mergesets!(self.set, b)
+        } else {
+            debug_assert!(false);
+            Err(OperationError::InvalidValueState)
+        }
+    }
+
+    fn as_external_id_set(&self) -> Option<&BTreeSet<(String, String)>> {
+        Some(&self.set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ValueSetExternalId;
+    use crate::be::dbvalue::DbValueSetV2;
+    use crate::prelude::*;
+
+    #[test]
+    fn test_valueset_external_id_db_round_trip() {
+        let mut vs: ValueSet = ValueSetExternalId::new(
+            "ad".to_string(),
+            "f4d3ba3c-6fb6-4d4c-9c8e-6d0d3e4e9b3a".to_string(),
+        );
+        assert!(vs
+            .insert_checked(Value::ExternalId(
+                "ldap".to_string(),
+                "uid=testuser,ou=people,dc=example,dc=com".to_string(),
+            ))
+            .unwrap());
+
+        let dbvs = vs.to_db_valueset_v2();
+        assert!(matches!(&dbvs, DbValueSetV2::ExternalId(set) if set.len() == 2));
+
+        let vs_out = crate::valueset::from_db_valueset_v2(dbvs).expect("Failed to load");
+        assert_eq!(vs_out.syntax(), SyntaxType::ExternalId);
+        assert!(vs.equal(&vs_out));
+        assert!(vs_out.contains(&PartialValue::ExternalId(
+            "ad".to_string(),
+            "f4d3ba3c-6fb6-4d4c-9c8e-6d0d3e4e9b3a".to_string()
+        )));
+    }
+}
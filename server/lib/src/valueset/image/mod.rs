@@ -7,7 +7,7 @@ use crypto_glue::{s256::Sha256, traits::Digest};
 use hashbrown::HashSet;
 use image::codecs::gif::GifDecoder;
 use image::codecs::webp::WebPDecoder;
-use image::ImageDecoder;
+use image::{ImageDecoder, ImageFormat, ImageReader};
 use kanidm_proto::internal::{ImageType, ImageValue};
 use std::fmt::Display;
 use std::io::Cursor;
@@ -20,11 +20,14 @@ pub struct ValueSetImage {
 pub(crate) const MAX_IMAGE_HEIGHT: u32 = 1024;
 pub(crate) const MAX_IMAGE_WIDTH: u32 = 1024;
 pub(crate) const MAX_FILE_SIZE: u32 = 1024 * 256;
+pub(crate) const MAX_IMAGE_BYTES: u64 = MAX_FILE_SIZE as u64;
 
 const WEBP_MAGIC: &[u8; 4] = b"RIFF";
 
 pub mod jpg;
 pub mod png;
+#[cfg(feature = "webp-to-png")]
+pub mod webp;
 
 pub trait ImageValueThings {
     fn validate_image(&self) -> Result<(), ImageValidationError>;
@@ -34,6 +37,10 @@ pub trait ImageValueThings {
     fn validate_is_webp(&self) -> Result<(), ImageValidationError>;
     fn validate_is_svg(&self) -> Result<(), ImageValidationError>;
 
+    /// Read the width and height from the image header without decoding the
+    /// image content. Returns `None` for formats without fixed dimensions (SVG).
+    fn read_dimensions(&self) -> Result<Option<(u32, u32)>, ImageValidationError>;
+
     /// A sha256 of the filename/type/contents
     fn hash_imagevalue(&self) -> String;
 
@@ -202,6 +209,26 @@ impl ImageValueThings for ImageValue {
         }
     }
 
+    fn read_dimensions(&self) -> Result<Option<(u32, u32)>, ImageValidationError> {
+        let format = match self.filetype {
+            ImageType::Svg => return Ok(None),
+            ImageType::Png => return png::png_dimensions(&self.contents).map(Some),
+            ImageType::Gif => ImageFormat::Gif,
+            ImageType::Jpg => ImageFormat::Jpeg,
+            ImageType::Webp => ImageFormat::WebP,
+        };
+
+        ImageReader::with_format(Cursor::new(&self.contents[..]), format)
+            .into_dimensions()
+            .map(Some)
+            .map_err(|err| {
+                ImageValidationError::InvalidImage(format!(
+                    "Failed to read dimensions of {}: {:?}",
+                    self.filename, err
+                ))
+            })
+    }
+
     /// A sha256 of the filename/type/contents, uses openssl so has to live here
     /// because proto don't need that jazz
     fn hash_imagevalue(&self) -> String {
@@ -214,17 +241,72 @@ impl ImageValueThings for ImageValue {
     }
 }
 
+/// The largest images that may be stored. These default to the compiled maximums,
+/// and may be lowered by the domain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ImageLimits {
+    pub width: u32,
+    pub height: u32,
+    pub bytes: u64,
+}
+
+impl Default for ImageLimits {
+    fn default() -> Self {
+        ImageLimits {
+            width: MAX_IMAGE_WIDTH,
+            height: MAX_IMAGE_HEIGHT,
+            bytes: MAX_IMAGE_BYTES,
+        }
+    }
+}
+
+/// Check an image against the maximum dimensions and file size. Only the image
+/// header is read, so this is cheap enough to run before full validation. If the
+/// header can't be read the image is left for [ImageValueThings::validate_image]
+/// to reject.
+pub(crate) fn check_image_limits(
+    image: &ImageValue,
+    limits: &ImageLimits,
+) -> Result<(), SchemaError> {
+    let bytes = image.contents.len() as u64;
+    let (width, height) = image.read_dimensions().ok().flatten().unwrap_or_default();
+
+    if width > limits.width || height > limits.height || bytes > limits.bytes {
+        Err(SchemaError::ImageTooLarge {
+            width,
+            height,
+            bytes,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Validate an image before it's stored. With the `webp-to-png` feature, WebP
+/// images are also converted to PNG at this point.
+fn prepare_image(image: ImageValue) -> Result<ImageValue, ImageValidationError> {
+    image.validate_image()?;
+
+    #[cfg(feature = "webp-to-png")]
+    if image.filetype == ImageType::Webp {
+        return webp::convert_to_png(&image);
+    }
+
+    Ok(image)
+}
+
 impl ValueSetImage {
     pub fn new(image: ImageValue) -> Box<Self> {
         let mut set = HashSet::new();
-        match image.validate_image() {
-            Ok(_) => {
+        let filename = image.filename.clone();
+        match prepare_image(image) {
+            Ok(image) => {
                 set.insert(image);
             }
             Err(err) => {
                 admin_error!(
                     "Image {} didn't pass validation, not adding to value! Error: {:?}",
-                    image.filename,
+                    filename,
                     err
                 );
             }
@@ -234,8 +316,8 @@ impl ValueSetImage {
 
     // add the image, return a bool if there was a change
     pub fn push(&mut self, image: ImageValue) -> bool {
-        match image.validate_image() {
-            Ok(_) => self.set.insert(image),
+        match prepare_image(image) {
+            Ok(image) => self.set.insert(image),
             Err(err) => {
                 admin_error!(
                     "Image didn't pass validation, not adding to value! Error: {}",
@@ -271,8 +353,8 @@ impl ValueSetImage {
     {
         let mut set: HashSet<ImageValue> = HashSet::new();
         for image in iter {
-            match image.validate_image() {
-                Ok(_) => set.insert(image),
+            match prepare_image(image) {
+                Ok(image) => set.insert(image),
                 Err(err) => {
                     admin_error!(
                         "Image didn't pass validation, not adding to value! Error: {}",
@@ -506,6 +588,78 @@ mod tests {
         assert!(!image.hash_imagevalue().is_empty());
     }
 
+    #[test]
+    fn test_image_limits() {
+        use super::{check_image_limits, ImageLimits};
+        use crate::prelude::SchemaError;
+
+        let limits = ImageLimits::default();
+
+        let load = |name: &str, extension: &str| ImageValue {
+            filename: format!("{name}.{extension}"),
+            filetype: ImageType::try_from(extension).unwrap(),
+            contents: std::fs::read(format!(
+                "{}/src/valueset/image/test_images/{name}.{extension}",
+                env!("CARGO_MANIFEST_DIR")
+            ))
+            .unwrap(),
+        };
+
+        ["gif", "png", "jpg", "webp"]
+            .into_iter()
+            .for_each(|extension| {
+                let image = load("ok", extension);
+                assert!(image.read_dimensions().unwrap().is_some());
+                assert_eq!(check_image_limits(&image, &limits), Ok(()));
+            });
+
+        // The oversize webp test image isn't actually a webp, so the header
+        // can't be read and it's left for validate_image to reject.
+        let image = load("oversize_dimensions", "webp");
+        assert!(image.read_dimensions().is_err());
+        assert_eq!(check_image_limits(&image, &limits), Ok(()));
+
+        ["gif", "png", "jpg"].into_iter().for_each(|extension| {
+            let image = load("oversize_dimensions", extension);
+            assert!(matches!(
+                check_image_limits(&image, &limits),
+                Err(SchemaError::ImageTooLarge { width, height, .. })
+                    if width > 1024 || height > 1024
+            ));
+        });
+
+        // No fixed dimensions, so only the size is checked.
+        let image = load("ok", "svg");
+        assert_eq!(image.read_dimensions().unwrap(), None);
+        assert_eq!(check_image_limits(&image, &limits), Ok(()));
+
+        let image = ImageValue {
+            filename: "big.svg".to_string(),
+            filetype: ImageType::Svg,
+            contents: vec![b' '; 1024 * 257],
+        };
+        assert_eq!(
+            check_image_limits(&image, &limits),
+            Err(SchemaError::ImageTooLarge {
+                width: 0,
+                height: 0,
+                bytes: 1024 * 257
+            })
+        );
+
+        // Lower limits reject images that are otherwise acceptable.
+        let image = load("ok", "png");
+        let (width, _) = image.read_dimensions().unwrap().unwrap();
+        let limits = ImageLimits {
+            width: width - 1,
+            ..ImageLimits::default()
+        };
+        assert!(matches!(
+            check_image_limits(&image, &limits),
+            Err(SchemaError::ImageTooLarge { .. })
+        ));
+    }
+
     // This test is broken on github as it appears to be changing the binary image hash.
     /*
     #[test]
@@ -99,6 +99,25 @@ fn png_validate_ihdr(chunk_type: &[u8], chunk_data: &[u8]) -> Result<(), ImageVa
     Ok(())
 }
 
+/// Read the dimensions of a PNG from its IHDR chunk.
+pub fn png_dimensions(contents: &[u8]) -> Result<(u32, u32), ImageValidationError> {
+    let buf = contents
+        .strip_prefix(PNG_PRELUDE)
+        .ok_or(ImageValidationError::InvalidPngPrelude)?;
+    let (chunk_type, chunk_data, _) = png_split_chunk(buf)?;
+
+    if chunk_type != PNG_CHUNK_IHDR {
+        return Err(ImageValidationError::InvalidImage(
+            "PNG first chunk must be IHDR".to_string(),
+        ));
+    }
+
+    let corrupt = || ImageValidationError::InvalidImage("PNG corrupt!".to_string());
+    let (width, chunk_data) = chunk_data.split_first_chunk::<4>().ok_or_else(corrupt)?;
+    let (height, _) = chunk_data.split_first_chunk::<4>().ok_or_else(corrupt)?;
+    Ok((u32::from_be_bytes(*width), u32::from_be_bytes(*height)))
+}
+
 /// Loop over the PNG file contents to find out if we've got valid chunks
 fn png_consume_chunks_until_iend(
     buf: &[u8],
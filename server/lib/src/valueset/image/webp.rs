@@ -0,0 +1,72 @@
+use super::{ImageValidationError, ImageValueThings};
+use image::codecs::webp::WebPDecoder;
+use image::{DynamicImage, ImageDecoder};
+use kanidm_proto::internal::{ImageType, ImageValue};
+use std::io::Cursor;
+use std::path::Path;
+
+/// Decode a WebP image and re-encode it as a PNG, so that clients without WebP
+/// support are still able to display it. The resulting PNG is validated against
+/// the same limits as any other uploaded PNG.
+pub fn convert_to_png(image: &ImageValue) -> Result<ImageValue, ImageValidationError> {
+    let mut decoder = WebPDecoder::new(Cursor::new(&image.contents[..])).map_err(|err| {
+        ImageValidationError::InvalidImage(format!("Failed to parse WebP file: {err:?}"))
+    })?;
+
+    decoder
+        .set_limits(image.get_limits())
+        .map_err(|_| ImageValidationError::ExceedsMaxDimensions)?;
+
+    let rgba = DynamicImage::from_decoder(decoder)
+        .map_err(|err| {
+            ImageValidationError::InvalidImage(format!("Failed to decode WebP file: {err:?}"))
+        })?
+        .into_rgba8();
+
+    let contents = lodepng::encode_memory(
+        rgba.as_raw(),
+        rgba.width() as usize,
+        rgba.height() as usize,
+        lodepng::ColorType::RGBA,
+        8,
+    )
+    .map_err(|err| ImageValidationError::InvalidImage(format!("Failed to encode PNG: {err:?}")))?;
+
+    let filename = Path::new(&image.filename)
+        .with_extension("png")
+        .to_string_lossy()
+        .into_owned();
+
+    let png = ImageValue::new(filename, ImageType::Png, contents);
+    png.validate_image()?;
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::convert_to_png;
+    use crate::valueset::image::ImageValueThings;
+    use kanidm_proto::internal::{ImageType, ImageValue};
+
+    #[test]
+    fn test_webp_convert_to_png() {
+        let filename = format!(
+            "{}/src/valueset/image/test_images/ok.webp",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        let image = ImageValue::new(
+            "ok.webp".to_string(),
+            ImageType::Webp,
+            std::fs::read(filename).unwrap(),
+        );
+
+        let png = convert_to_png(&image).unwrap();
+        assert_eq!(png.filetype, ImageType::Png);
+        assert_eq!(png.filename, "ok.png");
+        assert!(png.validate_image().is_ok());
+
+        // Garbage isn't converted.
+        let image = ImageValue::new("bad.webp".to_string(), ImageType::Webp, vec![0, 1, 2]);
+        assert!(convert_to_png(&image).is_err());
+    }
+}
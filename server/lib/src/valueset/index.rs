@@ -1,3 +1,4 @@
+use crate::be::dbvalue::DbValueIndexTypeV1;
 use crate::prelude::*;
 use crate::schema::SchemaAttribute;
 use crate::valueset::ScimResolveStatus;
@@ -22,8 +23,11 @@ impl ValueSetIndex {
         self.set.insert(s)
     }
 
-    pub fn from_dbvs2(data: Vec<u16>) -> Result<ValueSet, OperationError> {
-        let set: Result<_, _> = data.into_iter().map(IndexType::try_from).collect();
+    pub fn from_dbvs2(data: Vec<DbValueIndexTypeV1>) -> Result<ValueSet, OperationError> {
+        let set: Result<_, _> = data
+            .into_iter()
+            .map(|v| IndexType::try_from(u16::from(v)))
+            .collect();
         let set = set.map_err(|_| OperationError::InvalidValueState)?;
         Ok(Box::new(ValueSetIndex { set }))
     }
@@ -137,7 +141,12 @@ impl ValueSetT for ValueSetIndex {
     }
 
     fn to_db_valueset_v2(&self) -> DbValueSetV2 {
-        DbValueSetV2::IndexType(self.set.iter().map(|s| *s as u16).collect())
+        DbValueSetV2::IndexType(
+            self.set
+                .iter()
+                .map(|s| DbValueIndexTypeV1::from(*s as u16))
+                .collect(),
+        )
     }
 
     fn to_partialvalue_iter(&self) -> Box<dyn Iterator<Item = PartialValue> + '_> {
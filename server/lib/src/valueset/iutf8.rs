@@ -6,6 +6,7 @@ use crate::valueset::ScimResolveStatus;
 use crate::valueset::{DbValueSetV2, ValueSet, ValueSetResolveStatus, ValueSetScimPut};
 use kanidm_proto::scim_v1::client::ScimStrings;
 use kanidm_proto::scim_v1::JsonValue;
+use std::cmp::Ordering;
 use std::collections::BTreeSet;
 
 #[derive(Debug, Clone)]
@@ -187,6 +188,15 @@ impl ValueSetT for ValueSetIutf8 {
         }
     }
 
+    fn cmp(&self, other: &ValueSet) -> Ordering {
+        if let Some(other) = other.as_iutf8_set() {
+            self.set.cmp(other)
+        } else {
+            debug_assert!(false);
+            Ordering::Equal
+        }
+    }
+
     fn merge(&mut self, other: &ValueSet) -> Result<(), OperationError> {
         if let Some(b) = other.as_iutf8_set() {
             mergesets!(self.set, b)
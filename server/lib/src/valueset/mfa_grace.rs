@@ -0,0 +1,188 @@
+use crate::be::dbvalue::{DbCidV1, DbValueMfaGraceV1};
+use crate::prelude::*;
+use crate::repl::cid::Cid;
+use crate::schema::SchemaAttribute;
+use crate::valueset::{DbValueSetV2, ScimResolveStatus, ValueSet};
+use std::collections::BTreeMap;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+/// Grace windows in which an account may still authenticate without MFA, keyed by
+/// the change that granted them, with the time at which each window expires.
+#[derive(Debug, Clone)]
+pub struct ValueSetMfaGrace {
+    map: BTreeMap<Cid, OffsetDateTime>,
+}
+
+impl ValueSetMfaGrace {
+    pub fn new(granted_at: Cid, expires_at: OffsetDateTime) -> Box<Self> {
+        let mut map = BTreeMap::new();
+        map.insert(granted_at, expires_at);
+        Box::new(ValueSetMfaGrace { map })
+    }
+
+    pub fn from_dbvs2(data: Vec<DbValueMfaGraceV1>) -> Result<ValueSet, OperationError> {
+        let map = data
+            .into_iter()
+            .map(|dbv| {
+                OffsetDateTime::parse(dbv.expires_at(), &Rfc3339)
+                    .map(|odt| (Cid::from(dbv.granted_at().clone()), odt))
+                    .map_err(|err| {
+                        error!(?err, "Invalid MfaGrace expiry");
+                        OperationError::InvalidValueState
+                    })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Box::new(ValueSetMfaGrace { map }))
+    }
+
+    /// Returns `true` if any grace window is still open at `ct`.
+    pub fn is_active(&self, ct: OffsetDateTime) -> bool {
+        self.map.values().any(|expires_at| ct < *expires_at)
+    }
+}
+
+impl ValueSetT for ValueSetMfaGrace {
+    fn insert_checked(&mut self, value: Value) -> Result<bool, OperationError> {
+        match value {
+            Value::MfaGrace(granted_at, expires_at) => {
+                Ok(self.map.insert(granted_at, expires_at).is_none())
+            }
+            _ => {
+                debug_assert!(false);
+                Err(OperationError::InvalidValueState)
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    fn remove(&mut self, pv: &PartialValue, _cid: &Cid) -> bool {
+        match pv {
+            PartialValue::Cid(granted_at) => self.map.remove(granted_at).is_some(),
+            _ => {
+                debug_assert!(false);
+                false
+            }
+        }
+    }
+
+    fn contains(&self, pv: &PartialValue) -> bool {
+        match pv {
+            PartialValue::Cid(granted_at) => self.map.contains_key(granted_at),
+            _ => false,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    fn generate_idx_eq_keys(&self) -> Vec<String> {
+        Vec::with_capacity(0)
+    }
+
+    fn syntax(&self) -> SyntaxType {
+        SyntaxType::MfaGrace
+    }
+
+    fn validate(&self, _schema_attr: &SchemaAttribute) -> bool {
+        true
+    }
+
+    fn to_proto_string_clone_iter(&self) -> Box<dyn Iterator<Item = String> + '_> {
+        Box::new(self.map.iter().map(|(granted_at, expires_at)| {
+            #[allow(clippy::expect_used)]
+            let expires_at = expires_at
+                .format(&Rfc3339)
+                .expect("Failed to format timestamp into RFC3339");
+            format!("{granted_at}: {expires_at}")
+        }))
+    }
+
+    fn to_scim_value(&self) -> Option<ScimResolveStatus> {
+        Some(ScimResolveStatus::Resolved(ScimValueKanidm::from(
+            self.map.values().copied().collect::<Vec<_>>(),
+        )))
+    }
+
+    fn to_db_valueset_v2(&self) -> DbValueSetV2 {
+        DbValueSetV2::MfaGrace(
+            self.map
+                .iter()
+                .filter_map(|(granted_at, expires_at)| {
+                    let expires_at = expires_at.format(&Rfc3339).ok()?;
+                    DbValueMfaGraceV1::new(DbCidV1::from(granted_at.clone()), expires_at)
+                })
+                .collect(),
+        )
+    }
+
+    fn to_partialvalue_iter(&self) -> Box<dyn Iterator<Item = PartialValue> + '_> {
+        Box::new(self.map.keys().cloned().map(PartialValue::Cid))
+    }
+
+    fn to_value_iter(&self) -> Box<dyn Iterator<Item = Value> + '_> {
+        Box::new(
+            self.map
+                .iter()
+                .map(|(granted_at, expires_at)| Value::MfaGrace(granted_at.clone(), *expires_at)),
+        )
+    }
+
+    fn equal(&self, other: &ValueSet) -> bool {
+        if let Some(other) = other.as_mfa_grace_map() {
+            &self.map == other
+        } else {
+            debug_assert!(false);
+            false
+        }
+    }
+
+    fn merge(&mut self, other: &ValueSet) -> Result<(), OperationError> {
+        if let Some(b) = other.as_mfa_grace_map() {
+            mergemaps!(self.map, b)
+        } else {
+            debug_assert!(false);
+            Err(OperationError::InvalidValueState)
+        }
+    }
+
+    fn as_mfa_grace_map(&self) -> Option<&BTreeMap<Cid, OffsetDateTime>> {
+        Some(&self.map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ValueSetMfaGrace;
+    use crate::prelude::*;
+    use crate::repl::cid::Cid;
+    use std::time::Duration;
+    use time::OffsetDateTime;
+
+    #[test]
+    fn test_valueset_mfa_grace_db_round_trip() {
+        let granted_at = Cid::new_count(1);
+        let expires_at = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        let vs: ValueSet = ValueSetMfaGrace::new(granted_at.clone(), expires_at);
+        assert!(vs.contains(&PartialValue::Cid(granted_at)));
+
+        let vs_out =
+            crate::valueset::from_db_valueset_v2(vs.to_db_valueset_v2()).expect("Failed to load");
+        assert_eq!(vs_out.syntax(), SyntaxType::MfaGrace);
+        assert!(vs.equal(&vs_out));
+    }
+
+    #[test]
+    fn test_valueset_mfa_grace_is_active() {
+        let expires_at = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let vs = ValueSetMfaGrace::new(Cid::new_count(1), expires_at);
+
+        assert!(vs.is_active(expires_at - Duration::from_secs(1)));
+        assert!(!vs.is_active(expires_at));
+    }
+}
@@ -1,11 +1,12 @@
 use crate::be::dbvalue::DbValueSetV2;
-use crate::credential::{apppwd::ApplicationPassword, totp::Totp, Credential};
+use crate::credential::{apppwd::ApplicationPassword, totp::Totp, Credential, Password};
 use crate::prelude::*;
 use crate::repl::cid::Cid;
 use crate::schema::SchemaAttribute;
 use crate::server::keys::KeyId;
 use crate::value::{
-    Address, ApiToken, CredentialType, IntentTokenState, Oauth2Session, OauthClaimMapJoin, Session,
+    Address, ApiToken, AttestedPasskeyAttestation, CredentialType, IntentTokenState, Oauth2Session,
+    OauthClaimMapJoin, Session,
 };
 use compact_jwt::{crypto::JwsRs256Signer, JwsEs256Signer};
 use crypto_glue::{s256::Sha256Output, x509::Certificate};
@@ -30,6 +31,7 @@ use self::apppwd::ValueSetApplicationPassword;
 pub use self::auditlogstring::{ValueSetAuditLogString, AUDIT_LOG_STRING_CAPACITY};
 pub use self::binary::{ValueSetPrivateBinary, ValueSetPublicBinary};
 pub use self::bool::ValueSetBool;
+pub use self::break_glass::ValueSetBreakGlass;
 pub use self::certificate::ValueSetCertificate;
 pub use self::cid::ValueSetCid;
 pub use self::cred::{
@@ -37,6 +39,8 @@ pub use self::cred::{
     ValueSetPasskey, ValueSetWebauthnAttestationCaList,
 };
 pub use self::datetime::ValueSetDateTime;
+pub use self::extension_json::ValueSetExtensionJson;
+pub use self::external_id::ValueSetExternalId;
 pub use self::hexstring::ValueSetHexString;
 use self::image::ValueSetImage;
 pub use self::iname::ValueSetIname;
@@ -47,6 +51,7 @@ pub use self::json::{ValueSetJson, ValueSetJsonFilter};
 pub use self::jws::{ValueSetJwsKeyEs256, ValueSetJwsKeyRs256};
 pub use self::key_internal::{KeyInternalData, ValueSetKeyInternal};
 pub use self::message::ValueSetMessage;
+pub use self::mfa_grace::ValueSetMfaGrace;
 pub use self::nsuniqueid::ValueSetNsUniqueId;
 pub use self::oauth::{
     OauthClaimMapping, ValueSetOauthClaimMap, ValueSetOauthScope, ValueSetOauthScopeMap,
@@ -71,10 +76,13 @@ mod apppwd;
 mod auditlogstring;
 mod binary;
 mod bool;
+mod break_glass;
 mod certificate;
 mod cid;
 mod cred;
 mod datetime;
+mod extension_json;
+mod external_id;
 mod hexstring;
 pub mod image;
 mod iname;
@@ -85,6 +93,7 @@ mod json;
 mod jws;
 mod key_internal;
 mod message;
+mod mfa_grace;
 mod nsuniqueid;
 mod oauth;
 mod restricted;
@@ -340,6 +349,26 @@ pub trait ValueSetT: std::fmt::Debug + DynClone {
         None
     }
 
+    fn as_external_id_set(&self) -> Option<&BTreeSet<(String, String)>> {
+        debug_assert!(false);
+        None
+    }
+
+    fn as_mfa_grace_map(&self) -> Option<&BTreeMap<Cid, OffsetDateTime>> {
+        debug_assert!(false);
+        None
+    }
+
+    fn as_break_glass(&self) -> Option<(&BTreeMap<Uuid, Password>, &BTreeSet<Uuid>)> {
+        debug_assert!(false);
+        None
+    }
+
+    fn as_extension_json_map(&self) -> Option<&BTreeMap<String, JsonValue>> {
+        debug_assert!(false);
+        None
+    }
+
     fn as_url_set(&self) -> Option<&SmolSet<[Url; 1]>> {
         debug_assert!(false);
         None
@@ -414,6 +443,13 @@ pub trait ValueSetT: std::fmt::Debug + DynClone {
         None
     }
 
+    fn as_attestedpasskey_attestation_map(
+        &self,
+    ) -> Option<&BTreeMap<Uuid, AttestedPasskeyAttestation>> {
+        debug_assert!(false);
+        None
+    }
+
     fn as_webauthn_attestation_ca_list(&self) -> Option<&AttestationCaList> {
         debug_assert!(false);
         None
@@ -737,6 +773,7 @@ pub struct UnresolvedScimValueOauth2ClaimMap {
     pub claim: String,
     pub join_char: ScimOauth2ClaimMapJoinChar,
     pub values: BTreeSet<String>,
+    pub typed_values: Vec<JsonValue>,
 }
 
 pub struct UnresolvedScimValueOauth2ScopeMap {
@@ -827,6 +864,7 @@ pub struct UnresolvedValueSetOauth2ClaimMap {
     pub claim: String,
     pub join_char: OauthClaimMapJoin,
     pub claim_values: BTreeSet<String>,
+    pub typed_claim_values: Vec<JsonValue>,
 }
 
 pub struct ResolvedValueSetOauth2ClaimMap {
@@ -834,6 +872,7 @@ pub struct ResolvedValueSetOauth2ClaimMap {
     pub claim: String,
     pub join_char: OauthClaimMapJoin,
     pub claim_values: BTreeSet<String>,
+    pub typed_claim_values: Vec<JsonValue>,
 }
 
 pub struct UnresolvedValueSetOauth2ScopeMap {
@@ -878,6 +917,10 @@ pub fn from_result_value_iter(
         Value::Cid(u) => ValueSetCid::new(u),
         Value::JsonFilt(u) => ValueSetJsonFilter::new(u),
         Value::Nsuniqueid(u) => ValueSetNsUniqueId::new(u),
+        Value::ExternalId(system, identifier) => ValueSetExternalId::new(system, identifier),
+        Value::MfaGrace(granted_at, expires_at) => ValueSetMfaGrace::new(granted_at, expires_at),
+        Value::BreakGlass(id, code) => ValueSetBreakGlass::new(id, code),
+        Value::ExtensionJson(urn, data) => ValueSetExtensionJson::new(urn, data),
         Value::Url(u) => ValueSetUrl::new(u),
         Value::DateTime(u) => ValueSetDateTime::new(u),
         Value::PrivateBinary(u) => ValueSetPrivateBinary::new(u),
@@ -898,13 +941,14 @@ pub fn from_result_value_iter(
         | Value::PhoneNumber(_, _)
         | Value::ApplicationPassword(_)
         | Value::Passkey(_, _, _)
-        | Value::AttestedPasskey(_, _, _)
+        | Value::AttestedPasskey(..)
         | Value::TotpSecret(_, _)
         | Value::Session(_, _)
         | Value::ApiToken(_, _)
         | Value::Oauth2Session(_, _)
         | Value::OauthClaimMap(_, _)
         | Value::OauthClaimValue(_, _, _)
+        | Value::OauthClaimTypedValue(_, _, _)
         | Value::JwsKeyEs256(_)
         | Value::JwsKeyRs256(_)
         | Value::HexString(_)
@@ -948,6 +992,10 @@ pub fn from_value_iter(mut iter: impl Iterator<Item = Value>) -> Result<ValueSet
         Value::Cid(u) => ValueSetCid::new(u),
         Value::JsonFilt(u) => ValueSetJsonFilter::new(u),
         Value::Nsuniqueid(u) => ValueSetNsUniqueId::new(u),
+        Value::ExternalId(system, identifier) => ValueSetExternalId::new(system, identifier),
+        Value::MfaGrace(granted_at, expires_at) => ValueSetMfaGrace::new(granted_at, expires_at),
+        Value::BreakGlass(id, code) => ValueSetBreakGlass::new(id, code),
+        Value::ExtensionJson(urn, data) => ValueSetExtensionJson::new(urn, data),
         Value::Url(u) => ValueSetUrl::new(u),
         Value::DateTime(u) => ValueSetDateTime::new(u),
         Value::PrivateBinary(u) => ValueSetPrivateBinary::new(u),
@@ -960,7 +1008,7 @@ pub fn from_value_iter(mut iter: impl Iterator<Item = Value>) -> Result<ValueSet
         Value::IntentToken(u, s) => ValueSetIntentToken::new(u, s),
         Value::EmailAddress(a, _) => ValueSetEmailAddress::new(a),
         Value::Passkey(u, t, k) => ValueSetPasskey::new(u, t, k),
-        Value::AttestedPasskey(u, t, k) => ValueSetAttestedPasskey::new(u, t, k),
+        Value::AttestedPasskey(u, t, k, a) => ValueSetAttestedPasskey::new(u, t, k, a),
         Value::JwsKeyEs256(k) => ValueSetJwsKeyEs256::new(k),
         Value::JwsKeyRs256(k) => ValueSetJwsKeyRs256::new(k),
         Value::Session(u, m) => ValueSetSession::new(u, m),
@@ -978,6 +1026,9 @@ pub fn from_value_iter(mut iter: impl Iterator<Item = Value>) -> Result<ValueSet
         Value::OauthClaimValue(name, group, claims) => {
             ValueSetOauthClaimMap::new_value(name, group, claims)
         }
+        Value::OauthClaimTypedValue(name, group, claims) => {
+            ValueSetOauthClaimMap::new_typed_value(name, group, claims)
+        }
         Value::HexString(s) => ValueSetHexString::new(s),
 
         Value::KeyInternal {
@@ -1068,6 +1119,10 @@ pub fn from_db_valueset_v2(dbvs: DbValueSetV2) -> Result<ValueSet, OperationErro
         DbValueSetV2::Sha256(set) => ValueSetSha256::from_dbvs2(set),
         DbValueSetV2::Message(object) => Ok(ValueSetMessage::new(object)),
         DbValueSetV2::EcKeyPrivate(_key) => Err(OperationError::InvalidState),
+        DbValueSetV2::ExternalId(set) => ValueSetExternalId::from_dbvs2(set),
+        DbValueSetV2::MfaGrace(set) => ValueSetMfaGrace::from_dbvs2(set),
+        DbValueSetV2::BreakGlass(set) => ValueSetBreakGlass::from_dbvs2(set),
+        DbValueSetV2::ExtensionJson(set) => ValueSetExtensionJson::from_dbvs2(set),
     }
 }
 
@@ -439,6 +439,9 @@ impl ValueSetT for ValueSetOauthScopeMap {
 pub struct OauthClaimMapping {
     join: OauthClaimMapJoin,
     values: BTreeMap<Uuid, BTreeSet<String>>,
+    /// Claim values that are not strings, such as booleans, numbers or objects. These
+    /// are only able to be stored in a V2 claim map.
+    typed_values: BTreeMap<Uuid, Vec<JsonValue>>,
 }
 
 impl OauthClaimMapping {
@@ -449,6 +452,18 @@ impl OauthClaimMapping {
     pub(crate) fn values(&self) -> &BTreeMap<Uuid, BTreeSet<String>> {
         &self.values
     }
+
+    pub(crate) fn typed_values(&self) -> &BTreeMap<Uuid, Vec<JsonValue>> {
+        &self.typed_values
+    }
+
+    pub(crate) fn group_uuids(&self) -> impl Iterator<Item = &Uuid> {
+        self.values.keys().chain(
+            self.typed_values
+                .keys()
+                .filter(|u| !self.values.contains_key(u)),
+        )
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -462,6 +477,7 @@ impl ValueSetOauthClaimMap {
         let mapping = OauthClaimMapping {
             join,
             values: BTreeMap::default(),
+            typed_values: BTreeMap::default(),
         };
         let mut map = BTreeMap::new();
         map.insert(claim, mapping);
@@ -475,6 +491,24 @@ impl ValueSetOauthClaimMap {
         let mapping = OauthClaimMapping {
             join: OauthClaimMapJoin::default(),
             values,
+            typed_values: BTreeMap::default(),
+        };
+
+        let mut map = BTreeMap::new();
+        map.insert(claim, mapping);
+        Box::new(ValueSetOauthClaimMap { map })
+    }
+
+    pub(crate) fn new_typed_value(claim: String, group: Uuid, claims: Vec<JsonValue>) -> Box<Self> {
+        let mut typed_values = BTreeMap::default();
+        if !claims.is_empty() {
+            typed_values.insert(group, claims);
+        }
+
+        let mapping = OauthClaimMapping {
+            join: OauthClaimMapJoin::default(),
+            values: BTreeMap::default(),
+            typed_values,
         };
 
         let mut map = BTreeMap::new();
@@ -485,14 +519,32 @@ impl ValueSetOauthClaimMap {
     pub(crate) fn from_dbvs2(data: Vec<DbValueOauthClaimMap>) -> Result<ValueSet, OperationError> {
         let map = data
             .into_iter()
-            .map(|db_claim_map| match db_claim_map {
-                DbValueOauthClaimMap::V1 { name, join, values } => (
-                    name.clone(),
+            .map(|db_claim_map| {
+                // V1 maps are upgraded in place, string values remain string values.
+                let (name, join, db_values) = db_claim_map.into_v2();
+
+                let mut values: BTreeMap<Uuid, BTreeSet<String>> = BTreeMap::default();
+                let mut typed_values: BTreeMap<Uuid, Vec<JsonValue>> = BTreeMap::default();
+
+                for (group_uuid, claim_values) in db_values {
+                    for claim_value in claim_values {
+                        match claim_value {
+                            JsonValue::String(s) => {
+                                values.entry(group_uuid).or_default().insert(s);
+                            }
+                            typed => typed_values.entry(group_uuid).or_default().push(typed),
+                        }
+                    }
+                }
+
+                (
+                    name,
                     OauthClaimMapping {
                         join: join.into(),
-                        values: values.clone(),
+                        values,
+                        typed_values,
                     },
-                ),
+                )
             })
             .collect();
         Ok(Box::new(ValueSetOauthClaimMap { map }))
@@ -506,30 +558,50 @@ impl ValueSetOauthClaimMap {
             claim,
             join_char,
             claim_values,
+            typed_claim_values,
         } in resolved.into_iter()
         {
+            // A group with only typed values doesn't need an empty set of strings.
+            let claim_values =
+                (typed_claim_values.is_empty() || !claim_values.is_empty()).then_some(claim_values);
+
             match map.entry(claim) {
                 BTreeEntry::Vacant(e) => {
                     let mut values = BTreeMap::default();
-                    values.insert(group_uuid, claim_values);
+                    if let Some(claim_values) = claim_values {
+                        values.insert(group_uuid, claim_values);
+                    }
+
+                    let mut typed_values = BTreeMap::default();
+                    if !typed_claim_values.is_empty() {
+                        typed_values.insert(group_uuid, typed_claim_values);
+                    }
 
                     let claim_map = OauthClaimMapping {
                         join: join_char,
                         values,
+                        typed_values,
                     };
                     e.insert(claim_map);
                 }
                 BTreeEntry::Occupied(mut e) => {
                     // Just add the uuid/value, this claim name already exists.
                     let mapping_mut = e.get_mut();
-                    match mapping_mut.values.entry(group_uuid) {
-                        BTreeEntry::Vacant(e) => {
-                            e.insert(claim_values);
-                        }
-                        BTreeEntry::Occupied(mut e) => {
-                            e.insert(claim_values);
+                    if let Some(claim_values) = claim_values {
+                        match mapping_mut.values.entry(group_uuid) {
+                            BTreeEntry::Vacant(e) => {
+                                e.insert(claim_values);
+                            }
+                            BTreeEntry::Occupied(mut e) => {
+                                e.insert(claim_values);
+                            }
                         }
                     }
+                    if !typed_claim_values.is_empty() {
+                        mapping_mut
+                            .typed_values
+                            .insert(group_uuid, typed_claim_values);
+                    }
                 }
             }
         }
@@ -538,11 +610,13 @@ impl ValueSetOauthClaimMap {
     }
 
     fn trim(&mut self) {
-        self.map
-            .values_mut()
-            .for_each(|mapping_mut| mapping_mut.values.retain(|_k, v| !v.is_empty()));
+        self.map.values_mut().for_each(|mapping_mut| {
+            mapping_mut.values.retain(|_k, v| !v.is_empty());
+            mapping_mut.typed_values.retain(|_k, v| !v.is_empty());
+        });
 
-        self.map.retain(|_k, v| !v.values.is_empty());
+        self.map
+            .retain(|_k, v| !v.values.is_empty() || !v.typed_values.is_empty());
     }
 }
 
@@ -566,10 +640,20 @@ impl ValueSetScimPut for ValueSetOauthClaimMap {
             claim,
             join_char,
             values: claim_values,
+            typed_values: typed_claim_values,
         } in claim_maps.into_iter()
         {
             let join_char = OauthClaimMapJoin::from(join_char);
 
+            // String values belong in values, so that they are not changed by a reload.
+            if typed_claim_values
+                .iter()
+                .any(|v| matches!(v, JsonValue::Null | JsonValue::String(_)))
+            {
+                error!("SCIM Oauth2ClaimMap typed values must not be null or strings");
+                return Err(OperationError::SC0022Oauth2ClaimMapSyntaxInvalid);
+            }
+
             match (group_uuid, group) {
                 (None, None) => {
                     error!("SCIM Oauth2ClaimMap a group name or uuid must be present");
@@ -580,12 +664,14 @@ impl ValueSetScimPut for ValueSetOauthClaimMap {
                     claim,
                     join_char,
                     claim_values,
+                    typed_claim_values,
                 }),
                 (None, Some(group_name)) => unresolved.push(UnresolvedValueSetOauth2ClaimMap {
                     group_name,
                     claim,
                     join_char,
                     claim_values,
+                    typed_claim_values,
                 }),
             }
         }
@@ -603,8 +689,10 @@ impl ValueSetT for ValueSetOauthClaimMap {
     fn insert_checked(&mut self, value: Value) -> Result<bool, OperationError> {
         match value {
             Value::OauthClaimValue(name, uuid, claims) => {
-                // Add a value to this group associated to this claim.
+                // Add a value to this group associated to this claim. An empty set of
+                // values removes the string values of this group.
                 match self.map.entry(name) {
+                    BTreeEntry::Vacant(_) if claims.is_empty() => Ok(false),
                     BTreeEntry::Vacant(e) => {
                         // New map/value. Use a default joiner.
                         let mut values = BTreeMap::default();
@@ -613,10 +701,14 @@ impl ValueSetT for ValueSetOauthClaimMap {
                         let claim_map = OauthClaimMapping {
                             join: OauthClaimMapJoin::default(),
                             values,
+                            typed_values: BTreeMap::default(),
                         };
                         e.insert(claim_map);
                         Ok(true)
                     }
+                    BTreeEntry::Occupied(mut e) if claims.is_empty() => {
+                        Ok(e.get_mut().values.remove(&uuid).is_some())
+                    }
                     BTreeEntry::Occupied(mut e) => {
                         // Just add the uuid/value, this claim name already exists.
                         let mapping_mut = e.get_mut();
@@ -633,6 +725,32 @@ impl ValueSetT for ValueSetOauthClaimMap {
                     }
                 }
             }
+            Value::OauthClaimTypedValue(name, uuid, claims) => {
+                // Replace the typed values of this group associated to this claim. An
+                // empty set of values removes them from the group.
+                match self.map.entry(name) {
+                    BTreeEntry::Vacant(_) if claims.is_empty() => Ok(false),
+                    BTreeEntry::Vacant(e) => {
+                        let mut typed_values = BTreeMap::default();
+                        typed_values.insert(uuid, claims);
+
+                        let claim_map = OauthClaimMapping {
+                            join: OauthClaimMapJoin::default(),
+                            values: BTreeMap::default(),
+                            typed_values,
+                        };
+                        e.insert(claim_map);
+                        Ok(true)
+                    }
+                    BTreeEntry::Occupied(mut e) if claims.is_empty() => {
+                        Ok(e.get_mut().typed_values.remove(&uuid).is_some())
+                    }
+                    BTreeEntry::Occupied(mut e) => {
+                        e.get_mut().typed_values.insert(uuid, claims);
+                        Ok(true)
+                    }
+                }
+            }
             Value::OauthClaimMap(name, join) => {
                 match self.map.entry(name) {
                     BTreeEntry::Vacant(e) => {
@@ -640,6 +758,7 @@ impl ValueSetT for ValueSetOauthClaimMap {
                         let claim_map = OauthClaimMapping {
                             join,
                             values: BTreeMap::default(),
+                            typed_values: BTreeMap::default(),
                         };
                         e.insert(claim_map);
                         Ok(true)
@@ -668,13 +787,15 @@ impl ValueSetT for ValueSetOauthClaimMap {
                 let mut contained = false;
                 for mapping_mut in self.map.values_mut() {
                     contained |= mapping_mut.values.remove(u).is_some();
+                    contained |= mapping_mut.typed_values.remove(u).is_some();
                 }
                 contained
             }
             PartialValue::OauthClaim(s, u) => {
                 // Remove a uuid from this claim type.
                 if let Some(mapping_mut) = self.map.get_mut(s) {
-                    mapping_mut.values.remove(u).is_some()
+                    let removed = mapping_mut.values.remove(u).is_some();
+                    mapping_mut.typed_values.remove(u).is_some() || removed
                 } else {
                     false
                 }
@@ -706,7 +827,8 @@ impl ValueSetT for ValueSetOauthClaimMap {
             PartialValue::Refer(u) => {
                 let mut contained = false;
                 for mapping in self.map.values() {
-                    contained |= mapping.values.contains_key(u);
+                    contained |=
+                        mapping.values.contains_key(u) || mapping.typed_values.contains_key(u);
                 }
                 contained
             }
@@ -740,7 +862,7 @@ impl ValueSetT for ValueSetOauthClaimMap {
             .cloned()
             .chain(
                 self.map.values().flat_map(|mapping| {
-                    mapping.values.keys().map(|u| u.as_hyphenated().to_string())
+                    mapping.group_uuids().map(|u| u.as_hyphenated().to_string())
                 }),
             )
             .collect()
@@ -762,6 +884,11 @@ impl ValueSetT for ValueSetOauthClaimMap {
                         .map(|claim_values| claim_values.is_empty())
                 })
                 .all(|is_empty| !is_empty)
+            && self
+                .map
+                .values()
+                .flat_map(|mapping| mapping.typed_values.values())
+                .all(|claim_values| !claim_values.is_empty())
             && self
                 .map
                 .values()
@@ -776,18 +903,29 @@ impl ValueSetT for ValueSetOauthClaimMap {
 
     fn to_proto_string_clone_iter(&self) -> Box<dyn Iterator<Item = String> + '_> {
         Box::new(self.map.iter().flat_map(|(name, mapping)| {
-            mapping.values.iter().map(move |(group, claims)| {
-                let join_str = mapping.join.to_str();
+            mapping
+                .values
+                .iter()
+                .map(move |(group, claims)| {
+                    let join_str = mapping.join.to_str();
 
-                let joined = str_concat!(claims, join_str);
+                    let joined = str_concat!(claims, join_str);
 
-                format!(
-                    "{}: {} \"{:?}\"",
-                    name,
-                    uuid_to_proto_string(*group),
-                    joined
-                )
-            })
+                    format!(
+                        "{}: {} \"{:?}\"",
+                        name,
+                        uuid_to_proto_string(*group),
+                        joined
+                    )
+                })
+                .chain(mapping.typed_values.iter().map(move |(group, claims)| {
+                    format!(
+                        "{}: {} {}",
+                        name,
+                        uuid_to_proto_string(*group),
+                        JsonValue::Array(claims.clone())
+                    )
+                }))
         }))
     }
 
@@ -796,14 +934,19 @@ impl ValueSetT for ValueSetOauthClaimMap {
             .map
             .iter()
             .flat_map(|(claim_name, mappings)| {
-                mappings.values.iter().map(|(group_uuid, claim_values)| {
-                    UnresolvedScimValueOauth2ClaimMap {
+                mappings
+                    .group_uuids()
+                    .map(|group_uuid| UnresolvedScimValueOauth2ClaimMap {
                         group_uuid: *group_uuid,
                         claim: claim_name.to_string(),
                         join_char: mappings.join.into(),
-                        values: claim_values.clone(),
-                    }
-                })
+                        values: mappings.values.get(group_uuid).cloned().unwrap_or_default(),
+                        typed_values: mappings
+                            .typed_values
+                            .get(group_uuid)
+                            .cloned()
+                            .unwrap_or_default(),
+                    })
             })
             .collect::<Vec<_>>();
 
@@ -816,10 +959,45 @@ impl ValueSetT for ValueSetOauthClaimMap {
         DbValueSetV2::OauthClaimMap(
             self.map
                 .iter()
-                .map(|(name, mapping)| DbValueOauthClaimMap::V1 {
-                    name: name.clone(),
-                    join: mapping.join.into(),
-                    values: mapping.values.clone(),
+                .map(|(name, mapping)| {
+                    // Only maps with typed values need V2, this keeps string only maps
+                    // readable by older servers.
+                    if mapping.typed_values.is_empty() {
+                        return DbValueOauthClaimMap::V1 {
+                            name: name.clone(),
+                            join: mapping.join.into(),
+                            values: mapping.values.clone(),
+                        };
+                    }
+
+                    let values = mapping
+                        .group_uuids()
+                        .map(|group_uuid| {
+                            let claim_values = mapping
+                                .values
+                                .get(group_uuid)
+                                .into_iter()
+                                .flatten()
+                                .cloned()
+                                .map(JsonValue::String)
+                                .chain(
+                                    mapping
+                                        .typed_values
+                                        .get(group_uuid)
+                                        .into_iter()
+                                        .flatten()
+                                        .cloned(),
+                                )
+                                .collect();
+                            (*group_uuid, claim_values)
+                        })
+                        .collect();
+
+                    DbValueOauthClaimMap::V2 {
+                        name: name.clone(),
+                        join: mapping.join.into(),
+                        values,
+                    }
                 })
                 .collect(),
         )
@@ -867,7 +1045,7 @@ impl ValueSetT for ValueSetOauthClaimMap {
         Some(Box::new(
             self.map
                 .values()
-                .flat_map(|mapping| mapping.values.keys())
+                .flat_map(|mapping| mapping.group_uuids())
                 .copied(),
         ))
     }
@@ -877,6 +1055,7 @@ impl ValueSetT for ValueSetOauthClaimMap {
 mod tests {
     use super::{ValueSetOauthClaimMap, ValueSetOauthScope, ValueSetOauthScopeMap};
     use crate::prelude::*;
+    use crate::valueset::ValueSetScimPut;
     use std::collections::BTreeSet;
 
     #[test]
@@ -894,6 +1073,93 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_oauth_claim_map_typed_values_dbvs2() {
+        use crate::be::dbvalue::{DbValueOauthClaimMap, DbValueSetV2};
+
+        let group_uuid = uuid::uuid!("5a6b8783-3f67-4ebb-b6aa-77fd6e66589f");
+
+        // String only claim maps continue to be stored as V1.
+        let set = ["read".to_string()].into();
+        let vs: ValueSet = ValueSetOauthClaimMap::new_value("claim".to_string(), group_uuid, set);
+        assert!(matches!(
+            vs.to_db_valueset_v2(),
+            DbValueSetV2::OauthClaimMap(maps) if matches!(maps.as_slice(), [DbValueOauthClaimMap::V1 { .. }])
+        ));
+
+        let vs: ValueSet = ValueSetOauthClaimMap::new_typed_value(
+            "claim".to_string(),
+            group_uuid,
+            vec![JsonValue::Bool(true), serde_json::json!({ "level": 3 })],
+        );
+        assert!(vs.contains(&PartialValue::Refer(group_uuid)));
+
+        let DbValueSetV2::OauthClaimMap(maps) = vs.to_db_valueset_v2() else {
+            panic!("invalid db valueset");
+        };
+        assert!(matches!(maps.as_slice(), [DbValueOauthClaimMap::V2 { .. }]));
+
+        let vs2 = ValueSetOauthClaimMap::from_dbvs2(maps).expect("failed to load v2 claim map");
+        assert!(vs.equal(&vs2));
+        assert_eq!(
+            vs2.as_ref_uuid_iter().map(|iter| iter.collect::<Vec<_>>()),
+            Some(vec![group_uuid])
+        );
+    }
+
+    #[test]
+    fn test_oauth_claim_map_insert_typed_values() {
+        let group_uuid = uuid::uuid!("5a6b8783-3f67-4ebb-b6aa-77fd6e66589f");
+
+        // Strings and nulls are not typed values.
+        assert!(Value::new_oauthclaimmap_typed(
+            "claim".to_string(),
+            group_uuid,
+            vec![JsonValue::String("read".to_string())]
+        )
+        .is_none());
+        assert!(Value::new_oauthclaimmap_typed(
+            "claim".to_string(),
+            group_uuid,
+            vec![JsonValue::Null]
+        )
+        .is_none());
+
+        let set = ["read".to_string()].into();
+        let mut vs: ValueSet =
+            ValueSetOauthClaimMap::new_value("claim".to_string(), group_uuid, set);
+
+        let typed = Value::new_oauthclaimmap_typed(
+            "claim".to_string(),
+            group_uuid,
+            vec![JsonValue::Bool(true), serde_json::json!(3)],
+        )
+        .expect("invalid typed claim value");
+        assert_eq!(vs.insert_checked(typed), Ok(true));
+
+        let mapping = vs
+            .as_oauthclaim_map()
+            .and_then(|map| map.get("claim"))
+            .expect("claim map missing");
+        assert!(mapping.values().contains_key(&group_uuid));
+        assert_eq!(
+            mapping.typed_values().get(&group_uuid),
+            Some(&vec![JsonValue::Bool(true), serde_json::json!(3)])
+        );
+
+        // Empty typed values remove them, but keep the string values.
+        let typed = Value::new_oauthclaimmap_typed("claim".to_string(), group_uuid, Vec::new())
+            .expect("invalid typed claim value");
+        assert_eq!(vs.insert_checked(typed), Ok(true));
+
+        let mapping = vs
+            .as_oauthclaim_map()
+            .and_then(|map| map.get("claim"))
+            .expect("claim map missing");
+        assert!(mapping.values().contains_key(&group_uuid));
+        assert!(mapping.typed_values().is_empty());
+    }
+
     #[test]
     fn test_scim_oauth2_scope() {
         let vs: ValueSet = ValueSetOauthScope::new("fully_sick_scope_m8".to_string());
@@ -979,6 +1245,47 @@ mod tests {
             &[],
         );
 
+        // Typed claim values are included in the output.
+        let vs: ValueSet = ValueSetOauthClaimMap::new_typed_value(
+            "claim".to_string(),
+            g_uuid,
+            vec![JsonValue::Bool(true), serde_json::json!({ "level": 3 })],
+        );
+
+        let data = r#"
+[
+  {
+    "claim": "claim",
+    "group": "testgroup@example.com",
+    "groupUuid": "4d21d04a-dc0e-42eb-b850-34dd180b107f",
+    "joinChar": ";",
+    "values": [],
+    "typedValues": [true, { "level": 3 }]
+  }
+]
+        "#;
+        crate::valueset::scim_json_reflexive_unresolved(&mut write_txn, &vs, data);
+
+        // Typed claim values can be written with scim.
+        crate::valueset::scim_json_put_reflexive_unresolved::<ValueSetOauthClaimMap>(
+            &mut write_txn,
+            &vs,
+            &[],
+        );
+
+        // String typed values are refused, they must be in values.
+        let data = serde_json::json!([{
+            "claim": "claim",
+            "groupUuid": "4d21d04a-dc0e-42eb-b850-34dd180b107f",
+            "joinChar": ";",
+            "values": [],
+            "typedValues": ["read"]
+        }]);
+        assert!(matches!(
+            ValueSetOauthClaimMap::from_scim_json_put(data),
+            Err(OperationError::SC0022Oauth2ClaimMapSyntaxInvalid)
+        ));
+
         assert!(write_txn.commit().is_ok());
     }
 }
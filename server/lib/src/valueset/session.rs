@@ -37,7 +37,7 @@ impl ValueSetSession {
     fn to_vec_dbvs(&self) -> Vec<DbValueSession> {
         self.map
             .iter()
-            .map(|(u, m)| DbValueSession::V4 {
+            .map(|(u, m)| DbValueSession::V5 {
                 refer: *u,
                 label: m.label.clone(),
 
@@ -98,6 +98,42 @@ impl ValueSetSession {
                         refresh_token: refresh_token.clone(),
                     },
                 },
+                client_ip: m.client_ip,
+                last_seen: m.last_seen.map(|odt| {
+                    debug_assert_eq!(odt.offset(), time::UtcOffset::UTC);
+                    #[allow(clippy::expect_used)]
+                    odt.format(&Rfc3339)
+                        .expect("Failed to format timestamp into RFC3339!")
+                }),
+            })
+            .map(|dbv| match dbv {
+                // Sessions that don't use the V5 fields are written as V4, so that servers
+                // on an older domain level can still read them. The V5 fields are only
+                // populated once the domain level permits it.
+                DbValueSession::V5 {
+                    refer,
+                    label,
+                    state,
+                    issued_at,
+                    issued_by,
+                    cred_id,
+                    scope,
+                    type_,
+                    ext_metadata,
+                    client_ip: None,
+                    last_seen: None,
+                } => DbValueSession::V4 {
+                    refer,
+                    label,
+                    state,
+                    issued_at,
+                    issued_by,
+                    cred_id,
+                    scope,
+                    type_,
+                    ext_metadata,
+                },
+                dbv => dbv,
             })
             .collect()
     }
@@ -107,12 +143,24 @@ impl ValueSetSession {
     ) -> Result<ValueSet, OperationError> {
         let map = iter
             .filter_map(|dbv| {
-                match dbv {
+                let (
+                    refer,
+                    label,
+                    state,
+                    issued_at,
+                    issued_by,
+                    cred_id,
+                    scope,
+                    type_,
+                    ext_metadata,
+                    client_ip,
+                    last_seen,
+                ) = match dbv {
                     // We need to ignore all older session records as they lack the AuthType
                     // record which prevents re-auth working.
                     DbValueSession::V1 { .. }
                     | DbValueSession::V2 { .. }
-                    | DbValueSession::V3 { .. } => None,
+                    | DbValueSession::V3 { .. } => return None,
                     DbValueSession::V4 {
                         refer,
                         label,
@@ -123,98 +171,149 @@ impl ValueSetSession {
                         scope,
                         type_,
                         ext_metadata,
-                    } => {
-                        // Convert things.
-                        let issued_at = OffsetDateTime::parse(issued_at, &Rfc3339)
+                    } => (
+                        refer,
+                        label,
+                        state,
+                        issued_at,
+                        issued_by,
+                        cred_id,
+                        scope,
+                        type_,
+                        ext_metadata,
+                        None,
+                        None,
+                    ),
+                    DbValueSession::V5 {
+                        refer,
+                        label,
+                        state,
+                        issued_at,
+                        issued_by,
+                        cred_id,
+                        scope,
+                        type_,
+                        ext_metadata,
+                        client_ip,
+                        last_seen,
+                    } => (
+                        refer,
+                        label,
+                        state,
+                        issued_at,
+                        issued_by,
+                        cred_id,
+                        scope,
+                        type_,
+                        ext_metadata,
+                        *client_ip,
+                        last_seen.as_ref(),
+                    ),
+                };
+
+                // Convert things.
+                let issued_at = OffsetDateTime::parse(issued_at, &Rfc3339)
+                    .map(|odt| odt.to_offset(time::UtcOffset::UTC))
+                    .map_err(|e| {
+                        admin_error!(
+                            ?e,
+                            "Invalidating session {} due to invalid issued_at timestamp",
+                            refer
+                        )
+                    })
+                    .ok()?;
+
+                // last_seen is only a hint, so an invalid value doesn't invalidate the session.
+                let last_seen = last_seen.and_then(|ls| {
+                    OffsetDateTime::parse(ls, &Rfc3339)
+                        .map(|odt| odt.to_offset(time::UtcOffset::UTC))
+                        .map_err(|e| {
+                            admin_warn!(
+                                ?e,
+                                "Ignoring invalid last_seen timestamp of session {}",
+                                refer
+                            )
+                        })
+                        .ok()
+                });
+
+                let state = match state {
+                    DbValueSessionStateV1::ExpiresAt(e_inner) => {
+                        OffsetDateTime::parse(e_inner, &Rfc3339)
                             .map(|odt| odt.to_offset(time::UtcOffset::UTC))
+                            .map(SessionState::ExpiresAt)
                             .map_err(|e| {
                                 admin_error!(
                                     ?e,
-                                    "Invalidating session {} due to invalid issued_at timestamp",
+                                    "Invalidating session {} due to invalid expiry timestamp",
                                     refer
                                 )
                             })
-                            .ok()?;
-
-                        let state = match state {
-                            DbValueSessionStateV1::ExpiresAt(e_inner) => {
-                                OffsetDateTime::parse(e_inner, &Rfc3339)
-                                    .map(|odt| odt.to_offset(time::UtcOffset::UTC))
-                                    .map(SessionState::ExpiresAt)
-                                    .map_err(|e| {
-                                        admin_error!(
-                                        ?e,
-                                        "Invalidating session {} due to invalid expiry timestamp",
-                                        refer
-                                    )
-                                    })
-                                    .ok()?
-                            }
-                            DbValueSessionStateV1::Never => SessionState::NeverExpires,
-                            DbValueSessionStateV1::RevokedAt(dc) => SessionState::RevokedAt(Cid {
-                                s_uuid: dc.server_id,
-                                ts: dc.timestamp,
-                            }),
-                        };
-
-                        let issued_by = match issued_by {
-                            DbValueIdentityId::V1Internal => IdentityId::Internal(UUID_SYSTEM),
-                            DbValueIdentityId::V2Internal(u) => IdentityId::Internal(*u),
-                            DbValueIdentityId::V1Uuid(u) => IdentityId::User(*u),
-                            DbValueIdentityId::V1Sync(u) => IdentityId::Synch(*u),
-                        };
-
-                        let scope = match scope {
-                            DbValueAccessScopeV1::IdentityOnly | DbValueAccessScopeV1::ReadOnly => {
-                                SessionScope::ReadOnly
-                            }
-                            DbValueAccessScopeV1::ReadWrite => SessionScope::ReadWrite,
-                            DbValueAccessScopeV1::PrivilegeCapable => {
-                                SessionScope::PrivilegeCapable
-                            }
-                            DbValueAccessScopeV1::Synchronise => SessionScope::Synchronise,
-                        };
-
-                        let type_ = match type_ {
-                            DbValueAuthTypeV1::Anonymous => AuthType::Anonymous,
-                            DbValueAuthTypeV1::Password => AuthType::Password,
-                            DbValueAuthTypeV1::GeneratedPassword => AuthType::GeneratedPassword,
-                            DbValueAuthTypeV1::PasswordTotp => AuthType::PasswordTotp,
-                            DbValueAuthTypeV1::PasswordBackupCode => AuthType::PasswordBackupCode,
-                            DbValueAuthTypeV1::PasswordSecurityKey => AuthType::PasswordSecurityKey,
-                            DbValueAuthTypeV1::Passkey => AuthType::Passkey,
-                            DbValueAuthTypeV1::AttestedPasskey => AuthType::AttestedPasskey,
-                            DbValueAuthTypeV1::OAuth2Trust => AuthType::OAuth2Trust,
-                        };
-
-                        let ext_metadata = match ext_metadata {
-                            DbValueSessionExtMetadataV1::None => SessionExtMetadata::None,
-                            DbValueSessionExtMetadataV1::OAuth2 {
-                                access_expires_at,
-                                access_token,
-                                refresh_token,
-                            } => SessionExtMetadata::OAuth2 {
-                                access_expires_at: *access_expires_at,
-                                access_token: access_token.clone(),
-                                refresh_token: refresh_token.clone(),
-                            },
-                        };
-
-                        Some((
-                            *refer,
-                            Session {
-                                label: label.clone(),
-                                state,
-                                issued_at,
-                                issued_by,
-                                cred_id: *cred_id,
-                                scope,
-                                type_,
-                                ext_metadata,
-                            },
-                        ))
+                            .ok()?
                     }
-                }
+                    DbValueSessionStateV1::Never => SessionState::NeverExpires,
+                    DbValueSessionStateV1::RevokedAt(dc) => SessionState::RevokedAt(Cid {
+                        s_uuid: dc.server_id,
+                        ts: dc.timestamp,
+                    }),
+                };
+
+                let issued_by = match issued_by {
+                    DbValueIdentityId::V1Internal => IdentityId::Internal(UUID_SYSTEM),
+                    DbValueIdentityId::V2Internal(u) => IdentityId::Internal(*u),
+                    DbValueIdentityId::V1Uuid(u) => IdentityId::User(*u),
+                    DbValueIdentityId::V1Sync(u) => IdentityId::Synch(*u),
+                };
+
+                let scope = match scope {
+                    DbValueAccessScopeV1::IdentityOnly | DbValueAccessScopeV1::ReadOnly => {
+                        SessionScope::ReadOnly
+                    }
+                    DbValueAccessScopeV1::ReadWrite => SessionScope::ReadWrite,
+                    DbValueAccessScopeV1::PrivilegeCapable => SessionScope::PrivilegeCapable,
+                    DbValueAccessScopeV1::Synchronise => SessionScope::Synchronise,
+                };
+
+                let type_ = match type_ {
+                    DbValueAuthTypeV1::Anonymous => AuthType::Anonymous,
+                    DbValueAuthTypeV1::Password => AuthType::Password,
+                    DbValueAuthTypeV1::GeneratedPassword => AuthType::GeneratedPassword,
+                    DbValueAuthTypeV1::PasswordTotp => AuthType::PasswordTotp,
+                    DbValueAuthTypeV1::PasswordBackupCode => AuthType::PasswordBackupCode,
+                    DbValueAuthTypeV1::PasswordSecurityKey => AuthType::PasswordSecurityKey,
+                    DbValueAuthTypeV1::Passkey => AuthType::Passkey,
+                    DbValueAuthTypeV1::AttestedPasskey => AuthType::AttestedPasskey,
+                    DbValueAuthTypeV1::OAuth2Trust => AuthType::OAuth2Trust,
+                };
+
+                let ext_metadata = match ext_metadata {
+                    DbValueSessionExtMetadataV1::None => SessionExtMetadata::None,
+                    DbValueSessionExtMetadataV1::OAuth2 {
+                        access_expires_at,
+                        access_token,
+                        refresh_token,
+                    } => SessionExtMetadata::OAuth2 {
+                        access_expires_at: *access_expires_at,
+                        access_token: access_token.clone(),
+                        refresh_token: refresh_token.clone(),
+                    },
+                };
+
+                Some((
+                    *refer,
+                    Session {
+                        label: label.clone(),
+                        state,
+                        issued_at,
+                        issued_by,
+                        cred_id: *cred_id,
+                        scope,
+                        type_,
+                        ext_metadata,
+                        client_ip,
+                        last_seen,
+                    },
+                ))
             })
             .collect();
         Ok(Box::new(ValueSetSession { map }))
@@ -239,14 +338,23 @@ impl ValueSetSession {
 impl ValueSetT for ValueSetSession {
     fn insert_checked(&mut self, value: Value) -> Result<bool, OperationError> {
         match value {
-            Value::Session(u, m) => {
-                if let BTreeEntry::Vacant(e) = self.map.entry(u) {
+            Value::Session(u, m) => match self.map.entry(u) {
+                BTreeEntry::Vacant(e) => {
                     e.insert(m);
                     Ok(true)
-                } else {
-                    Ok(false)
                 }
-            }
+                BTreeEntry::Occupied(mut e) => {
+                    // The session itself can't be replaced, but the server may record
+                    // a later use of it.
+                    let existing = e.get_mut();
+                    if existing.state == m.state && m.last_seen > existing.last_seen {
+                        existing.last_seen = m.last_seen;
+                        Ok(true)
+                    } else {
+                        Ok(false)
+                    }
+                }
+            },
             _ => Err(OperationError::InvalidValueState),
         }
     }
@@ -445,6 +553,10 @@ impl ValueSetT for ValueSetSession {
                     // cids will always take effect.
                     if v_other.state > v_self.state {
                         *v_self = v_other.clone();
+                    } else if v_other.state == v_self.state && v_other.last_seen > v_self.last_seen
+                    {
+                        // Otherwise only carry over the latest use of the session.
+                        v_self.last_seen = v_other.last_seen;
                     }
                 } else {
                     // Not present, just insert.
@@ -481,6 +593,9 @@ impl ValueSetT for ValueSetSession {
                 // cids will always take effect.
                 if v_other.state > v_self.state {
                     *v_self = v_other.clone();
+                } else if v_other.state == v_self.state && v_other.last_seen > v_self.last_seen {
+                    // Otherwise only carry over the latest use of the session.
+                    v_self.last_seen = v_other.last_seen;
                 }
             } else {
                 // Not present, just insert.
@@ -1316,9 +1431,12 @@ impl ValueSetT for ValueSetApiToken {
 #[cfg(test)]
 mod tests {
     use super::{ValueSetOauth2Session, ValueSetSession, SESSION_MAXIMUM};
+    use crate::be::dbvalue::DbValueSession;
     use crate::prelude::{IdentityId, SessionScope, Uuid, ValueSet, UUID_SYSTEM};
     use crate::repl::cid::Cid;
-    use crate::value::{AuthType, Oauth2Session, Session, SessionState};
+    use crate::value::{AuthType, Oauth2Session, Session, SessionState, Value};
+    use crate::valueset::DbValueSetV2;
+    use std::time::Duration;
     use time::OffsetDateTime;
 
     #[test]
@@ -1337,6 +1455,8 @@ mod tests {
                 scope: SessionScope::ReadOnly,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                client_ip: None,
+                last_seen: None,
             },
         );
 
@@ -1372,6 +1492,8 @@ mod tests {
                 scope: SessionScope::ReadOnly,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                client_ip: None,
+                last_seen: None,
             },
         );
 
@@ -1387,6 +1509,8 @@ mod tests {
                 scope: SessionScope::ReadOnly,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                client_ip: None,
+                last_seen: None,
             },
         );
 
@@ -1417,6 +1541,8 @@ mod tests {
                 scope: SessionScope::ReadOnly,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                client_ip: None,
+                last_seen: None,
             },
         );
 
@@ -1432,6 +1558,8 @@ mod tests {
                 scope: SessionScope::ReadOnly,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                client_ip: None,
+                last_seen: None,
             },
         );
 
@@ -1465,6 +1593,8 @@ mod tests {
                 scope: SessionScope::ReadOnly,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                client_ip: None,
+                last_seen: None,
             },
         );
 
@@ -1481,6 +1611,8 @@ mod tests {
                     scope: SessionScope::ReadOnly,
                     type_: AuthType::Passkey,
                     ext_metadata: Default::default(),
+                    client_ip: None,
+                    last_seen: None,
                 },
             ),
             (
@@ -1495,6 +1627,8 @@ mod tests {
                     scope: SessionScope::ReadOnly,
                     type_: AuthType::Passkey,
                     ext_metadata: Default::default(),
+                    client_ip: None,
+                    last_seen: None,
                 },
             ),
         ])
@@ -1532,6 +1666,8 @@ mod tests {
                 scope: SessionScope::ReadOnly,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                client_ip: None,
+                last_seen: None,
             },
         );
 
@@ -1548,6 +1684,8 @@ mod tests {
                     scope: SessionScope::ReadOnly,
                     type_: AuthType::Passkey,
                     ext_metadata: Default::default(),
+                    client_ip: None,
+                    last_seen: None,
                 },
             ),
             (
@@ -1562,6 +1700,8 @@ mod tests {
                     scope: SessionScope::ReadOnly,
                     type_: AuthType::Passkey,
                     ext_metadata: Default::default(),
+                    client_ip: None,
+                    last_seen: None,
                 },
             ),
         ])
@@ -1603,6 +1743,8 @@ mod tests {
                     scope: SessionScope::ReadOnly,
                     type_: AuthType::Passkey,
                     ext_metadata: Default::default(),
+                    client_ip: None,
+                    last_seen: None,
                 },
             ),
             (
@@ -1617,6 +1759,8 @@ mod tests {
                     scope: SessionScope::ReadOnly,
                     type_: AuthType::Passkey,
                     ext_metadata: Default::default(),
+                    client_ip: None,
+                    last_seen: None,
                 },
             ),
             (
@@ -1631,6 +1775,8 @@ mod tests {
                     scope: SessionScope::ReadOnly,
                     type_: AuthType::Passkey,
                     ext_metadata: Default::default(),
+                    client_ip: None,
+                    last_seen: None,
                 },
             ),
         ])
@@ -1663,6 +1809,8 @@ mod tests {
                 scope: SessionScope::ReadOnly,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                client_ip: None,
+                last_seen: None,
             },
         ))
         .chain((0..SESSION_MAXIMUM).map(|_| {
@@ -1678,6 +1826,8 @@ mod tests {
                     scope: SessionScope::ReadOnly,
                     type_: AuthType::Passkey,
                     ext_metadata: Default::default(),
+                    client_ip: None,
+                    last_seen: None,
                 },
             )
         }));
@@ -1968,6 +2118,189 @@ mod tests {
         assert!(sessions.contains_key(&two_uuid));
     }
 
+    #[test]
+    fn test_valueset_session_dbvs_upgrade() {
+        let s_uuid = uuid::uuid!("3a163ca0-4762-4620-a188-06b750c84c86");
+
+        // A session stored before last_seen existed.
+        let data = r#"{"V5":{"u":"3a163ca0-4762-4620-a188-06b750c84c86","l":"hacks","e":"nv","i":"1970-01-01T00:00:00Z","b":{"v2i":"00000000-0000-0000-0000-ffffff000000"},"c":"3a163ca0-4762-4620-a188-06b750c84c86","s":"r","t":"as"}}"#;
+        let dbv: DbValueSession = serde_json::from_str(data).expect("Failed to deserialise");
+
+        let vs = ValueSetSession::from_dbvs2(&[dbv]).expect("Failed to load sessions");
+        let session = vs
+            .as_session_map()
+            .and_then(|map| map.get(&s_uuid))
+            .expect("Unable to locate session");
+        assert_eq!(session.last_seen, None);
+
+        // Without a client_ip or last_seen it is written back as V4, so it remains
+        // readable by servers that predate V5.
+        let DbValueSetV2::Session(dbvs) = vs.to_db_valueset_v2() else {
+            panic!("Invalid db valueset");
+        };
+        assert!(matches!(dbvs.as_slice(), [DbValueSession::V4 { .. }]));
+        let written = serde_json::to_string(&dbvs[0]).expect("Failed to serialise");
+        assert!(!written.contains(r#""ls""#));
+    }
+
+    #[test]
+    fn test_valueset_session_dbvs_client_ip() {
+        let s_uuid = Uuid::new_v4();
+        let session = Session {
+            label: "hacks".to_string(),
+            state: SessionState::NeverExpires,
+            issued_at: OffsetDateTime::UNIX_EPOCH,
+            issued_by: IdentityId::Internal(UUID_SYSTEM),
+            cred_id: Uuid::new_v4(),
+            scope: SessionScope::ReadOnly,
+            type_: AuthType::Passkey,
+            ext_metadata: Default::default(),
+            client_ip: Some(std::net::Ipv4Addr::LOCALHOST.into()),
+            last_seen: None,
+        };
+        let vs: ValueSet = ValueSetSession::new(s_uuid, session.clone());
+
+        // Only sessions that use the V5 fields are written as V5.
+        let DbValueSetV2::Session(dbvs) = vs.to_db_valueset_v2() else {
+            panic!("Invalid db valueset");
+        };
+        assert!(matches!(
+            dbvs.as_slice(),
+            [DbValueSession::V5 {
+                client_ip: Some(_),
+                ..
+            }]
+        ));
+
+        let vs = ValueSetSession::from_dbvs2(&dbvs).expect("Failed to load sessions");
+        let loaded = vs
+            .as_session_map()
+            .and_then(|map| map.get(&s_uuid))
+            .expect("Unable to locate session");
+        assert_eq!(loaded.client_ip, session.client_ip);
+    }
+
+    #[test]
+    fn test_valueset_session_dbvs_last_seen() {
+        let s_uuid = Uuid::new_v4();
+        let session = Session {
+            label: "hacks".to_string(),
+            state: SessionState::NeverExpires,
+            issued_at: OffsetDateTime::UNIX_EPOCH,
+            issued_by: IdentityId::Internal(UUID_SYSTEM),
+            cred_id: Uuid::new_v4(),
+            scope: SessionScope::ReadOnly,
+            type_: AuthType::Passkey,
+            ext_metadata: Default::default(),
+            client_ip: None,
+            last_seen: Some(OffsetDateTime::UNIX_EPOCH + Duration::from_secs(3600)),
+        };
+        let vs: ValueSet = ValueSetSession::new(s_uuid, session.clone());
+
+        let DbValueSetV2::Session(dbvs) = vs.to_db_valueset_v2() else {
+            panic!("Invalid db valueset");
+        };
+        let data = serde_json::to_string(&dbvs).expect("Failed to serialise");
+        assert!(data.contains(r#""ls":"1970-01-01T01:00:00Z""#));
+
+        let dbvs: Vec<DbValueSession> = serde_json::from_str(&data).expect("Failed to deserialise");
+        let vs = ValueSetSession::from_dbvs2(&dbvs).expect("Failed to load sessions");
+        let loaded = vs
+            .as_session_map()
+            .and_then(|map| map.get(&s_uuid))
+            .expect("Unable to locate session");
+        assert_eq!(loaded, &session);
+        assert_eq!(loaded.last_seen, session.last_seen);
+    }
+
+    #[test]
+    fn test_valueset_session_insert_last_seen() {
+        let s_uuid = Uuid::new_v4();
+        let session = Session {
+            label: "hacks".to_string(),
+            state: SessionState::NeverExpires,
+            issued_at: OffsetDateTime::UNIX_EPOCH,
+            issued_by: IdentityId::Internal(UUID_SYSTEM),
+            cred_id: Uuid::new_v4(),
+            scope: SessionScope::ReadOnly,
+            type_: AuthType::Passkey,
+            ext_metadata: Default::default(),
+            client_ip: None,
+            last_seen: None,
+        };
+        let seen = Session {
+            last_seen: Some(OffsetDateTime::UNIX_EPOCH + Duration::from_secs(3600)),
+            ..session.clone()
+        };
+
+        // The time a session was last seen is not part of its identity.
+        assert_eq!(session, seen);
+
+        let mut vs: ValueSet = ValueSetSession::new(s_uuid, session.clone());
+        assert!(vs
+            .insert_checked(Value::Session(s_uuid, seen.clone()))
+            .expect("Failed to insert"));
+        let updated = vs
+            .as_session_map()
+            .and_then(|map| map.get(&s_uuid))
+            .expect("Unable to locate session");
+        assert_eq!(updated.last_seen, seen.last_seen);
+
+        // An older sighting doesn't move last_seen backwards.
+        assert!(!vs
+            .insert_checked(Value::Session(s_uuid, session))
+            .expect("Failed to insert"));
+        let updated = vs
+            .as_session_map()
+            .and_then(|map| map.get(&s_uuid))
+            .expect("Unable to locate session");
+        assert_eq!(updated.last_seen, seen.last_seen);
+    }
+
+    #[test]
+    fn test_valueset_session_merge_last_seen() {
+        let s_uuid = Uuid::new_v4();
+        let session = Session {
+            label: "hacks".to_string(),
+            state: SessionState::NeverExpires,
+            issued_at: OffsetDateTime::UNIX_EPOCH,
+            issued_by: IdentityId::Internal(UUID_SYSTEM),
+            cred_id: Uuid::new_v4(),
+            scope: SessionScope::ReadOnly,
+            type_: AuthType::Passkey,
+            ext_metadata: Default::default(),
+            client_ip: None,
+            last_seen: None,
+        };
+        let last_seen = Some(OffsetDateTime::UNIX_EPOCH + Duration::from_secs(3600));
+
+        let mut vs_a: ValueSet = ValueSetSession::new(s_uuid, session.clone());
+        let vs_b: ValueSet = ValueSetSession::new(
+            s_uuid,
+            Session {
+                last_seen,
+                ..session
+            },
+        );
+
+        // With the same state, the latest use is kept regardless of merge order.
+        vs_a.merge(&vs_b).expect("failed to merge");
+        let merged = vs_a
+            .as_session_map()
+            .and_then(|map| map.get(&s_uuid))
+            .expect("Unable to locate session");
+        assert_eq!(merged.last_seen, last_seen);
+
+        let r_vs = vs_b
+            .repl_merge_valueset(&vs_a, &Cid::new_zero())
+            .expect("failed to merge");
+        let merged = r_vs
+            .as_session_map()
+            .and_then(|map| map.get(&s_uuid))
+            .expect("Unable to locate session");
+        assert_eq!(merged.last_seen, last_seen);
+    }
+
     #[test]
     fn test_scim_session() {
         let s_uuid = uuid::uuid!("3a163ca0-4762-4620-a188-06b750c84c86");
@@ -1983,6 +2316,8 @@ mod tests {
                 scope: SessionScope::ReadOnly,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                client_ip: None,
+                last_seen: None,
             },
         );
 
@@ -1,3 +1,4 @@
+use crate::be::dbvalue::DbValueSyntaxTypeV1;
 use crate::prelude::*;
 use crate::schema::SchemaAttribute;
 use crate::valueset::{
@@ -22,8 +23,11 @@ impl ValueSetSyntax {
         self.set.insert(s)
     }
 
-    pub fn from_dbvs2(data: Vec<u16>) -> Result<ValueSet, OperationError> {
-        let set: Result<_, _> = data.into_iter().map(SyntaxType::try_from).collect();
+    pub fn from_dbvs2(data: Vec<DbValueSyntaxTypeV1>) -> Result<ValueSet, OperationError> {
+        let set: Result<_, _> = data
+            .into_iter()
+            .map(|v| SyntaxType::try_from(u16::from(v)))
+            .collect();
         let set = set.map_err(|_| OperationError::InvalidValueState)?;
         Ok(Box::new(ValueSetSyntax { set }))
     }
@@ -138,7 +142,12 @@ impl ValueSetT for ValueSetSyntax {
     }
 
     fn to_db_valueset_v2(&self) -> DbValueSetV2 {
-        DbValueSetV2::SyntaxType(self.set.iter().map(|s| *s as u16).collect())
+        DbValueSetV2::SyntaxType(
+            self.set
+                .iter()
+                .map(|s| DbValueSyntaxTypeV1::from(*s as u16))
+                .collect(),
+        )
     }
 
     fn to_partialvalue_iter(&self) -> Box<dyn Iterator<Item = PartialValue> + '_> {
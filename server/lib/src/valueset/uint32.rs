@@ -5,6 +5,7 @@ use crate::valueset::{
 };
 use kanidm_proto::scim_v1::JsonValue;
 use smolset::SmolSet;
+use std::cmp::Ordering;
 
 #[derive(Debug, Clone)]
 pub struct ValueSetUint32 {
@@ -158,6 +159,15 @@ impl ValueSetT for ValueSetUint32 {
         }
     }
 
+    fn cmp(&self, other: &ValueSet) -> Ordering {
+        if let Some(other) = other.as_uint32_set() {
+            self.set.iter().min().cmp(&other.iter().min())
+        } else {
+            debug_assert!(false);
+            Ordering::Equal
+        }
+    }
+
     fn merge(&mut self, other: &ValueSet) -> Result<(), OperationError> {
         if let Some(b) = other.as_uint32_set() {
             mergesets!(self.set, b)
@@ -6,6 +6,7 @@ use crate::valueset::{
 };
 use kanidm_proto::scim_v1::client::ScimStrings;
 use kanidm_proto::scim_v1::JsonValue;
+use std::cmp::Ordering;
 use std::collections::BTreeSet;
 
 #[derive(Debug, Clone)]
@@ -202,6 +203,15 @@ impl ValueSetT for ValueSetUtf8 {
         }
     }
 
+    fn cmp(&self, other: &ValueSet) -> Ordering {
+        if let Some(other) = other.as_utf8_set() {
+            self.set.cmp(other)
+        } else {
+            debug_assert!(false);
+            Ordering::Equal
+        }
+    }
+
     fn merge(&mut self, other: &ValueSet) -> Result<(), OperationError> {
         if let Some(b) = other.as_utf8_set() {
             mergesets!(self.set, b)
@@ -1,5 +1,6 @@
-use kanidm_client::KanidmClient;
+use kanidm_client::{ClientError, KanidmClient, StatusCode};
 use kanidm_proto::constants::ATTR_DOMAIN_DISPLAY_NAME;
+use kanidm_proto::internal::{OperationError, SchemaError};
 use kanidmd_testkit::{ADMIN_TEST_PASSWORD, ADMIN_TEST_USER};
 
 #[kanidmd_testkit::test]
@@ -64,3 +65,27 @@ async fn test_idm_domain_set_display_name(rsclient: &KanidmClient) {
         Some(&vec![new_domain_display_name.to_string()])
     );
 }
+
+#[kanidmd_testkit::test]
+async fn test_idm_domain_set_display_name_invalid(rsclient: &KanidmClient) {
+    rsclient
+        .auth_simple_password(ADMIN_TEST_USER, ADMIN_TEST_PASSWORD)
+        .await
+        .expect("Failed to login as admin");
+
+    let err = rsclient
+        .idm_domain_set_display_name(&"a".repeat(65))
+        .await
+        .expect_err("Overly long display names must be rejected");
+
+    assert!(matches!(
+        err,
+        ClientError::Http(
+            StatusCode::BAD_REQUEST,
+            Some(OperationError::SchemaViolation(
+                SchemaError::InvalidDisplayName { .. }
+            )),
+            _
+        )
+    ));
+}
@@ -51,3 +51,45 @@ async fn test_v1_group_id_attr_post(rsclient: &KanidmClient) {
         ClientError::Http(StatusCode::BAD_REQUEST, _, _)
     ));
 }
+
+#[kanidmd_testkit::test]
+async fn test_v1_group_rename(rsclient: &KanidmClient) {
+    let res = rsclient
+        .auth_simple_password(ADMIN_TEST_USER, ADMIN_TEST_PASSWORD)
+        .await;
+    assert!(res.is_ok());
+
+    create_user(rsclient, "foo", "foogroup").await;
+    rsclient
+        .idm_group_create("bargroup", None)
+        .await
+        .expect("Failed to create group");
+
+    // A name that is already taken is rejected and nothing changes.
+    let err = rsclient
+        .group_rename("foogroup", "bargroup")
+        .await
+        .expect_err("Rename should fail");
+    assert!(matches!(err, ClientError::Http(StatusCode::CONFLICT, _, _)));
+    let members = rsclient
+        .idm_group_get_members("foogroup")
+        .await
+        .expect("Failed to get members");
+    assert_eq!(members.map(|m| m.len()), Some(1));
+
+    // Memberships refer to the group by uuid, so they follow the rename.
+    rsclient
+        .group_rename("foogroup", "renamedgroup")
+        .await
+        .expect("Failed to rename group");
+    assert!(rsclient
+        .idm_group_get("foogroup")
+        .await
+        .expect("Failed to get group")
+        .is_none());
+    let members = rsclient
+        .idm_group_get_members("renamedgroup")
+        .await
+        .expect("Failed to get members");
+    assert_eq!(members.map(|m| m.len()), Some(1));
+}
@@ -838,6 +838,48 @@ async fn test_oauth2_openid_public_flow_impl(
         .await
         .expect("Failed to update oauth2 claims");
 
+    // Add a claim map that isn't a string.
+    rsclient
+        .idm_oauth2_rs_update_claim_map_typed(
+            TEST_INTEGRATION_RS_ID,
+            "test_typed_claim",
+            NAME_IDM_ALL_ACCOUNTS,
+            &[serde_json::Value::Bool(true)],
+        )
+        .await
+        .expect("Failed to update oauth2 claims");
+
+    // The maps can be listed back.
+    let scope_maps = rsclient
+        .idm_oauth2_rs_get_scope_maps(TEST_INTEGRATION_RS_ID)
+        .await
+        .expect("Failed to list oauth2 scope maps");
+    assert_eq!(scope_maps.len(), 1);
+    assert_eq!(
+        scope_maps[0].scopes,
+        BTreeSet::from([
+            OAUTH2_SCOPE_READ.to_string(),
+            OAUTH2_SCOPE_EMAIL.to_string(),
+            OAUTH2_SCOPE_OPENID.to_string()
+        ])
+    );
+
+    let claim_maps = rsclient
+        .idm_oauth2_rs_get_claim_maps(TEST_INTEGRATION_RS_ID)
+        .await
+        .expect("Failed to list oauth2 claim maps");
+    assert_eq!(claim_maps.len(), 2);
+    assert_eq!(claim_maps[0].claim, "test_claim");
+    assert_eq!(
+        claim_maps[0].values,
+        BTreeSet::from(["claim_a".to_string(), "claim_b".to_string()])
+    );
+    assert_eq!(claim_maps[1].claim, "test_typed_claim");
+    assert_eq!(
+        claim_maps[1].typed_values,
+        vec![serde_json::Value::Bool(true)]
+    );
+
     // Get our admin's auth token for our new client.
     // We have to re-auth to update the mail field.
     let res = rsclient
@@ -1035,6 +1077,10 @@ async fn test_oauth2_openid_public_flow_impl(
         oidc.claims.get("test_claim").and_then(|v| v.as_str()),
         Some("claim_a claim_b")
     );
+    assert_eq!(
+        oidc.claims.get("test_typed_claim"),
+        Some(&serde_json::Value::Bool(true))
+    );
 
     // Check the preflight works.
     let response = client
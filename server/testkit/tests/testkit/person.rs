@@ -1,7 +1,10 @@
 use kanidm_client::{ClientError, KanidmClient, StatusCode};
+use kanidm_proto::attribute::Attribute;
 use kanidm_proto::constants::ATTR_MAIL;
+use kanidm_proto::scim_v1::{ScimEntryGetQuery, ScimFilter};
 use kanidmd_testkit::{create_user, ADMIN_TEST_PASSWORD, ADMIN_TEST_USER};
 use serde_json::Value;
+use std::str::FromStr;
 
 #[kanidmd_testkit::test]
 async fn test_v1_person_id_patch(rsclient: &KanidmClient) {
@@ -51,3 +54,31 @@ async fn test_v1_person_id_ssh_pubkeys_post(rsclient: &KanidmClient) {
         ClientError::Http(StatusCode::BAD_REQUEST, _, _)
     ));
 }
+
+#[kanidmd_testkit::test]
+async fn test_scim_v1_person_query_filter(rsclient: &KanidmClient) {
+    let res = rsclient
+        .auth_simple_password(ADMIN_TEST_USER, ADMIN_TEST_PASSWORD)
+        .await;
+    assert!(res.is_ok());
+
+    create_user(rsclient, "foo", "foogroup").await;
+    create_user(rsclient, "bar", "bargroup").await;
+
+    // Only persons are returned, even though foogroup matches too.
+    let query = ScimEntryGetQuery {
+        filter: Some(ScimFilter::from_str(r#"name sw "foo""#).expect("Invalid filter")),
+        ..Default::default()
+    };
+    let response = rsclient
+        .scim_v1_person_query(query)
+        .await
+        .expect("Failed to query persons");
+
+    let names: Vec<_> = response
+        .resources
+        .iter()
+        .filter_map(|entry| entry.attrs.get(&Attribute::Name))
+        .collect();
+    assert_eq!(names, vec![&Value::String("foo".to_string())]);
+}
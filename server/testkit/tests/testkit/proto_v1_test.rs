@@ -5,7 +5,8 @@ use hyper::header::CONTENT_TYPE;
 use kanidm_client::{ClientError, KanidmClient};
 use kanidm_proto::constants::{ATTR_GIDNUMBER, KSESSIONID};
 use kanidm_proto::internal::{
-    ApiToken, CURegState, Filter, ImageValue, Modify, ModifyList, UatPurpose, UserAuthToken,
+    ApiToken, CUCredState, CUIntentPermission, CURegState, Filter, ImageValue, Modify, ModifyList,
+    UatPurpose, UserAuthToken,
 };
 use kanidm_proto::v1::{
     AuthCredential, AuthIssueSession, AuthMech, AuthRequest, AuthResponse, AuthState, AuthStep,
@@ -15,7 +16,10 @@ use kanidmd_core::config::ServerRole;
 use kanidmd_lib::constants::{NAME_IDM_ADMINS, NAME_SYSTEM_ADMINS};
 use kanidmd_lib::credential::totp::Totp;
 use kanidmd_lib::prelude::{Attribute, APPLICATION_JSON};
-use kanidmd_testkit::{ADMIN_TEST_PASSWORD, ADMIN_TEST_USER};
+use kanidmd_testkit::{
+    ADMIN_TEST_PASSWORD, ADMIN_TEST_USER, IDM_ADMIN_TEST_PASSWORD, IDM_ADMIN_TEST_USER,
+};
+use std::collections::BTreeSet;
 use std::path::Path;
 use std::str::FromStr;
 use std::time::SystemTime;
@@ -1071,6 +1075,86 @@ async fn test_server_credential_update_session_pw(rsclient: &KanidmClient) {
     assert!(res.is_ok());
 }
 
+#[kanidmd_testkit::test]
+async fn test_server_credential_update_intent_permissions(rsclient: &KanidmClient) {
+    let res = rsclient
+        .auth_simple_password("admin", ADMIN_TEST_PASSWORD)
+        .await;
+    assert!(res.is_ok());
+
+    // Not recommended in production!
+    rsclient
+        .idm_group_add_members(NAME_IDM_ADMINS, &["admin"])
+        .await
+        .unwrap();
+
+    rsclient
+        .idm_person_account_create("demo_account", "Demo Account")
+        .await
+        .unwrap();
+
+    let intent_token = rsclient
+        .idm_person_account_credential_update_intent_with_permissions(
+            "demo_account",
+            Some(3600),
+            [CUIntentPermission::Passkeys].into(),
+        )
+        .await
+        .unwrap();
+
+    let _ = rsclient.logout().await;
+
+    // Only passkeys can be changed with this token.
+    let (_session_token, status) = rsclient
+        .idm_account_credential_update_exchange(intent_token.token)
+        .await
+        .unwrap();
+
+    assert_eq!(status.passkeys_state, CUCredState::Modifiable);
+    assert_eq!(status.primary_state, CUCredState::AccessDeny);
+    assert_eq!(status.sshkeys_state, CUCredState::AccessDeny);
+
+    // Without any permissions, as when --permissions is omitted, the token keeps
+    // everything the initiator could change.
+    let res = rsclient
+        .auth_simple_password("admin", ADMIN_TEST_PASSWORD)
+        .await;
+    assert!(res.is_ok());
+
+    let intent_token = rsclient
+        .idm_person_account_credential_update_intent_with_permissions(
+            "demo_account",
+            Some(3600),
+            BTreeSet::new(),
+        )
+        .await
+        .unwrap();
+
+    let default_intent_token = rsclient
+        .idm_person_account_credential_update_intent("demo_account", Some(3600))
+        .await
+        .unwrap();
+
+    let _ = rsclient.logout().await;
+
+    let (_session_token, status) = rsclient
+        .idm_account_credential_update_exchange(intent_token.token)
+        .await
+        .unwrap();
+
+    let (_session_token, default_status) = rsclient
+        .idm_account_credential_update_exchange(default_intent_token.token)
+        .await
+        .unwrap();
+
+    assert_eq!(status.passkeys_state, CUCredState::Modifiable);
+    assert_eq!(status.primary_state, CUCredState::Modifiable);
+    assert_eq!(status.passkeys_state, default_status.passkeys_state);
+    assert_eq!(status.primary_state, default_status.primary_state);
+    assert_eq!(status.sshkeys_state, default_status.sshkeys_state);
+    assert_eq!(status.unixcred_state, default_status.unixcred_state);
+}
+
 #[kanidmd_testkit::test]
 async fn test_server_credential_update_session_totp_pw(rsclient: &KanidmClient) {
     let res = rsclient
@@ -1860,6 +1944,116 @@ async fn test_server_user_auth_privileged_shortcut(rsclient: &KanidmClient) {
     }
 }
 
+// Generated with:
+//
+// openssl ecparam -out ec_key.pem -name secp256r1 -genkey
+// openssl req -new -key ec_key.pem -x509 -nodes -days 365 -out cert.pem
+const IDP_CERT_PEM: &str = r#"-----BEGIN CERTIFICATE-----
+MIIB3zCCAYWgAwIBAgIUdJ6IWvI+8M6nwK7ykUK7/iBq7yQwCgYIKoZIzj0EAwIw
+RTELMAkGA1UEBhMCQVUxEzARBgNVBAgMClNvbWUtU3RhdGUxITAfBgNVBAoMGElu
+dGVybmV0IFdpZGdpdHMgUHR5IEx0ZDAeFw0yNDA4MjEwNjQ2MzBaFw0yNTA4MjEw
+NjQ2MzBaMEUxCzAJBgNVBAYTAkFVMRMwEQYDVQQIDApTb21lLVN0YXRlMSEwHwYD
+VQQKDBhJbnRlcm5ldCBXaWRnaXRzIFB0eSBMdGQwWTATBgcqhkjOPQIBBggqhkjO
+PQMBBwNCAAS2Szn4NPmgxawC1+MRC41jqobemNkXkRZ9AgozK0zRDFc6k1IHUZ++
+wN0USpXDQYDnJfATqvlpKPebnHxTytt6o1MwUTAdBgNVHQ4EFgQU1oR1x2CnoPap
+JMKPCVVzqWf2ANYwHwYDVR0jBBgwFoAU1oR1x2CnoPapJMKPCVVzqWf2ANYwDwYD
+VR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNIADBFAiBpy0o2CY97MIxeQ0HgG44Y
+raBy6edj7W0EIH+yQxkDEwIhAI0nVKaI6duHLAvtKW6CfEQFG6jKg7dyk37YYiRD
+2jS0
+-----END CERTIFICATE-----"#;
+
+// The sha256 digest of the DER form of IDP_CERT_PEM.
+const IDP_CERT_FINGERPRINT: &str =
+    "49709e125b28f34e2d24fd012e787a4a4ecd9afb1676f9e2b2030e3bfddfb654";
+
+#[kanidmd_testkit::test]
+async fn test_server_idp_certificate_lifecycle(rsclient: &KanidmClient) {
+    // Not logged in - should fail!
+    let res = rsclient
+        .idm_account_certificate_list(IDM_ADMIN_TEST_USER)
+        .await;
+    assert!(res.is_err());
+
+    let res = rsclient
+        .auth_simple_password(IDM_ADMIN_TEST_USER, IDM_ADMIN_TEST_PASSWORD)
+        .await;
+    assert!(res.is_ok());
+
+    rsclient
+        .idm_person_account_create("idp_cert_account", "IdP Cert Account")
+        .await
+        .expect("Failed to create account");
+
+    let certs = rsclient
+        .idm_account_certificate_list("idp_cert_account")
+        .await
+        .expect("Failed to list certificates");
+    assert!(certs.is_empty());
+
+    rsclient
+        .idm_account_certificate_add("idp_cert_account", IDP_CERT_PEM)
+        .await
+        .expect("Failed to add certificate");
+
+    let certs = rsclient
+        .idm_account_certificate_list("idp_cert_account")
+        .await
+        .expect("Failed to list certificates");
+    assert_eq!(certs.len(), 1);
+    let summary = &certs[0];
+    assert_eq!(
+        summary.subject,
+        "O=Internet Widgits Pty Ltd,ST=Some-State,C=AU"
+    );
+    assert_eq!(summary.fingerprint_sha256, IDP_CERT_FINGERPRINT);
+
+    // The certificate must never be usable for client authentication, so it
+    // can not be stored as a client certificate.
+    let client_certs = rsclient
+        .search(Filter::Eq(
+            Attribute::Class.to_string(),
+            "client_certificate".to_string(),
+        ))
+        .await
+        .expect("Failed to search client certificates");
+    assert!(client_certs.is_empty());
+
+    let idp_cert_entry = rsclient
+        .search(Filter::Eq(
+            Attribute::Uuid.to_string(),
+            summary.uuid.to_string(),
+        ))
+        .await
+        .expect("Failed to search idp certificates");
+    assert_eq!(idp_cert_entry.len(), 1);
+    assert!(!idp_cert_entry[0]
+        .attrs
+        .contains_key(Attribute::Certificate.as_str()));
+    assert!(idp_cert_entry[0]
+        .attrs
+        .contains_key(Attribute::IdpCertificate.as_str()));
+
+    // An unknown fingerprint removes nothing.
+    let res = rsclient
+        .idm_account_certificate_remove(
+            "idp_cert_account",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+        )
+        .await;
+    assert!(res.is_err());
+
+    rsclient
+        .idm_account_certificate_remove("idp_cert_account", IDP_CERT_FINGERPRINT)
+        .await
+        .expect("Failed to remove certificate");
+
+    let certs = rsclient
+        .idm_account_certificate_list("idp_cert_account")
+        .await
+        .expect("Failed to list certificates");
+    assert!(certs.is_empty());
+}
+
 // wanna test how long it takes for testkit to start up? here's your biz.
 // turns out  as of 2023-10-11 on my M2 Max, it's about 1.0 seconds per iteration
 // #[kanidmd_testkit::test]
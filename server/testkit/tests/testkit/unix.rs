@@ -45,3 +45,35 @@ async fn account_id_unix_token(rsclient: &KanidmClient) {
         .await
         .is_ok());
 }
+
+#[kanidmd_testkit::test]
+async fn unix_changes_wait(rsclient: &KanidmClient) {
+    login_put_admin_idm_admins(rsclient).await;
+
+    create_user(rsclient, NOT_ADMIN_TEST_USERNAME, NAME_IDM_ADMINS).await;
+    rsclient
+        .idm_person_account_unix_extend(NOT_ADMIN_TEST_USERNAME, None, None)
+        .await
+        .unwrap();
+    let token = rsclient
+        .idm_account_unix_token_get(NOT_ADMIN_TEST_USERNAME)
+        .await
+        .unwrap();
+
+    // Nothing changed, so the wait ends without any changes.
+    let changes = rsclient.idm_unix_changes_wait(0).await.unwrap();
+    assert!(changes.uuids.is_empty());
+    assert!(!changes.all);
+
+    // Changing the shell of the account is seen by a waiting client.
+    let (changes, extend) = tokio::join!(rsclient.idm_unix_changes_wait(30), async {
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        rsclient
+            .idm_person_account_unix_extend(NOT_ADMIN_TEST_USERNAME, None, Some("/bin/zsh"))
+            .await
+    });
+    assert!(extend.is_ok());
+    let changes = changes.unwrap();
+    assert_eq!(changes.uuids, vec![token.uuid]);
+    assert!(!changes.all);
+}
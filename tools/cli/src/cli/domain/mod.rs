@@ -1,7 +1,8 @@
 use crate::OpType;
 use crate::{handle_client_error, DomainOpt, KanidmClientParser};
 use anyhow::{Context, Error};
-use kanidm_proto::internal::ImageValue;
+use kanidm_client::ClientError;
+use kanidm_proto::internal::{ImageValue, OperationError, SchemaError};
 use std::fs::read;
 
 impl DomainOpt {
@@ -18,6 +19,18 @@ impl DomainOpt {
                     .await
                 {
                     Ok(_) => println!("Success"),
+                    Err(ClientError::Http(
+                        _,
+                        Some(OperationError::SchemaViolation(SchemaError::InvalidDisplayName {
+                            value,
+                            reason,
+                        })),
+                        opid,
+                    )) => {
+                        error!("OperationId: {:?}", opid);
+                        error!("The display name {:?} is not valid, it {}", value, reason);
+                        std::process::exit(1);
+                    }
                     Err(e) => handle_client_error(e, opt.output_mode),
                 }
             }
@@ -1,5 +1,6 @@
 use crate::OpType;
 use crate::{handle_client_error, GroupOpt, GroupPosix, KanidmClientParser, OutputMode};
+use kanidm_client::{ClientError, StatusCode};
 use kanidm_proto::constants::ATTR_GIDNUMBER;
 
 mod account_policy;
@@ -195,13 +196,29 @@ impl GroupOpt {
             GroupOpt::Rename { name, new_name } => {
                 let client = opt.to_client(OpType::Write).await;
 
+                // The rename is a single modification of the group, so if the new name is
+                // already taken it is rejected without any change being made.
                 let result = client.group_rename(name.as_str(), new_name.as_str()).await;
 
                 match result {
+                    Err(ClientError::Http(StatusCode::CONFLICT, _, _)) => {
+                        error!(
+                            "Unable to rename group {name}, the name {new_name} is already in use"
+                        );
+                        std::process::exit(1);
+                    }
                     Err(e) => handle_client_error(e, opt.output_mode),
-                    Ok(_) => opt
-                        .output_mode
-                        .print_message(format!("Successfully renamed group {name} to {new_name}")),
+                    Ok(_) => {
+                        opt.output_mode.print_message(format!(
+                            "Successfully renamed group {name} to {new_name}"
+                        ));
+                        // Memberships, OAuth2 scope maps and claim maps refer to the group by
+                        // uuid so they follow the rename. Client configuration refers to the
+                        // group by name and can't be updated from here.
+                        warn!(
+                            "Any client configuration that refers to the group {name} by name must be updated to {new_name}, such as pam_allowed_login_groups, map_group and kanidm_to_local in the kanidm-unixd configuration."
+                        );
+                    }
                 }
             }
             GroupOpt::SetEntryManagedBy {
@@ -0,0 +1,88 @@
+use crate::OpType;
+use crate::{handle_client_error, IdpCertOpt, KanidmClientParser, OutputMode};
+
+impl IdpCertOpt {
+    pub async fn exec(&self, opt: KanidmClientParser) {
+        match self {
+            IdpCertOpt::List { account } => {
+                let client = opt.to_client(OpType::Read).await;
+
+                let account = match account {
+                    Some(account) => account.clone(),
+                    None => {
+                        match client.whoami().await {
+                            Ok(Some(ent)) => match ent.attrs.get("spn").and_then(|v| v.first()) {
+                                Some(spn) => spn.clone(),
+                                None => {
+                                    error!("Failed to parse your SPN from the whoami endpoint");
+                                    return;
+                                }
+                            },
+                            Ok(None) => {
+                                error!("Authentication with cached token failed, can't query information.");
+                                return;
+                            }
+                            Err(e) => {
+                                handle_client_error(e, opt.output_mode);
+                                return;
+                            }
+                        }
+                    }
+                };
+
+                match client.idm_account_certificate_list(&account).await {
+                    Ok(certs) => match opt.output_mode {
+                        OutputMode::Json => {
+                            let json = serde_json::to_string(&certs)
+                                .expect("Failed to serialise list to JSON!");
+                            println!("{json}");
+                        }
+                        OutputMode::Text => {
+                            if certs.is_empty() {
+                                println!("No certificates available");
+                            } else {
+                                for cert in certs {
+                                    println!("{cert}");
+                                }
+                            }
+                        }
+                    },
+                    Err(e) => handle_client_error(e, opt.output_mode),
+                }
+            }
+            IdpCertOpt::Add { account_id, pem } => {
+                let pem_data = match tokio::fs::read_to_string(pem).await {
+                    Ok(pd) => pd,
+                    Err(io_err) => {
+                        error!(?io_err, ?pem, "Unable to read PEM data");
+                        return;
+                    }
+                };
+
+                let client = opt.to_client(OpType::Write).await;
+
+                match client
+                    .idm_account_certificate_add(account_id, &pem_data)
+                    .await
+                {
+                    Ok(()) => opt.output_mode.print_message("Success"),
+                    Err(e) => handle_client_error(e, opt.output_mode),
+                }
+            }
+            IdpCertOpt::Remove {
+                account_id,
+                fingerprint,
+            } => {
+                let client = opt.to_client(OpType::Write).await;
+
+                match client
+                    .idm_account_certificate_remove(account_id, fingerprint)
+                    .await
+                {
+                    Ok(()) => opt.output_mode.print_message("Success"),
+                    Err(e) => handle_client_error(e, opt.output_mode),
+                }
+            }
+        }
+    }
+}
@@ -28,6 +28,7 @@ mod common;
 mod domain;
 mod graph;
 mod group;
+mod idp_cert;
 mod oauth2;
 mod person;
 mod raw;
@@ -147,6 +148,7 @@ impl SystemOpt {
     pub async fn exec(&self, opt: KanidmClientParser) {
         match self {
             SystemOpt::Api { commands } => commands.exec(opt).await,
+            SystemOpt::ApiToken { commands } => commands.exec(opt).await,
             SystemOpt::PwBadlist { commands } => commands.exec(opt).await,
             SystemOpt::DeniedNames { commands } => commands.exec(opt).await,
             SystemOpt::Oauth2 { commands } => commands.exec(opt).await,
@@ -177,6 +179,7 @@ impl KanidmClientParser {
             KanidmClientOpt::Schema {
                 commands: SchemaOpt::Attribute { commands },
             } => commands.exec(self).await,
+            KanidmClientOpt::IdpCert { commands } => commands.exec(self).await,
             KanidmClientOpt::Recycle { commands } => commands.exec(self).await,
             KanidmClientOpt::Version => {
                 self.output_mode
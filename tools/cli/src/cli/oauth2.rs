@@ -3,8 +3,11 @@ use crate::{handle_client_error, Oauth2Opt, OutputMode};
 use crate::{KanidmClientParser, Oauth2ClaimMapJoin};
 use anyhow::{Context, Error};
 use kanidm_proto::internal::{ImageValue, Oauth2ClaimMapJoin as ProtoOauth2ClaimMapJoin};
+use kanidm_proto::oauth2::OidcDiscoveryResponse;
+use kanidm_proto::scim_v1::ScimOauth2ClaimMapJoinChar;
 use std::fs::read;
 use std::process::exit;
+use url::Url;
 
 impl Oauth2Opt {
     pub async fn exec(&self, opt: KanidmClientParser) {
@@ -49,9 +52,67 @@ impl Oauth2Opt {
                     Err(e) => handle_client_error(e, opt.output_mode),
                 }
             }
-            Oauth2Opt::Get(nopt) => {
+            Oauth2Opt::Get(gopt) if gopt.openid_configuration => {
                 let client = opt.to_client(OpType::Read).await;
-                match client.idm_oauth2_rs_get(nopt.name.as_str()).await {
+                let discovery = match client
+                    .idm_oauth2_rs_get_openid_configuration(gopt.nopt.name.as_str())
+                    .await
+                {
+                    Ok(discovery) => discovery,
+                    Err(e) => return handle_client_error(e, opt.output_mode),
+                };
+
+                let endpoints = discovery_endpoints(&discovery);
+
+                let mut all_reachable = true;
+                let mut results = Vec::with_capacity(endpoints.len());
+                if gopt.check_reachable {
+                    for (_, url) in endpoints.iter() {
+                        // Any http response, even an error, shows the endpoint can be reached.
+                        let result = match client.client().get(url.as_str()).send().await {
+                            Ok(response) if response.status().is_server_error() => {
+                                all_reachable = false;
+                                format!("server error ({})", response.status())
+                            }
+                            Ok(response) => format!("reachable ({})", response.status()),
+                            Err(err) => {
+                                all_reachable = false;
+                                format!("unreachable ({err})")
+                            }
+                        };
+                        results.push(result);
+                    }
+                }
+
+                match opt.output_mode {
+                    OutputMode::Json => println!(
+                        "{}",
+                        openid_configuration_json(&discovery, &endpoints, &results)
+                    ),
+                    OutputMode::Text => {
+                        println!("Endpoints for relying party configuration:");
+                        for (name, url) in endpoints.iter() {
+                            println!("  {name}: {url}");
+                        }
+                        println!();
+                        println!(
+                            "{}",
+                            serde_json::to_string_pretty(&discovery)
+                                .expect("Failed to serialise json")
+                        );
+                        for ((name, _), result) in endpoints.iter().zip(results.iter()) {
+                            println!("{name}: {result}");
+                        }
+                    }
+                }
+
+                if !all_reachable {
+                    exit(1);
+                }
+            }
+            Oauth2Opt::Get(gopt) => {
+                let client = opt.to_client(OpType::Read).await;
+                match client.idm_oauth2_rs_get(gopt.nopt.name.as_str()).await {
                     Ok(Some(e)) => opt.output_mode.print_message(e),
                     Ok(None) => opt.output_mode.print_message("No matching entries"),
                     Err(e) => handle_client_error(e, opt.output_mode),
@@ -117,6 +178,28 @@ impl Oauth2Opt {
                     Err(e) => handle_client_error(e, opt.output_mode),
                 }
             }
+            Oauth2Opt::ListScopeMap(nopt) => {
+                let client = opt.to_client(OpType::Read).await;
+                match client
+                    .idm_oauth2_rs_get_scope_maps(nopt.name.as_str())
+                    .await
+                {
+                    Ok(maps) => match opt.output_mode {
+                        OutputMode::Json => println!(
+                            "{}",
+                            serde_json::to_string(&maps).expect("Failed to serialise json")
+                        ),
+                        OutputMode::Text => {
+                            for map in maps {
+                                let group = group_display(map.group, map.group_uuid);
+                                let scopes: Vec<_> = map.scopes.into_iter().collect();
+                                println!("{group}: {}", scopes.join(" "));
+                            }
+                        }
+                    },
+                    Err(e) => handle_client_error(e, opt.output_mode),
+                }
+            }
             Oauth2Opt::UpdateSupScopeMap(cbopt) => {
                 let client = opt.to_client(OpType::Write).await;
                 match client
@@ -297,6 +380,16 @@ impl Oauth2Opt {
                     Err(e) => handle_client_error(e, opt.output_mode),
                 }
             }
+            Oauth2Opt::SetAccessTokenExpiry { name, expiry } => {
+                let client = opt.to_client(OpType::Write).await;
+                match client
+                    .idm_oauth2_rs_set_access_token_expiry(name.as_str(), *expiry)
+                    .await
+                {
+                    Ok(_) => opt.output_mode.print_message("Success"),
+                    Err(e) => handle_client_error(e, opt.output_mode),
+                }
+            }
             Oauth2Opt::EnablePkce(nopt) => {
                 let client = opt.to_client(OpType::Write).await;
                 match client.idm_oauth2_rs_enable_pkce(nopt.name.as_str()).await {
@@ -371,17 +464,40 @@ impl Oauth2Opt {
                 group,
                 claim_name,
                 values,
+                json,
             } => {
                 let client = opt.to_client(OpType::Write).await;
-                match client
-                    .idm_oauth2_rs_update_claim_map(
-                        name.as_str(),
-                        claim_name.as_str(),
-                        group.as_str(),
-                        values,
-                    )
-                    .await
-                {
+                let result = if *json {
+                    let values = match values
+                        .iter()
+                        .map(|value| serde_json::from_str(value))
+                        .collect::<Result<Vec<serde_json::Value>, _>>()
+                    {
+                        Ok(values) => values,
+                        Err(e) => {
+                            error!("Invalid json claim value -> {:?}", e);
+                            exit(1)
+                        }
+                    };
+                    client
+                        .idm_oauth2_rs_update_claim_map_typed(
+                            name.as_str(),
+                            claim_name.as_str(),
+                            group.as_str(),
+                            &values,
+                        )
+                        .await
+                } else {
+                    client
+                        .idm_oauth2_rs_update_claim_map(
+                            name.as_str(),
+                            claim_name.as_str(),
+                            group.as_str(),
+                            values,
+                        )
+                        .await
+                };
+                match result {
                     Ok(_) => opt.output_mode.print_message("Success"),
                     Err(e) => handle_client_error(e, opt.output_mode),
                 }
@@ -425,6 +541,44 @@ impl Oauth2Opt {
                     Err(e) => handle_client_error(e, opt.output_mode),
                 }
             }
+            Oauth2Opt::ListClaimMap(nopt) => {
+                let client = opt.to_client(OpType::Read).await;
+                match client
+                    .idm_oauth2_rs_get_claim_maps(nopt.name.as_str())
+                    .await
+                {
+                    Ok(maps) => match opt.output_mode {
+                        OutputMode::Json => println!(
+                            "{}",
+                            serde_json::to_string(&maps).expect("Failed to serialise json")
+                        ),
+                        OutputMode::Text => {
+                            for map in maps {
+                                let group = group_display(map.group, map.group_uuid);
+                                let join = match map.join_char {
+                                    ScimOauth2ClaimMapJoinChar::CommaSeparatedValue => {
+                                        Oauth2ClaimMapJoin::Csv
+                                    }
+                                    ScimOauth2ClaimMapJoinChar::SpaceSeparatedValue => {
+                                        Oauth2ClaimMapJoin::Ssv
+                                    }
+                                    ScimOauth2ClaimMapJoinChar::JsonArray => {
+                                        Oauth2ClaimMapJoin::Array
+                                    }
+                                };
+                                let values: Vec<_> = map.values.into_iter().collect();
+                                println!(
+                                    "{} ({}) {group}: {}",
+                                    map.claim,
+                                    join.as_str(),
+                                    values.join(" ")
+                                );
+                            }
+                        }
+                    },
+                    Err(e) => handle_client_error(e, opt.output_mode),
+                }
+            }
 
             Oauth2Opt::EnablePublicLocalhost { name } => {
                 let client = opt.to_client(OpType::Write).await;
@@ -511,3 +665,151 @@ impl Oauth2Opt {
         }
     }
 }
+
+/// Prefer the group name when displaying a mapping, falling back to the uuid.
+fn group_display(group: Option<String>, group_uuid: Option<uuid::Uuid>) -> String {
+    group
+        .or_else(|| group_uuid.map(|u| u.to_string()))
+        .unwrap_or_else(|| "<unknown group>".to_string())
+}
+
+/// The endpoints of a discovery document that a relying party is configured with.
+fn discovery_endpoints(discovery: &OidcDiscoveryResponse) -> Vec<(&'static str, &Url)> {
+    [
+        ("issuer", Some(&discovery.issuer)),
+        (
+            "authorization_endpoint",
+            Some(&discovery.authorization_endpoint),
+        ),
+        ("token_endpoint", Some(&discovery.token_endpoint)),
+        ("userinfo_endpoint", discovery.userinfo_endpoint.as_ref()),
+        ("jwks_uri", Some(&discovery.jwks_uri)),
+        (
+            "revocation_endpoint",
+            discovery.revocation_endpoint.as_ref(),
+        ),
+        (
+            "introspection_endpoint",
+            discovery.introspection_endpoint.as_ref(),
+        ),
+        (
+            "device_authorization_endpoint",
+            discovery.device_authorization_endpoint.as_ref(),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(name, url)| url.map(|url| (name, url)))
+    .collect()
+}
+
+/// The discovery document and its endpoints as a single json document. The result of
+/// checking each endpoint is included when the endpoints were checked.
+fn openid_configuration_json(
+    discovery: &OidcDiscoveryResponse,
+    endpoints: &[(&'static str, &Url)],
+    results: &[String],
+) -> serde_json::Value {
+    let endpoints: Vec<_> = endpoints
+        .iter()
+        .enumerate()
+        .map(|(idx, (name, url))| {
+            let mut endpoint = serde_json::json!({
+                "endpoint": name,
+                "url": url,
+            });
+            if let Some(result) = results.get(idx) {
+                endpoint["result"] = serde_json::Value::from(result.as_str());
+            }
+            endpoint
+        })
+        .collect();
+
+    serde_json::json!({
+        "endpoints": endpoints,
+        "discovery": discovery,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{discovery_endpoints, openid_configuration_json};
+    use kanidm_proto::oauth2::OidcDiscoveryResponse;
+
+    fn test_discovery() -> OidcDiscoveryResponse {
+        serde_json::from_value(serde_json::json!({
+            "issuer": "https://idm.example.com/oauth2/openid/test",
+            "authorization_endpoint": "https://idm.example.com/ui/oauth2",
+            "token_endpoint": "https://idm.example.com/oauth2/token",
+            "jwks_uri": "https://idm.example.com/oauth2/openid/test/public_key.jwk",
+            "response_types_supported": [],
+            "subject_types_supported": [],
+            "id_token_signing_alg_values_supported": [],
+            "code_challenge_methods_supported": [],
+            "revocation_endpoint": "https://idm.example.com/oauth2/token/revoke",
+            "revocation_endpoint_auth_methods_supported": [],
+            "introspection_endpoint_auth_methods_supported": [],
+        }))
+        .expect("Failed to parse discovery document")
+    }
+
+    #[test]
+    fn test_discovery_endpoints() {
+        let discovery = test_discovery();
+
+        // Endpoints that aren't in the discovery document are skipped.
+        let endpoints: Vec<_> = discovery_endpoints(&discovery)
+            .into_iter()
+            .map(|(name, url)| (name, url.as_str()))
+            .collect();
+        assert_eq!(
+            endpoints,
+            vec![
+                ("issuer", "https://idm.example.com/oauth2/openid/test"),
+                (
+                    "authorization_endpoint",
+                    "https://idm.example.com/ui/oauth2"
+                ),
+                ("token_endpoint", "https://idm.example.com/oauth2/token"),
+                (
+                    "jwks_uri",
+                    "https://idm.example.com/oauth2/openid/test/public_key.jwk"
+                ),
+                (
+                    "revocation_endpoint",
+                    "https://idm.example.com/oauth2/token/revoke"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_openid_configuration_json() {
+        let discovery = test_discovery();
+        let endpoints = discovery_endpoints(&discovery);
+
+        let unchecked = openid_configuration_json(&discovery, &endpoints, &[]);
+        assert_eq!(
+            unchecked["discovery"]["issuer"],
+            "https://idm.example.com/oauth2/openid/test"
+        );
+        assert_eq!(unchecked["endpoints"].as_array().map(Vec::len), Some(5));
+        assert_eq!(unchecked["endpoints"][2]["endpoint"], "token_endpoint");
+        assert_eq!(
+            unchecked["endpoints"][2]["url"],
+            "https://idm.example.com/oauth2/token"
+        );
+        assert!(unchecked["endpoints"][2].get("result").is_none());
+
+        let results: Vec<_> = endpoints
+            .iter()
+            .map(|_| "reachable (200 OK)".to_string())
+            .collect();
+        let checked = openid_configuration_json(&discovery, &endpoints, &results);
+        assert_eq!(checked["endpoints"][2]["result"], "reachable (200 OK)");
+
+        // The whole output is a single json document.
+        let output = checked.to_string();
+        assert_eq!(output.lines().count(), 1);
+        assert!(serde_json::from_str::<serde_json::Value>(&output).is_ok());
+    }
+}
@@ -1,9 +1,9 @@
 use crate::common::try_expire_at_from_string;
 use crate::OpType;
 use crate::{
-    handle_client_error, password_prompt, AccountCertificate, AccountCredential, AccountRadius,
-    AccountSsh, AccountUserAuthToken, AccountValidity, KanidmClientParser, OutputMode, PersonOpt,
-    PersonPosix,
+    handle_client_error, password_prompt, AccountCertificate, AccountCredential,
+    AccountNamedGithubOpt, AccountRadius, AccountResetLink, AccountSsh, AccountUserAuthToken,
+    AccountValidity, KanidmClientParser, OutputMode, PersonOpt, PersonPosix, PersonSsh,
 };
 use dialoguer::theme::ColorfulTheme;
 use dialoguer::{Confirm, Input, Password, Select};
@@ -24,10 +24,12 @@ use kanidm_proto::messages::{AccountChangeMessage, ConsoleOutputMode, MessageSta
 use kanidm_proto::scim_v1::{client::ScimSshPublicKeys, ScimEntryGetQuery};
 use qrcode::render::unicode;
 use qrcode::QrCode;
+use serde::Deserialize;
 use std::fmt::{self, Debug};
 use std::str::FromStr;
 use time::format_description::well_known::Rfc3339;
 use time::{OffsetDateTime, UtcOffset};
+use url::Url;
 use uuid::Uuid;
 
 #[cfg(any(target_os = "macos", target_os = "linux", target_os = "windows"))]
@@ -35,6 +37,116 @@ use crate::webauthn::get_authenticator;
 #[cfg(any(target_os = "macos", target_os = "linux", target_os = "windows"))]
 use webauthn_authenticator_rs::WebauthnAuthenticator;
 
+/// A public key as returned by `https://api.github.com/users/<user>/keys`
+#[derive(Debug, Deserialize)]
+struct GithubSshKey {
+    id: u64,
+    key: String,
+}
+
+/// The base64 key material of an openssh formatted public key, ignoring the
+/// key type and comment.
+fn ssh_key_material(key: &str) -> Option<&str> {
+    key.split_whitespace().nth(1)
+}
+
+/// The url of the public keys of a GitHub user. The username is escaped as a single
+/// path segment, so it can not alter the rest of the request.
+fn github_ssh_keys_url(github_username: &str) -> Url {
+    let mut url = Url::parse("https://api.github.com/users").expect("Invalid GitHub api url");
+    url.path_segments_mut()
+        .expect("GitHub api url can not be a base")
+        .push(github_username)
+        .push("keys");
+    url
+}
+
+async fn fetch_github_ssh_keys(github_username: &str) -> Result<Vec<GithubSshKey>, reqwest::Error> {
+    reqwest::Client::new()
+        .get(github_ssh_keys_url(github_username))
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        // GitHub rejects requests without a user agent.
+        .header(
+            reqwest::header::USER_AGENT,
+            concat!("kanidm/", env!("CARGO_PKG_VERSION")),
+        )
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+}
+
+async fn import_github_ssh_keys(
+    client: &KanidmClient,
+    aopt: &AccountNamedGithubOpt,
+    output_mode: OutputMode,
+) {
+    let account_id = aopt.aopts.account_id.as_str();
+
+    let github_keys = match fetch_github_ssh_keys(aopt.github_username.as_str()).await {
+        Ok(keys) => keys,
+        Err(err) => {
+            error!(
+                ?err,
+                "Failed to fetch ssh keys for GitHub user {}", aopt.github_username
+            );
+            return;
+        }
+    };
+
+    let mut entry = match client
+        .scim_v1_person_get(
+            account_id,
+            Some(ScimEntryGetQuery {
+                attributes: Some(vec![Attribute::SshPublicKey]),
+                ..Default::default()
+            }),
+        )
+        .await
+    {
+        Ok(entry) => entry,
+        Err(e) => return handle_client_error(e, output_mode),
+    };
+
+    let existing_keys = match entry.attrs.remove(&Attribute::SshPublicKey) {
+        Some(pkeys) => match serde_json::from_value::<ScimSshPublicKeys>(pkeys) {
+            Ok(keys) => keys
+                .into_iter()
+                .map(|key| key.value.to_string())
+                .collect::<Vec<_>>(),
+            Err(_) => {
+                eprintln!("Invalid ssh public key format");
+                return;
+            }
+        },
+        None => Vec::new(),
+    };
+
+    for github_key in github_keys {
+        let label = format!("github/{}", github_key.id);
+
+        let already_present = ssh_key_material(&github_key.key).is_some_and(|material| {
+            existing_keys
+                .iter()
+                .any(|existing| ssh_key_material(existing) == Some(material))
+        });
+
+        if already_present {
+            println!("Skipping {label}, the key is already present");
+        } else if aopt.dry_run {
+            println!("Would add {label}: {}", github_key.key);
+        } else if let Err(e) = client
+            .idm_person_account_post_ssh_pubkey(account_id, &label, &github_key.key)
+            .await
+        {
+            handle_client_error(e, output_mode)
+        } else {
+            println!("Added {label}");
+        }
+    }
+}
+
 impl PersonOpt {
     pub async fn exec(&self, opt: KanidmClientParser) {
         match self {
@@ -152,6 +264,62 @@ impl PersonOpt {
                         handle_client_error(e, opt.output_mode)
                     }
                 }
+                PersonPosix::SetExpiry(ano) => {
+                    #[allow(clippy::disallowed_methods)]
+                    // Allowed as this is a local time from the callers machine.
+                    let now = OffsetDateTime::now_utc();
+                    let new_expiry = match OffsetDateTime::parse(ano.datetime.as_str(), &Rfc3339) {
+                        Ok(odt) if odt > now => odt,
+                        Ok(_) => {
+                            error!("The expiry time {} is not in the future", ano.datetime);
+                            return;
+                        }
+                        Err(e) => {
+                            error!("Error parsing the expiry time -> {:?}", e);
+                            return;
+                        }
+                    };
+                    let new_expiry = match new_expiry.format(&Rfc3339) {
+                        Ok(val) => val,
+                        Err(e) => {
+                            error!("Error formatting the expiry time -> {:?}", e);
+                            return;
+                        }
+                    };
+
+                    let client = opt.to_client(OpType::Write).await;
+                    let account_id = ano.aopts.account_id.as_str();
+                    let res = async {
+                        print_account_expiry(&client, account_id, "before").await?;
+                        client
+                            .idm_person_account_set_attr(
+                                account_id,
+                                ATTR_ACCOUNT_EXPIRE,
+                                &[&new_expiry],
+                            )
+                            .await?;
+                        print_account_expiry(&client, account_id, "after").await
+                    }
+                    .await;
+                    if let Err(e) = res {
+                        handle_client_error(e, opt.output_mode)
+                    }
+                }
+                PersonPosix::ClearExpiry(ano) => {
+                    let client = opt.to_client(OpType::Write).await;
+                    let account_id = ano.aopts.account_id.as_str();
+                    let res = async {
+                        print_account_expiry(&client, account_id, "before").await?;
+                        client
+                            .idm_person_account_purge_attr(account_id, ATTR_ACCOUNT_EXPIRE)
+                            .await?;
+                        print_account_expiry(&client, account_id, "after").await
+                    }
+                    .await;
+                    if let Err(e) = res {
+                        handle_client_error(e, opt.output_mode)
+                    }
+                }
             }, // end PersonOpt::Posix
             PersonOpt::Session { commands } => match commands {
                 AccountUserAuthToken::Status(apo) => {
@@ -189,7 +357,7 @@ impl PersonOpt {
                 }
             }, // End PersonOpt::Session
             PersonOpt::Ssh { commands } => match commands {
-                AccountSsh::List(aopt) => {
+                PersonSsh::Account(AccountSsh::List(aopt)) => {
                     let client = opt.to_client(OpType::Read).await;
 
                     let mut entry = match client
@@ -220,7 +388,7 @@ impl PersonOpt {
                         println!("{}: {}", key.label, key.value);
                     }
                 }
-                AccountSsh::Add(aopt) => {
+                PersonSsh::Account(AccountSsh::Add(aopt)) => {
                     let client = opt.to_client(OpType::Write).await;
                     if let Err(e) = client
                         .idm_person_account_post_ssh_pubkey(
@@ -233,7 +401,7 @@ impl PersonOpt {
                         handle_client_error(e, opt.output_mode)
                     }
                 }
-                AccountSsh::Delete(aopt) => {
+                PersonSsh::Account(AccountSsh::Delete(aopt)) => {
                     let client = opt.to_client(OpType::Write).await;
                     if let Err(e) = client
                         .idm_person_account_delete_ssh_pubkey(
@@ -245,8 +413,43 @@ impl PersonOpt {
                         handle_client_error(e, opt.output_mode)
                     }
                 }
+                PersonSsh::ImportFromGithub(aopt) => {
+                    let client = opt.to_client(OpType::Write).await;
+                    import_github_ssh_keys(&client, aopt, opt.output_mode).await;
+                }
             }, // end PersonOpt::Ssh
-            PersonOpt::List => {
+            PersonOpt::List {
+                filter: Some(filter),
+            } => {
+                let client = opt.to_client(OpType::Read).await;
+                let query = ScimEntryGetQuery {
+                    filter: Some(filter.clone()),
+                    ..Default::default()
+                };
+                match client.scim_v1_person_query(query).await {
+                    Ok(r) => match opt.output_mode {
+                        OutputMode::Json => {
+                            println!(
+                                "{}",
+                                serde_json::to_string(&r.resources)
+                                    .expect("Failed to serialise json")
+                            );
+                        }
+                        OutputMode::Text => r.resources.iter().for_each(|ent| {
+                            println!("---");
+                            println!("id: {}", ent.header.id);
+                            for (attr, value) in ent.attrs.iter() {
+                                println!("{attr}: {value}");
+                            }
+                        }),
+                    },
+                    Err(e) => {
+                        error!("Failed to list persons matching the filter {filter}");
+                        handle_client_error(e, opt.output_mode)
+                    }
+                }
+            }
+            PersonOpt::List { filter: None } => {
                 let client = opt.to_client(OpType::Read).await;
                 match client.idm_person_account_list().await {
                     Ok(r) => match opt.output_mode {
@@ -406,21 +609,10 @@ impl PersonOpt {
                         println!("valid after: any time");
                     }
 
-                    if let Some(t) = entry.attrs.get(ATTR_ACCOUNT_EXPIRE) {
-                        let t = OffsetDateTime::parse(&t[0], &Rfc3339)
-                            .map(|odt| {
-                                odt.to_offset(
-                                    time::UtcOffset::local_offset_at(OffsetDateTime::UNIX_EPOCH)
-                                        .unwrap_or(time::UtcOffset::UTC),
-                                )
-                                .format(&Rfc3339)
-                                .unwrap_or(odt.to_string())
-                            })
-                            .unwrap_or_else(|_| "invalid timestamp".to_string());
-                        println!("expire: {t}");
-                    } else {
-                        println!("expire: never");
-                    }
+                    println!(
+                        "expire: {}",
+                        display_expiry(entry.attrs.get(ATTR_ACCOUNT_EXPIRE))
+                    );
                 }
                 AccountValidity::ExpireAt(ano) => {
                     let client = opt.to_client(OpType::Write).await;
@@ -500,6 +692,36 @@ impl PersonOpt {
     }
 }
 
+/// Render a stored account expiry in the local timezone.
+fn display_expiry(expiry: Option<&Vec<String>>) -> String {
+    let Some(t) = expiry.and_then(|values| values.first()) else {
+        return "never".to_string();
+    };
+
+    OffsetDateTime::parse(t, &Rfc3339)
+        .map(|odt| {
+            odt.to_offset(
+                time::UtcOffset::local_offset_at(OffsetDateTime::UNIX_EPOCH)
+                    .unwrap_or(time::UtcOffset::UTC),
+            )
+            .format(&Rfc3339)
+            .unwrap_or(odt.to_string())
+        })
+        .unwrap_or_else(|_| "invalid timestamp".to_string())
+}
+
+async fn print_account_expiry(
+    client: &KanidmClient,
+    account_id: &str,
+    label: &str,
+) -> Result<(), ClientError> {
+    let expiry = client
+        .idm_person_account_get_attr(account_id, ATTR_ACCOUNT_EXPIRE)
+        .await?;
+    println!("expire ({label}): {}", display_expiry(expiry.as_ref()));
+    Ok(())
+}
+
 impl AccountCertificate {
     pub async fn exec(&self, opt: KanidmClientParser) {
         match self {
@@ -666,6 +888,58 @@ impl AccountCredential {
                 }
             }
 
+            AccountCredential::ResetLink {
+                commands:
+                    AccountResetLink::Generate {
+                        aopts,
+                        ttl,
+                        permissions,
+                    },
+            } => {
+                let client = opt.to_client(OpType::Write).await;
+
+                #[allow(clippy::disallowed_methods)]
+                // Allowed as this is only compared to the expiry the server returns.
+                let requested_expiry = ttl.map(|ttl| OffsetDateTime::now_utc() + ttl);
+
+                match client
+                    .idm_person_account_credential_update_intent_with_permissions(
+                        aopts.account_id.as_str(),
+                        ttl.map(|ttl| ttl.as_secs()),
+                        permissions.iter().copied().collect(),
+                    )
+                    .await
+                {
+                    Ok(CUIntentToken { token, expiry_time }) => {
+                        let mut url = client.make_url("/ui/reset");
+                        url.query_pairs_mut().append_pair("token", token.as_str());
+
+                        // The server clamps the ttl rather than rejecting it, allowing
+                        // a minute of clock skew between us and the server.
+                        if let Some(requested_expiry) = requested_expiry {
+                            if expiry_time + time::Duration::minutes(1) < requested_expiry {
+                                warn!(
+                                    "The requested ttl exceeds the maximum allowed by the server, the link will expire sooner than requested"
+                                );
+                            }
+                        }
+
+                        let local_offset =
+                            UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
+                        let expiry_time = expiry_time.to_offset(local_offset);
+
+                        println!("{}", url.as_str());
+                        println!(
+                            "This link will expire at: {}",
+                            expiry_time
+                                .format(&Rfc3339)
+                                .expect("Failed to format date time!!!")
+                        );
+                    }
+                    Err(e) => handle_client_error(e, opt.output_mode),
+                }
+            }
+
             AccountCredential::SendResetToken {
                 account_id,
                 ttl,
@@ -1778,3 +2052,37 @@ async fn credential_update_exec(
     }
     trace!("ended credential update exec");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{github_ssh_keys_url, ssh_key_material, GithubSshKey};
+
+    #[test]
+    fn test_github_ssh_keys_parse() {
+        let keys: Vec<GithubSshKey> = serde_json::from_str(
+            r#"[{"id": 1234, "key": "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIBhF"}]"#,
+        )
+        .unwrap();
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].id, 1234);
+
+        // The comment and key type don't affect the comparison.
+        assert_eq!(
+            ssh_key_material(&keys[0].key),
+            ssh_key_material("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIBhF user@host")
+        );
+        assert_eq!(ssh_key_material("garbage"), None);
+    }
+
+    #[test]
+    fn test_github_ssh_keys_url() {
+        assert_eq!(
+            github_ssh_keys_url("octocat").as_str(),
+            "https://api.github.com/users/octocat/keys"
+        );
+        assert_eq!(
+            github_ssh_keys_url("a/b?c#d").as_str(),
+            "https://api.github.com/users/a%2Fb%3Fc%23d/keys"
+        );
+    }
+}
@@ -0,0 +1,33 @@
+use crate::OpType;
+use crate::{KanidmClientParser, OutputMode, SystemApiTokenOpt};
+
+impl SystemApiTokenOpt {
+    pub async fn exec(&self, opt: KanidmClientParser) {
+        match self {
+            SystemApiTokenOpt::List { account } => {
+                let client = opt.to_client(OpType::Read).await;
+                match client.system_api_token_list(account.as_deref()).await {
+                    Ok(tokens) => match opt.output_mode {
+                        OutputMode::Json => {
+                            let json = serde_json::to_string(&tokens)
+                                .expect("Failed to serialise list to JSON!");
+                            println!("{json}");
+                        }
+                        OutputMode::Text => {
+                            if tokens.is_empty() {
+                                println!("No api tokens exist");
+                            } else {
+                                for token in tokens {
+                                    println!("{token}");
+                                }
+                            }
+                            eprintln!("--");
+                            eprintln!("Success");
+                        }
+                    },
+                    Err(e) => crate::handle_client_error(e, opt.output_mode),
+                }
+            }
+        }
+    }
+}
@@ -1,4 +1,5 @@
 pub mod api;
+pub mod api_token;
 pub mod badlist;
 pub mod denied_names;
 pub mod message;
@@ -1,8 +1,9 @@
 use clap::{builder::PossibleValue, Args, Subcommand, ValueEnum};
 use kanidm_proto::constants::CLIENT_TOKEN_CACHE;
-use kanidm_proto::internal::ImageType;
+use kanidm_proto::internal::{CUIntentPermission, ImageType};
 use kanidm_proto::scim_v1::ScimFilter;
 use std::fmt;
+use std::time::Duration;
 use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
 
@@ -335,6 +336,15 @@ pub struct AccountNamedExpireDateTimeOpt {
     datetime: String,
 }
 
+#[derive(Debug, Args, Clone)]
+pub struct AccountNamedFutureDateTimeOpt {
+    #[clap(flatten)]
+    aopts: AccountCommonOpt,
+    #[clap(name = "datetime")]
+    /// An rfc3339 time in the future, of the format "YYYY-MM-DDTHH:MM:SS+TZ", "2020-09-25T11:22:02+10:00"
+    datetime: String,
+}
+
 #[derive(Debug, Args, Clone)]
 pub struct AccountNamedValidDateTimeOpt {
     #[clap(flatten)]
@@ -363,6 +373,18 @@ pub struct AccountNamedTagPkOpt {
     pubkey: String,
 }
 
+#[derive(Debug, Args, Clone)]
+pub struct AccountNamedGithubOpt {
+    #[clap(flatten)]
+    aopts: AccountCommonOpt,
+    /// The GitHub user whose public keys will be imported
+    #[clap(name = "github_username")]
+    github_username: String,
+    /// Show the keys that would be added without changing the account
+    #[clap(long)]
+    dry_run: bool,
+}
+
 #[derive(Debug, Args, Clone)]
 /// Command-line options for account credential use-reset-token
 pub struct UseResetTokenOpt {
@@ -401,6 +423,13 @@ pub enum AccountCredential {
         #[clap(long)]
         ttl: Option<u32>,
     },
+    /// Manage links that can be given to another person so they can
+    /// recover or reset their account credentials.
+    #[clap(name = "reset-link")]
+    ResetLink {
+        #[clap(subcommand)]
+        commands: AccountResetLink,
+    },
     /// Send a reset token to the account's email so that the user may
     /// recover or reset their account credentials.
     #[clap(name = "send-reset-token")]
@@ -428,6 +457,27 @@ pub enum AccountCredential {
     }
 }
 
+#[derive(Debug, Subcommand, Clone)]
+pub enum AccountResetLink {
+    /// Generate a credential reset link and print the URL the person should visit.
+    #[clap(name = "generate")]
+    Generate {
+        #[clap(flatten)]
+        aopts: AccountCommonOpt,
+
+        /// How long the link is valid for, such as "30m" or "24h".
+        /// Default: 1h
+        #[clap(long, value_parser = humantime::parse_duration)]
+        ttl: Option<Duration>,
+
+        /// Limit the link to these credential types, separated by commas. Any of
+        /// primary, passkeys, attested_passkeys, unixcred or sshpubkey.
+        /// Default: all that you are able to update
+        #[clap(long, value_delimiter = ',')]
+        permissions: Vec<CUIntentPermission>,
+    },
+}
+
 /// RADIUS secret management
 #[derive(Debug, Subcommand, Clone)]
 pub enum AccountRadius {
@@ -464,6 +514,12 @@ pub enum PersonPosix {
     /// Reset the gidnumber of this person to the generated default
     #[clap(name = "reset-gidnumber")]
     ResetGidnumber { account_id: String },
+    /// Set the time at which this person's account expires
+    #[clap(name = "set-expiry")]
+    SetExpiry(AccountNamedFutureDateTimeOpt),
+    /// Remove the expiry time of this person's account, so that it never expires
+    #[clap(name = "clear-expiry")]
+    ClearExpiry(AccountNamedOpt),
 }
 
 #[derive(Debug, Subcommand, Clone)]
@@ -508,6 +564,17 @@ pub enum AccountSsh {
     Delete(AccountNamedTagOpt),
 }
 
+#[derive(Debug, Subcommand, Clone)]
+pub enum PersonSsh {
+    #[clap(flatten)]
+    Account(AccountSsh),
+    /// Import the ssh public keys published on a GitHub profile
+    ///
+    /// Each key is labeled `github/<key_id>`, and keys already on the account are skipped.
+    #[clap(name = "import-from-github")]
+    ImportFromGithub(AccountNamedGithubOpt),
+}
+
 #[derive(Debug, Subcommand, Clone)]
 pub enum AccountValidity {
     /// Show an accounts validity window
@@ -580,11 +647,17 @@ pub enum PersonOpt {
     #[clap(name = "ssh")]
     Ssh {
         #[clap(subcommand)]
-        commands: AccountSsh,
+        commands: PersonSsh,
     },
     /// List all persons
     #[clap(name = "list")]
-    List,
+    List {
+        /// Only list the persons matching this SCIM filter, such as
+        /// `'emails[primary eq true and value ew "@example.com"]'`. The filter is
+        /// evaluated by the server.
+        #[clap(long)]
+        filter: Option<ScimFilter>,
+    },
     /// View a specific person
     #[clap(name = "get")]
     Get(AccountNamedOpt),
@@ -765,6 +838,33 @@ pub enum ServiceAccountOpt {
     IntoPerson(AccountNamedOpt),
 }
 
+#[derive(Debug, Subcommand, Clone)]
+pub enum IdpCertOpt {
+    /// List the certificates held by an account
+    #[clap(name = "list")]
+    List {
+        /// The account to list certificates for. Defaults to the authenticated account.
+        #[clap(long)]
+        account: Option<String>,
+    },
+    /// Add a PEM encoded certificate to an account
+    #[clap(name = "add")]
+    Add {
+        account_id: String,
+        /// Path to the PEM encoded certificate
+        #[clap(long)]
+        pem: PathBuf,
+    },
+    /// Remove a certificate from an account
+    #[clap(name = "remove")]
+    Remove {
+        account_id: String,
+        /// The sha256 fingerprint of the certificate, as shown by "list"
+        #[clap(long)]
+        fingerprint: String,
+    },
+}
+
 #[derive(Debug, Subcommand, Clone)]
 pub enum RecycleOpt {
     #[clap(name = "list")]
@@ -861,6 +961,19 @@ pub struct Oauth2DeleteScopeMapOpt {
     group: String,
 }
 
+#[derive(Debug, Args, Clone)]
+pub struct Oauth2GetOpt {
+    #[clap(flatten)]
+    nopt: Named,
+    /// Display the OpenID Connect discovery document of this client rather than its
+    /// entry. The endpoints a relying party needs to be configured with are listed first.
+    #[clap(long)]
+    openid_configuration: bool,
+    /// Check that each endpoint in the discovery document can be reached.
+    #[clap(long, requires = "openid_configuration")]
+    check_reachable: bool,
+}
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum Oauth2ClaimMapJoin {
     Csv,
@@ -895,7 +1008,7 @@ pub enum Oauth2Opt {
     List,
     #[clap(name = "get")]
     /// Display a selected oauth2 client
-    Get(Named),
+    Get(Oauth2GetOpt),
     // #[clap(name = "set")]
     // /// Set options for a selected oauth2 client
     // Set(),
@@ -929,6 +1042,9 @@ pub enum Oauth2Opt {
     #[clap(name = "delete-scope-map")]
     /// Remove a mapping from groups to scopes
     DeleteScopeMap(Oauth2DeleteScopeMapOpt),
+    #[clap(name = "list-scope-map")]
+    /// List the mappings from groups to scopes
+    ListScopeMap(Named),
 
     #[clap(name = "update-sup-scope-map", visible_aliases=&["create-sup-scope-map"])]
     /// Update or add a new mapping from a group to scopes that it provides to members
@@ -944,6 +1060,9 @@ pub enum Oauth2Opt {
         claim_name: String,
         group: String,
         values: Vec<String>,
+        /// Parse each value as json, so that claims can be booleans, numbers or objects.
+        #[clap(long)]
+        json: bool,
     },
     #[clap(name = "update-claim-map-join")]
     UpdateClaimMapJoin {
@@ -960,6 +1079,9 @@ pub enum Oauth2Opt {
         claim_name: String,
         group: String,
     },
+    #[clap(name = "list-claim-map")]
+    /// List the mappings from groups to custom claims
+    ListClaimMap(Named),
 
     #[clap(name = "reset-basic-secret")]
     /// Reset the client basic secret. You will need to update your client after
@@ -1017,6 +1139,14 @@ pub enum Oauth2Opt {
         expiry: Option<u32>,
     },
 
+    /// Set the access token expiry in seconds, between 30 and 3600. An empty value will
+    /// reset the value to default.
+    #[clap(name = "set-access-token-expiry")]
+    SetAccessTokenExpiry {
+        name: String,
+        expiry: Option<u32>,
+    },
+
     /// Add a supplemental URL as a redirection target. For example a phone app
     /// may use a redirect URL such as `app://my-cool-app` to trigger a native
     /// redirection event out of a browser.
@@ -1395,6 +1525,17 @@ pub enum SchemaOpt {
     },
 }
 
+#[derive(Debug, Subcommand, Clone)]
+pub enum SystemApiTokenOpt {
+    #[clap(name = "list")]
+    /// List the api tokens held by all accounts
+    List {
+        /// Only list the api tokens of this account
+        #[clap(long)]
+        account: Option<String>,
+    },
+}
+
 #[derive(Debug, Subcommand, Clone)]
 pub enum SystemOpt {
     #[clap(name = "pw-badlist")]
@@ -1433,6 +1574,12 @@ pub enum SystemOpt {
         #[clap(subcommand)]
         commands: MessageOpt,
     },
+    #[clap(name = "api-token")]
+    /// Audit the api tokens held by all accounts
+    ApiToken {
+        #[clap(subcommand)]
+        commands: SystemApiTokenOpt,
+    },
     #[clap(name = "api")]
     /// API related things
     Api {
@@ -1493,6 +1640,12 @@ pub enum KanidmClientOpt {
         #[clap(subcommand)]
         commands: SystemOpt,
     },
+    /// Manage the certificates held by accounts
+    #[clap(name = "idp-cert")]
+    IdpCert {
+        #[clap(subcommand)]
+        commands: IdpCertOpt,
+    },
     #[clap(name = "recycle-bin")]
     /// Recycle Bin operations
     Recycle {
@@ -1592,3 +1745,41 @@ impl KanidmClientParser {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::AccountResetLink;
+    use clap::Parser;
+    use std::time::Duration;
+
+    #[derive(Debug, Parser)]
+    struct ResetLinkParser {
+        #[clap(subcommand)]
+        commands: AccountResetLink,
+    }
+
+    fn parse_ttl(ttl: &str) -> Result<Option<Duration>, clap::Error> {
+        ResetLinkParser::try_parse_from(["reset-link", "generate", "alice", "--ttl", ttl]).map(
+            |parsed| match parsed.commands {
+                AccountResetLink::Generate { ttl, .. } => ttl,
+            },
+        )
+    }
+
+    #[test]
+    fn test_parse_reset_link_ttl() {
+        assert_eq!(parse_ttl("90s").ok(), Some(Some(Duration::from_secs(90))));
+        assert_eq!(parse_ttl("30m").ok(), Some(Some(Duration::from_secs(1800))));
+        assert_eq!(parse_ttl("24h").ok(), Some(Some(Duration::from_secs(86400))));
+        assert_eq!(parse_ttl("1d").ok(), Some(Some(Duration::from_secs(86400))));
+        assert_eq!(
+            parse_ttl("1h 30m").ok(),
+            Some(Some(Duration::from_secs(5400)))
+        );
+
+        assert!(parse_ttl("").is_err());
+        assert!(parse_ttl("h").is_err());
+        assert!(parse_ttl("24x").is_err());
+        assert!(parse_ttl("99999999999999999999w").is_err());
+    }
+}
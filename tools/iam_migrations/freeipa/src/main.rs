@@ -27,7 +27,7 @@ use kanidm_proto::constants::{
 };
 use kanidm_proto::scim_v1::{
     MultiValueAttr, ScimEntry, ScimSshPubKey, ScimSyncGroup, ScimSyncPerson, ScimSyncRequest,
-    ScimSyncRetentionMode, ScimSyncState, ScimTotp,
+    ScimSyncRetentionMode, ScimSyncState, ScimTotp, TotpAlgorithm,
 };
 use kanidmd_lib::prelude::{Attribute, EntryClass, ENTRYCLASS_PERSON};
 use ldap3_client::{
@@ -377,7 +377,7 @@ async fn run_sync(
 
     let cookie = match &scim_sync_status {
         ScimSyncState::Refresh => None,
-        ScimSyncState::Active { cookie } => Some(cookie.to_vec()),
+        ScimSyncState::Active { cookie, .. } => Some(cookie.to_vec()),
     };
 
     let is_initialise = cookie.is_none();
@@ -462,9 +462,7 @@ async fn run_sync(
             }
 
             let to_state = cookie
-                .map(|cookie| {
-                    ScimSyncState::Active { cookie }
-                })
+                .map(ScimSyncState::active)
                 .ok_or_else(|| {
                     error!("Invalid state, ldap sync repl did not provide a valid state cookie in response.");
 
@@ -985,6 +983,9 @@ fn ipa_to_scim_entry(
             .set_password_import(password_import)
             .set_unix_password_import(unix_password_import)
             .set_totp_import(totp_import)
+            .map_err(|totp_err| {
+                error!(?totp_err, "Invalid totp for scim_sync_person");
+            })?
             .set_login_shell(login_shell)
             .set_mail(mail)
             .set_ssh_publickey(ssh_publickey)
@@ -1080,7 +1081,8 @@ fn ipa_to_totp(sync_entry: &LdapSyncReplEntry) -> Option<ScimTotp> {
         .entry
         .attrs
         .get("ipatokenotpalgorithm")
-        .and_then(|v| v.first().cloned())
+        .and_then(|v| v.first())
+        .and_then(|a| TotpAlgorithm::from_str(a).ok())
         .or_else(|| {
             warn!("Invalid ipatokenotpalgorithm");
             None
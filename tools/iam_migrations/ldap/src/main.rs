@@ -343,7 +343,7 @@ async fn run_sync(
 
     let cookie = match &scim_sync_status {
         ScimSyncState::Refresh => None,
-        ScimSyncState::Active { cookie } => Some(cookie.to_vec()),
+        ScimSyncState::Active { cookie, .. } => Some(cookie.to_vec()),
     };
 
     let filter = sync_config.ldap_filter.clone();
@@ -383,7 +383,7 @@ async fn run_sync(
 
             let to_state = if let Some(cookie) = cookie {
                 // Only update the cookie if it's present - openldap omits!
-                ScimSyncState::Active { cookie }
+                ScimSyncState::active(cookie)
             } else {
                 info!("no changes required");
                 return Ok(());
@@ -752,6 +752,9 @@ fn ldap_to_scim_entry(
             .set_password_import(password_import)
             .set_unix_password_import(unix_password_import)
             .set_totp_import(totp_import)
+            .map_err(|totp_err| {
+                error!(?totp_err, "Invalid totp for scim_sync_person");
+            })?
             .set_login_shell(login_shell)
             .set_mail(mail)
             .set_ssh_publickey(ssh_publickey)
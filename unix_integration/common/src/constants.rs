@@ -1,4 +1,4 @@
-use crate::unix_config::{HomeAttr, UidAttr};
+use crate::unix_config::{EnumerationMode, GecosAttr, HomeAttr, UidAttr};
 
 pub const DEFAULT_CONFIG_PATH: &str = env!("KANIDM_RESOLVER_CONFIG_PATH");
 pub const DEFAULT_SOCK_PATH: &str = "/var/run/kanidm-unixd/sock";
@@ -9,7 +9,9 @@ pub const DEFAULT_CONN_TIMEOUT: u64 = 2;
 pub const DEFAULT_CACHE_TIMEOUT_MINIMUM: u64 = 60; // seconds
 pub const DEFAULT_CACHE_TIMEOUT_MAXIMUM: u64 = 24 * 3600; // seconds
 pub const DEFAULT_CACHE_TIMEOUT: u64 = 300; // seconds
+pub const DEFAULT_NEGATIVE_CACHE_TIMEOUT: u64 = 30; // seconds
 pub const DEFAULT_CACHE_TIMEOUT_JITTER_MS: u64 = 10_000; //milliseconds
+pub const DEFAULT_CACHE_WARMUP_TIMEOUT: u64 = 300; // seconds
 pub const DEFAULT_OFFLINE_PROVIDER_CHECK_TIME: u64 = 180; // seconds
 pub const DEFAULT_SHELL: &str = env!("KANIDM_RESOLVER_UNIX_SHELL_PATH");
 pub const DEFAULT_HOME_PREFIX: &str = "/home/";
@@ -18,6 +20,9 @@ pub const DEFAULT_HOME_ALIAS: Option<HomeAttr> = Some(HomeAttr::Spn);
 pub const DEFAULT_USE_ETC_SKEL: bool = false;
 pub const DEFAULT_UID_ATTR_MAP: UidAttr = UidAttr::Spn;
 pub const DEFAULT_GID_ATTR_MAP: UidAttr = UidAttr::Spn;
+pub const DEFAULT_GECOS_ATTR: GecosAttr = GecosAttr::DisplayName;
+pub const DEFAULT_INCLUDE_EXPIRED: bool = false;
+pub const DEFAULT_NSS_ENUMERATION: EnumerationMode = EnumerationMode::Full;
 pub const DEFAULT_SELINUX: bool = true;
 pub const DEFAULT_TPM_TCTI_NAME: &str = "device:/dev/tpmrm0";
 pub const DEFAULT_HSM_PIN_PATH: &str = "/var/lib/kanidm-unixd/hsm-pin";
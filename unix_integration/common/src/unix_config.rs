@@ -11,14 +11,18 @@ use crate::constants::*;
 #[cfg(all(target_family = "unix", feature = "selinux"))]
 use crate::selinux_util;
 use crate::unix_passwd::UnixIntegrationError;
-use serde::Deserialize;
+use crate::unix_proto::HomeTemplateValues;
+use kanidm_proto::internal::TlsPinSha256;
+use serde::{Deserialize, Serialize};
 use std::env;
 use std::fmt::{Display, Formatter};
 use std::fs::{read_to_string, File};
 use std::io::{ErrorKind, Read};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum HomeAttr {
     Uuid,
     Spn,
@@ -39,10 +43,126 @@ impl Display for HomeAttr {
     }
 }
 
+impl HomeAttr {
+    /// The token used for this attribute in the `home_attr` and `home_alias` options.
+    fn as_config_str(self) -> &'static str {
+        match self {
+            HomeAttr::Uuid => "uuid",
+            HomeAttr::Spn => "spn",
+            HomeAttr::Name => "name",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HomeTemplatePart {
+    Literal(String),
+    /// `%u` - the uid attribute, as selected by `uid_attr_map`.
+    Uid,
+    /// `%U` - the uuid.
+    Uuid,
+    /// `%d` - the domain, from the spn.
+    Domain,
+}
+
+/// A template for the home directory path of a user, such as `/home/%d/%u`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HomeTemplate {
+    template: String,
+    parts: Vec<HomeTemplatePart>,
+}
+
+impl HomeTemplate {
+    /// If the template contains `%u`.
+    pub fn uses_uid(&self) -> bool {
+        self.parts.contains(&HomeTemplatePart::Uid)
+    }
+
+    /// Render the home directory of a user. Each token must expand to a single path
+    /// component, so a value that is empty, `.` or `..`, or that contains a `/` or `@`
+    /// is refused. This means that `%u` can't be rendered from an spn.
+    pub fn render(&self, values: &HomeTemplateValues) -> Result<String, String> {
+        let mut path = String::with_capacity(self.template.len());
+        for part in self.parts.iter() {
+            let (token, value) = match part {
+                HomeTemplatePart::Literal(text) => {
+                    path.push_str(text);
+                    continue;
+                }
+                HomeTemplatePart::Uid => ("%u", values.uid.as_str()),
+                HomeTemplatePart::Uuid => ("%U", values.uuid.as_str()),
+                HomeTemplatePart::Domain => ("%d", values.domain.as_str()),
+            };
+
+            if matches!(value, "" | "." | "..") || value.contains(['/', '@', '\0']) {
+                return Err(format!(
+                    "home_template token {token} can not be rendered from {value:?}"
+                ));
+            }
+            path.push_str(value);
+        }
+        Ok(path)
+    }
+}
+
+impl FromStr for HomeTemplate {
+    type Err = String;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        if !template.starts_with('/') {
+            return Err("home_template must be an absolute path".to_string());
+        }
+
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                literal.push(c);
+                continue;
+            }
+
+            let part = match chars.next() {
+                Some('u') => HomeTemplatePart::Uid,
+                Some('U') => HomeTemplatePart::Uuid,
+                Some('d') => HomeTemplatePart::Domain,
+                Some('%') => {
+                    literal.push('%');
+                    continue;
+                }
+                Some(other) => return Err(format!("unknown home_template token %{other}")),
+                None => return Err("home_template ends with an incomplete token".to_string()),
+            };
+
+            if !literal.is_empty() {
+                parts.push(HomeTemplatePart::Literal(std::mem::take(&mut literal)));
+            }
+            parts.push(part);
+        }
+        if !literal.is_empty() {
+            parts.push(HomeTemplatePart::Literal(literal));
+        }
+
+        Ok(HomeTemplate {
+            template: template.to_string(),
+            parts,
+        })
+    }
+}
+
+impl Display for HomeTemplate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.template)
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub enum UidAttr {
     Name,
     Spn,
+    /// Use the name, unless another user or group on the system shares that name. In
+    /// that case the spn is used instead so that the two remain distinct.
+    NamePreferSpnOnCollision,
 }
 
 impl Display for UidAttr {
@@ -53,12 +173,107 @@ impl Display for UidAttr {
             match self {
                 UidAttr::Name => "Name",
                 UidAttr::Spn => "SPN",
+                UidAttr::NamePreferSpnOnCollision => "Name (SPN on collision)",
             }
         )
     }
 }
 
-#[derive(Debug, Clone, Default)]
+impl UidAttr {
+    /// The token used for this attribute in the `uid_attr_map` and `gid_attr_map` options.
+    fn as_config_str(self) -> &'static str {
+        match self {
+            UidAttr::Name => "name",
+            UidAttr::Spn => "spn",
+            UidAttr::NamePreferSpnOnCollision => "name_prefer_spn_on_collision",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GecosAttr {
+    DisplayName,
+    Spn,
+    Name,
+}
+
+impl Display for GecosAttr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                GecosAttr::DisplayName => "Display Name",
+                GecosAttr::Spn => "SPN",
+                GecosAttr::Name => "Name",
+            }
+        )
+    }
+}
+
+impl GecosAttr {
+    /// The token used for this attribute in the `gecos_attr` option.
+    fn as_config_str(self) -> &'static str {
+        match self {
+            GecosAttr::DisplayName => "displayname",
+            GecosAttr::Spn => "spn",
+            GecosAttr::Name => "name",
+        }
+    }
+}
+
+/// Which entries the resolver returns when nss enumerates all users or groups,
+/// such as for `getent passwd`. Lookups of a single user or group are not affected.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EnumerationMode {
+    /// Nothing is returned.
+    Disabled,
+    /// Only system entries and cached entries that have not yet expired are returned.
+    CachedOnly,
+    /// System entries and every cached entry, including expired ones, are returned.
+    Full,
+}
+
+impl EnumerationMode {
+    /// If a cached entry should be returned by an enumeration given whether its
+    /// cache lifetime has passed.
+    pub fn includes_cached(&self, cache_expired: bool) -> bool {
+        match self {
+            EnumerationMode::Disabled => false,
+            EnumerationMode::CachedOnly => !cache_expired,
+            EnumerationMode::Full => true,
+        }
+    }
+}
+
+impl Display for EnumerationMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                EnumerationMode::Disabled => "disabled",
+                EnumerationMode::CachedOnly => "cached_only",
+                EnumerationMode::Full => "full",
+            }
+        )
+    }
+}
+
+impl FromStr for EnumerationMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "disabled" => Ok(EnumerationMode::Disabled),
+            "cached_only" => Ok(EnumerationMode::CachedOnly),
+            "full" => Ok(EnumerationMode::Full),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum HsmType {
     #[cfg_attr(not(feature = "tpm"), default)]
     Soft,
@@ -77,6 +292,31 @@ impl Display for HsmType {
     }
 }
 
+impl HsmType {
+    /// The token used for this hsm type in the configuration file. Unlike [Display], this
+    /// can be parsed back with [FromStr].
+    pub fn as_config_str(&self) -> &'static str {
+        match self {
+            HsmType::Soft => "soft",
+            HsmType::TpmIfPossible => "tpm_if_possible",
+            HsmType::Tpm => "tpm",
+        }
+    }
+}
+
+impl FromStr for HsmType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "soft" => Ok(HsmType::Soft),
+            "tpm_if_possible" => Ok(HsmType::TpmIfPossible),
+            "tpm" => Ok(HsmType::Tpm),
+            _ => Err(()),
+        }
+    }
+}
+
 // Allowed as the large enum is only short lived at startup to the true config
 #[allow(clippy::large_enum_variant)]
 // This bit of magic lets us deserialise the old config and the new versions.
@@ -97,7 +337,44 @@ enum ConfigVersion {
     },
 }
 
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+/// `home_alias` may be a single value, or a list of values in the order the
+/// aliases are created.
+enum HomeAliasConfig {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl HomeAliasConfig {
+    /// Returns `None` if any of the values are invalid.
+    fn parse(self) -> Option<Vec<HomeAttr>> {
+        let values = match self {
+            HomeAliasConfig::Single(value) if value == "none" => return Some(Vec::new()),
+            HomeAliasConfig::Single(value) => vec![value],
+            HomeAliasConfig::Multiple(values) => values,
+        };
+
+        let mut aliases = Vec::with_capacity(values.len());
+        for value in values {
+            let alias = match value.as_str() {
+                "uuid" => HomeAttr::Uuid,
+                "spn" => HomeAttr::Spn,
+                "name" => HomeAttr::Name,
+                _ => {
+                    warn!("Invalid home_alias configured, using default ...");
+                    return None;
+                }
+            };
+            if !aliases.contains(&alias) {
+                aliases.push(alias);
+            }
+        }
+        Some(aliases)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
 #[serde(rename_all = "lowercase")]
 enum HomeStrategyV2 {
     #[default]
@@ -106,49 +383,64 @@ enum HomeStrategyV2 {
     BindMount,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 /// This is the version 2 of the JSON configuration specification for the unixd suite.
 struct ConfigV2 {
     cache_db_path: Option<String>,
+    cache_db_path_migrate_from: Option<String>,
     sock_path: Option<String>,
     task_sock_path: Option<String>,
 
     cache_timeout: Option<u64>,
+    negative_cache_timeout: Option<u64>,
 
     default_shell: Option<String>,
     home_prefix: Option<String>,
     home_mount_prefix: Option<String>,
     home_attr: Option<String>,
-    home_alias: Option<String>,
+    home_alias: Option<HomeAliasConfig>,
+    home_template: Option<String>,
     #[serde(default)]
     home_strategy: HomeStrategyV2,
     use_etc_skel: Option<bool>,
     uid_attr_map: Option<String>,
     gid_attr_map: Option<String>,
+    gid_allow_range_min: Option<u32>,
+    gid_allow_range_max: Option<u32>,
+    gecos_attr: Option<String>,
+    include_expired: Option<bool>,
+    nss_enumeration: Option<String>,
     selinux: Option<bool>,
 
     hsm_pin_path: Option<String>,
     hsm_type: Option<String>,
     tpm_tcti_name: Option<String>,
+    hsm_tpm_pcr_policy: Option<Vec<u8>>,
+    hsm_tpm_pin_nv_index: Option<u32>,
 
     kanidm: Option<KanidmConfigV2>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct GroupMap {
     pub local: String,
     pub with: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 struct KanidmConfigV2 {
     conn_timeout: Option<u64>,
     request_timeout: Option<u64>,
+    idle_timeout: Option<u64>,
     pam_allowed_login_groups: Option<Vec<String>>,
     #[serde(default)]
     map_group: Vec<GroupMap>,
+    #[serde(default)]
+    kanidm_to_local: Vec<GroupMap>,
     service_account_token_path: Option<PathBuf>,
+    tls_pin_sha256: Option<String>,
+    client_user_agent: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -165,7 +457,7 @@ struct ConfigInt {
     home_prefix: Option<String>,
     home_mount_prefix: Option<String>,
     home_attr: Option<String>,
-    home_alias: Option<String>,
+    home_alias: Option<HomeAliasConfig>,
     use_etc_skel: Option<bool>,
     uid_attr_map: Option<String>,
     gid_attr_map: Option<String>,
@@ -187,15 +479,109 @@ struct ConfigInt {
 
 // ========================================================================
 
+/// Count the symlinks directly within `home_prefix` that do not point into
+/// `home_mount_prefix`.
+fn count_misdirected_home_symlinks(home_prefix: &Path, home_mount_prefix: &Path) -> usize {
+    let Ok(entries) = home_prefix.read_dir() else {
+        return 0;
+    };
+
+    // The tasks daemon creates links to the canonical mount path.
+    let home_mount_prefix = home_mount_prefix
+        .canonicalize()
+        .unwrap_or_else(|_| home_mount_prefix.to_path_buf());
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|ft| ft.is_symlink()))
+        .filter_map(|entry| entry.path().read_link().ok())
+        .filter(|target| {
+            // Relative links such as `../u/alice` resolve against the home prefix. The
+            // home itself may not exist yet, so only its parent is canonicalised.
+            let parent = home_prefix.join(target).parent().map(|parent| {
+                parent
+                    .canonicalize()
+                    .unwrap_or_else(|_| parent.to_path_buf())
+            });
+            parent.as_deref() != Some(home_mount_prefix.as_path())
+        })
+        .count()
+}
+
+/// The user agent the resolver sends to Kanidm when none is configured.
+fn default_client_user_agent() -> String {
+    format!("kanidm-unixd/{}", env!("KANIDM_PKG_VERSION"))
+}
+
+/// A user agent is sent as a http header, so it must be printable ASCII.
+fn is_valid_user_agent(user_agent: &str) -> bool {
+    !user_agent.is_empty() && user_agent.bytes().all(|b| (b' '..=b'~').contains(&b))
+}
+
+/// Read an environment variable, unless this is a setuid or setgid process. A privileged
+/// binary run by an unprivileged user must not trust their environment, in the same way
+/// as `secure_getenv(3)`.
+#[cfg(all(target_family = "unix", feature = "user_overrides"))]
+fn secure_var_os(key: &str) -> Option<std::ffi::OsString> {
+    use kanidm_utils_users::{
+        get_current_gid, get_current_uid, get_effective_gid, get_effective_uid,
+    };
+
+    let value = env::var_os(key)?;
+
+    if get_current_uid() != get_effective_uid() || get_current_gid() != get_effective_gid() {
+        warn!("Ignoring {} in a setuid or setgid process.", key);
+        return None;
+    }
+
+    Some(value)
+}
+
+/// The unix socket timeout is double the connection timeout, so that the resolver
+/// has the chance to complete its own request to Kanidm before the pam/nss client
+/// gives up waiting on the socket.
+const fn unix_sock_timeout_from_conn(conn_timeout: u64) -> u64 {
+    conn_timeout.saturating_mul(2)
+}
+
 #[derive(Debug)]
 /// This is the parsed Kanidm provider configuration that the Unixd resolver
 /// will use to connect to Kanidm.
 pub struct KanidmConfig {
     pub conn_timeout: u64,
     pub request_timeout: u64,
+    /// How long a pooled connection to Kanidm may sit unused before it is closed.
+    pub idle_timeout: u64,
     pub pam_allowed_login_groups: Vec<String>,
     pub map_group: Vec<GroupMap>,
+    /// Local groups that the members of a Kanidm group are added to. This is evaluated
+    /// from each user's own group memberships rather than the member list of the
+    /// Kanidm group, and a local group that a user is mapped into is matched again so
+    /// that mappings may be chained.
+    pub kanidm_to_local: Vec<GroupMap>,
     pub service_account_token: Option<String>,
+    /// The file the service account token was read from, if it was configured.
+    pub service_account_token_path: Option<PathBuf>,
+    /// If set, the SHA-256 of the public key the Kanidm server must present. Connections
+    /// to a server with any other key are refused, even if its certificate is trusted.
+    pub tls_pin_sha256: Option<TlsPinSha256>,
+    /// The user agent sent to Kanidm, so that the server can tell resolvers apart.
+    pub client_user_agent: String,
+}
+
+impl KanidmConfig {
+    pub fn conn_timeout_duration(&self) -> Duration {
+        Duration::from_secs(self.conn_timeout)
+    }
+
+    pub fn request_timeout_duration(&self) -> Duration {
+        Duration::from_secs(self.request_timeout)
+    }
+
+    /// The unix socket timeout that corresponds to this connection timeout.
+    pub fn unix_sock_timeout_duration(&self) -> Duration {
+        Duration::from_secs(unix_sock_timeout_from_conn(self.conn_timeout))
+    }
 }
 
 #[derive(Debug, Default)]
@@ -210,23 +596,52 @@ pub enum HomeStrategy {
 /// This is the parsed configuration for the Unixd resolver.
 pub struct UnixdConfig {
     pub cache_db_path: String,
+    /// A previous cache db path. If `cache_db_path` does not exist yet, the cache
+    /// is copied from here on startup rather than starting empty.
+    pub cache_db_path_migrate_from: Option<String>,
     pub sock_path: String,
     pub task_sock_path: String,
     pub cache_timeout: u64,
+    /// How long a "no such user or group" answer is cached for. 0 disables the
+    /// negative cache.
+    pub negative_cache_timeout: u64,
     pub unix_sock_timeout: u64,
     pub default_shell: String,
     pub home_prefix: PathBuf,
     pub home_mount_prefix: Option<PathBuf>,
     pub home_attr: HomeAttr,
-    pub home_alias: Option<HomeAttr>,
+    /// The aliases linked to the home directory, created in order. The first alias
+    /// is the home directory reported to nss.
+    pub home_aliases: Vec<HomeAttr>,
+    /// If set, the home directory reported to nss and created by the tasks daemon, in
+    /// place of `home_prefix` with the first of `home_aliases` or `home_attr`.
+    pub home_template: Option<HomeTemplate>,
     pub home_strategy: HomeStrategy,
     pub use_etc_skel: bool,
     pub uid_attr_map: UidAttr,
     pub gid_attr_map: UidAttr,
+    /// The lowest GID of a Kanidm group that will be resolved by nss.
+    pub gid_allow_range_min: Option<u32>,
+    /// The highest GID of a Kanidm group that will be resolved by nss.
+    pub gid_allow_range_max: Option<u32>,
+    /// The attribute presented as the gecos (full name) field of passwd entries.
+    pub gecos_attr: GecosAttr,
+    /// If true, accounts that have expired are still resolved by nss. Authentication
+    /// of these accounts is still refused.
+    pub include_expired: bool,
+    /// Which entries are returned when nss enumerates all users or groups.
+    pub nss_enumeration: EnumerationMode,
     pub selinux: bool,
     pub hsm_type: HsmType,
     pub hsm_pin_path: String,
     pub tpm_tcti_name: String,
+    /// The PCR selection mask the HSM pin is sealed against. Byte `n` bit `b`
+    /// selects PCR `n * 8 + b` in the sha256 bank.
+    pub hsm_tpm_pcr_policy: Option<Vec<u8>>,
+    /// The TPM NV index the PCR sealed HSM pin is stored in. When set along
+    /// with `hsm_tpm_pcr_policy` the pin is held in the TPM rather than in
+    /// `hsm_pin_path`.
+    pub hsm_tpm_pin_nv_index: Option<u32>,
     pub kanidm_config: Option<KanidmConfig>,
 }
 
@@ -239,10 +654,15 @@ impl Default for UnixdConfig {
 impl Display for UnixdConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "cache_db_path: {}", &self.cache_db_path)?;
+        match self.cache_db_path_migrate_from.as_deref() {
+            Some(val) => writeln!(f, "cache_db_path_migrate_from: {val}")?,
+            None => writeln!(f, "cache_db_path_migrate_from: unset")?,
+        }
         writeln!(f, "sock_path: {}", self.sock_path)?;
         writeln!(f, "task_sock_path: {}", self.task_sock_path)?;
         writeln!(f, "unix_sock_timeout: {}", self.unix_sock_timeout)?;
         writeln!(f, "cache_timeout: {}", self.cache_timeout)?;
+        writeln!(f, "negative_cache_timeout: {}", self.negative_cache_timeout)?;
         writeln!(f, "default_shell: {}", self.default_shell)?;
         writeln!(f, "home_strategy: {:?}", self.home_strategy)?;
         writeln!(f, "home_prefix: {:?}", self.home_prefix)?;
@@ -251,16 +671,41 @@ impl Display for UnixdConfig {
             None => writeln!(f, "home_mount_prefix: unset")?,
         }
         writeln!(f, "home_attr: {}", self.home_attr)?;
-        match self.home_alias {
-            Some(val) => writeln!(f, "home_alias: {val}")?,
-            None => writeln!(f, "home_alias: unset")?,
+        if self.home_aliases.is_empty() {
+            writeln!(f, "home_alias: unset")?;
+        } else {
+            let aliases: Vec<_> = self.home_aliases.iter().map(|a| a.to_string()).collect();
+            writeln!(f, "home_alias: {}", aliases.join(", "))?;
+        }
+        match &self.home_template {
+            Some(val) => writeln!(f, "home_template: {val}")?,
+            None => writeln!(f, "home_template: unset")?,
         }
 
         writeln!(f, "uid_attr_map: {}", self.uid_attr_map)?;
         writeln!(f, "gid_attr_map: {}", self.gid_attr_map)?;
+        match self.gid_allow_range_min {
+            Some(val) => writeln!(f, "gid_allow_range_min: {val}")?,
+            None => writeln!(f, "gid_allow_range_min: unset")?,
+        }
+        match self.gid_allow_range_max {
+            Some(val) => writeln!(f, "gid_allow_range_max: {val}")?,
+            None => writeln!(f, "gid_allow_range_max: unset")?,
+        }
+        writeln!(f, "gecos_attr: {}", self.gecos_attr)?;
+        writeln!(f, "include_expired: {}", self.include_expired)?;
+        writeln!(f, "nss_enumeration: {}", self.nss_enumeration)?;
 
         writeln!(f, "hsm_type: {}", self.hsm_type)?;
         writeln!(f, "tpm_tcti_name: {}", self.tpm_tcti_name)?;
+        match self.hsm_tpm_pcr_policy.as_deref() {
+            Some(val) => writeln!(f, "hsm_tpm_pcr_policy: {val:02x?}")?,
+            None => writeln!(f, "hsm_tpm_pcr_policy: unset")?,
+        }
+        match self.hsm_tpm_pin_nv_index {
+            Some(val) => writeln!(f, "hsm_tpm_pin_nv_index: {val:#010x}")?,
+            None => writeln!(f, "hsm_tpm_pin_nv_index: unset")?,
+        }
 
         writeln!(f, "selinux: {}", self.selinux)?;
 
@@ -273,6 +718,11 @@ impl Display for UnixdConfig {
             )?;
             writeln!(f, "kanidm conn_timeout: {}", kconfig.conn_timeout)?;
             writeln!(f, "kanidm request_timeout: {}", kconfig.request_timeout)?;
+            writeln!(f, "kanidm idle_timeout: {}", kconfig.idle_timeout)?;
+            if let Some(tls_pin_sha256) = &kconfig.tls_pin_sha256 {
+                writeln!(f, "kanidm tls_pin_sha256: {tls_pin_sha256}")?;
+            }
+            writeln!(f, "kanidm client_user_agent: {}", kconfig.client_user_agent)?;
         } else {
             writeln!(f, "kanidm: disabled")?;
         };
@@ -294,23 +744,33 @@ impl UnixdConfig {
 
         UnixdConfig {
             cache_db_path,
+            cache_db_path_migrate_from: None,
             sock_path: DEFAULT_SOCK_PATH.to_string(),
             task_sock_path: DEFAULT_TASK_SOCK_PATH.to_string(),
-            unix_sock_timeout: DEFAULT_CONN_TIMEOUT * 2,
+            unix_sock_timeout: unix_sock_timeout_from_conn(DEFAULT_CONN_TIMEOUT),
             cache_timeout: DEFAULT_CACHE_TIMEOUT,
+            negative_cache_timeout: DEFAULT_NEGATIVE_CACHE_TIMEOUT,
             default_shell: DEFAULT_SHELL.to_string(),
             home_prefix: DEFAULT_HOME_PREFIX.into(),
             home_mount_prefix: None,
             home_attr: DEFAULT_HOME_ATTR,
-            home_alias: DEFAULT_HOME_ALIAS,
+            home_aliases: DEFAULT_HOME_ALIAS.into_iter().collect(),
+            home_template: None,
             home_strategy: HomeStrategy::default(),
             use_etc_skel: DEFAULT_USE_ETC_SKEL,
             uid_attr_map: DEFAULT_UID_ATTR_MAP,
             gid_attr_map: DEFAULT_GID_ATTR_MAP,
+            gid_allow_range_min: None,
+            gid_allow_range_max: None,
+            gecos_attr: DEFAULT_GECOS_ATTR,
+            include_expired: DEFAULT_INCLUDE_EXPIRED,
+            nss_enumeration: DEFAULT_NSS_ENUMERATION,
             selinux: DEFAULT_SELINUX,
             hsm_pin_path,
             hsm_type: HsmType::default(),
             tpm_tcti_name: DEFAULT_TPM_TCTI_NAME.to_string(),
+            hsm_tpm_pcr_policy: None,
+            hsm_tpm_pin_nv_index: None,
 
             kanidm_config: None,
         }
@@ -370,6 +830,129 @@ impl UnixdConfig {
         }
     }
 
+    /// Check that the configured options are consistent with each other and with
+    /// this host. Problems that will not prevent the daemons from working are only
+    /// logged as warnings.
+    pub fn validate(&self) -> Result<(), UnixIntegrationError> {
+        if let Some(home_mount_prefix) = self.home_mount_prefix.as_deref() {
+            if home_mount_prefix == self.home_prefix.as_path() {
+                error!(
+                    ?home_mount_prefix,
+                    "home_mount_prefix is the same as home_prefix and has no effect, remove it from the configuration"
+                );
+                return Err(UnixIntegrationError);
+            }
+
+            if !home_mount_prefix.exists() {
+                warn!(
+                    ?home_mount_prefix,
+                    "home_mount_prefix does not exist, home directories can not be created until it is mounted"
+                );
+            }
+
+            let misdirected = count_misdirected_home_symlinks(&self.home_prefix, home_mount_prefix);
+            if misdirected > 0 {
+                warn!(
+                    count = misdirected,
+                    home_prefix = ?self.home_prefix,
+                    ?home_mount_prefix,
+                    "symlinks in home_prefix point outside of home_mount_prefix, these will be updated as users are resolved"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Render the resolved configuration as a version 2 config document, so that
+    /// the values in effect, including defaults, can be inspected. The service
+    /// account token is never included, only the path it was read from.
+    pub fn to_effective_toml(&self) -> String {
+        let kanidm = self.kanidm_config.as_ref().map(|kconfig| KanidmConfigV2 {
+            conn_timeout: Some(kconfig.conn_timeout),
+            request_timeout: Some(kconfig.request_timeout),
+            idle_timeout: Some(kconfig.idle_timeout),
+            pam_allowed_login_groups: Some(kconfig.pam_allowed_login_groups.clone()),
+            map_group: kconfig.map_group.clone(),
+            kanidm_to_local: kconfig.kanidm_to_local.clone(),
+            service_account_token_path: kconfig.service_account_token_path.clone(),
+            tls_pin_sha256: kconfig.tls_pin_sha256.as_ref().map(|pin| pin.to_string()),
+            client_user_agent: Some(kconfig.client_user_agent.clone()),
+        });
+
+        // home_alias can't be set with a home_template.
+        let home_alias = if self.home_template.is_some() {
+            None
+        } else if self.home_aliases.is_empty() {
+            Some(HomeAliasConfig::Single("none".to_string()))
+        } else {
+            Some(HomeAliasConfig::Multiple(
+                self.home_aliases
+                    .iter()
+                    .map(|alias| alias.as_config_str().to_string())
+                    .collect(),
+            ))
+        };
+
+        let config = ConfigV2 {
+            cache_db_path: Some(self.cache_db_path.clone()),
+            cache_db_path_migrate_from: self.cache_db_path_migrate_from.clone(),
+            sock_path: Some(self.sock_path.clone()),
+            task_sock_path: Some(self.task_sock_path.clone()),
+            cache_timeout: Some(self.cache_timeout),
+            negative_cache_timeout: Some(self.negative_cache_timeout),
+            default_shell: Some(self.default_shell.clone()),
+            home_prefix: Some(self.home_prefix.to_string_lossy().into_owned()),
+            home_mount_prefix: self
+                .home_mount_prefix
+                .as_deref()
+                .map(|p| p.to_string_lossy().into_owned()),
+            home_attr: Some(self.home_attr.as_config_str().to_string()),
+            home_alias,
+            home_template: self.home_template.as_ref().map(|t| t.to_string()),
+            home_strategy: match self.home_strategy {
+                HomeStrategy::Symlink => HomeStrategyV2::Symlink,
+                #[cfg(target_os = "linux")]
+                HomeStrategy::BindMount => HomeStrategyV2::BindMount,
+            },
+            use_etc_skel: Some(self.use_etc_skel),
+            uid_attr_map: Some(self.uid_attr_map.as_config_str().to_string()),
+            gid_attr_map: Some(self.gid_attr_map.as_config_str().to_string()),
+            gid_allow_range_min: self.gid_allow_range_min,
+            gid_allow_range_max: self.gid_allow_range_max,
+            gecos_attr: Some(self.gecos_attr.as_config_str().to_string()),
+            include_expired: Some(self.include_expired),
+            nss_enumeration: Some(self.nss_enumeration.to_string()),
+            selinux: Some(self.selinux),
+            hsm_pin_path: Some(self.hsm_pin_path.clone()),
+            hsm_type: Some(self.hsm_type.as_config_str().to_string()),
+            tpm_tcti_name: Some(self.tpm_tcti_name.clone()),
+            hsm_tpm_pcr_policy: self.hsm_tpm_pcr_policy.clone(),
+            hsm_tpm_pin_nv_index: self.hsm_tpm_pin_nv_index,
+            kanidm,
+        };
+
+        let values = match toml::to_string(&config) {
+            Ok(values) => values,
+            Err(err) => {
+                error!(?err, "Unable to render the effective configuration");
+                return format!("# Unable to render the effective configuration: {err}\n");
+            }
+        };
+
+        let mut effective = String::from("version = '2'\n");
+        if self
+            .kanidm_config
+            .as_ref()
+            .is_some_and(|kconfig| kconfig.service_account_token.is_some())
+        {
+            effective.push_str("# service_account_token: redacted\n");
+        }
+        effective.push('\n');
+        effective.push_str(&values);
+        effective
+    }
+
     fn apply_from_config_legacy(self, config: ConfigInt) -> Result<Self, UnixIntegrationError> {
         if config.kanidm.is_some() || config.cache_db_path.is_some() {
             error!("You are using version=\"2\" options in a legacy config. THESE WILL NOT WORK.");
@@ -385,21 +968,30 @@ impl UnixdConfig {
             })
             .collect();
 
+        let request_timeout = config.request_timeout.unwrap_or(DEFAULT_CONN_TIMEOUT * 2);
+
         let kanidm_config = Some(KanidmConfig {
             conn_timeout: config.conn_timeout.unwrap_or(DEFAULT_CONN_TIMEOUT),
-            request_timeout: config.request_timeout.unwrap_or(DEFAULT_CONN_TIMEOUT * 2),
+            request_timeout,
+            idle_timeout: request_timeout,
             pam_allowed_login_groups: config.pam_allowed_login_groups.unwrap_or_default(),
             map_group,
+            kanidm_to_local: Vec::new(),
             service_account_token: None,
+            service_account_token_path: None,
+            tls_pin_sha256: None,
+            client_user_agent: default_client_user_agent(),
         });
 
         // Now map the values into our config.
         Ok(UnixdConfig {
             cache_db_path: config.db_path.unwrap_or(self.cache_db_path),
+            cache_db_path_migrate_from: self.cache_db_path_migrate_from,
             sock_path: config.sock_path.unwrap_or(self.sock_path),
             task_sock_path: config.task_sock_path.unwrap_or(self.task_sock_path),
-            unix_sock_timeout: DEFAULT_CONN_TIMEOUT * 2,
+            unix_sock_timeout: unix_sock_timeout_from_conn(DEFAULT_CONN_TIMEOUT),
             cache_timeout: config.cache_timeout.unwrap_or(self.cache_timeout),
+            negative_cache_timeout: self.negative_cache_timeout,
             default_shell: config.default_shell.unwrap_or(self.default_shell),
             home_prefix: config
                 .home_prefix
@@ -418,19 +1010,11 @@ impl UnixdConfig {
                     }
                 })
                 .unwrap_or(self.home_attr),
-            home_alias: config
+            home_aliases: config
                 .home_alias
-                .and_then(|v| match v.as_str() {
-                    "none" => Some(None),
-                    "uuid" => Some(Some(HomeAttr::Uuid)),
-                    "spn" => Some(Some(HomeAttr::Spn)),
-                    "name" => Some(Some(HomeAttr::Name)),
-                    _ => {
-                        warn!("Invalid home_alias configured, using default ...");
-                        None
-                    }
-                })
-                .unwrap_or(self.home_alias),
+                .and_then(HomeAliasConfig::parse)
+                .unwrap_or(self.home_aliases),
+            home_template: self.home_template,
             home_strategy: HomeStrategy::default(),
             use_etc_skel: config.use_etc_skel.unwrap_or(self.use_etc_skel),
             uid_attr_map: config
@@ -438,6 +1022,7 @@ impl UnixdConfig {
                 .and_then(|v| match v.as_str() {
                     "spn" => Some(UidAttr::Spn),
                     "name" => Some(UidAttr::Name),
+                    "name_prefer_spn_on_collision" => Some(UidAttr::NamePreferSpnOnCollision),
                     _ => {
                         warn!("Invalid uid_attr_map configured, using default ...");
                         None
@@ -449,12 +1034,18 @@ impl UnixdConfig {
                 .and_then(|v| match v.as_str() {
                     "spn" => Some(UidAttr::Spn),
                     "name" => Some(UidAttr::Name),
+                    "name_prefer_spn_on_collision" => Some(UidAttr::NamePreferSpnOnCollision),
                     _ => {
                         warn!("Invalid gid_attr_map configured, using default ...");
                         None
                     }
                 })
                 .unwrap_or(self.gid_attr_map),
+            gid_allow_range_min: self.gid_allow_range_min,
+            gid_allow_range_max: self.gid_allow_range_max,
+            gecos_attr: self.gecos_attr,
+            include_expired: self.include_expired,
+            nss_enumeration: self.nss_enumeration,
             selinux: match config.selinux.unwrap_or(self.selinux) {
                 #[cfg(all(target_family = "unix", feature = "selinux"))]
                 true => selinux_util::supported(),
@@ -463,24 +1054,119 @@ impl UnixdConfig {
             hsm_pin_path: config.hsm_pin_path.unwrap_or(self.hsm_pin_path),
             hsm_type: config
                 .hsm_type
-                .and_then(|v| match v.as_str() {
-                    "soft" => Some(HsmType::Soft),
-                    "tpm_if_possible" => Some(HsmType::TpmIfPossible),
-                    "tpm" => Some(HsmType::Tpm),
-                    _ => {
-                        warn!("Invalid hsm_type configured, using default ...");
-                        None
-                    }
+                .and_then(|v| {
+                    HsmType::from_str(&v)
+                        .inspect_err(|_| warn!("Invalid hsm_type configured, using default ..."))
+                        .ok()
                 })
                 .unwrap_or(self.hsm_type),
             tpm_tcti_name: config
                 .tpm_tcti_name
                 .unwrap_or(DEFAULT_TPM_TCTI_NAME.to_string()),
+            hsm_tpm_pcr_policy: self.hsm_tpm_pcr_policy,
+            hsm_tpm_pin_nv_index: self.hsm_tpm_pin_nv_index,
             kanidm_config,
         })
     }
 
     fn apply_from_config_v2(self, config: ConfigV2) -> Result<Self, UnixIntegrationError> {
+        if let (Some(gid_min), Some(gid_max)) =
+            (config.gid_allow_range_min, config.gid_allow_range_max)
+        {
+            if gid_min > gid_max {
+                error!(
+                    gid_min,
+                    gid_max, "gid_allow_range_min must not be greater than gid_allow_range_max"
+                );
+                return Err(UnixIntegrationError);
+            }
+        }
+
+        let hsm_type = config
+            .hsm_type
+            .and_then(|v| {
+                HsmType::from_str(&v)
+                    .inspect_err(|_| warn!("Invalid hsm_type configured, using default ..."))
+                    .ok()
+            })
+            .unwrap_or(self.hsm_type);
+
+        // Sealing the pin needs both the policy and somewhere to store it.
+        if config.hsm_tpm_pcr_policy.is_some() != config.hsm_tpm_pin_nv_index.is_some() {
+            error!("hsm_tpm_pcr_policy and hsm_tpm_pin_nv_index must be set together");
+            return Err(UnixIntegrationError);
+        }
+
+        // The pin can only be sealed when a hardware tpm is always used, as the soft
+        // hsm fallback of tpm_if_possible would have no tpm to unseal it from.
+        if config.hsm_tpm_pin_nv_index.is_some() && hsm_type != HsmType::Tpm {
+            error!(
+                %hsm_type,
+                "hsm_tpm_pcr_policy and hsm_tpm_pin_nv_index require hsm_type = \"tpm\""
+            );
+            return Err(UnixIntegrationError);
+        }
+
+        let home_template = config
+            .home_template
+            .as_deref()
+            .map(HomeTemplate::from_str)
+            .transpose()
+            .map_err(|err| {
+                error!(?err, "Invalid home_template");
+                UnixIntegrationError
+            })?;
+
+        let uid_attr_map = config
+            .uid_attr_map
+            .and_then(|v| match v.as_str() {
+                "spn" => Some(UidAttr::Spn),
+                "name" => Some(UidAttr::Name),
+                "name_prefer_spn_on_collision" => Some(UidAttr::NamePreferSpnOnCollision),
+                _ => {
+                    warn!("Invalid uid_attr_map configured, using default ...");
+                    None
+                }
+            })
+            .unwrap_or(self.uid_attr_map);
+
+        let home_aliases = if let Some(home_template) = &home_template {
+            // The spn contains an '@', so it can never be a single path component.
+            if home_template.uses_uid() && matches!(uid_attr_map, UidAttr::Spn) {
+                error!(
+                    %home_template,
+                    "home_template uses %u, which can not be rendered when uid_attr_map is spn"
+                );
+                return Err(UnixIntegrationError);
+            }
+            // Aliases aren't created for a templated home directory.
+            if config.home_alias.is_some() {
+                error!("home_alias can not be set together with home_template");
+                return Err(UnixIntegrationError);
+            }
+            Vec::new()
+        } else {
+            config
+                .home_alias
+                .and_then(HomeAliasConfig::parse)
+                .unwrap_or(self.home_aliases)
+        };
+
+        let cache_timeout = config.cache_timeout.unwrap_or(self.cache_timeout);
+        let negative_cache_timeout = config
+            .negative_cache_timeout
+            .unwrap_or(self.negative_cache_timeout);
+
+        // A negative entry that outlives a positive one would hide newly created
+        // accounts for longer than the cache itself would.
+        if negative_cache_timeout != 0 && negative_cache_timeout >= cache_timeout {
+            warn!(
+                negative_cache_timeout,
+                cache_timeout,
+                "negative_cache_timeout should be shorter than cache_timeout, otherwise new accounts may be hidden"
+            );
+        }
+
         let kanidm_config = if let Some(kconfig) = config.kanidm {
             let service_account_token_path_env = match env::var("KANIDM_SERVICE_ACCOUNT_TOKEN_PATH")
             {
@@ -521,12 +1207,46 @@ impl UnixdConfig {
                 None
             };
 
+            let tls_pin_sha256 = kconfig
+                .tls_pin_sha256
+                .as_deref()
+                .map(TlsPinSha256::from_str)
+                .transpose()
+                .map_err(|err| {
+                    error!(
+                        ?err,
+                        "Invalid kanidm tls_pin_sha256, expected hex or base64"
+                    );
+                    UnixIntegrationError
+                })?;
+
+            let client_user_agent = match kconfig.client_user_agent {
+                Some(client_user_agent) if is_valid_user_agent(&client_user_agent) => {
+                    client_user_agent
+                }
+                Some(client_user_agent) => {
+                    error!(
+                        ?client_user_agent,
+                        "Invalid kanidm client_user_agent, must be non-empty printable ASCII"
+                    );
+                    return Err(UnixIntegrationError);
+                }
+                None => default_client_user_agent(),
+            };
+
+            let request_timeout = kconfig.request_timeout.unwrap_or(DEFAULT_CONN_TIMEOUT * 2);
+
             Some(KanidmConfig {
                 conn_timeout: kconfig.conn_timeout.unwrap_or(DEFAULT_CONN_TIMEOUT),
-                request_timeout: kconfig.request_timeout.unwrap_or(DEFAULT_CONN_TIMEOUT * 2),
+                request_timeout,
+                idle_timeout: kconfig.idle_timeout.unwrap_or(request_timeout),
                 pam_allowed_login_groups: kconfig.pam_allowed_login_groups.unwrap_or_default(),
                 map_group: kconfig.map_group,
+                kanidm_to_local: kconfig.kanidm_to_local,
                 service_account_token,
+                service_account_token_path: Some(service_account_token_path),
+                tls_pin_sha256,
+                client_user_agent,
             })
         } else {
             error!(
@@ -538,10 +1258,12 @@ impl UnixdConfig {
         // Now map the values into our config.
         Ok(UnixdConfig {
             cache_db_path: config.cache_db_path.unwrap_or(self.cache_db_path),
+            cache_db_path_migrate_from: config.cache_db_path_migrate_from,
             sock_path: config.sock_path.unwrap_or(self.sock_path),
             task_sock_path: config.task_sock_path.unwrap_or(self.task_sock_path),
-            unix_sock_timeout: DEFAULT_CONN_TIMEOUT * 2,
-            cache_timeout: config.cache_timeout.unwrap_or(self.cache_timeout),
+            unix_sock_timeout: unix_sock_timeout_from_conn(DEFAULT_CONN_TIMEOUT),
+            cache_timeout,
+            negative_cache_timeout,
             default_shell: config.default_shell.unwrap_or(self.default_shell),
             home_prefix: config
                 .home_prefix
@@ -560,19 +1282,8 @@ impl UnixdConfig {
                     }
                 })
                 .unwrap_or(self.home_attr),
-            home_alias: config
-                .home_alias
-                .and_then(|v| match v.as_str() {
-                    "none" => Some(None),
-                    "uuid" => Some(Some(HomeAttr::Uuid)),
-                    "spn" => Some(Some(HomeAttr::Spn)),
-                    "name" => Some(Some(HomeAttr::Name)),
-                    _ => {
-                        warn!("Invalid home_alias configured, using default ...");
-                        None
-                    }
-                })
-                .unwrap_or(self.home_alias),
+            home_aliases,
+            home_template,
             home_strategy: match config.home_strategy {
                 HomeStrategyV2::Symlink => HomeStrategy::Symlink,
                 #[cfg(target_os = "linux")]
@@ -584,55 +1295,62 @@ impl UnixdConfig {
                 }
             },
             use_etc_skel: config.use_etc_skel.unwrap_or(self.use_etc_skel),
-            uid_attr_map: config
-                .uid_attr_map
+            uid_attr_map,
+            gid_attr_map: config
+                .gid_attr_map
                 .and_then(|v| match v.as_str() {
                     "spn" => Some(UidAttr::Spn),
                     "name" => Some(UidAttr::Name),
+                    "name_prefer_spn_on_collision" => Some(UidAttr::NamePreferSpnOnCollision),
                     _ => {
-                        warn!("Invalid uid_attr_map configured, using default ...");
+                        warn!("Invalid gid_attr_map configured, using default ...");
                         None
                     }
                 })
-                .unwrap_or(self.uid_attr_map),
-            gid_attr_map: config
-                .gid_attr_map
+                .unwrap_or(self.gid_attr_map),
+            gid_allow_range_min: config.gid_allow_range_min,
+            gid_allow_range_max: config.gid_allow_range_max,
+            gecos_attr: config
+                .gecos_attr
                 .and_then(|v| match v.as_str() {
-                    "spn" => Some(UidAttr::Spn),
-                    "name" => Some(UidAttr::Name),
+                    "displayname" => Some(GecosAttr::DisplayName),
+                    "spn" => Some(GecosAttr::Spn),
+                    "name" => Some(GecosAttr::Name),
                     _ => {
-                        warn!("Invalid gid_attr_map configured, using default ...");
+                        warn!("Invalid gecos_attr configured, using default ...");
                         None
                     }
                 })
-                .unwrap_or(self.gid_attr_map),
+                .unwrap_or(self.gecos_attr),
+            include_expired: config.include_expired.unwrap_or(self.include_expired),
+            nss_enumeration: config
+                .nss_enumeration
+                .and_then(|v| {
+                    EnumerationMode::from_str(&v)
+                        .inspect_err(|_| {
+                            warn!("Invalid nss_enumeration configured, using default ...")
+                        })
+                        .ok()
+                })
+                .unwrap_or(self.nss_enumeration),
             selinux: match config.selinux.unwrap_or(self.selinux) {
                 #[cfg(all(target_family = "unix", feature = "selinux"))]
                 true => selinux_util::supported(),
                 _ => false,
             },
             hsm_pin_path: config.hsm_pin_path.unwrap_or(self.hsm_pin_path),
-            hsm_type: config
-                .hsm_type
-                .and_then(|v| match v.as_str() {
-                    "soft" => Some(HsmType::Soft),
-                    "tpm_if_possible" => Some(HsmType::TpmIfPossible),
-                    "tpm" => Some(HsmType::Tpm),
-                    _ => {
-                        warn!("Invalid hsm_type configured, using default ...");
-                        None
-                    }
-                })
-                .unwrap_or(self.hsm_type),
+            hsm_type,
             tpm_tcti_name: config
                 .tpm_tcti_name
                 .unwrap_or(DEFAULT_TPM_TCTI_NAME.to_string()),
+            hsm_tpm_pcr_policy: config.hsm_tpm_pcr_policy,
+            hsm_tpm_pin_nv_index: config.hsm_tpm_pin_nv_index,
             kanidm_config,
         })
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 /// This is the parsed configuration that will be used by pam/nss tools that need fast access to
 /// only the socket and timeout information related to the resolver.
 pub struct PamNssConfig {
@@ -658,10 +1376,14 @@ impl PamNssConfig {
     pub fn new() -> Self {
         PamNssConfig {
             sock_path: DEFAULT_SOCK_PATH.to_string(),
-            unix_sock_timeout: DEFAULT_CONN_TIMEOUT * 2,
+            unix_sock_timeout: unix_sock_timeout_from_conn(DEFAULT_CONN_TIMEOUT),
         }
     }
 
+    pub fn unix_sock_timeout_duration(&self) -> Duration {
+        Duration::from_secs(self.unix_sock_timeout)
+    }
+
     pub fn read_options_from_optional_config<P: AsRef<Path> + std::fmt::Debug>(
         self,
         config_path: P,
@@ -716,10 +1438,134 @@ impl PamNssConfig {
         }
     }
 
+    /// Read options from the calling user's configuration at `$XDG_CONFIG_HOME/kanidm/unixd`
+    /// over the top of the current (system) configuration. The file is only read if it is
+    /// owned by the calling user, and never in a setuid or setgid process. Only the timeout
+    /// may be changed - the socket path is security sensitive, so a user supplied socket path
+    /// that does not match the system is ignored. A user configuration that can't be read is
+    /// an error, and callers should continue with the system configuration so that a user
+    /// can't disable it.
+    #[cfg(all(target_family = "unix", feature = "user_overrides"))]
+    pub fn read_options_from_user_config(self) -> Result<Self, UnixIntegrationError> {
+        let Some(config_path) = secure_var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            // Relative paths are invalid according to the XDG base directory spec.
+            .filter(|xdg_config_home| xdg_config_home.is_absolute())
+            .map(|xdg_config_home| xdg_config_home.join("kanidm").join("unixd"))
+        else {
+            return Ok(self);
+        };
+
+        self.read_options_from_user_config_path(&config_path, kanidm_utils_users::get_current_uid())
+    }
+
+    #[cfg(all(target_family = "unix", feature = "user_overrides"))]
+    fn read_options_from_user_config_path(
+        self,
+        config_path: &Path,
+        uid: u32,
+    ) -> Result<Self, UnixIntegrationError> {
+        use std::os::unix::fs::MetadataExt;
+
+        match std::fs::metadata(config_path) {
+            Ok(meta) if meta.uid() == uid => {}
+            Ok(_) => {
+                warn!(
+                    "User configuration file {:#?} is not owned by the calling user, skipping.",
+                    &config_path
+                );
+                return Ok(self);
+            }
+            Err(e) => {
+                debug!(
+                    "Unable to access user configuration file {:#?} [{:?}], skipping ...",
+                    &config_path, e
+                );
+                return Ok(self);
+            }
+        }
+
+        let user_config = PamNssConfig {
+            sock_path: self.sock_path.clone(),
+            unix_sock_timeout: self.unix_sock_timeout,
+        }
+        .read_options_from_optional_config(config_path)
+        .inspect_err(|_| {
+            error!(
+                "Unable to parse user configuration file {:#?}",
+                &config_path
+            );
+        })?;
+
+        if user_config.sock_path != self.sock_path {
+            warn!(
+                "Ignoring sock_path from user configuration file {:#?} as it does not match the system configuration.",
+                &config_path
+            );
+        }
+
+        Ok(PamNssConfig {
+            sock_path: self.sock_path,
+            unix_sock_timeout: user_config.unix_sock_timeout,
+        })
+    }
+
+    /// Override the socket path from `KANIDM_SOCKET_PATH`, for environments such as containers
+    /// where the system configuration can not be changed. As this is security sensitive, the
+    /// override is only accepted when running as root, or when the socket is owned by the
+    /// calling user. It is always ignored in setuid or setgid processes.
+    #[cfg(all(target_family = "unix", feature = "user_overrides"))]
+    pub fn read_options_from_env(self) -> Self {
+        match secure_var_os("KANIDM_SOCKET_PATH") {
+            Some(sock_path) => self.apply_sock_path_override(Path::new(&sock_path)),
+            None => self,
+        }
+    }
+
+    #[cfg(all(target_family = "unix", feature = "user_overrides"))]
+    fn apply_sock_path_override(self, sock_path: &Path) -> Self {
+        use kanidm_utils_users::{get_current_uid, get_effective_uid};
+        use std::os::unix::fs::MetadataExt;
+
+        let uid = get_current_uid();
+        let euid = get_effective_uid();
+
+        let Some(sock_path_str) = sock_path.to_str() else {
+            warn!("Ignoring KANIDM_SOCKET_PATH as it is not valid utf-8.");
+            return self;
+        };
+
+        if euid != 0 {
+            match std::fs::metadata(sock_path) {
+                Ok(meta) if meta.uid() == uid => {}
+                Ok(_) => {
+                    warn!(
+                        "Ignoring KANIDM_SOCKET_PATH {:#?} as it is not owned by the calling user.",
+                        sock_path
+                    );
+                    return self;
+                }
+                Err(e) => {
+                    debug!(
+                        "Unable to access KANIDM_SOCKET_PATH {:#?} [{:?}], ignoring ...",
+                        sock_path, e
+                    );
+                    return self;
+                }
+            }
+        }
+
+        debug!("Using socket path {:#?} from KANIDM_SOCKET_PATH", sock_path);
+        PamNssConfig {
+            sock_path: sock_path_str.to_string(),
+            ..self
+        }
+    }
+
     fn apply_from_config_legacy(self, config: ConfigInt) -> Result<Self, UnixIntegrationError> {
         let unix_sock_timeout = config
             .conn_timeout
-            .map(|v| v * 2)
+            .map(unix_sock_timeout_from_conn)
             .unwrap_or(self.unix_sock_timeout);
 
         // Now map the values into our config.
@@ -734,7 +1580,7 @@ impl PamNssConfig {
             .kanidm
             .as_ref()
             .and_then(|k_config| k_config.conn_timeout)
-            .map(|timeout| timeout * 2);
+            .map(unix_sock_timeout_from_conn);
 
         // Now map the values into our config.
         Ok(PamNssConfig {
@@ -750,6 +1596,15 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_hsm_type_config_str_round_trip() {
+        for hsm_type in [HsmType::Soft, HsmType::TpmIfPossible, HsmType::Tpm] {
+            assert_eq!(HsmType::from_str(hsm_type.as_config_str()), Ok(hsm_type));
+        }
+        // The human readable form is not a config token.
+        assert!(HsmType::from_str(&HsmType::TpmIfPossible.to_string()).is_err());
+    }
+
     #[test]
     fn test_load_example_configs() {
         // Test the various included configs
@@ -777,4 +1632,763 @@ mod tests {
             }
         }
     }
+
+    fn parse_config(name: &str, contents: &str) -> UnixdConfig {
+        let path =
+            std::env::temp_dir().join(format!("kanidm-unixd-{name}-{}.toml", std::process::id()));
+        std::fs::write(&path, contents).expect("Failed to write config");
+        let config = UnixdConfig::new().read_options_from_optional_config(&path);
+        let _ = std::fs::remove_file(&path);
+        config.expect("Failed to parse config")
+    }
+
+    #[test]
+    fn test_timeout_durations() {
+        let config = parse_config(
+            "timeout-durations",
+            r#"
+version = '2'
+
+[kanidm]
+conn_timeout = 7
+request_timeout = 20
+"#,
+        );
+        let kconfig = config.kanidm_config.expect("No kanidm config");
+        assert_eq!(kconfig.conn_timeout_duration(), Duration::from_secs(7));
+        assert_eq!(kconfig.request_timeout_duration(), Duration::from_secs(20));
+        assert_eq!(
+            kconfig.unix_sock_timeout_duration(),
+            kconfig.conn_timeout_duration() * 2
+        );
+
+        // pam/nss derive the same socket timeout from the same config.
+        let path = std::env::temp_dir().join(format!(
+            "kanidm-pamnss-timeout-durations-{}.toml",
+            std::process::id()
+        ));
+        std::fs::write(
+            &path,
+            r#"
+version = '2'
+
+[kanidm]
+conn_timeout = 7
+"#,
+        )
+        .expect("Failed to write config");
+        let pam_config = PamNssConfig::new().read_options_from_optional_config(&path);
+        let _ = std::fs::remove_file(&path);
+        let pam_config = pam_config.expect("Failed to parse config");
+        assert_eq!(pam_config.unix_sock_timeout, 14);
+        assert_eq!(
+            pam_config.unix_sock_timeout_duration(),
+            kconfig.unix_sock_timeout_duration()
+        );
+
+        // As do the defaults.
+        assert_eq!(
+            PamNssConfig::new().unix_sock_timeout_duration(),
+            Duration::from_secs(DEFAULT_CONN_TIMEOUT) * 2
+        );
+    }
+
+    #[test]
+    fn test_kanidm_to_local_group_map() {
+        let config = parse_config(
+            "kanidm-to-local",
+            r#"
+version = '2'
+
+[kanidm]
+
+[[kanidm.map_group]]
+local = "wheel"
+with = "admins"
+
+[[kanidm.kanidm_to_local]]
+with = "admins"
+local = "wheel"
+
+[[kanidm.kanidm_to_local]]
+with = "admins"
+local = "libvirt"
+"#,
+        );
+        let kconfig = config.kanidm_config.as_ref().expect("No kanidm config");
+        assert_eq!(kconfig.map_group.len(), 1);
+        assert_eq!(
+            kconfig
+                .kanidm_to_local
+                .iter()
+                .map(|GroupMap { local, with }| (local.as_str(), with.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("wheel", "admins"), ("libvirt", "admins")]
+        );
+
+        let config = parse_config("kanidm-to-local-unset", "version = '2'\n\n[kanidm]\n");
+        let kconfig = config.kanidm_config.as_ref().expect("No kanidm config");
+        assert!(kconfig.kanidm_to_local.is_empty());
+    }
+
+    #[test]
+    fn test_kanidm_tls_pin_sha256() {
+        let config = parse_config(
+            "tls-pin-hex",
+            r#"
+version = '2'
+
+[kanidm]
+tls_pin_sha256 = "5c204469c555c9d7815e86f23bd60e6776a7728c979f0b6b4f50c4979c5bb4eb"
+"#,
+        );
+        let hex_pin = config
+            .kanidm_config
+            .and_then(|kconfig| kconfig.tls_pin_sha256)
+            .expect("No tls pin");
+
+        let config = parse_config(
+            "tls-pin-base64",
+            r#"
+version = '2'
+
+[kanidm]
+tls_pin_sha256 = "XCBEacVVydeBXobyO9YOZ3ancoyXnwtrT1DEl5xbtOs="
+"#,
+        );
+        let base64_pin = config
+            .kanidm_config
+            .and_then(|kconfig| kconfig.tls_pin_sha256)
+            .expect("No tls pin");
+        assert_eq!(hex_pin, base64_pin);
+
+        let config = parse_config("tls-pin-unset", "version = '2'\n\n[kanidm]\n");
+        let kconfig = config.kanidm_config.expect("No kanidm config");
+        assert!(kconfig.tls_pin_sha256.is_none());
+
+        // A pin that isn't a sha256 digest is a config error, not silently ignored.
+        for (name, pin) in [
+            ("tls-pin-garbage", "not a pin"),
+            ("tls-pin-short", "5c204469c555c9d7815e86f2"),
+        ] {
+            let path = std::env::temp_dir()
+                .join(format!("kanidm-unixd-{name}-{}.toml", std::process::id()));
+            std::fs::write(
+                &path,
+                format!("version = '2'\n\n[kanidm]\ntls_pin_sha256 = \"{pin}\"\n"),
+            )
+            .expect("Failed to write config");
+            let config = UnixdConfig::new().read_options_from_optional_config(&path);
+            let _ = std::fs::remove_file(&path);
+            assert!(config.is_err());
+        }
+    }
+
+    #[test]
+    fn test_kanidm_client_user_agent() {
+        let config = parse_config("user-agent-unset", "version = '2'\n\n[kanidm]\n");
+        let kconfig = config.kanidm_config.expect("No kanidm config");
+        assert_eq!(
+            kconfig.client_user_agent,
+            format!("kanidm-unixd/{}", env!("KANIDM_PKG_VERSION"))
+        );
+
+        let config = parse_config(
+            "user-agent-set",
+            r#"
+version = '2'
+
+[kanidm]
+client_user_agent = "kanidm-unixd/web-01 (rack 4)"
+"#,
+        );
+        let kconfig = config.kanidm_config.expect("No kanidm config");
+        assert_eq!(kconfig.client_user_agent, "kanidm-unixd/web-01 (rack 4)");
+
+        // Header values can't carry control characters or non-ASCII text.
+        for (name, user_agent) in [
+            ("user-agent-empty", ""),
+            ("user-agent-newline", "kanidm-unixd\\nX-Injected: 1"),
+            ("user-agent-unicode", "kanidm-unixd/🦀"),
+        ] {
+            let path = std::env::temp_dir()
+                .join(format!("kanidm-unixd-{name}-{}.toml", std::process::id()));
+            std::fs::write(
+                &path,
+                format!("version = '2'\n\n[kanidm]\nclient_user_agent = \"{user_agent}\"\n"),
+            )
+            .expect("Failed to write config");
+            let config = UnixdConfig::new().read_options_from_optional_config(&path);
+            let _ = std::fs::remove_file(&path);
+            assert!(config.is_err());
+        }
+    }
+
+    #[test]
+    fn test_kanidm_idle_timeout() {
+        let config = parse_config(
+            "idle-explicit",
+            r#"
+version = '2'
+
+[kanidm]
+request_timeout = 20
+idle_timeout = 90
+"#,
+        );
+        let kconfig = config.kanidm_config.as_ref().expect("No kanidm config");
+        assert_eq!(kconfig.request_timeout, 20);
+        assert_eq!(kconfig.idle_timeout, 90);
+        assert!(config.to_string().contains("kanidm idle_timeout: 90"));
+
+        // Defaults to the request timeout
+        let config = parse_config(
+            "idle-default",
+            r#"
+version = '2'
+
+[kanidm]
+request_timeout = 20
+"#,
+        );
+        let kconfig = config.kanidm_config.expect("No kanidm config");
+        assert_eq!(kconfig.idle_timeout, 20);
+
+        let config = parse_config(
+            "idle-unset",
+            r#"
+version = '2'
+
+[kanidm]
+"#,
+        );
+        let kconfig = config.kanidm_config.expect("No kanidm config");
+        assert_eq!(kconfig.idle_timeout, DEFAULT_CONN_TIMEOUT * 2);
+    }
+
+    #[test]
+    fn test_negative_cache_timeout() {
+        let config = parse_config(
+            "negative-cache",
+            r#"
+version = '2'
+cache_timeout = 600
+negative_cache_timeout = 15
+"#,
+        );
+        assert_eq!(config.cache_timeout, 600);
+        assert_eq!(config.negative_cache_timeout, 15);
+        assert!(config.to_string().contains("negative_cache_timeout: 15"));
+
+        // 0 disables the negative cache.
+        let config = parse_config(
+            "negative-cache-disabled",
+            r#"
+version = '2'
+negative_cache_timeout = 0
+"#,
+        );
+        assert_eq!(config.negative_cache_timeout, 0);
+
+        let config = parse_config("negative-cache-unset", "version = '2'\n");
+        assert_eq!(
+            config.negative_cache_timeout,
+            DEFAULT_NEGATIVE_CACHE_TIMEOUT
+        );
+        assert!(config.negative_cache_timeout < config.cache_timeout);
+
+        // Longer than the cache timeout is accepted, but warned about.
+        let config = parse_config(
+            "negative-cache-long",
+            r#"
+version = '2'
+cache_timeout = 120
+negative_cache_timeout = 600
+"#,
+        );
+        assert_eq!(config.negative_cache_timeout, 600);
+    }
+
+    #[test]
+    fn test_cache_db_path_migrate_from() {
+        let config = parse_config(
+            "cache-db-migrate",
+            r#"
+version = '2'
+cache_db_path = "/var/cache/kanidm-unixd/new.cache.db"
+cache_db_path_migrate_from = "/var/cache/kanidm-unixd/kanidm.cache.db"
+"#,
+        );
+        assert_eq!(config.cache_db_path, "/var/cache/kanidm-unixd/new.cache.db");
+        assert_eq!(
+            config.cache_db_path_migrate_from.as_deref(),
+            Some("/var/cache/kanidm-unixd/kanidm.cache.db")
+        );
+
+        let config = parse_config("cache-db-migrate-unset", "version = '2'\n");
+        assert_eq!(config.cache_db_path_migrate_from, None);
+    }
+
+    #[test]
+    fn test_gid_allow_range() {
+        let config = parse_config(
+            "gid-range",
+            r#"
+version = '2'
+gid_allow_range_min = 60000
+gid_allow_range_max = 65000
+"#,
+        );
+        assert_eq!(config.gid_allow_range_min, Some(60000));
+        assert_eq!(config.gid_allow_range_max, Some(65000));
+        assert!(config.to_string().contains("gid_allow_range_min: 60000"));
+
+        let config = parse_config("gid-range-unset", "version = '2'\n");
+        assert_eq!(config.gid_allow_range_min, None);
+        assert_eq!(config.gid_allow_range_max, None);
+
+        let path = std::env::temp_dir().join(format!(
+            "kanidm-unixd-gid-range-invalid-{}.toml",
+            std::process::id()
+        ));
+        std::fs::write(
+            &path,
+            "version = '2'\ngid_allow_range_min = 65000\ngid_allow_range_max = 60000\n",
+        )
+        .expect("Failed to write config");
+        let config = UnixdConfig::new().read_options_from_optional_config(&path);
+        let _ = std::fs::remove_file(&path);
+        assert!(config.is_err());
+    }
+
+    #[test]
+    fn test_home_template() {
+        let config = parse_config(
+            "home-template",
+            r#"
+version = '2'
+home_template = "/home/%d/%u"
+uid_attr_map = "name"
+"#,
+        );
+        let values = |uid: &str| HomeTemplateValues {
+            uid: uid.to_string(),
+            uuid: "cc8e95b4-c24f-4d68-ba54-8bed76f63930".to_string(),
+            domain: "idm.example.com".to_string(),
+        };
+
+        let template = config.home_template.clone().expect("home_template not set");
+        assert_eq!(
+            template.render(&values("testuser")),
+            Ok("/home/idm.example.com/testuser".to_string())
+        );
+        assert_eq!(template.to_string(), "/home/%d/%u");
+
+        let template = HomeTemplate::from_str("/srv/%%/%U-%u").expect("valid template");
+        assert_eq!(
+            template.render(&values("testuser")),
+            Ok("/srv/%/cc8e95b4-c24f-4d68-ba54-8bed76f63930-testuser".to_string())
+        );
+
+        // Values that aren't a single path component are refused.
+        for uid in ["testuser@idm.example.com", "../root", "a/b", "..", ".", ""] {
+            assert!(template.render(&values(uid)).is_err());
+        }
+
+        // Aliases aren't used with a template, and it survives the round trip.
+        assert!(config.home_aliases.is_empty());
+        let reparsed = parse_config("home-template-reparsed", &config.to_effective_toml());
+        assert_eq!(reparsed.home_template, config.home_template);
+        assert!(reparsed.home_aliases.is_empty());
+
+        // %U can be used with the default uid_attr_map.
+        let config = parse_config(
+            "home-template-uuid",
+            "version = '2'\nhome_template = \"/home/%d/%U\"\n",
+        );
+        assert!(config.home_template.is_some());
+
+        let config = parse_config("home-template-unset", "version = '2'\n");
+        assert_eq!(config.home_template, None);
+
+        assert!(HomeTemplate::from_str("/home/%x").is_err());
+        assert!(HomeTemplate::from_str("/home/%").is_err());
+        assert!(HomeTemplate::from_str("home/%u").is_err());
+
+        let path = std::env::temp_dir().join(format!(
+            "kanidm-unixd-home-template-invalid-{}.toml",
+            std::process::id()
+        ));
+        for contents in [
+            "version = '2'\nhome_template = \"/home/%n\"\n",
+            // %u can't be rendered from an spn, which is the default uid_attr_map.
+            "version = '2'\nhome_template = \"/home/%u\"\n",
+            "version = '2'\nhome_template = \"/home/%u\"\nuid_attr_map = \"spn\"\n",
+            // home_alias can't be set with a template.
+            "version = '2'\nhome_template = \"/home/%U\"\nhome_alias = \"name\"\n",
+        ] {
+            std::fs::write(&path, contents).expect("Failed to write config");
+            let config = UnixdConfig::new().read_options_from_optional_config(&path);
+            assert!(config.is_err(), "{contents}");
+        }
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_gecos_attr() {
+        for (value, expect) in [
+            ("displayname", GecosAttr::DisplayName),
+            ("spn", GecosAttr::Spn),
+            ("name", GecosAttr::Name),
+        ] {
+            let config = parse_config(
+                &format!("gecos-attr-{value}"),
+                &format!("version = '2'\ngecos_attr = \"{value}\"\n"),
+            );
+            assert_eq!(config.gecos_attr, expect);
+        }
+
+        let config = parse_config("gecos-attr-unset", "version = '2'\n");
+        assert_eq!(config.gecos_attr, DEFAULT_GECOS_ATTR);
+        assert!(config.to_string().contains("gecos_attr: Display Name"));
+
+        // Unknown values fall back to the default.
+        let config = parse_config(
+            "gecos-attr-invalid",
+            "version = '2'\ngecos_attr = \"mail\"\n",
+        );
+        assert_eq!(config.gecos_attr, DEFAULT_GECOS_ATTR);
+    }
+
+    #[test]
+    fn test_home_alias() {
+        let config = parse_config("home-alias-unset", "version = '2'\n");
+        assert_eq!(config.home_aliases, vec![HomeAttr::Spn]);
+
+        let config = parse_config(
+            "home-alias-single",
+            "version = '2'\nhome_alias = \"name\"\n",
+        );
+        assert_eq!(config.home_aliases, vec![HomeAttr::Name]);
+        assert!(config.to_string().contains("home_alias: Name"));
+
+        let config = parse_config("home-alias-none", "version = '2'\nhome_alias = \"none\"\n");
+        assert!(config.home_aliases.is_empty());
+        assert!(config.to_string().contains("home_alias: unset"));
+
+        // Order is preserved, and repeated values are only created once.
+        let config = parse_config(
+            "home-alias-multiple",
+            "version = '2'\nhome_alias = [\"spn\", \"name\", \"spn\"]\n",
+        );
+        assert_eq!(config.home_aliases, vec![HomeAttr::Spn, HomeAttr::Name]);
+        assert!(config.to_string().contains("home_alias: SPN, Name"));
+
+        // The legacy format accepts the same forms.
+        let config = parse_config("home-alias-legacy", "home_alias = [\"name\", \"uuid\"]\n");
+        assert_eq!(config.home_aliases, vec![HomeAttr::Name, HomeAttr::Uuid]);
+
+        // Any invalid value falls back to the default.
+        let config = parse_config(
+            "home-alias-invalid",
+            "version = '2'\nhome_alias = [\"name\", \"mail\"]\n",
+        );
+        assert_eq!(config.home_aliases, vec![HomeAttr::Spn]);
+    }
+
+    #[test]
+    fn test_include_expired() {
+        let config = parse_config("include-expired-unset", "version = '2'\n");
+        assert!(!config.include_expired);
+        assert!(config.to_string().contains("include_expired: false"));
+
+        let config = parse_config("include-expired", "version = '2'\ninclude_expired = true\n");
+        assert!(config.include_expired);
+        assert!(config.to_string().contains("include_expired: true"));
+    }
+
+    #[test]
+    fn test_nss_enumeration() {
+        let config = parse_config("nss-enumeration-unset", "version = '2'\n");
+        assert_eq!(config.nss_enumeration, EnumerationMode::Full);
+        assert!(config.to_string().contains("nss_enumeration: full"));
+
+        for (value, mode) in [
+            ("disabled", EnumerationMode::Disabled),
+            ("cached_only", EnumerationMode::CachedOnly),
+            ("full", EnumerationMode::Full),
+        ] {
+            let config = parse_config(
+                &format!("nss-enumeration-{value}"),
+                &format!("version = '2'\nnss_enumeration = \"{value}\"\n"),
+            );
+            assert_eq!(config.nss_enumeration, mode);
+            assert!(config
+                .to_string()
+                .contains(&format!("nss_enumeration: {value}")));
+        }
+
+        // An invalid value falls back to the default.
+        let config = parse_config(
+            "nss-enumeration-invalid",
+            "version = '2'\nnss_enumeration = \"all\"\ninclude_expired = true\n",
+        );
+        assert_eq!(config.nss_enumeration, EnumerationMode::Full);
+        assert!(config.include_expired);
+    }
+
+    #[test]
+    fn test_nss_enumeration_includes_cached() {
+        assert!(!EnumerationMode::Disabled.includes_cached(false));
+        assert!(!EnumerationMode::Disabled.includes_cached(true));
+
+        assert!(EnumerationMode::CachedOnly.includes_cached(false));
+        assert!(!EnumerationMode::CachedOnly.includes_cached(true));
+
+        assert!(EnumerationMode::Full.includes_cached(false));
+        assert!(EnumerationMode::Full.includes_cached(true));
+    }
+
+    #[test]
+    fn test_hsm_tpm_pin_sealing() {
+        let config = parse_config(
+            "hsm-tpm-pin",
+            r#"
+version = '2'
+hsm_type = "tpm"
+hsm_tpm_pcr_policy = [0x81, 0x00, 0x00]
+hsm_tpm_pin_nv_index = 0x01800100
+"#,
+        );
+        assert_eq!(config.hsm_tpm_pcr_policy, Some(vec![0x81, 0x00, 0x00]));
+        assert_eq!(config.hsm_tpm_pin_nv_index, Some(0x0180_0100));
+        assert!(config
+            .to_string()
+            .contains("hsm_tpm_pin_nv_index: 0x01800100"));
+
+        let config = parse_config("hsm-tpm-pin-unset", "version = '2'\n");
+        assert_eq!(config.hsm_tpm_pcr_policy, None);
+        assert_eq!(config.hsm_tpm_pin_nv_index, None);
+
+        let path = std::env::temp_dir().join(format!(
+            "kanidm-unixd-hsm-tpm-pin-invalid-{}.toml",
+            std::process::id()
+        ));
+        std::fs::write(&path, "version = '2'\nhsm_tpm_pin_nv_index = 0x01800100\n")
+            .expect("Failed to write config");
+        let config = UnixdConfig::new().read_options_from_optional_config(&path);
+        let _ = std::fs::remove_file(&path);
+        assert!(config.is_err());
+
+        // Sealing is refused unless the tpm is always used.
+        for hsm_type in ["soft", "tpm_if_possible"] {
+            std::fs::write(
+                &path,
+                format!(
+                    "version = '2'\nhsm_type = \"{hsm_type}\"\nhsm_tpm_pcr_policy = [0x81]\nhsm_tpm_pin_nv_index = 0x01800100\n"
+                ),
+            )
+            .expect("Failed to write config");
+            let config = UnixdConfig::new().read_options_from_optional_config(&path);
+            let _ = std::fs::remove_file(&path);
+            assert!(config.is_err());
+        }
+    }
+
+    #[cfg(feature = "user_overrides")]
+    #[test]
+    fn test_pam_nss_user_config() {
+        let path = std::env::temp_dir().join(format!(
+            "kanidm-unixd-user-config-{}.toml",
+            std::process::id()
+        ));
+        std::fs::write(
+            &path,
+            r#"
+version = '2'
+sock_path = '/tmp/not-the-system-socket'
+
+[kanidm]
+conn_timeout = 11
+"#,
+        )
+        .expect("Failed to write config");
+        let uid = kanidm_utils_users::get_current_uid();
+        let config = PamNssConfig::new()
+            .read_options_from_user_config_path(&path, uid)
+            .expect("Failed to read user config");
+
+        // The timeout is taken from the user config, but the socket path is not.
+        assert_eq!(config.sock_path, DEFAULT_SOCK_PATH);
+        assert_eq!(config.unix_sock_timeout, unix_sock_timeout_from_conn(11));
+
+        // A file owned by someone else is never read.
+        let config = PamNssConfig::new()
+            .read_options_from_user_config_path(&path, uid + 1)
+            .expect("Failed to skip user config");
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(config.sock_path, DEFAULT_SOCK_PATH);
+        assert_eq!(
+            config.unix_sock_timeout,
+            PamNssConfig::new().unix_sock_timeout
+        );
+
+        // A malformed file is reported as an error.
+        std::fs::write(&path, "version = '2'\n[kanidm\nconn_timeout = 11\n")
+            .expect("Failed to write config");
+        let result = PamNssConfig::new().read_options_from_user_config_path(&path, uid);
+        let _ = std::fs::remove_file(&path);
+        assert!(result.is_err());
+    }
+
+    #[cfg(feature = "user_overrides")]
+    #[test]
+    fn test_pam_nss_sock_path_override() {
+        let path = std::env::temp_dir().join(format!(
+            "kanidm-unixd-sock-override-{}.sock",
+            std::process::id()
+        ));
+        std::fs::write(&path, "").expect("Failed to write socket placeholder");
+
+        // A socket owned by the calling user is accepted.
+        let config = PamNssConfig::new().apply_sock_path_override(&path);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(Path::new(&config.sock_path), path.as_path());
+
+        // A socket that can't be shown to be owned by the caller is only accepted as root.
+        let missing = std::env::temp_dir().join("kanidm-unixd-sock-override-missing.sock");
+        let config = PamNssConfig::new().apply_sock_path_override(&missing);
+        if kanidm_utils_users::get_effective_uid() == 0 {
+            assert_eq!(Path::new(&config.sock_path), missing.as_path());
+        } else {
+            assert_eq!(config.sock_path, DEFAULT_SOCK_PATH);
+        }
+    }
+
+    #[test]
+    fn test_validate_home_mount_prefix() {
+        let base = std::env::temp_dir().join(format!("kanidm-unixd-homes-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        std::fs::create_dir_all(&base).expect("Failed to create test dir");
+        let base = base
+            .canonicalize()
+            .expect("Failed to canonicalize test dir");
+        let home = base.join("home");
+        let mount = base.join("u");
+        std::fs::create_dir_all(&home).expect("Failed to create home_prefix");
+        std::fs::create_dir_all(mount.join("alice")).expect("Failed to create home");
+
+        let mut config = UnixdConfig::new();
+        config.home_prefix = home.clone();
+
+        // Unset is always valid.
+        assert!(config.validate().is_ok());
+
+        // The same path, even when written differently, has no effect.
+        config.home_mount_prefix = Some(PathBuf::from(format!("{}/", home.display())));
+        assert!(config.validate().is_err());
+
+        // A mount prefix that doesn't exist yet is only warned about.
+        config.home_mount_prefix = Some(base.join("missing"));
+        assert!(config.validate().is_ok());
+
+        // Links into the mount prefix are correct, anything else is counted. Plain
+        // directories are not links and are ignored.
+        std::os::unix::fs::symlink(mount.join("alice"), home.join("alice"))
+            .expect("Failed to create symlink");
+        std::os::unix::fs::symlink(base.join("other/bob"), home.join("bob"))
+            .expect("Failed to create symlink");
+        std::os::unix::fs::symlink("../other/carol", home.join("carol"))
+            .expect("Failed to create symlink");
+        std::fs::create_dir_all(home.join("dave")).expect("Failed to create home");
+        // Relative links into the mount prefix are also correct, even if dangling.
+        std::fs::create_dir_all(mount.join("erin")).expect("Failed to create home");
+        std::os::unix::fs::symlink("../u/erin", home.join("erin"))
+            .expect("Failed to create symlink");
+        std::os::unix::fs::symlink("../u/frank", home.join("frank"))
+            .expect("Failed to create symlink");
+
+        assert_eq!(count_misdirected_home_symlinks(&home, &mount), 2);
+        config.home_mount_prefix = Some(mount);
+        assert!(config.validate().is_ok());
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_effective_toml_round_trip() {
+        let token_path =
+            std::env::temp_dir().join(format!("kanidm-unixd-token-{}", std::process::id()));
+        std::fs::write(&token_path, "secret-api-token\n").expect("Failed to write token");
+
+        let config = parse_config(
+            "effective-toml",
+            &format!(
+                r#"
+version = '2'
+cache_timeout = 600
+negative_cache_timeout = 30
+home_prefix = "/u"
+home_attr = "name"
+home_alias = ["spn", "uuid"]
+uid_attr_map = "name_prefer_spn_on_collision"
+gid_allow_range_min = 1000
+gid_allow_range_max = 60000
+gecos_attr = "spn"
+nss_enumeration = "full"
+hsm_type = "tpm"
+hsm_tpm_pcr_policy = [1, 0, 128]
+hsm_tpm_pin_nv_index = 25165824
+
+[kanidm]
+conn_timeout = 7
+pam_allowed_login_groups = ["posix_group"]
+service_account_token_path = "{}"
+tls_pin_sha256 = "2f3b0e2a4d8d1f3c5b7a9e1d3f5b7a9c2e4d6f8a0b2c4e6d8f0a1b3c5d7e9f1a"
+client_user_agent = "kanidm-unixd/test"
+
+[[kanidm.map_group]]
+local = "wheel"
+with = "admins"
+"#,
+                token_path.display()
+            ),
+        );
+
+        let effective = config.to_effective_toml();
+        assert!(!effective.contains("secret-api-token"));
+        assert!(effective.contains("# service_account_token: redacted"));
+
+        let reparsed = parse_config("effective-toml-reparsed", &effective);
+        let _ = std::fs::remove_file(&token_path);
+
+        // Everything in the config, including the defaults, survives the round trip.
+        assert_eq!(reparsed.to_effective_toml(), effective);
+        assert_eq!(reparsed.home_aliases, vec![HomeAttr::Spn, HomeAttr::Uuid]);
+        assert_eq!(reparsed.nss_enumeration, EnumerationMode::Full);
+        assert_eq!(reparsed.hsm_tpm_pcr_policy, Some(vec![1, 0, 128]));
+
+        let kconfig = reparsed.kanidm_config.expect("No kanidm config");
+        assert_eq!(
+            kconfig.service_account_token.as_deref(),
+            Some("secret-api-token")
+        );
+        assert_eq!(kconfig.map_group.len(), 1);
+        assert_eq!(
+            kconfig.tls_pin_sha256,
+            config.kanidm_config.and_then(|k| k.tls_pin_sha256)
+        );
+
+        // An empty alias list is written in a form that parses back as empty.
+        let config = parse_config(
+            "effective-toml-no-alias",
+            "version = '2'\nhome_alias = \"none\"\n",
+        );
+        let reparsed = parse_config(
+            "effective-toml-no-alias-reparsed",
+            &config.to_effective_toml(),
+        );
+        assert!(reparsed.home_aliases.is_empty());
+        assert!(reparsed.kanidm_config.is_none());
+    }
 }
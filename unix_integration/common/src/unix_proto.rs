@@ -133,7 +133,10 @@ pub enum ClientRequest {
     PamAccountBeginSession(String),
     InvalidateCache,
     ClearCache,
+    /// Cache the members of these groups. If empty, the login groups are used.
+    CacheWarmup(Vec<String>),
     Status,
+    CacheStats,
 }
 
 impl ClientRequest {
@@ -161,7 +164,9 @@ impl ClientRequest {
             ClientRequest::PamAccountBeginSession(_) => "PamAccountBeginSession".to_string(),
             ClientRequest::InvalidateCache => "InvalidateCache".to_string(),
             ClientRequest::ClearCache => "ClearCache".to_string(),
+            ClientRequest::CacheWarmup(groups) => format!("CacheWarmup({})", groups.join(",")),
             ClientRequest::Status => "Status".to_string(),
+            ClientRequest::CacheStats => "CacheStats".to_string(),
         }
     }
 }
@@ -172,6 +177,19 @@ pub struct ProviderStatus {
     pub online: bool,
 }
 
+/// Counters describing how effective the resolver cache has been since the daemon started.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct CacheStats {
+    pub user_hits: u64,
+    pub user_misses: u64,
+    pub group_hits: u64,
+    pub group_misses: u64,
+    /// If all remote providers are currently online.
+    pub online: bool,
+    /// The unix timestamp of when the providers were last seen online, or 0 if never.
+    pub last_online: u64,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum ClientResponse {
     SshKeys(Vec<String>),
@@ -187,17 +205,96 @@ pub enum ClientResponse {
     },
 
     ProviderStatus(Vec<ProviderStatus>),
+    CacheStats(CacheStats),
 
     Ok,
     Error(OperationError),
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "HomeDirectoryInfoWire", into = "HomeDirectoryInfoWire")]
 pub struct HomeDirectoryInfo {
     pub uid: u32,
     pub gid: u32,
     pub name: String,
-    pub alias: Option<String>,
+    /// The aliases to link to the home directory, in the order they are created.
+    pub aliases: Vec<String>,
+    /// Set when `home_template` is configured, in which case the home directory is
+    /// created at the rendered template rather than within `home_prefix`.
+    pub template_values: Option<HomeTemplateValues>,
+}
+
+/// The serialised form of [HomeDirectoryInfo]. Older versions sent a single `alias`,
+/// so it is still read and written alongside `aliases`. This allows unixd and the
+/// tasks daemon to be upgraded at different times.
+#[derive(Serialize, Deserialize)]
+struct HomeDirectoryInfoWire {
+    uid: u32,
+    gid: u32,
+    name: String,
+    #[serde(default)]
+    aliases: Vec<String>,
+    #[serde(default)]
+    alias: Option<String>,
+    #[serde(default)]
+    template_values: Option<HomeTemplateValues>,
+}
+
+impl From<HomeDirectoryInfoWire> for HomeDirectoryInfo {
+    fn from(wire: HomeDirectoryInfoWire) -> Self {
+        let HomeDirectoryInfoWire {
+            uid,
+            gid,
+            name,
+            mut aliases,
+            alias,
+            template_values,
+        } = wire;
+
+        if aliases.is_empty() {
+            aliases.extend(alias);
+        }
+
+        HomeDirectoryInfo {
+            uid,
+            gid,
+            name,
+            aliases,
+            template_values,
+        }
+    }
+}
+
+impl From<HomeDirectoryInfo> for HomeDirectoryInfoWire {
+    fn from(info: HomeDirectoryInfo) -> Self {
+        let HomeDirectoryInfo {
+            uid,
+            gid,
+            name,
+            aliases,
+            template_values,
+        } = info;
+
+        HomeDirectoryInfoWire {
+            uid,
+            gid,
+            name,
+            alias: aliases.first().cloned(),
+            aliases,
+            template_values,
+        }
+    }
+}
+
+/// The values of a user that `home_template` is rendered with.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HomeTemplateValues {
+    /// `%u` - the uid attribute, as selected by `uid_attr_map`.
+    pub uid: String,
+    /// `%U` - the uuid.
+    pub uuid: String,
+    /// `%d` - the domain, from the spn.
+    pub domain: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -229,3 +326,28 @@ fn test_clientrequest_as_safe_string() {
         format!("SshKey({})", "cheese")
     );
 }
+
+#[test]
+fn test_home_directory_info_legacy_alias() {
+    // A request from an older unixd, with a single alias.
+    let info: HomeDirectoryInfo =
+        serde_json::from_str(r#"{"uid":1000,"gid":1000,"name":"alice","alias":"alice_alias"}"#)
+            .expect("Failed to parse legacy home directory info");
+    assert_eq!(info.aliases, vec!["alice_alias".to_string()]);
+    assert_eq!(info.template_values, None);
+
+    // Older tasks daemons only read the first alias.
+    let info = HomeDirectoryInfo {
+        uid: 1000,
+        gid: 1000,
+        name: "alice".to_string(),
+        aliases: vec!["a".to_string(), "b".to_string()],
+        template_values: None,
+    };
+    let value = serde_json::to_value(&info).expect("Failed to serialise home directory info");
+    assert_eq!(value["alias"], "a");
+
+    let round_trip: HomeDirectoryInfo =
+        serde_json::from_value(value).expect("Failed to parse home directory info");
+    assert_eq!(round_trip, info);
+}
@@ -55,6 +55,10 @@ impl RequestOptions {
             RequestOptions::Main { config_path } => {
                 let maybe_client = PamNssConfig::new()
                     .read_options_from_optional_config(config_path)
+                    // A user configuration that can't be read never discards the system
+                    // configuration.
+                    .map(|cfg| cfg.clone().read_options_from_user_config().unwrap_or(cfg))
+                    .map(|cfg| cfg.read_options_from_env())
                     .ok()
                     .and_then(|cfg| {
                         DaemonClientBlocking::new(cfg.sock_path.as_str(), cfg.unix_sock_timeout)
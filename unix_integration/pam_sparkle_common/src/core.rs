@@ -375,6 +375,7 @@ pub fn sm_authenticate_connected<P: PamHandler>(
             | ClientResponse::NssGroups(_)
             | ClientResponse::PamStatus(_)
             | ClientResponse::ProviderStatus(_)
+            | ClientResponse::CacheStats(_)
             | ClientResponse::NssGroup(_) => {
                 debug!("PamResultCode::PAM_AUTH_ERR");
                 return PamResultCode::PAM_AUTH_ERR;
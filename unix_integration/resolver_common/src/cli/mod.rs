@@ -1,4 +1,5 @@
 pub mod resolver;
 pub mod ssh_authorisedkeys;
+pub mod status;
 pub mod tasks;
 pub mod tool;
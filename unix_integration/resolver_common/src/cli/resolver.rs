@@ -9,7 +9,7 @@ use crate::{
 };
 use clap::{Arg, ArgAction, Command};
 use futures::{SinkExt, StreamExt};
-use kanidm_client::KanidmClientBuilder;
+use kanidm_client::{ClientError, KanidmClient, KanidmClientBuilder, StatusCode};
 use kanidm_hsm_crypto::{
     provider::{BoxedDynTpm, SoftTpm, Tpm},
     AuthValue,
@@ -48,6 +48,7 @@ use tokio::net::{UnixListener, UnixStream};
 use tokio::sync::broadcast;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
 use tokio::sync::oneshot;
+use tokio::task::JoinSet;
 use tokio_util::codec::Framed;
 
 #[cfg(feature = "dhat-heap")]
@@ -59,6 +60,12 @@ const DEFAULT_CONCURRENT_AUTH_SESSIONS: NonZeroUsize = NonZeroUsize::new(64)
 const REFRESH_DEBOUNCE_SIZE: NonZeroUsize =
     NonZeroUsize::new(16).expect("Invalid REFRESH_DEBOUNCE_SIZE constant at compile time");
 const REFRESH_DEBOUNCE_WINDOW: Duration = Duration::from_secs(5);
+/// How long in-flight client requests may take to complete once shutdown begins.
+const SHUTDOWN_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long in seconds each request waits for posix accounts or groups to change.
+const UNIX_CHANGES_WAIT: u64 = 30;
+/// How long to wait before asking for changes again after the server can't be reached.
+const UNIX_CHANGES_RETRY: Duration = Duration::from_secs(30);
 
 struct AsyncTaskRequest {
     task_req: TaskRequest,
@@ -81,6 +88,61 @@ fn rm_if_exist(p: &str) {
     });
 }
 
+/// Wait for posix accounts and groups to change on the server, and expire them in the
+/// cache so that they are refreshed on next use rather than once their cache timeout
+/// passes. Changes made while the server can't be reached are not seen, and those
+/// entries are refreshed once they expire as usual.
+async fn handle_unix_changes(
+    client: KanidmClient,
+    service_account_token: Option<String>,
+    cachelayer: Arc<Resolver>,
+) {
+    let bearer_token_set = service_account_token.is_some();
+    if let Some(token) = service_account_token {
+        client.set_token(token).await;
+    }
+    let mut authenticated = bearer_token_set;
+
+    loop {
+        if !authenticated {
+            if let Err(err) = client.auth_anonymous().await {
+                debug!(?err, "Unable to authenticate to wait for unix changes");
+                tokio::time::sleep(UNIX_CHANGES_RETRY).await;
+                continue;
+            }
+            authenticated = true;
+        }
+
+        match client.idm_unix_changes_wait(UNIX_CHANGES_WAIT).await {
+            Ok(changes) if changes.all => {
+                debug!("All unix entries changed");
+                if cachelayer.invalidate().await.is_err() {
+                    error!("Failed to invalidate the cache");
+                }
+            }
+            Ok(changes) if changes.uuids.is_empty() => {}
+            Ok(changes) => {
+                debug!(count = changes.uuids.len(), "Unix entries changed");
+                if cachelayer.invalidate_uuids(&changes.uuids).await.is_err() {
+                    error!("Failed to invalidate changed unix entries");
+                }
+            }
+            Err(ClientError::Http(StatusCode::NOT_FOUND, _, _)) => {
+                info!("The server does not send unix change notifications, cached entries are refreshed when they expire");
+                return;
+            }
+            Err(ClientError::Http(StatusCode::UNAUTHORIZED, _, _)) if !bearer_token_set => {
+                // The anonymous session has expired, start a new one.
+                authenticated = false;
+            }
+            Err(err) => {
+                debug!(?err, "Unable to wait for unix changes");
+                tokio::time::sleep(UNIX_CHANGES_RETRY).await;
+            }
+        }
+    }
+}
+
 async fn handle_task_client(
     stream: UnixStream,
     notify_shadow_change_tx: &Sender<EtcDb>,
@@ -172,6 +234,7 @@ async fn handle_client(
     sock: UnixStream,
     cachelayer: Arc<Resolver>,
     task_channel_tx: &Sender<AsyncTaskRequest>,
+    mut drain_rx: broadcast::Receiver<bool>,
 ) {
     let conn_id = uuid::Uuid::new_v4();
 
@@ -191,7 +254,20 @@ async fn handle_client(
     // tell consumers to stop work.
     let (shutdown_tx, _shutdown_rx) = broadcast::channel(1);
 
-    while let Some(Ok(req)) = reqs.next().await {
+    loop {
+        // Once the daemon begins to shut down we stop reading new requests, but a
+        // request that is already being processed is allowed to complete.
+        let req = tokio::select! {
+            _ = drain_rx.recv() => {
+                debug!(uuid = %conn_id, "shutdown in progress, closing client connection");
+                break;
+            }
+            next_req = reqs.next() => match next_req {
+                Some(Ok(req)) => req,
+                _ => break,
+            },
+        };
+
         let maybe_err: Result<(), Box<dyn Error>> = async {
             debug!(uid = ?ucred.uid(), gid = ?ucred.gid(), pid = ?ucred.pid());
 
@@ -398,10 +474,31 @@ async fn handle_client(
                         ClientResponse::Error(OperationError::KU006OnlyRootAllowed)
                     }
                 }
+                ClientRequest::CacheWarmup(groups) => {
+                    if ucred.uid() == 0 {
+                        cachelayer
+                            .warmup(groups.as_slice())
+                            .await
+                            .map(|cached| {
+                                info!(?cached, "cache warmup complete");
+                                ClientResponse::Ok
+                            })
+                            .unwrap_or(ClientResponse::Error(
+                                OperationError::KU007CacheWarmupFailed,
+                            ))
+                    } else {
+                        error!("{}", OperationError::KU006OnlyRootAllowed);
+                        ClientResponse::Error(OperationError::KU006OnlyRootAllowed)
+                    }
+                }
                 ClientRequest::Status => {
                     let status = cachelayer.provider_status().await;
                     ClientResponse::ProviderStatus(status)
                 }
+                ClientRequest::CacheStats => {
+                    let stats = cachelayer.cache_stats().await;
+                    ClientResponse::CacheStats(stats)
+                }
             };
 
             trace!(?resp);
@@ -469,6 +566,184 @@ async fn write_hsm_pin(hsm_pin_path: &str) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+#[cfg(feature = "tpm")]
+fn pcr_selection_from_mask(
+    pcr_mask: &[u8],
+) -> Result<tss_esapi::structures::PcrSelectionList, Box<dyn Error>> {
+    use tss_esapi::interface_types::algorithm::HashingAlgorithm;
+    use tss_esapi::structures::{PcrSelectionListBuilder, PcrSlot};
+
+    // PcrSlot is a bitmask over a u32, so at most 32 PCRs can be selected.
+    if pcr_mask.len() > 4 {
+        return Err("hsm_tpm_pcr_policy may select at most 32 PCRs".into());
+    }
+
+    let slots = pcr_mask
+        .iter()
+        .enumerate()
+        .flat_map(|(byte_idx, byte)| {
+            (0..8u32)
+                .filter(move |bit| byte & (1 << bit) != 0)
+                .map(move |bit| (byte_idx as u32) * 8 + bit)
+        })
+        .map(|pcr| PcrSlot::try_from(1u32 << pcr))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if slots.is_empty() {
+        return Err("hsm_tpm_pcr_policy does not select any PCRs".into());
+    }
+
+    Ok(PcrSelectionListBuilder::new()
+        .with_selection(HashingAlgorithm::Sha256, &slots)
+        .build()?)
+}
+
+#[cfg(feature = "tpm")]
+fn start_pcr_policy_session(
+    tpm_ctx: &mut tss_esapi::Context,
+    pcr_selection: tss_esapi::structures::PcrSelectionList,
+    session_type: tss_esapi::constants::SessionType,
+) -> Result<tss_esapi::interface_types::session_handles::PolicySession, Box<dyn Error>> {
+    use tss_esapi::attributes::SessionAttributesBuilder;
+    use tss_esapi::interface_types::algorithm::HashingAlgorithm;
+    use tss_esapi::interface_types::session_handles::PolicySession;
+    use tss_esapi::structures::{Digest, SymmetricDefinition};
+
+    let session = tpm_ctx
+        .start_auth_session(
+            None,
+            None,
+            None,
+            session_type,
+            SymmetricDefinition::AES_128_CFB,
+            HashingAlgorithm::Sha256,
+        )?
+        .ok_or("No policy session created by tpm context")?;
+
+    let (session_attributes, session_attributes_mask) = SessionAttributesBuilder::new()
+        .with_decrypt(true)
+        .with_encrypt(true)
+        .build();
+    tpm_ctx.tr_sess_set_attributes(session, session_attributes, session_attributes_mask)?;
+
+    let policy_session = PolicySession::try_from(session)?;
+    // An empty digest has the tpm use the current value of the selected PCRs.
+    tpm_ctx.policy_pcr(policy_session, Digest::default(), pcr_selection)?;
+
+    Ok(policy_session)
+}
+
+#[cfg(feature = "tpm")]
+fn flush_policy_session(
+    tpm_ctx: &mut tss_esapi::Context,
+    policy_session: tss_esapi::interface_types::session_handles::PolicySession,
+) {
+    use tss_esapi::handles::SessionHandle;
+    use tss_esapi::interface_types::session_handles::AuthSession;
+
+    let session_handle = SessionHandle::from(AuthSession::from(policy_session));
+    if let Err(tpm_err) = tpm_ctx.flush_context(session_handle.into()) {
+        warn!(?tpm_err, "Unable to flush tpm policy session");
+    }
+}
+
+/// Generate a new HSM pin and store it in the requested NV index, readable only
+/// while the selected PCRs hold their current values.
+#[cfg(feature = "tpm")]
+fn seal_hsm_pin_nv(
+    tpm_ctx: &mut tss_esapi::Context,
+    nv_tpm_handle: tss_esapi::handles::NvIndexTpmHandle,
+    pcr_selection: tss_esapi::structures::PcrSelectionList,
+) -> Result<tss_esapi::handles::NvIndexHandle, Box<dyn Error>> {
+    use tss_esapi::attributes::NvIndexAttributesBuilder;
+    use tss_esapi::constants::{NvIndexType, SessionType};
+    use tss_esapi::interface_types::algorithm::HashingAlgorithm;
+    use tss_esapi::interface_types::resource_handles::{NvAuth, Provision};
+    use tss_esapi::interface_types::session_handles::AuthSession;
+    use tss_esapi::structures::{MaxNvBuffer, NvPublicBuilder};
+
+    let trial_session = start_pcr_policy_session(tpm_ctx, pcr_selection, SessionType::Trial)?;
+    let policy_digest = tpm_ctx.policy_get_digest(trial_session);
+    flush_policy_session(tpm_ctx, trial_session);
+    let policy_digest = policy_digest?;
+
+    let new_pin = AuthValue::generate().map_err(|hsm_err| {
+        error!(?hsm_err, "Unable to generate new pin");
+        std::io::Error::other("Unable to generate new pin")
+    })?;
+    let new_pin = MaxNvBuffer::try_from(new_pin.into_bytes())?;
+
+    let nv_index_attributes = NvIndexAttributesBuilder::new()
+        .with_owner_write(true)
+        .with_policy_read(true)
+        .with_nv_index_type(NvIndexType::Ordinary)
+        .build()?;
+
+    let nv_public = NvPublicBuilder::new()
+        .with_nv_index(nv_tpm_handle)
+        .with_index_name_algorithm(HashingAlgorithm::Sha256)
+        .with_index_attributes(nv_index_attributes)
+        .with_index_auth_policy(policy_digest)
+        .with_data_area_size(new_pin.len())
+        .build()?;
+
+    let nv_index = tpm_ctx.execute_with_session(Some(AuthSession::Password), |ctx| {
+        ctx.nv_define_space(Provision::Owner, None, nv_public)
+    })?;
+
+    tpm_ctx.execute_with_session(Some(AuthSession::Password), |ctx| {
+        ctx.nv_write(NvAuth::Owner, nv_index, new_pin, 0)
+    })?;
+
+    info!("Generated new HSM pin sealed to tpm nv index");
+
+    Ok(nv_index)
+}
+
+/// Read the HSM pin from a PCR sealed NV index, creating and sealing a new pin
+/// if the index is not yet defined.
+#[cfg(feature = "tpm")]
+fn read_hsm_pin_nv(
+    tcti_name: &str,
+    nv_index: u32,
+    pcr_mask: &[u8],
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    use tss_esapi::constants::SessionType;
+    use tss_esapi::handles::{NvIndexHandle, NvIndexTpmHandle, TpmHandle};
+    use tss_esapi::interface_types::resource_handles::NvAuth;
+    use tss_esapi::{Context, TctiNameConf};
+
+    let pcr_selection = pcr_selection_from_mask(pcr_mask)?;
+    let nv_tpm_handle = NvIndexTpmHandle::new(nv_index)?;
+    let mut tpm_ctx = Context::new(TctiNameConf::from_str(tcti_name)?)?;
+
+    let nv_index = match tpm_ctx.tr_from_tpm_public(TpmHandle::NvIndex(nv_tpm_handle)) {
+        Ok(object_handle) => NvIndexHandle::from(object_handle),
+        Err(_) => seal_hsm_pin_nv(&mut tpm_ctx, nv_tpm_handle, pcr_selection.clone())?,
+    };
+
+    let (nv_public, _nv_name) = tpm_ctx.nv_read_public(nv_index)?;
+    let size = u16::try_from(nv_public.data_size())?;
+
+    let policy_session =
+        start_pcr_policy_session(&mut tpm_ctx, pcr_selection, SessionType::Policy)?;
+    let hsm_pin = tpm_ctx.execute_with_session(Some(policy_session.into()), |ctx| {
+        ctx.nv_read(NvAuth::NvIndex(nv_index), nv_index, size, 0)
+    });
+    flush_policy_session(&mut tpm_ctx, policy_session);
+
+    Ok(hsm_pin?.value().to_vec())
+}
+
+#[cfg(not(feature = "tpm"))]
+fn read_hsm_pin_nv(
+    _tcti_name: &str,
+    _nv_index: u32,
+    _pcr_mask: &[u8],
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    Err("Hardware TPM support was not enabled in this build. Unable to unseal the HSM PIN".into())
+}
+
 #[cfg(feature = "tpm")]
 fn open_tpm(tcti_name: &str) -> Option<BoxedDynTpm> {
     use kanidm_hsm_crypto::provider::TssTpm;
@@ -606,7 +881,7 @@ async fn main_inner<F: SparkleFlavour>(clap_args: clap::ArgMatches, flavour: F)
         }
     }
 
-    let cfg = match UnixdConfig::new().read_options_from_optional_config(&unixd_path) {
+    let mut cfg = match UnixdConfig::new().read_options_from_optional_config(&unixd_path) {
         Ok(v) => v,
         Err(_) => {
             error!("Failed to parse {}", unixd_path_str);
@@ -614,6 +889,11 @@ async fn main_inner<F: SparkleFlavour>(clap_args: clap::ArgMatches, flavour: F)
         }
     };
 
+    if cfg.validate().is_err() {
+        error!("Invalid configuration in {}", unixd_path_str);
+        return ExitCode::FAILURE;
+    }
+
     let client_builder = if let Some(kconfig) = &cfg.kanidm_config {
         if kconfig.pam_allowed_login_groups.is_empty() {
             error!("Kanidm is enabled but no pam_allowed_login_groups are set - KANIDM USERS CANNOT AUTHENTICATE !!!");
@@ -738,6 +1018,34 @@ async fn main_inner<F: SparkleFlavour>(clap_args: clap::ArgMatches, flavour: F)
         };
     }
 
+    // Carry the cache over from a previous path, rather than starting empty.
+    if let Some(migrate_from) = cfg.cache_db_path_migrate_from.take() {
+        if cfg.cache_db_path.is_empty() || PathBuf::from(cfg.cache_db_path.as_str()).exists() {
+            debug!(
+                "cache_db_path {} already exists, not migrating from {}",
+                cfg.cache_db_path, migrate_from
+            );
+        } else if !PathBuf::from(migrate_from.as_str()).exists() {
+            warn!(
+                "cache_db_path_migrate_from {} does not exist, starting with an empty cache",
+                migrate_from
+            );
+        } else if Db::migrate_from(migrate_from.as_str(), cfg.cache_db_path.as_str()).is_ok() {
+            info!(
+                "Migrated cache db from {} to {}, you can now remove cache_db_path_migrate_from",
+                migrate_from, cfg.cache_db_path
+            );
+        } else {
+            let diag = diagnose_path(cfg.cache_db_path.as_ref());
+            info!(%diag);
+            error!(
+                "Failed to migrate cache db from {} to {}",
+                migrate_from, cfg.cache_db_path
+            );
+            return ExitCode::FAILURE;
+        }
+    }
+
     let db = match Db::new(cfg.cache_db_path.as_str()) {
         Ok(db) => db,
         Err(_e) => {
@@ -753,30 +1061,56 @@ async fn main_inner<F: SparkleFlavour>(clap_args: clap::ArgMatches, flavour: F)
         return ExitCode::FAILURE;
     }
 
-    // Check for and create the hsm pin if required.
-    if let Err(err) = write_hsm_pin(cfg.hsm_pin_path.as_str()).await {
-        let diag = diagnose_path(cfg.hsm_pin_path.as_ref());
-        info!(%diag);
-        error!(
-            ?err,
-            "Failed to create HSM PIN into {}",
-            cfg.hsm_pin_path.as_str()
-        );
-        return ExitCode::FAILURE;
-    };
-
-    // read the hsm pin
-    let hsm_pin = match read_hsm_pin(cfg.hsm_pin_path.as_str()).await {
-        Ok(hp) => hp,
-        Err(err) => {
+    let hsm_pin = if let (HsmType::Tpm, Some(pcr_mask), Some(nv_index)) = (
+        &cfg.hsm_type,
+        cfg.hsm_tpm_pcr_policy.as_deref(),
+        cfg.hsm_tpm_pin_nv_index,
+    ) {
+        // The pin is sealed in the tpm, and only released when the PCRs match.
+        match read_hsm_pin_nv(&cfg.tpm_tcti_name, nv_index, pcr_mask) {
+            Ok(hp) => hp,
+            Err(err) => {
+                error!(
+                    ?err,
+                    "Failed to unseal HSM PIN from tpm nv index {:#010x}", nv_index
+                );
+                // The old pin can't be recovered once the PCRs have changed, so the
+                // keys bound to it are lost. Starting again seals a new pin to the
+                // current PCR values.
+                error!(
+                    "If the selected PCRs changed due to a firmware, bootloader or kernel update, you must remove the nv index (tpm2_nvundefine {:#010x}) and the content of the cache db ({}) to reset all keys",
+                    nv_index,
+                    cfg.cache_db_path.as_str()
+                );
+                return ExitCode::FAILURE;
+            }
+        }
+    } else {
+        // Check for and create the hsm pin if required.
+        if let Err(err) = write_hsm_pin(cfg.hsm_pin_path.as_str()).await {
             let diag = diagnose_path(cfg.hsm_pin_path.as_ref());
             info!(%diag);
             error!(
                 ?err,
-                "Failed to read HSM PIN from {}",
+                "Failed to create HSM PIN into {}",
                 cfg.hsm_pin_path.as_str()
             );
             return ExitCode::FAILURE;
+        };
+
+        // read the hsm pin
+        match read_hsm_pin(cfg.hsm_pin_path.as_str()).await {
+            Ok(hp) => hp,
+            Err(err) => {
+                let diag = diagnose_path(cfg.hsm_pin_path.as_ref());
+                info!(%diag);
+                error!(
+                    ?err,
+                    "Failed to read HSM PIN from {}",
+                    cfg.hsm_pin_path.as_str()
+                );
+                return ExitCode::FAILURE;
+            }
         }
     };
 
@@ -848,11 +1182,24 @@ async fn main_inner<F: SparkleFlavour>(clap_args: clap::ArgMatches, flavour: F)
     info!("Started system provider");
 
     let mut clients: Vec<Arc<dyn IdProvider + Send + Sync>> = Vec::with_capacity(1);
+    let mut unix_changes_client = None;
 
     // Setup Kanidm provider if the configuration requests it.
     if let Some((cb, kconfig)) = client_builder {
         let cb = cb.connect_timeout(kconfig.conn_timeout);
         let cb = cb.request_timeout(kconfig.request_timeout);
+        let cb = cb.idle_timeout(kconfig.idle_timeout);
+        let cb = match kconfig.tls_pin_sha256 {
+            Some(pin) => cb.tls_pin_sha256(pin),
+            None => cb,
+        };
+        let cb = cb.set_user_agent(Some(kconfig.client_user_agent.clone()));
+
+        // Waiting for unix changes holds each request open, so it needs a separate
+        // client with a longer request timeout.
+        let changes_cb = cb
+            .clone()
+            .request_timeout(kconfig.request_timeout + UNIX_CHANGES_WAIT);
 
         let rsclient = match cb.build() {
             Ok(rsc) => rsc,
@@ -862,6 +1209,16 @@ async fn main_inner<F: SparkleFlavour>(clap_args: clap::ArgMatches, flavour: F)
             }
         };
 
+        match changes_cb.build() {
+            Ok(changes_client) => {
+                unix_changes_client = Some((changes_client, kconfig.service_account_token.clone()));
+            }
+            Err(_e) => {
+                error!("Failed to build async client");
+                return ExitCode::FAILURE;
+            }
+        };
+
         let Ok(idprovider) = KanidmProvider::new(
             rsclient,
             kconfig,
@@ -909,12 +1266,19 @@ async fn main_inner<F: SparkleFlavour>(clap_args: clap::ArgMatches, flavour: F)
         clients,
         hsm,
         cfg.cache_timeout,
+        cfg.negative_cache_timeout,
         cfg.default_shell.clone(),
         cfg.home_prefix.clone(),
         cfg.home_attr,
-        cfg.home_alias,
+        cfg.home_aliases.clone(),
+        cfg.home_template.clone(),
         cfg.uid_attr_map,
         cfg.gid_attr_map,
+        cfg.gid_allow_range_min,
+        cfg.gid_allow_range_max,
+        cfg.gecos_attr,
+        cfg.include_expired,
+        cfg.nss_enumeration,
     )
     .await
     {
@@ -925,6 +1289,8 @@ async fn main_inner<F: SparkleFlavour>(clap_args: clap::ArgMatches, flavour: F)
         }
     };
 
+    cl_inner.check_gid_range().await;
+
     let cachelayer = Arc::new(cl_inner);
 
     // Setup the root-only tasks socket. Take away all other access bits.
@@ -964,7 +1330,7 @@ async fn main_inner<F: SparkleFlavour>(clap_args: clap::ArgMatches, flavour: F)
 
     // Broadcast receivers so that the tasks-task can be shut down when we get
     // signals etc.
-    let (broadcast_tx, mut broadcast_rx) = broadcast::channel(4);
+    let (broadcast_tx, _broadcast_rx) = broadcast::channel(4);
     let mut c_broadcast_rx = broadcast_tx.subscribe();
     let mut d_broadcast_rx = broadcast_tx.subscribe();
 
@@ -1037,6 +1403,22 @@ async fn main_inner<F: SparkleFlavour>(clap_args: clap::ArgMatches, flavour: F)
         info!("Stopped shadow reload task handler");
     });
 
+    // ====== Listen for posix account and group changes from the server ======
+
+    let task_d = unix_changes_client.map(|(client, service_account_token)| {
+        let unix_changes_cachelayer = cachelayer.clone();
+        let mut c_broadcast_rx = broadcast_tx.subscribe();
+
+        tokio::spawn(async move {
+            debug!("Spawned unix change handler");
+            tokio::select! {
+                _ = c_broadcast_rx.recv() => {}
+                _ = handle_unix_changes(client, service_account_token, unix_changes_cachelayer) => {}
+            }
+            info!("Stopped unix change handler");
+        })
+    });
+
     // Setup the task that handles async pre-fetching here.
     let prefetch_cachelayer = cachelayer.clone();
     let _task_prefetch = tokio::spawn(async move {
@@ -1086,20 +1468,30 @@ async fn main_inner<F: SparkleFlavour>(clap_args: clap::ArgMatches, flavour: F)
     // Undo umask changes.
     let _ = unsafe { umask(before) };
 
+    // The client listener is shut down separately, after the task socket is closed,
+    // so that in-flight requests can drain without new home directory tasks starting.
+    let (client_shutdown_tx, mut client_shutdown_rx) = broadcast::channel(1);
+    let drain_tx = client_shutdown_tx.clone();
+
     let task_a = tokio::spawn(async move {
+        let mut clients = JoinSet::new();
+
         loop {
             let tc_tx = task_channel_tx_cln.clone();
 
             tokio::select! {
-                _ = broadcast_rx.recv() => {
+                _ = client_shutdown_rx.recv() => {
                     break;
                 }
+                // Reap completed client connections so the set doesn't grow unbounded.
+                Some(_) = clients.join_next(), if !clients.is_empty() => {}
                 accept_res = listener.accept() => {
                     match accept_res {
                         Ok((socket, _addr)) => {
                             let cachelayer_ref = cachelayer.clone();
-                            tokio::spawn(async move {
-                                handle_client(socket, cachelayer_ref.clone(), &tc_tx).await;
+                            let drain_rx = drain_tx.subscribe();
+                            clients.spawn(async move {
+                                handle_client(socket, cachelayer_ref.clone(), &tc_tx, drain_rx).await;
                             });
                         }
                         Err(err) => {
@@ -1109,6 +1501,25 @@ async fn main_inner<F: SparkleFlavour>(clap_args: clap::ArgMatches, flavour: F)
                 }
             }
         }
+
+        // Stop accepting new connections, then wait for in-flight requests.
+        drop(listener);
+        info!(
+            in_flight = clients.len(),
+            "Closed client socket, draining in-flight requests"
+        );
+
+        let drain = async { while clients.join_next().await.is_some() {} };
+        if tokio::time::timeout(SHUTDOWN_DRAIN_TIMEOUT, drain)
+            .await
+            .is_err()
+        {
+            warn!(
+                remaining = clients.len(),
+                "Drain timeout exceeded, aborting remaining client requests"
+            );
+            clients.abort_all();
+        }
         info!("Stopped resolver");
     });
 
@@ -1163,14 +1574,22 @@ async fn main_inner<F: SparkleFlavour>(clap_args: clap::ArgMatches, flavour: F)
         }
     }
     info!("Signal received, sending down signal to tasks");
-    // Send a broadcast that we are done.
+    // Close the task socket first, so that no new home directory creation
+    // tasks can begin while we drain.
     if let Err(e) = broadcast_tx.send(true) {
         error!("Unable to shutdown workers {:?}", e);
     }
+    let _ = task_b.await;
 
+    // Now stop the client socket, and allow in-flight requests to complete.
+    if let Err(e) = client_shutdown_tx.send(true) {
+        error!("Unable to shutdown client listener {:?}", e);
+    }
     let _ = task_a.await;
-    let _ = task_b.await;
     let _ = task_c.await;
+    if let Some(task_d) = task_d {
+        let _ = task_d.await;
+    }
 
     ExitCode::SUCCESS
 }
@@ -0,0 +1,83 @@
+use crate::opt::status::KanidmUnixStatusOpt;
+use crate::SparkleFlavour;
+use clap::Parser;
+use sparkle_unix_common::client::DaemonClient;
+use sparkle_unix_common::constants::DEFAULT_CONFIG_PATH;
+use sparkle_unix_common::unix_config::PamNssConfig;
+use sparkle_unix_common::unix_proto::{ClientRequest, ClientResponse};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+pub async fn main<F: SparkleFlavour>(_flavour: F) -> ExitCode {
+    let opt = KanidmUnixStatusOpt::parse();
+    if opt.debug {
+        ::std::env::set_var("RUST_LOG", "kanidm=debug,kanidm_client=debug");
+    }
+
+    sketching::tracing_subscriber::fmt::init();
+
+    let cfg = match PamNssConfig::new().read_options_from_optional_config(DEFAULT_CONFIG_PATH) {
+        Ok(c) => c,
+        Err(e) => {
+            error!("Failed to parse {}: {:?}", DEFAULT_CONFIG_PATH, e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    debug!(
+        "Using kanidm_unixd socket path: {:?}",
+        cfg.sock_path.as_str()
+    );
+
+    // see if the kanidm_unixd socket exists and quit if not
+    if !PathBuf::from(&cfg.sock_path).exists() {
+        error!(
+            "Failed to find unix socket at {}, quitting!",
+            cfg.sock_path.as_str()
+        );
+        return ExitCode::FAILURE;
+    }
+
+    let mut daemon_client =
+        match DaemonClient::new(cfg.sock_path.as_str(), cfg.unix_sock_timeout).await {
+            Ok(dc) => dc,
+            Err(err) => {
+                error!(
+                    "Failed to connect to resolver at {}-> {:?}",
+                    cfg.sock_path.as_str(),
+                    err
+                );
+                return ExitCode::FAILURE;
+            }
+        };
+
+    match daemon_client.call(ClientRequest::CacheStats, None).await {
+        Ok(ClientResponse::CacheStats(stats)) => {
+            if opt.json {
+                match serde_json::to_string_pretty(&stats) {
+                    Ok(json) => println!("{json}"),
+                    Err(err) => {
+                        error!(?err, "Unable to serialise cache stats");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            } else {
+                println!("user_hits: {}", stats.user_hits);
+                println!("user_misses: {}", stats.user_misses);
+                println!("group_hits: {}", stats.group_hits);
+                println!("group_misses: {}", stats.group_misses);
+                println!("online: {}", stats.online);
+                println!("last_online: {}", stats.last_online);
+            }
+            ExitCode::SUCCESS
+        }
+        Ok(r) => {
+            error!("Error calling kanidm_unixd: unexpected response -> {:?}", r);
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            error!("Error calling kanidm_unixd -> {:?}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -190,32 +190,70 @@ fn create_home_directory(
     #[cfg(all(target_family = "unix", feature = "selinux"))]
     labeler.set_default_context_for_fs_objects()?;
 
-    let Some(alias) = info.alias.as_ref() else {
+    if info.aliases.is_empty() {
         // No alias for the home dir, lets go.
         debug!("No home directory alias present, success.");
         return Ok(());
     };
 
-    // Sanity check the alias.
-    // let alias = alias.replace(".", "").replace("/", "").replace("\\", "");
-    let alias = alias.trim_start_matches('.').replace(['/', '\\'], "");
+    for alias in info.aliases.iter() {
+        // Sanity check the alias.
+        // let alias = alias.replace(".", "").replace("/", "").replace("\\", "");
+        let alias = alias.trim_start_matches('.').replace(['/', '\\'], "");
 
-    let alias_path = Path::join(&home_prefix_path, &alias);
+        let alias_path = Path::join(&home_prefix_path, &alias);
 
-    // Assert the resulting alias path is consistent and correct within the home_prefix.
-    if let Some(pp) = alias_path.parent() {
-        if pp != home_prefix_path {
-            return Err("Invalid home directory alias - not within home_prefix".to_string());
+        // Assert the resulting alias path is consistent and correct within the home_prefix.
+        if let Some(pp) = alias_path.parent() {
+            if pp != home_prefix_path {
+                return Err("Invalid home directory alias - not within home_prefix".to_string());
+            }
+        } else {
+            return Err("Invalid/Corrupt alias directory path - no prefix found".to_string());
         }
-    } else {
-        return Err("Invalid/Corrupt alias directory path - no prefix found".to_string());
-    }
 
-    match home_strategy {
-        HomeStrategy::Symlink => home_alias_update_symlink(&alias_path, &hd_mount_path),
-        #[cfg(target_os = "linux")]
-        HomeStrategy::BindMount => home_alias_update_bind_mount(&alias_path, &hd_mount_path),
+        match home_strategy {
+            HomeStrategy::Symlink => home_alias_update_symlink(&alias_path, &hd_mount_path)?,
+            #[cfg(target_os = "linux")]
+            HomeStrategy::BindMount => home_alias_update_bind_mount(&alias_path, &hd_mount_path)?,
+        }
     }
+
+    Ok(())
+}
+
+/// Create a home directory at the path rendered from `home_template`. Any missing parent
+/// directories are created first, and aliases are not linked.
+fn create_templated_home_directory(
+    info: &HomeDirectoryInfo,
+    home_directory: &Path,
+    home_strategy: &HomeStrategy,
+    use_etc_skel: bool,
+    use_selinux: bool,
+) -> Result<(), String> {
+    let (Some(parent), Some(name)) = (home_directory.parent(), home_directory.file_name()) else {
+        return Err("Invalid home directory from home_template - no prefix found".to_string());
+    };
+
+    fs::create_dir_all(parent).map_err(|e| {
+        error!(err = ?e, ?parent, "Unable to create home directory parent");
+        format!("{e:?}")
+    })?;
+
+    let info = HomeDirectoryInfo {
+        name: name.to_string_lossy().to_string(),
+        aliases: Vec::new(),
+        ..info.clone()
+    };
+
+    create_home_directory(
+        &info,
+        parent,
+        None,
+        home_strategy,
+        use_etc_skel,
+        use_selinux,
+    )
 }
 
 fn create_dir_path(hd_mount_path: &Path, info: &HomeDirectoryInfo) -> Result<(), String> {
@@ -447,14 +485,37 @@ async fn handle_unixd_request(
         })) => {
             debug!("Received task -> HomeDirectory({:?})", info);
 
-            match create_home_directory(
-                &info,
-                cfg.home_prefix.as_ref(),
-                cfg.home_mount_prefix.as_ref(),
-                &cfg.home_strategy,
-                cfg.use_etc_skel,
-                cfg.selinux,
-            ) {
+            // Render the same template as the home directory reported to nss.
+            let home_directory = match (&cfg.home_template, &info.template_values) {
+                (Some(home_template), Some(values)) => match home_template.render(values) {
+                    Ok(home_directory) => Some(PathBuf::from(home_directory)),
+                    Err(err) => {
+                        warn!(?err, "Unable to render home_template, using home_prefix");
+                        None
+                    }
+                },
+                _ => None,
+            };
+
+            let result = match home_directory {
+                Some(home_directory) => create_templated_home_directory(
+                    &info,
+                    &home_directory,
+                    &cfg.home_strategy,
+                    cfg.use_etc_skel,
+                    cfg.selinux,
+                ),
+                None => create_home_directory(
+                    &info,
+                    cfg.home_prefix.as_ref(),
+                    cfg.home_mount_prefix.as_ref(),
+                    &cfg.home_strategy,
+                    cfg.use_etc_skel,
+                    cfg.selinux,
+                ),
+            };
+
+            match result {
                 Ok(()) => Ok(TaskResponse::Success(id)),
                 Err(msg) => Ok(TaskResponse::Error(msg)),
             }
@@ -678,6 +739,11 @@ pub async fn main<F: SparkleFlavour>(_flavour: F) -> ExitCode {
                 }
             };
 
+            if cfg.validate().is_err() {
+                error!("Invalid configuration in {}", unixd_path_str);
+                return ExitCode::FAILURE;
+            }
+
             let task_sock_path = cfg.task_sock_path.clone();
             debug!("Attempting to use {} ...", task_sock_path);
 
@@ -1,9 +1,10 @@
 use crate::check::check_nsswitch_has_module;
+use crate::db::Db;
 use crate::opt::tool::{KanidmUnixOpt, KanidmUnixParser};
 use crate::SparkleFlavour;
 use clap::Parser;
 use sparkle_unix_common::client::DaemonClient;
-use sparkle_unix_common::constants::DEFAULT_CONFIG_PATH;
+use sparkle_unix_common::constants::{DEFAULT_CACHE_WARMUP_TIMEOUT, DEFAULT_CONFIG_PATH};
 use sparkle_unix_common::unix_config::PamNssConfig;
 use sparkle_unix_common::unix_proto::{
     ClientRequest, ClientResponse, PamAuthRequest, PamAuthResponse, PamServiceInfo,
@@ -59,6 +60,8 @@ pub async fn main<F: SparkleFlavour>(flavour: F) -> ExitCode {
         } => debug,
         KanidmUnixOpt::CacheClear { debug, really: _ } => debug,
         KanidmUnixOpt::CacheInvalidate { debug } => debug,
+        KanidmUnixOpt::Warmup { debug, groups: _ } => debug,
+        KanidmUnixOpt::MigrateCache { debug, .. } => debug,
         KanidmUnixOpt::Status { debug } => debug,
         KanidmUnixOpt::Version { debug } => debug,
     };
@@ -146,6 +149,7 @@ pub async fn main<F: SparkleFlavour>(flavour: F) -> ExitCode {
                         | ClientResponse::NssGroup(_)
                         | ClientResponse::NssGroups(_)
                         | ClientResponse::ProviderStatus(_)
+                        | ClientResponse::CacheStats(_)
                         | ClientResponse::Ok
                         | ClientResponse::PamStatus(_) => {
                             // unexpected response.
@@ -231,6 +235,56 @@ pub async fn main<F: SparkleFlavour>(flavour: F) -> ExitCode {
             println!("success");
             ExitCode::SUCCESS
         }
+        KanidmUnixOpt::Warmup { debug: _, groups } => {
+            debug!("Starting cache warmup tool ...");
+
+            let mut daemon_client = setup_client!();
+
+            let req = ClientRequest::CacheWarmup(groups);
+
+            // Fetching many accounts can take far longer than a normal request.
+            match daemon_client
+                .call(req, Some(DEFAULT_CACHE_WARMUP_TIMEOUT))
+                .await
+            {
+                Ok(ClientResponse::Ok) => {
+                    println!("success");
+                    ExitCode::SUCCESS
+                }
+                Ok(r) => {
+                    error!("Error: unexpected response -> {:?}", r);
+                    ExitCode::FAILURE
+                }
+                Err(e) => {
+                    error!("Error -> {:?}", e);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        KanidmUnixOpt::MigrateCache { debug: _, from, to } => {
+            debug!("Starting cache migrate tool ...");
+
+            if !PathBuf::from(&from).is_file() {
+                error!("Cache db {} does not exist", from);
+                return ExitCode::FAILURE;
+            }
+
+            if PathBuf::from(&to).exists() {
+                error!("Refusing to overwrite {}", to);
+                return ExitCode::FAILURE;
+            }
+
+            match Db::migrate_from(&from, &to) {
+                Ok(()) => {
+                    println!("success");
+                    ExitCode::SUCCESS
+                }
+                Err(e) => {
+                    error!("Error -> {:?}", e);
+                    ExitCode::FAILURE
+                }
+            }
+        }
         KanidmUnixOpt::Status { debug: _ } => {
             trace!("Starting cache status tool ...");
 
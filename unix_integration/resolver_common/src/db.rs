@@ -2,7 +2,7 @@ use crate::idprovider::interface::{GroupToken, Id, UserToken};
 use async_trait::async_trait;
 use kanidm_hsm_crypto::structures::{LoadableHmacS256Key, LoadableStorageKey};
 use libc::umask;
-use rusqlite::{Connection, OptionalExtension};
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
 use serde::{de::DeserializeOwned, Serialize};
 use std::convert::TryFrom;
 use std::fmt;
@@ -33,6 +33,22 @@ pub trait KeyStore {
     async fn write_keystore<'db>(&'db self) -> Self::Txn<'db>;
 }
 
+/// The namespace that a name is issued in.
+#[derive(Debug, Clone, Copy)]
+pub enum NameKind {
+    User,
+    Group,
+}
+
+impl NameKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            NameKind::User => "user",
+            NameKind::Group => "group",
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum CacheError {
     Cryptography,
@@ -114,6 +130,31 @@ impl Db {
             conn: Mutex::new(conn),
         })
     }
+
+    /// Copy the cache db at `from` to the new path `to`, which must not exist yet.
+    /// The copy is taken with `VACUUM INTO` so that any content still in the
+    /// write ahead log of `from` is included. `from` is not modified.
+    pub fn migrate_from(from: &str, to: &str) -> Result<(), DbError> {
+        if std::path::Path::new(to).exists() {
+            error!(?to, "refusing to migrate cache db over an existing file");
+            return Err(DbError::Sqlite);
+        }
+
+        let conn =
+            Connection::open_with_flags(from, OpenFlags::SQLITE_OPEN_READ_WRITE).map_err(|e| {
+                error!(err = ?e, ?from, "unable to open cache db to migrate");
+                DbError::Sqlite
+            })?;
+
+        let before = unsafe { umask(0o0027) };
+        let result = conn.execute("VACUUM INTO ?1", [to]);
+        let _ = unsafe { umask(before) };
+
+        result.map(|_| ()).map_err(|e| {
+            error!(err = ?e, ?from, ?to, "unable to migrate cache db");
+            DbError::Sqlite
+        })
+    }
 }
 
 #[async_trait]
@@ -453,9 +494,6 @@ impl DbTxn<'_> {
                     [],
                 )
                 .map_err(|e| self.sqlite_error("hsm_data_t create error", &e))?;
-
-            // Since this is the 0th migration, we have to reset the HSM here.
-            self.clear_hsm()?;
         }
 
         if db_version < 2 {
@@ -530,7 +568,62 @@ impl DbTxn<'_> {
                 .map_err(|e| self.sqlite_error("memberof_t a_uuid index create", &e))?;
         }
 
-        self.set_db_version(DBV_MAIN, 2)?;
+        if db_version < 3 {
+            // Names that have been issued to an account or group when the spn is only
+            // used on collision. A name stays with the uuid it was issued to, and is never
+            // issued to another uuid, so these are also the names that would collide.
+            self.conn
+                .execute(
+                    "CREATE TABLE IF NOT EXISTS issued_name_t (
+                    kind TEXT NOT NULL,
+                    name TEXT NOT NULL,
+                    uuid TEXT NOT NULL,
+                    PRIMARY KEY (kind, name),
+                    UNIQUE (kind, uuid)
+                )
+                ",
+                    [],
+                )
+                .map_err(|e| self.sqlite_error("issued_name_t create", &e))?;
+        }
+
+        if db_version < 4 {
+            // The names and spns of the groups each account is a member of. This allows
+            // the members of a group to be found by name when the group itself may not be
+            // cached, such as for kanidm_to_local mappings.
+            self.conn
+                .execute(
+                    "CREATE TABLE IF NOT EXISTS memberof_name_t (
+                    a_uuid TEXT NOT NULL,
+                    g_name TEXT NOT NULL,
+                    PRIMARY KEY (a_uuid, g_name),
+                    FOREIGN KEY(a_uuid) REFERENCES account_t(uuid) ON DELETE CASCADE
+                )
+                ",
+                    [],
+                )
+                .map_err(|e| self.sqlite_error("memberof_name_t create", &e))?;
+
+            self.conn
+                .execute(
+                    "CREATE INDEX IF NOT EXISTS memberof_name_t_g_name_idx ON memberof_name_t ( g_name )",
+                    [],
+                )
+                .map_err(|e| self.sqlite_error("memberof_name_t g_name index create", &e))?;
+
+            // Index the memberships of the accounts that are already cached.
+            for (account, _) in self.get_accounts()? {
+                self.update_memberof_names(&account)?;
+            }
+        }
+
+        // Since this is the 0th migration, we have to reset the HSM here. This is done
+        // once every table exists, as clearing the HSM also clears the cache.
+        if db_version < 1 {
+            self.clear_hsm()?;
+        }
+
+        self.set_db_version(DBV_MAIN, 4)?;
 
         Ok(())
     }
@@ -562,11 +655,37 @@ impl DbTxn<'_> {
         Ok(())
     }
 
+    pub fn invalidate_uuids(&mut self, uuids: &[Uuid]) -> Result<(), CacheError> {
+        for uuid in uuids {
+            let uuid = uuid.as_hyphenated().to_string();
+
+            self.conn
+                .execute(
+                    "UPDATE group_t SET expiry = 0 WHERE uuid = :uuid",
+                    params![&uuid],
+                )
+                .map_err(|e| self.sqlite_error("update group_t", &e))?;
+
+            self.conn
+                .execute(
+                    "UPDATE account_t SET expiry = 0 WHERE uuid = :uuid",
+                    params![&uuid],
+                )
+                .map_err(|e| self.sqlite_error("update account_t", &e))?;
+        }
+
+        Ok(())
+    }
+
     pub fn clear(&mut self) -> Result<(), CacheError> {
         self.conn
             .execute("DELETE FROM memberof_t", [])
             .map_err(|e| self.sqlite_error("delete memberof_t", &e))?;
 
+        self.conn
+            .execute("DELETE FROM memberof_name_t", [])
+            .map_err(|e| self.sqlite_error("delete memberof_name_t", &e))?;
+
         self.conn
             .execute("DELETE FROM group_t", [])
             .map_err(|e| self.sqlite_error("delete group_t", &e))?;
@@ -682,6 +801,82 @@ impl DbTxn<'_> {
         .map_err(|e| self.sqlite_error("execute", &e))
     }
 
+    /// Issue a name to this uuid, and return the name it holds. A uuid keeps the name it
+    /// was first issued until it is renamed, so `name` is only issued if the uuid holds no
+    /// name yet, `name` is not reserved, and no other uuid holds `name`. Otherwise
+    /// `fallback` is issued.
+    #[instrument(level = "debug", skip_all)]
+    pub fn issue_name(
+        &mut self,
+        kind: NameKind,
+        uuid: Uuid,
+        name: &str,
+        fallback: &str,
+        name_reserved: bool,
+    ) -> Result<String, CacheError> {
+        let uuid = uuid.as_hyphenated().to_string();
+
+        let issued: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT name FROM issued_name_t WHERE kind = :kind AND uuid = :uuid",
+                named_params! {
+                    ":kind": kind.as_str(),
+                    ":uuid": &uuid,
+                },
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| self.sqlite_error("select issued_name_t", &e))?;
+
+        match issued {
+            Some(issued) if issued == name || issued == fallback => return Ok(issued),
+            Some(_) => {
+                // The entry was renamed, so the name it held is released.
+                self.conn
+                    .execute(
+                        "DELETE FROM issued_name_t WHERE kind = :kind AND uuid = :uuid",
+                        named_params! {
+                            ":kind": kind.as_str(),
+                            ":uuid": &uuid,
+                        },
+                    )
+                    .map_err(|e| self.sqlite_error("delete issued_name_t", &e))?;
+            }
+            None => {}
+        }
+
+        let held_by_other: bool = self
+            .conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM issued_name_t WHERE kind = :kind AND name = :name)",
+                named_params! {
+                    ":kind": kind.as_str(),
+                    ":name": name,
+                },
+                |row| row.get(0),
+            )
+            .map_err(|e| self.sqlite_error("select issued_name_t", &e))?;
+
+        let issue = if name_reserved || held_by_other {
+            fallback
+        } else {
+            name
+        };
+
+        self.conn
+            .execute(
+                "INSERT INTO issued_name_t (kind, name, uuid) VALUES (:kind, :name, :uuid)",
+                named_params! {
+                    ":kind": kind.as_str(),
+                    ":name": issue,
+                    ":uuid": &uuid,
+                },
+            )
+            .map(|_| issue.to_string())
+            .map_err(|e| self.sqlite_error("insert issued_name_t", &e))
+    }
+
     #[instrument(level = "debug", skip_all)]
     pub fn get_account(&mut self, account_id: &Id) -> Result<Option<(UserToken, u64)>, CacheError> {
         let data = match account_id {
@@ -718,16 +913,17 @@ impl DbTxn<'_> {
     }
 
     #[instrument(level = "debug", skip_all)]
-    pub fn get_accounts(&mut self) -> Result<Vec<UserToken>, CacheError> {
+    pub fn get_accounts(&mut self) -> Result<Vec<(UserToken, u64)>, CacheError> {
         let mut stmt = self
             .conn
-            .prepare("SELECT token FROM account_t")
+            .prepare("SELECT token, expiry FROM account_t")
             .map_err(|e| self.sqlite_error("select prepare", &e))?;
 
+        // Makes tuple (token, expiry)
         let data_iter = stmt
-            .query_map([], |row| row.get(0))
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
             .map_err(|e| self.sqlite_error("query_map", &e))?;
-        let data: Result<Vec<Vec<u8>>, _> = data_iter
+        let data: Result<Vec<(Vec<u8>, i64)>, _> = data_iter
             .map(|v| v.map_err(|e| self.sqlite_error("map", &e)))
             .collect();
 
@@ -736,13 +932,19 @@ impl DbTxn<'_> {
         Ok(data
             .iter()
             // We filter map here so that anything invalid is skipped.
-            .filter_map(|token| {
+            .filter_map(|(token, expiry)| {
                 // token convert with json.
-                serde_json::from_slice(token.as_slice())
+                let token = serde_json::from_slice(token.as_slice())
                     .map_err(|e| {
                         warn!("get_accounts json error -> {:?}", e);
                     })
-                    .ok()
+                    .ok()?;
+                let expiry = u64::try_from(*expiry)
+                    .map_err(|e| {
+                        warn!("get_accounts u64 convert error -> {:?}", e);
+                    })
+                    .ok()?;
+                Some((token, expiry))
             })
             .collect())
     }
@@ -841,7 +1043,47 @@ impl DbTxn<'_> {
                 trace!("insert membership -> {:?}", r);
             })
             .map_err(|error| self.sqlite_transaction_error(&error, &stmt))
-        })
+        })?;
+
+        self.update_memberof_names(account)
+    }
+
+    /// Replace the names and spns of the groups this account is a member of.
+    fn update_memberof_names(&self, account: &UserToken) -> Result<(), CacheError> {
+        let account_uuid = account.uuid.as_hyphenated().to_string();
+
+        let mut stmt = self
+            .conn
+            .prepare("DELETE FROM memberof_name_t WHERE a_uuid = :a_uuid")
+            .map_err(|e| self.sqlite_error("prepare", &e))?;
+
+        stmt.execute([&account_uuid])
+            .map(|r| {
+                trace!("delete membership names -> {:?}", r);
+            })
+            .map_err(|error| self.sqlite_transaction_error(&error, &stmt))?;
+
+        let mut stmt = self
+            .conn
+            .prepare(
+                "INSERT OR IGNORE INTO memberof_name_t (a_uuid, g_name) VALUES (:a_uuid, :g_name)",
+            )
+            .map_err(|e| self.sqlite_error("prepare", &e))?;
+
+        account
+            .groups
+            .iter()
+            .flat_map(|g| [g.name.as_str(), g.spn.as_str()])
+            .try_for_each(|g_name| {
+                stmt.execute(named_params! {
+                    ":a_uuid": &account_uuid,
+                    ":g_name": g_name,
+                })
+                .map(|r| {
+                    trace!("insert membership name -> {:?}", r);
+                })
+                .map_err(|error| self.sqlite_transaction_error(&error, &stmt))
+            })
     }
 
     #[instrument(level = "debug", skip_all)]
@@ -856,6 +1098,14 @@ impl DbTxn<'_> {
             .map(|_| ())
             .map_err(|e| self.sqlite_error("account_t memberof_t cascade delete", &e))?;
 
+        self.conn
+            .execute(
+                "DELETE FROM memberof_name_t WHERE a_uuid = :a_uuid",
+                params![&account_uuid],
+            )
+            .map(|_| ())
+            .map_err(|e| self.sqlite_error("account_t memberof_name_t cascade delete", &e))?;
+
         self.conn
             .execute(
                 "DELETE FROM account_t WHERE uuid = :a_uuid",
@@ -930,17 +1180,54 @@ impl DbTxn<'_> {
             .collect()
     }
 
+    /// The accounts that are members of any group with one of these names or spns.
+    #[instrument(level = "debug", skip_all)]
+    pub fn get_group_members_by_name(
+        &mut self,
+        names: &[&str],
+    ) -> Result<Vec<UserToken>, CacheError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT account_t.token FROM (account_t, memberof_name_t) WHERE account_t.uuid = memberof_name_t.a_uuid AND memberof_name_t.g_name = :g_name")
+            .map_err(|e| {
+                self.sqlite_error("select prepare", &e)
+            })?;
+
+        let mut data: Vec<Vec<u8>> = Vec::new();
+        for name in names {
+            let data_iter = stmt
+                .query_map([name], |row| row.get(0))
+                .map_err(|e| self.sqlite_error("query_map", &e))?;
+            for token in data_iter {
+                data.push(token.map_err(|e| self.sqlite_error("map", &e))?);
+            }
+        }
+        // An account that is a member of more than one of these groups is only returned once.
+        data.sort_unstable();
+        data.dedup();
+
+        data.iter()
+            .map(|token| {
+                serde_json::from_slice(token.as_slice()).map_err(|e| {
+                    error!("json error -> {:?}", e);
+                    CacheError::SerdeJson
+                })
+            })
+            .collect()
+    }
+
     #[instrument(level = "debug", skip_all)]
-    pub fn get_groups(&mut self) -> Result<Vec<GroupToken>, CacheError> {
+    pub fn get_groups(&mut self) -> Result<Vec<(GroupToken, u64)>, CacheError> {
         let mut stmt = self
             .conn
-            .prepare("SELECT token FROM group_t")
+            .prepare("SELECT token, expiry FROM group_t")
             .map_err(|e| self.sqlite_error("select prepare", &e))?;
 
+        // Makes tuple (token, expiry)
         let data_iter = stmt
-            .query_map([], |row| row.get(0))
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
             .map_err(|e| self.sqlite_error("query_map", &e))?;
-        let data: Result<Vec<Vec<u8>>, _> = data_iter
+        let data: Result<Vec<(Vec<u8>, i64)>, _> = data_iter
             .map(|v| v.map_err(|e| self.sqlite_error("map", &e)))
             .collect();
 
@@ -948,14 +1235,20 @@ impl DbTxn<'_> {
 
         Ok(data
             .iter()
-            .filter_map(|token| {
+            .filter_map(|(token, expiry)| {
                 // token convert with json.
                 // trace!("{:?}", token);
-                serde_json::from_slice(token.as_slice())
+                let token = serde_json::from_slice(token.as_slice())
                     .map_err(|e| {
                         error!("json error -> {:?}", e);
                     })
-                    .ok()
+                    .ok()?;
+                let expiry = u64::try_from(*expiry)
+                    .map_err(|e| {
+                        error!("u64 convert error -> {:?}", e);
+                    })
+                    .ok()?;
+                Some((token, expiry))
             })
             .collect())
     }
@@ -1030,9 +1323,53 @@ impl Drop for DbTxn<'_> {
 
 #[cfg(test)]
 mod tests {
-    use super::{Cache, Db};
+    use super::{Cache, Db, NameKind};
     use crate::idprovider::interface::{GroupToken, Id, ProviderOrigin, UserToken};
 
+    #[tokio::test]
+    async fn test_cache_db_migrate_from() {
+        sketching::test_init();
+        let tmp_dir = tempfile::tempdir().expect("failed to create tempdir");
+        let old_path = tmp_dir.path().join("old.cache.db");
+        let new_path = tmp_dir.path().join("new.cache.db");
+        let old_path = old_path.to_str().expect("invalid path");
+        let new_path = new_path.to_str().expect("invalid path");
+
+        let ut1 = UserToken {
+            provider: ProviderOrigin::System,
+            name: "testuser".to_string(),
+            spn: "testuser@example.com".to_string(),
+            displayname: "Test User".to_string(),
+            gidnumber: 2000,
+            uuid: uuid::uuid!("0302b99c-f0f6-41ab-9492-852692b0fd16"),
+            shell: None,
+            groups: Vec::new(),
+            sshkeys: Vec::new(),
+            valid: true,
+            extra_keys: Default::default(),
+        };
+
+        // Leave the old db open, so the account may still be in its wal.
+        let old_db = Db::new(old_path).expect("failed to create.");
+        let mut dbtxn = old_db.write().await;
+        assert!(dbtxn.migrate().is_ok());
+        dbtxn.update_account(&ut1, 0).unwrap();
+        assert!(dbtxn.commit().is_ok());
+
+        assert!(Db::migrate_from(old_path, new_path).is_ok());
+        // The new path now exists, so it's never overwritten.
+        assert!(Db::migrate_from(old_path, new_path).is_err());
+        drop(old_db);
+
+        let new_db = Db::new(new_path).expect("failed to open.");
+        let mut dbtxn = new_db.write().await;
+        assert!(dbtxn.migrate().is_ok());
+        let r1 = dbtxn
+            .get_account(&Id::Name("testuser".to_string()))
+            .unwrap();
+        assert!(r1.is_some());
+    }
+
     #[tokio::test]
     async fn test_cache_db_account_basic() {
         sketching::test_init();
@@ -1253,6 +1590,14 @@ mod tests {
         assert_eq!(m1[0].name, "testuser");
         assert_eq!(m2[0].name, "testuser");
 
+        // Members can also be found by the name or spn of the group, and are only
+        // returned once when they match more than one name.
+        let m3 = dbtxn
+            .get_group_members_by_name(&["testgroup", "testgroup@example.com", "testuser"])
+            .unwrap();
+        assert_eq!(m3.len(), 1);
+        assert_eq!(m3[0].name, "testuser");
+
         // Now alter testuser, remove gt2, update.
         ut1.groups = vec![gt1];
         dbtxn.update_account(&ut1, 0).unwrap();
@@ -1267,6 +1612,11 @@ mod tests {
         assert_eq!(m1[0].name, "testuser");
         assert!(m2.is_empty());
 
+        let m3 = dbtxn
+            .get_group_members_by_name(&["testgroup", "testgroup@example.com"])
+            .unwrap();
+        assert!(m3.is_empty());
+
         assert!(dbtxn.commit().is_ok());
     }
 
@@ -1421,4 +1771,119 @@ mod tests {
 
         assert!(dbtxn.commit().is_ok());
     }
+
+    #[tokio::test]
+    async fn test_cache_db_issue_name() {
+        sketching::test_init();
+        let db = Db::new("").expect("failed to create.");
+        let mut dbtxn = db.write().await;
+        assert!(dbtxn.migrate().is_ok());
+
+        let uuid_a = uuid::uuid!("0302b99c-f0f6-41ab-9492-852692b0fd16");
+        let uuid_b = uuid::uuid!("799123b2-3802-4b19-b0b8-1ffae2aa9a4b");
+
+        // The first holder of a name is issued it.
+        let name = dbtxn
+            .issue_name(
+                NameKind::User,
+                uuid_a,
+                "testuser",
+                "testuser@a.example.com",
+                false,
+            )
+            .unwrap();
+        assert_eq!(name, "testuser");
+
+        // A second uuid with the same name is given the fallback.
+        let name = dbtxn
+            .issue_name(
+                NameKind::User,
+                uuid_b,
+                "testuser",
+                "testuser@b.example.com",
+                false,
+            )
+            .unwrap();
+        assert_eq!(name, "testuser@b.example.com");
+
+        // A reserved name is never issued.
+        let uuid_c = uuid::uuid!("b9d6b8a5-2c0b-4a38-a4a5-0b1c2e5f7a10");
+        let name = dbtxn
+            .issue_name(NameKind::User, uuid_c, "other", "other@c.example.com", true)
+            .unwrap();
+        assert_eq!(name, "other@c.example.com");
+
+        // Names are issued per namespace.
+        let name = dbtxn
+            .issue_name(
+                NameKind::Group,
+                uuid_b,
+                "testuser",
+                "testuser@b.example.com",
+                false,
+            )
+            .unwrap();
+        assert_eq!(name, "testuser");
+
+        assert!(dbtxn.commit().is_ok());
+
+        // The issued names persist, and do not change when asked again. This holds
+        // even once a name becomes reserved, or is free again.
+        let mut dbtxn = db.write().await;
+        let name = dbtxn
+            .issue_name(
+                NameKind::User,
+                uuid_a,
+                "testuser",
+                "testuser@a.example.com",
+                true,
+            )
+            .unwrap();
+        assert_eq!(name, "testuser");
+        let name = dbtxn
+            .issue_name(
+                NameKind::User,
+                uuid_b,
+                "testuser",
+                "testuser@b.example.com",
+                false,
+            )
+            .unwrap();
+        assert_eq!(name, "testuser@b.example.com");
+        let name = dbtxn
+            .issue_name(
+                NameKind::User,
+                uuid_c,
+                "other",
+                "other@c.example.com",
+                false,
+            )
+            .unwrap();
+        assert_eq!(name, "other@c.example.com");
+
+        // A rename releases the old name, so it can be issued to another uuid.
+        let name = dbtxn
+            .issue_name(
+                NameKind::User,
+                uuid_a,
+                "renamed",
+                "renamed@a.example.com",
+                false,
+            )
+            .unwrap();
+        assert_eq!(name, "renamed");
+
+        let uuid_d = uuid::uuid!("5e0d3c5a-7a0e-4f7f-9c3e-3f0b6f1f2d44");
+        let name = dbtxn
+            .issue_name(
+                NameKind::User,
+                uuid_d,
+                "testuser",
+                "testuser@d.example.com",
+                false,
+            )
+            .unwrap();
+        assert_eq!(name, "testuser");
+        assert!(dbtxn.commit().is_ok());
+    }
 }
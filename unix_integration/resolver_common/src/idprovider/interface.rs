@@ -217,6 +217,9 @@ pub trait IdProvider {
     /// not attempt to go online.
     async fn is_online(&self) -> bool;
 
+    /// The last time this provider was known to be online, if ever.
+    async fn last_online(&self) -> Option<SystemTime>;
+
     /// Mark that this provider should attempt to go online next time it
     /// receives a request
     async fn mark_next_check(&self, _now: SystemTime);
@@ -228,6 +231,9 @@ pub trait IdProvider {
     /// with remote members.
     fn has_map_group(&self, local: &str) -> Option<&Id>;
 
+    /// The groups of this provider whose members are added to this local system group.
+    fn local_to_kanidm(&self, local: &str) -> &[String];
+
     // This is similar to a "domain join" process. What do we actually need to pass here
     // for this to work for kanidm or himmelblau? Should we make it take a generic?
     /*
@@ -319,4 +325,18 @@ pub trait IdProvider {
         _tpm: &mut BoxedDynTpm,
         _now: SystemTime,
     ) -> Result<GroupTokenState, IdpError>;
+
+    /// The groups whose members should be cached by a warmup when no groups are
+    /// requested.
+    async fn unix_warmup_groups(&self) -> Vec<String>;
+
+    /// Retrieve the account names of the members of this group, so that they can be
+    /// cached ahead of their first use. If the provider is offline, no members are
+    /// returned.
+    async fn unix_group_members(
+        &self,
+        _id: &Id,
+        _tpm: &mut BoxedDynTpm,
+        _now: SystemTime,
+    ) -> Result<Vec<String>, IdpError>;
 }
@@ -41,6 +41,7 @@ enum CacheState {
 
 struct KanidmProviderInternal {
     state: CacheState,
+    last_online: Option<SystemTime>,
     client: KanidmClient,
     hmac_key: HmacS256Key,
     crypto_policy: CryptoPolicy,
@@ -53,6 +54,9 @@ pub struct KanidmProvider {
     // Because this value doesn't change, to support fast
     // lookup we store the extension map here.
     map_group: HashMap<String, Id>,
+    // The kanidm groups whose members are added to a local group, keyed by
+    // the local group name.
+    local_to_kanidm: HashMap<String, Vec<String>>,
 }
 
 impl KanidmProvider {
@@ -110,6 +114,14 @@ impl KanidmProvider {
             .map(|GroupMap { local, with }| (local, Id::Name(with)))
             .collect();
 
+        let mut local_to_kanidm: HashMap<String, Vec<String>> = HashMap::new();
+        for GroupMap { local, with } in config.kanidm_to_local.iter().cloned() {
+            let groups = local_to_kanidm.entry(local).or_default();
+            if !groups.contains(&with) {
+                groups.push(with);
+            }
+        }
+
         // Set the api token if one is set
         if let Some(token) = config.service_account_token.clone() {
             client.set_token(token).await;
@@ -119,6 +131,7 @@ impl KanidmProvider {
         Ok(KanidmProvider {
             inner: Mutex::new(KanidmProviderInternal {
                 state: CacheState::OfflineNextCheck(now),
+                last_online: None,
                 client,
                 hmac_key,
                 crypto_policy,
@@ -126,6 +139,7 @@ impl KanidmProvider {
                 bearer_token_set,
             }),
             map_group,
+            local_to_kanidm,
         })
     }
 }
@@ -270,7 +284,10 @@ impl KanidmProviderInternal {
     async fn check_online(&mut self, tpm: &mut BoxedDynTpm, now: SystemTime) -> bool {
         match self.state {
             // Proceed
-            CacheState::Online => true,
+            CacheState::Online => {
+                self.last_online = Some(now);
+                true
+            }
             CacheState::OfflineNextCheck(at_time) if now >= at_time => {
                 self.attempt_online(tpm, now).await
             }
@@ -281,7 +298,10 @@ impl KanidmProviderInternal {
     #[instrument(level = "debug", skip_all)]
     async fn check_online_right_meow(&mut self, tpm: &mut BoxedDynTpm, now: SystemTime) -> bool {
         match self.state {
-            CacheState::Online => true,
+            CacheState::Online => {
+                self.last_online = Some(now);
+                true
+            }
             CacheState::OfflineNextCheck(_) => self.attempt_online(tpm, now).await,
             CacheState::Offline => false,
         }
@@ -310,6 +330,7 @@ impl KanidmProviderInternal {
                 Ok(_uat) => {
                     debug!("provider is now online");
                     self.state = CacheState::Online;
+                    self.last_online = Some(now);
                     return true;
                 }
                 Err(ClientError::Http(StatusCode::UNAUTHORIZED, reason, opid)) => {
@@ -349,6 +370,11 @@ impl IdProvider for KanidmProvider {
         inner.is_online().await
     }
 
+    async fn last_online(&self) -> Option<SystemTime> {
+        let inner = self.inner.lock().await;
+        inner.last_online
+    }
+
     async fn mark_next_check(&self, now: SystemTime) {
         let mut inner = self.inner.lock().await;
         inner.state = CacheState::OfflineNextCheck(now);
@@ -358,6 +384,13 @@ impl IdProvider for KanidmProvider {
         self.map_group.get(local)
     }
 
+    fn local_to_kanidm(&self, local: &str) -> &[String] {
+        self.local_to_kanidm
+            .get(local)
+            .map(|groups| groups.as_slice())
+            .unwrap_or_default()
+    }
+
     async fn mark_offline(&self) {
         let mut inner = self.inner.lock().await;
         inner.state = CacheState::Offline;
@@ -746,6 +779,58 @@ impl IdProvider for KanidmProvider {
         }
     }
 
+    async fn unix_warmup_groups(&self) -> Vec<String> {
+        let inner = self.inner.lock().await;
+        inner.pam_allow_groups.iter().cloned().collect()
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    async fn unix_group_members(
+        &self,
+        id: &Id,
+        tpm: &mut BoxedDynTpm,
+        now: SystemTime,
+    ) -> Result<Vec<String>, IdpError> {
+        let mut inner = self.inner.lock().await;
+
+        if !inner.check_online(tpm, now).await {
+            // We are offline, there is nothing we can warm up.
+            return Ok(Vec::with_capacity(0));
+        }
+
+        match inner
+            .client
+            .idm_group_get_members(id.to_string().as_str())
+            .await
+        {
+            Ok(members) => Ok(members.unwrap_or_default()),
+            // Offline?
+            Err(ClientError::Transport(err)) => {
+                error!(?err, "transport error");
+                inner.state = CacheState::OfflineNextCheck(next_offline_check(now));
+                Ok(Vec::with_capacity(0))
+            }
+            Err(ClientError::Http(StatusCode::UNAUTHORIZED, reason, opid)) => {
+                error!(?reason, ?opid, "authentication error, moving to offline");
+                inner.state = CacheState::OfflineNextCheck(next_offline_check(now));
+                Ok(Vec::with_capacity(0))
+            }
+            Err(ClientError::Http(StatusCode::NOT_FOUND, _, opid))
+            | Err(ClientError::Http(
+                StatusCode::BAD_REQUEST,
+                Some(OperationError::NoMatchingEntries),
+                opid,
+            )) => {
+                debug!(?opid, "group does not exist");
+                Err(IdpError::NotFound)
+            }
+            Err(err) => {
+                error!(?err, "client error");
+                Err(IdpError::BadRequest)
+            }
+        }
+    }
+
     async fn unix_user_authorise(&self, token: &UserToken) -> Result<Option<bool>, IdpError> {
         let inner = self.inner.lock().await;
 
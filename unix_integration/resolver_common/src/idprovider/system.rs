@@ -10,7 +10,7 @@ use time::OffsetDateTime;
 use tokio::sync::Mutex;
 
 // The minimum GID that Kanidm will consider for creating a UPG
-const SYSTEM_GID_BOUNDARY: u32 = 1000;
+pub(crate) const SYSTEM_GID_BOUNDARY: u32 = 1000;
 
 pub struct SystemProviderInternal {
     users: HashMap<Id, Arc<EtcUser>>,
@@ -316,6 +316,11 @@ impl SystemProvider {
         }
     }
 
+    pub async fn contains_account(&self, account_id: &Id) -> bool {
+        let inner = self.inner.lock().await;
+        inner.users.contains_key(account_id)
+    }
+
     pub async fn contains_group(&self, account_id: &Id) -> bool {
         let inner = self.inner.lock().await;
         inner.groups.contains_key(account_id)
@@ -1,7 +1,9 @@
 pub mod ssh_authorisedkeys;
+pub mod status;
 pub mod tool;
 
 pub use self::{
     ssh_authorisedkeys::SshAuthorisedKeysOpt,
+    status::KanidmUnixStatusOpt,
     tool::{KanidmUnixOpt, KanidmUnixParser},
 };
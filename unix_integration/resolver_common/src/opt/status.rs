@@ -0,0 +1,12 @@
+use clap::Parser;
+
+#[derive(Debug, Parser)]
+#[command(name = "kanidm-unix-status")]
+/// Show the cache statistics of the kanidm unixd resolver.
+pub struct KanidmUnixStatusOpt {
+    #[clap(short, long)]
+    pub debug: bool,
+    /// Print the statistics as json.
+    #[clap(long)]
+    pub json: bool,
+}
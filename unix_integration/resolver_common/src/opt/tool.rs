@@ -28,6 +28,26 @@ pub enum KanidmUnixOpt {
         #[clap(short, long)]
         debug: bool,
     },
+    /// Fetch the members of these groups into the unixd resolver cache, so that their first
+    /// login does not need to contact the kanidmd server. If no groups are given, the
+    /// `pam_allowed_login_groups` are used. Accounts that are still valid in the cache are
+    /// not fetched again.
+    Warmup {
+        #[clap(short, long)]
+        debug: bool,
+        #[clap(long, value_delimiter = ',')]
+        groups: Vec<String>,
+    },
+    /// Copy the unixd resolver cache db to a new path, such as when changing `cache_db_path`.
+    /// The unixd daemon should be stopped first, and the new path must not exist.
+    MigrateCache {
+        #[clap(short, long)]
+        debug: bool,
+        #[clap(long)]
+        from: String,
+        #[clap(long)]
+        to: String,
+    },
     /// Check that the unixd daemon is online and able to connect correctly to the kanidmd server.
     Status {
         #[clap(short, long)]
@@ -1,5 +1,5 @@
 // use async_trait::async_trait;
-use crate::db::{Cache, Db};
+use crate::db::{Cache, Db, NameKind};
 use crate::idprovider::interface::{
     AuthCredHandler,
     AuthResult,
@@ -15,6 +15,7 @@ use crate::idprovider::interface::{
 };
 use crate::idprovider::system::{
     Shadow, SystemAuthResult, SystemProvider, SystemProviderAuthInit, SystemProviderSession,
+    SYSTEM_GID_BOUNDARY,
 };
 use hashbrown::HashMap;
 use kanidm_hsm_crypto::provider::BoxedDynTpm;
@@ -24,19 +25,23 @@ use sparkle_unix_common::constants::{
     DEFAULT_CACHE_TIMEOUT_JITTER_MS, DEFAULT_CACHE_TIMEOUT_MAXIMUM, DEFAULT_CACHE_TIMEOUT_MINIMUM,
     DEFAULT_SHELL_SEARCH_PATHS, SYSTEM_SHADOW_PATH,
 };
-use sparkle_unix_common::unix_config::{HomeAttr, UidAttr};
+use sparkle_unix_common::unix_config::{
+    EnumerationMode, GecosAttr, HomeAttr, HomeTemplate, UidAttr,
+};
 use sparkle_unix_common::unix_passwd::{EtcGroup, EtcShadow, EtcUser};
 use sparkle_unix_common::unix_proto::{
-    HomeDirectoryInfo, NssGroup, NssUser, PamAuthRequest, PamAuthResponse, PamServiceInfo,
-    ProviderStatus,
+    CacheStats, HomeDirectoryInfo, HomeTemplateValues, NssGroup, NssUser, PamAuthRequest,
+    PamAuthResponse, PamServiceInfo, ProviderStatus,
 };
+use std::collections::BTreeSet;
 use std::fmt::Display;
 use std::num::NonZeroUsize;
 use std::ops::DerefMut;
 use std::path::{Path, PathBuf};
 use std::string::ToString;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use time::OffsetDateTime;
 use tokio::sync::broadcast;
 use tokio::sync::mpsc;
@@ -98,6 +103,29 @@ enum ExpiryState {
     Expired,
 }
 
+/// The nss name of a user or group, before any names that must be issued from the
+/// cache db have been issued.
+enum NssName {
+    Fixed(String),
+    Issue {
+        kind: NameKind,
+        uuid: Uuid,
+        name: String,
+        spn: String,
+        reserved: bool,
+    },
+}
+
+impl NssName {
+    /// The name to use if this name can't be issued.
+    fn into_fallback(self) -> String {
+        match self {
+            NssName::Fixed(name) => name,
+            NssName::Issue { spn, .. } => spn,
+        }
+    }
+}
+
 pub struct Resolver {
     // Generic / modular types.
     db: Db,
@@ -116,15 +144,31 @@ pub struct Resolver {
     primary_origin: ProviderOrigin,
 
     timeout_seconds: u64,
+    negative_timeout_seconds: u64,
     async_refresh_seconds: u64,
     default_shell: String,
     home_prefix: PathBuf,
     home_attr: HomeAttr,
-    home_alias: Option<HomeAttr>,
+    home_aliases: Vec<HomeAttr>,
+    home_template: Option<HomeTemplate>,
     uid_attr_map: UidAttr,
     gid_attr_map: UidAttr,
+    gid_allow_range_min: Option<u32>,
+    gid_allow_range_max: Option<u32>,
+    gecos_attr: GecosAttr,
+    include_expired: bool,
+    nss_enumeration: EnumerationMode,
     nxcache: Mutex<LruCache<Id, SystemTime>>,
     async_refresh_tx: mpsc::Sender<Id>,
+    counters: CacheCounters,
+}
+
+#[derive(Default)]
+struct CacheCounters {
+    user_hits: AtomicU64,
+    user_misses: AtomicU64,
+    group_hits: AtomicU64,
+    group_misses: AtomicU64,
 }
 
 impl Display for Id {
@@ -144,12 +188,19 @@ impl Resolver {
         clients: Vec<Arc<dyn IdProvider + Sync + Send>>,
         hsm: BoxedDynTpm,
         timeout_seconds: u64,
+        negative_timeout_seconds: u64,
         default_shell: String,
         home_prefix: PathBuf,
         home_attr: HomeAttr,
-        home_alias: Option<HomeAttr>,
+        home_aliases: Vec<HomeAttr>,
+        home_template: Option<HomeTemplate>,
         uid_attr_map: UidAttr,
         gid_attr_map: UidAttr,
+        gid_allow_range_min: Option<u32>,
+        gid_allow_range_max: Option<u32>,
+        gecos_attr: GecosAttr,
+        include_expired: bool,
+        nss_enumeration: EnumerationMode,
     ) -> Result<(Self, mpsc::Receiver<Id>), ()> {
         let hsm = Mutex::new(hsm);
 
@@ -171,6 +222,10 @@ impl Resolver {
             timeout_seconds.clamp(DEFAULT_CACHE_TIMEOUT_MINIMUM, DEFAULT_CACHE_TIMEOUT_MAXIMUM);
         let async_refresh_seconds = (timeout_seconds / 3) * 2;
 
+        // Negative entries must not outlive positive ones, else a newly created account
+        // could remain hidden for longer than the cache timeout.
+        let negative_timeout_seconds = negative_timeout_seconds.min(timeout_seconds);
+
         // We assume we are offline at start up, and we mark the next "online check" as
         // being valid from "now".
         Ok((
@@ -182,15 +237,23 @@ impl Resolver {
                 primary_origin,
                 client_ids,
                 timeout_seconds,
+                negative_timeout_seconds,
                 async_refresh_seconds,
                 default_shell,
                 home_prefix,
                 home_attr,
-                home_alias,
+                home_aliases,
+                home_template,
                 uid_attr_map,
                 gid_attr_map,
+                gid_allow_range_min,
+                gid_allow_range_max,
+                gecos_attr,
+                include_expired,
+                nss_enumeration,
                 nxcache: Mutex::new(LruCache::new(NXCACHE_SIZE)),
                 async_refresh_tx,
+                counters: CacheCounters::default(),
             },
             async_refresh_rx,
         ))
@@ -229,31 +292,63 @@ impl Resolver {
             .map_err(|_| ())
     }
 
+    /// Expire only the cached accounts and groups with these uuids, so that they
+    /// are refreshed on next use without waiting for their cache timeout.
     #[instrument(level = "debug", skip_all)]
-    async fn get_cached_usertokens(&self) -> Result<Vec<UserToken>, ()> {
+    pub async fn invalidate_uuids(&self, uuids: &[Uuid]) -> Result<(), ()> {
+        let mut dbtxn = self.db.write().await;
+        // A changed entry may also have been renamed or given a new uid/gid
+        // which could currently be negatively cached.
+        let mut nxcache_txn = self.nxcache.lock().await;
+        nxcache_txn.clear();
+        dbtxn
+            .invalidate_uuids(uuids)
+            .and_then(|_| dbtxn.commit())
+            .map_err(|_| ())
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    async fn get_cached_usertokens(&self) -> Result<Vec<(UserToken, u64)>, ()> {
         let mut dbtxn = self.db.write().await;
         dbtxn.get_accounts().map_err(|_| ())
     }
 
     #[instrument(level = "debug", skip_all)]
-    async fn get_cached_grouptokens(&self) -> Result<Vec<GroupToken>, ()> {
+    async fn get_cached_grouptokens(&self) -> Result<Vec<(GroupToken, u64)>, ()> {
         let mut dbtxn = self.db.write().await;
         dbtxn.get_groups().map_err(|_| ())
     }
 
+    /// If a cached entry with this cache expiry should be returned when nss
+    /// enumerates all users or groups.
+    fn nss_enumerable(&self, expiry: u64, current_time: SystemTime) -> bool {
+        let ex_time = SystemTime::UNIX_EPOCH + Duration::from_secs(expiry);
+        self.nss_enumeration
+            .includes_cached(current_time >= ex_time)
+    }
+
     #[instrument(level = "debug", skip_all)]
     async fn set_nxcache(&self, id: &Id) {
+        // A timeout of 0 disables the negative cache.
+        if self.negative_timeout_seconds == 0 {
+            return;
+        }
         let mut nxcache_txn = self.nxcache.lock().await;
         // To try and prevent too many requests occuring all at the same time, we subtract a small
-        // amount of "jitter" from expiry values so that we space out refreshes.
-        let jitter = rand::random_range(0..DEFAULT_CACHE_TIMEOUT_JITTER_MS);
-        let ex_time = SystemTime::now() + Duration::from_secs(self.timeout_seconds)
+        // amount of "jitter" from expiry values so that we space out refreshes. This is capped
+        // to a tenth of the timeout so that short negative timeouts are still honoured.
+        let jitter_max = DEFAULT_CACHE_TIMEOUT_JITTER_MS.min(self.negative_timeout_seconds * 100);
+        let jitter = rand::random_range(0..jitter_max);
+        let ex_time = SystemTime::now() + Duration::from_secs(self.negative_timeout_seconds)
             - Duration::from_millis(jitter);
         nxcache_txn.put(id.clone(), ex_time);
     }
 
     #[instrument(level = "debug", skip_all)]
     pub async fn check_nxcache(&self, id: &Id) -> Option<SystemTime> {
+        if self.negative_timeout_seconds == 0 {
+            return None;
+        }
         let mut nxcache_txn = self.nxcache.lock().await;
         nxcache_txn.get(id).copied()
     }
@@ -691,6 +786,7 @@ impl Resolver {
 
         // If the token isn't found, get_cached will set expired = true.
         if expiry_state == ExpiryState::Expired {
+            self.counters.user_misses.fetch_add(1, Ordering::Relaxed);
             self.refresh_usertoken(account_id, current_time).await
         } else {
             if expiry_state == ExpiryState::ValidRefresh {
@@ -699,6 +795,7 @@ impl Resolver {
                     debug!(?account_id, "unable to queue async refresh");
                 }
             }
+            self.counters.user_hits.fetch_add(1, Ordering::Relaxed);
             // Still valid, return the cached entry.
             Ok(item)
         }
@@ -719,8 +816,10 @@ impl Resolver {
         })?;
 
         if expired {
+            self.counters.group_misses.fetch_add(1, Ordering::Relaxed);
             self.refresh_grouptoken(&grp_id, item, current_time).await
         } else {
+            self.counters.group_hits.fetch_add(1, Ordering::Relaxed);
             // Still valid, return the cached entry.
             Ok(item)
         }
@@ -730,17 +829,65 @@ impl Resolver {
         })
     }
 
-    async fn get_groupmembers(&self, g_uuid: Uuid) -> Vec<String> {
+    /// The cached members of this group that are visible to nss.
+    async fn get_groupmember_tokens(&self, g_uuid: Uuid) -> Vec<UserToken> {
         let mut dbtxn = self.db.write().await;
-
         dbtxn
             .get_group_members(g_uuid)
             .unwrap_or_else(|_| Vec::new())
             .into_iter()
-            .map(|ut| self.token_uidattr(&ut))
+            .filter(|ut| self.nss_visible(ut))
             .collect()
     }
 
+    async fn get_groupmembers(&self, g_uuid: Uuid) -> Vec<String> {
+        let tokens = self.get_groupmember_tokens(g_uuid).await;
+        self.tokens_uidattr(&tokens).await
+    }
+
+    /// The groups whose members are added to this local system group by `kanidm_to_local`
+    /// mappings. The groups that map into this one are themselves matched against the
+    /// mappings so that they can be chained. Each group is only visited once, so a
+    /// chain that leads back to an earlier group ends there.
+    fn local_group_sources(&self, local: &str) -> BTreeSet<&str> {
+        let mut pending = vec![local];
+        let mut sources = BTreeSet::new();
+
+        while let Some(group) = pending.pop() {
+            for client in self.clients.iter() {
+                for source in client.local_to_kanidm(group) {
+                    if sources.insert(source.as_str()) {
+                        pending.push(source.as_str());
+                    }
+                }
+            }
+        }
+
+        sources
+    }
+
+    /// The members that this local system group receives from `kanidm_to_local` mappings.
+    /// Only cached accounts are considered.
+    async fn get_kanidm_to_local_members(&self, local: &str) -> Result<Vec<String>, ()> {
+        let sources: Vec<&str> = self.local_group_sources(local).into_iter().collect();
+        if sources.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut dbtxn = self.db.write().await;
+        let tokens: Vec<UserToken> = dbtxn
+            .get_group_members_by_name(&sources)
+            .map_err(|err| {
+                debug!(?err, "get_kanidm_to_local_members");
+            })?
+            .into_iter()
+            .filter(|ut| self.nss_visible(ut))
+            .collect();
+        drop(dbtxn);
+
+        Ok(self.tokens_uidattr(&tokens).await)
+    }
+
     // Get ssh keys for an account id
     #[instrument(level = "debug", skip(self))]
     pub async fn get_sshkeys(&self, account_id: &str) -> Result<Vec<String>, ()> {
@@ -760,14 +907,23 @@ impl Resolver {
             .unwrap_or_else(|| Vec::with_capacity(0)))
     }
 
-    fn token_homedirectory_alias(&self, token: &UserToken) -> Option<String> {
+    /// The home directory aliases of this token in the configured order. Aliases that
+    /// resolve to the same value, such as name and spn outside the primary origin,
+    /// are only returned once.
+    fn token_homedirectory_aliases(&self, token: &UserToken) -> Vec<String> {
         let is_primary_origin = token.provider == self.primary_origin;
-        self.home_alias.map(|t| match t {
-            // If we have an alias. use it.
-            HomeAttr::Name if is_primary_origin => token.name.as_str().to_string(),
-            HomeAttr::Uuid => token.uuid.hyphenated().to_string(),
-            HomeAttr::Spn | HomeAttr::Name => token.spn.as_str().to_string(),
-        })
+        let mut aliases: Vec<String> = Vec::with_capacity(self.home_aliases.len());
+        for home_alias in self.home_aliases.iter() {
+            let alias = match home_alias {
+                HomeAttr::Name if is_primary_origin => token.name.as_str().to_string(),
+                HomeAttr::Uuid => token.uuid.hyphenated().to_string(),
+                HomeAttr::Spn | HomeAttr::Name => token.spn.as_str().to_string(),
+            };
+            if !aliases.contains(&alias) {
+                aliases.push(alias);
+            }
+        }
+        aliases
     }
 
     fn token_homedirectory_attr(&self, token: &UserToken) -> String {
@@ -780,45 +936,177 @@ impl Resolver {
     }
 
     fn token_homedirectory(&self, token: &UserToken) -> String {
-        self.token_homedirectory_alias(token)
+        // If we have an alias, use the first one.
+        self.token_homedirectory_aliases(token)
+            .into_iter()
+            .next()
             .unwrap_or_else(|| self.token_homedirectory_attr(token))
     }
 
-    fn token_abs_homedirectory(&self, token: &UserToken) -> String {
+    fn token_home_template_values(&self, token: &UserToken, uid: &str) -> HomeTemplateValues {
+        let domain = token
+            .spn
+            .rsplit_once('@')
+            .map(|(_, domain)| domain)
+            .unwrap_or_default();
+        HomeTemplateValues {
+            uid: uid.to_string(),
+            uuid: token.uuid.hyphenated().to_string(),
+            domain: domain.to_string(),
+        }
+    }
+
+    fn token_abs_homedirectory(&self, token: &UserToken, uid: &str) -> String {
+        if let Some(home_template) = &self.home_template {
+            let values = self.token_home_template_values(token, uid);
+            match home_template.render(&values) {
+                Ok(home_directory) => return home_directory,
+                Err(err) => warn!(
+                    ?err,
+                    spn = %token.spn,
+                    "Unable to render home_template, using home_prefix"
+                ),
+            }
+        }
+
         self.home_prefix
             .join(self.token_homedirectory(token))
             .to_string_lossy()
             .to_string()
     }
 
-    fn token_uidattr(&self, token: &UserToken) -> String {
+    async fn token_uidattr(&self, token: &UserToken) -> String {
+        let name = self.token_nss_name(token).await;
+        self.resolve_nss_names(vec![name])
+            .await
+            .pop()
+            .unwrap_or_else(|| token.spn.clone())
+    }
+
+    async fn tokens_uidattr(&self, tokens: &[UserToken]) -> Vec<String> {
+        let mut names = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            names.push(self.token_nss_name(token).await);
+        }
+        self.resolve_nss_names(names).await
+    }
+
+    async fn token_nss_name(&self, token: &UserToken) -> NssName {
         let is_primary_origin = token.provider == self.primary_origin;
         match self.uid_attr_map {
-            UidAttr::Name if is_primary_origin => token.name.as_str(),
-            UidAttr::Spn | UidAttr::Name => token.spn.as_str(),
+            UidAttr::Name if is_primary_origin => NssName::Fixed(token.name.clone()),
+            UidAttr::NamePreferSpnOnCollision if is_primary_origin => {
+                let reserved = self
+                    .system_provider
+                    .contains_account(&Id::Name(token.name.clone()))
+                    .await;
+                NssName::Issue {
+                    kind: NameKind::User,
+                    uuid: token.uuid,
+                    name: token.name.clone(),
+                    spn: token.spn.clone(),
+                    reserved,
+                }
+            }
+            UidAttr::Spn | UidAttr::Name | UidAttr::NamePreferSpnOnCollision => {
+                NssName::Fixed(token.spn.clone())
+            }
+        }
+    }
+
+    /// Resolve these names, in order. Names that must be issued are issued from the
+    /// cache db in a single transaction. Once a name has been issued for an entry it is
+    /// kept until the entry is renamed, so that the name does not change when a
+    /// colliding user or group later appears or goes away. If the name is held by the
+    /// system or has already been issued to another entry, the spn is issued instead.
+    async fn resolve_nss_names(&self, names: Vec<NssName>) -> Vec<String> {
+        if names.iter().all(|name| matches!(name, NssName::Fixed(_))) {
+            return names.into_iter().map(NssName::into_fallback).collect();
+        }
+
+        let mut dbtxn = self.db.write().await;
+        let issued: Result<Vec<String>, _> = names
+            .iter()
+            .map(|name| match name {
+                NssName::Fixed(name) => Ok(name.clone()),
+                NssName::Issue {
+                    kind,
+                    uuid,
+                    name,
+                    spn,
+                    reserved,
+                } => dbtxn.issue_name(*kind, *uuid, name, spn, *reserved),
+            })
+            .collect();
+
+        issued
+            .and_then(|issued| dbtxn.commit().map(|_| issued))
+            .unwrap_or_else(|_| {
+                error!("Failed to issue names, using spns");
+                names.into_iter().map(NssName::into_fallback).collect()
+            })
+    }
+
+    /// Expired accounts are hidden from nss unless include_expired is set. This only
+    /// affects name resolution, authentication of expired accounts is always refused.
+    fn nss_visible(&self, token: &UserToken) -> bool {
+        token.valid || self.include_expired
+    }
+
+    fn token_gecos(&self, token: &UserToken) -> String {
+        let is_primary_origin = token.provider == self.primary_origin;
+        match self.gecos_attr {
+            GecosAttr::DisplayName => token.displayname.as_str(),
+            GecosAttr::Name if is_primary_origin => token.name.as_str(),
+            GecosAttr::Spn | GecosAttr::Name => token.spn.as_str(),
         }
         .to_string()
     }
 
     #[instrument(level = "debug", skip_all)]
     pub async fn get_nssaccounts(&self) -> Result<Vec<NssUser>, ()> {
+        if self.nss_enumeration == EnumerationMode::Disabled {
+            debug!("nss enumeration is disabled");
+            return Ok(Vec::new());
+        }
+
         // We don't need to filter the cached tokens as the cache shouldn't
         // have anything that collides with system.
-        let system_nss_users = self.system_provider.get_nssaccounts().await;
+        let mut r = self.system_provider.get_nssaccounts().await;
 
         let cached = self.get_cached_usertokens().await?;
 
-        Ok(system_nss_users
+        let current_time = SystemTime::now();
+
+        let tokens: Vec<UserToken> = cached
             .into_iter()
-            .chain(cached.into_iter().map(|tok| NssUser {
-                homedir: self.token_abs_homedirectory(&tok),
-                name: self.token_uidattr(&tok),
-                uid: tok.gidnumber,
-                gid: tok.gidnumber,
-                gecos: tok.displayname,
-                shell: tok.shell.unwrap_or_else(|| self.default_shell.clone()),
-            }))
-            .collect())
+            .filter(|(tok, expiry)| {
+                self.nss_visible(tok) && self.nss_enumerable(*expiry, current_time)
+            })
+            .map(|(tok, _)| tok)
+            .collect();
+
+        let names = self.tokens_uidattr(&tokens).await;
+
+        r.reserve(tokens.len());
+        r.extend(
+            tokens
+                .into_iter()
+                .zip(names)
+                .map(|(tok, name)| self.token_nssaccount(tok, name)),
+        );
+        Ok(r)
+    }
+
+    fn token_nssaccount(&self, tok: UserToken, name: String) -> NssUser {
+        NssUser {
+            homedir: self.token_abs_homedirectory(&tok, &name),
+            name,
+            uid: tok.gidnumber,
+            gid: tok.gidnumber,
+            gecos: self.token_gecos(&tok),
+            shell: tok.shell.unwrap_or_else(|| self.default_shell.clone()),
+        }
     }
 
     #[instrument(level = "trace", skip_all)]
@@ -833,14 +1121,13 @@ impl Resolver {
         }
 
         let token = self.get_usertoken(&account_id, current_time).await?;
-        Ok(token.map(|tok| NssUser {
-            homedir: self.token_abs_homedirectory(&tok),
-            name: self.token_uidattr(&tok),
-            uid: tok.gidnumber,
-            gid: tok.gidnumber,
-            gecos: tok.displayname,
-            shell: tok.shell.unwrap_or_else(|| self.default_shell.clone()),
-        }))
+        match token.filter(|tok| self.nss_visible(tok)) {
+            Some(tok) => {
+                let name = self.token_uidattr(&tok).await;
+                Ok(Some(self.token_nssaccount(tok, name)))
+            }
+            None => Ok(None),
+        }
     }
 
     #[instrument(level = "debug", skip(self))]
@@ -866,26 +1153,107 @@ impl Resolver {
         self.get_nssaccount(Id::Gid(gid), current_time).await
     }
 
-    fn token_gidattr(&self, token: &GroupToken) -> String {
+    async fn token_gidattr(&self, token: &GroupToken) -> String {
+        let name = self.group_token_nss_name(token).await;
+        self.resolve_nss_names(vec![name])
+            .await
+            .pop()
+            .unwrap_or_else(|| token.spn.clone())
+    }
+
+    async fn group_token_nss_name(&self, token: &GroupToken) -> NssName {
         match self.gid_attr_map {
-            UidAttr::Spn => token.spn.as_str(),
-            UidAttr::Name => token.name.as_str(),
+            UidAttr::Spn => NssName::Fixed(token.spn.clone()),
+            UidAttr::Name => NssName::Fixed(token.name.clone()),
+            UidAttr::NamePreferSpnOnCollision => {
+                let reserved = self
+                    .system_provider
+                    .contains_group(&Id::Name(token.name.clone()))
+                    .await;
+                NssName::Issue {
+                    kind: NameKind::Group,
+                    uuid: token.uuid,
+                    name: token.name.clone(),
+                    spn: token.spn.clone(),
+                    reserved,
+                }
+            }
+        }
+    }
+
+    /// Determine if this group's gid is within the configured gid_allow_range. Groups
+    /// outside of the range are not resolved so that they can not shadow system groups.
+    fn gid_allowed(&self, token: &GroupToken) -> bool {
+        let above_min = self
+            .gid_allow_range_min
+            .map(|min| token.gidnumber >= min)
+            .unwrap_or(true);
+        let below_max = self
+            .gid_allow_range_max
+            .map(|max| token.gidnumber <= max)
+            .unwrap_or(true);
+
+        if above_min && below_max {
+            true
+        } else {
+            warn!(
+                gid = token.gidnumber,
+                name = %token.name,
+                "Group gid is outside of the configured gid_allow_range, refusing to resolve"
+            );
+            false
+        }
+    }
+
+    /// Warn if any cached groups have a gid that overlaps with the range reserved for
+    /// system groups.
+    pub async fn check_gid_range(&self) {
+        if let Some(min) = self.gid_allow_range_min {
+            if min < SYSTEM_GID_BOUNDARY {
+                warn!(
+                    gid_allow_range_min = min,
+                    "gid_allow_range_min overlaps with the system gid range (0-{})",
+                    SYSTEM_GID_BOUNDARY - 1
+                );
+            }
+        }
+
+        let Ok(tokens) = self.get_cached_grouptokens().await else {
+            return;
+        };
+
+        for (tok, _) in tokens
+            .iter()
+            .filter(|(tok, _)| tok.gidnumber < SYSTEM_GID_BOUNDARY)
+        {
+            warn!(
+                gid = tok.gidnumber,
+                name = %tok.name,
+                "Group gid overlaps with the system gid range (0-{})",
+                SYSTEM_GID_BOUNDARY - 1
+            );
         }
-        .to_string()
     }
 
     #[instrument(level = "debug", skip_all)]
     pub async fn get_nssgroups(&self) -> Result<Vec<NssGroup>, ()> {
+        if self.nss_enumeration == EnumerationMode::Disabled {
+            debug!("nss enumeration is disabled");
+            return Ok(Vec::new());
+        }
+
         let mut r = self.system_provider.get_nssgroups().await;
 
         // Extend all the local groups if maps exist.
         for nss_group in r.iter_mut() {
+            let mut extended = false;
             for client in self.clients.iter() {
                 if let Some(extend_group_id) = client.has_map_group(&nss_group.name) {
                     let (_, token) = self.get_cached_grouptoken(extend_group_id).await?;
                     if let Some(token) = token {
                         let members = self.get_groupmembers(token.uuid).await;
                         nss_group.members.extend(members);
+                        extended = true;
                         debug!(
                             "extended group {} with members from {}",
                             nss_group.name, token.name
@@ -893,14 +1261,53 @@ impl Resolver {
                     }
                 }
             }
+
+            let members = self.get_kanidm_to_local_members(&nss_group.name).await?;
+            if !members.is_empty() {
+                debug!("extended group {} with mapped members", nss_group.name);
+                nss_group.members.extend(members);
+                extended = true;
+            }
+
+            if extended {
+                nss_group.members.sort_unstable();
+                nss_group.members.dedup();
+            }
         }
 
         let l = self.get_cached_grouptokens().await?;
-        r.reserve(l.len());
-        for tok in l.into_iter() {
-            let members = self.get_groupmembers(tok.uuid).await;
+
+        let current_time = SystemTime::now();
+
+        let mut groups = Vec::with_capacity(l.len());
+        for (tok, expiry) in l.into_iter() {
+            if !self.gid_allowed(&tok) || !self.nss_enumerable(expiry, current_time) {
+                continue;
+            }
+            let members = self.get_groupmember_tokens(tok.uuid).await;
+            groups.push((tok, members));
+        }
+
+        // Resolve the names of every group and member together, so that any names that
+        // must be issued are issued in one transaction.
+        let mut names = Vec::new();
+        for (tok, members) in groups.iter() {
+            names.push(self.group_token_nss_name(tok).await);
+            for member in members {
+                names.push(self.token_nss_name(member).await);
+            }
+        }
+        let mut names = self.resolve_nss_names(names).await.into_iter();
+
+        r.reserve(groups.len());
+        for (tok, members) in groups {
+            let name = names.next().unwrap_or_else(|| tok.spn.clone());
+            let members = members
+                .into_iter()
+                .map(|member| names.next().unwrap_or(member.spn))
+                .collect();
             r.push(NssGroup {
-                name: self.token_gidattr(&tok),
+                name,
                 gid: tok.gidnumber,
                 members,
             })
@@ -929,6 +1336,12 @@ impl Resolver {
                 }
             }
 
+            let members = self.get_kanidm_to_local_members(&nss_group.name).await?;
+            if !members.is_empty() {
+                debug!("extended group {} with mapped members", nss_group.name);
+                nss_group.members.extend(members);
+            }
+
             nss_group.members.sort_unstable();
             nss_group.members.dedup();
 
@@ -938,10 +1351,11 @@ impl Resolver {
         let token = self.get_grouptoken(grp_id, SystemTime::now()).await?;
         // Get members set.
         match token {
+            Some(tok) if !self.gid_allowed(&tok) => Ok(None),
             Some(tok) => {
                 let members = self.get_groupmembers(tok.uuid).await;
                 Ok(Some(NssGroup {
-                    name: self.token_gidattr(&tok),
+                    name: self.token_gidattr(&tok).await,
                     gid: tok.gidnumber,
                     members,
                 }))
@@ -960,6 +1374,61 @@ impl Resolver {
         self.get_nssgroup(Id::Gid(gid)).await
     }
 
+    /// Cache the members of these groups ahead of their first use. If no groups are
+    /// given, each provider's login groups are used. Entries that are still valid in
+    /// the cache are not fetched again. Accounts that can't be fetched are logged and
+    /// skipped. Returns the number of accounts that are cached.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn warmup(&self, groups: &[String]) -> Result<usize, ()> {
+        let current_time = SystemTime::now();
+
+        let mut hsm_lock = self.hsm.lock().await;
+        let mut members = BTreeSet::new();
+
+        for client in self.clients.iter() {
+            let client_groups = if groups.is_empty() {
+                client.unix_warmup_groups().await
+            } else {
+                groups.to_vec()
+            };
+
+            for group in client_groups {
+                match client
+                    .unix_group_members(
+                        &Id::Name(group.clone()),
+                        hsm_lock.deref_mut(),
+                        current_time,
+                    )
+                    .await
+                {
+                    Ok(group_members) => members.extend(group_members),
+                    Err(IdpError::NotFound) => {
+                        debug!(?group, origin = ?client.origin(), "group not found");
+                    }
+                    Err(err) => {
+                        warn!(?err, ?group, "unable to retrieve group members");
+                    }
+                }
+            }
+        }
+
+        drop(hsm_lock);
+
+        let mut cached = 0;
+        for member in members {
+            match self
+                .get_usertoken(&Id::Name(member.clone()), current_time)
+                .await
+            {
+                Ok(Some(_)) => cached += 1,
+                Ok(None) => debug!(?member, "account not found"),
+                Err(()) => warn!(?member, "unable to cache account"),
+            }
+        }
+
+        Ok(cached)
+    }
+
     #[instrument(level = "debug", skip(self))]
     pub async fn pam_account_allowed(&self, account_id: &str) -> Result<Option<bool>, ()> {
         let current_time = SystemTime::now();
@@ -1457,12 +1926,24 @@ impl Resolver {
         };
 
         // Not a system account, check based on the token and resolve.
-        let token = self.get_usertoken(&id, current_time).await?;
-        Ok(token.as_ref().map(|tok| HomeDirectoryInfo {
+        let Some(tok) = self.get_usertoken(&id, current_time).await? else {
+            return Ok(None);
+        };
+
+        let template_values = match self.home_template {
+            Some(_) => {
+                let uid = self.token_uidattr(&tok).await;
+                Some(self.token_home_template_values(&tok, &uid))
+            }
+            None => None,
+        };
+
+        Ok(Some(HomeDirectoryInfo {
             uid: tok.gidnumber,
             gid: tok.gidnumber,
-            name: self.token_homedirectory_attr(tok),
-            alias: self.token_homedirectory_alias(tok),
+            name: self.token_homedirectory_attr(&tok),
+            aliases: self.token_homedirectory_aliases(&tok),
+            template_values,
         }))
     }
 
@@ -1489,6 +1970,32 @@ impl Resolver {
         results
     }
 
+    #[instrument(level = "debug", skip_all)]
+    pub async fn cache_stats(&self) -> CacheStats {
+        let mut online = true;
+        let mut last_online = None;
+
+        // Unlike provider_status, this must not attempt to bring providers online.
+        for client in self.clients.iter() {
+            online &= client.is_online().await;
+            last_online = last_online.max(client.last_online().await);
+        }
+
+        let last_online = last_online
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        CacheStats {
+            user_hits: self.counters.user_hits.load(Ordering::Relaxed),
+            user_misses: self.counters.user_misses.load(Ordering::Relaxed),
+            group_hits: self.counters.group_hits.load(Ordering::Relaxed),
+            group_misses: self.counters.group_misses.load(Ordering::Relaxed),
+            online,
+            last_online,
+        }
+    }
+
     #[instrument(level = "debug", skip_all)]
     pub async fn test_connection(&self) -> bool {
         let now = SystemTime::now();
@@ -14,11 +14,14 @@ use sparkle_resolver_common::idprovider::kanidm::KanidmProvider;
 use sparkle_resolver_common::idprovider::system::SystemProvider;
 use sparkle_resolver_common::resolver::Resolver;
 use sparkle_unix_common::constants::{
-    DEFAULT_CACHE_TIMEOUT, DEFAULT_CACHE_TIMEOUT_JITTER_MS, DEFAULT_GID_ATTR_MAP,
-    DEFAULT_HOME_ALIAS, DEFAULT_HOME_ATTR, DEFAULT_HOME_PREFIX, DEFAULT_SHELL,
-    DEFAULT_UID_ATTR_MAP,
+    DEFAULT_CACHE_TIMEOUT, DEFAULT_CACHE_TIMEOUT_JITTER_MS, DEFAULT_GECOS_ATTR,
+    DEFAULT_GID_ATTR_MAP, DEFAULT_HOME_ALIAS, DEFAULT_HOME_ATTR, DEFAULT_HOME_PREFIX,
+    DEFAULT_INCLUDE_EXPIRED, DEFAULT_NEGATIVE_CACHE_TIMEOUT, DEFAULT_NSS_ENUMERATION,
+    DEFAULT_SHELL, DEFAULT_UID_ATTR_MAP,
+};
+use sparkle_unix_common::unix_config::{
+    EnumerationMode, GroupMap, HomeAttr, KanidmConfig, UidAttr,
 };
-use sparkle_unix_common::unix_config::{GroupMap, KanidmConfig};
 use sparkle_unix_common::unix_passwd::{CryptPw, EtcGroup, EtcShadow, EtcUser};
 use std::future::Future;
 use std::pin::Pin;
@@ -48,7 +51,52 @@ where
     Box::new(move |n| Box::pin(f(n)))
 }
 
+/// The resolver and provider settings a test can change from their defaults.
+struct TestOptions {
+    uid_attr_map: UidAttr,
+    gid_attr_map: UidAttr,
+    include_expired: bool,
+    home_aliases: Vec<HomeAttr>,
+    nss_enumeration: EnumerationMode,
+    kanidm_to_local: Vec<GroupMap>,
+    gid_allow_range_min: Option<u32>,
+    gid_allow_range_max: Option<u32>,
+}
+
+impl Default for TestOptions {
+    fn default() -> Self {
+        TestOptions {
+            uid_attr_map: DEFAULT_UID_ATTR_MAP,
+            gid_attr_map: DEFAULT_GID_ATTR_MAP,
+            include_expired: DEFAULT_INCLUDE_EXPIRED,
+            home_aliases: DEFAULT_HOME_ALIAS.into_iter().collect(),
+            nss_enumeration: DEFAULT_NSS_ENUMERATION,
+            kanidm_to_local: Vec::new(),
+            gid_allow_range_min: None,
+            gid_allow_range_max: None,
+        }
+    }
+}
+
 async fn setup_test(fix_fn: Fixture) -> (Resolver, mpsc::Receiver<Id>, KanidmClient) {
+    setup_test_with(fix_fn, TestOptions::default()).await
+}
+
+async fn setup_test_with(
+    fix_fn: Fixture,
+    options: TestOptions,
+) -> (Resolver, mpsc::Receiver<Id>, KanidmClient) {
+    let TestOptions {
+        uid_attr_map,
+        gid_attr_map,
+        include_expired,
+        home_aliases,
+        nss_enumeration,
+        kanidm_to_local,
+        gid_allow_range_min,
+        gid_allow_range_max,
+    } = options;
+
     sketching::test_init();
 
     let mut counter = 0;
@@ -165,12 +213,17 @@ async fn setup_test(fix_fn: Fixture) -> (Resolver, mpsc::Receiver<Id>, KanidmCli
         &KanidmConfig {
             conn_timeout: 1,
             request_timeout: 1,
+            idle_timeout: 1,
             pam_allowed_login_groups: vec!["allowed_group".to_string()],
             map_group: vec![GroupMap {
                 local: "extensible_group".to_string(),
                 with: "testgroup1".to_string(),
             }],
+            kanidm_to_local,
             service_account_token: Some(service_api_token),
+            service_account_token_path: None,
+            tls_pin_sha256: None,
+            client_user_agent: "kanidm-unixd/test".to_string(),
         },
         SystemTime::now(),
         &mut (&mut dbtxn).into(),
@@ -190,12 +243,19 @@ async fn setup_test(fix_fn: Fixture) -> (Resolver, mpsc::Receiver<Id>, KanidmCli
         vec![Arc::new(idprovider)],
         hsm,
         DEFAULT_CACHE_TIMEOUT,
+        DEFAULT_NEGATIVE_CACHE_TIMEOUT,
         DEFAULT_SHELL.to_string(),
         DEFAULT_HOME_PREFIX.into(),
         DEFAULT_HOME_ATTR,
-        DEFAULT_HOME_ALIAS,
-        DEFAULT_UID_ATTR_MAP,
-        DEFAULT_GID_ATTR_MAP,
+        home_aliases,
+        None,
+        uid_attr_map,
+        gid_attr_map,
+        gid_allow_range_min,
+        gid_allow_range_max,
+        DEFAULT_GECOS_ATTR,
+        include_expired,
+        nss_enumeration,
     )
     .await
     .expect("Failed to build cache layer.");
@@ -364,6 +424,56 @@ async fn test_cache_account() {
     assert_eq!(async_refresh_rx.len(), 1);
 }
 
+#[tokio::test]
+async fn test_cache_group_gid_allow_range() {
+    let (cachelayer, _async_refresh_rx, _adminclient) = setup_test_with(
+        fixture(test_fixture),
+        TestOptions {
+            gid_allow_range_min: Some(20002),
+            gid_allow_range_max: Some(20002),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    cachelayer.mark_next_check_now(SystemTime::now()).await;
+    assert!(cachelayer.test_connection().await);
+
+    // testgroup1 has gid 20001, below the range.
+    assert!(cachelayer
+        .get_nssgroup_name("testgroup1")
+        .await
+        .expect("Failed to get from cache")
+        .is_none());
+    assert!(cachelayer
+        .get_nssgroup_gid(20001)
+        .await
+        .expect("Failed to get from cache")
+        .is_none());
+
+    // masked_group has gid 20003, above the range.
+    assert!(cachelayer
+        .get_nssgroup_gid(20003)
+        .await
+        .expect("Failed to get from cache")
+        .is_none());
+
+    // allowed_group is inside the range.
+    let gt = cachelayer
+        .get_nssgroup_gid(20002)
+        .await
+        .expect("Failed to get from cache")
+        .expect("Group in the range was not resolved");
+    assert_eq!(gt.name, "allowed_group@idm.example.com");
+
+    // Out of range groups are also left out of enumeration.
+    let groups = cachelayer
+        .get_nssgroups()
+        .await
+        .expect("Failed to enumerate groups");
+    assert!(groups.iter().all(|g| g.gid != 20001 && g.gid != 20003));
+}
+
 #[tokio::test]
 async fn test_cache_group() {
     let (cachelayer, async_refresh_rx, _adminclient) = setup_test(fixture(test_fixture)).await;
@@ -639,6 +749,140 @@ async fn test_cache_account_pam_allowed() {
     assert!(async_refresh_rx.is_empty());
 }
 
+#[tokio::test]
+async fn test_cache_warmup() {
+    let (cachelayer, async_refresh_rx, adminclient) = setup_test(fixture(test_fixture)).await;
+
+    // Offline, there is nothing we can warm up.
+    cachelayer.mark_offline().await;
+    assert_eq!(cachelayer.warmup(&[]).await, Ok(0));
+
+    cachelayer.mark_next_check_now(SystemTime::now()).await;
+    assert!(cachelayer.test_connection().await);
+
+    adminclient
+        .auth_simple_password("admin", ADMIN_TEST_PASSWORD)
+        .await
+        .expect("failed to auth as admin");
+    adminclient
+        .idm_group_add_members("allowed_group", &["testaccount1"])
+        .await
+        .unwrap();
+
+    // With no groups, the pam allowed groups are warmed.
+    assert_eq!(cachelayer.warmup(&[]).await, Ok(1));
+
+    // Unknown groups are skipped.
+    assert_eq!(
+        cachelayer
+            .warmup(&["testgroup1".to_string(), "nonexist".to_string()])
+            .await,
+        Ok(1)
+    );
+
+    // The account is now available while offline.
+    cachelayer.mark_offline().await;
+    let ut = cachelayer
+        .get_nssaccount_name("testaccount1")
+        .await
+        .expect("Failed to get from cache");
+    assert!(ut.is_some());
+    assert!(async_refresh_rx.is_empty());
+}
+
+#[tokio::test]
+async fn test_cache_stats() {
+    let (cachelayer, async_refresh_rx, _adminclient) = setup_test(fixture(test_fixture)).await;
+
+    let stats = cachelayer.cache_stats().await;
+    assert_eq!(stats.user_hits + stats.user_misses, 0);
+    assert_eq!(stats.group_hits + stats.group_misses, 0);
+
+    cachelayer.mark_next_check_now(SystemTime::now()).await;
+    assert!(cachelayer.test_connection().await);
+
+    // The first lookup misses, the second is served from the cache. The group is
+    // looked up first, as caching the account also caches its groups.
+    for _ in 0..2 {
+        let gt = cachelayer
+            .get_nssgroup_name("testgroup1")
+            .await
+            .expect("Failed to get from cache");
+        assert!(gt.is_some());
+        let ut = cachelayer
+            .get_nssaccount_name("testaccount1")
+            .await
+            .expect("Failed to get from cache");
+        assert!(ut.is_some());
+    }
+
+    let stats = cachelayer.cache_stats().await;
+    assert_eq!(stats.user_misses, 1);
+    assert!(stats.user_hits >= 1);
+    assert_eq!(stats.group_misses, 1);
+    assert!(stats.group_hits >= 1);
+    assert!(stats.online);
+    assert_ne!(stats.last_online, 0);
+
+    // Going offline keeps the last time we were online.
+    cachelayer.mark_offline().await;
+    let offline_stats = cachelayer.cache_stats().await;
+    assert!(!offline_stats.online);
+    assert_eq!(offline_stats.last_online, stats.last_online);
+    assert!(async_refresh_rx.is_empty());
+}
+
+#[tokio::test]
+async fn test_cache_invalidate_uuids() {
+    let (cachelayer, async_refresh_rx, adminclient) = setup_test(fixture(test_fixture)).await;
+    cachelayer.mark_next_check_now(SystemTime::now()).await;
+    assert!(cachelayer.test_connection().await);
+
+    adminclient
+        .auth_simple_password("admin", ADMIN_TEST_PASSWORD)
+        .await
+        .expect("failed to auth as admin");
+    let group_uuid = adminclient
+        .idm_group_get("testgroup1")
+        .await
+        .expect("failed to get group")
+        .and_then(|entry| entry.attrs.get("uuid").and_then(|v| v.first().cloned()))
+        .and_then(|uuid| uuid::Uuid::parse_str(&uuid).ok())
+        .expect("group has no uuid");
+
+    // Cache the group, then it is served from the cache.
+    for _ in 0..2 {
+        let gt = cachelayer
+            .get_nssgroup_name("testgroup1")
+            .await
+            .expect("Failed to get from cache");
+        assert!(gt.is_some());
+    }
+    assert_eq!(cachelayer.cache_stats().await.group_misses, 1);
+
+    // Invalidating an unrelated uuid has no effect.
+    assert!(cachelayer
+        .invalidate_uuids(&[uuid::Uuid::new_v4()])
+        .await
+        .is_ok());
+    let gt = cachelayer
+        .get_nssgroup_name("testgroup1")
+        .await
+        .expect("Failed to get from cache");
+    assert!(gt.is_some());
+    assert_eq!(cachelayer.cache_stats().await.group_misses, 1);
+
+    // Invalidating the group forces it to be fetched again.
+    assert!(cachelayer.invalidate_uuids(&[group_uuid]).await.is_ok());
+    let gt = cachelayer
+        .get_nssgroup_name("testgroup1")
+        .await
+        .expect("Failed to get from cache");
+    assert!(gt.is_some());
+    assert_eq!(cachelayer.cache_stats().await.group_misses, 2);
+    assert!(async_refresh_rx.is_empty());
+}
+
 #[tokio::test]
 async fn test_cache_account_pam_nonexist() {
     #[allow(clippy::disallowed_methods)]
@@ -722,6 +966,18 @@ async fn test_cache_account_expiry() {
         .expect("failed to authenticate");
     assert_eq!(a3, Some(false));
 
+    // Expired accounts are hidden from nss by default.
+    let ut = cachelayer
+        .get_nssaccount_name("testaccount1")
+        .await
+        .expect("Failed to get from cache");
+    assert!(ut.is_none());
+    let us = cachelayer
+        .get_nssaccounts()
+        .await
+        .expect("failed to list all accounts");
+    assert!(us.is_empty());
+
     // go offline
     cachelayer.mark_offline().await;
 
@@ -750,6 +1006,119 @@ async fn test_cache_account_expiry() {
     assert!(async_refresh_rx.is_empty());
 }
 
+#[tokio::test]
+async fn test_cache_account_expiry_include_expired() {
+    #[allow(clippy::disallowed_methods)]
+    let current_time = OffsetDateTime::now_utc();
+    let (cachelayer, async_refresh_rx, adminclient) = setup_test_with(
+        fixture(test_fixture),
+        TestOptions {
+            include_expired: true,
+            ..Default::default()
+        },
+    )
+    .await;
+    cachelayer.mark_next_check_now(SystemTime::now()).await;
+    assert!(cachelayer.test_connection().await);
+
+    // expire the account
+    adminclient
+        .auth_simple_password("admin", ADMIN_TEST_PASSWORD)
+        .await
+        .expect("failed to auth as admin");
+    adminclient
+        .idm_person_account_set_attr("testaccount1", ATTR_ACCOUNT_EXPIRE, &[ACCOUNT_EXPIRE])
+        .await
+        .unwrap();
+
+    // auth will fail
+    let a1 = cachelayer
+        .pam_account_authenticate("testaccount1", current_time, TESTACCOUNT1_PASSWORD_A)
+        .await
+        .expect("failed to authenticate");
+    assert_eq!(a1, Some(false));
+
+    // Pam account allowed should be denied.
+    let a2 = cachelayer
+        .pam_account_allowed("testaccount1")
+        .await
+        .expect("failed to authenticate");
+    assert_eq!(a2, Some(false));
+
+    // But the account is still resolved and enumerated by nss.
+    let ut = cachelayer
+        .get_nssaccount_name("testaccount1")
+        .await
+        .expect("Failed to get from cache");
+    assert!(ut.is_some());
+    let us = cachelayer
+        .get_nssaccounts()
+        .await
+        .expect("failed to list all accounts");
+    assert_eq!(us.len(), 1);
+
+    // Assert no outstanding async requests
+    assert!(async_refresh_rx.is_empty());
+}
+
+#[tokio::test]
+async fn test_cache_nss_enumeration() {
+    // (mode, accounts and groups while cached, accounts and groups once the cache expires)
+    for (mode, cached, expired) in [
+        (EnumerationMode::Disabled, (0, 0), (0, 0)),
+        (EnumerationMode::CachedOnly, (1, 2), (0, 0)),
+        (EnumerationMode::Full, (1, 2), (1, 2)),
+    ] {
+        let (cachelayer, _async_refresh_rx, _adminclient) = setup_test_with(
+            fixture(test_fixture),
+            TestOptions {
+                nss_enumeration: mode,
+                ..Default::default()
+            },
+        )
+        .await;
+        cachelayer.mark_next_check_now(SystemTime::now()).await;
+        assert!(cachelayer.test_connection().await);
+
+        // Populate the cache. Direct lookups are never affected by the mode.
+        let ut = cachelayer
+            .get_nssaccount_name("testaccount1")
+            .await
+            .expect("Failed to get from cache");
+        assert!(ut.is_some());
+        let gt = cachelayer
+            .get_nssgroup_name("testgroup1")
+            .await
+            .expect("Failed to get from cache");
+        assert!(gt.is_some());
+
+        // Stay offline so that enumeration only reflects the cache.
+        cachelayer.mark_offline().await;
+
+        let us = cachelayer
+            .get_nssaccounts()
+            .await
+            .expect("failed to list all accounts");
+        let gs = cachelayer
+            .get_nssgroups()
+            .await
+            .expect("failed to list all groups");
+        assert_eq!((us.len(), gs.len()), cached, "{mode}");
+
+        assert!(cachelayer.invalidate().await.is_ok());
+
+        let us = cachelayer
+            .get_nssaccounts()
+            .await
+            .expect("failed to list all accounts");
+        let gs = cachelayer
+            .get_nssgroups()
+            .await
+            .expect("failed to list all groups");
+        assert_eq!((us.len(), gs.len()), expired, "{mode}");
+    }
+}
+
 #[tokio::test]
 async fn test_cache_nxcache() {
     let (cachelayer, async_refresh_rx, _adminclient) = setup_test(fixture(test_fixture)).await;
@@ -1176,6 +1545,40 @@ async fn test_cache_authenticate_system_account() {
     assert!(async_refresh_rx.is_empty());
 }
 
+#[tokio::test]
+async fn test_cache_home_aliases() {
+    let (cachelayer, _async_refresh_rx, _adminclient) = setup_test_with(
+        fixture(test_fixture),
+        TestOptions {
+            home_aliases: vec![HomeAttr::Name, HomeAttr::Spn, HomeAttr::Name],
+            ..Default::default()
+        },
+    )
+    .await;
+
+    cachelayer.mark_next_check_now(SystemTime::now()).await;
+    assert!(cachelayer.test_connection().await);
+
+    // The aliases are created in order, and only once each.
+    let info = cachelayer
+        .pam_account_beginsession("testaccount1")
+        .await
+        .expect("failed to begin session")
+        .expect("no home directory info");
+    assert_eq!(info.aliases.len(), 2);
+    assert_eq!(info.aliases[0], "testaccount1");
+    assert!(info.aliases[1].starts_with("testaccount1@"));
+    assert!(!info.aliases.contains(&info.name));
+
+    // The first alias is the home directory reported to nss.
+    let user = cachelayer
+        .get_nssaccount_name("testaccount1")
+        .await
+        .expect("Failed to query cache")
+        .expect("No such account");
+    assert_eq!(user.homedir, format!("{}testaccount1", DEFAULT_HOME_PREFIX));
+}
+
 /// Issue 1830. If cache items expire where we have an account and a group, and we
 /// refresh the group *first*, the group appears to drop it's members. This is because
 /// sqlite "INSERT OR REPLACE INTO" triggers a delete cascade of the foreign key elements
@@ -1374,3 +1777,234 @@ async fn test_cache_extend_group_members() {
     // Assert no outstanding async requests
     assert!(async_refresh_rx.is_empty());
 }
+
+#[tokio::test]
+async fn test_cache_kanidm_to_local_group_map() {
+    let (cachelayer, async_refresh_rx, _adminclient) = setup_test_with(
+        fixture(test_fixture),
+        TestOptions {
+            kanidm_to_local: vec![
+                // The reverse of the map_group of extensible_group.
+                GroupMap {
+                    local: "extensible_group".to_string(),
+                    with: "testgroup1".to_string(),
+                },
+                // testgroup1 -> wheel -> adm -> testgroup1 is a cycle.
+                GroupMap {
+                    local: "wheel".to_string(),
+                    with: "testgroup1".to_string(),
+                },
+                GroupMap {
+                    local: "adm".to_string(),
+                    with: "wheel".to_string(),
+                },
+                GroupMap {
+                    local: "testgroup1".to_string(),
+                    with: "adm".to_string(),
+                },
+                // testaccount1 is not a member of allowed_group.
+                GroupMap {
+                    local: "libvirt".to_string(),
+                    with: "allowed_group".to_string(),
+                },
+            ],
+            ..Default::default()
+        },
+    )
+    .await;
+
+    let local_group = |name: &str, gid: u32, members: &[&str]| EtcGroup {
+        name: name.to_string(),
+        gid,
+        password: Default::default(),
+        members: members.iter().map(|member| member.to_string()).collect(),
+    };
+
+    cachelayer
+        .reload_system_identities(
+            vec![EtcUser {
+                name: "local_account".to_string(),
+                uid: 30000,
+                gid: 30000,
+                password: Default::default(),
+                gecos: Default::default(),
+                homedir: Default::default(),
+                shell: Default::default(),
+            }],
+            vec![],
+            vec![
+                local_group("extensible_group", 30001, &["local_account"]),
+                local_group("wheel", 30002, &["local_account"]),
+                local_group("adm", 30003, &[]),
+                local_group("libvirt", 30004, &[]),
+            ],
+        )
+        .await;
+
+    cachelayer.mark_next_check_now(SystemTime::now()).await;
+    assert!(cachelayer.test_connection().await);
+
+    // The mappings are evaluated from the user's memberships, so until the
+    // account is cached the local groups are not extended.
+    let gt = cachelayer
+        .get_nssgroup_name("wheel")
+        .await
+        .expect("Failed to get from cache")
+        .expect("Group not found");
+    assert_eq!(gt.members.as_slice(), &["local_account".to_string()]);
+
+    let ut = cachelayer
+        .get_nssaccount_name("testaccount1")
+        .await
+        .expect("Failed to get from cache");
+    assert!(ut.is_some());
+
+    let expected = [
+        // Extended by map_group and kanidm_to_local, the member appears once.
+        (
+            "extensible_group",
+            vec!["local_account", "testaccount1@idm.example.com"],
+        ),
+        (
+            "wheel",
+            vec!["local_account", "testaccount1@idm.example.com"],
+        ),
+        // Through the chain from wheel.
+        ("adm", vec!["testaccount1@idm.example.com"]),
+        ("libvirt", vec![]),
+    ];
+
+    for (name, members) in expected.iter() {
+        let gt = cachelayer
+            .get_nssgroup_name(name)
+            .await
+            .expect("Failed to get from cache")
+            .expect("Group not found");
+        assert_eq!(&gt.members, members);
+    }
+
+    let groups = cachelayer
+        .get_nssgroups()
+        .await
+        .expect("Failed to get from cache");
+
+    for (name, members) in expected.iter() {
+        let gt = groups
+            .iter()
+            .find(|group| group.name == *name)
+            .expect("Group not found");
+        assert_eq!(&gt.members, members);
+    }
+
+    // The cycle back to testgroup1 does not change the kanidm group.
+    let gt = cachelayer
+        .get_nssgroup_name("testgroup1")
+        .await
+        .expect("Failed to get from cache")
+        .expect("Group not found");
+    assert_eq!(
+        gt.members.as_slice(),
+        &["testaccount1@idm.example.com".to_string()]
+    );
+
+    // Assert no outstanding async requests
+    assert!(async_refresh_rx.is_empty());
+}
+
+#[tokio::test]
+async fn test_cache_attr_map_prefer_spn_on_collision() {
+    let (cachelayer, async_refresh_rx, _adminclient) = setup_test_with(
+        fixture(test_fixture),
+        TestOptions {
+            uid_attr_map: UidAttr::NamePreferSpnOnCollision,
+            gid_attr_map: UidAttr::NamePreferSpnOnCollision,
+            ..Default::default()
+        },
+    )
+    .await;
+
+    cachelayer.mark_next_check_now(SystemTime::now()).await;
+    assert!(cachelayer.test_connection().await);
+
+    // A system user holds the name before the account is first resolved.
+    cachelayer
+        .reload_system_identities(
+            vec![EtcUser {
+                name: "testaccount1".to_string(),
+                uid: 30000,
+                gid: 30000,
+                password: Default::default(),
+                gecos: Default::default(),
+                homedir: Default::default(),
+                shell: Default::default(),
+            }],
+            vec![],
+            vec![],
+        )
+        .await;
+
+    // The account uses its spn so it remains distinct, the group has no collision.
+    let ut = cachelayer
+        .get_nssaccount_gid(20000)
+        .await
+        .expect("Failed to get from cache")
+        .expect("Account not found");
+    assert_eq!(ut.name, "testaccount1@idm.example.com");
+
+    let gt = cachelayer
+        .get_nssgroup_name("testgroup1")
+        .await
+        .expect("Failed to get from cache")
+        .expect("Group not found");
+    assert_eq!(gt.name, "testgroup1");
+    assert_eq!(gt.members, vec!["testaccount1@idm.example.com".to_string()]);
+
+    // Now the system user goes away and a system group takes the group name.
+    cachelayer
+        .reload_system_identities(
+            vec![],
+            vec![],
+            vec![EtcGroup {
+                name: "testgroup1".to_string(),
+                gid: 30001,
+                password: Default::default(),
+                members: Default::default(),
+            }],
+        )
+        .await;
+
+    // The names that were issued are stable, neither flips.
+    let ut = cachelayer
+        .get_nssaccount_gid(20000)
+        .await
+        .expect("Failed to get from cache")
+        .expect("Account not found");
+    assert_eq!(ut.name, "testaccount1@idm.example.com");
+
+    let gt = cachelayer
+        .get_nssgroup_gid(20001)
+        .await
+        .expect("Failed to get from cache")
+        .expect("Group not found");
+    assert_eq!(gt.name, "testgroup1");
+    assert_eq!(gt.members, vec!["testaccount1@idm.example.com".to_string()]);
+
+    let us = cachelayer
+        .get_nssaccounts()
+        .await
+        .expect("failed to list all accounts");
+    assert!(us
+        .iter()
+        .any(|nss_user| nss_user.name == "testaccount1@idm.example.com"));
+
+    let gs = cachelayer
+        .get_nssgroups()
+        .await
+        .expect("failed to list all groups");
+    assert!(gs
+        .iter()
+        .any(|nss_group| nss_group.name == "testgroup1" && nss_group.gid == 20001));
+
+    // Assert no outstanding async requests
+    assert!(async_refresh_rx.is_empty());
+}
@@ -5,7 +5,7 @@ use std::path::PathBuf;
 use clap::CommandFactory;
 use clap_complete::{generate_to, Shell};
 
-use sparkle_resolver_common::opt::{KanidmUnixParser, SshAuthorisedKeysOpt};
+use sparkle_resolver_common::opt::{KanidmUnixParser, KanidmUnixStatusOpt, SshAuthorisedKeysOpt};
 
 fn main() {
     profiles::apply_profile();
@@ -43,5 +43,13 @@ fn main() {
             comp_dir.clone(),
         )
         .ok();
+
+        generate_to(
+            shell,
+            &mut KanidmUnixStatusOpt::command(),
+            "kanidm_unix_status",
+            comp_dir.clone(),
+        )
+        .ok();
     }
 }